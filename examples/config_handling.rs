@@ -81,6 +81,22 @@ fn main() -> Result<()> {
         config.clear_data,
         file_config.clear_data || command_line_args.clear_data
     );
+    assert_eq!(
+        config.warm_standby,
+        file_config.warm_standby || command_line_args.warm_standby
+    );
+    assert_eq!(
+        config.supervised,
+        file_config.supervised || command_line_args.supervised
+    );
+    assert_eq!(
+        config.secure_delete,
+        file_config.secure_delete || command_line_args.secure_delete
+    );
+    assert_eq!(
+        config.batch_chunk_replication,
+        file_config.batch_chunk_replication || command_line_args.batch_chunk_replication
+    );
 
     if command_line_args.local_addr.is_some() || command_line_args.first.is_some() {
         assert_eq!(
@@ -188,6 +204,231 @@ fn main() -> Result<()> {
         )
     }
 
+    if command_line_args.min_write_acks.is_some() {
+        assert_eq!(command_line_args.min_write_acks, config.min_write_acks)
+    } else {
+        assert_eq!(file_config.min_write_acks, config.min_write_acks)
+    }
+
+    if command_line_args.treasury_percentage.is_some() {
+        assert_eq!(
+            command_line_args.treasury_percentage,
+            config.treasury_percentage
+        )
+    } else {
+        assert_eq!(file_config.treasury_percentage, config.treasury_percentage)
+    }
+
+    if command_line_args.min_reward_age.is_some() {
+        assert_eq!(command_line_args.min_reward_age, config.min_reward_age)
+    } else {
+        assert_eq!(file_config.min_reward_age, config.min_reward_age)
+    }
+
+    if command_line_args.used_space_limits.is_some() {
+        assert_eq!(
+            command_line_args.used_space_limits,
+            config.used_space_limits
+        )
+    } else {
+        assert_eq!(file_config.used_space_limits, config.used_space_limits)
+    }
+
+    if command_line_args.economy.is_some() {
+        assert_eq!(command_line_args.economy, config.economy)
+    } else {
+        assert_eq!(file_config.economy, config.economy)
+    }
+
+    if command_line_args.scrubbing.is_some() {
+        assert_eq!(command_line_args.scrubbing, config.scrubbing)
+    } else {
+        assert_eq!(file_config.scrubbing, config.scrubbing)
+    }
+
+    if command_line_args.chunk_write_buffer.is_some() {
+        assert_eq!(
+            command_line_args.chunk_write_buffer,
+            config.chunk_write_buffer
+        )
+    } else {
+        assert_eq!(file_config.chunk_write_buffer, config.chunk_write_buffer)
+    }
+
+    if command_line_args.chunk_compression.is_some() {
+        assert_eq!(
+            command_line_args.chunk_compression,
+            config.chunk_compression
+        )
+    } else {
+        assert_eq!(file_config.chunk_compression, config.chunk_compression)
+    }
+
+    if command_line_args.max_chunk_size.is_some() {
+        assert_eq!(command_line_args.max_chunk_size, config.max_chunk_size)
+    } else {
+        assert_eq!(file_config.max_chunk_size, config.max_chunk_size)
+    }
+
+    if command_line_args.storage_policy.is_some() {
+        assert_eq!(command_line_args.storage_policy, config.storage_policy)
+    } else {
+        assert_eq!(file_config.storage_policy, config.storage_policy)
+    }
+
+    if command_line_args.chunk_read_cache.is_some() {
+        assert_eq!(command_line_args.chunk_read_cache, config.chunk_read_cache)
+    } else {
+        assert_eq!(file_config.chunk_read_cache, config.chunk_read_cache)
+    }
+
+    if command_line_args.query_hedge_after_msec.is_some() {
+        assert_eq!(
+            command_line_args.query_hedge_after_msec,
+            config.query_hedge_after_msec
+        )
+    } else {
+        assert_eq!(
+            file_config.query_hedge_after_msec,
+            config.query_hedge_after_msec
+        )
+    }
+
+    if command_line_args.storage_challenge_interval_msec.is_some() {
+        assert_eq!(
+            command_line_args.storage_challenge_interval_msec,
+            config.storage_challenge_interval_msec
+        )
+    } else {
+        assert_eq!(
+            file_config.storage_challenge_interval_msec,
+            config.storage_challenge_interval_msec
+        )
+    }
+
+    if command_line_args.replication_concurrency.is_some() {
+        assert_eq!(
+            command_line_args.replication_concurrency,
+            config.replication_concurrency
+        )
+    } else {
+        assert_eq!(
+            file_config.replication_concurrency,
+            config.replication_concurrency
+        )
+    }
+
+    if command_line_args.relayed_read_holders.is_some() {
+        assert_eq!(
+            command_line_args.relayed_read_holders,
+            config.relayed_read_holders
+        )
+    } else {
+        assert_eq!(
+            file_config.relayed_read_holders,
+            config.relayed_read_holders
+        )
+    }
+
+    if command_line_args.max_map_entries.is_some() {
+        assert_eq!(command_line_args.max_map_entries, config.max_map_entries)
+    } else {
+        assert_eq!(file_config.max_map_entries, config.max_map_entries)
+    }
+
+    if command_line_args.max_sequence_appends_per_hour.is_some() {
+        assert_eq!(
+            command_line_args.max_sequence_appends_per_hour,
+            config.max_sequence_appends_per_hour
+        )
+    } else {
+        assert_eq!(
+            file_config.max_sequence_appends_per_hour,
+            config.max_sequence_appends_per_hour
+        )
+    }
+
+    if command_line_args.max_entry_size.is_some() {
+        assert_eq!(command_line_args.max_entry_size, config.max_entry_size)
+    } else {
+        assert_eq!(file_config.max_entry_size, config.max_entry_size)
+    }
+
+    if command_line_args.erasure_data_shards.is_some() {
+        assert_eq!(
+            command_line_args.erasure_data_shards,
+            config.erasure_data_shards
+        )
+    } else {
+        assert_eq!(file_config.erasure_data_shards, config.erasure_data_shards)
+    }
+
+    if command_line_args.erasure_parity_shards.is_some() {
+        assert_eq!(
+            command_line_args.erasure_parity_shards,
+            config.erasure_parity_shards
+        )
+    } else {
+        assert_eq!(
+            file_config.erasure_parity_shards,
+            config.erasure_parity_shards
+        )
+    }
+
+    if command_line_args.egress_rate.is_some() {
+        assert_eq!(command_line_args.egress_rate, config.egress_rate)
+    } else {
+        assert_eq!(file_config.egress_rate, config.egress_rate)
+    }
+
+    if command_line_args.egress_client_rate.is_some() {
+        assert_eq!(
+            command_line_args.egress_client_rate,
+            config.egress_client_rate
+        )
+    } else {
+        assert_eq!(file_config.egress_client_rate, config.egress_client_rate)
+    }
+
+    if command_line_args.client_msg_rate.is_some() {
+        assert_eq!(command_line_args.client_msg_rate, config.client_msg_rate)
+    } else {
+        assert_eq!(file_config.client_msg_rate, config.client_msg_rate)
+    }
+
+    if command_line_args.restarts.is_some() {
+        assert_eq!(command_line_args.restarts, config.restarts)
+    } else {
+        assert_eq!(file_config.restarts, config.restarts)
+    }
+
+    if command_line_args.genesis_key.is_some() {
+        assert_eq!(command_line_args.genesis_key, config.genesis_key)
+    } else {
+        assert_eq!(file_config.genesis_key, config.genesis_key)
+    }
+
+    if command_line_args.operator_key.is_some() {
+        assert_eq!(command_line_args.operator_key, config.operator_key)
+    } else {
+        assert_eq!(file_config.operator_key, config.operator_key)
+    }
+
+    if command_line_args.operator_rpc_addr.is_some() {
+        assert_eq!(
+            command_line_args.operator_rpc_addr,
+            config.operator_rpc_addr
+        )
+    } else {
+        assert_eq!(file_config.operator_rpc_addr, config.operator_rpc_addr)
+    }
+
+    if command_line_args.operator_tls_dir.is_some() {
+        assert_eq!(command_line_args.operator_tls_dir, config.operator_tls_dir)
+    } else {
+        assert_eq!(file_config.operator_tls_dir, config.operator_tls_dir)
+    }
+
     clear_disk_config()?;
 
     Ok(())
@@ -29,9 +29,16 @@
 
 use log::{self, error, info};
 use self_update::{cargo_crate_version, Status};
-use sn_node::{self, add_connection_info, set_connection_info, utils, Config, Node};
-use std::{io::Write, process};
+use sn_node::{
+    self, add_connection_info, set_connection_info, utils, Config, Node, RestartBackoff,
+};
+use std::{
+    io::Write,
+    process::{self, Command},
+    time::Instant,
+};
 use structopt::{clap, StructOpt};
+use tokio::{task, time};
 
 /// Runs a Safe Network node.
 fn main() {
@@ -96,6 +103,11 @@ async fn run_node() {
         }
     }
 
+    // not when run by the supervisor, which also reads the config file
+    if config.supervised() && config.restarts().is_none() {
+        supervise().await;
+    }
+
     let message = format!(
         "Running {} v{}",
         Config::clap().get_name(),
@@ -126,6 +138,18 @@ async fn run_node() {
         our_pid, node_prefix, node_name, our_conn_info_json,
     );
 
+    if let Some(chain) = node.verified_section_chain() {
+        let chain_keys: Vec<_> = chain.keys().collect();
+        println!(
+            "Section chain verified against trusted genesis key: {:?}",
+            chain_keys
+        );
+        info!(
+            "Section chain verified against trusted genesis key: {:?}",
+            chain_keys
+        );
+    }
+
     if config.is_first() {
         set_connection_info(our_conn_info).unwrap_or_else(|err| {
             log::error!("Unable to write our connection info to disk: {}", err);
@@ -146,6 +170,71 @@ async fn run_node() {
     }
 }
 
+/// Runs the node as a child process, restarting it with the same args, thus the same
+/// root dir and kept keys, when it crashes, backing off from restarting it if it keeps
+/// crashing. Exits once the node exits without crashing.
+async fn supervise() -> ! {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            println!("Cannot supervise node: {:?}", e);
+            error!("Cannot supervise node: {:?}", e);
+            process::exit(1);
+        }
+    };
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut backoff = RestartBackoff::default();
+    loop {
+        let started = Instant::now();
+        let node = Command::new(&exe)
+            .args(supervised_args(&args, backoff.restarts()))
+            .spawn();
+        // waiting on the node blocks, so it is done off the runtime
+        let status = match node {
+            Ok(mut node) => task::spawn_blocking(move || node.wait())
+                .await
+                .map_err(std::io::Error::from)
+                .and_then(|status| status),
+            Err(e) => Err(e),
+        };
+        match status {
+            Ok(status) if status.success() => process::exit(0),
+            Ok(status) => error!("Supervised node crashed: {}", status),
+            Err(e) => {
+                println!("Cannot run supervised node: {:?}", e);
+                error!("Cannot run supervised node: {:?}", e);
+                process::exit(1);
+            }
+        }
+        let delay = backoff.crashed(started.elapsed());
+        info!(
+            "Restarting node in {:?}, having restarted it {} times",
+            delay,
+            backoff.restarts()
+        );
+        time::sleep(delay).await;
+    }
+}
+
+/// The args of the node run by the supervisor, being those of the supervisor
+/// but for supervising, self-updating and clearing the data, done by the supervisor.
+fn supervised_args(args: &[String], restarts: u32) -> Vec<String> {
+    let mut supervised = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--supervised" | "--update" | "--clear-data" => (),
+            "--restarts" => {
+                let _ = args.next();
+            }
+            _ => supervised.push(arg.clone()),
+        }
+    }
+    supervised.push("--restarts".to_string());
+    supervised.push(restarts.to_string());
+    supervised
+}
+
 fn update() -> Result<Status, Box<dyn (::std::error::Error)>> {
     info!("Checking for updates...");
     let target = self_update::get_target();
@@ -6,7 +6,10 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{utils, Result};
+use crate::{
+    persistence::{LoggedDb, Transactions},
+    utils, Result,
+};
 use futures::lock::Mutex;
 use pickledb::PickleDb;
 use std::sync::Arc;
@@ -15,25 +18,38 @@ use std::{cell::RefCell, path::Path, rc::Rc};
 const BLOB_META_DB_NAME: &str = "immutable_data.db";
 const HOLDER_META_DB_NAME: &str = "holder_data.db";
 const FULL_ADULTS_DB_NAME: &str = "full_adults.db";
+const CAPABILITIES_DB_NAME: &str = "adult_capabilities.db";
+const SHARD_MAP_DB_NAME: &str = "shard_maps.db";
 // The number of separate copies of a blob chunk which should be maintained.
 
 #[derive(Clone)]
 pub struct ChunkHolderDbs {
-    pub metadata: Arc<Mutex<PickleDb>>,
-    pub holders: Arc<Mutex<PickleDb>>,
+    pub metadata: Arc<Mutex<LoggedDb>>,
+    pub holders: Arc<Mutex<LoggedDb>>,
     pub full_adults: Arc<Mutex<PickleDb>>,
+    /// The capability of each Adult, as reported by it.
+    pub capabilities: Arc<Mutex<LoggedDb>>,
+    /// The shards of each chunk stored erasure coded.
+    pub shard_maps: Arc<Mutex<LoggedDb>>,
+    /// Commits the changes to the metadata and holders of chunks as a whole.
+    pub transactions: Transactions,
 }
 
 impl ChunkHolderDbs {
     ///
     pub fn new(path: &Path) -> Result<Self> {
-        let metadata = utils::new_auto_dump_db(path, BLOB_META_DB_NAME)?;
-        let holders = utils::new_auto_dump_db(path, HOLDER_META_DB_NAME)?;
+        let metadata = LoggedDb::open(path, BLOB_META_DB_NAME)?;
+        let holders = LoggedDb::open(path, HOLDER_META_DB_NAME)?;
         let full_adults = utils::new_auto_dump_db(path, FULL_ADULTS_DB_NAME)?;
+        let capabilities = LoggedDb::open(path, CAPABILITIES_DB_NAME)?;
+        let shard_maps = LoggedDb::open(path, SHARD_MAP_DB_NAME)?;
         Ok(Self {
             metadata: Arc::new(Mutex::new(metadata)),
             holders: Arc::new(Mutex::new(holders)),
             full_adults: Arc::new(Mutex::new(full_adults)),
+            capabilities: Arc::new(Mutex::new(capabilities)),
+            shard_maps: Arc::new(Mutex::new(shard_maps)),
+            transactions: Transactions::new(path),
         })
     }
 }
@@ -0,0 +1,127 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    StoreCostQuote, CHUNK_MSG_OVERHEAD, MAX_CHUNK_SIZE, MAX_NETWORK_STORAGE_PERCENTAGE, MAX_SUPPLY,
+    MIN_CHUNK_SIZE, PREMIUM_REDUNDANCY_MULTIPLIER,
+};
+use serde::{Deserialize, Serialize};
+use sn_data_types::Token;
+
+/// The parameters of the network economy, i.e. of minting, rewards and store cost.
+/// Elders only agree on payouts and store costs computed alike, so these are the same
+/// at all nodes of a network, set at genesis and pushed by the Elders of each section
+/// along with the other parameters of the section. They only differ between networks,
+/// e.g. for test networks to experiment with. Parameters not given are the defaults
+/// of the main network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EconomyConfig {
+    /// The max supply of tokens in the network, in nanos.
+    /// Each section is responsible for its proportional share of it.
+    pub max_supply: u64,
+    /// Newly minted tokens at churn, as a percentage of the payments received
+    /// since last churn, as long as the max supply is not reached.
+    pub mint_percentage: u64,
+    /// Store cost grows with the square of the data size, relative to this many bytes.
+    pub cost_chunk_size: u64,
+    /// The least store cost factor of supply and demand, in thousandths,
+    /// i.e. when the section has plenty of space.
+    pub base_cost_per_mille: u64,
+    /// More nodes are required once this percentage of adults are full.
+    pub max_full_nodes_percentage: u8,
+    /// The replication multiplier a client gets
+    /// when paying for premium storage of a chunk.
+    pub premium_redundancy_multiplier: u64,
+}
+
+impl Default for EconomyConfig {
+    fn default() -> Self {
+        Self {
+            max_supply: MAX_SUPPLY,
+            mint_percentage: 100,
+            cost_chunk_size: MAX_CHUNK_SIZE,
+            base_cost_per_mille: 1,
+            max_full_nodes_percentage: MAX_NETWORK_STORAGE_PERCENTAGE,
+            premium_redundancy_multiplier: PREMIUM_REDUNDANCY_MULTIPLIER,
+        }
+    }
+}
+
+/// The sizes of chunks to self-encrypt data into, for the
+/// network they are stored to, rather than assumed by clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkSizeAdvice {
+    /// Bytes of a chunk at least.
+    pub min_chunk_size: u64,
+    /// Bytes of a chunk at most. Store cost grows with the square of
+    /// the chunk size beyond it, and a chunk of it fits in a msg.
+    pub max_chunk_size: u64,
+    /// Bytes of a msg at most, if limited.
+    pub max_msg_size: Option<u32>,
+}
+
+impl EconomyConfig {
+    /// The chunk sizes to store at the cost of the economy,
+    /// in msgs of the given size at most, if limited.
+    pub fn chunk_size_advice(&self, max_msg_size: Option<u32>) -> ChunkSizeAdvice {
+        let mut max_chunk_size = self.cost_chunk_size;
+        if let Some(max_msg_size) = max_msg_size {
+            let max_msg_chunk = (max_msg_size as u64).saturating_sub(CHUNK_MSG_OVERHEAD);
+            max_chunk_size = max_chunk_size.min(max_msg_chunk);
+        }
+        ChunkSizeAdvice {
+            min_chunk_size: MIN_CHUNK_SIZE.min(max_chunk_size),
+            max_chunk_size,
+            max_msg_size,
+        }
+    }
+
+    /// The share of max supply a section is responsible for,
+    /// in a network of sections with prefixes of this length.
+    pub fn max_section_nanos(&self, prefix_len: usize) -> u64 {
+        (self.max_supply as f64 / 2_f64.powf(prefix_len as f64)).floor() as u64
+    }
+
+    /// The quote of the standard cost, along with the premium cost,
+    /// i.e. the standard cost times the premium redundancy multiplier.
+    pub fn quote(&self, standard: Token) -> StoreCostQuote {
+        let premium = standard
+            .as_nano()
+            .saturating_mul(self.premium_redundancy_multiplier);
+        StoreCostQuote {
+            standard,
+            premium: Token::from_nano(premium),
+            premium_redundancy: self.premium_redundancy_multiplier,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunks_are_advised_to_fit_in_a_msg() {
+        let economy = EconomyConfig::default();
+        let advice = economy.chunk_size_advice(None);
+        assert_eq!(advice.min_chunk_size, MIN_CHUNK_SIZE);
+        assert_eq!(advice.max_chunk_size, economy.cost_chunk_size);
+
+        let max_msg_size = 512 * 1024;
+        let advice = economy.chunk_size_advice(Some(max_msg_size));
+        assert_eq!(
+            advice.max_chunk_size,
+            max_msg_size as u64 - CHUNK_MSG_OVERHEAD
+        );
+        assert_eq!(advice.max_msg_size, Some(max_msg_size));
+
+        let advice = economy.chunk_size_advice(Some(1024));
+        assert_eq!((advice.min_chunk_size, advice.max_chunk_size), (0, 0));
+    }
+}
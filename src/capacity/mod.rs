@@ -7,17 +7,28 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 mod chunk_dbs;
+mod economy;
 mod rate_limit;
 
 use crate::Result;
 pub use chunk_dbs::ChunkHolderDbs;
+pub use economy::{ChunkSizeAdvice, EconomyConfig};
 use log::info;
-pub use rate_limit::RateLimit;
+pub use rate_limit::{RateLimit, SectionFullness, StoreCostQuote};
 use sn_data_types::PublicKey;
 
+// The defaults of the economy; see `EconomyConfig`.
 pub const MAX_SUPPLY: u64 = u32::MAX as u64 * 1_000_000_000_u64;
 const MAX_CHUNK_SIZE: u64 = 1_000_000;
-const MAX_NETWORK_STORAGE_RATIO: f64 = 0.5;
+// The least size self-encryption chunks data into.
+pub(crate) const MIN_CHUNK_SIZE: u64 = 1024;
+// Bytes of a msg storing a chunk, besides the chunk itself,
+// i.e. of headers, signatures and proof of payment.
+const CHUNK_MSG_OVERHEAD: u64 = 64 * 1024;
+const MAX_NETWORK_STORAGE_PERCENTAGE: u8 = 50;
+/// The replication multiplier a client gets
+/// when paying for premium storage of a chunk.
+pub const PREMIUM_REDUNDANCY_MULTIPLIER: u64 = 2;
 
 /// A util for sharing the
 /// info on data capacity among the
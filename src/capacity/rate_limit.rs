@@ -6,23 +6,70 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::{Capacity, MAX_CHUNK_SIZE, MAX_NETWORK_STORAGE_RATIO, MAX_SUPPLY};
+use super::{Capacity, EconomyConfig};
 use crate::Network;
 use crate::Result;
 use log::info;
+use serde::{Deserialize, Serialize};
 use sn_data_types::{PublicKey, Token};
 
+/// How full the section is, for clients and tooling to weigh its store cost by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionFullness {
+    /// The percentage of the capacity of the Adults used, of those reporting it lately, if any.
+    pub used_percentage: Option<u8>,
+    /// The Adults of the section.
+    pub adults: u64,
+    /// Of those, the Adults which reported being full.
+    pub full_adults: u64,
+    /// Whether the section lets new nodes join, as it does while too many of its Adults are full.
+    pub joins_allowed: bool,
+}
+
+/// The store cost quoted to a client, at the standard redundancy of the data, as
+/// answered by the `GetStoreCost` response, and at the premium redundancy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreCostQuote {
+    /// The cost of storing the bytes at the standard redundancy.
+    pub standard: Token,
+    /// The cost of storing the bytes at the premium redundancy.
+    pub premium: Token,
+    /// The replication multiplier of the chunks paid for at the premium cost.
+    pub premium_redundancy: u64,
+}
+
+impl StoreCostQuote {
+    /// Returns the redundancy multiplier covered by the paid amount.
+    pub fn redundancy_paid_for(&self, paid: Token) -> u64 {
+        if self.premium > self.standard && paid >= self.premium {
+            self.premium_redundancy
+        } else {
+            1
+        }
+    }
+}
+
 /// Calculation of rate limit for writes.
 #[derive(Clone)]
 pub struct RateLimit {
     capacity: Capacity,
     network: Network,
+    economy: EconomyConfig,
 }
 
 impl RateLimit {
     /// gets a new instance of rate limit
-    pub fn new(network: Network, capacity: Capacity) -> RateLimit {
-        Self { network, capacity }
+    pub fn new(network: Network, capacity: Capacity, economy: EconomyConfig) -> RateLimit {
+        Self {
+            network,
+            capacity,
+            economy,
+        }
+    }
+
+    /// Goes by the economy from now on, as pushed with the section parameters.
+    pub fn set_economy(&mut self, economy: EconomyConfig) {
+        self.economy = economy;
     }
 
     /// Calculates the rate limit of write operations,
@@ -34,7 +81,14 @@ impl RateLimit {
         let full_nodes = self.capacity.full_nodes().await;
         let all_nodes = self.network.our_adults().await.len() as u8;
 
-        RateLimit::rate_limit(bytes, full_nodes, all_nodes, prefix_len)
+        RateLimit::rate_limit(&self.economy, bytes, full_nodes, all_nodes, prefix_len)
+    }
+
+    /// The cost of storing the bytes at the standard redundancy, and at the premium,
+    /// i.e. the standard cost times the premium redundancy multiplier.
+    pub async fn quote(&self, bytes: u64) -> StoreCostQuote {
+        let standard = self.from(bytes).await;
+        self.economy.quote(standard)
     }
 
     /// Adds this node to the list of full nodes.
@@ -43,7 +97,7 @@ impl RateLimit {
     }
 
     /// Returns `true` if more storage capacity is required, otherwise `false`.
-    pub async fn more_nodes_required(&mut self) -> bool {
+    pub async fn more_nodes_required(&self) -> bool {
         info!("Checking if more nodes are required");
         let all_nodes = self.network.our_adults().await.len() as f64;
         let full_nodes = self.capacity.full_nodes().await as f64;
@@ -51,31 +105,46 @@ impl RateLimit {
         info!("Total number of adult nodes: {:?}", all_nodes);
         info!("Number of full adult nodes: {:?}", full_nodes);
         info!("Section storage usage ratio: {:?}", usage_ratio);
-        usage_ratio > MAX_NETWORK_STORAGE_RATIO
+        usage_ratio * 100_f64 > self.economy.max_full_nodes_percentage as f64
     }
 
-    fn rate_limit(bytes: u64, full_nodes: u8, all_nodes: u8, prefix_len: usize) -> Token {
+    /// How full the section is, by its Adults reported full,
+    /// and the percentage of their capacity used, if reported.
+    pub async fn fullness(&self, used_percentage: Option<u8>) -> SectionFullness {
+        SectionFullness {
+            used_percentage,
+            adults: self.network.our_adults().await.len() as u64,
+            full_adults: u64::from(self.capacity.full_nodes().await),
+            joins_allowed: self.more_nodes_required().await,
+        }
+    }
+
+    fn rate_limit(
+        economy: &EconomyConfig,
+        bytes: u64,
+        full_nodes: u8,
+        all_nodes: u8,
+        prefix_len: usize,
+    ) -> Token {
         let available_nodes = (all_nodes - full_nodes) as f64;
-        let supply_demand_factor = 0.001
+        let supply_demand_factor = economy.base_cost_per_mille as f64 / 1_000_f64
             + (1_f64 / available_nodes).powf(8_f64)
             + (full_nodes as f64 / all_nodes as f64).powf(88_f64);
-        let data_size_factor = (bytes as f64 / MAX_CHUNK_SIZE as f64).powf(2_f64)
-            + (bytes as f64 / MAX_CHUNK_SIZE as f64);
+        let chunk_size = economy.cost_chunk_size as f64;
+        let data_size_factor =
+            (bytes as f64 / chunk_size).powf(2_f64) + (bytes as f64 / chunk_size);
         let steepness_reductor = prefix_len as f64 + 1_f64;
-        let section_supply_share = RateLimit::max_section_nanos(prefix_len) as f64;
+        let section_supply_share = economy.max_section_nanos(prefix_len) as f64;
         let token_source = steepness_reductor * section_supply_share.powf(0.5_f64);
         let rate_limit = (token_source * data_size_factor * supply_demand_factor).round() as u64;
         Token::from_nano(rate_limit)
     }
-
-    fn max_section_nanos(prefix_len: usize) -> u64 {
-        (MAX_SUPPLY as f64 / 2_f64.powf(prefix_len as f64)).floor() as u64
-    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::capacity::{MAX_SUPPLY, PREMIUM_REDUNDANCY_MULTIPLIER};
     use sn_messaging::client::DataCmd;
     use std::mem;
 
@@ -85,20 +154,47 @@ mod test {
         let prefix_len = 0;
         let all_nodes = 8;
         let full_nodes = 7;
-        let rate_limit = RateLimit::rate_limit(bytes, full_nodes, all_nodes, prefix_len).as_nano();
+        let rate_limit = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            bytes,
+            full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
         assert_eq!(rate_limit, 2076594);
     }
 
+    #[test]
+    fn premium_redundancy_requires_the_premium_quoted() {
+        let quote = EconomyConfig::default().quote(Token::from_nano(1_000));
+        let premium = Token::from_nano(1_000 * PREMIUM_REDUNDANCY_MULTIPLIER);
+        assert_eq!(quote.premium, premium);
+        assert_eq!(quote.premium_redundancy, PREMIUM_REDUNDANCY_MULTIPLIER);
+        assert_eq!(1, quote.redundancy_paid_for(quote.standard));
+        assert_eq!(
+            1,
+            quote.redundancy_paid_for(Token::from_nano(premium.as_nano() - 1))
+        );
+        assert_eq!(
+            PREMIUM_REDUNDANCY_MULTIPLIER,
+            quote.redundancy_paid_for(premium)
+        );
+        // nothing to pay is no premium either
+        let free = EconomyConfig::default().quote(Token::zero());
+        assert_eq!(1, free.redundancy_paid_for(Token::zero()));
+    }
+
     #[test]
     fn calculates_max_section_nanos() {
         // prefix zero is one section so is responsible for all tokens
-        let first_section_nanos = RateLimit::max_section_nanos(0);
+        let first_section_nanos = EconomyConfig::default().max_section_nanos(0);
         assert_eq!(MAX_SUPPLY, first_section_nanos);
         // first split leads to each section having half the tokens
-        let first_split_nanos = RateLimit::max_section_nanos(1);
+        let first_split_nanos = EconomyConfig::default().max_section_nanos(1);
         assert_eq!(MAX_SUPPLY / 2, first_split_nanos);
         // some tokens remain in section up to 2.6 * 10^18 sections, (which is more than one billion times one billion sections).
-        let last_split_nanos = RateLimit::max_section_nanos(61);
+        let last_split_nanos = EconomyConfig::default().max_section_nanos(61);
         assert!(last_split_nanos > 0);
     }
 
@@ -115,13 +211,25 @@ mod test {
         let prefix_len = 0;
         let all_nodes = 8;
         let full_nodes = 7;
-        let standard_rl =
-            RateLimit::rate_limit(one_mb_bytes, full_nodes, all_nodes, prefix_len).as_nano();
+        let standard_rl = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_mb_bytes,
+            full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
 
         // smaller chunks cost less
         let one_mb_less_one_byte = one_mb_bytes - 1;
-        let small = RateLimit::rate_limit(one_mb_less_one_byte, full_nodes, all_nodes, prefix_len)
-            .as_nano();
+        let small = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_mb_less_one_byte,
+            full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
         assert!(
             small <= standard_rl,
             "small chunks don't cost less, expect {} <= {}",
@@ -137,12 +245,24 @@ mod test {
         let prefix_len = 2; // first couple of sections see an increase in cost, whereafter it is strictly decreasing
         let all_nodes = 8;
         let full_nodes = 7;
-        let standard_rl =
-            RateLimit::rate_limit(one_mb_bytes, full_nodes, all_nodes, prefix_len).as_nano();
+        let standard_rl = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_mb_bytes,
+            full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
         // large network is cheaper to store than smaller network
         let big_prefix_len = prefix_len + 1;
-        let big =
-            RateLimit::rate_limit(one_mb_bytes, full_nodes, all_nodes, big_prefix_len).as_nano();
+        let big = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_mb_bytes,
+            full_nodes,
+            all_nodes,
+            big_prefix_len,
+        )
+        .as_nano();
         assert!(
             big <= standard_rl,
             "larger network is not cheaper, expect {} <= {}",
@@ -158,12 +278,24 @@ mod test {
         let prefix_len = 0;
         let all_nodes = 8;
         let full_nodes = 7;
-        let standard_rl =
-            RateLimit::rate_limit(one_mb_bytes, full_nodes, all_nodes, prefix_len).as_nano();
+        let standard_rl = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_mb_bytes,
+            full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
         // less full section is cheaper than more full section
         let less_full_nodes = full_nodes - 1;
-        let empty =
-            RateLimit::rate_limit(one_mb_bytes, less_full_nodes, all_nodes, prefix_len).as_nano();
+        let empty = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_mb_bytes,
+            less_full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
         assert!(
             empty <= standard_rl,
             "less full section is not cheaper, expect {} <= {}",
@@ -180,12 +312,24 @@ mod test {
         let prefix_len = 2;
         let all_nodes = 8;
         let full_nodes = 7;
-        let standard_rl =
-            RateLimit::rate_limit(one_mb_bytes, full_nodes, all_nodes, prefix_len).as_nano();
+        let standard_rl = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_mb_bytes,
+            full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
         // many tiny chunks is cheaper than the same bytes in one big chunk
         let one_kb_bytes = 1024;
-        let reduced =
-            RateLimit::rate_limit(one_kb_bytes, full_nodes, all_nodes, prefix_len).as_nano();
+        let reduced = RateLimit::rate_limit(
+            &EconomyConfig::default(),
+            one_kb_bytes,
+            full_nodes,
+            all_nodes,
+            prefix_len,
+        )
+        .as_nano();
         let combined = 1024 * reduced;
         assert!(
             combined <= standard_rl,
@@ -209,6 +353,7 @@ mod test {
         let big_prefix_len = 33;
         // storage rate limit is applied up to 170 billion nodes
         let endcost = RateLimit::rate_limit(
+            &EconomyConfig::default(),
             minimum_storage_bytes,
             half_full_nodes,
             big_section_node_count,
@@ -236,6 +381,7 @@ mod test {
         let big_prefix_len = 256;
         // storage rate limit is applied up to 2.3 * 10^78 nodes.
         let endcost = RateLimit::rate_limit(
+            &EconomyConfig::default(),
             minimum_storage_bytes,
             half_full_nodes,
             big_section_node_count,
@@ -259,6 +405,7 @@ mod test {
         let first_section_prefix = 0;
         // the first chunk is a reasonable cost
         let startcost = RateLimit::rate_limit(
+            &EconomyConfig::default(),
             one_mb_bytes,
             zero_full_nodes,
             minimum_section_nodes,
@@ -0,0 +1,136 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// Compressed chunk files start with this marker, followed by the byte of the
+/// compression. Uncompressed chunk files start with the index of the variant of
+/// the chunk, as a little endian u32, so they never start with it.
+const MARKER: [u8; 4] = [0xc5, b'S', b'N', b'Z'];
+const ZSTD: u8 = 1;
+const LZ4: u8 = 2;
+// Bytes of the header of a compressed chunk file.
+const HEADER_LEN: usize = MARKER.len() + 1;
+
+/// How the chunks written to disk are compressed. Chunks are decompressed when
+/// read whatever the compression, so it can be changed, or left off, at any time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChunkCompression {
+    /// Zstandard, at its default level, for the smaller files.
+    Zstd,
+    /// LZ4, for the cheaper writes and reads.
+    Lz4,
+}
+
+impl ChunkCompression {
+    fn byte(self) -> u8 {
+        match self {
+            Self::Zstd => ZSTD,
+            Self::Lz4 => LZ4,
+        }
+    }
+}
+
+impl FromStr for ChunkCompression {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        match text {
+            "zstd" => Ok(Self::Zstd),
+            "lz4" => Ok(Self::Lz4),
+            _ => Err(format!("Unknown chunk compression: {}", text)),
+        }
+    }
+}
+
+impl Display for ChunkCompression {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Zstd => write!(formatter, "zstd"),
+            Self::Lz4 => write!(formatter, "lz4"),
+        }
+    }
+}
+
+/// Serialises the chunk into the writer, compressed behind the header, if so configured.
+pub(super) fn serialize_into<W: Write, T: Serialize>(
+    mut writer: W,
+    chunk: &T,
+    compression: Option<ChunkCompression>,
+) -> io::Result<()> {
+    let compression = match compression {
+        Some(compression) => compression,
+        None => return bincode::serialize_into(writer, chunk).map_err(io::Error::other),
+    };
+    writer.write_all(&MARKER)?;
+    writer.write_all(&[compression.byte()])?;
+    match compression {
+        ChunkCompression::Zstd => {
+            let mut encoder = zstd::stream::write::Encoder::new(writer, 0)?;
+            bincode::serialize_into(&mut encoder, chunk).map_err(io::Error::other)?;
+            let _ = encoder.finish()?;
+        }
+        ChunkCompression::Lz4 => {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(writer);
+            bincode::serialize_into(&mut encoder, chunk).map_err(io::Error::other)?;
+            let _ = encoder.finish().map_err(io::Error::other)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deserialises the chunk from the contents of its file, decompressing it if compressed.
+pub(crate) fn deserialize<T: DeserializeOwned>(contents: &[u8]) -> Result<T> {
+    if !contents.starts_with(&MARKER) {
+        return Ok(bincode::deserialize(contents)?);
+    }
+    let compressed = contents.get(HEADER_LEN..).unwrap_or_default();
+    let chunk = match contents.get(MARKER.len()) {
+        Some(&ZSTD) => bincode::deserialize_from(zstd::stream::read::Decoder::new(compressed)?)?,
+        Some(&LZ4) => bincode::deserialize_from(lz4_flex::frame::FrameDecoder::new(compressed))?,
+        byte => {
+            return Err(Error::Logic(format!(
+                "Unknown compression of chunk: {:?}",
+                byte
+            )))
+        }
+    };
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_data_types::{Blob, PublicBlob};
+
+    #[test]
+    fn chunks_are_read_back_whatever_the_compression() -> Result<()> {
+        let chunk = Blob::Public(PublicBlob::new(b"text ".repeat(1000)));
+        let mut uncompressed = vec![];
+        serialize_into(&mut uncompressed, &chunk, None)?;
+        assert!(!uncompressed.starts_with(&MARKER));
+        assert_eq!(deserialize::<Blob>(&uncompressed)?, chunk);
+
+        for compression in [ChunkCompression::Zstd, ChunkCompression::Lz4] {
+            let mut compressed = vec![];
+            serialize_into(&mut compressed, &chunk, Some(compression))?;
+            assert!(compressed.starts_with(&MARKER));
+            assert!(compressed.len() < uncompressed.len() / 10);
+            assert_eq!(deserialize::<Blob>(&compressed)?, chunk);
+            assert_eq!(compression.to_string().parse(), Ok(compression));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{utils, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Changes to the index are checkpointed to disk at most this often,
+/// and when the store is closed.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// An indexed chunk file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct IndexEntry {
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Seconds since unix epoch at which the chunk was last written or read.
+    pub last_access: u64,
+}
+
+/// In-memory index of the chunk files of a store, by file name,
+/// so that existence checks and listing don't hit the filesystem.
+/// The index is rebuilt by scanning the store dir only when its checkpoint is missing or corrupt.
+pub(super) struct ChunkIndex {
+    entries: HashMap<String, IndexEntry>,
+    checkpoint_path: PathBuf,
+    last_checkpoint: Instant,
+    dirty: bool,
+}
+
+impl ChunkIndex {
+    /// Opens the index of the chunk files in `dir`, those found by scanning
+    /// the dir taken as accessed at `now_secs`.
+    pub(super) fn open(dir: &Path, now_secs: u64) -> Self {
+        // kept beside the dir, so it is not taken for a chunk file
+        let checkpoint_path = dir.with_extension("index");
+        let checkpoint = fs::read(&checkpoint_path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok());
+        let (entries, dirty) = match checkpoint {
+            Some(entries) => (entries, false),
+            None => {
+                info!(
+                    "No valid chunk index at {:?}, rebuilding it",
+                    checkpoint_path
+                );
+                (scan(dir, now_secs), true)
+            }
+        };
+        Self {
+            entries,
+            checkpoint_path,
+            last_checkpoint: Instant::now(),
+            dirty,
+        }
+    }
+
+    pub(super) fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub(super) fn get(&self, name: &str) -> Option<IndexEntry> {
+        self.entries.get(name).copied()
+    }
+
+    pub(super) fn names(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    pub(super) fn entries(&self) -> impl Iterator<Item = (&String, &IndexEntry)> {
+        self.entries.iter()
+    }
+
+    pub(super) fn insert(&mut self, name: String, size: u64, now_secs: u64) {
+        let _ = self.entries.insert(
+            name,
+            IndexEntry {
+                size,
+                last_access: now_secs,
+            },
+        );
+        self.changed();
+    }
+
+    pub(super) fn remove(&mut self, name: &str) -> Option<IndexEntry> {
+        let entry = self.entries.remove(name);
+        if entry.is_some() {
+            self.changed();
+        }
+        entry
+    }
+
+    /// Records the chunk as accessed at `now_secs`.
+    pub(super) fn touch(&mut self, name: &str, now_secs: u64) {
+        if let Some(entry) = self.entries.get_mut(name) {
+            entry.last_access = now_secs;
+            self.dirty = true;
+        }
+    }
+
+    /// Writes the index to disk, replacing the previous checkpoint.
+    pub(super) fn checkpoint(&mut self) -> Result<()> {
+        let temp_path = self.checkpoint_path.with_extension("index.temp");
+        fs::write(&temp_path, utils::serialise(&self.entries)?)?;
+        fs::rename(&temp_path, &self.checkpoint_path)?;
+        self.last_checkpoint = Instant::now();
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn changed(&mut self) {
+        self.dirty = true;
+        if self.last_checkpoint.elapsed() < CHECKPOINT_INTERVAL {
+            return;
+        }
+        if let Err(e) = self.checkpoint() {
+            warn!("Could not checkpoint chunk index: {}", e);
+        }
+    }
+}
+
+impl Drop for ChunkIndex {
+    fn drop(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        if let Err(e) = self.checkpoint() {
+            warn!("Could not checkpoint chunk index: {}", e);
+        }
+    }
+}
+
+fn scan(dir: &Path, last_access: u64) -> HashMap<String, IndexEntry> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| {
+                    let entry = entry.ok()?;
+                    let metadata = entry.metadata().ok()?;
+                    let name = entry.file_name().into_string().ok()?;
+                    // the chunk files are named by the hex of their id
+                    if !metadata.is_file() || hex::decode(&name).is_err() {
+                        return None;
+                    }
+                    let entry = IndexEntry {
+                        size: metadata.len(),
+                        last_access,
+                    };
+                    Some((name, entry))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,116 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::UsageCategory;
+use crate::{utils, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// The journal is kept in the dir of the store with this extension.
+pub(super) const JOURNAL_DIR_EXTENSION: &str = "journal";
+// The intent of a write is recorded under the file name of the chunk with this extension,
+// while the chunk itself is written under its file name, until moved into the store.
+const INTENT_EXTENSION: &str = "intent";
+
+/// The intent to write a chunk, recorded before it is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct JournalEntry {
+    /// The space reserved for the chunk.
+    pub reserved: u64,
+    /// The category the space is attributed to, if not the default of the store.
+    pub category: Option<UsageCategory>,
+}
+
+/// Write-ahead journal of the chunks written to a store. Each chunk is written in full
+/// to the journal, and only then moved into the store, so that a crash mid-write leaves no
+/// truncated chunk file in it. The writes interrupted are replayed or discarded on opening.
+pub(super) struct Journal {
+    dir: PathBuf,
+}
+
+impl Journal {
+    /// Opens the journal of the store in `store_dir`, kept beside it.
+    pub(super) fn open(store_dir: &Path) -> Result<Self> {
+        let dir = store_dir.with_extension(JOURNAL_DIR_EXTENSION);
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Records the intent to write the chunk, before it is written to `data_path`.
+    pub(super) fn begin(&self, file_name: &str, entry: &JournalEntry) -> Result<()> {
+        let mut file = File::create(self.intent_path(file_name))?;
+        file.write_all(&utils::serialise(entry)?)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// The path the chunk is written to, before it is moved into the store.
+    pub(super) fn data_path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
+
+    /// Moves the chunk written in full into the store, as a whole.
+    pub(super) fn commit(&self, file_name: &str, store_dir: &Path) -> io::Result<()> {
+        fs::rename(self.data_path(file_name), store_dir.join(file_name))?;
+        // the rename is only durable once the dir is, where dirs can be synced
+        let _ = File::open(store_dir).and_then(|dir| dir.sync_all());
+        Ok(())
+    }
+
+    /// Concludes the write of the chunk, committed or not.
+    pub(super) fn end(&self, file_name: &str) -> Result<()> {
+        match fs::remove_file(self.data_path(file_name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => (),
+        }
+        match fs::remove_file(self.intent_path(file_name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The writes not concluded, as of a crash, by the file name of the chunk, with the
+    /// intent of each, unless it cannot be read, e.g. when the crash was mid-way through it.
+    pub(super) fn pending(&self) -> Vec<(String, Option<JournalEntry>)> {
+        let mut pending = vec![];
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return pending,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(INTENT_EXTENSION) {
+                // chunks with an intent are found by it, those without are stray
+                if !self
+                    .intent_path(&entry.file_name().to_string_lossy())
+                    .exists()
+                {
+                    pending.push((entry.file_name().to_string_lossy().into_owned(), None));
+                }
+                continue;
+            }
+            let file_name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(file_name) => file_name.to_string(),
+                None => continue,
+            };
+            let journal_entry = fs::read(&path)
+                .ok()
+                .and_then(|bytes| utils::deserialise(&bytes).ok());
+            pending.push((file_name, journal_entry));
+        }
+        pending
+    }
+
+    fn intent_path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", file_name, INTENT_EXTENSION))
+    }
+}
@@ -9,7 +9,10 @@
 //! A simple, persistent, disk-based key-value store.
 
 mod chunk;
+mod compression;
 mod immutable;
+mod index;
+mod journal;
 mod mutable;
 mod sequence;
 #[cfg(test)]
@@ -17,20 +20,35 @@ mod tests;
 mod used_space;
 
 use crate::error::{Error, Result};
-use crate::utils;
+use crate::{network::NetworkClock, utils};
 use chunk::{Chunk, ChunkId};
-use log::{info, trace};
+pub(crate) use compression::deserialize as deserialize_chunk;
+pub use compression::ChunkCompression;
+use index::{ChunkIndex, IndexEntry};
+use journal::{Journal, JournalEntry, JOURNAL_DIR_EXTENSION};
+use log::{info, trace, warn};
+use pickledb::PickleDb;
 use sn_data_types::{Blob, Map, Sequence};
 use std::{
-    fs::{self, DirEntry, File, Metadata},
-    io::{Read, Write},
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Read, Write},
     marker::PhantomData,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
 };
 use used_space::StoreId;
-pub use used_space::UsedSpace;
+pub use used_space::{UsageCategory, UsedSpace};
 
 const CHUNK_STORE_DIR: &str = "chunks";
+/// The chunks stored as other than the default category of the store.
+const CHUNK_CATEGORIES_DB_NAME: &str = "chunk_categories.db";
+
+/// The chunks quarantined are moved to the dir of the store with this extension.
+const QUARANTINE_DIR_EXTENSION: &str = "quarantine";
+
+/// Bytes buffered at most when writing a chunk, unless configured otherwise.
+const DEFAULT_WRITE_BUFFER_SIZE: usize = 64 * 1024;
 
 /// The max name length for a chunk file.
 const MAX_CHUNK_FILE_NAME_LENGTH: usize = 104;
@@ -46,6 +64,21 @@ pub(crate) struct ChunkStore<T: Chunk> {
     // Maximum space allowed for all `ChunkStore`s to consume.
     used_space: UsedSpace,
     id: StoreId,
+    categories: PickleDb,
+    // behind a lock, as reads record the access
+    index: Mutex<ChunkIndex>,
+    // the chunks being written, until moved into the store
+    journal: Journal,
+    // the store relocated from, while its chunks are migrated to this one
+    previous: Option<Box<ChunkStore<T>>>,
+    // whether removed chunks are overwritten before unlinking them
+    secure_delete: bool,
+    // bytes buffered at most when writing a chunk
+    write_buffer_size: usize,
+    // how the chunks written are compressed, if they are
+    compression: Option<ChunkCompression>,
+    // the time the accesses of chunks are recorded at
+    clock: Arc<NetworkClock>,
     _phantom: PhantomData<T>,
 }
 
@@ -61,6 +94,8 @@ where
     ///
     /// The maximum storage space is defined by `max_capacity`.  This specifies the max usable by
     /// _all_ `ChunkStores`, not per `ChunkStore`.
+    ///
+    /// The writes interrupted by a crash are replayed or discarded before the store is returned.
     pub async fn new<P: AsRef<Path>>(root: P, used_space: UsedSpace) -> Result<Self> {
         let dir = root.as_ref().join(CHUNK_STORE_DIR).join(Self::subdir());
 
@@ -68,13 +103,74 @@ where
             Self::create_new_root(&dir)?
         }
 
-        let id = used_space.add_local_store(&dir).await?;
-        Ok(ChunkStore {
+        let id = used_space
+            .add_local_store(&dir, Self::usage_category())
+            .await?;
+        let categories = utils::new_auto_dump_db(&dir, CHUNK_CATEGORIES_DB_NAME)?;
+        let clock = Arc::new(NetworkClock::default());
+        let index = Mutex::new(ChunkIndex::open(&dir, clock.now_secs()));
+        let journal = Journal::open(&dir)?;
+        let mut store = ChunkStore {
             dir,
             used_space,
             id,
+            categories,
+            index,
+            journal,
+            previous: None,
+            secure_delete: false,
+            write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
+            compression: None,
+            clock,
             _phantom: PhantomData,
-        })
+        };
+        store.replay_journal().await?;
+        Ok(store)
+    }
+
+    /// Opens the `ChunkStore` at `root`, along with the one at `previous_root` it was relocated
+    /// from, if its chunks are not yet all migrated.
+    pub async fn open_relocated<P: AsRef<Path>>(
+        root: P,
+        previous_root: Option<&Path>,
+        used_space: UsedSpace,
+    ) -> Result<Self> {
+        let mut store = Self::new(root, used_space.clone()).await?;
+        if let Some(previous_root) = previous_root {
+            let previous_dir = previous_root.join(CHUNK_STORE_DIR).join(Self::subdir());
+            if previous_dir.is_dir() {
+                store.previous = Some(Box::new(Self::new(previous_root, used_space).await?));
+            }
+        }
+        Ok(store)
+    }
+
+    /// Relocates the store to `root`, e.g. on another disk. Chunks are written to the new
+    /// location from then on, while the chunks at the old one are still read from there,
+    /// until they are migrated by `migrate`.
+    pub async fn relocate<P: AsRef<Path>>(&mut self, root: P) -> Result<()> {
+        if self.previous.is_some() {
+            return Err(Error::InvalidOperation(
+                "Chunks of the previous relocation are still being migrated".to_string(),
+            ));
+        }
+        let dir = root.as_ref().join(CHUNK_STORE_DIR).join(Self::subdir());
+        if dir == self.dir {
+            return Err(Error::InvalidOperation(format!(
+                "Chunks are already stored at {:?}",
+                dir
+            )));
+        }
+        info!("Relocating chunks from {:?} to {:?}", self.dir, dir);
+        let mut relocated = Self::new(root, self.used_space.clone()).await?;
+        relocated.secure_delete = self.secure_delete;
+        relocated.write_buffer_size = self.write_buffer_size;
+        relocated.compression = self.compression;
+        relocated.clock = self.clock.clone();
+        self.checkpoint()?;
+        let previous = std::mem::replace(self, relocated);
+        self.previous = Some(Box::new(previous));
+        Ok(())
     }
 }
 
@@ -97,48 +193,148 @@ impl<T: Chunk> ChunkStore<T> {
     ///
     /// If a chunk with the same id already exists, it will be overwritten.
     pub async fn put(&mut self, chunk: &T) -> Result<()> {
+        self.do_put(chunk, None).await
+    }
+
+    /// Stores a new data chunk, attributing the space it uses to the category
+    /// instead of the default category of the store.
+    pub async fn put_as(&mut self, chunk: &T, category: UsageCategory) -> Result<()> {
+        self.do_put(chunk, Some(category)).await
+    }
+
+    async fn do_put(&mut self, chunk: &T, category: Option<UsageCategory>) -> Result<()> {
         info!("Writing chunk");
-        let serialised_chunk = utils::serialise(chunk)?;
-        let consumed_space = serialised_chunk.len() as u64;
+        let consumed_space = bincode::serialized_size(chunk)?;
 
         info!("consumed space: {:?}", consumed_space);
         info!("max : {:?}", self.used_space.max_capacity().await);
         info!("use space total : {:?}", self.used_space.total().await);
 
-        let file_path = self.file_path(chunk.id())?;
-        self.do_delete(&file_path).await?;
+        let file_name = file_name(chunk.id())?;
+        self.delete_own(&file_name).await?;
 
         // pre-reserve space
-        self.used_space.increase(self.id, consumed_space).await?;
+        match category {
+            Some(category) => {
+                self.used_space
+                    .increase_as(self.id, category, consumed_space)
+                    .await?
+            }
+            None => self.used_space.increase(self.id, consumed_space).await?,
+        }
         trace!(
             "use space total after add: {:?}",
             self.used_space.total().await
         );
 
-        let res = File::create(&file_path).and_then(|mut file| {
-            file.write_all(&serialised_chunk)?;
-            file.sync_all()
-        });
-
-        match res {
-            Ok(_) => {
+        let entry = JournalEntry {
+            reserved: consumed_space,
+            category,
+        };
+        match self.write_journaled(&file_name, chunk, &entry) {
+            Ok(()) => {
                 info!("Writing chunk succeeded!");
+                self.journal.end(&file_name)?;
+                self.conclude_write(&file_name, entry).await?;
+                // superseding the copy at the location relocated from, if any
+                if let Some(previous) = &mut self.previous {
+                    previous.delete_own(&file_name).await?;
+                }
                 Ok(())
             }
             Err(e) => {
                 info!("Writing chunk failed!");
-                self.used_space.decrease(self.id, consumed_space).await?;
-                Err(e.into())
+                let _ = self.journal.end(&file_name);
+                self.release(category, consumed_space).await?;
+                Err(e)
             }
         }
     }
 
+    // Writes the chunk in full to the journal, and only then moves it into the store.
+    fn write_journaled(&self, file_name: &str, chunk: &T, entry: &JournalEntry) -> Result<()> {
+        self.journal.begin(file_name, entry)?;
+        // serialised into the file through the buffer, rather than into a copy in memory first
+        let buffer_size = self.write_buffer_size;
+        let compression = self.compression;
+        File::create(self.journal.data_path(file_name)).and_then(|file| {
+            let mut writer = BufWriter::with_capacity(buffer_size, file);
+            compression::serialize_into(&mut writer, chunk, compression)?;
+            writer.into_inner()?.sync_all()
+        })?;
+        self.journal.commit(file_name, &self.dir)?;
+        Ok(())
+    }
+
+    // Accounts for the chunk moved into the store, releasing the space reserved beyond
+    // the size of its file, once compressed, and indexing it.
+    async fn conclude_write(&mut self, file_name: &str, entry: JournalEntry) -> Result<()> {
+        let file_size = fs::metadata(self.dir.join(file_name))?.len();
+        let saved = entry.reserved.saturating_sub(file_size);
+        if saved > 0 {
+            self.release(entry.category, saved).await?;
+        }
+        if let Some(category) = entry.category {
+            self.categories.set(file_name, &category)?;
+        }
+        let now_secs = self.clock.now_secs();
+        self.index()
+            .insert(file_name.to_string(), entry.reserved - saved, now_secs);
+        Ok(())
+    }
+
+    // Completes the writes moved into the store before a crash, as well as those written in
+    // full to the journal, and discards the others, so that no truncated chunk is ever served.
+    async fn replay_journal(&mut self) -> Result<()> {
+        for (file_name, entry) in self.journal.pending() {
+            let entry = match entry {
+                Some(entry) => entry,
+                None => {
+                    // the space reserved for it, if any, is not known
+                    warn!("Discarding the stray journaled chunk {}", file_name);
+                    self.journal.end(&file_name)?;
+                    continue;
+                }
+            };
+            let data_path = self.journal.data_path(&file_name);
+            let replayed = if data_path.exists() {
+                // written in full, if it reads back as the chunk
+                let written_in_full = fs::read(&data_path)
+                    .ok()
+                    .and_then(|bytes| compression::deserialize::<T>(&bytes).ok())
+                    .is_some_and(|chunk| {
+                        self::file_name(chunk.id()).is_ok_and(|name| name == file_name)
+                    });
+                written_in_full && self.journal.commit(&file_name, &self.dir).is_ok()
+            } else {
+                self.dir.join(&file_name).is_file()
+            };
+            self.journal.end(&file_name)?;
+            if replayed {
+                info!("Replaying the interrupted write of chunk {}", file_name);
+                self.conclude_write(&file_name, entry).await?;
+            } else {
+                warn!("Discarding the incomplete write of chunk {}", file_name);
+                self.release(entry.category, entry.reserved).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Releases the space used, from the category, or else the default of the store.
+    async fn release(&self, category: Option<UsageCategory>, size: u64) -> Result<()> {
+        match category {
+            Some(category) => self.used_space.decrease_as(self.id, category, size).await,
+            None => self.used_space.decrease(self.id, size).await,
+        }
+    }
+
     /// Deletes the data chunk stored under `id`.
     ///
     /// If the data doesn't exist, it does nothing and returns `Ok`.  In the case of an IO error, it
     /// returns `Error::Io`.
     pub async fn delete(&mut self, id: &T::Id) -> Result<()> {
-        self.do_delete(&self.file_path(id)?).await
+        self.do_delete(&file_name(id)?).await
     }
 
     /// Used space to max space ratio.
@@ -156,10 +352,25 @@ impl<T: Chunk> ChunkStore<T> {
     ///
     /// If the data file can't be accessed, it returns `Error::NoSuchChunk`.
     pub fn get(&self, id: &T::Id) -> Result<T> {
-        let mut file = File::open(self.file_path(id)?).map_err(|_| Error::NoSuchChunk)?;
+        let file_name = file_name(id)?;
+        if !self.index().contains(&file_name) {
+            return match &self.previous {
+                Some(previous) => previous.get(id),
+                None => Err(Error::NoSuchChunk),
+            };
+        }
+        let mut file = match File::open(self.dir.join(&file_name)) {
+            Ok(file) => file,
+            Err(_) => {
+                // the index is only as recent as its last checkpoint, after a crash
+                let _ = self.index().remove(&file_name);
+                return Err(Error::NoSuchChunk);
+            }
+        };
+        self.index().touch(&file_name, self.clock.now_secs());
         let mut contents = vec![];
         let _ = file.read_to_end(&mut contents)?;
-        let chunk = bincode::deserialize::<T>(&contents)?;
+        let chunk = compression::deserialize::<T>(&contents)?;
         // Check it's the requested chunk variant.
         if chunk.id() == id {
             Ok(chunk)
@@ -172,69 +383,309 @@ impl<T: Chunk> ChunkStore<T> {
         self.used_space.total().await
     }
 
+    /// Max space usable by all `ChunkStore`s.
+    pub async fn max_capacity(&self) -> u64 {
+        self.used_space.max_capacity().await
+    }
+
+    /// Space used by all `ChunkStore`s, per category.
+    pub async fn used_space_by_category(&self) -> BTreeMap<UsageCategory, u64> {
+        self.used_space.by_category().await
+    }
+
     /// Tests if a data chunk has been previously stored under `id`.
     pub fn has(&self, id: &T::Id) -> bool {
-        if let Ok(path) = self.file_path(id) {
-            fs::metadata(path)
-                .as_ref()
-                .map(Metadata::is_file)
-                .unwrap_or(false)
-        } else {
-            false
+        match file_name(id) {
+            Ok(file_name) => {
+                self.index().contains(&file_name)
+                    || self
+                        .previous
+                        .as_ref()
+                        .is_some_and(|previous| previous.has(id))
+            }
+            Err(_) => false,
         }
     }
 
     /// Lists all keys of currently stored data.
-    #[cfg_attr(not(test), allow(unused))]
     pub fn keys(&self) -> Vec<T::Id> {
-        fs::read_dir(&self.dir)
-            .map(|entries| {
-                entries
-                    .filter_map(|entry| to_chunk_id(&entry.ok()?))
-                    .collect()
-            })
-            .unwrap_or_else(|_| Vec::new())
-    }
-
-    async fn do_delete(&mut self, file_path: &Path) -> Result<()> {
-        if let Ok(metadata) = fs::metadata(file_path) {
-            self.used_space.decrease(self.id, metadata.len()).await?;
-            fs::remove_file(file_path).map_err(From::from)
+        let mut keys: Vec<_> = self
+            .index()
+            .names()
+            .filter_map(|file_name| to_chunk_id(file_name))
+            .collect();
+        if let Some(previous) = &self.previous {
+            keys.extend(previous.keys());
+        }
+        keys
+    }
+
+    /// Lists all keys of currently stored data with the size of
+    /// each, the least recently written or read first.
+    pub fn keys_by_last_access(&self) -> Vec<(T::Id, u64)> {
+        let mut entries = self.entries();
+        entries.sort_by_key(|(_, entry)| entry.last_access);
+        entries
+            .into_iter()
+            .map(|(id, entry)| (id, entry.size))
+            .collect()
+    }
+
+    fn entries(&self) -> Vec<(T::Id, IndexEntry)> {
+        let mut entries: Vec<_> = self
+            .index()
+            .entries()
+            .filter_map(|(file_name, entry)| Some((to_chunk_id(file_name)?, *entry)))
+            .collect();
+        if let Some(previous) = &self.previous {
+            entries.extend(previous.entries());
+        }
+        entries
+    }
+
+    /// Writes the index of the stored chunks to disk.
+    pub fn checkpoint(&self) -> Result<()> {
+        if let Some(previous) = &self.previous {
+            previous.checkpoint()?;
+        }
+        self.index().checkpoint()
+    }
+
+    /// Overwrites the chunks removed from then on before unlinking them, if `secure_delete`,
+    /// so that they cannot be recovered from the disk.
+    pub fn set_secure_delete(&mut self, secure_delete: bool) {
+        self.secure_delete = secure_delete;
+        if let Some(previous) = &mut self.previous {
+            previous.set_secure_delete(secure_delete);
+        }
+    }
+
+    /// Buffers at most `size` bytes when writing chunks from then on, so that chunks are
+    /// serialised straight into their files, without a serialised copy of them in memory.
+    pub fn set_write_buffer_size(&mut self, size: usize) {
+        self.write_buffer_size = size.max(1);
+        if let Some(previous) = &mut self.previous {
+            previous.set_write_buffer_size(size);
+        }
+    }
+
+    /// Records the accesses of chunks from then on at the time of the network, as
+    /// told by `clock`, rather than by our own clock.
+    pub(crate) fn set_clock(&mut self, clock: Arc<NetworkClock>) {
+        if let Some(previous) = &mut self.previous {
+            previous.set_clock(clock.clone());
+        }
+        self.clock = clock;
+    }
+
+    /// Compresses the chunks written from then on, if `compression` is set. Chunks are
+    /// decompressed when read whatever the compression they were written with.
+    pub fn set_compression(&mut self, compression: Option<ChunkCompression>) {
+        self.compression = compression;
+        if let Some(previous) = &mut self.previous {
+            previous.set_compression(compression);
+        }
+    }
+
+    /// Whether chunks of the store relocated from are yet to be migrated.
+    pub fn is_migrating(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// Moves up to `count` chunks over from the store relocated from, keeping the
+    /// category of the space they use. Once all have been, the old location is removed.
+    /// Chunks that cannot be read there are dropped, for the section to repair them.
+    pub async fn migrate(&mut self, count: usize) -> Result<()> {
+        let mut previous = match self.previous.take() {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+        let result = self.migrate_from(&mut previous, count).await;
+        if previous.index().names().next().is_none() {
+            let dir = previous.dir.clone();
+            drop(previous);
+            info!("Migrated all chunks from {:?}, removing it", dir);
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                warn!("Could not remove {:?}: {}", dir, e);
+            }
+            let _ = fs::remove_file(dir.with_extension("index"));
+            let _ = fs::remove_dir_all(dir.with_extension(JOURNAL_DIR_EXTENSION));
         } else {
-            Ok(())
+            self.previous = Some(previous);
+        }
+        result
+    }
+
+    async fn migrate_from(&mut self, previous: &mut ChunkStore<T>, count: usize) -> Result<()> {
+        let file_names: Vec<_> = previous.index().names().take(count).cloned().collect();
+        for file_name in file_names {
+            let chunk = match to_chunk_id(&file_name).map(|id| previous.get(&id)) {
+                Some(Ok(chunk)) => chunk,
+                _ => {
+                    warn!("Dropping unreadable chunk {} at migration", file_name);
+                    previous.delete_own(&file_name).await?;
+                    continue;
+                }
+            };
+            let category = previous.categories.get::<UsageCategory>(&file_name);
+            self.do_put(&chunk, category).await?;
+            previous.delete_own(&file_name).await?;
+        }
+        Ok(())
+    }
+
+    async fn do_delete(&mut self, file_name: &str) -> Result<()> {
+        if let Some(previous) = &mut self.previous {
+            previous.delete_own(file_name).await?;
+        }
+        self.delete_own(file_name).await
+    }
+
+    /// Moves the data chunk stored under `id` out of the store, into the quarantine dir next to
+    /// it, for the operator to inspect, e.g. when found corrupt. The space of the chunks there is
+    /// no longer accounted for, and it is up to the operator to remove them. With secure
+    /// deletion, the chunk is deleted instead, so that no copy of it is kept.
+    pub async fn quarantine(&mut self, id: &T::Id) -> Result<()> {
+        let file_name = file_name(id)?;
+        if !self.index().contains(&file_name) {
+            if let Some(previous) = &mut self.previous {
+                return previous.quarantine_own(&file_name).await;
+            }
+        }
+        self.quarantine_own(&file_name).await
+    }
+
+    async fn quarantine_own(&mut self, file_name: &str) -> Result<()> {
+        if self.secure_delete {
+            return self.delete_own(file_name).await;
+        }
+        let indexed = match self.forget_own(file_name).await? {
+            Some(indexed) => indexed,
+            None => return Ok(()),
+        };
+        let quarantine_dir = self.dir.with_extension(QUARANTINE_DIR_EXTENSION);
+        let moved = fs::create_dir_all(&quarantine_dir)
+            .and_then(|()| fs::rename(self.dir.join(file_name), quarantine_dir.join(file_name)));
+        match moved {
+            Err(e) if indexed => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_own(&mut self, file_name: &str) -> Result<()> {
+        let file_path = self.dir.join(file_name);
+        let indexed = match self.forget_own(file_name).await? {
+            Some(indexed) => indexed,
+            None => return Ok(()),
+        };
+        if self.secure_delete {
+            if let Err(e) = shred(&file_path) {
+                warn!(
+                    "Could not overwrite {:?} before removing it: {}",
+                    file_path, e
+                );
+                if indexed {
+                    return Err(e.into());
+                }
+            }
+        }
+        match fs::remove_file(&file_path) {
+            Err(e) if indexed => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    // Drops the chunk from the index and the used space, returning whether it was indexed,
+    // or `None` if there is no such chunk.
+    async fn forget_own(&mut self, file_name: &str) -> Result<Option<bool>> {
+        let file_path = self.dir.join(file_name);
+        let indexed = self.index().remove(file_name);
+        // files not yet indexed as of a crash are still accounted for
+        let size = match indexed {
+            Some(entry) => entry.size,
+            None => match fs::metadata(&file_path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => return Ok(None),
+            },
+        };
+        match self.categories.get::<UsageCategory>(file_name) {
+            Some(category) => {
+                self.used_space.decrease_as(self.id, category, size).await?;
+                let _ = self.categories.rem(file_name)?;
+            }
+            None => self.used_space.decrease(self.id, size).await?,
         }
+        Ok(Some(indexed.is_some()))
     }
 
-    fn file_path(&self, id: &T::Id) -> Result<PathBuf> {
-        Ok(self.dir.join(&hex::encode(utils::serialise(id)?)))
+    fn index(&self) -> MutexGuard<'_, ChunkIndex> {
+        // the index is always left consistent, so a poisoned lock is still usable
+        self.index
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 }
 
 pub(crate) trait Subdir {
     fn subdir() -> &'static Path;
+    /// The category the space used by the store is attributed to, by default.
+    fn usage_category() -> UsageCategory;
 }
 
 impl Subdir for BlobChunkStore {
     fn subdir() -> &'static Path {
         Path::new("immutable")
     }
+
+    fn usage_category() -> UsageCategory {
+        UsageCategory::NewChunks
+    }
 }
 
 impl Subdir for MapChunkStore {
     fn subdir() -> &'static Path {
         Path::new("mutable")
     }
+
+    fn usage_category() -> UsageCategory {
+        UsageCategory::Metadata
+    }
 }
 
 impl Subdir for SequenceChunkStore {
     fn subdir() -> &'static Path {
         Path::new("sequence")
     }
+
+    fn usage_category() -> UsageCategory {
+        UsageCategory::Metadata
+    }
+}
+
+/// Bytes overwritten per write, when shredding a file.
+const SHRED_BLOCK_SIZE: usize = 64 * 1024;
+
+// Overwrites the contents of the file with zeros, on the disk, before it is removed.
+// Journaling and copy-on-write file systems may still keep copies of the contents
+// elsewhere, which only encrypting the disk guards against.
+fn shred(path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let zeros = [0u8; SHRED_BLOCK_SIZE];
+    while remaining > 0 {
+        let len = remaining.min(SHRED_BLOCK_SIZE as u64) as usize;
+        file.write_all(&zeros[..len])?;
+        remaining -= len as u64;
+    }
+    file.sync_all()
+}
+
+// The chunk files are named by the hex of their id.
+fn file_name<I: ChunkId>(id: &I) -> Result<String> {
+    Ok(hex::encode(utils::serialise(id)?))
 }
 
-fn to_chunk_id<T: ChunkId>(entry: &DirEntry) -> Option<T> {
-    let file_name = entry.file_name();
-    let file_name = file_name.into_string().ok()?;
+fn to_chunk_id<T: ChunkId>(file_name: &str) -> Option<T> {
     let bytes = hex::decode(file_name).ok()?;
     bincode::deserialize(&bytes).ok()
 }
@@ -8,7 +8,8 @@
 
 use super::{
     chunk::{Chunk, ChunkId},
-    ChunkStore, Result as ChunkStoreResult, Subdir, UsedSpace,
+    journal::JournalEntry,
+    ChunkCompression, ChunkStore, Result as ChunkStoreResult, Subdir, UsageCategory, UsedSpace,
 };
 use crate::{Error, Result, ToDbKey};
 use rand::{distributions::Standard, rngs::ThreadRng, Rng};
@@ -40,6 +41,10 @@ impl Subdir for ChunkStore<Data> {
     fn subdir() -> &'static Path {
         Path::new("test")
     }
+
+    fn usage_category() -> UsageCategory {
+        UsageCategory::NewChunks
+    }
 }
 
 // TODO: use seedable rng
@@ -268,3 +273,269 @@ async fn keys() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn index_is_checkpointed_and_rebuilt_when_corrupt() -> Result<()> {
+    let root = temp_dir()?;
+    let used_space = UsedSpace::new(u64::MAX);
+    let ids: Vec<_> = (0..3).map(Id).collect();
+    let mut chunk_store = ChunkStore::new(root.path(), used_space.clone()).await?;
+    for id in &ids {
+        chunk_store
+            .put(&Data {
+                id: *id,
+                value: vec![0; 10],
+            })
+            .await?;
+    }
+    chunk_store.checkpoint()?;
+    let checkpoint = chunk_store.dir.with_extension("index");
+    assert!(checkpoint.is_file());
+
+    // the chunk is deleted behind the back of the index
+    std::fs::remove_file(chunk_store.dir.join(super::file_name(&ids[0])?))?;
+    drop(chunk_store);
+    let chunk_store = ChunkStore::<Data>::new(root.path(), used_space.clone()).await?;
+    assert!(chunk_store.has(&ids[0]));
+    assert!(chunk_store.get(&ids[0]).is_err());
+    assert!(!chunk_store.has(&ids[0]));
+    drop(chunk_store);
+
+    std::fs::write(&checkpoint, b"corrupt")?;
+    std::fs::remove_file(
+        root.path()
+            .join("chunks")
+            .join("test")
+            .join(super::file_name(&ids[1])?),
+    )?;
+    let chunk_store = ChunkStore::<Data>::new(root.path(), used_space).await?;
+    let mut keys = chunk_store.keys();
+    keys.sort();
+    assert_eq!(keys, vec![ids[2]]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn chunks_are_served_while_migrated_to_relocated_store() -> Result<()> {
+    let old_root = temp_dir()?;
+    let new_root = temp_dir()?;
+    let used_space = UsedSpace::new(u64::MAX);
+    let data = |id| Data {
+        id: Id(id),
+        value: vec![id as u8; 10],
+    };
+    let mut chunk_store = ChunkStore::new(old_root.path(), used_space.clone()).await?;
+    for id in 0..3 {
+        chunk_store.put(&data(id)).await?;
+    }
+    let used_before = chunk_store.total_used_space().await;
+
+    chunk_store.relocate(new_root.path()).await?;
+    assert!(chunk_store.relocate(old_root.path()).await.is_err());
+    chunk_store.put(&data(3)).await?;
+    // the old chunks are read from the old location, and new ones from the new
+    assert_eq!(chunk_store.keys().len(), 4);
+    assert_eq!(chunk_store.get(&Id(0))?, data(0));
+    assert_eq!(chunk_store.get(&Id(3))?, data(3));
+
+    chunk_store.migrate(2).await?;
+    assert!(chunk_store.is_migrating());
+    drop(chunk_store);
+    // resumed after a restart
+    let mut chunk_store = ChunkStore::<Data>::open_relocated(
+        new_root.path(),
+        Some(old_root.path()),
+        used_space.clone(),
+    )
+    .await?;
+    assert!(chunk_store.is_migrating());
+    chunk_store.migrate(2).await?;
+    assert!(!chunk_store.is_migrating());
+
+    let mut keys = chunk_store.keys();
+    keys.sort();
+    assert_eq!(keys, (0..4).map(Id).collect::<Vec<_>>());
+    for id in 0..4 {
+        assert_eq!(chunk_store.get(&Id(id))?, data(id));
+    }
+    assert!(!old_root.path().join("chunks").join("test").exists());
+    assert_eq!(
+        chunk_store.total_used_space().await,
+        used_before + bincode::serialized_size(&data(3)).map_err(Error::Bincode)?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn compressed_chunks_use_the_space_of_their_files() -> Result<()> {
+    let root = temp_dir()?;
+    let used_space = UsedSpace::new(u64::MAX);
+    let mut chunk_store = ChunkStore::new(root.path(), used_space.clone()).await?;
+
+    let data = |i| Data {
+        id: Id(i),
+        value: b"text ".repeat(1000),
+    };
+    chunk_store.put(&data(0)).await?;
+    chunk_store.set_compression(Some(ChunkCompression::Zstd));
+    chunk_store.put(&data(1)).await?;
+
+    // chunks written before the compression was set are still read
+    assert_eq!(chunk_store.get(&Id(0))?, data(0));
+    assert_eq!(chunk_store.get(&Id(1))?, data(1));
+
+    let file_size = |i| -> Result<u64> {
+        let file_path = chunk_store.dir.join(super::file_name(&Id(i))?);
+        Ok(std::fs::metadata(file_path)?.len())
+    };
+    let (uncompressed, compressed) = (file_size(0)?, file_size(1)?);
+    assert!(compressed < uncompressed / 10);
+    assert_eq!(
+        chunk_store.total_used_space().await,
+        uncompressed + compressed
+    );
+
+    chunk_store.delete(&Id(1)).await?;
+    assert_eq!(chunk_store.total_used_space().await, uncompressed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn chunks_are_overwritten_before_removal_when_secure_delete() -> Result<()> {
+    let root = temp_dir()?;
+    let used_space = UsedSpace::new(u64::MAX);
+    let mut chunk_store = ChunkStore::new(root.path(), used_space.clone()).await?;
+    chunk_store.set_secure_delete(true);
+
+    let data = Data {
+        id: Id(0),
+        value: new_rng().sample_iter(&Standard).take(1000).collect(),
+    };
+    let chunk_path = chunk_store.dir.join(super::file_name(&data.id)?);
+    chunk_store.put(&data).await?;
+    assert!(chunk_path.exists());
+    chunk_store.delete(&data.id).await?;
+    assert!(!chunk_store.has(&data.id));
+    assert!(!chunk_path.exists());
+    assert_eq!(chunk_store.total_used_space().await, 0);
+
+    // what is left on the disk of a shredded file are zeros
+    let file_path = root.path().join("shredded");
+    std::fs::write(&file_path, &data.value)?;
+    super::shred(&file_path)?;
+    assert_eq!(std::fs::read(&file_path)?, vec![0; data.value.len()]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn corrupt_chunks_are_quarantined() -> Result<()> {
+    let root = temp_dir()?;
+    let used_space = UsedSpace::new(u64::MAX);
+    let mut chunk_store = ChunkStore::new(root.path(), used_space.clone()).await?;
+
+    let data = Data {
+        id: Id(0),
+        value: new_rng().sample_iter(&Standard).take(100).collect(),
+    };
+    let file_name = super::file_name(&data.id)?;
+    chunk_store.put(&data).await?;
+    std::fs::write(chunk_store.dir.join(&file_name), b"corrupt")?;
+    assert!(chunk_store.get(&data.id).is_err());
+
+    chunk_store.quarantine(&data.id).await?;
+    assert!(!chunk_store.has(&data.id));
+    assert!(!chunk_store.dir.join(&file_name).exists());
+    let quarantined = chunk_store
+        .dir
+        .with_extension(super::QUARANTINE_DIR_EXTENSION)
+        .join(&file_name);
+    assert_eq!(std::fs::read(quarantined)?, b"corrupt".to_vec());
+    assert_eq!(chunk_store.total_used_space().await, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn chunks_larger_than_the_write_buffer_are_written_through_it() -> Result<()> {
+    let root = temp_dir()?;
+    let used_space = UsedSpace::new(u64::MAX);
+    let mut chunk_store = ChunkStore::new(root.path(), used_space.clone()).await?;
+    chunk_store.set_write_buffer_size(16);
+
+    let data = Data {
+        id: Id(0),
+        value: new_rng().sample_iter(&Standard).take(10_000).collect(),
+    };
+    chunk_store.put(&data).await?;
+
+    let serialised = crate::utils::serialise(&data)?;
+    let file_name = super::file_name(&data.id)?;
+    assert_eq!(
+        std::fs::read(chunk_store.dir.join(file_name))?,
+        serialised.to_vec()
+    );
+    assert_eq!(
+        chunk_store.total_used_space().await,
+        serialised.len() as u64
+    );
+    assert_eq!(chunk_store.get(&data.id)?, data);
+    Ok(())
+}
+
+#[tokio::test]
+async fn writes_interrupted_by_a_crash_are_replayed_or_discarded() -> Result<()> {
+    let root = temp_dir()?;
+    let chunk_store = ChunkStore::<Data>::new(root.path(), UsedSpace::new(u64::MAX)).await?;
+    let (complete, truncated) = (
+        Data {
+            id: Id(0),
+            value: vec![1; 100],
+        },
+        Data {
+            id: Id(1),
+            value: vec![2; 100],
+        },
+    );
+    // as left by a crash after reserving the space, mid-way through the writes
+    for data in [&complete, &truncated] {
+        let reserved = bincode::serialized_size(data).map_err(Error::Bincode)?;
+        chunk_store
+            .used_space
+            .increase(chunk_store.id, reserved)
+            .await?;
+        let file_name = super::file_name(&data.id)?;
+        let entry = JournalEntry {
+            reserved,
+            category: None,
+        };
+        chunk_store.journal.begin(&file_name, &entry)?;
+        let serialised = crate::utils::serialise(data)?;
+        let written = if data == &complete {
+            &serialised[..]
+        } else {
+            &serialised[..50]
+        };
+        std::fs::write(chunk_store.journal.data_path(&file_name), written)?;
+    }
+    let journal_path = chunk_store.journal.data_path("");
+    drop(chunk_store);
+
+    let chunk_store = ChunkStore::<Data>::new(root.path(), UsedSpace::new(u64::MAX)).await?;
+    assert_eq!(chunk_store.get(&complete.id)?, complete);
+    assert!(!chunk_store.has(&truncated.id));
+    assert!(!chunk_store
+        .dir
+        .join(super::file_name(&truncated.id)?)
+        .exists());
+    // the space recorded by the store, as not loaded into the total on opening
+    assert_eq!(
+        chunk_store.used_space.local(chunk_store.id).await,
+        bincode::serialized_size(&complete).map_err(Error::Bincode)?
+    );
+    assert_eq!(std::fs::read_dir(journal_path)?.count(), 0);
+    Ok(())
+}
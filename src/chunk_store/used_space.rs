@@ -8,10 +8,46 @@
 
 use crate::{Error, Result};
 use log::warn;
-use std::{path::Path, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    path::Path,
+    sync::Arc,
+};
 use tokio::{io::AsyncSeekExt, sync::Mutex};
 
 const USED_SPACE_FILENAME: &str = "used_space";
+const USED_SPACE_BY_CATEGORY_FILENAME: &str = "used_space_by_category";
+
+/// The categories of data the used space is attributed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UsageCategory {
+    /// Chunks stored on behalf of clients.
+    NewChunks,
+    /// Chunks stored as copies, on replication.
+    ReplicatedChunks,
+    /// Maps and Sequences held at Elders.
+    Metadata,
+    /// Histories of transfers held at Elders.
+    TransferLogs,
+    /// Data set aside until it can be verified or deleted.
+    Quarantine,
+}
+
+impl Display for UsageCategory {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::NewChunks => "new-chunks",
+            Self::ReplicatedChunks => "replicated-chunks",
+            Self::Metadata => "metadata",
+            Self::TransferLogs => "transfer-logs",
+            Self::Quarantine => "quarantine",
+        };
+        write!(formatter, "{}", name)
+    }
+}
 
 /// This holds a record (in-memory and on-disk) of the space used by a single `ChunkStore`, and also
 /// an in-memory record of the total space used by all `ChunkStore`s.
@@ -56,6 +92,16 @@ impl UsedSpace {
         inner::UsedSpace::total(self.inner.clone()).await
     }
 
+    /// Returns the used space per category as a snapshot
+    pub async fn by_category(&self) -> BTreeMap<UsageCategory, u64> {
+        inner::UsedSpace::by_category(self.inner.clone()).await
+    }
+
+    /// Limits the space the category may use, in addition to the max capacity
+    pub async fn set_limit(&self, category: UsageCategory, limit: u64) {
+        inner::UsedSpace::set_limit(self.inner.clone(), category, limit).await
+    }
+
     /// Returns the used space of a local store as a snapshot
     /// Note, due to the async nature of this, the value
     /// may be stale by the time it is read if there are multiple
@@ -66,19 +112,43 @@ impl UsedSpace {
     }
 
     /// Add an object and file store to track used space of a single
-    /// `ChunkStore`
-    pub async fn add_local_store<T: AsRef<Path>>(&self, dir: T) -> Result<StoreId> {
-        inner::UsedSpace::add_local_store(self.inner.clone(), dir).await
+    /// `ChunkStore`, attributing its usage to the category unless told otherwise
+    pub async fn add_local_store<T: AsRef<Path>>(
+        &self,
+        dir: T,
+        category: UsageCategory,
+    ) -> Result<StoreId> {
+        inner::UsedSpace::add_local_store(self.inner.clone(), dir, category).await
     }
 
     /// Increase the used amount of a single chunk store and the global used value
     pub async fn increase(&self, id: StoreId, consumed: u64) -> Result<()> {
-        inner::UsedSpace::increase(self.inner.clone(), id, consumed).await
+        inner::UsedSpace::increase(self.inner.clone(), id, None, consumed).await
+    }
+
+    /// Increase the used amount of a single chunk store, attributed to the category
+    pub async fn increase_as(
+        &self,
+        id: StoreId,
+        category: UsageCategory,
+        consumed: u64,
+    ) -> Result<()> {
+        inner::UsedSpace::increase(self.inner.clone(), id, Some(category), consumed).await
     }
 
     /// Decrease the used amount of a single chunk store and the global used value
     pub async fn decrease(&self, id: StoreId, released: u64) -> Result<()> {
-        inner::UsedSpace::decrease(self.inner.clone(), id, released).await
+        inner::UsedSpace::decrease(self.inner.clone(), id, None, released).await
+    }
+
+    /// Decrease the used amount of a single chunk store, attributed to the category
+    pub async fn decrease_as(
+        &self,
+        id: StoreId,
+        category: UsageCategory,
+        released: u64,
+    ) -> Result<()> {
+        inner::UsedSpace::decrease(self.inner.clone(), id, Some(category), released).await
     }
 }
 
@@ -99,6 +169,10 @@ mod inner {
         max_capacity: u64,
         /// Total space consumed across all `ChunkStore`s, including this one
         total_value: u64,
+        /// Total space consumed per category, across all `ChunkStore`s
+        by_category: BTreeMap<UsageCategory, u64>,
+        /// the max value (inclusive) the space consumed by a category can attain
+        limits: BTreeMap<UsageCategory, u64>,
         /// the used space tracking for each chunk store
         local_stores: HashMap<StoreId, LocalUsedSpace>,
         /// next local `ChunkStore` id to use
@@ -115,6 +189,12 @@ mod inner {
         // to ensure stale entries aren't recorded, and to avoid holding the lock for the
         // whole inner::UsedSpace struct during the entirety of the file write.
         pub local_record: File,
+        // Space consumed by this one `ChunkStore`, per category.
+        pub by_category: BTreeMap<UsageCategory, u64>,
+        // File used to maintain on-disk record of `by_category`.
+        pub category_record: File,
+        // The category usage is attributed to, unless told otherwise.
+        pub default_category: UsageCategory,
     }
 
     impl UsedSpace {
@@ -122,6 +202,8 @@ mod inner {
             Self {
                 max_capacity,
                 total_value: 0u64,
+                by_category: BTreeMap::new(),
+                limits: BTreeMap::new(),
                 local_stores: HashMap::new(),
                 next_id: 0u64,
             }
@@ -133,10 +215,20 @@ mod inner {
         pub async fn reset(used_space: Arc<Mutex<UsedSpace>>) {
             let mut used_space_lock = used_space.lock().await;
             used_space_lock.total_value = 0;
+            used_space_lock.by_category.clear();
             for (_id, local_used_space) in used_space_lock.local_stores.iter_mut() {
                 local_used_space.local_value = 0;
+                local_used_space.by_category.clear();
                 if let Err(err) =
-                    Self::write_local_to_file(&mut local_used_space.local_record, 0).await
+                    Self::write_local_to_file(&mut local_used_space.local_record, &0_u64).await
+                {
+                    warn!("Error updating used_space file on disk: {}", err);
+                }
+                if let Err(err) = Self::write_local_to_file(
+                    &mut local_used_space.category_record,
+                    &local_used_space.by_category,
+                )
+                .await
                 {
                     warn!("Error updating used_space file on disk: {}", err);
                 }
@@ -159,6 +251,24 @@ mod inner {
             used_space_lock.total_value
         }
 
+        /// Returns the used space per category as a snapshot
+        pub async fn by_category(
+            used_space: Arc<Mutex<UsedSpace>>,
+        ) -> BTreeMap<UsageCategory, u64> {
+            let used_space_lock = used_space.lock().await;
+            used_space_lock.by_category.clone()
+        }
+
+        /// Sets the max space the category can consume
+        pub async fn set_limit(
+            used_space: Arc<Mutex<UsedSpace>>,
+            category: UsageCategory,
+            limit: u64,
+        ) {
+            let mut used_space_lock = used_space.lock().await;
+            let _ = used_space_lock.limits.insert(category, limit);
+        }
+
         /// Returns the used space of a local store as a snapshot
         /// Note, due to the async nature of this, the value
         /// may be stale by the time it is read if there are multiple
@@ -177,37 +287,65 @@ mod inner {
         pub async fn add_local_store<T: AsRef<Path>>(
             used_space: Arc<Mutex<UsedSpace>>,
             dir: T,
+            default_category: UsageCategory,
         ) -> Result<StoreId> {
-            let mut local_record = OpenOptions::new()
+            let (local_record, local_value) =
+                Self::open_record(&dir.as_ref().join(USED_SPACE_FILENAME), 0_u64).await?;
+            let (category_record, mut by_category) = Self::open_record(
+                &dir.as_ref().join(USED_SPACE_BY_CATEGORY_FILENAME),
+                BTreeMap::<UsageCategory, u64>::new(),
+            )
+            .await?;
+            // stores recorded before attribution was tracked
+            let attributed: u64 = by_category.values().sum();
+            if attributed < local_value {
+                *by_category.entry(default_category).or_default() += local_value - attributed;
+            }
+
+            let mut used_space_lock = used_space.lock().await;
+            for (category, value) in &by_category {
+                *used_space_lock.by_category.entry(*category).or_default() += value;
+            }
+            let local_store = LocalUsedSpace {
+                local_value,
+                local_record,
+                by_category,
+                category_record,
+                default_category,
+            };
+            let id = used_space_lock.next_id;
+            used_space_lock.next_id += 1;
+            let _ = used_space_lock.local_stores.insert(id, local_store);
+            Ok(id)
+        }
+
+        // Opens the file of a record, reading the value in it,
+        // or writing the initial value if it is empty.
+        async fn open_record<V: Serialize + serde::de::DeserializeOwned>(
+            path: &Path,
+            initial: V,
+        ) -> Result<(File, V)> {
+            let mut record = OpenOptions::new()
                 .read(true)
                 .write(true)
                 .create(true)
-                .open(dir.as_ref().join(USED_SPACE_FILENAME))
+                .open(path)
                 .await?;
 
             // try read
             let mut buffer = vec![];
-            let could_read = local_record.read_to_end(&mut buffer).await.is_ok();
+            let could_read = record.read_to_end(&mut buffer).await.is_ok();
             let has_value = !buffer.is_empty();
-            let local_value = if could_read && has_value {
+            let value = if could_read && has_value {
                 // TODO - if this can't be parsed, we should consider emptying `dir` of any chunks.
-                bincode::deserialize::<u64>(&buffer)?
+                bincode::deserialize::<V>(&buffer)?
             } else {
                 let mut bytes = Vec::<u8>::new();
-                bincode::serialize_into(&mut bytes, &0_u64)?;
-                local_record.write_all(&bytes).await?;
-                0
+                bincode::serialize_into(&mut bytes, &initial)?;
+                record.write_all(&bytes).await?;
+                initial
             };
-
-            let local_store = LocalUsedSpace {
-                local_value,
-                local_record,
-            };
-            let mut used_space_lock = used_space.lock().await;
-            let id = used_space_lock.next_id;
-            used_space_lock.next_id += 1;
-            let _ = used_space_lock.local_stores.insert(id, local_store);
-            Ok(id)
+            Ok((record, value))
         }
 
         /// Asynchronous implementation to increase used space in a local store
@@ -215,6 +353,7 @@ mod inner {
         pub async fn increase(
             used_space: Arc<Mutex<UsedSpace>>,
             id: StoreId,
+            category: Option<UsageCategory>,
             consumed: u64,
         ) -> Result<()> {
             let mut used_space_lock = used_space.lock().await;
@@ -225,28 +364,37 @@ mod inner {
             if new_total > used_space_lock.max_capacity {
                 return Err(Error::NotEnoughSpace);
             }
-            let new_local = used_space_lock
+            let local = used_space_lock
                 .local_stores
                 .get(&id)
-                .ok_or(Error::NoStoreId)?
+                .ok_or(Error::NoStoreId)?;
+            let category = category.unwrap_or(local.default_category);
+            let new_local = local
                 .local_value
                 .checked_add(consumed)
                 .ok_or(Error::NotEnoughSpace)?;
-
-            {
-                let record = &mut used_space_lock
-                    .local_stores
-                    .get_mut(&id)
-                    .ok_or(Error::NoStoreId)?
-                    .local_record;
-                Self::write_local_to_file(record, new_local).await?;
+            let new_category_total = used_space_lock
+                .by_category
+                .get(&category)
+                .copied()
+                .unwrap_or_default()
+                .checked_add(consumed)
+                .ok_or(Error::NotEnoughSpace)?;
+            if let Some(limit) = used_space_lock.limits.get(&category) {
+                if new_category_total > *limit {
+                    warn!("Used space limit of {} reached", category);
+                    return Err(Error::NotEnoughSpace);
+                }
             }
+
+            Self::update_local(&mut used_space_lock, id, category, new_local, |value| {
+                value + consumed
+            })
+            .await?;
             used_space_lock.total_value = new_total;
-            used_space_lock
-                .local_stores
-                .get_mut(&id)
-                .ok_or(Error::NoStoreId)?
-                .local_value = new_local;
+            let _ = used_space_lock
+                .by_category
+                .insert(category, new_category_total);
 
             Ok(())
         }
@@ -256,42 +404,60 @@ mod inner {
         pub async fn decrease(
             used_space: Arc<Mutex<UsedSpace>>,
             id: StoreId,
+            category: Option<UsageCategory>,
             released: u64,
         ) -> Result<()> {
             let mut used_space_lock = used_space.lock().await;
-            let new_local = used_space_lock
+            let local = used_space_lock
                 .local_stores
-                .get_mut(&id)
-                .ok_or(Error::NoStoreId)?
-                .local_value
-                .saturating_sub(released);
+                .get(&id)
+                .ok_or(Error::NoStoreId)?;
+            let category = category.unwrap_or(local.default_category);
+            let new_local = local.local_value.saturating_sub(released);
             let new_total = used_space_lock.total_value.saturating_sub(released);
-            {
-                let record = &mut used_space_lock
-                    .local_stores
-                    .get_mut(&id)
-                    .ok_or(Error::NoStoreId)?
-                    .local_record;
-                Self::write_local_to_file(record, new_local).await?;
-            }
+            Self::update_local(&mut used_space_lock, id, category, new_local, |value| {
+                value.saturating_sub(released)
+            })
+            .await?;
             used_space_lock.total_value = new_total;
-            used_space_lock
+            let category_total = used_space_lock.by_category.entry(category).or_default();
+            *category_total = category_total.saturating_sub(released);
+            Ok(())
+        }
+
+        // Updates the value of a local store, and the value of the category in it,
+        // both in memory and on disk.
+        async fn update_local(
+            used_space: &mut UsedSpace,
+            id: StoreId,
+            category: UsageCategory,
+            new_local: u64,
+            update_category: impl FnOnce(u64) -> u64,
+        ) -> Result<()> {
+            let local = used_space
                 .local_stores
                 .get_mut(&id)
-                .ok_or(Error::NoStoreId)?
-                .local_value = new_local;
+                .ok_or(Error::NoStoreId)?;
+            let mut by_category = local.by_category.clone();
+            let value = by_category.entry(category).or_default();
+            *value = update_category(*value);
+
+            Self::write_local_to_file(&mut local.local_record, &new_local).await?;
+            Self::write_local_to_file(&mut local.category_record, &by_category).await?;
+            local.local_value = new_local;
+            local.by_category = by_category;
             Ok(())
         }
 
         /// helper to write the contents of local to file
         /// NOTE: For now, ou should hold the lock on the inner while doing this
         /// It's slow, but maintains behaviour from the previous implementation
-        async fn write_local_to_file(record: &mut File, local: u64) -> Result<()> {
+        async fn write_local_to_file<V: Serialize>(record: &mut File, local: &V) -> Result<()> {
             record.set_len(0).await?;
             let _ = record.seek(SeekFrom::Start(0)).await?;
 
             let mut contents = Vec::<u8>::new();
-            bincode::serialize_into(&mut contents, &local)?;
+            bincode::serialize_into(&mut contents, local)?;
             record.write_all(&contents).await?;
             record.sync_all().await?;
 
@@ -302,7 +468,7 @@ mod inner {
 
 #[cfg(test)]
 mod tests {
-    use super::{Error, Result, UsedSpace};
+    use super::{Error, Result, UsageCategory, UsedSpace};
     use tempdir::TempDir;
 
     const TEST_STORE_MAX_SIZE: u64 = u64::MAX;
@@ -329,7 +495,9 @@ mod tests {
         let root_dir = create_temp_root()?;
         let store_dir = create_temp_store(&root_dir)?;
         let used_space = UsedSpace::new(TEST_STORE_MAX_SIZE);
-        let id = used_space.add_local_store(&store_dir).await?;
+        let id = used_space
+            .add_local_store(&store_dir, UsageCategory::NewChunks)
+            .await?;
         // get a random vec of u64 by adding u32 (avoid overflow)
         let mut rng = rand::thread_rng();
         let bytes = crate::utils::random_vec(&mut rng, std::mem::size_of::<u32>() * NUMS_TO_ADD);
@@ -365,4 +533,46 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn used_space_is_attributed_and_limited_per_category() -> Result<()> {
+        let root_dir = create_temp_root()?;
+        let store_dir = create_temp_store(&root_dir)?;
+        let used_space = UsedSpace::new(TEST_STORE_MAX_SIZE);
+        used_space
+            .set_limit(UsageCategory::ReplicatedChunks, 100)
+            .await;
+        let id = used_space
+            .add_local_store(&store_dir, UsageCategory::NewChunks)
+            .await?;
+
+        used_space.increase(id, 50).await?;
+        used_space
+            .increase_as(id, UsageCategory::ReplicatedChunks, 80)
+            .await?;
+        assert!(matches!(
+            used_space
+                .increase_as(id, UsageCategory::ReplicatedChunks, 21)
+                .await,
+            Err(Error::NotEnoughSpace)
+        ));
+        // other categories are not limited by it
+        used_space.increase(id, 200).await?;
+        used_space
+            .decrease_as(id, UsageCategory::ReplicatedChunks, 30)
+            .await?;
+
+        let by_category = used_space.by_category().await;
+        assert_eq!(by_category.get(&UsageCategory::NewChunks), Some(&250));
+        assert_eq!(by_category.get(&UsageCategory::ReplicatedChunks), Some(&50));
+        assert_eq!(used_space.total().await, 300);
+
+        // the attribution is kept on disk
+        let reopened = UsedSpace::new(TEST_STORE_MAX_SIZE);
+        let _ = reopened
+            .add_local_store(&store_dir, UsageCategory::NewChunks)
+            .await?;
+        assert_eq!(reopened.by_category().await, by_category);
+        Ok(())
+    }
 }
@@ -0,0 +1,151 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{utils, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sn_data_types::BlobAddress;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+const ACCESS_STATS_FILE_NAME: &str = "chunk_access_stats";
+// Changes to the stats are checkpointed to disk at most this often, and when closed.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How much a chunk held by an Adult is read by clients.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkAccess {
+    /// Reads of the chunk served since it was first read by us.
+    pub reads: u64,
+    /// Seconds since unix epoch at which the chunk was last read.
+    pub last_read: u64,
+}
+
+/// The reads by clients of the chunks we hold, kept across restarts, whichever
+/// store the chunks are in, and counting the reads served from the read cache.
+pub(super) struct AccessStats {
+    entries: BTreeMap<BlobAddress, ChunkAccess>,
+    path: PathBuf,
+    last_checkpoint: Instant,
+    dirty: bool,
+}
+
+impl AccessStats {
+    /// Opens the stats checkpointed under the root dir of the node, if any.
+    pub(super) fn open(root: &Path) -> Self {
+        let path = root.join(ACCESS_STATS_FILE_NAME);
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            entries,
+            path,
+            last_checkpoint: Instant::now(),
+            dirty: false,
+        }
+    }
+
+    /// Counts a read of the chunk by a client, at `now_secs`.
+    pub(super) fn read(&mut self, address: &BlobAddress, now_secs: u64) {
+        let access = self.entries.entry(*address).or_default();
+        access.reads = access.reads.saturating_add(1);
+        access.last_read = now_secs;
+        self.changed();
+    }
+
+    /// Forgets the reads of the chunk no longer held.
+    pub(super) fn remove(&mut self, address: &BlobAddress) {
+        if self.entries.remove(address).is_some() {
+            self.changed();
+        }
+    }
+
+    pub(super) fn get(&self, address: &BlobAddress) -> Option<ChunkAccess> {
+        self.entries.get(address).copied()
+    }
+
+    /// The chunks read, the most read first.
+    pub(super) fn most_read(&self) -> Vec<(BlobAddress, ChunkAccess)> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(address, access)| (*address, *access))
+            .collect();
+        entries.sort_by_key(|(_, access)| std::cmp::Reverse(access.reads));
+        entries
+    }
+
+    /// Writes the stats to disk, replacing the previous checkpoint.
+    fn checkpoint(&mut self) -> Result<()> {
+        let temp_path = self.path.with_extension("temp");
+        fs::write(&temp_path, utils::serialise(&self.entries)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        self.last_checkpoint = Instant::now();
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn changed(&mut self) {
+        self.dirty = true;
+        if self.last_checkpoint.elapsed() < CHECKPOINT_INTERVAL {
+            return;
+        }
+        if let Err(e) = self.checkpoint() {
+            warn!("Could not checkpoint chunk access stats: {}", e);
+        }
+    }
+}
+
+impl Drop for AccessStats {
+    fn drop(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        if let Err(e) = self.checkpoint() {
+            warn!("Could not checkpoint chunk access stats: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+    use xor_name::XorName;
+
+    #[test]
+    fn reads_are_kept_across_restarts() -> Result<()> {
+        let root = TempDir::new("chunk_access_stats")?;
+        let (hot, cold) = (
+            BlobAddress::Public(XorName::random()),
+            BlobAddress::Public(XorName::random()),
+        );
+        {
+            let mut stats = AccessStats::open(root.path());
+            stats.read(&cold, 1_000);
+            for now in 1_001..1_004 {
+                stats.read(&hot, now);
+            }
+        }
+
+        let mut stats = AccessStats::open(root.path());
+        assert_eq!(stats.get(&hot).map(|access| access.reads), Some(3));
+        assert_eq!(stats.get(&hot).map(|access| access.last_read), Some(1_003));
+        let most_read: Vec<_> = stats.most_read().into_iter().map(|(a, _)| a).collect();
+        assert_eq!(most_read, vec![hot, cold]);
+
+        stats.remove(&hot);
+        assert_eq!(stats.get(&hot), None);
+        Ok(())
+    }
+}
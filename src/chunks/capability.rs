@@ -0,0 +1,186 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{utils, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::Path,
+    time::Instant,
+};
+use xor_name::XorName;
+
+/// The score of a node benchmarked at the rates of reference.
+pub const REFERENCE_CAPABILITY: u32 = 100;
+// The benchmark is kept in the root dir under this name, so that it is run on the first join only.
+const CAPABILITY_FILENAME: &str = "capability";
+// The file benchmarking the disk is written under this name, and removed once read back.
+const BENCHMARK_FILENAME: &str = "capability_benchmark";
+// Bytes written to and read from disk, hashed, and copied in memory, by the benchmark.
+const DISK_BENCHMARK_BYTES: usize = 16 * 1024 * 1024;
+const HASH_BENCHMARK_BYTES: usize = 32 * 1024 * 1024;
+const MEMORY_BENCHMARK_BYTES: usize = 256 * 1024 * 1024;
+// The bytes are processed in blocks of the size of a chunk at most.
+const BLOCK_BYTES: usize = 1024 * 1024;
+// The rates of reference, in bytes per second, scoring `REFERENCE_CAPABILITY` each.
+const REFERENCE_DISK_RATE: u64 = 100 * 1024 * 1024;
+const REFERENCE_HASH_RATE: u64 = 200 * 1024 * 1024;
+const REFERENCE_MEMORY_RATE: u64 = 2 * 1024 * 1024 * 1024;
+// A rate scores at most this many times the score of reference, so that a single
+// outstanding rate does not outweigh the others.
+const MAX_RATE_MULTIPLE: u64 = 4;
+
+/// The capability of a node, as benchmarked on its first join, reported to its Elders
+/// with its capacity, for them to weigh the chunks it is to hold and the reads it is to serve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capability {
+    /// Bytes per second written to disk, synced.
+    pub disk_write_rate: u64,
+    /// Bytes per second read back from disk.
+    pub disk_read_rate: u64,
+    /// Bytes per second hashed into chunk names.
+    pub hash_rate: u64,
+    /// Bytes per second copied in memory.
+    pub memory_rate: u64,
+    /// The score of the rates, `REFERENCE_CAPABILITY` for those of reference.
+    pub score: u32,
+}
+
+impl Capability {
+    fn new(disk_write_rate: u64, disk_read_rate: u64, hash_rate: u64, memory_rate: u64) -> Self {
+        let scored = |rate: u64, reference: u64| {
+            (u128::from(rate) * u128::from(REFERENCE_CAPABILITY) / u128::from(reference)).min(
+                u128::from(MAX_RATE_MULTIPLE * u64::from(REFERENCE_CAPABILITY)),
+            ) as u32
+        };
+        // the disk weighs as much as hashing and memory together, as chunks are stored on it
+        let disk = (scored(disk_write_rate, REFERENCE_DISK_RATE)
+            + scored(disk_read_rate, REFERENCE_DISK_RATE))
+            / 2;
+        let score = (2 * disk
+            + scored(hash_rate, REFERENCE_HASH_RATE)
+            + scored(memory_rate, REFERENCE_MEMORY_RATE))
+            / 4;
+        Self {
+            disk_write_rate,
+            disk_read_rate,
+            hash_rate,
+            memory_rate,
+            score,
+        }
+    }
+
+    /// The capability of the node, as benchmarked on its first join,
+    /// benchmarking it now if it has not been yet.
+    pub(crate) fn of_node(root_dir: &Path) -> Result<Self> {
+        let path = root_dir.join(CAPABILITY_FILENAME);
+        match fs::read(&path) {
+            Ok(bytes) => return utils::deserialise(&bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+        let capability = Self::benchmark(root_dir)?;
+        info!("Benchmarked the capability of the node: {:?}", capability);
+        fs::write(path, utils::serialise(&capability)?)?;
+        Ok(capability)
+    }
+
+    // Benchmarks the disk in the dir, hashing, and memory, in turn.
+    fn benchmark(dir: &Path) -> Result<Self> {
+        let block: Vec<u8> = (0..BLOCK_BYTES).map(|byte| byte as u8).collect();
+
+        let path = dir.join(BENCHMARK_FILENAME);
+        let started = Instant::now();
+        let mut file = File::create(&path)?;
+        for _ in 0..DISK_BENCHMARK_BYTES / BLOCK_BYTES {
+            file.write_all(&block)?;
+        }
+        file.sync_all()?;
+        let disk_write_rate = rate(DISK_BENCHMARK_BYTES, started);
+        drop(file);
+        // read back mostly from the page cache, which is what serves the reads of chunks most
+        let started = Instant::now();
+        let mut read = vec![0; BLOCK_BYTES];
+        let mut file = File::open(&path)?;
+        for _ in 0..DISK_BENCHMARK_BYTES / BLOCK_BYTES {
+            file.read_exact(&mut read)?;
+        }
+        let disk_read_rate = rate(DISK_BENCHMARK_BYTES, started);
+        drop(file);
+        fs::remove_file(&path)?;
+
+        let started = Instant::now();
+        let mut name = XorName::default();
+        for _ in 0..HASH_BENCHMARK_BYTES / BLOCK_BYTES {
+            name = XorName::from_content(&[&block, &name.0]);
+        }
+        let hash_rate = rate(HASH_BENCHMARK_BYTES, started);
+
+        let started = Instant::now();
+        let mut copy = vec![0; BLOCK_BYTES];
+        for _ in 0..MEMORY_BENCHMARK_BYTES / BLOCK_BYTES {
+            copy.copy_from_slice(&block);
+        }
+        let memory_rate = rate(MEMORY_BENCHMARK_BYTES, started);
+        // so that the copies are not optimised away
+        let _ = std::hint::black_box((name, copy));
+
+        Ok(Self::new(
+            disk_write_rate,
+            disk_read_rate,
+            hash_rate,
+            memory_rate,
+        ))
+    }
+}
+
+// Bytes per second, of the bytes processed since the time started.
+fn rate(bytes: usize, started: Instant) -> u64 {
+    let micros = started.elapsed().as_micros().max(1);
+    (bytes as u128 * 1_000_000 / micros).min(u128::from(u64::MAX)) as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+    use tempdir::TempDir;
+
+    #[test]
+    fn rates_are_scored_against_those_of_reference() {
+        let reference = Capability::new(
+            REFERENCE_DISK_RATE,
+            REFERENCE_DISK_RATE,
+            REFERENCE_HASH_RATE,
+            REFERENCE_MEMORY_RATE,
+        );
+        assert_eq!(reference.score, REFERENCE_CAPABILITY);
+        // a slow disk weighs as much as slow hashing and memory together
+        let slow_disk = Capability::new(0, 0, REFERENCE_HASH_RATE, REFERENCE_MEMORY_RATE);
+        assert_eq!(slow_disk.score, REFERENCE_CAPABILITY / 2);
+        // and a fast memory alone only scores so much
+        let fast_memory = Capability::new(0, 0, 0, u64::MAX);
+        assert_eq!(
+            fast_memory.score,
+            MAX_RATE_MULTIPLE as u32 * REFERENCE_CAPABILITY / 4
+        );
+    }
+
+    #[test]
+    fn node_is_benchmarked_on_its_first_join_only() -> Result<()> {
+        let root =
+            TempDir::new("capability").map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let first = Capability::of_node(root.path())?;
+        assert!(first.disk_write_rate > 0 && first.hash_rate > 0);
+        assert!(!root.path().join(BENCHMARK_FILENAME).exists());
+        assert_eq!(Capability::of_node(root.path())?, first);
+        Ok(())
+    }
+}
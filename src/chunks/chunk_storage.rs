@@ -6,15 +6,33 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::{
+    access_stats::{AccessStats, ChunkAccess},
+    import::{self, ChunkFile, ChunkImport, ChunkImportReport, ImportedChunks},
+    orphans::OrphanOffer,
+    read_cache::{ReadCache, ReadCacheStats},
+    reading::ChunkRange,
+    scrubbing::Scrubber,
+    storage_policy::{Pressure, StorageGuard, StoragePolicy},
+    store::ChunkStore,
+};
 use crate::{
-    chunk_store::{BlobChunkStore, UsedSpace},
+    chunk_store::{BlobChunkStore, ChunkCompression, UsageCategory, UsedSpace},
     error::convert_to_error_message,
-    node_ops::{NodeDuty, OutgoingMsg},
+    metadata::{
+        CapacityReport, RelayedChunk, RelayedRead, StorageChallenge, StorageProof,
+        MAX_CHUNK_ACCESS_REPORT,
+    },
+    network::NetworkClock,
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
     section_funds::elder_signing,
-    Error, NodeInfo, Result,
+    utils, Error, NodeInfo, Result, ToDbKey,
 };
-use log::{error, info};
-use sn_data_types::{Blob, BlobAddress};
+use log::{error, info, warn};
+use pickledb::PickleDb;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Blob, BlobAddress, PublicKey, Signature};
 use sn_messaging::{
     client::{
         CmdError, Error as ErrorMessage, Message, NodeDataQueryResponse, NodeQuery,
@@ -23,17 +41,52 @@ use sn_messaging::{
     Aggregation, DstLocation, EndUser, MessageId, SrcLocation,
 };
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     env::current_dir,
     fmt::{self, Display, Formatter},
-    path::Path,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 use xor_name::XorName;
 
+const CONFLICTS_DB_NAME: &str = "chunk_conflicts.db";
+/// Where the chunks are stored, once relocated from the node root dir.
+const CHUNK_ROOTS_FILE_NAME: &str = "chunk_roots";
+/// Chunks migrated per migration tick.
+const MIGRATION_BATCH: usize = 16;
+
+/// The roots of the chunk store, as relocated by the operator.
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkRoots {
+    root: PathBuf,
+    // the root relocated from, while its chunks are migrated
+    migrating_from: Option<PathBuf>,
+}
+
 /// Storage of data chunks.
 pub(crate) struct ChunkStorage {
     node_name: XorName,
-    chunks: BlobChunkStore,
+    chunks: Box<dyn ChunkStore>,
+    // whether the store was plugged in by the operator, rather than the default one on disk
+    plugged: bool,
+    // Number of conflicting writes seen per chunk address.
+    conflicts: PickleDb,
+    // the node root dir, and the root the chunks are stored at
+    node_root: PathBuf,
+    root: PathBuf,
+    // bounds the chunks stored, if a storage policy is set
+    guard: Option<StorageGuard>,
+    // the chunks most recently read by clients, if they are cached
+    read_cache: Option<ReadCache>,
+    // bytes of a chunk at most accepted by our section, if set
+    max_chunk_size: Option<u64>,
+    // the import of chunks by the operator, if started
+    import: Option<ChunkImport>,
+    // the reads of the chunks by clients
+    access: AccessStats,
+    // the time the reads of chunks are recorded at
+    clock: Arc<NetworkClock>,
 }
 
 impl ChunkStorage {
@@ -41,9 +94,289 @@ impl ChunkStorage {
         node_name: XorName,
         path: &Path,
         used_space: UsedSpace,
+        secure_delete: bool,
+        write_buffer: Option<usize>,
+        compression: Option<ChunkCompression>,
+        clock: Arc<NetworkClock>,
+    ) -> Result<Self> {
+        let roots = fs::read(path.join(CHUNK_ROOTS_FILE_NAME))
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<ChunkRoots>(&bytes).ok());
+        let (mut chunks, root) = match roots {
+            Some(roots) => {
+                info!("Chunks are relocated: {:?}", roots);
+                let chunks = BlobChunkStore::open_relocated(
+                    &roots.root,
+                    roots.migrating_from.as_deref(),
+                    used_space,
+                )
+                .await?;
+                (chunks, roots.root)
+            }
+            None => (
+                BlobChunkStore::new(path, used_space).await?,
+                path.to_path_buf(),
+            ),
+        };
+        chunks.set_secure_delete(secure_delete);
+        if let Some(size) = write_buffer {
+            chunks.set_write_buffer_size(size);
+        }
+        chunks.set_compression(compression);
+        chunks.set_clock(clock.clone());
+        Self::open(node_name, path, Box::new(chunks), root, false, clock)
+    }
+
+    /// Storage of the chunks in the store plugged in, rather than on disk.
+    pub(crate) fn with_store(
+        node_name: XorName,
+        path: &Path,
+        store: Box<dyn ChunkStore>,
+        clock: Arc<NetworkClock>,
     ) -> Result<Self> {
-        let chunks = BlobChunkStore::new(path, used_space).await?;
-        Ok(Self { chunks, node_name })
+        Self::open(node_name, path, store, path.to_path_buf(), true, clock)
+    }
+
+    fn open(
+        node_name: XorName,
+        path: &Path,
+        chunks: Box<dyn ChunkStore>,
+        root: PathBuf,
+        plugged: bool,
+        clock: Arc<NetworkClock>,
+    ) -> Result<Self> {
+        let conflicts = utils::new_auto_dump_db(path, CONFLICTS_DB_NAME)?;
+        Ok(Self {
+            chunks,
+            plugged,
+            node_name,
+            conflicts,
+            node_root: path.to_path_buf(),
+            root,
+            guard: None,
+            read_cache: None,
+            max_chunk_size: None,
+            import: None,
+            access: AccessStats::open(path),
+            clock,
+        })
+    }
+
+    /// The store plugged in, if any, to be kept for when we store chunks again.
+    /// The default store is reopened from disk instead.
+    pub(crate) fn into_store(self) -> Option<Box<dyn ChunkStore>> {
+        if self.plugged {
+            Some(self.chunks)
+        } else {
+            None
+        }
+    }
+
+    /// Relocates the chunks to the root, e.g. on another disk, while they keep being served.
+    /// New chunks are stored at the new root, while the chunks at the old one are
+    /// read from there, until migrated over in the background by `migrate`.
+    pub(crate) async fn relocate(&mut self, root: PathBuf) -> Result<()> {
+        self.chunks.relocate(&root).await?;
+        let previous = std::mem::replace(&mut self.root, root);
+        self.save_roots(Some(previous))
+    }
+
+    /// Migrates the next batch of chunks to the root relocated to, if any are left.
+    pub(crate) async fn migrate(&mut self) -> Result<()> {
+        if !self.chunks.is_migrating() {
+            return Ok(());
+        }
+        self.chunks.migrate(MIGRATION_BATCH).await?;
+        if !self.chunks.is_migrating() {
+            info!(
+                "{}: Relocation of chunks to {:?} completed",
+                self, self.root
+            );
+            self.save_roots(None)?;
+        }
+        Ok(())
+    }
+
+    /// Starts importing the chunks of the dir or archive, in batches, unless
+    /// an import is in progress already.
+    pub(crate) fn start_import(&mut self, source: PathBuf) -> Result<()> {
+        if let Some(import) = &self.import {
+            if !import.report().completed {
+                return Err(Error::InvalidOperation(format!(
+                    "Import of chunks from {:?} in progress",
+                    import.report().source
+                )));
+            }
+        }
+        self.import = Some(ChunkImport::open(source, &self.node_root)?);
+        Ok(())
+    }
+
+    /// Imports the next batch of chunks, if an import is in progress, returning those
+    /// held of them, by then, for the Elders to register us as their holder.
+    pub(crate) async fn import_next(&mut self) -> Result<Option<ImportedChunks>> {
+        let mut import = match self.import.take() {
+            Some(import) if !import.report().completed => import,
+            done => {
+                self.import = done;
+                return Ok(None);
+            }
+        };
+        let result = self.import_batch(&mut import).await;
+        import.complete_if_done();
+        self.import = Some(import);
+        let chunks = result?;
+        if chunks.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(ImportedChunks { chunks }))
+        }
+    }
+
+    async fn import_batch(
+        &mut self,
+        import: &mut ChunkImport,
+    ) -> Result<BTreeMap<BlobAddress, Option<PublicKey>>> {
+        let mut chunks = BTreeMap::new();
+        for path in import.next_batch() {
+            let report = import.report_mut();
+            let blob = match import::read_chunk_file(&path) {
+                ChunkFile::Chunk(blob) => blob,
+                ChunkFile::Corrupt => {
+                    warn!("{}: Corrupt chunk not imported: {:?}", self, path);
+                    report.corrupt += 1;
+                    continue;
+                }
+                ChunkFile::Unrecognised => {
+                    report.unrecognised += 1;
+                    continue;
+                }
+            };
+            let address = *blob.address();
+            let owner = blob.owner().copied();
+            if self.chunks.has(&address) {
+                report.already_held += 1;
+            } else if self.check_size(&blob).is_err() || !self.admits(&blob, true).await? {
+                warn!("{}: Chunk refused for import: {:?}", self, address);
+                report.refused += 1;
+                continue;
+            } else {
+                self.chunks.put(&blob, UsageCategory::NewChunks).await?;
+                report.imported += 1;
+                report.imported_bytes += blob.value().len() as u64;
+            }
+            let _ = chunks.insert(address, owner);
+        }
+        Ok(chunks)
+    }
+
+    /// The progress of the last import of chunks, if any.
+    pub(crate) fn import_report(&self) -> Option<ChunkImportReport> {
+        self.import.as_ref().map(|import| import.report().clone())
+    }
+
+    /// Bounds the chunks stored by the storage policy from now on.
+    pub(crate) fn set_storage_policy(&mut self, policy: StoragePolicy) {
+        self.guard = Some(StorageGuard::new(policy));
+    }
+
+    /// Refuses chunks beyond the max chunk size of our section from now on.
+    pub(crate) fn set_max_chunk_size(&mut self, max_chunk_size: u64) {
+        self.max_chunk_size = Some(max_chunk_size);
+    }
+
+    // Whether the chunk is within the max chunk size of our section, if set.
+    fn check_size(&self, data: &Blob) -> Result<()> {
+        let size = data.value().len() as u64;
+        match self.max_chunk_size {
+            Some(max) if size > max => Err(Error::ChunkTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Caches the chunks most recently read by clients, up to the budget of bytes.
+    pub(crate) fn set_read_cache(&mut self, budget: u64) {
+        self.read_cache = Some(ReadCache::new(budget));
+    }
+
+    /// Counters of the read cache, if chunks are cached.
+    pub(crate) fn read_cache_stats(&self) -> Option<ReadCacheStats> {
+        self.read_cache.as_ref().map(ReadCache::stats)
+    }
+
+    // Reads the chunk for a client, from the read cache, if cached there.
+    fn read(&mut self, address: &BlobAddress) -> Result<Blob> {
+        if let Some(blob) = self
+            .read_cache
+            .as_mut()
+            .and_then(|cache| cache.get(address))
+        {
+            return Ok(blob);
+        }
+        let blob = self.chunks.get(address)?;
+        if let Some(cache) = &mut self.read_cache {
+            cache.insert(blob.clone());
+        }
+        Ok(blob)
+    }
+
+    // Deletes the chunk from the store, evicting it from the read cache.
+    async fn remove(&mut self, address: &BlobAddress) -> Result<()> {
+        if let Some(cache) = &mut self.read_cache {
+            cache.remove(address);
+        }
+        self.access.remove(address);
+        self.chunks.delete(address).await
+    }
+
+    /// Applies the storage policy, if set, to the chunks stored, picking the chunks to
+    /// offload when beyond its high watermark. Returns where they are at, by the policy.
+    pub(crate) async fn apply_storage_policy(&mut self) -> Option<Pressure> {
+        let guard = self.guard.as_mut()?;
+        let quota = guard.quota(self.chunks.max_capacity().await);
+        let used = self.chunks.used_space().await;
+        let pressure = guard.assess(used, quota);
+        if pressure == Pressure::High {
+            let offloading = guard.offload(used, quota, self.chunks.keys_by_last_access());
+            if !offloading.is_empty() {
+                // removed once copied by new holders, as the Elders
+                // have them on `NodeDuty::ProcessChunkOffload`
+                info!(
+                    "{}: Offloading {} chunks to the section",
+                    self,
+                    offloading.len()
+                );
+            }
+        }
+        Some(pressure)
+    }
+
+    // Whether the chunk is to be stored, by the storage policy, if set.
+    async fn admits(&self, data: &Blob, new: bool) -> Result<bool> {
+        let guard = match &self.guard {
+            Some(guard) => guard,
+            None => return Ok(true),
+        };
+        let quota = guard.quota(self.chunks.max_capacity().await);
+        let used = self.chunks.used_space().await;
+        let size = bincode::serialized_size(data)?;
+        if new {
+            Ok(guard.admits_new(used, size, quota))
+        } else {
+            Ok(guard.fits(used, size, quota))
+        }
+    }
+
+    fn save_roots(&self, migrating_from: Option<PathBuf>) -> Result<()> {
+        let roots = ChunkRoots {
+            root: self.root.clone(),
+            migrating_from,
+        };
+        fs::write(
+            self.node_root.join(CHUNK_ROOTS_FILE_NAME),
+            utils::serialise(&roots)?,
+        )?;
+        Ok(())
     }
 
     pub(crate) async fn store(
@@ -83,39 +416,101 @@ impl ChunkStorage {
                 return Err(Error::InvalidOwners(*origin.id()));
             }
         }
+        self.check_size(data)?;
 
         if self.chunks.has(data.address()) {
+            match self.chunks.get(data.address()) {
+                Ok(existing) if &existing == data => {
+                    info!(
+                        "{}: Immutable chunk already exists, not storing: {:?}",
+                        self,
+                        data.address()
+                    );
+                    return Err(Error::DataExists);
+                }
+                Ok(_) => {
+                    self.record_conflict(data.address(), origin)?;
+                    return Err(Error::DataConflict(*data.address()));
+                }
+                // gone since it was indexed, so it is written below
+                Err(Error::NoSuchChunk) => (),
+                // a copy we cannot read is a fault of our storage, not a conflict
+                Err(error) => {
+                    error!(
+                        "{}: Unable to read existing chunk {:?}: {:?}",
+                        self,
+                        data.address(),
+                        error
+                    );
+                    return Err(Error::UnreadableChunk(*data.address()));
+                }
+            }
+        }
+        if !self.admits(data, true).await? {
             info!(
-                "{}: Immutable chunk already exists, not storing: {:?}",
+                "{}: Refusing chunk by the storage policy: {:?}",
                 self,
                 data.address()
             );
-            return Err(Error::DataExists);
+            return Err(Error::NotEnoughSpace);
         }
-        self.chunks.put(&data).await
+        self.chunks.put(&data, UsageCategory::NewChunks).await
     }
 
+    // Immutable chunks are write-once, so an attempt to write
+    // different content to an existing address is kept track of.
+    fn record_conflict(&mut self, address: &BlobAddress, origin: EndUser) -> Result<()> {
+        let db_key = address.to_db_key()?;
+        let count = self.conflicts.get::<u64>(&db_key).unwrap_or_default() + 1;
+        error!(
+            "{}: Write of different content to existing chunk {:?}, from {:?} (conflict no. {})",
+            self, address, origin, count
+        );
+        self.conflicts.set(&db_key, &count)?;
+        Ok(())
+    }
+
+    /// Reads are only sent to us by Elders having us on record as holding the chunk,
+    /// so a chunk missing or unreadable is reported to them, for us to be repaired.
+    /// Only the bytes of the range are responded with, if one is given.
     pub(crate) async fn get(
-        &self,
+        &mut self,
         address: &BlobAddress,
+        range: Option<&ChunkRange>,
         msg_id: MessageId,
         origin: EndUser,
-    ) -> Result<NodeDuty> {
-        let result = self
-            .chunks
-            .get(address)
-            .map_err(|_| ErrorMessage::NoSuchData);
-        Ok(NodeDuty::Send(OutgoingMsg {
+    ) -> Result<NodeDuties> {
+        let result = self.read(address).map_err(|_| ErrorMessage::NoSuchData);
+        if result.is_ok() {
+            self.access.read(address, self.clock.now_secs());
+        }
+        let response = match (&result, range) {
+            (Ok(blob), Some(range)) => range.slice(blob),
+            _ => result.clone(),
+        };
+        let mut duties = vec![NodeDuty::Send(OutgoingMsg {
             msg: Message::QueryResponse {
                 id: MessageId::in_response_to(&msg_id),
-                response: QueryResponse::GetBlob(result),
+                response: QueryResponse::GetBlob(response),
                 correlation_id: msg_id,
                 target_section_pk: None,
             },
             section_source: false, // sent as single node
             dst: DstLocation::EndUser(origin),
             aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
-        }))
+        })];
+        if result.is_err() {
+            warn!("{}: Failed read of chunk {:?}, reporting it", self, address);
+            // a corrupt copy is dropped, for a good one to be copied over it
+            if self.chunks.has(address) {
+                self.remove(address).await?;
+            }
+            duties.push(NodeDuty::SendNodeMsg {
+                msg: NodeMsg::new(NodeMsgBody::ChunkReadFailed(*address)),
+                dst: DstLocation::Section(*address.name()),
+            });
+        }
+        Ok(duties)
     }
 
     pub async fn replicate_chunk(
@@ -141,9 +536,10 @@ impl ChunkStorage {
         })
     }
 
-    ///
+    /// Sends the chunk to the new holder, removing it from our
+    /// store after if it is being offloaded by the storage policy.
     pub async fn get_for_replication(
-        &self,
+        &mut self,
         address: BlobAddress,
         msg_id: MessageId,
         new_holder: XorName,
@@ -154,6 +550,17 @@ impl ChunkStorage {
         };
 
         if let Ok(data) = result {
+            let offloaded = self
+                .guard
+                .as_mut()
+                .is_some_and(|guard| guard.offloaded(&address));
+            if offloaded {
+                info!(
+                    "{}: Removing chunk offloaded to {}: {:?}",
+                    self, new_holder, address
+                );
+                self.remove(&address).await?;
+            }
             Ok(NodeDuty::Send(OutgoingMsg {
                 msg: Message::NodeQueryResponse {
                     response: NodeQueryResponse::Data(NodeDataQueryResponse::GetChunk(Ok(data))),
@@ -171,6 +578,69 @@ impl ChunkStorage {
         }
     }
 
+    /// Answers the storage challenge of the Elder with the hash of the range of the chunk.
+    /// A chunk missing or unreadable is not answered, for the Elder to have us repaired.
+    /// The reads of the chunks queried, or else of the most read chunks,
+    /// of those we still hold.
+    pub(crate) fn chunk_access(
+        &self,
+        addresses: &[BlobAddress],
+    ) -> Vec<(BlobAddress, ChunkAccess)> {
+        let chunks: Vec<_> = if addresses.is_empty() {
+            self.access.most_read()
+        } else {
+            addresses
+                .iter()
+                .map(|address| (*address, self.access.get(address).unwrap_or_default()))
+                .collect()
+        };
+        chunks
+            .into_iter()
+            .filter(|(address, _)| self.chunks.has(address))
+            .take(MAX_CHUNK_ACCESS_REPORT)
+            .collect()
+    }
+
+    pub(crate) fn answer_challenge(
+        &self,
+        challenge: &StorageChallenge,
+        id: MessageId,
+        elder: XorName,
+    ) -> Result<NodeDuty> {
+        match self.chunks.get(&challenge.address) {
+            Ok(blob) => Ok(StorageProof {
+                challenge: id,
+                hash: challenge.answer(&blob),
+            }
+            .msg(elder)),
+            Err(error) => {
+                warn!(
+                    "{}: Unable to answer storage challenge of {:?}: {:?}",
+                    self, challenge.address, error
+                );
+                Ok(NodeDuty::NoOp)
+            }
+        }
+    }
+
+    /// Answers the read relayed by the Elder with the chunk. A chunk missing or
+    /// unreadable is not answered, for the Elder to query the next holders.
+    pub(crate) fn read_for_elder(&mut self, read: RelayedRead, elder: XorName) -> Result<NodeDuty> {
+        match self.read(&read.address) {
+            Ok(chunk) => {
+                self.access.read(&read.address, self.clock.now_secs());
+                Ok(RelayedChunk { read, chunk }.msg(elder))
+            }
+            Err(error) => {
+                warn!(
+                    "{}: Unable to read {:?} for {}: {:?}",
+                    self, read.address, elder, error
+                );
+                Ok(NodeDuty::NoOp)
+            }
+        }
+    }
+
     ///
     pub async fn store_for_replication(&mut self, blob: Blob) -> Result<NodeDuty> {
         if self.chunks.has(blob.address()) {
@@ -181,8 +651,19 @@ impl ChunkStorage {
             );
             return Ok(NodeDuty::NoOp);
         }
+        self.check_size(&blob)?;
+        if !self.admits(&blob, false).await? {
+            warn!(
+                "{}: Chunk copy beyond the quota of the storage policy, not storing: {:?}",
+                self,
+                blob.address()
+            );
+            return Err(Error::NotEnoughSpace);
+        }
 
-        self.chunks.put(&blob).await?;
+        self.chunks
+            .put(&blob, UsageCategory::ReplicatedChunks)
+            .await?;
 
         Ok(NodeDuty::NoOp)
     }
@@ -191,6 +672,132 @@ impl ChunkStorage {
         self.chunks.used_space_ratio().await
     }
 
+    /// The capacity of the store, as of now.
+    pub(crate) async fn capacity_report(&self) -> CapacityReport {
+        CapacityReport::new(
+            self.chunks.used_space().await,
+            self.chunks.max_capacity().await,
+            self.chunks.keys().len() as u64,
+        )
+    }
+
+    /// Reads back the chunks due for scrubbing, up to the IO budget of a tick,
+    /// verifying them against their address, which is hashed from the content read.
+    /// Chunks found corrupt are quarantined, so that reads of them fail, and are
+    /// returned, to be repaired from the other holders.
+    pub(crate) async fn scrub(&mut self, scrubber: &mut Scrubber) -> Result<Vec<BlobAddress>> {
+        let mut corrupt = vec![];
+        let mut budget = scrubber.schedule().tick_budget();
+        for address in scrubber.due(self.chunks.keys()) {
+            if budget == 0 {
+                break;
+            }
+            match self.chunks.get(&address) {
+                Ok(blob) => budget = budget.saturating_sub(blob.value().len() as u64),
+                Err(error) => {
+                    warn!(
+                        "{}: Quarantining chunk {:?} found corrupt by scrubbing: {:?}",
+                        self, address, error
+                    );
+                    self.quarantine(&address).await?;
+                    corrupt.push(address);
+                }
+            }
+            scrubber.verified(address);
+        }
+        Ok(corrupt)
+    }
+
+    /// Reads back the chunk, verifying it against its address, e.g. once a new holder
+    /// found the copy we sent it corrupt. A chunk found corrupt is quarantined,
+    /// and true returned, for it to be repaired from the other holders.
+    pub(crate) async fn verify(&mut self, address: &BlobAddress) -> Result<bool> {
+        if !self.chunks.has(address) {
+            return Ok(false);
+        }
+        match self.chunks.get(address) {
+            Ok(_) => Ok(false),
+            Err(error) => {
+                warn!(
+                    "{}: Quarantining chunk {:?} found corrupt: {:?}",
+                    self, address, error
+                );
+                self.quarantine(address).await?;
+                Ok(true)
+            }
+        }
+    }
+
+    async fn quarantine(&mut self, address: &BlobAddress) -> Result<()> {
+        if let Some(cache) = &mut self.read_cache {
+            cache.remove(address);
+        }
+        self.chunks.quarantine(address).await
+    }
+
+    pub async fn used_space_by_category(&self) -> BTreeMap<UsageCategory, u64> {
+        self.chunks.used_space_by_category().await
+    }
+
+    pub(crate) fn keys(&self) -> Vec<BlobAddress> {
+        self.chunks.keys()
+    }
+
+    /// The offer of the chunk held, if any, to the section owning it.
+    pub(crate) fn orphan_offer(&self, address: BlobAddress) -> Option<OrphanOffer> {
+        if !self.chunks.has(&address) {
+            return None;
+        }
+        let owner = if address.is_private() {
+            match self.chunks.get(&address) {
+                Ok(Blob::Private(data)) => Some(*data.owner()),
+                _ => {
+                    warn!("{}: Unable to read orphan chunk {:?}", self, address);
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+        Some(OrphanOffer { address, owner })
+    }
+
+    /// Deletes the orphan chunk released by the section owning it.
+    pub(crate) async fn reclaim(&mut self, address: BlobAddress) -> Result<()> {
+        if self.chunks.has(&address) {
+            info!(
+                "{}: Deleting chunk released by its section: {:?}",
+                self, address
+            );
+            self.remove(&address).await?;
+        }
+        Ok(())
+    }
+
+    /// Compacts the store, once chunks are deleted in bulk.
+    pub(crate) fn compact(&mut self) -> Result<()> {
+        self.chunks.compact()
+    }
+
+    /// Deletes the chunk as told by the section, whoever owns it, and
+    /// acknowledges it to the section, with our signature over the address.
+    /// A chunk we don't hold is acknowledged all the same, as it is gone.
+    pub(crate) async fn delete_by_section(
+        &mut self,
+        address: BlobAddress,
+        proof: Signature,
+        section: SrcLocation,
+    ) -> Result<NodeDuty> {
+        if self.chunks.has(&address) {
+            info!("{}: Deleting chunk by section policy: {:?}", self, address);
+            self.remove(&address).await?;
+        }
+        Ok(NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::ChunkDeleted { address, proof }),
+            dst: section.to_dst(),
+        })
+    }
+
     pub(crate) async fn delete(
         &mut self,
         address: BlobAddress,
@@ -205,8 +812,7 @@ impl ChunkStorage {
         let result = match self.chunks.get(&address) {
             Ok(Blob::Private(data)) => {
                 if data.owner() == origin.id() {
-                    self.chunks
-                        .delete(&address)
+                    self.remove(&address)
                         .await
                         .map_err(|_error| ErrorMessage::FailedToDelete)
                 } else {
@@ -245,3 +851,257 @@ impl Display for ChunkStorage {
         write!(formatter, "ChunkStorage")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_trait::async_trait;
+    use bls::SecretKey;
+    use sn_data_types::{PrivateBlob, PublicBlob};
+    use std::collections::BTreeMap;
+    use tempdir::TempDir;
+
+    // A backend holding whatever chunk it is given under an address, if readable,
+    // to have a chunk at an address other than the one written there.
+    #[derive(Default)]
+    struct Held(BTreeMap<BlobAddress, Option<Blob>>);
+
+    #[async_trait]
+    impl ChunkStore for Held {
+        fn get(&self, address: &BlobAddress) -> Result<Blob> {
+            match self.0.get(address) {
+                Some(Some(blob)) => Ok(blob.clone()),
+                Some(None) => Err(Error::Logic("Unreadable chunk".to_string())),
+                None => Err(Error::NoSuchChunk),
+            }
+        }
+
+        fn has(&self, address: &BlobAddress) -> bool {
+            self.0.contains_key(address)
+        }
+
+        async fn put(&mut self, blob: &Blob, _: UsageCategory) -> Result<()> {
+            let _ = self.0.insert(*blob.address(), Some(blob.clone()));
+            Ok(())
+        }
+
+        async fn delete(&mut self, address: &BlobAddress) -> Result<()> {
+            let _ = self.0.remove(address);
+            Ok(())
+        }
+
+        fn keys(&self) -> Vec<BlobAddress> {
+            self.0.keys().copied().collect()
+        }
+
+        async fn used_space_ratio(&self) -> f64 {
+            0.0
+        }
+
+        async fn used_space_by_category(&self) -> BTreeMap<UsageCategory, u64> {
+            BTreeMap::new()
+        }
+    }
+
+    fn storage_holding(
+        root: &TempDir,
+        address: BlobAddress,
+        held: Option<Blob>,
+    ) -> Result<ChunkStorage> {
+        let mut store = Held::default();
+        let _ = store.0.insert(address, held);
+        ChunkStorage::with_store(
+            XorName::random(),
+            root.path(),
+            Box::new(store),
+            Arc::default(),
+        )
+    }
+
+    fn conflicts(storage: &ChunkStorage, address: &BlobAddress) -> Result<Option<u64>> {
+        Ok(storage.conflicts.get::<u64>(&address.to_db_key()?))
+    }
+
+    #[tokio::test]
+    async fn writes_of_other_content_to_existing_chunks_are_conflicts() -> Result<()> {
+        let root = TempDir::new("chunk_storage")
+            .map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let client = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let data = Blob::Public(PublicBlob::new(b"data".to_vec()));
+        let address = *data.address();
+
+        let mut storage = storage_holding(&root, address, Some(data.clone()))?;
+        assert!(matches!(
+            storage.try_store(&data, client).await,
+            Err(Error::DataExists)
+        ));
+        assert_eq!(conflicts(&storage, &address)?, None);
+
+        let other = Blob::Public(PublicBlob::new(b"other".to_vec()));
+        let mut storage = storage_holding(&root, address, Some(other))?;
+        assert!(matches!(
+            storage.try_store(&data, client).await,
+            Err(Error::DataConflict(conflict)) if conflict == address
+        ));
+        assert_eq!(conflicts(&storage, &address)?, Some(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unreadable_existing_copies_are_storage_errors() -> Result<()> {
+        let root = TempDir::new("chunk_storage")
+            .map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let client = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let data = Blob::Public(PublicBlob::new(b"data".to_vec()));
+        let address = *data.address();
+
+        let mut storage = storage_holding(&root, address, None)?;
+        assert!(matches!(
+            storage.try_store(&data, client).await,
+            Err(Error::UnreadableChunk(unreadable)) if unreadable == address
+        ));
+        // not taken for a conflict
+        assert_eq!(conflicts(&storage, &address)?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chunks_beyond_the_max_chunk_size_are_refused() -> Result<()> {
+        let root = TempDir::new("chunk_storage")
+            .map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let used_space = UsedSpace::new(u64::MAX);
+        let mut storage = ChunkStorage::new(
+            XorName::random(),
+            root.path(),
+            used_space,
+            false,
+            None,
+            None,
+            Arc::default(),
+        )
+        .await?;
+        storage.set_max_chunk_size(4);
+
+        let within = Blob::Public(PublicBlob::new(b"max".to_vec()));
+        let beyond = Blob::Public(PublicBlob::new(b"beyond".to_vec()));
+        let _ = storage.store_for_replication(within.clone()).await?;
+        assert!(matches!(
+            storage.store_for_replication(beyond.clone()).await,
+            Err(Error::ChunkTooLarge { size: 6, max: 4 })
+        ));
+        assert!(storage.chunks.has(within.address()));
+        assert!(!storage.chunks.has(beyond.address()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chunks_imported_are_verified_against_their_file_name() -> Result<()> {
+        let temp_dir =
+            |name| TempDir::new(name).map_err(|e| Error::TempDirCreationFailed(e.to_string()));
+        let (root, backup) = (temp_dir("chunk_storage")?, temp_dir("backup")?);
+        let chunk = |value: &[u8]| Blob::Public(PublicBlob::new(value.to_vec()));
+        let write = |name: String, blob: &Blob| -> Result<()> {
+            fs::write(backup.path().join(name), utils::serialise(blob)?)?;
+            Ok(())
+        };
+        let (good, held, tampered) = (chunk(b"good"), chunk(b"held"), chunk(b"tampered"));
+        write(good.address().to_db_key()?, &good)?;
+        write(held.address().to_db_key()?, &held)?;
+        write(chunk(b"original").address().to_db_key()?, &tampered)?;
+        write("chunk_index".to_string(), &good)?;
+
+        let mut storage = ChunkStorage::new(
+            XorName::random(),
+            root.path(),
+            UsedSpace::new(u64::MAX),
+            false,
+            None,
+            None,
+            Arc::default(),
+        )
+        .await?;
+        let _ = storage.store_for_replication(held.clone()).await?;
+        storage.start_import(backup.path().to_path_buf())?;
+        let imported = storage.import_next().await?.expect("chunks imported");
+        let addresses: BTreeSet<_> = imported.chunks.keys().copied().collect();
+        let expected: BTreeSet<_> = vec![*good.address(), *held.address()].into_iter().collect();
+        assert_eq!(addresses, expected);
+        assert!(storage.chunks.has(good.address()));
+        assert!(!storage.chunks.has(tampered.address()));
+
+        let report = storage.import_report().expect("a report");
+        assert!(report.completed);
+        assert_eq!(
+            (report.files, report.imported, report.already_held),
+            (4, 1, 1)
+        );
+        assert_eq!(
+            (report.corrupt, report.unrecognised, report.refused),
+            (1, 1, 0)
+        );
+        assert_eq!(storage.import_next().await?, None);
+
+        // the same, from a gzipped archive of it, is held by then
+        let archive_path = backup.path().with_extension("tar.gz");
+        let encoder = flate2::write::GzEncoder::new(
+            fs::File::create(&archive_path)?,
+            flate2::Compression::default(),
+        );
+        let mut archive = tar::Builder::new(encoder);
+        archive.append_dir_all("chunks", backup.path())?;
+        let _ = archive.into_inner()?.finish()?;
+        storage.start_import(archive_path.clone())?;
+        let imported = storage.import_next().await?.expect("chunks imported");
+        assert_eq!(imported.chunks.len(), 2);
+        let report = storage.import_report().expect("a report");
+        assert_eq!((report.imported, report.already_held), (0, 2));
+        assert!(!root.path().join("chunk_import").exists());
+        fs::remove_file(archive_path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chunk_deleted_by_its_owner_frees_its_space() -> Result<()> {
+        let root = TempDir::new("chunk_storage")
+            .map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let mut storage = ChunkStorage::new(
+            XorName::random(),
+            root.path(),
+            UsedSpace::new(u64::MAX),
+            false,
+            None,
+            None,
+            Arc::default(),
+        )
+        .await?;
+        let client = || PublicKey::from(SecretKey::random().public_key());
+        let owner = client();
+        let chunk = Blob::Private(PrivateBlob::new(b"private".to_vec(), owner));
+        let unused = storage.chunks.used_space().await;
+        let _ = storage.store_for_replication(chunk.clone()).await?;
+        assert!(storage.chunks.used_space().await > unused);
+
+        // a client not owning it is refused
+        let other = EndUser::AllClients(client());
+        let duty = storage
+            .delete(*chunk.address(), MessageId::new(), other)
+            .await?;
+        assert!(matches!(
+            duty,
+            NodeDuty::Send(OutgoingMsg {
+                msg: Message::CmdError { .. },
+                ..
+            })
+        ));
+        assert!(storage.chunks.has(chunk.address()));
+
+        let owner = EndUser::AllClients(owner);
+        let duty = storage
+            .delete(*chunk.address(), MessageId::new(), owner)
+            .await?;
+        assert!(matches!(duty, NodeDuty::NoOp));
+        assert!(!storage.chunks.has(chunk.address()));
+        assert_eq!(storage.chunks.used_space().await, unused);
+        Ok(())
+    }
+}
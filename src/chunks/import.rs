@@ -0,0 +1,207 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    chunk_store::deserialize_chunk,
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    to_db_key::from_db_key,
+    Result, ToDbKey,
+};
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Blob, BlobAddress, PublicKey};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+use xor_name::XorName;
+
+/// Chunks imported per import tick.
+pub(super) const IMPORT_BATCH: usize = 64;
+// The dir under the node root dir archives are unpacked to, while imported.
+const IMPORT_STAGING_DIR: &str = "chunk_import";
+
+/// The progress of an import of chunks by the operator.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkImportReport {
+    /// The dir or archive imported from.
+    pub source: PathBuf,
+    /// Whether all the files have been gone through.
+    pub completed: bool,
+    /// Files gone through so far.
+    pub files: u64,
+    /// Chunks verified and stored.
+    pub imported: u64,
+    /// Bytes of the chunks stored.
+    pub imported_bytes: u64,
+    /// Chunks already held, thus not stored again, though registered with the Elders.
+    pub already_held: u64,
+    /// Files not named by the address of a chunk, or not holding a chunk.
+    pub unrecognised: u64,
+    /// Chunks not hashing to the address their file is named by.
+    pub corrupt: u64,
+    /// Chunks refused, as too large, or beyond the quota of the storage policy.
+    pub refused: u64,
+}
+
+/// The chunks imported by a holder, for the Elders to register it as holding them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedChunks {
+    /// The chunks, with the owner of those private.
+    pub chunks: BTreeMap<BlobAddress, Option<PublicKey>>,
+}
+
+impl ImportedChunks {
+    /// The msg telling our Elders of the chunks, under an id derived from them,
+    /// as imports ticking again resend none of the same.
+    pub(super) fn msg(&self, elders: BTreeSet<XorName>) -> Result<NodeDuty> {
+        Ok(NodeDuty::SendNodeMsgToNodes {
+            targets: elders,
+            msg: NodeMsg::derived(NodeMsgBody::ImportedChunks(self.clone()))?,
+        })
+    }
+}
+
+/// What a file to import was found to hold.
+#[allow(clippy::large_enum_variant)]
+pub(super) enum ChunkFile {
+    Chunk(Blob),
+    Corrupt,
+    Unrecognised,
+}
+
+/// Reads the chunk of the file, verified against the address the file is named by,
+/// as files are named by the chunk store. The file may be compressed by the store.
+pub(super) fn read_chunk_file(path: &Path) -> ChunkFile {
+    let address = match path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| Some((name, from_db_key::<BlobAddress>(name).ok()?)))
+    {
+        // the name of a file of another kind of data can decode to an address too
+        Some((name, address)) if address.to_db_key().ok().as_deref() == Some(name) => address,
+        _ => return ChunkFile::Unrecognised,
+    };
+    let blob = match fs::read(path)
+        .map_err(Into::into)
+        .and_then(|contents| deserialize_chunk::<Blob>(&contents))
+    {
+        Ok(blob) => blob,
+        Err(error) => {
+            warn!("Unable to read chunk file {:?}: {:?}", path, error);
+            return ChunkFile::Unrecognised;
+        }
+    };
+    // the address of a chunk read is hashed from its content
+    if *blob.address() == address {
+        ChunkFile::Chunk(blob)
+    } else {
+        ChunkFile::Corrupt
+    }
+}
+
+/// An import of chunks from a dir, or from an archive unpacked for it.
+pub(super) struct ChunkImport {
+    files: Vec<PathBuf>,
+    // where the archive imported from is unpacked, if it is one
+    staging: Option<PathBuf>,
+    report: ChunkImportReport,
+}
+
+impl ChunkImport {
+    /// Lists the files to import from the source. A tar archive, gzipped or not,
+    /// is unpacked first, to a dir under the node root dir.
+    pub(super) fn open(source: PathBuf, node_root: &Path) -> Result<Self> {
+        let (dir, staging) = if source.is_dir() {
+            (source.clone(), None)
+        } else {
+            let staging = node_root.join(IMPORT_STAGING_DIR);
+            let _ = fs::remove_dir_all(&staging);
+            fs::create_dir_all(&staging)?;
+            let file = File::open(&source)?;
+            let reader: Box<dyn Read> = if is_gzipped(&source) {
+                Box::new(GzDecoder::new(file))
+            } else {
+                Box::new(file)
+            };
+            tar::Archive::new(reader).unpack(&staging)?;
+            (staging.clone(), Some(staging))
+        };
+        let mut files = vec![];
+        list_files(&dir, &mut files)?;
+        info!(
+            "Importing chunks from {} files of {:?}",
+            files.len(),
+            source
+        );
+        Ok(Self {
+            files,
+            staging,
+            report: ChunkImportReport {
+                source,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// The next files to import, up to the batch size.
+    pub(super) fn next_batch(&mut self) -> Vec<PathBuf> {
+        let batch = self
+            .files
+            .split_off(self.files.len().saturating_sub(IMPORT_BATCH));
+        self.report.files += batch.len() as u64;
+        batch
+    }
+
+    /// Completes the import once all files have been gone through,
+    /// removing the archive unpacked for it, if any.
+    pub(super) fn complete_if_done(&mut self) {
+        if !self.files.is_empty() || self.report.completed {
+            return;
+        }
+        self.report.completed = true;
+        if let Some(staging) = self.staging.take() {
+            if let Err(error) = fs::remove_dir_all(&staging) {
+                warn!(
+                    "Unable to remove unpacked archive {:?}: {:?}",
+                    staging, error
+                );
+            }
+        }
+        info!("Import of chunks completed: {:?}", self.report);
+    }
+
+    pub(super) fn report(&self) -> &ChunkImportReport {
+        &self.report
+    }
+
+    pub(super) fn report_mut(&mut self) -> &mut ChunkImportReport {
+        &mut self.report
+    }
+}
+
+fn is_gzipped(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".gz") || name.ends_with(".tgz")
+}
+
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -6,18 +6,45 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod access_stats;
+mod capability;
 mod chunk_storage;
+mod import;
+mod orphans;
+mod read_cache;
 mod reading;
+mod replication;
+mod replication_observer;
+mod scrubbing;
+mod storage_policy;
+mod store;
 mod writing;
 
 use crate::{
-    chunk_store::UsedSpace,
-    node_ops::{NodeDuties, NodeDuty},
+    chunk_store::{ChunkCompression, UsedSpace},
+    metadata::{ChunkAccessQuery, ChunkAccessReport, RelayedRead, StorageChallenge},
+    network::NetworkClock,
+    node_ops::{NodeDuties, NodeDuty, PeriodicTask},
     NodeInfo, Result,
 };
+pub use access_stats::ChunkAccess;
+pub use capability::{Capability, REFERENCE_CAPABILITY};
 use chunk_storage::ChunkStorage;
-use log::info;
-use sn_data_types::{Blob, BlobAddress};
+pub use import::{ChunkImportReport, ImportedChunks};
+use log::{info, warn};
+use orphans::OrphanCollector;
+use orphans::ORPHAN_TICK;
+pub use orphans::{OrphanOffer, OrphanRelease};
+pub use read_cache::ReadCacheStats;
+pub use reading::{ChunkRange, RangedChunkRead};
+pub use replication::ReplicationFailed;
+use replication::REPLICATION_TICK;
+use replication::{ReplicationScheduler, DEFAULT_REPLICATION_CONCURRENCY};
+pub use replication_observer::{ReplicationEvent, ReplicationObserver};
+use scrubbing::Scrubber;
+use scrubbing::SCRUB_TICK;
+pub use scrubbing::{ScrubSchedule, ScrubWindow};
+use sn_data_types::{Blob, BlobAddress, Signature};
 use sn_messaging::{
     client::{BlobRead, BlobWrite},
     EndUser, MessageId, SrcLocation,
@@ -25,31 +52,141 @@ use sn_messaging::{
 use std::{
     collections::BTreeSet,
     fmt::{self, Display, Formatter},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use xor_name::XorName;
+use storage_policy::Pressure;
+pub use storage_policy::{Eviction, StoragePolicy};
+pub use store::ChunkStore;
+use xor_name::{Prefix, XorName};
 
+/// Beyond this used space to max space ratio, the section is told we are nearly
+/// full, unless the node has a storage policy, whose high watermark is used instead.
 pub const MAX_STORAGE_USAGE_RATIO: f64 = 0.8;
+/// How often a batch of chunks is migrated, while relocating them.
+const MIGRATION_TICK: Duration = Duration::from_secs(1);
+/// How often a batch of chunks is imported, while importing them.
+const IMPORT_TICK: Duration = Duration::from_secs(1);
+// How often the used space is checked against the storage policy.
+const STORAGE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The background work on the stored chunks, scrubbing them only if scheduled.
+pub(crate) fn periodic_tasks(scrubbing: bool) -> Vec<PeriodicTask> {
+    let mut tasks = vec![
+        PeriodicTask {
+            name: "storage check",
+            interval: STORAGE_CHECK_INTERVAL,
+            duty: || NodeDuty::CheckStorage,
+        },
+        PeriodicTask {
+            name: "chunk migration",
+            interval: MIGRATION_TICK,
+            duty: || NodeDuty::MigrateChunks,
+        },
+        PeriodicTask {
+            name: "chunk replication",
+            interval: REPLICATION_TICK,
+            duty: || NodeDuty::ReplicateQueuedChunks,
+        },
+        PeriodicTask {
+            name: "orphan chunk offers",
+            interval: ORPHAN_TICK,
+            duty: || NodeDuty::OfferOrphanChunks,
+        },
+        PeriodicTask {
+            name: "chunk import",
+            interval: IMPORT_TICK,
+            duty: || NodeDuty::ImportChunks,
+        },
+    ];
+    if scrubbing {
+        tasks.push(PeriodicTask {
+            name: "scrubbing",
+            interval: SCRUB_TICK,
+            duty: || NodeDuty::ScrubChunks,
+        });
+    }
+    tasks
+}
 
 /// Operations on data chunks.
 pub(crate) struct Chunks {
     chunk_storage: ChunkStorage,
+    // verifies the stored chunks, if scheduled
+    scrubber: Option<Scrubber>,
+    // the chunks being copied to us, as their new holder
+    replication: ReplicationScheduler,
+    // told of each step of the copying, if registered
+    replication_observer: Option<Arc<dyn ReplicationObserver>>,
+    // the chunks held out of our prefix, being offered to the sections owning them
+    orphans: OrphanCollector,
+    // reported to our Elders with our capacity, if benchmarked
+    capability: Option<Capability>,
 }
 
 impl Chunks {
-    pub async fn new(node_name: XorName, path: &Path, used_space: UsedSpace) -> Result<Self> {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        node_name: XorName,
+        path: &Path,
+        used_space: UsedSpace,
+        scrubbing: Option<ScrubSchedule>,
+        secure_delete: bool,
+        write_buffer: Option<usize>,
+        compression: Option<ChunkCompression>,
+        clock: Arc<NetworkClock>,
+    ) -> Result<Self> {
+        Ok(Self {
+            chunk_storage: ChunkStorage::new(
+                node_name,
+                path,
+                used_space,
+                secure_delete,
+                write_buffer,
+                compression,
+                clock,
+            )
+            .await?,
+            scrubber: scrubbing.map(Scrubber::new),
+            replication: ReplicationScheduler::new(DEFAULT_REPLICATION_CONCURRENCY),
+            replication_observer: None,
+            orphans: OrphanCollector::default(),
+            capability: None,
+        })
+    }
+
+    /// Operations on the chunks held in the store plugged in, rather than on disk.
+    pub fn with_store(
+        node_name: XorName,
+        path: &Path,
+        store: Box<dyn ChunkStore>,
+        scrubbing: Option<ScrubSchedule>,
+        clock: Arc<NetworkClock>,
+    ) -> Result<Self> {
         Ok(Self {
-            chunk_storage: ChunkStorage::new(node_name, path, used_space).await?,
+            chunk_storage: ChunkStorage::with_store(node_name, path, store, clock)?,
+            scrubber: scrubbing.map(Scrubber::new),
+            replication: ReplicationScheduler::new(DEFAULT_REPLICATION_CONCURRENCY),
+            replication_observer: None,
+            orphans: OrphanCollector::default(),
+            capability: None,
         })
     }
 
+    /// The store plugged in, if any, to hold on to while not storing chunks.
+    pub fn into_store(self) -> Option<Box<dyn ChunkStore>> {
+        self.chunk_storage.into_store()
+    }
+
     pub async fn read(
         &mut self,
         read: &BlobRead,
+        range: Option<&ChunkRange>,
         msg_id: MessageId,
         origin: EndUser,
-    ) -> Result<NodeDuty> {
-        reading::get_result(read, msg_id, origin, &self.chunk_storage).await
+    ) -> Result<NodeDuties> {
+        reading::get_result(read, range, msg_id, origin, &mut self.chunk_storage).await
     }
 
     pub async fn write(
@@ -61,31 +198,196 @@ impl Chunks {
         writing::get_result(write, msg_id, origin, &mut self.chunk_storage).await
     }
 
-    pub async fn check_storage(&self) -> Result<NodeDuties> {
+    /// Deletes the chunk of the client, if owned by it, reclaiming its space.
+    pub async fn delete(
+        &mut self,
+        address: BlobAddress,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuty> {
+        self.chunk_storage.delete(address, msg_id, origin).await
+    }
+
+    /// Bounds the chunks stored by the storage policy from now on.
+    pub fn set_storage_policy(&mut self, policy: StoragePolicy) {
+        self.chunk_storage.set_storage_policy(policy)
+    }
+
+    /// Refuses chunks beyond the max chunk size of our section from now on.
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: u64) {
+        self.chunk_storage.set_max_chunk_size(max_chunk_size)
+    }
+
+    /// Caches the chunks most recently read by clients, up to the budget of bytes.
+    pub fn set_read_cache(&mut self, budget: u64) {
+        self.chunk_storage.set_read_cache(budget)
+    }
+
+    /// Asks for at most this many chunks at a time, when replicating them to us.
+    pub fn set_replication_concurrency(&mut self, concurrency: usize) {
+        self.replication.set_concurrency(concurrency)
+    }
+
+    /// Reports the capability of the node to our Elders with our capacity from now on.
+    pub fn set_capability(&mut self, capability: Capability) {
+        self.capability = Some(capability);
+    }
+
+    /// Tells the observer of each step of the replication of chunks to us from now on.
+    pub fn set_replication_observer(&mut self, observer: Arc<dyn ReplicationObserver>) {
+        self.replication_observer = Some(observer);
+    }
+
+    fn observe(&self, event: ReplicationEvent) {
+        if let Some(observer) = &self.replication_observer {
+            observer.observe(event);
+        }
+    }
+
+    /// Counters of the read cache, if chunks are cached.
+    pub fn read_cache_stats(&self) -> Option<ReadCacheStats> {
+        self.chunk_storage.read_cache_stats()
+    }
+
+    pub async fn check_storage(&mut self) -> Result<NodeDuties> {
         info!("Checking used storage");
-        if self.chunk_storage.used_space_ratio().await > MAX_STORAGE_USAGE_RATIO {
+        for (category, used) in self.chunk_storage.used_space_by_category().await {
+            info!("Used space by {}: {}", category, used);
+        }
+        let reaching_max_capacity = match self.chunk_storage.apply_storage_policy().await {
+            Some(pressure) => pressure == Pressure::High,
+            None => self.chunk_storage.used_space_ratio().await > MAX_STORAGE_USAGE_RATIO,
+        };
+        if reaching_max_capacity {
             Ok(NodeDuties::from(NodeDuty::ReachingMaxCapacity))
         } else {
             Ok(vec![])
         }
     }
 
-    ///
+    /// Verifies the stored chunks due for scrubbing by the schedule, if any,
+    /// returning those found corrupt, and quarantined, to be repaired.
+    pub async fn scrub(&mut self) -> Result<Vec<BlobAddress>> {
+        match &mut self.scrubber {
+            Some(scrubber) => self.chunk_storage.scrub(scrubber).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The addresses of all chunks held.
+    pub fn keys(&self) -> Vec<BlobAddress> {
+        self.chunk_storage.keys()
+    }
+
+    /// Relocates the chunks to the root dir, migrating them over in the background.
+    pub async fn relocate(&mut self, root: PathBuf) -> Result<()> {
+        self.chunk_storage.relocate(root).await
+    }
+
+    /// Starts importing the chunks of the dir or archive, e.g. restored from a backup.
+    pub fn start_import(&mut self, source: PathBuf) -> Result<()> {
+        self.chunk_storage.start_import(source)
+    }
+
+    /// Imports the next batch of chunks, while importing them, telling
+    /// our Elders of those held, for them to register us as their holder.
+    pub async fn import(&mut self, elders: BTreeSet<XorName>) -> Result<NodeDuties> {
+        match self.chunk_storage.import_next().await? {
+            Some(imported) => Ok(vec![imported.msg(elders)?]),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The progress of the last import of chunks, if any.
+    pub fn import_report(&self) -> Option<ChunkImportReport> {
+        self.chunk_storage.import_report()
+    }
+
+    /// Collects the chunks held out of our prefix, e.g. after a split,
+    /// to be offered to the sections owning them in the background.
+    pub fn collect_orphans(&mut self, our_prefix: &Prefix) {
+        let collected = self.orphans.collect(our_prefix, self.chunk_storage.keys());
+        if collected > 0 {
+            info!(
+                "Collected {} chunks out of our prefix {:?}",
+                collected, our_prefix
+            );
+        }
+    }
+
+    /// Offers the next batch of orphan chunks to the sections owning them,
+    /// while collecting them, offering again those not released in time.
+    pub fn offer_orphans(&mut self) -> Result<NodeDuties> {
+        let now = Instant::now();
+        let mut duties = vec![];
+        for address in self.orphans.next_batch(now) {
+            if let Some(offer) = self.chunk_storage.orphan_offer(address) {
+                duties.push(self.orphans.offer(offer, now)?);
+            }
+        }
+        Ok(duties)
+    }
+
+    /// Deletes the orphan chunk released by the section owning it, compacting
+    /// the store once all chunks collected are released.
+    pub async fn release_orphan(&mut self, release: OrphanRelease) -> Result<NodeDuties> {
+        if !self.orphans.released(&release) {
+            return Ok(vec![]);
+        }
+        self.chunk_storage.reclaim(release.address).await?;
+        if self.orphans.is_done() {
+            info!("All orphan chunks released, compacting the chunk store");
+            self.chunk_storage.compact()?;
+        }
+        Ok(vec![])
+    }
+
+    /// Migrates the next batch of chunks, while relocating them.
+    pub async fn migrate(&mut self) -> Result<NodeDuties> {
+        self.chunk_storage.migrate().await?;
+        Ok(vec![])
+    }
+
+    /// Queues the chunk for replication to us, asking for it
+    /// from one of its current holders as soon as a slot is free.
     pub async fn replicate_chunk(
-        &self,
+        &mut self,
         address: BlobAddress,
         current_holders: BTreeSet<XorName>,
         msg_id: MessageId,
-    ) -> Result<NodeDuty> {
-        info!("Creating new Message for acquiring chunk from current_holders");
-        self.chunk_storage
-            .replicate_chunk(address, current_holders, msg_id)
-            .await
+    ) -> Result<NodeDuties> {
+        self.replication
+            .schedule(address, current_holders, msg_id, Instant::now());
+        self.replicate_next().await
+    }
+
+    /// Asks for the chunks queued for replication, as slots are free,
+    /// asking again, from the next holder, for those timed out.
+    pub async fn replicate_next(&mut self) -> Result<NodeDuties> {
+        let mut duties = vec![];
+        let asked = self.replication.next(Instant::now());
+        for address in self.replication.take_given_up() {
+            self.observe(ReplicationEvent::Failed {
+                address,
+                holder: None,
+                reason: "given up after the max attempts".to_string(),
+            });
+        }
+        for (address, holder, msg_id) in asked {
+            info!("Creating new Message for acquiring chunk from a current holder");
+            self.observe(ReplicationEvent::Requested { address, holder });
+            duties.push(
+                self.chunk_storage
+                    .replicate_chunk(address, BTreeSet::from([holder]), msg_id)
+                    .await?,
+            );
+        }
+        Ok(duties)
     }
 
     ///
     pub async fn get_chunk_for_replication(
-        &self,
+        &mut self,
         address: BlobAddress,
         msg_id: MessageId,
         new_holder: XorName,
@@ -96,9 +398,119 @@ impl Chunks {
             .await
     }
 
-    ///
-    pub async fn store_replicated_chunk(&mut self, blob: Blob) -> Result<NodeDuty> {
-        self.chunk_storage.store_for_replication(blob).await
+    /// Whether the msg asking for a chunk for replication is still in flight.
+    pub fn is_replicating(&self, correlation_id: MessageId) -> bool {
+        self.replication.asked_for(correlation_id).is_some()
+    }
+
+    /// Verifies our copy of the chunk found corrupt by the new holder we sent it to,
+    /// returning whether it is corrupt, and now quarantined, to be repaired.
+    pub async fn verify_replicated_chunk(&mut self, failed: &ReplicationFailed) -> Result<bool> {
+        self.chunk_storage.verify(&failed.address).await
+    }
+
+    /// Answers the storage challenge of the Elder, proving we hold the chunk.
+    pub fn answer_storage_challenge(
+        &self,
+        challenge: &StorageChallenge,
+        id: MessageId,
+        elder: XorName,
+    ) -> Result<NodeDuty> {
+        self.chunk_storage.answer_challenge(challenge, id, elder)
+    }
+
+    /// Answers the read of the chunk relayed by the Elder with the chunk.
+    pub fn read_for_elder(&mut self, read: RelayedRead, elder: XorName) -> Result<NodeDuty> {
+        self.chunk_storage.read_for_elder(read, elder)
+    }
+
+    /// Reports the reads of our chunks to the Elder querying them.
+    pub fn report_chunk_access(
+        &self,
+        query: &ChunkAccessQuery,
+        elder: XorName,
+    ) -> Result<NodeDuty> {
+        Ok(ChunkAccessReport {
+            query: query.id()?,
+            chunks: self.chunk_storage.chunk_access(&query.addresses),
+        }
+        .msg(elder))
+    }
+
+    /// Reports the capacity of our storage, and our capability,
+    /// to our Elders, for them to choose holders by.
+    pub async fn report_capacity(&self, elders: BTreeSet<XorName>) -> Result<NodeDuty> {
+        let mut report = self.chunk_storage.capacity_report().await;
+        report.capability = self.capability.map(|capability| capability.score);
+        Ok(report.msg(elders))
+    }
+
+    /// Deletes the chunk as told by the section, acknowledging it with the proof.
+    pub async fn delete_by_section(
+        &mut self,
+        address: BlobAddress,
+        proof: Signature,
+        section: SrcLocation,
+    ) -> Result<NodeDuty> {
+        self.chunk_storage
+            .delete_by_section(address, proof, section)
+            .await
+    }
+
+    /// Stores the chunk replicated to us, freeing its slot for the next one queued.
+    pub async fn store_replicated_chunk(
+        &mut self,
+        blob: Blob,
+        correlation_id: MessageId,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        // the address is hashed from the content received, so a chunk corrupted
+        // on the way, or at the holder, does not match the address asked for
+        let asked_for = self.replication.asked_for(correlation_id);
+        self.observe(ReplicationEvent::Fetched {
+            address: asked_for.unwrap_or_else(|| *blob.address()),
+            holder,
+        });
+        match asked_for {
+            Some(address) if address != *blob.address() => {
+                warn!(
+                    "Chunk {:?} sent by {} for replication is corrupt, asking the next holder",
+                    address, holder
+                );
+                self.observe(ReplicationEvent::Failed {
+                    address,
+                    holder: Some(holder),
+                    reason: "corrupt, not matching the address".to_string(),
+                });
+                self.replication.failed(&address, Instant::now());
+                let failed = ReplicationFailed {
+                    address,
+                    id: correlation_id,
+                };
+                let mut duties = vec![failed.msg(holder)];
+                duties.extend(self.replicate_next().await?);
+                return Ok(duties);
+            }
+            _ => (),
+        }
+        let address = *blob.address();
+        self.observe(ReplicationEvent::Verified { address });
+        self.replication.received(&address);
+        let stored = match self.chunk_storage.store_for_replication(blob).await {
+            Ok(stored) => stored,
+            Err(error) => {
+                self.observe(ReplicationEvent::Failed {
+                    address,
+                    holder: Some(holder),
+                    reason: error.to_string(),
+                });
+                return Err(error);
+            }
+        };
+        self.observe(ReplicationEvent::Stored { address });
+        let mut duties = self.replicate_next().await?;
+        duties.push(stored);
+        Ok(duties)
     }
 }
 
@@ -0,0 +1,208 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{BlobAddress, PublicKey};
+use sn_messaging::{DstLocation, MessageId};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::{Prefix, XorName};
+
+/// How often the next batch of orphan chunks is offered, while collecting them.
+pub(crate) const ORPHAN_TICK: Duration = Duration::from_secs(5);
+// Orphan chunks offered per tick.
+const ORPHAN_BATCH: usize = 32;
+// The time the owning section has to take on an orphan chunk, before it is offered again.
+const ORPHAN_OFFER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// An offer of a chunk held by us, though out of the prefix of our section, e.g. after
+/// a split, to the section owning it, for it to copy the chunk to its own Adults.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrphanOffer {
+    /// The chunk offered.
+    pub address: BlobAddress,
+    /// The owner of the chunk, if private.
+    pub owner: Option<PublicKey>,
+}
+
+impl OrphanOffer {
+    // The id of the msg of the offer, by which the release answering it is told apart.
+    fn id(&self) -> Result<MessageId> {
+        Ok(self.msg()?.id)
+    }
+
+    // The msg offering the chunk to the section owning it, under an id derived from the
+    // offer, so that the Elders answering it know the id without holding on to the msg.
+    fn msg(&self) -> Result<NodeMsg> {
+        NodeMsg::derived(NodeMsgBody::OrphanOffer(self.clone()))
+    }
+}
+
+/// The answer of an Elder of the owning section to an offer of an orphan chunk,
+/// once the chunk is held by enough of its Adults, for the holder to delete it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrphanRelease {
+    /// Id of the msg of the offer answered.
+    pub offer: MessageId,
+    /// The chunk released.
+    pub address: BlobAddress,
+}
+
+impl OrphanRelease {
+    /// The release answering the offer.
+    pub(crate) fn new(offer: &OrphanOffer) -> Result<Self> {
+        Ok(Self {
+            offer: offer.id()?,
+            address: offer.address,
+        })
+    }
+
+    /// The msg releasing the holder of the chunk.
+    pub(crate) fn msg(&self, holder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::OrphanRelease(self.clone())),
+            dst: DstLocation::Node(holder),
+        }
+    }
+}
+
+/// Collects the chunks held by us out of the prefix of our section, offering them
+/// to the sections owning them, in batches, and offering them again if not released
+/// in time. A chunk is only deleted once released by the owning section.
+#[derive(Default)]
+pub(super) struct OrphanCollector {
+    queued: BTreeSet<BlobAddress>,
+    // the offers pending, by chunk, with the id of the offer
+    offered: BTreeMap<BlobAddress, (MessageId, Instant)>,
+}
+
+impl OrphanCollector {
+    /// Queues the chunks out of our prefix to be offered, unless queued or offered already.
+    pub(super) fn collect(&mut self, our_prefix: &Prefix, keys: Vec<BlobAddress>) -> usize {
+        let mut collected = 0;
+        for address in keys {
+            if our_prefix.matches(address.name()) || self.offered.contains_key(&address) {
+                continue;
+            }
+            if self.queued.insert(address) {
+                collected += 1;
+            }
+        }
+        collected
+    }
+
+    /// Queues again the chunks whose offer timed out, returning the next batch to offer.
+    pub(super) fn next_batch(&mut self, now: Instant) -> Vec<BlobAddress> {
+        let timed_out: Vec<_> = self
+            .offered
+            .iter()
+            .filter(|(_, (_, at))| now.saturating_duration_since(*at) >= ORPHAN_OFFER_TIMEOUT)
+            .map(|(address, _)| *address)
+            .collect();
+        for address in timed_out {
+            let _ = self.offered.remove(&address);
+            let _ = self.queued.insert(address);
+        }
+        let batch: Vec<_> = self.queued.iter().take(ORPHAN_BATCH).copied().collect();
+        for address in &batch {
+            let _ = self.queued.remove(address);
+        }
+        batch
+    }
+
+    /// The msg offering the chunk, recorded as pending.
+    pub(super) fn offer(&mut self, offer: OrphanOffer, now: Instant) -> Result<NodeDuty> {
+        let msg = offer.msg()?;
+        let _ = self.offered.insert(offer.address, (msg.id, now));
+        Ok(NodeDuty::SendNodeMsg {
+            msg,
+            dst: DstLocation::Section(*offer.address.name()),
+        })
+    }
+
+    /// Whether the release answers a pending offer, which is then concluded.
+    pub(super) fn released(&mut self, release: &OrphanRelease) -> bool {
+        match self.offered.get(&release.address) {
+            Some((id, _)) if *id == release.offer => {
+                let _ = self.offered.remove(&release.address);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether all chunks collected have been offered and released.
+    pub(super) fn is_done(&self) -> bool {
+        self.queued.is_empty() && self.offered.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn offer_of(duty: &NodeDuty) -> OrphanOffer {
+        match duty {
+            NodeDuty::SendNodeMsg {
+                msg:
+                    NodeMsg {
+                        body: NodeMsgBody::OrphanOffer(offer),
+                        ..
+                    },
+                dst: DstLocation::Section(name),
+            } if name == offer.address.name() => offer.clone(),
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    #[test]
+    fn chunks_out_of_our_prefix_are_offered_until_released() -> Result<()> {
+        let our_prefix = Prefix::default().pushed(false);
+        let ours = BlobAddress::Public(our_prefix.substituted_in(XorName::random()));
+        let orphan = BlobAddress::Public(our_prefix.sibling().substituted_in(XorName::random()));
+        let mut collector = OrphanCollector::default();
+        let mut now = Instant::now();
+
+        assert_eq!(collector.collect(&our_prefix, vec![ours, orphan]), 1);
+        assert_eq!(collector.collect(&our_prefix, vec![ours, orphan]), 0);
+        assert_eq!(collector.next_batch(now), vec![orphan]);
+        let offer = offer_of(&collector.offer(
+            OrphanOffer {
+                address: orphan,
+                owner: None,
+            },
+            now,
+        )?);
+        assert_eq!(offer.address, orphan);
+        assert!(!collector.is_done());
+
+        // offered again once timed out
+        assert!(collector.next_batch(now).is_empty());
+        now += ORPHAN_OFFER_TIMEOUT;
+        assert_eq!(collector.next_batch(now), vec![orphan]);
+        let offer = offer_of(&collector.offer(offer, now)?);
+
+        let release = OrphanRelease::new(&offer)?;
+        let forged = OrphanRelease {
+            offer: MessageId::new(),
+            address: orphan,
+        };
+        assert!(!collector.released(&forged));
+        assert!(collector.released(&release));
+        assert!(!collector.released(&release));
+        assert!(collector.is_done());
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Blob, BlobAddress};
+use std::collections::BTreeMap;
+
+/// Counters of the chunk read cache, for tuning its budget.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadCacheStats {
+    /// Bytes of chunks cached at most.
+    pub budget: u64,
+    /// Bytes of chunks cached.
+    pub used: u64,
+    /// Number of chunks cached.
+    pub chunks: usize,
+    /// Reads served from the cache.
+    pub hits: u64,
+    /// Reads of chunks not cached, served from the store.
+    pub misses: u64,
+}
+
+/// The chunks most recently read by clients, kept in memory up to a budget of
+/// bytes, so that reads of popular chunks skip the store. Chunks are immutable,
+/// so a cached chunk only goes stale when deleted, which evicts it.
+pub(super) struct ReadCache {
+    budget: u64,
+    used: u64,
+    // the chunks cached, with the tick of their last read
+    chunks: BTreeMap<BlobAddress, (Blob, u64)>,
+    // the chunks cached by the tick of their last read, to evict the least recent
+    recency: BTreeMap<u64, BlobAddress>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl ReadCache {
+    pub(super) fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            used: 0,
+            chunks: BTreeMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The chunk, if cached, which is then the most recently read.
+    pub(super) fn get(&mut self, address: &BlobAddress) -> Option<Blob> {
+        let tick = self.next_tick();
+        let (blob, last_read) = match self.chunks.get_mut(address) {
+            Some(cached) => cached,
+            None => {
+                self.misses += 1;
+                return None;
+            }
+        };
+        let _ = self.recency.remove(last_read);
+        let _ = self.recency.insert(tick, *address);
+        *last_read = tick;
+        self.hits += 1;
+        Some(blob.clone())
+    }
+
+    /// Caches the chunk read from the store, evicting the least recently read
+    /// chunks as needed to stay within the budget. Chunks beyond the whole
+    /// budget are not cached.
+    pub(super) fn insert(&mut self, blob: Blob) {
+        let size = size_of(&blob);
+        if size > self.budget || self.chunks.contains_key(blob.address()) {
+            return;
+        }
+        while self.used + size > self.budget {
+            let least_recent = match self.recency.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            if let Some(address) = self.recency.remove(&least_recent) {
+                self.remove(&address);
+            }
+        }
+        let tick = self.next_tick();
+        let _ = self.recency.insert(tick, *blob.address());
+        let _ = self.chunks.insert(*blob.address(), (blob, tick));
+        self.used += size;
+    }
+
+    /// Evicts the chunk, if cached, e.g. as it is deleted from the store.
+    pub(super) fn remove(&mut self, address: &BlobAddress) {
+        if let Some((blob, last_read)) = self.chunks.remove(address) {
+            let _ = self.recency.remove(&last_read);
+            self.used -= size_of(&blob);
+        }
+    }
+
+    pub(super) fn stats(&self) -> ReadCacheStats {
+        ReadCacheStats {
+            budget: self.budget,
+            used: self.used,
+            chunks: self.chunks.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+fn size_of(blob: &Blob) -> u64 {
+    blob.value().len() as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_data_types::PublicBlob;
+
+    fn chunk(byte: u8) -> Blob {
+        Blob::Public(PublicBlob::new(vec![byte; 10]))
+    }
+
+    #[test]
+    fn least_recently_read_chunks_are_evicted_within_the_budget() {
+        let mut cache = ReadCache::new(25);
+        let (first, second, third) = (chunk(1), chunk(2), chunk(3));
+        assert_eq!(cache.get(first.address()), None);
+        cache.insert(first.clone());
+        cache.insert(second.clone());
+        // the first now read more recently than the second
+        assert_eq!(cache.get(first.address()), Some(first.clone()));
+
+        cache.insert(third.clone());
+        assert_eq!(cache.get(second.address()), None);
+        assert_eq!(cache.get(first.address()), Some(first.clone()));
+        assert_eq!(cache.get(third.address()), Some(third.clone()));
+
+        cache.remove(first.address());
+        cache.insert(Blob::Public(PublicBlob::new(vec![4; 26])));
+        assert_eq!(
+            cache.stats(),
+            ReadCacheStats {
+                budget: 25,
+                used: 10,
+                chunks: 1,
+                hits: 3,
+                misses: 2,
+            }
+        );
+    }
+}
@@ -7,18 +7,102 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::chunk_storage::ChunkStorage;
-use crate::node_ops::NodeDuty;
+use crate::node_msg::{NodeMsg, NodeMsgBody};
+use crate::node_ops::{NodeDuties, NodeDuty};
 use crate::Result;
-use sn_messaging::{client::BlobRead, EndUser, MessageId};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Blob, BlobAddress, PrivateBlob, PublicBlob};
+use sn_messaging::{
+    client::{BlobRead, Error as ErrorMessage},
+    EndUser, MessageId,
+};
+use std::collections::BTreeSet;
+use xor_name::XorName;
 
 /// Read operations on data chunks.
 
 pub(super) async fn get_result(
     read: &BlobRead,
+    range: Option<&ChunkRange>,
     msg_id: MessageId,
     origin: EndUser,
-    storage: &ChunkStorage,
-) -> Result<NodeDuty> {
+    storage: &mut ChunkStorage,
+) -> Result<NodeDuties> {
     let BlobRead::Get(address) = read;
-    storage.get(address, msg_id, origin).await
+    storage.get(address, range, msg_id, origin).await
+}
+
+/// The bytes of a chunk to read, e.g. when streaming it or resuming its download,
+/// rather than reading all of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkRange {
+    /// Index of the first byte to read.
+    pub offset: u64,
+    /// Bytes to read at most, fewer being read past the end of the chunk.
+    pub len: u64,
+}
+
+impl ChunkRange {
+    /// The chunk holding only the bytes of the range, owned as the chunk read.
+    /// A range starting past the end of the chunk is invalid.
+    pub(crate) fn slice(&self, blob: &Blob) -> Result<Blob, ErrorMessage> {
+        let value = blob.value();
+        let size = value.len() as u64;
+        if self.offset > size {
+            return Err(ErrorMessage::InvalidOperation);
+        }
+        let end = self.offset.saturating_add(self.len).min(size);
+        let bytes = value[self.offset as usize..end as usize].to_vec();
+        Ok(match blob {
+            Blob::Public(_) => Blob::Public(PublicBlob::new(bytes)),
+            Blob::Private(data) => Blob::Private(PrivateBlob::new(bytes, *data.owner())),
+        })
+    }
+}
+
+/// A read of a range of a chunk by a client, sent by an Elder to holders of the chunk,
+/// for them to answer the client with the bytes of the range.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangedChunkRead {
+    /// The chunk read.
+    pub address: BlobAddress,
+    /// The bytes of the chunk read.
+    pub range: ChunkRange,
+    /// Id of the msg of the client reading it.
+    pub msg_id: MessageId,
+    /// The client reading it.
+    pub origin: EndUser,
+}
+
+impl RangedChunkRead {
+    /// The msg querying the holders.
+    pub(crate) fn msg(&self, holders: BTreeSet<XorName>) -> NodeDuty {
+        NodeDuty::SendNodeMsgToNodes {
+            targets: holders,
+            msg: NodeMsg::new(NodeMsgBody::RangedChunkRead(self.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn range_is_read_up_to_the_end_of_the_chunk() {
+        let blob = Blob::Public(PublicBlob::new((0..10).collect()));
+        let range = |offset, len| ChunkRange { offset, len };
+
+        assert_eq!(range(2, 3).slice(&blob).unwrap().value(), &vec![2, 3, 4]);
+        assert_eq!(range(8, 5).slice(&blob).unwrap().value(), &vec![8, 9]);
+        assert_eq!(
+            range(0, u64::MAX).slice(&blob).unwrap().value(),
+            blob.value()
+        );
+        assert!(range(10, 1).slice(&blob).unwrap().value().is_empty());
+        assert!(matches!(
+            range(11, 1).slice(&blob),
+            Err(ErrorMessage::InvalidOperation)
+        ));
+    }
 }
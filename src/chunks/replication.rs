@@ -0,0 +1,288 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_data_types::BlobAddress;
+use sn_messaging::{DstLocation, MessageId};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// How often the chunks queued for replication are asked for, and those timed out retried.
+pub(crate) const REPLICATION_TICK: Duration = Duration::from_secs(1);
+/// Chunks asked for at most at a time, by default.
+pub(crate) const DEFAULT_REPLICATION_CONCURRENCY: usize = 16;
+// The time a holder has to send a chunk asked for, before the next holder is asked.
+const REPLICATION_TIMEOUT: Duration = Duration::from_secs(20);
+// The wait before asking again for a chunk timed out, doubled with each attempt.
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+// The times a chunk is asked for at most, before its replication is given up.
+const MAX_REPLICATION_ATTEMPTS: usize = 5;
+
+/// The answer of a new holder to a holder which sent it a chunk for replication
+/// not matching the address asked for, for the holder to verify its copy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationFailed {
+    /// The chunk asked for.
+    pub address: BlobAddress,
+    /// Id of the msg asking for the chunk.
+    pub id: MessageId,
+}
+
+impl ReplicationFailed {
+    /// The msg telling the holder.
+    pub(super) fn msg(&self, holder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::ReplicationFailed(self.clone())),
+            dst: DstLocation::Node(holder),
+        }
+    }
+}
+
+// A chunk to copy from its current holders.
+struct Replication {
+    address: BlobAddress,
+    current_holders: Vec<XorName>,
+    id: MessageId,
+    // the times asked for so far
+    attempts: usize,
+    // when queued, the time to ask for it from, and when in flight, the time it was asked for
+    at: Instant,
+}
+
+/// Schedules the copying of chunks to us as their new holder, asking for a bounded
+/// number of them at a time, from one holder each, and asking the next holder,
+/// after a backoff, for those not sent in time.
+pub(super) struct ReplicationScheduler {
+    concurrency: usize,
+    queued: VecDeque<Replication>,
+    in_flight: BTreeMap<BlobAddress, Replication>,
+    // the chunks given up since last taken
+    given_up: Vec<BlobAddress>,
+}
+
+impl ReplicationScheduler {
+    pub(super) fn new(concurrency: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            queued: VecDeque::new(),
+            in_flight: BTreeMap::new(),
+            given_up: vec![],
+        }
+    }
+
+    pub(super) fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+
+    /// Queues the chunk for replication, unless it is queued or asked for already.
+    pub(super) fn schedule(
+        &mut self,
+        address: BlobAddress,
+        current_holders: BTreeSet<XorName>,
+        id: MessageId,
+        now: Instant,
+    ) {
+        if current_holders.is_empty()
+            || self.in_flight.contains_key(&address)
+            || self.queued.iter().any(|queued| queued.address == address)
+        {
+            return;
+        }
+        self.queued.push_back(Replication {
+            address,
+            current_holders: current_holders.into_iter().collect(),
+            id,
+            attempts: 0,
+            at: now,
+        });
+    }
+
+    /// Frees the slot of the chunk received.
+    pub(super) fn received(&mut self, address: &BlobAddress) {
+        let _ = self.in_flight.remove(address);
+    }
+
+    /// The chunk asked for by the msg, if still in flight.
+    pub(super) fn asked_for(&self, id: MessageId) -> Option<BlobAddress> {
+        self.in_flight
+            .values()
+            .find(|replication| replication.id == id)
+            .map(|replication| replication.address)
+    }
+
+    /// Queues the chunk again, to be asked for from the next holder right away,
+    /// as the holder asked sent it corrupt, unless given up past the max attempts.
+    pub(super) fn failed(&mut self, address: &BlobAddress, now: Instant) {
+        if let Some(mut replication) = self.in_flight.remove(address) {
+            if replication.attempts >= MAX_REPLICATION_ATTEMPTS {
+                warn!(
+                    "Replication of {:?} given up after {} attempts",
+                    address, replication.attempts
+                );
+                self.given_up.push(*address);
+                return;
+            }
+            replication.at = now;
+            self.queued.push_front(replication);
+        }
+    }
+
+    /// The chunks given up since last taken, past the max attempts.
+    pub(super) fn take_given_up(&mut self) -> Vec<BlobAddress> {
+        std::mem::take(&mut self.given_up)
+    }
+
+    /// The chunks to ask for now, with the holder to ask each from, as the concurrency
+    /// allows. Those timed out are queued again first, or given up past the max attempts.
+    pub(super) fn next(&mut self, now: Instant) -> Vec<(BlobAddress, XorName, MessageId)> {
+        let timed_out: Vec<_> = self
+            .in_flight
+            .iter()
+            .filter(|(_, replication)| {
+                now.saturating_duration_since(replication.at) >= REPLICATION_TIMEOUT
+            })
+            .map(|(address, _)| *address)
+            .collect();
+        for address in timed_out {
+            let mut replication = match self.in_flight.remove(&address) {
+                Some(replication) => replication,
+                None => continue,
+            };
+            if replication.attempts >= MAX_REPLICATION_ATTEMPTS {
+                warn!(
+                    "Replication of {:?} given up after {} attempts",
+                    address, replication.attempts
+                );
+                self.given_up.push(address);
+                continue;
+            }
+            info!(
+                "Replication of {:?} timed out, retrying (attempt no. {})",
+                address,
+                replication.attempts + 1
+            );
+            replication.at = now + RETRY_BACKOFF * 2u32.pow(replication.attempts as u32 - 1);
+            self.queued.push_back(replication);
+        }
+
+        let mut asked = vec![];
+        let mut waiting = VecDeque::new();
+        while self.in_flight.len() < self.concurrency {
+            let mut replication = match self.queued.pop_front() {
+                Some(replication) => replication,
+                None => break,
+            };
+            if replication.at > now {
+                waiting.push_back(replication);
+                continue;
+            }
+            // the holders are asked in turn, so that each chunk is sent once
+            let holder = replication.current_holders
+                [replication.attempts % replication.current_holders.len()];
+            asked.push((replication.address, holder, replication.id));
+            replication.attempts += 1;
+            replication.at = now;
+            let _ = self.in_flight.insert(replication.address, replication);
+        }
+        waiting.append(&mut self.queued);
+        self.queued = waiting;
+        asked
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn holders(count: usize) -> BTreeSet<XorName> {
+        (0..count).map(|_| XorName::random()).collect()
+    }
+
+    #[test]
+    fn chunks_are_asked_for_up_to_the_concurrency() {
+        let mut scheduler = ReplicationScheduler::new(2);
+        let now = Instant::now();
+        let addresses: Vec<_> = (0..3)
+            .map(|_| BlobAddress::Public(XorName::random()))
+            .collect();
+        for address in &addresses {
+            scheduler.schedule(*address, holders(2), MessageId::new(), now);
+        }
+        scheduler.schedule(addresses[0], holders(2), MessageId::new(), now);
+
+        let asked: Vec<_> = scheduler.next(now).into_iter().map(|(a, ..)| a).collect();
+        assert_eq!(asked, addresses[..2].to_vec());
+        assert!(scheduler.next(now).is_empty());
+
+        scheduler.received(&addresses[0]);
+        let asked: Vec<_> = scheduler.next(now).into_iter().map(|(a, ..)| a).collect();
+        assert_eq!(asked, vec![addresses[2]]);
+    }
+
+    #[test]
+    fn the_next_holder_is_asked_after_a_timeout_and_backoff() {
+        let mut scheduler = ReplicationScheduler::new(1);
+        let mut now = Instant::now();
+        let address = BlobAddress::Public(XorName::random());
+        let current_holders = holders(2);
+        scheduler.schedule(address, current_holders.clone(), MessageId::new(), now);
+
+        let mut asked_holders = vec![];
+        for attempt in 0..MAX_REPLICATION_ATTEMPTS {
+            match scheduler.next(now).as_slice() {
+                [(asked, holder, _)] if *asked == address => asked_holders.push(*holder),
+                asked => panic!("Unexpected chunks asked for: {:?}", asked),
+            }
+            now += REPLICATION_TIMEOUT;
+            assert!(scheduler.next(now).is_empty());
+            now += RETRY_BACKOFF * 2u32.pow(attempt as u32);
+        }
+        assert_eq!(asked_holders[0], asked_holders[2]);
+        assert_ne!(asked_holders[0], asked_holders[1]);
+        let asked: BTreeSet<_> = asked_holders.into_iter().collect();
+        assert_eq!(asked, current_holders);
+
+        // given up past the max attempts
+        now += REPLICATION_TIMEOUT;
+        assert!(scheduler.next(now).is_empty());
+        assert!(scheduler.queued.is_empty() && scheduler.in_flight.is_empty());
+    }
+
+    #[test]
+    fn the_next_holder_is_asked_right_away_on_failure() {
+        let mut scheduler = ReplicationScheduler::new(1);
+        let now = Instant::now();
+        let address = BlobAddress::Public(XorName::random());
+        let id = MessageId::new();
+        scheduler.schedule(address, holders(2), id, now);
+
+        let first = match scheduler.next(now).as_slice() {
+            [(_, holder, _)] => *holder,
+            asked => panic!("Unexpected chunks asked for: {:?}", asked),
+        };
+        assert_eq!(scheduler.asked_for(id), Some(address));
+        assert_eq!(scheduler.asked_for(MessageId::new()), None);
+
+        scheduler.failed(&address, now);
+        match scheduler.next(now).as_slice() {
+            [(asked, holder, _)] => {
+                assert_eq!(*asked, address);
+                assert_ne!(*holder, first);
+            }
+            asked => panic!("Unexpected chunks asked for: {:?}", asked),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use sn_data_types::BlobAddress;
+use xor_name::XorName;
+
+/// A step of the replication of a chunk to us, as its new holder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplicationEvent {
+    /// The chunk was asked for from one of its current holders.
+    Requested {
+        /// The chunk.
+        address: BlobAddress,
+        /// The holder asked.
+        holder: XorName,
+    },
+    /// The chunk asked for was received from the holder.
+    Fetched {
+        /// The chunk.
+        address: BlobAddress,
+        /// The holder which sent it.
+        holder: XorName,
+    },
+    /// The chunk received was verified to match the address asked for.
+    Verified {
+        /// The chunk.
+        address: BlobAddress,
+    },
+    /// The chunk was stored.
+    Stored {
+        /// The chunk.
+        address: BlobAddress,
+    },
+    /// The chunk received could not be used, or its replication was given up.
+    Failed {
+        /// The chunk.
+        address: BlobAddress,
+        /// The holder which sent it, or none when its replication was given up.
+        holder: Option<XorName>,
+        /// Why it failed.
+        reason: String,
+    },
+}
+
+/// Observes the replication of chunks to the node, e.g. for test harnesses
+/// and network visualizers to track the data moved during churn,
+/// as registered with the node by the application running it.
+pub trait ReplicationObserver: Send + Sync {
+    /// Called at each step of the replication of a chunk, in order.
+    /// It is called from the event loop of the node, so should not block.
+    fn observe(&self, event: ReplicationEvent);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{chunk_store::UsedSpace, chunks::Chunks, Error, Result};
+    use sn_data_types::{Blob, PublicBlob};
+    use sn_messaging::MessageId;
+    use std::sync::{Arc, Mutex};
+    use tempdir::TempDir;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<ReplicationEvent>>);
+
+    impl ReplicationObserver for Recorder {
+        fn observe(&self, event: ReplicationEvent) {
+            if let Ok(mut events) = self.0.lock() {
+                events.push(event);
+            }
+        }
+    }
+
+    impl Recorder {
+        fn take(&self) -> Vec<ReplicationEvent> {
+            self.0
+                .lock()
+                .map(|mut events| events.drain(..).collect())
+                .unwrap_or_default()
+        }
+    }
+
+    #[tokio::test]
+    async fn replication_steps_are_observed_in_order() -> Result<()> {
+        let root = TempDir::new("replication_observer")
+            .map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let mut chunks = Chunks::new(
+            XorName::random(),
+            root.path(),
+            UsedSpace::new(u64::MAX),
+            None,
+            false,
+            None,
+            None,
+            Arc::default(),
+        )
+        .await?;
+        let recorder = Arc::new(Recorder::default());
+        chunks.set_replication_observer(recorder.clone());
+
+        let blob = Blob::Public(PublicBlob::new(b"chunk".to_vec()));
+        let address = *blob.address();
+        let holder = XorName::random();
+        let id = MessageId::new();
+        let _ = chunks
+            .replicate_chunk(address, vec![holder].into_iter().collect(), id)
+            .await?;
+        let _ = chunks.store_replicated_chunk(blob, id, holder).await?;
+        assert_eq!(
+            recorder.take(),
+            vec![
+                ReplicationEvent::Requested { address, holder },
+                ReplicationEvent::Fetched { address, holder },
+                ReplicationEvent::Verified { address },
+                ReplicationEvent::Stored { address },
+            ]
+        );
+
+        // a chunk not matching the address asked for
+        let other = Blob::Public(PublicBlob::new(b"other chunk".to_vec()));
+        let address = *other.address();
+        let id = MessageId::new();
+        let _ = chunks
+            .replicate_chunk(address, vec![holder].into_iter().collect(), id)
+            .await?;
+        let corrupt = Blob::Public(PublicBlob::new(b"corrupt".to_vec()));
+        let _ = chunks.store_replicated_chunk(corrupt, id, holder).await?;
+        let events = recorder.take();
+        assert_eq!(
+            events[..2],
+            [
+                ReplicationEvent::Requested { address, holder },
+                ReplicationEvent::Fetched { address, holder },
+            ]
+        );
+        assert!(matches!(
+            &events[2],
+            ReplicationEvent::Failed { address: failed, holder: Some(from), .. }
+                if *failed == address && *from == holder
+        ));
+        // and asked for again at once
+        assert_eq!(
+            events[3..],
+            [ReplicationEvent::Requested { address, holder }]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_data_types::BlobAddress;
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+/// How often scrubbing is checked for being due, each time
+/// reading up to the max IO rate worth of chunks since the last.
+pub(crate) const SCRUB_TICK: Duration = Duration::from_secs(10);
+
+/// A time of day scrubbing is done within, in UTC, given as e.g. "01:00-05:00".
+/// A window ending before it starts runs past midnight, and "00:00-24:00" is the whole day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ScrubWindow {
+    // minutes into the day
+    start: u16,
+    end: u16,
+}
+
+impl ScrubWindow {
+    fn contains(&self, minute: u16) -> bool {
+        if self.start <= self.end {
+            self.start <= minute && minute < self.end
+        } else {
+            self.start <= minute || minute < self.end
+        }
+    }
+
+    // the part of a window past midnight is of the day it started
+    fn opened_on(&self, day: u64, minute: u16) -> u64 {
+        if self.start > self.end && minute < self.end {
+            day.saturating_sub(1)
+        } else {
+            day
+        }
+    }
+}
+
+fn parse_minute(time: &str) -> Option<u16> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u16>().ok()?, minutes.parse::<u16>().ok()?);
+    if minutes >= 60 || hours * 60 + minutes > MINUTES_PER_DAY {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+impl TryFrom<String> for ScrubWindow {
+    type Error = String;
+
+    fn try_from(window: String) -> Result<Self, Self::Error> {
+        let times = window
+            .split_once('-')
+            .and_then(|(start, end)| Some((parse_minute(start)?, parse_minute(end)?)));
+        match times {
+            Some((start, end)) if start != end && start < MINUTES_PER_DAY => {
+                Ok(Self { start, end })
+            }
+            _ => Err(format!(
+                "Invalid scrubbing window, expected HH:MM-HH:MM: {}",
+                window
+            )),
+        }
+    }
+}
+
+impl From<ScrubWindow> for String {
+    fn from(window: ScrubWindow) -> Self {
+        window.to_string()
+    }
+}
+
+impl Display for ScrubWindow {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+/// When, and how much of, the stored chunks are read back to verify them, so that
+/// operators on spinning disks can confine it to off-peak hours. Each window opening
+/// starts a pass, which continues where the last pass left off, so that all chunks
+/// are verified every so many passes. Chunks found corrupt are quarantined, and
+/// copied over again from the other holders.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ScrubSchedule {
+    /// The times of day to scrub within. The whole day if none are given,
+    /// in which case a pass is started each day.
+    pub windows: Vec<ScrubWindow>,
+    /// Bytes per second read at most when scrubbing.
+    pub max_io_rate: u64,
+    /// Percentage of the stored chunks verified per pass.
+    pub sample_percentage: u8,
+}
+
+impl Default for ScrubSchedule {
+    fn default() -> Self {
+        Self {
+            windows: vec![],
+            max_io_rate: 1024 * 1024,
+            sample_percentage: 10,
+        }
+    }
+}
+
+impl ScrubSchedule {
+    /// The pass due at the time, by the day and window it opened in, if any.
+    fn pass_at(&self, day: u64, minute: u16) -> Option<(u64, usize)> {
+        if self.windows.is_empty() {
+            return Some((day, 0));
+        }
+        self.windows
+            .iter()
+            .enumerate()
+            .find(|(_, window)| window.contains(minute))
+            .map(|(index, window)| (window.opened_on(day, minute), index))
+    }
+
+    /// Bytes to read at most per scrubbing tick.
+    pub(super) fn tick_budget(&self) -> u64 {
+        self.max_io_rate.saturating_mul(SCRUB_TICK.as_secs())
+    }
+}
+
+/// Keeps track of the passes over the stored chunks.
+pub(super) struct Scrubber {
+    schedule: ScrubSchedule,
+    // the pass being done, and the number of chunks left in it
+    pass: Option<((u64, usize), usize)>,
+    // the last chunk verified, for the next to continue after
+    cursor: Option<BlobAddress>,
+}
+
+impl Scrubber {
+    pub(super) fn new(schedule: ScrubSchedule) -> Self {
+        Self {
+            schedule,
+            pass: None,
+            cursor: None,
+        }
+    }
+
+    pub(super) fn schedule(&self) -> &ScrubSchedule {
+        &self.schedule
+    }
+
+    /// The chunks to verify next, in the order to verify them, if scrubbing is due.
+    /// The chunks are to be verified in turn, as the IO budget allows.
+    pub(super) fn due(&mut self, mut stored: Vec<BlobAddress>) -> Vec<BlobAddress> {
+        let (day, minute) = now_utc();
+        self.due_at(day, minute, &mut stored)
+    }
+
+    fn due_at(&mut self, day: u64, minute: u16, stored: &mut Vec<BlobAddress>) -> Vec<BlobAddress> {
+        let pass = match self.schedule.pass_at(day, minute) {
+            Some(pass) => pass,
+            None => return vec![],
+        };
+        let remaining = match self.pass {
+            Some((current, remaining)) if current == pass => remaining,
+            _ => {
+                let percentage = self.schedule.sample_percentage.clamp(1, 100) as usize;
+                let sample = (stored.len() * percentage).div_ceil(100);
+                info!(
+                    "Starting scrubbing pass of {} out of {} chunks",
+                    sample,
+                    stored.len()
+                );
+                self.pass = Some((pass, sample));
+                sample
+            }
+        };
+        stored.sort();
+        let next = match self.cursor {
+            Some(cursor) => stored.iter().position(|address| *address > cursor),
+            None => None,
+        };
+        stored.rotate_left(next.unwrap_or_default());
+        stored.truncate(remaining);
+        std::mem::take(stored)
+    }
+
+    /// Records the chunk as verified.
+    pub(super) fn verified(&mut self, address: BlobAddress) {
+        self.cursor = Some(address);
+        if let Some((_, remaining)) = &mut self.pass {
+            *remaining = remaining.saturating_sub(1);
+            if *remaining == 0 {
+                info!("Scrubbing pass completed");
+            }
+        }
+    }
+}
+
+fn now_utc() -> (u64, u16) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let minutes = secs / 60;
+    (
+        minutes / MINUTES_PER_DAY as u64,
+        (minutes % MINUTES_PER_DAY as u64) as u16,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use xor_name::XorName;
+
+    #[test]
+    fn windows_are_parsed_from_config() {
+        let schedule: ScrubSchedule =
+            serde_json::from_str(r#"{"windows": ["22:30-04:00"], "sample-percentage": 50}"#)
+                .unwrap();
+        let window = schedule.windows[0];
+        assert_eq!(window.to_string(), "22:30-04:00");
+        assert!(window.contains(23 * 60));
+        assert!(window.contains(60));
+        assert!(!window.contains(12 * 60));
+        assert_eq!(schedule.max_io_rate, ScrubSchedule::default().max_io_rate);
+
+        for invalid in &["25:00-01:00", "01:00-01:00", "01:60-02:00", "0100"] {
+            assert!(ScrubWindow::try_from(invalid.to_string()).is_err());
+        }
+    }
+
+    #[test]
+    fn passes_cover_all_chunks_across_windows() {
+        let mut scrubber = Scrubber::new(ScrubSchedule {
+            windows: vec![ScrubWindow::try_from("23:00-01:00".to_string()).unwrap()],
+            sample_percentage: 50,
+            ..Default::default()
+        });
+        let mut stored: Vec<_> = (0..4)
+            .map(|_| BlobAddress::Public(XorName::random()))
+            .collect();
+        stored.sort();
+
+        // not due outside the window
+        assert!(scrubber.due_at(1, 12 * 60, &mut stored.clone()).is_empty());
+
+        let first = scrubber.due_at(1, 23 * 60, &mut stored.clone());
+        assert_eq!(first, stored[..2].to_vec());
+        scrubber.verified(first[0]);
+        // past midnight is the same pass, continuing where it left off
+        let rest = scrubber.due_at(2, 30, &mut stored.clone());
+        assert_eq!(rest, stored[1..2].to_vec());
+        scrubber.verified(rest[0]);
+        assert!(scrubber.due_at(2, 40, &mut stored.clone()).is_empty());
+
+        // the next pass continues with the chunks not yet verified
+        let next = scrubber.due_at(2, 23 * 60, &mut stored.clone());
+        assert_eq!(next, stored[2..].to_vec());
+    }
+}
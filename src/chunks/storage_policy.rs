@@ -0,0 +1,212 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_data_types::BlobAddress;
+use std::collections::BTreeSet;
+
+/// What is done once the chunks stored use up the quota beyond the high watermark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Eviction {
+    /// New chunks are refused, until the chunks stored are back below the low watermark.
+    RefuseNew,
+    /// The least recently accessed chunks are offloaded to the other Adults of the
+    /// section, down to the low watermark. Each is only removed from our store once
+    /// copied by another holder, so no chunk is lost on the way.
+    OffloadToSection,
+}
+
+/// How much disk the chunks stored by the node may use, e.g.
+/// '{"quota": 10737418240, "high-watermark": 80, "low-watermark": 60, "eviction": "offload-to-section"}'.
+/// Beyond the quota, new chunks are always refused. Beyond the high watermark, the section
+/// is told we are nearly full, and the eviction applied.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct StoragePolicy {
+    /// Bytes of chunks stored at most. Defaults to the max capacity of the node.
+    pub quota: Option<u64>,
+    /// Percentage of the quota beyond which the eviction is applied.
+    pub high_watermark: u8,
+    /// Percentage of the quota the eviction brings the chunks stored back down to.
+    pub low_watermark: u8,
+    /// What is done beyond the high watermark.
+    pub eviction: Eviction,
+}
+
+impl Default for StoragePolicy {
+    fn default() -> Self {
+        Self {
+            quota: None,
+            high_watermark: 80,
+            low_watermark: 60,
+            eviction: Eviction::RefuseNew,
+        }
+    }
+}
+
+/// Where the chunks stored are at, by the policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Pressure {
+    /// Within the high watermark.
+    Normal,
+    /// Beyond the high watermark.
+    High,
+}
+
+/// Applies the storage policy to the chunks stored.
+pub(super) struct StorageGuard {
+    policy: StoragePolicy,
+    // whether new chunks are refused, until back below the low watermark
+    refusing: bool,
+    // the chunks to be removed once copied by another holder
+    offloading: BTreeSet<BlobAddress>,
+}
+
+impl StorageGuard {
+    pub(super) fn new(policy: StoragePolicy) -> Self {
+        Self {
+            policy,
+            refusing: false,
+            offloading: BTreeSet::new(),
+        }
+    }
+
+    /// The quota, given the max capacity of the node.
+    pub(super) fn quota(&self, max_capacity: u64) -> u64 {
+        self.policy
+            .quota
+            .map_or(max_capacity, |quota| quota.min(max_capacity))
+    }
+
+    /// Whether a chunk of the size fits in the quota, given the bytes used of it.
+    pub(super) fn fits(&self, used: u64, size: u64, quota: u64) -> bool {
+        used.saturating_add(size) <= quota
+    }
+
+    /// Whether a new chunk of the size is to be stored, given the bytes used of the quota.
+    /// Copies of chunks held for repair are only bound by the quota.
+    pub(super) fn admits_new(&self, used: u64, size: u64, quota: u64) -> bool {
+        !self.refusing && self.fits(used, size, quota)
+    }
+
+    /// Judges the bytes used of the quota, refusing new chunks from beyond the high
+    /// watermark down to the low watermark, if that is the eviction of the policy.
+    pub(super) fn assess(&mut self, used: u64, quota: u64) -> Pressure {
+        let high = percentage_of(quota, self.policy.high_watermark);
+        let low = percentage_of(
+            quota,
+            self.policy.low_watermark.min(self.policy.high_watermark),
+        );
+        if used > high {
+            if self.policy.eviction == Eviction::RefuseNew && !self.refusing {
+                info!("Chunks stored beyond the high watermark, refusing new chunks");
+                self.refusing = true;
+            }
+            Pressure::High
+        } else {
+            if used <= low && self.refusing {
+                info!("Chunks stored back below the low watermark, accepting new chunks");
+                self.refusing = false;
+            }
+            Pressure::Normal
+        }
+    }
+
+    /// Picks the chunks to offload, the least recently accessed first, until the
+    /// bytes used would be down to the low watermark, if that is the eviction of
+    /// the policy. The chunks stored are given with their size, by last access.
+    pub(super) fn offload(
+        &mut self,
+        used: u64,
+        quota: u64,
+        by_last_access: Vec<(BlobAddress, u64)>,
+    ) -> Vec<BlobAddress> {
+        if self.policy.eviction != Eviction::OffloadToSection {
+            return vec![];
+        }
+        let low = percentage_of(
+            quota,
+            self.policy.low_watermark.min(self.policy.high_watermark),
+        );
+        let mut remaining = by_last_access
+            .iter()
+            .filter(|(address, _)| self.offloading.contains(address))
+            .fold(used, |remaining, (_, size)| remaining.saturating_sub(*size));
+        let mut picked = vec![];
+        for (address, size) in by_last_access {
+            if remaining <= low {
+                break;
+            }
+            if self.offloading.insert(address) {
+                remaining = remaining.saturating_sub(size);
+                picked.push(address);
+            }
+        }
+        picked
+    }
+
+    /// The chunk was copied by another holder. Returns
+    /// whether it was being offloaded, and is to be removed.
+    pub(super) fn offloaded(&mut self, address: &BlobAddress) -> bool {
+        self.offloading.remove(address)
+    }
+}
+
+fn percentage_of(quota: u64, percentage: u8) -> u64 {
+    (quota as u128 * percentage.min(100) as u128 / 100) as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use xor_name::XorName;
+
+    #[test]
+    fn new_chunks_are_refused_between_the_watermarks() {
+        let mut guard = StorageGuard::new(StoragePolicy::default());
+        let quota = guard.quota(1000);
+        assert!(guard.admits_new(900, 100, quota));
+        assert!(!guard.fits(901, 100, quota));
+
+        assert_eq!(guard.assess(801, quota), Pressure::High);
+        assert!(!guard.admits_new(801, 1, quota));
+        assert!(guard.fits(801, 1, quota));
+        // still refusing until back below the low watermark
+        assert_eq!(guard.assess(700, quota), Pressure::Normal);
+        assert!(!guard.admits_new(700, 1, quota));
+        assert_eq!(guard.assess(600, quota), Pressure::Normal);
+        assert!(guard.admits_new(600, 1, quota));
+    }
+
+    #[test]
+    fn least_recently_accessed_chunks_are_offloaded_down_to_the_low_watermark() {
+        let mut guard = StorageGuard::new(StoragePolicy {
+            quota: Some(1000),
+            eviction: Eviction::OffloadToSection,
+            ..Default::default()
+        });
+        let quota = guard.quota(u64::MAX);
+        let chunks: Vec<_> = (0..4)
+            .map(|_| (BlobAddress::Public(XorName::random()), 100))
+            .collect();
+
+        assert_eq!(guard.assess(900, quota), Pressure::High);
+        // offloading does not refuse new chunks
+        assert!(guard.admits_new(900, 1, quota));
+        let picked = guard.offload(900, quota, chunks.clone());
+        assert_eq!(picked, vec![chunks[0].0, chunks[1].0, chunks[2].0]);
+        // those being offloaded are not picked again
+        assert!(guard.offload(900, quota, chunks.clone()).is_empty());
+
+        assert!(guard.offloaded(&chunks[0].0));
+        assert!(!guard.offloaded(&chunks[0].0));
+        assert!(!guard.offloaded(&chunks[3].0));
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    chunk_store::{BlobChunkStore, Subdir, UsageCategory},
+    Error, Result,
+};
+use async_trait::async_trait;
+use sn_data_types::{Blob, BlobAddress};
+use std::{collections::BTreeMap, path::Path};
+
+/// The backend storing the chunks held by an Adult. By default the chunks are
+/// stored as files under the root dir of the node, but operators can plug in
+/// other backends, e.g. a database or a remote object store, by constructing
+/// the node with `Node::with_chunk_store`.
+#[async_trait]
+pub trait ChunkStore: Send + Sync {
+    /// Reads the chunk stored at the address.
+    fn get(&self, address: &BlobAddress) -> Result<Blob>;
+
+    /// Whether a chunk is stored at the address.
+    fn has(&self, address: &BlobAddress) -> bool;
+
+    /// Writes the chunk, attributing the space it uses to the category, e.g. to
+    /// `UsageCategory::ReplicatedChunks` for the copies of chunks held by us for repair.
+    /// A chunk already stored at the address is overwritten.
+    async fn put(&mut self, blob: &Blob, category: UsageCategory) -> Result<()>;
+
+    /// Deletes the chunk stored at the address, if any.
+    async fn delete(&mut self, address: &BlobAddress) -> Result<()>;
+
+    /// Moves the chunk stored at the address out of the store, e.g. when found corrupt,
+    /// for the operator to inspect, if the backend supports it, or else deletes it.
+    async fn quarantine(&mut self, address: &BlobAddress) -> Result<()> {
+        self.delete(address).await
+    }
+
+    /// Lists the addresses of all chunks stored.
+    fn keys(&self) -> Vec<BlobAddress>;
+
+    /// Lists the addresses of all chunks stored with the size of each, the least
+    /// recently accessed first, to be offloaded by the storage policy. Backends
+    /// not tracking accesses list none, so that nothing is offloaded from them.
+    fn keys_by_last_access(&self) -> Vec<(BlobAddress, u64)> {
+        vec![]
+    }
+
+    /// Used space to max space ratio.
+    async fn used_space_ratio(&self) -> f64;
+
+    /// Used space, per category.
+    async fn used_space_by_category(&self) -> BTreeMap<UsageCategory, u64>;
+
+    /// Used space, in bytes.
+    async fn used_space(&self) -> u64 {
+        self.used_space_by_category().await.values().sum()
+    }
+
+    /// Max space, in bytes, which is not bounded unless the backend bounds it.
+    async fn max_capacity(&self) -> u64 {
+        u64::MAX
+    }
+
+    /// Relocates the chunks to the root dir, serving them meanwhile,
+    /// which only backends storing them under a root dir support.
+    async fn relocate(&mut self, root: &Path) -> Result<()> {
+        Err(Error::InvalidOperation(format!(
+            "Chunks cannot be relocated to {:?} by this chunk store",
+            root
+        )))
+    }
+
+    /// Whether chunks are yet to be migrated to the root relocated to.
+    fn is_migrating(&self) -> bool {
+        false
+    }
+
+    /// Migrates up to `count` chunks to the root relocated to.
+    async fn migrate(&mut self, _count: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Compacts the store once chunks are deleted in bulk, e.g. after a split,
+    /// if the backend needs it to reclaim the space of their records.
+    fn compact(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The default backend, storing the chunks as files on disk.
+#[async_trait]
+impl ChunkStore for BlobChunkStore {
+    fn get(&self, address: &BlobAddress) -> Result<Blob> {
+        BlobChunkStore::get(self, address)
+    }
+
+    fn has(&self, address: &BlobAddress) -> bool {
+        BlobChunkStore::has(self, address)
+    }
+
+    async fn put(&mut self, blob: &Blob, category: UsageCategory) -> Result<()> {
+        if category == Self::usage_category() {
+            BlobChunkStore::put(self, blob).await
+        } else {
+            self.put_as(blob, category).await
+        }
+    }
+
+    async fn delete(&mut self, address: &BlobAddress) -> Result<()> {
+        BlobChunkStore::delete(self, address).await
+    }
+
+    async fn quarantine(&mut self, address: &BlobAddress) -> Result<()> {
+        BlobChunkStore::quarantine(self, address).await
+    }
+
+    fn keys(&self) -> Vec<BlobAddress> {
+        BlobChunkStore::keys(self)
+    }
+
+    fn keys_by_last_access(&self) -> Vec<(BlobAddress, u64)> {
+        BlobChunkStore::keys_by_last_access(self)
+    }
+
+    async fn used_space_ratio(&self) -> f64 {
+        BlobChunkStore::used_space_ratio(self).await
+    }
+
+    async fn used_space_by_category(&self) -> BTreeMap<UsageCategory, u64> {
+        BlobChunkStore::used_space_by_category(self).await
+    }
+
+    async fn used_space(&self) -> u64 {
+        self.total_used_space().await
+    }
+
+    async fn max_capacity(&self) -> u64 {
+        BlobChunkStore::max_capacity(self).await
+    }
+
+    async fn relocate(&mut self, root: &Path) -> Result<()> {
+        BlobChunkStore::relocate(self, root).await
+    }
+
+    fn is_migrating(&self) -> bool {
+        BlobChunkStore::is_migrating(self)
+    }
+
+    async fn migrate(&mut self, count: usize) -> Result<()> {
+        BlobChunkStore::migrate(self, count).await
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        // the index is rewritten without the entries of the chunks deleted
+        self.checkpoint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chunk_store::UsedSpace;
+    use sn_data_types::PublicBlob;
+    use tempdir::TempDir;
+
+    #[tokio::test]
+    async fn space_of_chunks_on_disk_is_attributed_to_their_category() -> Result<()> {
+        let root =
+            TempDir::new("chunk_store").map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let mut store: Box<dyn ChunkStore> =
+            Box::new(BlobChunkStore::new(root.path(), UsedSpace::new(u64::MAX)).await?);
+        let new = Blob::Public(PublicBlob::new(b"new".to_vec()));
+        let replicated = Blob::Public(PublicBlob::new(b"replicated".to_vec()));
+
+        store.put(&new, UsageCategory::NewChunks).await?;
+        store
+            .put(&replicated, UsageCategory::ReplicatedChunks)
+            .await?;
+        let used = store.used_space_by_category().await;
+        assert!(used[&UsageCategory::NewChunks] > 0);
+        assert!(used[&UsageCategory::ReplicatedChunks] > 0);
+        assert_eq!(store.get(replicated.address())?, replicated);
+
+        store.delete(new.address()).await?;
+        assert!(!store.has(new.address()));
+        assert_eq!(store.keys(), vec![*replicated.address()]);
+        Ok(())
+    }
+}
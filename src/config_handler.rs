@@ -8,7 +8,13 @@
 
 #![allow(trivial_numeric_casts)] // FIXME
 
-use crate::{Error, Result};
+use crate::{
+    capacity::EconomyConfig,
+    chunk_store::{ChunkCompression, UsageCategory},
+    chunks::{ScrubSchedule, StoragePolicy},
+    metadata::{ErasureCoding, WriteQuotas},
+    Error, Result,
+};
 use log::{debug, Level};
 use serde::{Deserialize, Serialize};
 use sn_routing::TransportConfig as NetworkConfig;
@@ -16,7 +22,7 @@ use std::convert::Infallible;
 use std::net::AddrParseError;
 use std::num::ParseIntError;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fs::{self, File},
     io::{self, BufReader},
     net::{IpAddr, Ipv4Addr, SocketAddr},
@@ -28,6 +34,10 @@ const CONFIG_FILE: &str = "node.config";
 const CONNECTION_INFO_FILE: &str = "node_connection_info.config";
 const DEFAULT_ROOT_DIR_NAME: &str = "root_dir";
 const DEFAULT_MAX_CAPACITY: u64 = 2 * 1024 * 1024 * 1024;
+const DEFAULT_MIN_WRITE_ACKS: u8 = 0;
+const DEFAULT_TREASURY_PERCENTAGE: u8 = 0;
+const DEFAULT_MIN_REWARD_AGE: u8 = 6;
+const DEFAULT_OPERATOR_TLS_DIR_NAME: &str = "operator_tls";
 
 /// Node configuration
 #[derive(Default, Clone, Debug, Serialize, Deserialize, Eq, PartialEq, StructOpt)]
@@ -86,6 +96,89 @@ pub struct Config {
     /// or when a node is connected to the internect directly without a router. Eg. Digital Ocean droplets.
     #[structopt(long)]
     pub skip_igd: bool,
+    /// Keep a warm copy of the Elder state while among the oldest Adults, next in line
+    /// for promotion, so that on promotion only the changes since are caught up on.
+    #[structopt(long)]
+    pub warm_standby: bool,
+    /// Run the node as the child of a supervising process, which restarts it when it
+    /// crashes, with the same root dir and keys, backing off from restarting if it
+    /// keeps crashing.
+    #[structopt(long)]
+    pub supervised: bool,
+    /// Overwrite the chunks removed from the disk before unlinking them, so that removed
+    /// client data cannot be recovered from the disk.
+    #[structopt(long)]
+    pub secure_delete: bool,
+    /// When a member holding chunks is lost, send the chunks to replicate to each new
+    /// holder in batches, rather than in one msg per chunk. Cuts the msgs sent at large
+    /// churn, though new holders of a version without batches will not copy the chunks.
+    #[structopt(long)]
+    pub batch_chunk_replication: bool,
+    /// Bytes buffered at most when writing a chunk to disk, as it is serialised straight
+    /// into the file rather than into a copy in memory first. The chunk received is still
+    /// held in memory in full. Defaults to 64 KiB.
+    #[structopt(long)]
+    pub chunk_write_buffer: Option<usize>,
+    /// Compress the chunks written to disk, with 'zstd' for the smaller files, or 'lz4'
+    /// for the cheaper writes and reads. Chunks are read back whatever the compression
+    /// they were written with, so it can be changed at any time.
+    #[structopt(long)]
+    pub chunk_compression: Option<ChunkCompression>,
+    /// Bytes of a chunk at most accepted by the section, for networks whose transport
+    /// limits differ. Defaults to the max chunk size advised for the store cost of the
+    /// economy and the max msg size. The Elders at genesis set it for the section.
+    #[structopt(long)]
+    pub max_chunk_size: Option<u64>,
+    /// How much disk the chunks stored may use, as JSON, e.g. '{"quota": 10737418240,
+    /// "high-watermark": 80, "low-watermark": 60, "eviction": "refuse-new"}', with the
+    /// watermarks in percent of the quota, and "refuse-new" or "offload-to-section" beyond
+    /// the high one. If not set, the section is only told once the node is nearly full.
+    #[structopt(long, parse(try_from_str = serde_json::from_str))]
+    pub storage_policy: Option<StoragePolicy>,
+    /// Bytes of the chunks most recently read by clients to keep in memory at most,
+    /// so that reads of popular chunks skip the disk. Chunks are not cached if not set.
+    #[structopt(long)]
+    pub chunk_read_cache: Option<u64>,
+    /// Milliseconds after which a query forwarded to another section is also sent to
+    /// an alternate Elder of it, keeping the first outstanding, so that a slow Elder
+    /// does not hold up the read. Queries are not hedged if not set.
+    #[structopt(long)]
+    pub query_hedge_after_msec: Option<u64>,
+    /// Milliseconds between the rounds of storage challenges issued while an Elder, by
+    /// which the holders of random chunks prove they hold them, or are repaired. Off if
+    /// not set, as Adults of a version without challenges do not answer them.
+    #[structopt(long)]
+    pub storage_challenge_interval_msec: Option<u64>,
+    /// Chunks asked for at most at a time, while they are replicated to us as their new
+    /// holder, e.g. after the loss of an Adult. 16 if not set.
+    #[structopt(long)]
+    pub replication_concurrency: Option<usize>,
+    /// Relay the reads of chunks by clients while an Elder, querying this many holders at a
+    /// time and answering the first chunk verified to be the one read, falling back to the
+    /// next holders on timeout, rather than the holders answering clients directly.
+    #[structopt(long)]
+    pub relayed_read_holders: Option<usize>,
+    /// Entries a Map may hold at most, writes adding to a Map with as many being refused
+    /// while an Elder. 10000 if not set.
+    #[structopt(long)]
+    pub max_map_entries: Option<usize>,
+    /// Entries that may be appended to a Sequence at most over the last hour, appends
+    /// beyond being refused while an Elder. 3600 if not set.
+    #[structopt(long)]
+    pub max_sequence_appends_per_hour: Option<usize>,
+    /// Bytes of an entry of a Sequence or Map at most, key and value, writes of larger
+    /// entries being refused while an Elder. 1 MiB if not set.
+    #[structopt(long)]
+    pub max_entry_size: Option<usize>,
+    /// Store new chunks while an Elder erasure coded into this many shards of their content,
+    /// each with a holder of its own, plus the parity shards, rather than copied whole to
+    /// each holder. Chunks are stored whole if not set.
+    #[structopt(long)]
+    pub erasure_data_shards: Option<usize>,
+    /// Shards of parity of the chunks erasure coded, as many holders of shards of a chunk
+    /// as may be lost. 2 if not set.
+    #[structopt(long)]
+    pub erasure_parity_shards: Option<usize>,
     /// Hard Coded contacts
     #[structopt(
         short,
@@ -118,6 +211,73 @@ pub struct Config {
     /// Duration of a UPnP port mapping.
     #[structopt(long)]
     pub upnp_lease_duration: Option<u32>,
+    /// Number of Elders to have applied a client write before it is acknowledged
+    /// to the client. Taken by the genesis node only, setting it for the network,
+    /// the Elders of each section pushing it to its nodes.
+    /// Defaults to 0, where writes are not acknowledged.
+    #[structopt(long)]
+    pub min_write_acks: Option<u8>,
+    /// Percentage of each churn payout retained in the section wallet, rather than
+    /// distributed to nodes. Taken by the genesis node only, setting it for the network,
+    /// the Elders of each section pushing it to its nodes. Defaults to 0, at most 100.
+    #[structopt(long)]
+    pub treasury_percentage: Option<u8>,
+    /// Age nodes are to have reached to take part in reward distribution. Taken by the
+    /// genesis node only, setting it for the network, the Elders of each section pushing
+    /// it to its nodes. Defaults to 6.
+    #[structopt(long)]
+    pub min_reward_age: Option<u8>,
+    /// Upper limits in bytes for the storage used by each category of data,
+    /// within the max capacity, e.g. '{"replicated-chunks": 1073741824}'.
+    /// Categories without a limit can use up to the max capacity.
+    #[structopt(long, parse(try_from_str = serde_json::from_str))]
+    pub used_space_limits: Option<BTreeMap<UsageCategory, u64>>,
+    /// The parameters of minting, rewards and store cost of the network, for test networks
+    /// to experiment with, e.g. '{"mint-percentage": 50, "cost-chunk-size": 500000}'.
+    /// Taken by the genesis node only, setting them for the network, the Elders of each
+    /// section pushing them to its nodes. Parameters not given are those of the main network.
+    #[structopt(long, parse(try_from_str = serde_json::from_str))]
+    pub economy: Option<EconomyConfig>,
+    /// When, and how much of, the stored chunks to read back to verify them, e.g.
+    /// '{"windows": ["01:00-05:00"], "max-io-rate": 1048576, "sample-percentage": 10}',
+    /// with the windows in UTC. Stored chunks are not verified if not set.
+    #[structopt(long, parse(try_from_str = serde_json::from_str))]
+    pub scrubbing: Option<ScrubSchedule>,
+    /// Bytes per second of responses to clients the node sends at most, shared fairly
+    /// among the clients served, so that no single client takes up the uplink.
+    /// Responses are not shaped if not set.
+    #[structopt(long)]
+    pub egress_rate: Option<u64>,
+    /// Bytes per second of responses the node sends at most to any single client,
+    /// within the egress rate. Defaults to the egress rate.
+    #[structopt(long)]
+    pub egress_client_rate: Option<u64>,
+    /// Msgs per second the node handles at most from any single client,
+    /// refusing those beyond. Clients are not limited if not set.
+    #[structopt(long)]
+    pub client_msg_rate: Option<u32>,
+    /// Times the node has been restarted by its supervisor. Set by the supervisor
+    /// for the node it runs, the keys of which are kept in the root dir, and reused.
+    #[structopt(long, hidden = true)]
+    pub restarts: Option<u32>,
+    /// The genesis key of the network to join. A hex formatted BLS public key.
+    /// The node refuses to join a network whose section chain is not anchored to it.
+    /// Any network is joined if not set.
+    #[structopt(long)]
+    pub genesis_key: Option<String>,
+    /// The key authorising operator commands to the running node.
+    /// A hex formatted BLS public key. Operator commands are rejected if not set.
+    #[structopt(long)]
+    pub operator_key: Option<String>,
+    /// Address of the operator control socket, secured by mutual TLS.
+    /// The socket is not opened if not set.
+    #[structopt(long)]
+    pub operator_rpc_addr: Option<SocketAddr>,
+    /// Directory holding the node certificate and key, and the operator CA certificate,
+    /// of the operator control socket. Defaults to `operator_tls` within the root dir.
+    /// Replaced files are picked up without restart, for certificate rotation.
+    #[structopt(long, parse(from_os_str))]
+    pub operator_tls_dir: Option<PathBuf>,
     #[structopt(skip)]
     #[allow(missing_docs)]
     pub network_config: NetworkConfig,
@@ -196,6 +356,11 @@ impl Config {
         self.update = config.update || self.update;
         self.update_only = config.update_only || self.update_only;
         self.clear_data = config.clear_data || self.clear_data;
+        self.warm_standby = config.warm_standby || self.warm_standby;
+        self.supervised = config.supervised || self.supervised;
+        self.secure_delete = config.secure_delete || self.secure_delete;
+        self.batch_chunk_replication =
+            config.batch_chunk_replication || self.batch_chunk_replication;
 
         if let Some(socket_addr) = config.first {
             self.first = Some(socket_addr);
@@ -239,6 +404,118 @@ impl Config {
         if let Some(upnp_lease_duration) = config.upnp_lease_duration {
             self.network_config.upnp_lease_duration = Some(upnp_lease_duration);
         }
+
+        if let Some(min_write_acks) = config.min_write_acks {
+            self.min_write_acks = Some(min_write_acks);
+        }
+
+        if let Some(treasury_percentage) = config.treasury_percentage {
+            self.treasury_percentage = Some(treasury_percentage);
+        }
+
+        if let Some(min_reward_age) = config.min_reward_age {
+            self.min_reward_age = Some(min_reward_age);
+        }
+
+        if let Some(used_space_limits) = config.used_space_limits {
+            self.used_space_limits = Some(used_space_limits);
+        }
+
+        if let Some(economy) = config.economy {
+            self.economy = Some(economy);
+        }
+
+        if let Some(scrubbing) = config.scrubbing {
+            self.scrubbing = Some(scrubbing);
+        }
+
+        if let Some(chunk_write_buffer) = config.chunk_write_buffer {
+            self.chunk_write_buffer = Some(chunk_write_buffer);
+        }
+
+        if let Some(chunk_compression) = config.chunk_compression {
+            self.chunk_compression = Some(chunk_compression);
+        }
+
+        if let Some(max_chunk_size) = config.max_chunk_size {
+            self.max_chunk_size = Some(max_chunk_size);
+        }
+
+        if let Some(storage_policy) = config.storage_policy {
+            self.storage_policy = Some(storage_policy);
+        }
+
+        if let Some(chunk_read_cache) = config.chunk_read_cache {
+            self.chunk_read_cache = Some(chunk_read_cache);
+        }
+
+        if let Some(query_hedge_after_msec) = config.query_hedge_after_msec {
+            self.query_hedge_after_msec = Some(query_hedge_after_msec);
+        }
+
+        if let Some(interval) = config.storage_challenge_interval_msec {
+            self.storage_challenge_interval_msec = Some(interval);
+        }
+
+        if let Some(concurrency) = config.replication_concurrency {
+            self.replication_concurrency = Some(concurrency);
+        }
+
+        if let Some(holders) = config.relayed_read_holders {
+            self.relayed_read_holders = Some(holders);
+        }
+
+        if let Some(entries) = config.max_map_entries {
+            self.max_map_entries = Some(entries);
+        }
+
+        if let Some(appends) = config.max_sequence_appends_per_hour {
+            self.max_sequence_appends_per_hour = Some(appends);
+        }
+
+        if let Some(size) = config.max_entry_size {
+            self.max_entry_size = Some(size);
+        }
+
+        if let Some(shards) = config.erasure_data_shards {
+            self.erasure_data_shards = Some(shards);
+        }
+
+        if let Some(shards) = config.erasure_parity_shards {
+            self.erasure_parity_shards = Some(shards);
+        }
+
+        if let Some(egress_rate) = config.egress_rate {
+            self.egress_rate = Some(egress_rate);
+        }
+
+        if let Some(egress_client_rate) = config.egress_client_rate {
+            self.egress_client_rate = Some(egress_client_rate);
+        }
+
+        if let Some(client_msg_rate) = config.client_msg_rate {
+            self.client_msg_rate = Some(client_msg_rate);
+        }
+
+        if let Some(restarts) = config.restarts {
+            self.restarts = Some(restarts);
+        }
+
+        if let Some(genesis_key) = config.genesis_key {
+            self.genesis_key = Some(genesis_key);
+        }
+
+        if let Some(operator_key) = config.operator_key {
+            self.operator_key = Some(operator_key);
+        }
+
+        if let Some(operator_rpc_addr) = config.operator_rpc_addr {
+            self.operator_rpc_addr = Some(operator_rpc_addr);
+        }
+
+        if let Some(operator_tls_dir) = config.operator_tls_dir {
+            self.operator_tls_dir = Some(operator_tls_dir);
+        }
     }
 
     /// The address to be credited when this node farms SafeCoin.
@@ -256,6 +533,169 @@ impl Config {
         self.max_capacity.unwrap_or(DEFAULT_MAX_CAPACITY)
     }
 
+    /// Upper limits in bytes for the storage used by categories of data.
+    pub fn used_space_limits(&self) -> BTreeMap<UsageCategory, u64> {
+        self.used_space_limits.clone().unwrap_or_default()
+    }
+
+    /// The parameters of minting, rewards and store cost.
+    pub fn economy(&self) -> EconomyConfig {
+        self.economy.unwrap_or_default()
+    }
+
+    /// When to verify the stored chunks, if at all.
+    pub fn scrubbing(&self) -> Option<ScrubSchedule> {
+        self.scrubbing.clone()
+    }
+
+    /// Whether to keep a warm copy of the Elder state while next in line for promotion.
+    pub fn warm_standby(&self) -> bool {
+        self.warm_standby
+    }
+
+    /// Run the node as the child of a supervising process?
+    pub fn supervised(&self) -> bool {
+        self.supervised
+    }
+
+    /// Whether to overwrite removed chunks before unlinking them.
+    pub fn secure_delete(&self) -> bool {
+        self.secure_delete
+    }
+
+    /// Whether to send the chunks to replicate to each new holder in batches.
+    pub fn batch_chunk_replication(&self) -> bool {
+        self.batch_chunk_replication
+    }
+
+    /// Bytes buffered at most when writing a chunk to disk, if configured.
+    pub fn chunk_write_buffer(&self) -> Option<usize> {
+        self.chunk_write_buffer
+    }
+
+    /// How the chunks written to disk are compressed, if they are.
+    pub fn chunk_compression(&self) -> Option<ChunkCompression> {
+        self.chunk_compression
+    }
+
+    /// Bytes of a chunk at most accepted by the section, if configured.
+    pub fn max_chunk_size(&self) -> Option<u64> {
+        self.max_chunk_size
+    }
+
+    /// How much disk the chunks stored may use, if bounded by a policy.
+    pub fn storage_policy(&self) -> Option<StoragePolicy> {
+        self.storage_policy.clone()
+    }
+
+    /// Bytes of chunks read to keep in memory at most, if they are cached.
+    pub fn chunk_read_cache(&self) -> Option<u64> {
+        self.chunk_read_cache
+    }
+
+    /// Milliseconds after which forwarded queries are hedged, if they are.
+    pub fn query_hedge_after_msec(&self) -> Option<u64> {
+        self.query_hedge_after_msec
+    }
+
+    /// Milliseconds between the rounds of storage challenges, if they are issued.
+    pub fn storage_challenge_interval_msec(&self) -> Option<u64> {
+        self.storage_challenge_interval_msec
+    }
+
+    /// Chunks asked for at most at a time while replicating them, if configured.
+    pub fn replication_concurrency(&self) -> Option<usize> {
+        self.replication_concurrency
+    }
+
+    /// Holders queried at a time by the reads relayed, if reads are relayed.
+    pub fn relayed_read_holders(&self) -> Option<usize> {
+        self.relayed_read_holders
+    }
+
+    /// The quotas on the writes to each Sequence and Map, the defaults where not configured.
+    pub fn write_quotas(&self) -> WriteQuotas {
+        let defaults = WriteQuotas::default();
+        WriteQuotas {
+            max_map_entries: self.max_map_entries.unwrap_or(defaults.max_map_entries),
+            max_sequence_appends_per_hour: self
+                .max_sequence_appends_per_hour
+                .unwrap_or(defaults.max_sequence_appends_per_hour),
+            max_entry_size: self.max_entry_size.unwrap_or(defaults.max_entry_size),
+        }
+    }
+
+    /// The shards new chunks are erasure coded into, if they are.
+    pub fn erasure_coding(&self) -> Option<ErasureCoding> {
+        let data_shards = self.erasure_data_shards?;
+        Some(ErasureCoding {
+            data_shards,
+            parity_shards: self
+                .erasure_parity_shards
+                .unwrap_or(ErasureCoding::default().parity_shards),
+        })
+    }
+
+    /// Bytes per second of responses to clients, if they are to be shaped.
+    pub fn egress_rate(&self) -> Option<u64> {
+        self.egress_rate
+    }
+
+    /// Bytes per second of responses to any single client, if set.
+    pub fn egress_client_rate(&self) -> Option<u64> {
+        self.egress_client_rate
+    }
+
+    /// Msgs per second handled from any single client, if limited.
+    pub fn client_msg_rate(&self) -> Option<u32> {
+        self.client_msg_rate
+    }
+
+    /// Times restarted by the supervisor, if run by one.
+    pub fn restarts(&self) -> Option<u32> {
+        self.restarts
+    }
+
+    /// The trusted genesis key of the network to join.
+    pub fn genesis_key(&self) -> Option<&String> {
+        self.genesis_key.as_ref()
+    }
+
+    /// The key authorising operator commands.
+    pub fn operator_key(&self) -> Option<&String> {
+        self.operator_key.as_ref()
+    }
+
+    /// Address of the operator control socket, if it is to be opened.
+    pub fn operator_rpc_addr(&self) -> Option<SocketAddr> {
+        self.operator_rpc_addr
+    }
+
+    /// Directory of the operator control socket certificates.
+    pub fn operator_tls_dir(&self) -> Result<PathBuf> {
+        match &self.operator_tls_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(self.root_dir()?.join(DEFAULT_OPERATOR_TLS_DIR_NAME)),
+        }
+    }
+
+    /// Number of Elders to have applied a client write before it is acknowledged.
+    pub fn min_write_acks(&self) -> u8 {
+        self.min_write_acks.unwrap_or(DEFAULT_MIN_WRITE_ACKS)
+    }
+
+    /// Percentage of each churn payout retained in the section wallet.
+    pub fn treasury_percentage(&self) -> u8 {
+        self.treasury_percentage
+            .unwrap_or(DEFAULT_TREASURY_PERCENTAGE)
+            .min(100)
+    }
+
+    /// Age nodes are to have reached to take part in reward distribution.
+    pub fn min_reward_age(&self) -> u8 {
+        self.min_reward_age.unwrap_or(DEFAULT_MIN_REWARD_AGE)
+    }
+
     /// Root directory for `ChunkStore`s and cached state. If not set, it defaults to
     /// `DEFAULT_ROOT_DIR_NAME` within the project's data directory (see `Config::root_dir` for the
     /// directories on each platform).
@@ -324,7 +764,8 @@ impl Config {
 
     // Clear data from of a previous node running on the same PC
     fn clear_data_from_disk(&self) -> Result<()> {
-        if self.clear_data {
+        // the data of a node run by a supervisor is cleared by the supervisor, once
+        if self.clear_data && self.restarts.is_none() {
             let path = project_dirs()?.join(self.root_dir()?);
             if path.exists() {
                 std::fs::remove_dir_all(&path)?;
@@ -431,7 +872,7 @@ fn smoke() {
     // NOTE: IF this value is being changed due to a change in the config,
     // the change in config also be handled in Config::merge()
     // and in examples/config_handling.rs
-    let expected_size = 504;
+    let expected_size = 1000;
 
     assert_eq!(std::mem::size_of::<Config>(), expected_size);
 }
@@ -6,7 +6,11 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use sn_data_types::{Error as DtError, PublicKey};
+use crate::{
+    node::{Busy, DutyRefusal},
+    node_ops::DutyContext,
+};
+use sn_data_types::{BlobAddress, Error as DtError, PublicKey, Token};
 use sn_messaging::{client::Error as ErrorMessage, MessageId};
 use std::io;
 use thiserror::Error;
@@ -23,6 +27,12 @@ pub enum Error {
     /// Not enough space in `ChunkStore` to perform `put`.
     #[error("Not enough space")]
     NotEnoughSpace,
+    /// The network joined is not that of the trusted genesis key.
+    #[error("Untrusted network: {0}")]
+    UntrustedNetwork(String),
+    /// Calls to the routing layer keep failing.
+    #[error("Network degraded, not calling {0} until routing recovers")]
+    NetworkDegraded(String),
     /// Not Section PublicKey.
     #[error("Not section public key returned from routing")]
     NoSectionPublicKey,
@@ -62,6 +72,9 @@ pub enum Error {
     /// This node does not know or manage any section funds
     #[error("No section funds")]
     NoSectionFunds,
+    /// The section wallet does not cover a proposed payout.
+    #[error("Section funds do not cover the payout: {0}")]
+    PayoutNotCovered(String),
     /// Unable to process fund churn message.
     #[error("Cannot process fund churn message")]
     NotChurningFunds,
@@ -77,6 +90,45 @@ pub enum Error {
     /// Chunk already exists for this node
     #[error("Data already exists at this node")]
     DataExists,
+    /// Chunk with different content already exists at the address
+    #[error("Different data already exists at this node for {0:?}")]
+    DataConflict(BlobAddress),
+    /// Existing copy of a chunk which could not be read from our storage
+    #[error("Existing copy of chunk {0:?} cannot be read")]
+    UnreadableChunk(BlobAddress),
+    /// Chunk beyond the max chunk size of the section
+    #[error("Chunk of {size} bytes exceeds the max chunk size of {max} bytes")]
+    ChunkTooLarge {
+        /// Bytes of the chunk.
+        size: u64,
+        /// Bytes of a chunk at most accepted by the section.
+        max: u64,
+    },
+    /// Write of a Map beyond the entries it may hold.
+    #[error("Map of {entries} entries exceeds the max of {max} entries")]
+    MapEntriesExceeded {
+        /// Entries the Map would hold.
+        entries: usize,
+        /// Entries a Map holds at most.
+        max: usize,
+    },
+    /// Append to a Sequence beyond the appends allowed per hour.
+    #[error("Sequence appended to more than {max} times in the last hour")]
+    SequenceAppendsExceeded {
+        /// Appends to a Sequence at most per hour.
+        max: usize,
+    },
+    /// Erasure coding of chunks misconfigured, or shards not recovering a chunk.
+    #[error("Erasure coding: {0}")]
+    ErasureCoding(String),
+    /// Entry of a Sequence or Map beyond the max entry size.
+    #[error("Entry of {size} bytes exceeds the max entry size of {max} bytes")]
+    EntryTooLarge {
+        /// Bytes of the entry, key and value.
+        size: usize,
+        /// Bytes of an entry at most.
+        max: usize,
+    },
     /// I/O error.
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
@@ -113,6 +165,12 @@ pub enum Error {
     /// Transfer message is invalid.
     #[error("Signed transfer for Dot: '{0:?}' is not valid. Debit or credit are missing")]
     InvalidSignedTransfer(crdts::Dot<PublicKey>),
+    /// Debit spending credits still time-locked.
+    #[error("Debit spends time-locked credits, only {spendable} is spendable")]
+    FundsLocked {
+        /// The balance not time-locked.
+        spendable: Token,
+    },
     /// Transfer message is invalid.
     #[error("Propagated Credit Agreement proof is not valid. Proof received: {0:?}")]
     InvalidPropagatedTransfer(sn_data_types::CreditAgreementProof),
@@ -134,6 +192,69 @@ pub enum Error {
     /// Configuration error.
     #[error("Configuration error: {0}")]
     Configuration(String),
+    /// No data map by this name.
+    #[error("No such data map: {0}")]
+    NoSuchDataMap(XorName),
+    /// Operator command not authorised.
+    #[error("Operator command not authorised: {0}")]
+    OperatorAuthorisation(String),
+    /// Operator control socket error.
+    #[error("Operator control socket error: {0}")]
+    OperatorRpc(String),
+    /// Node is in maintenance mode.
+    #[error("Node is in maintenance mode")]
+    InMaintenance,
+    /// Export of section funds which cannot be imported.
+    #[error("Invalid export of section funds: {0}")]
+    InvalidFundsExport(String),
+    /// Dump of section metadata which cannot be imported.
+    #[error("Invalid dump of section metadata: {0}")]
+    InvalidMetadataDump(String),
+    /// Summary of a wallet history which cannot be imported.
+    #[error("Invalid summary of a wallet history: {0}")]
+    InvalidWalletSummary(String),
+    /// Client sending msgs beyond the rate allowed.
+    #[error("Client {0} exceeded the msg rate")]
+    ClientRateExceeded(PublicKey),
+    /// Client cmd refused, as the node is congested.
+    #[error("Node is busy, retry after {:?}", .0.retry_after)]
+    Busy(Busy),
+    /// Msg between nodes of a version this node does not support.
+    #[error("Unsupported version {0} of a msg between nodes")]
+    UnsupportedNodeMsgVersion(u8),
+    /// Duty triggered by a msg without the authority for it.
+    #[error("Duty refused, as {0}")]
+    DutyRefused(DutyRefusal),
+    /// The work of a background task panicked while handling the duty.
+    #[error("Duty {duty} panicked: {panic}")]
+    DutyPanicked {
+        /// The duty being handled.
+        duty: String,
+        /// The message of the panic.
+        panic: String,
+    },
+    /// Error when handling a duty, with the context of that duty.
+    #[error("{context} failed: {source}")]
+    Duty {
+        /// The duty that failed.
+        context: Box<DutyContext>,
+        /// The underlying error.
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Attaches the context of the duty being handled.
+    /// Errors already carrying a context are left as is.
+    pub fn with_context(self, context: DutyContext) -> Self {
+        match self {
+            error @ Error::Duty { .. } => error,
+            error => Error::Duty {
+                context: Box::new(context),
+                source: Box::new(error),
+            },
+        }
+    }
 }
 
 pub(crate) fn convert_to_error_message(error: Error) -> Result<sn_messaging::client::Error> {
@@ -142,12 +263,26 @@ pub(crate) fn convert_to_error_message(error: Error) -> Result<sn_messaging::cli
         Error::InvalidOwners(key) => Ok(ErrorMessage::InvalidOwners(key)),
         Error::InvalidSignedTransfer(_) => Ok(ErrorMessage::InvalidSignature),
         Error::TransferAlreadyRegistered => Ok(ErrorMessage::TransactionIdExists),
+        Error::FundsLocked { .. } => Ok(ErrorMessage::InsufficientBalance),
         Error::NoSuchChunk => Ok(ErrorMessage::NoSuchData),
+        Error::NoSuchDataMap(_) => Ok(ErrorMessage::NoSuchData),
         Error::NotEnoughSpace => Ok(ErrorMessage::NotEnoughSpace),
         Error::BalanceExists => Ok(ErrorMessage::BalanceExists),
         Error::TempDirCreationFailed(_) => Ok(ErrorMessage::FailedToWriteFile),
         Error::DataExists => Ok(ErrorMessage::DataExists),
+        // the conflict itself is told to the client by a `ClientMsg`
+        Error::DataConflict(_) => Ok(ErrorMessage::InvalidOperation),
+        Error::UnreadableChunk(_) => Ok(ErrorMessage::FailedToWriteFile),
+        Error::ChunkTooLarge { .. } => Ok(ErrorMessage::ExcessiveValue),
+        Error::MapEntriesExceeded { .. } => Ok(ErrorMessage::TooManyEntries),
+        Error::SequenceAppendsExceeded { .. } => Ok(ErrorMessage::TooManyEntries),
+        Error::EntryTooLarge { .. } => Ok(ErrorMessage::ExceededSize),
+        Error::InMaintenance => Ok(ErrorMessage::InvalidOperation),
+        Error::DutyRefused(_) => Ok(ErrorMessage::InvalidOperation),
+        Error::ClientRateExceeded(_) => Ok(ErrorMessage::InvalidOperation),
+        Error::Busy(_) => Ok(ErrorMessage::InvalidOperation),
         Error::NetworkData(error) => convert_dt_error_to_error_message(error),
+        Error::Duty { source, .. } => convert_to_error_message(*source),
         error => Err(Error::NoErrorMapping(error.to_string())),
     }
 }
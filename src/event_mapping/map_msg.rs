@@ -10,31 +10,94 @@ use std::thread::current;
 
 use super::{LazyError, Mapping, MsgContext};
 use crate::{
+    node_msg::{ClientCmd, ClientRequest, NodeMsg, NodeMsgBody},
     node_ops::{NodeDuties, NodeDuty},
     Error, Result,
 };
-use log::debug;
+use log::{debug, info, warn};
 use sn_messaging::{
     client::{
-        Cmd, Message, NodeCmd, NodeDataQueryResponse, NodeEvent, NodeQuery, NodeQueryResponse,
-        NodeRewardQuery, NodeSystemCmd, NodeSystemQuery, NodeSystemQueryResponse, NodeTransferCmd,
-        NodeTransferQuery, NodeTransferQueryResponse, Query, TransferCmd, TransferQuery,
+        BlobRead, BlobWrite, Cmd, DataCmd, DataQuery, Message, NodeCmd, NodeDataQueryResponse,
+        NodeEvent, NodeQuery, NodeQueryResponse, NodeRewardQuery, NodeSystemCmd, NodeSystemQuery,
+        NodeSystemQueryResponse, NodeTransferCmd, NodeTransferQuery, NodeTransferQueryResponse,
+        Query, TransferCmd, TransferQuery,
     },
-    DstLocation, EndUser, SrcLocation,
+    DstLocation, EndUser, MessageId, SrcLocation,
 };
 
+/// The duty of the client request sent as the query, if it is one,
+/// a `NoOp` if the request cannot be read.
+fn map_client_request(query: &DataQuery, id: MessageId, origin: EndUser) -> Option<NodeDuty> {
+    let request = match ClientRequest::from_query(query)? {
+        Ok(request) => request,
+        Err(error) => {
+            warn!("Unreadable client request {:?}: {:?}", id, error);
+            return Some(NodeDuty::NoOp);
+        }
+    };
+    Some(match request {
+        ClientRequest::Existence(query) => NodeDuty::ProcessExistenceQuery { query, id, origin },
+        ClientRequest::ChunkRange { address, range } => NodeDuty::ProcessRangedRead {
+            address,
+            range,
+            id,
+            origin,
+        },
+        ClientRequest::ChunkRepair(addresses) => NodeDuty::ProcessChunkRepair {
+            addresses,
+            id,
+            origin,
+        },
+        ClientRequest::DataMap(query) => NodeDuty::ProcessDataMapRead { query, id, origin },
+        ClientRequest::ReadBatch(queries) => NodeDuty::ProcessReadBatch {
+            queries,
+            id,
+            origin,
+        },
+        ClientRequest::TransferStatus(query) => NodeDuty::GetTransferById {
+            query,
+            msg_id: id,
+            origin,
+        },
+    })
+}
+
+/// The duty of the client cmd sent as the write, if it is one,
+/// a `NoOp` if the cmd cannot be read.
+fn map_client_cmd(cmd: &DataCmd, id: MessageId, origin: EndUser) -> Option<NodeDuty> {
+    let client_cmd = match ClientCmd::from_cmd(cmd)? {
+        Ok(client_cmd) => client_cmd,
+        Err(error) => {
+            warn!("Unreadable client cmd {:?}: {:?}", id, error);
+            return Some(NodeDuty::NoOp);
+        }
+    };
+    Some(match client_cmd {
+        ClientCmd::DataMap(cmd) => NodeDuty::ProcessDataMapWrite { cmd, id, origin },
+    })
+}
+
 pub fn match_user_sent_msg(msg: Message, dst: DstLocation, origin: EndUser) -> Mapping {
     match msg.to_owned() {
         Message::Query {
             query: Query::Data(query),
             id,
             ..
-        } => Mapping::Ok {
-            op: NodeDuty::ProcessRead { query, id, origin },
-            ctx: Some(super::MsgContext::Msg {
-                msg,
-                src: SrcLocation::EndUser(origin),
+        } => match map_client_request(&query, id, origin) {
+            Some(NodeDuty::NoOp) => Mapping::Error(LazyError {
+                error: Error::InvalidMessage(id, format!("Unreadable client request: {:?}", msg)),
+                msg: MsgContext::Msg {
+                    msg,
+                    src: SrcLocation::EndUser(origin),
+                },
             }),
+            op => Mapping::Ok {
+                op: op.unwrap_or(NodeDuty::ProcessRead { query, id, origin }),
+                ctx: Some(MsgContext::Msg {
+                    msg,
+                    src: SrcLocation::EndUser(origin),
+                }),
+            },
         },
         Message::Cmd {
             cmd: Cmd::Data { .. },
@@ -161,6 +224,154 @@ pub fn map_node_msg(msg: Message, src: SrcLocation, dst: DstLocation) -> Mapping
     }
 }
 
+/// Maps a msg between nodes, for us, to the duty of handling it.
+pub fn map_msg_between_nodes(msg: NodeMsg, src: SrcLocation) -> Mapping {
+    let origin = src.name();
+    let op = match msg.body.clone() {
+        NodeMsgBody::MetadataRangeQuery(query) => NodeDuty::ProcessMetadataExport {
+            query,
+            id: msg.id,
+            origin,
+        },
+        NodeMsgBody::MetadataPage { query, page } => NodeDuty::ImportMetadataRange {
+            query,
+            page,
+            elder: origin,
+        },
+        NodeMsgBody::OrphanOffer(offer) => NodeDuty::AdoptOrphanChunk {
+            offer,
+            holder: origin,
+        },
+        NodeMsgBody::OrphanRelease(release) => NodeDuty::ReleaseOrphanChunk {
+            release,
+            elder: origin,
+        },
+        NodeMsgBody::ReplicationFailed(failed) => NodeDuty::VerifyReplicatedChunk {
+            failed,
+            new_holder: origin,
+        },
+        NodeMsgBody::ImportedChunks(imported) => NodeDuty::RegisterImportedChunks {
+            imported,
+            holder: origin,
+        },
+        NodeMsgBody::ChunkReadFailed(address) => NodeDuty::RepairChunkHolder {
+            address,
+            holder: origin,
+        },
+        NodeMsgBody::ChunkDeleted { address, proof } => NodeDuty::RecordChunkDeleted {
+            address,
+            holder: origin,
+            proof,
+        },
+        NodeMsgBody::StorageChallenge(challenge) => NodeDuty::AnswerStorageChallenge {
+            challenge,
+            id: msg.id,
+            elder: origin,
+        },
+        NodeMsgBody::StorageProof(proof) => NodeDuty::VerifyStorageProof {
+            proof,
+            holder: origin,
+        },
+        NodeMsgBody::PeerPing(ping) => NodeDuty::AnswerPing { ping, peer: origin },
+        NodeMsgBody::PeerPong(pong) => NodeDuty::RecordPong { pong, peer: origin },
+        NodeMsgBody::CapacityReport(report) => NodeDuty::RecordCapacity {
+            report,
+            adult: origin,
+        },
+        NodeMsgBody::CatchUpQuery(query) => NodeDuty::AnswerMetadataCatchUp {
+            query,
+            elder: origin,
+        },
+        NodeMsgBody::SignedCatchUpPage(page) => NodeDuty::CatchUpOnMetadata {
+            page,
+            elder: origin,
+        },
+        NodeMsgBody::ChunkAccessQuery(query) => NodeDuty::ReportChunkAccess {
+            query,
+            elder: origin,
+        },
+        NodeMsgBody::ChunkAccessReport(report) => NodeDuty::RecordChunkAccess {
+            report,
+            holder: origin,
+        },
+        NodeMsgBody::RelayedRead(read) => NodeDuty::ReadChunkForElder {
+            read,
+            elder: origin,
+        },
+        NodeMsgBody::RelayedChunk(answer) => NodeDuty::RelayChunk {
+            answer,
+            holder: origin,
+        },
+        NodeMsgBody::RangedChunkRead(read) => NodeDuty::ReadChunk {
+            read: BlobRead::Get(read.address),
+            range: Some(read.range),
+            msg_id: read.msg_id,
+            origin: read.origin,
+        },
+        NodeMsgBody::SignedMetadataDump(dump) => NodeDuty::ImportSectionMetadata {
+            dump,
+            elder: origin,
+        },
+        // the Elders batching the chunks all send it under the id derived from it
+        NodeMsgBody::ReplicationBatch(batch) if msg.is_derived() => NodeDuty::ReplicateChunks {
+            batch,
+            elder: origin,
+        },
+        NodeMsgBody::ReplicationBatch(_) => {
+            warn!(
+                "Chunks batched for replication by {} under another id",
+                origin
+            );
+            NodeDuty::NoOp
+        }
+        NodeMsgBody::SignedFundsExport(export) => NodeDuty::ImportSectionFunds {
+            export,
+            elder: origin,
+        },
+        NodeMsgBody::PaymentHandover(handover) => NodeDuty::TakeOverPayments {
+            handover,
+            elder: origin,
+        },
+        NodeMsgBody::MaxChunkSize(max_chunk_size) => NodeDuty::SetMaxChunkSize {
+            max_chunk_size,
+            elder: origin,
+        },
+        NodeMsgBody::SectionParams(params) => NodeDuty::SetSectionParams {
+            params,
+            elder: origin,
+        },
+        NodeMsgBody::WriteAck(ack) => NodeDuty::RecordWriteAck {
+            id: ack.id,
+            origin: ack.origin,
+            elder: origin,
+        },
+        NodeMsgBody::WriteAckLevel(selected) => NodeDuty::SetWriteAckLevel {
+            id: selected.id,
+            origin: selected.origin,
+            level: selected.level,
+            elder: origin,
+        },
+        NodeMsgBody::StatePushAck(ack) => NodeDuty::ReceiveStatePushAck { ack, elder: origin },
+        NodeMsgBody::WalletSummaryPush(push) => NodeDuty::ReceiveWalletSummaries {
+            push,
+            elder: origin,
+        },
+        NodeMsgBody::RewardStatusQuery => NodeDuty::ProcessRewardStatusQuery { id: msg.id, origin },
+        // no Elder queries for the progress yet, as the operator reads it from the node
+        NodeMsgBody::RewardStatus { query, status } => {
+            info!(
+                "Reward process of {} at {:?}, answering {:?}",
+                origin, status.stage, query
+            );
+            NodeDuty::NoOp
+        }
+    };
+    Mapping::Ok {
+        op,
+        ctx: Some(MsgContext::Node { msg, src }),
+    }
+}
+
 fn match_or_err(msg: Message, src: SrcLocation) -> Mapping {
     match match_section_msg(msg.clone(), src) {
         NodeDuty::NoOp => match match_node_msg(msg.clone(), src) {
@@ -200,10 +411,13 @@ fn match_section_msg(msg: Message, origin: SrcLocation) -> NodeDuty {
                     node_rewards,
                     user_wallets,
                 }),
+            id,
             ..
         } => NodeDuty::SynchState {
             node_rewards: node_rewards.to_owned(),
             user_wallets: user_wallets.to_owned(),
+            msg_id: *id,
+            origin,
         },
         Message::NodeCmd {
             cmd: NodeCmd::System(NodeSystemCmd::ProposeRewardPayout(proposal)),
@@ -239,20 +453,20 @@ fn match_section_msg(msg: Message, origin: SrcLocation) -> NodeDuty {
             query: NodeQuery::Metadata { query, origin },
             id,
             ..
-        } => NodeDuty::ProcessRead {
+        } => map_client_request(query, *id, *origin).unwrap_or_else(|| NodeDuty::ProcessRead {
             query: query.clone(),
             id: *id,
             origin: *origin,
-        },
+        }),
         Message::NodeCmd {
             cmd: NodeCmd::Metadata { cmd, origin },
             id,
             ..
-        } => NodeDuty::ProcessWrite {
+        } => map_client_cmd(cmd, *id, *origin).unwrap_or_else(|| NodeDuty::ProcessWrite {
             cmd: cmd.clone(),
             id: *id,
             origin: *origin,
-        },
+        }),
         //
         // ------ adult ------
         Message::NodeQuery {
@@ -261,9 +475,40 @@ fn match_section_msg(msg: Message, origin: SrcLocation) -> NodeDuty {
             ..
         } => NodeDuty::ReadChunk {
             read: query.clone(),
+            // not carried by the msg yet
+            range: None,
             msg_id: *id,
             origin: *origin,
         },
+        // deletes by the section are by section policy, rather than by the owner
+        Message::NodeCmd {
+            cmd:
+                NodeCmd::Chunks {
+                    cmd: BlobWrite::DeletePrivate(address),
+                    ..
+                },
+            id,
+            ..
+        } if matches!(origin, SrcLocation::Section(_)) => NodeDuty::DeleteChunkBySection {
+            address: *address,
+            msg_id: *id,
+            section: origin,
+        },
+        // while deletes by a client are sent by the Elders holding the metadata of the chunk
+        Message::NodeCmd {
+            cmd:
+                NodeCmd::Chunks {
+                    cmd: BlobWrite::DeletePrivate(address),
+                    origin: end_user,
+                },
+            id,
+            ..
+        } => NodeDuty::DeleteChunk {
+            address: *address,
+            msg_id: *id,
+            origin: *end_user,
+            elder: origin.name(),
+        },
         Message::NodeCmd {
             cmd: NodeCmd::Chunks { cmd, origin },
             id,
@@ -337,6 +582,7 @@ fn match_node_msg(msg: Message, origin: SrcLocation) -> NodeDuty {
                 NodeDuty::StoreChunkForReplication {
                     data: data.clone(),
                     correlation_id: *correlation_id,
+                    holder: origin.name(),
                 }
             } else {
                 log::warn!("Got error when reading chunk for replication: {:?}", result);
@@ -360,6 +606,8 @@ fn match_node_msg(msg: Message, origin: SrcLocation) -> NodeDuty {
             ..
         } => NodeDuty::ReadChunk {
             read: query.clone(),
+            // not carried by the msg yet
+            range: None,
             msg_id: *id,
             origin: *origin,
         },
@@ -375,3 +623,350 @@ fn match_node_msg(msg: Message, origin: SrcLocation) -> NodeDuty {
         _ => NodeDuty::NoOp,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chunks::{ChunkRange, RangedChunkRead},
+        metadata::{AckLevel, DataMapCmd, DataMapQuery, ExistenceQuery, WriteAck, WriteAckLevel},
+        node::SectionParams,
+        transfers::{TransferId, TransferStatusQuery},
+    };
+    use bls::SecretKey;
+    use sn_data_types::{BlobAddress, PublicKey};
+    use xor_name::XorName;
+
+    fn map(body: NodeMsgBody, elder: XorName) -> NodeDuty {
+        match map_msg_between_nodes(NodeMsg::new(body), SrcLocation::Node(elder)) {
+            Mapping::Ok { op, .. } => op,
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+    }
+
+    #[test]
+    fn write_acks_are_recorded_as_of_the_elder_sending_them() {
+        let elder = XorName::random();
+        let ack = WriteAck {
+            id: MessageId::new(),
+            origin: EndUser::AllClients(PublicKey::from(SecretKey::random().public_key())),
+        };
+        match map(NodeMsgBody::WriteAck(ack), elder) {
+            NodeDuty::RecordWriteAck {
+                id,
+                origin,
+                elder: acking,
+            } => {
+                assert_eq!(id, ack.id);
+                assert_eq!(origin, ack.origin);
+                assert_eq!(acking, elder);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    #[test]
+    fn ack_levels_are_set_as_told_by_the_elder() {
+        let elder = XorName::random();
+        let selected = WriteAckLevel {
+            id: MessageId::new(),
+            origin: EndUser::AllClients(PublicKey::from(SecretKey::random().public_key())),
+            level: AckLevel::Replicated,
+        };
+        match map(NodeMsgBody::WriteAckLevel(selected), elder) {
+            NodeDuty::SetWriteAckLevel {
+                id,
+                origin,
+                level,
+                elder: telling,
+            } => {
+                assert_eq!(id, selected.id);
+                assert_eq!(origin, selected.origin);
+                assert_eq!(level, AckLevel::Replicated);
+                assert_eq!(telling, elder);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    #[test]
+    fn section_params_are_set_as_pushed_by_the_elder() {
+        let elder = XorName::random();
+        let params = SectionParams {
+            min_write_acks: 3,
+            treasury_percentage: 10,
+            min_reward_age: 4,
+            economy: crate::EconomyConfig {
+                mint_percentage: 50,
+                ..Default::default()
+            },
+        };
+        match map(NodeMsgBody::SectionParams(params), elder) {
+            NodeDuty::SetSectionParams {
+                params: pushed,
+                elder: pushing,
+            } => {
+                assert_eq!(pushed, params);
+                assert_eq!(pushing, elder);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    #[test]
+    fn client_requests_are_mapped_when_sent_by_the_client_and_when_forwarded() -> Result<()> {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let query = ExistenceQuery {
+            address: BlobAddress::Public(XorName::random()),
+            timestamp: 1_600_000_000,
+        };
+        let id = MessageId::new();
+        let data_query = ClientRequest::Existence(query.clone()).query()?;
+        let expect_existence_query = |duty| match duty {
+            NodeDuty::ProcessExistenceQuery {
+                query: queried,
+                id: queried_id,
+                origin: querying,
+            } => {
+                assert_eq!(queried, query);
+                assert_eq!(queried_id, id);
+                assert_eq!(querying, origin);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        };
+
+        let sent = Message::Query {
+            query: Query::Data(data_query.clone()),
+            id,
+            target_section_pk: None,
+        };
+        match match_user_sent_msg(sent, DstLocation::Section(XorName::random()), origin) {
+            Mapping::Ok { op, .. } => expect_existence_query(op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+
+        let forwarded = Message::NodeQuery {
+            query: NodeQuery::Metadata {
+                query: data_query,
+                origin,
+            },
+            id,
+            target_section_pk: None,
+        };
+        match match_or_err(forwarded, SrcLocation::Node(XorName::random())) {
+            Mapping::Ok { op, .. } => expect_existence_query(op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn data_maps_are_read_as_queried_and_written_as_paid_for() -> Result<()> {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let name = XorName::random();
+        let id = MessageId::new();
+        let query = DataMapQuery::ListVersions(name);
+        let sent = Message::Query {
+            query: Query::Data(ClientRequest::DataMap(query.clone()).query()?),
+            id,
+            target_section_pk: None,
+        };
+        match match_user_sent_msg(sent, DstLocation::Section(name), origin) {
+            Mapping::Ok {
+                op:
+                    NodeDuty::ProcessDataMapRead {
+                        query: queried,
+                        id: queried_id,
+                        origin: querying,
+                    },
+                ..
+            } => {
+                assert_eq!(queried, query);
+                assert_eq!(queried_id, id);
+                assert_eq!(querying, origin);
+            }
+            Mapping::Ok { op, .. } => panic!("Unexpected duty: {:?}", op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+
+        // the write is forwarded by the Elders of the payment, once paid for
+        let cmd = DataMapCmd::Put {
+            name,
+            data_map: vec![1, 2, 3],
+            expected_version: Some(0),
+        };
+        let forwarded = Message::NodeCmd {
+            cmd: NodeCmd::Metadata {
+                cmd: ClientCmd::DataMap(cmd.clone()).cmd()?,
+                origin,
+            },
+            id,
+            target_section_pk: None,
+        };
+        match match_or_err(forwarded, SrcLocation::Node(XorName::random())) {
+            Mapping::Ok {
+                op:
+                    NodeDuty::ProcessDataMapWrite {
+                        cmd: written,
+                        id: written_id,
+                        origin: writing,
+                    },
+                ..
+            } => {
+                assert_eq!(written, cmd);
+                assert_eq!(written_id, id);
+                assert_eq!(writing, origin);
+            }
+            Mapping::Ok { op, .. } => panic!("Unexpected duty: {:?}", op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_batches_are_processed_as_sent_by_the_client() -> Result<()> {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let queries = vec![
+            DataQuery::Blob(BlobRead::Get(BlobAddress::Public(XorName::random()))),
+            DataQuery::Blob(BlobRead::Get(BlobAddress::Private(XorName::random()))),
+        ];
+        let id = MessageId::new();
+        let request = ClientRequest::ReadBatch(queries.clone());
+        assert_eq!(request.name(), queries[0].dst_address());
+        let sent = Message::Query {
+            query: Query::Data(request.query()?),
+            id,
+            target_section_pk: None,
+        };
+        match match_user_sent_msg(sent, DstLocation::Section(request.name()), origin) {
+            Mapping::Ok {
+                op:
+                    NodeDuty::ProcessReadBatch {
+                        queries: read,
+                        id: read_id,
+                        origin: reading,
+                    },
+                ..
+            } => {
+                assert_eq!(read, queries);
+                assert_eq!(read_id, id);
+                assert_eq!(reading, origin);
+            }
+            Mapping::Ok { op, .. } => panic!("Unexpected duty: {:?}", op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_status_queries_are_answered_to_the_client() -> Result<()> {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let wallet = PublicKey::from(SecretKey::random().public_key());
+        let query = TransferStatusQuery {
+            wallet,
+            id: TransferId::Debit(crdts::Dot::new(wallet, 1)),
+        };
+        let id = MessageId::new();
+        let sent = Message::Query {
+            query: Query::Data(ClientRequest::TransferStatus(query).query()?),
+            id,
+            target_section_pk: None,
+        };
+        match match_user_sent_msg(sent, DstLocation::Section(XorName::from(wallet)), origin) {
+            Mapping::Ok {
+                op:
+                    NodeDuty::GetTransferById {
+                        query: queried,
+                        msg_id,
+                        origin: querying,
+                    },
+                ..
+            } => {
+                assert_eq!(queried, query);
+                assert_eq!(msg_id, id);
+                assert_eq!(querying, origin);
+            }
+            Mapping::Ok { op, .. } => panic!("Unexpected duty: {:?}", op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn chunk_repairs_are_processed_as_requested_by_the_owner() -> Result<()> {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let addresses = vec![
+            BlobAddress::Private(XorName::random()),
+            BlobAddress::Public(XorName::random()),
+        ];
+        let id = MessageId::new();
+        let request = ClientRequest::ChunkRepair(addresses.clone());
+        assert_eq!(request.name(), *addresses[0].name());
+        let sent = Message::Query {
+            query: Query::Data(request.query()?),
+            id,
+            target_section_pk: None,
+        };
+        match match_user_sent_msg(sent, DstLocation::Section(request.name()), origin) {
+            Mapping::Ok {
+                op:
+                    NodeDuty::ProcessChunkRepair {
+                        addresses: requested,
+                        id: requested_id,
+                        origin: requesting,
+                    },
+                ..
+            } => {
+                assert_eq!(requested, addresses);
+                assert_eq!(requested_id, id);
+                assert_eq!(requesting, origin);
+            }
+            Mapping::Ok { op, .. } => panic!("Unexpected duty: {:?}", op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ranged_reads_are_read_by_the_holders_as_sent_by_the_elder() -> Result<()> {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let address = BlobAddress::Public(XorName::random());
+        let range = ChunkRange { offset: 2, len: 3 };
+        let id = MessageId::new();
+        let sent = Message::Query {
+            query: Query::Data(ClientRequest::ChunkRange { address, range }.query()?),
+            id,
+            target_section_pk: None,
+        };
+        match match_user_sent_msg(sent, DstLocation::Section(*address.name()), origin) {
+            Mapping::Ok {
+                op: NodeDuty::ProcessRangedRead { range: read, .. },
+                ..
+            } => assert_eq!(read, range),
+            Mapping::Ok { op, .. } => panic!("Unexpected duty: {:?}", op),
+            Mapping::Error(error) => panic!("Unmapped msg: {:?}", error.error),
+        }
+
+        let read = RangedChunkRead {
+            address,
+            range,
+            msg_id: id,
+            origin,
+        };
+        match map(NodeMsgBody::RangedChunkRead(read), XorName::random()) {
+            NodeDuty::ReadChunk {
+                read: BlobRead::Get(read),
+                range: Some(read_range),
+                msg_id,
+                origin: reading,
+            } => {
+                assert_eq!(read, address);
+                assert_eq!(read_range, range);
+                assert_eq!(msg_id, id);
+                assert_eq!(reading, origin);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+        Ok(())
+    }
+}
@@ -7,18 +7,20 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 mod map_msg;
+mod unknown_msg;
 
 use super::node_ops::{NodeDuties, NodeDuty};
-use crate::{Network, Result};
+use crate::{node_msg::NodeMsg, Network, Result};
 use hex_fmt::HexFmt;
-use log::{debug, info, trace};
-use map_msg::{map_node_msg, match_user_sent_msg};
+use log::{debug, info, trace, warn};
+use map_msg::{map_msg_between_nodes, map_node_msg, match_user_sent_msg};
 use sn_data_types::PublicKey;
 use sn_messaging::{client::Message, DstLocation, SrcLocation};
 use sn_routing::{Event as RoutingEvent, EventStream, NodeElderChange, MIN_AGE};
 use sn_routing::{Prefix, XorName, ELDER_SIZE as GENESIS_ELDER_COUNT};
 use std::collections::HashSet;
 use std::{thread::sleep, time::Duration};
+pub use unknown_msg::{UnsupportedMessage, UNSUPPORTED_MESSAGE};
 
 #[derive(Debug)]
 pub enum Mapping {
@@ -33,6 +35,7 @@ pub enum Mapping {
 pub enum MsgContext {
     Msg { msg: Message, src: SrcLocation },
     Bytes { msg: bytes::Bytes, src: SrcLocation },
+    Node { msg: NodeMsg, src: SrcLocation },
 }
 
 #[derive(Debug)]
@@ -48,17 +51,42 @@ pub async fn map_routing_event(event: RoutingEvent, network_api: &Network) -> Ma
         RoutingEvent::MessageReceived {
             content, src, dst, ..
         } => {
-            let msg = match Message::from(content.clone()) {
-                Ok(msg) => msg,
-                Err(error) => {
-                    return Mapping::Error(LazyError {
-                        msg: MsgContext::Bytes { msg: content, src },
-                        error: crate::Error::Message(error),
-                    })
+            let error = match NodeMsg::from_content(&content) {
+                // passed on as is when we are only relaying it
+                Some(Ok(msg)) if !is_for_us(&dst, network_api).await => {
+                    return Mapping::Ok {
+                        op: NodeDuty::ForwardUnknownMsg {
+                            bytes: content.clone(),
+                            dst,
+                        },
+                        ctx: Some(MsgContext::Node { msg, src }),
+                    }
                 }
+                Some(Ok(msg)) => return map_msg_between_nodes(msg, src),
+                Some(Err(error)) => error,
+                None => match Message::from(content.clone()) {
+                    Ok(msg) => return map_node_msg(msg, src, dst),
+                    Err(error) => error.into(),
+                },
             };
-
-            map_node_msg(msg, src, dst)
+            // e.g. a msg of a variant only newer nodes know of, which is
+            // passed on when we are only relaying it, or else answered
+            warn!("Could not parse msg from {:?}: {:?}", src, error);
+            let op = if is_for_us(&dst, network_api).await {
+                NodeDuty::RejectUnsupportedMsg {
+                    msg: UnsupportedMessage::of(&content),
+                    src,
+                }
+            } else {
+                NodeDuty::ForwardUnknownMsg {
+                    bytes: content.clone(),
+                    dst,
+                }
+            };
+            Mapping::Ok {
+                op,
+                ctx: Some(MsgContext::Bytes { msg: content, src }),
+            }
         }
         RoutingEvent::ClientMessageReceived { msg, user } => match_user_sent_msg(
             *msg.clone(),
@@ -83,8 +111,9 @@ pub async fn map_routing_event(event: RoutingEvent, network_api: &Network) -> Ma
             match self_status_change {
                 NodeElderChange::None => {
                     if !network_api.is_elder().await {
+                        // as an Adult, the chunks no longer ours after a split are collected
                         return Mapping::Ok {
-                            op: NodeDuty::NoOp,
+                            op: NodeDuty::CollectOrphanChunks { our_prefix: prefix },
                             ctx: None,
                         };
                     }
@@ -212,3 +241,13 @@ pub async fn map_routing_event(event: RoutingEvent, network_api: &Network) -> Ma
         },
     }
 }
+
+// Whether the msg is for us, rather than for us to relay on.
+async fn is_for_us(dst: &DstLocation, network_api: &Network) -> bool {
+    match dst {
+        DstLocation::Node(name) => *name == network_api.our_name().await,
+        DstLocation::Section(name) => network_api.our_prefix().await.matches(name),
+        DstLocation::EndUser(_) => false,
+        DstLocation::Direct => true,
+    }
+}
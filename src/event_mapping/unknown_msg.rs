@@ -0,0 +1,173 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{node_ops::OutgoingMsg, Result};
+use serde::{Deserialize, Serialize};
+use sn_messaging::{
+    client::{CmdError, Error as ErrorMessage, Message},
+    Aggregation, MessageId, SrcLocation,
+};
+use std::{collections::BTreeMap, convert::TryInto};
+use xor_name::XorName;
+
+/// Prefix of the error responding to a msg we don't support, followed by the
+/// `UnsupportedMessage` as JSON, as there is no dedicated error for it yet.
+pub const UNSUPPORTED_MESSAGE: &str = "unsupported-message:";
+
+// Bytes of the header size, at the front of a wire msg.
+const HEADER_SIZE_BYTES_LEN: usize = 2;
+
+/// Response to a msg this version of the node cannot parse, e.g. of a variant
+/// only newer nodes know of, so that the sender can tell it apart from a failure.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsupportedMessage {
+    /// Id of the msg, or the hash of its bytes, if its id could not be read.
+    pub msg_id: MessageId,
+    /// Index of the variant of the msg, if it could be read.
+    pub variant: Option<u32>,
+    /// Version of the node responding.
+    pub node_version: String,
+}
+
+impl UnsupportedMessage {
+    /// Reads what can be read of the msg from its bytes.
+    pub(crate) fn of(bytes: &[u8]) -> Self {
+        let (variant, id) = match envelope(bytes) {
+            Some((variant, id)) => (Some(variant), id),
+            None => (None, None),
+        };
+        Self {
+            msg_id: id.unwrap_or_else(|| MessageId(XorName::from_content(&[bytes]))),
+            variant,
+            node_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Reads it back from the error responding to the msg, if it is one.
+    pub fn from_error(error: &ErrorMessage) -> Option<Self> {
+        match error {
+            ErrorMessage::Serialization(text) => {
+                serde_json::from_str(text.strip_prefix(UNSUPPORTED_MESSAGE)?).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// The response to the sender of the msg.
+    pub(crate) fn response(&self, src: SrcLocation) -> Result<OutgoingMsg> {
+        let error = ErrorMessage::Serialization(format!(
+            "{}{}",
+            UNSUPPORTED_MESSAGE,
+            serde_json::to_string(self)?
+        ));
+        Ok(OutgoingMsg {
+            msg: Message::CmdError {
+                error: CmdError::Data(error),
+                id: MessageId::in_response_to(&self.msg_id),
+                correlation_id: self.msg_id,
+                target_section_pk: None,
+            },
+            section_source: false, // sent as single node
+            dst: src.to_dst(),
+            aggregation: Aggregation::None,
+        })
+    }
+}
+
+// The fields of any msg variant, of which only the id is read.
+#[derive(Deserialize)]
+struct Envelope {
+    id: Option<MessageId>,
+}
+
+// Reads the variant and id of the msg from its payload, which is the msg serialised as
+// Msgpack with named fields, as a map of the variant index to them, e.g. `{0: {"cmd": ..,
+// "id": .., ..}}`, so they can be read without knowing the variant, nor what else it holds.
+fn envelope(bytes: &[u8]) -> Option<(u32, Option<MessageId>)> {
+    let header_size = bytes.get(..HEADER_SIZE_BYTES_LEN)?.try_into().ok()?;
+    let payload = bytes.get(u16::from_be_bytes(header_size) as usize..)?;
+    let envelope: BTreeMap<u32, Envelope> = rmp_serde::from_slice(payload).ok()?;
+    envelope
+        .into_iter()
+        .next()
+        .map(|(variant, envelope)| (variant, envelope.id))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_messaging::client::{NodeQuery, NodeSystemQuery};
+    use sn_messaging::DstLocation;
+
+    // The fields of a msg variant only newer nodes know of.
+    #[derive(Serialize)]
+    struct NewVariant {
+        payload: Vec<u8>,
+        id: MessageId,
+    }
+
+    // Beyond the variants we know of.
+    const NEW_VARIANT: u32 = 42;
+
+    #[test]
+    fn variant_and_id_of_unknown_msgs_are_read() -> Result<()> {
+        let known = Message::NodeQuery {
+            query: NodeQuery::System(NodeSystemQuery::GetSectionElders),
+            id: MessageId::new(),
+            target_section_pk: None,
+        };
+        let bytes = known.serialize()?;
+        let header_size = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+
+        let id = MessageId::new();
+        let newer: BTreeMap<_, _> = vec![(
+            NEW_VARIANT,
+            NewVariant {
+                payload: vec![1, 2, 3],
+                id,
+            },
+        )]
+        .into_iter()
+        .collect();
+        // serialised as the variant would be
+        let mut unknown = bytes[..header_size].to_vec();
+        unknown.extend(rmp_serde::to_vec_named(&newer).expect("serialisable"));
+        assert!(Message::from(unknown.clone().into()).is_err());
+
+        let unsupported = UnsupportedMessage::of(&unknown);
+        assert_eq!(unsupported.msg_id, id);
+        assert_eq!(unsupported.variant, Some(NEW_VARIANT));
+
+        let origin = XorName::random();
+        let response = unsupported.response(SrcLocation::Node(origin))?;
+        assert_eq!(response.dst, DstLocation::Node(origin));
+        match response.msg {
+            Message::CmdError {
+                error: CmdError::Data(error),
+                correlation_id,
+                ..
+            } => {
+                assert_eq!(correlation_id, id);
+                assert_eq!(UnsupportedMessage::from_error(&error), Some(unsupported));
+            }
+            msg => panic!("Unexpected response: {:?}", msg),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unreadable_msgs_are_identified_by_their_hash() {
+        let garbage = [0u8, 5, 0, 1, 2, 42, 42];
+        let unsupported = UnsupportedMessage::of(&garbage);
+        assert_eq!(unsupported.variant, None);
+        assert_eq!(
+            unsupported.msg_id,
+            MessageId(XorName::from_content(&[&garbage]))
+        );
+    }
+}
@@ -40,6 +40,10 @@ mod event_mapping;
 mod metadata;
 mod network;
 mod node;
+mod node_msg;
+mod persistence;
+#[cfg(test)]
+mod schema;
 mod section_funds;
 mod to_db_key;
 mod transfers;
@@ -50,12 +54,44 @@ pub mod state_db;
 
 pub mod utils;
 
+/// Commands by the node operator
+pub mod operator;
+
 pub(crate) use to_db_key::ToDbKey;
 
+#[cfg(feature = "replication-events")]
+pub use crate::chunks::{ReplicationEvent, ReplicationObserver};
 pub use crate::{
+    capacity::{ChunkSizeAdvice, EconomyConfig, SectionFullness, StoreCostQuote},
+    chunk_store::{ChunkCompression, UsageCategory},
+    chunks::{
+        Capability, ChunkAccess, ChunkImportReport, ChunkRange, ChunkStore, Eviction,
+        ReadCacheStats, ScrubSchedule, ScrubWindow, StoragePolicy,
+    },
     config_handler::{add_connection_info, set_connection_info, Config},
     error::{Error, Result},
-    network::Network,
+    event_mapping::{UnsupportedMessage, UNSUPPORTED_MESSAGE},
+    metadata::{
+        Access, AckLevel, AdultLiveness, CapacityHistogram, CapacityReport, ChunkAccessQuery,
+        ChunkAccessReport, ChunkDeletion, ChunkDeletionPolicy, ChunkRepair, ConflictStrategy,
+        DataKind, DataMapCmd, DataMapQuery, ExistenceAttestation, ExistenceQuery,
+        ExistenceStatement, HotData, HotObject, MapMerge, RepairReport, WriteQuotas, ACK_LEVEL,
+        CAPACITY_HISTOGRAM_BUCKET, CONFLICT_STRATEGY_KEY, HOT_DATA_WINDOW, READERS_KEY,
+    },
+    network::{Network, PeerLatency, SiblingContact},
     node::Node,
+    node::NodeEvent,
     node::NodeInfo,
+    node::{
+        batch_item_id, BatchItem, BatchQueryResponse, Busy, DecommissionStatus, DutyLogFilter,
+        DutyMetrics, DutyRefusal, DutyStats, EarningsRecord, EgressStats, NodeStatus,
+        PendingQueryStats, RefusedDuties, RestartBackoff, SectionQueryStats, SectionRedirect,
+        SectionUpdate, MAX_BATCH_QUERIES, MAX_QUEUE_DEPTH, MAX_RESTART_DELAY, MIN_RESTART_DELAY,
+        QUERY_TIMED_OUT, STABLE_RUN,
+    },
+    node_msg::{ClientCmd, ClientMsg, ClientRequest, NODE_MSG_VERSION},
+    transfers::{
+        InvariantViolation, SignedStoreCostEstimate, StoreCostEstimate, StoreCostItem, TimeLock,
+        ESTIMATE_VALIDITY, MAX_ESTIMATED_CHUNKS, TIME_LOCK_TAG,
+    },
 };
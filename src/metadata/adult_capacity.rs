@@ -0,0 +1,388 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    chunks::REFERENCE_CAPABILITY,
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use xor_name::XorName;
+
+/// How often Adults report the capacity of their storage to their Elders.
+pub(crate) const CAPACITY_REPORT_TICK: Duration = Duration::from_secs(5 * 60);
+/// Adults are counted by the percentage of their capacity used, in buckets of this many percent.
+pub const CAPACITY_HISTOGRAM_BUCKET: u8 = 10;
+const CAPACITY_HISTOGRAM_BUCKETS: usize = (100 / CAPACITY_HISTOGRAM_BUCKET) as usize;
+// Adults using this percentage of their capacity or more are chosen as holders of new chunks
+// only after those with room, closest first either way.
+const NEARLY_FULL_PERCENTAGE: u8 = 90;
+// The report of an Adult missing this many ticks is no longer taken into account.
+const REPORT_VALIDITY: Duration = Duration::from_secs(3 * CAPACITY_REPORT_TICK.as_secs());
+// The holders of a chunk are chosen among this many times as many of the Adults closest to it,
+// so that chunks are not held far from their name because of a little more room elsewhere.
+const CANDIDATES_PER_HOLDER: usize = 2;
+// Failures of an Adult, e.g. to read a chunk or to answer a storage challenge,
+// weigh against it being chosen as a holder for this long.
+const FAILURE_MEMORY: Duration = Duration::from_secs(60 * 60);
+// The weight of an Adult is its free capacity, in per mille.
+const FULL_WEIGHT: u64 = 1_000;
+// The capability of an Adult weighs it down to half, or up to twice, that of reference.
+const MIN_CAPABILITY: u32 = REFERENCE_CAPABILITY / 2;
+const MAX_CAPABILITY: u32 = REFERENCE_CAPABILITY * 2;
+// Holders serve this many turns of the reads of a chunk at most, by their capability,
+// one for half that of reference or less, two for that of reference.
+const MAX_READ_TURNS: u32 = 4;
+
+/// The capacity of the storage of an Adult, as reported by it to its Elders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapacityReport {
+    /// Bytes used.
+    pub used: u64,
+    /// Bytes the Adult can store at most.
+    pub total: u64,
+    /// Chunks held.
+    pub chunks: u64,
+    /// Seconds since unix epoch at which it was reported.
+    pub reported_at: u64,
+    /// The score of the capability of the Adult, as benchmarked on its first join, if it was.
+    pub capability: Option<u32>,
+}
+
+impl CapacityReport {
+    /// The report of the capacity, as of now.
+    pub(crate) fn new(used: u64, total: u64, chunks: u64) -> Self {
+        Self {
+            used,
+            total,
+            chunks,
+            reported_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            capability: None,
+        }
+    }
+
+    /// The percentage of the capacity used, from 0 to 100.
+    pub fn used_percentage(&self) -> u8 {
+        if self.total == 0 {
+            return 100;
+        }
+        (u128::from(self.used.min(self.total)) * 100 / u128::from(self.total)) as u8
+    }
+
+    // The capacity not used, in per mille, or none when nearly full.
+    fn free_per_mille(&self) -> u64 {
+        if self.used_percentage() >= NEARLY_FULL_PERCENTAGE {
+            return 0;
+        }
+        let used = u128::from(self.used.min(self.total)) * u128::from(FULL_WEIGHT);
+        FULL_WEIGHT - (used / u128::from(self.total)) as u64
+    }
+
+    /// The msg reporting the capacity to our Elders.
+    pub(crate) fn msg(&self, elders: BTreeSet<XorName>) -> NodeDuty {
+        NodeDuty::SendNodeMsgToNodes {
+            targets: elders,
+            msg: NodeMsg::new(NodeMsgBody::CapacityReport(*self)),
+        }
+    }
+}
+
+/// The capacity of the storage of the Adults of our section, as last reported by them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CapacityHistogram {
+    /// Adults by the percentage of their capacity used, the first bucket counting those using
+    /// less than `CAPACITY_HISTOGRAM_BUCKET` percent, the last those using the most, up to all.
+    pub buckets: [usize; CAPACITY_HISTOGRAM_BUCKETS],
+    /// Adults not reporting their capacity lately.
+    pub unreported: usize,
+    /// Bytes used by the Adults reporting.
+    pub used: u64,
+    /// Bytes the Adults reporting can store at most.
+    pub total: u64,
+    /// Chunks held by the Adults reporting.
+    pub chunks: u64,
+}
+
+impl CapacityHistogram {
+    /// The percentage of the capacity of the Adults reporting used, from 0 to 100,
+    /// or none when none of them reported.
+    pub fn used_percentage(&self) -> Option<u8> {
+        if self.total == 0 {
+            return None;
+        }
+        Some((u128::from(self.used.min(self.total)) * 100 / u128::from(self.total)) as u8)
+    }
+}
+
+/// The capacity of each of our Adults, as last reported by it,
+/// and the failures of each lately.
+#[derive(Default)]
+pub(super) struct AdultCapacities {
+    reports: BTreeMap<XorName, (CapacityReport, Instant)>,
+    failures: BTreeMap<XorName, Vec<Instant>>,
+}
+
+impl AdultCapacities {
+    pub(super) fn record(&mut self, adult: XorName, report: CapacityReport, now: Instant) {
+        let _ = self.reports.insert(adult, (report, now));
+    }
+
+    /// Records a failure of the Adult, e.g. to read a chunk, forgetting those of it
+    /// older than `FAILURE_MEMORY`.
+    pub(super) fn record_failure(&mut self, adult: XorName, now: Instant) {
+        let failures = self.failures.entry(adult).or_default();
+        failures.retain(|at| now.saturating_duration_since(*at) < FAILURE_MEMORY);
+        failures.push(now);
+    }
+
+    // The failures of the Adult within `FAILURE_MEMORY`.
+    fn recent_failures(&self, adult: &XorName, now: Instant) -> u64 {
+        self.failures.get(adult).map_or(0, |failures| {
+            failures
+                .iter()
+                .filter(|at| now.saturating_duration_since(**at) < FAILURE_MEMORY)
+                .count() as u64
+        })
+    }
+
+    // The report of the Adult, if recent.
+    fn report(&self, adult: &XorName, now: Instant) -> Option<&CapacityReport> {
+        match self.reports.get(adult) {
+            Some((report, at)) if now.saturating_duration_since(*at) < REPORT_VALIDITY => {
+                Some(report)
+            }
+            _ => None,
+        }
+    }
+
+    /// Chooses `count` holders of a chunk among the Adults, sorted by distance to it.
+    /// The closest `CANDIDATES_PER_HOLDER` times `count` are weighed by their free capacity,
+    /// times their capability relative to that of reference, if known, divided by one more
+    /// than their recent failures, the heaviest chosen first, closest first among those of
+    /// the same weight. Adults nearly full weigh nothing, and those not reporting lately weigh
+    /// the average of those reporting. When none of the candidates reported lately, the
+    /// closest are chosen, as by distance alone.
+    /// The choice is deterministic, so that Elders with the same reports choose alike.
+    pub(super) fn choose_holders(
+        &self,
+        mut adults: Vec<XorName>,
+        count: usize,
+        capabilities: &BTreeMap<XorName, u32>,
+        now: Instant,
+    ) -> Vec<XorName> {
+        let candidates = count
+            .saturating_mul(CANDIDATES_PER_HOLDER)
+            .min(adults.len());
+        let free: Vec<_> = adults[..candidates]
+            .iter()
+            .map(|adult| self.report(adult, now).map(CapacityReport::free_per_mille))
+            .collect();
+        let reported: Vec<_> = free.iter().flatten().collect();
+        if reported.is_empty() {
+            adults.truncate(count);
+            return adults;
+        }
+        let average = reported.iter().copied().sum::<u64>() / reported.len() as u64;
+        let mut weighed: Vec<_> = adults
+            .drain(..candidates)
+            .zip(free)
+            .map(|(adult, free)| {
+                let capability = capabilities
+                    .get(&adult)
+                    .map_or(REFERENCE_CAPABILITY, |score| {
+                        (*score).clamp(MIN_CAPABILITY, MAX_CAPABILITY)
+                    });
+                let weight = free.unwrap_or(average) * u64::from(capability)
+                    / u64::from(REFERENCE_CAPABILITY)
+                    / (1 + self.recent_failures(&adult, now));
+                (adult, weight)
+            })
+            .collect();
+        // stable, so that the closest come first among the same weight
+        weighed.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let mut holders: Vec<_> = weighed.into_iter().map(|(adult, _)| adult).collect();
+        holders.extend(adults);
+        holders.truncate(count);
+        holders
+    }
+
+    /// The turns of the holders at serving the reads of a chunk, in order, each taking more
+    /// of them the more capable it is, from one to `MAX_READ_TURNS`, as by its capability
+    /// relative to that of reference, or two turns if not known.
+    pub(super) fn read_turns(
+        holders: &BTreeSet<XorName>,
+        capabilities: &BTreeMap<XorName, u32>,
+    ) -> Vec<XorName> {
+        holders
+            .iter()
+            .flat_map(|holder| {
+                let turns = capabilities
+                    .get(holder)
+                    .map_or(2, |score| (score / MIN_CAPABILITY).clamp(1, MAX_READ_TURNS));
+                std::iter::repeat_n(*holder, turns as usize)
+            })
+            .collect()
+    }
+
+    /// The histogram of the capacity of the Adults, forgetting those no longer Adults of ours.
+    pub(super) fn histogram(
+        &mut self,
+        adults: &BTreeSet<XorName>,
+        now: Instant,
+    ) -> CapacityHistogram {
+        self.reports.retain(|adult, _| adults.contains(adult));
+        let mut histogram = CapacityHistogram::default();
+        for adult in adults {
+            let report = match self.report(adult, now) {
+                Some(report) => report,
+                None => {
+                    histogram.unreported += 1;
+                    continue;
+                }
+            };
+            let bucket = (report.used_percentage() / CAPACITY_HISTOGRAM_BUCKET) as usize;
+            histogram.buckets[bucket.min(CAPACITY_HISTOGRAM_BUCKETS - 1)] += 1;
+            histogram.used = histogram.used.saturating_add(report.used);
+            histogram.total = histogram.total.saturating_add(report.total);
+            histogram.chunks = histogram.chunks.saturating_add(report.chunks);
+        }
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn adults_nearly_full_are_chosen_last() {
+        let (full, roomy, unreported, stale) = (
+            XorName::random(),
+            XorName::random(),
+            XorName::random(),
+            XorName::random(),
+        );
+        let mut capacities = AdultCapacities::default();
+        let now = Instant::now();
+        capacities.record(full, CapacityReport::new(95, 100, 9), now);
+        capacities.record(roomy, CapacityReport::new(15, 100, 1), now);
+        capacities.record(stale, CapacityReport::new(100, 100, 10), now);
+        let later = now + REPORT_VALIDITY;
+        capacities.record(full, CapacityReport::new(95, 100, 9), later);
+        capacities.record(roomy, CapacityReport::new(15, 100, 1), later);
+
+        let ordered = capacities.choose_holders(
+            vec![full, roomy, unreported, stale],
+            4,
+            &BTreeMap::new(),
+            later,
+        );
+        assert_eq!(ordered, vec![roomy, unreported, stale, full]);
+
+        let adults = vec![full, roomy, unreported, stale].into_iter().collect();
+        let histogram = capacities.histogram(&adults, later);
+        assert_eq!(histogram.buckets[1], 1);
+        assert_eq!(histogram.buckets[CAPACITY_HISTOGRAM_BUCKETS - 1], 1);
+        assert_eq!(histogram.unreported, 2);
+        assert_eq!(
+            (histogram.used, histogram.total, histogram.chunks),
+            (110, 200, 10)
+        );
+        assert_eq!(histogram.used_percentage(), Some(55));
+        assert_eq!(CapacityHistogram::default().used_percentage(), None);
+    }
+
+    #[test]
+    fn holders_are_weighed_by_room_and_failures() {
+        let adults: Vec<_> = (0..6).map(|_| XorName::random()).collect();
+        let mut capacities = AdultCapacities::default();
+        let none = BTreeMap::new();
+        let now = Instant::now();
+
+        // without reports, by distance alone
+        assert_eq!(
+            capacities.choose_holders(adults.clone(), 2, &none, now),
+            adults[..2]
+        );
+
+        capacities.record(adults[0], CapacityReport::new(80, 100, 8), now);
+        capacities.record(adults[1], CapacityReport::new(50, 100, 5), now);
+        capacities.record(adults[2], CapacityReport::new(10, 100, 1), now);
+        capacities.record(adults[3], CapacityReport::new(20, 100, 2), now);
+        // beyond the candidates, however roomy
+        capacities.record(adults[4], CapacityReport::new(0, 100, 0), now);
+        assert_eq!(
+            capacities.choose_holders(adults.clone(), 2, &none, now),
+            vec![adults[2], adults[3]]
+        );
+
+        // 900 / 3 weighs less than 800 and 500
+        capacities.record_failure(adults[2], now);
+        capacities.record_failure(adults[2], now);
+        assert_eq!(
+            capacities.choose_holders(adults.clone(), 2, &none, now),
+            vec![adults[3], adults[1]]
+        );
+        // until the failures are forgotten
+        let later = now + FAILURE_MEMORY;
+        capacities.record(adults[0], CapacityReport::new(80, 100, 8), later);
+        capacities.record(adults[1], CapacityReport::new(50, 100, 5), later);
+        capacities.record(adults[2], CapacityReport::new(10, 100, 1), later);
+        capacities.record(adults[3], CapacityReport::new(20, 100, 2), later);
+        assert_eq!(
+            capacities.choose_holders(adults.clone(), 2, &none, later),
+            vec![adults[2], adults[3]]
+        );
+
+        // stale reports fall back to distance alone
+        let stale = later + REPORT_VALIDITY;
+        assert_eq!(
+            capacities.choose_holders(adults.clone(), 2, &none, stale),
+            adults[..2]
+        );
+    }
+
+    #[test]
+    fn capable_adults_hold_and_serve_more() {
+        let adults: Vec<_> = (0..4).map(|_| XorName::random()).collect();
+        let mut capacities = AdultCapacities::default();
+        let now = Instant::now();
+        for adult in &adults {
+            capacities.record(*adult, CapacityReport::new(50, 100, 5), now);
+        }
+        // as roomy, so by capability, when known
+        let capabilities = vec![
+            (adults[0], REFERENCE_CAPABILITY / 4),
+            (adults[2], REFERENCE_CAPABILITY * 2),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            capacities.choose_holders(adults.clone(), 2, &capabilities, now),
+            vec![adults[2], adults[1]]
+        );
+
+        let holders = adults.iter().copied().collect();
+        let turns = AdultCapacities::read_turns(&holders, &capabilities);
+        let turns_of = |adult| turns.iter().filter(|turn| **turn == adult).count();
+        assert_eq!(
+            adults
+                .iter()
+                .map(|adult| turns_of(*adult))
+                .collect::<Vec<_>>(),
+            vec![1, 2, MAX_READ_TURNS as usize, 2]
+        );
+    }
+}
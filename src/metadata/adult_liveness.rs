@@ -0,0 +1,209 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    convert::TryFrom,
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// How often the liveness of our Adults is checked, while an Elder.
+pub(crate) const LIVENESS_TICK: Duration = Duration::from_secs(30);
+// Queries not answered within this time are missed.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+// An Adult missing this many queries in a row is unresponsive.
+const MAX_MISSED_IN_A_ROW: u32 = 5;
+// As is one answering, on average, in no less than this time.
+const MAX_MEAN_LATENCY: Duration = Duration::from_secs(10);
+// The answers the mean latency is over, roughly, before it is judged by.
+const LATENCY_SAMPLES: u64 = 8;
+// Adults found unresponsive are not chosen as holders for this long.
+const UNRESPONSIVE_MEMORY: Duration = Duration::from_secs(60 * 60);
+
+/// The answers of an Adult to the queries of the Elders, e.g. to storage challenges
+/// and relayed reads, as tracked by an Elder.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdultLiveness {
+    /// Queries answered.
+    pub answered: u64,
+    /// Queries not answered in time.
+    pub missed: u64,
+    /// Queries not answered in time since the last one answered.
+    pub missed_in_a_row: u32,
+    /// Moving mean of the millis taken to answer.
+    pub mean_latency_ms: u64,
+    /// Whether the Adult is judged unresponsive, and its chunks copied to other holders.
+    pub unresponsive: bool,
+}
+
+impl AdultLiveness {
+    fn is_unresponsive(&self) -> bool {
+        self.missed_in_a_row >= MAX_MISSED_IN_A_ROW
+            || (self.answered >= LATENCY_SAMPLES
+                && Duration::from_millis(self.mean_latency_ms) >= MAX_MEAN_LATENCY)
+    }
+}
+
+#[derive(Default)]
+struct Tracked {
+    liveness: AdultLiveness,
+    // when each query pending an answer was sent, the oldest first,
+    // as Adults answer queries in the order they get them
+    pending: VecDeque<Instant>,
+    // when the Adult was found unresponsive, if it was
+    unresponsive_since: Option<Instant>,
+}
+
+/// Tracks the latency of the answers of our Adults to queries, and the queries they miss,
+/// to find the Adults no longer serving their chunks, rather than waiting for them to leave.
+#[derive(Default)]
+pub(super) struct LivenessTracker {
+    adults: BTreeMap<XorName, Tracked>,
+}
+
+impl LivenessTracker {
+    /// The Adults were sent a query.
+    pub(super) fn queried(&mut self, adults: impl IntoIterator<Item = XorName>, now: Instant) {
+        for adult in adults {
+            self.adults.entry(adult).or_default().pending.push_back(now);
+        }
+    }
+
+    /// The Adult answered the oldest of the queries pending with it.
+    pub(super) fn answered(&mut self, adult: XorName, now: Instant) {
+        let tracked = match self.adults.get_mut(&adult) {
+            Some(tracked) => tracked,
+            None => return,
+        };
+        let sent = match tracked.pending.pop_front() {
+            Some(sent) => sent,
+            None => return,
+        };
+        let latency =
+            u64::try_from(now.saturating_duration_since(sent).as_millis()).unwrap_or(u64::MAX);
+        let liveness = &mut tracked.liveness;
+        liveness.mean_latency_ms = if liveness.answered == 0 {
+            latency
+        } else {
+            let weight = (liveness.answered + 1).min(LATENCY_SAMPLES);
+            (liveness.mean_latency_ms * (weight - 1) + latency) / weight
+        };
+        liveness.answered += 1;
+        liveness.missed_in_a_row = 0;
+    }
+
+    /// Counts the queries not answered in time as missed, returning the
+    /// Adults found unresponsive since last checked, with their liveness.
+    pub(super) fn check(&mut self, now: Instant) -> Vec<(XorName, AdultLiveness)> {
+        let mut unresponsive = vec![];
+        for (adult, tracked) in &mut self.adults {
+            while let Some(sent) = tracked.pending.front() {
+                if now.saturating_duration_since(*sent) < QUERY_TIMEOUT {
+                    break;
+                }
+                let _ = tracked.pending.pop_front();
+                tracked.liveness.missed += 1;
+                tracked.liveness.missed_in_a_row += 1;
+            }
+            if let Some(since) = tracked.unresponsive_since {
+                if now.saturating_duration_since(since) < UNRESPONSIVE_MEMORY {
+                    continue;
+                }
+                // given another chance, from a clean slate
+                tracked.liveness = AdultLiveness::default();
+                tracked.unresponsive_since = None;
+            }
+            if tracked.liveness.is_unresponsive() {
+                tracked.liveness.unresponsive = true;
+                tracked.unresponsive_since = Some(now);
+                unresponsive.push((*adult, tracked.liveness));
+            }
+        }
+        unresponsive
+    }
+
+    /// Whether the Adult was found unresponsive lately.
+    pub(super) fn is_unresponsive(&self, adult: &XorName) -> bool {
+        matches!(
+            self.adults.get(adult),
+            Some(Tracked {
+                unresponsive_since: Some(_),
+                ..
+            })
+        )
+    }
+
+    /// The liveness of each of the Adults tracked.
+    pub(super) fn liveness(&self) -> BTreeMap<XorName, AdultLiveness> {
+        self.adults
+            .iter()
+            .map(|(adult, tracked)| (*adult, tracked.liveness))
+            .collect()
+    }
+
+    /// Stops tracking the Adult, e.g. once it has left.
+    pub(super) fn forget(&mut self, adult: &XorName) {
+        let _ = self.adults.remove(adult);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn adults_missing_queries_in_a_row_are_unresponsive() {
+        let (silent, answering) = (XorName::random(), XorName::random());
+        let mut tracker = LivenessTracker::default();
+        let now = Instant::now();
+        for _ in 0..MAX_MISSED_IN_A_ROW {
+            tracker.queried(vec![silent, answering], now);
+        }
+        for _ in 0..MAX_MISSED_IN_A_ROW - 1 {
+            tracker.answered(answering, now + Duration::from_millis(100));
+        }
+        assert!(tracker.check(now).is_empty());
+
+        let later = now + QUERY_TIMEOUT;
+        let unresponsive = tracker.check(later);
+        assert_eq!(unresponsive.len(), 1);
+        assert_eq!(unresponsive[0].0, silent);
+        assert_eq!(unresponsive[0].1.missed_in_a_row, MAX_MISSED_IN_A_ROW);
+        assert!(tracker.is_unresponsive(&silent));
+        assert!(!tracker.is_unresponsive(&answering));
+        assert_eq!(tracker.liveness()[&answering].mean_latency_ms, 100);
+        // and it is only reported once
+        assert!(tracker.check(later).is_empty());
+
+        // until given another chance
+        let much_later = later + UNRESPONSIVE_MEMORY;
+        assert!(tracker.check(much_later).is_empty());
+        assert!(!tracker.is_unresponsive(&silent));
+    }
+
+    #[test]
+    fn adults_answering_slowly_are_unresponsive() {
+        let slow = XorName::random();
+        let mut tracker = LivenessTracker::default();
+        let now = Instant::now();
+        for _ in 0..LATENCY_SAMPLES {
+            tracker.queried(vec![slow], now);
+            tracker.answered(slow, now + MAX_MEAN_LATENCY / 2);
+        }
+        assert!(tracker.check(now).is_empty());
+        for _ in 0..LATENCY_SAMPLES * 2 {
+            tracker.queried(vec![slow], now);
+            tracker.answered(slow, now + MAX_MEAN_LATENCY * 2);
+        }
+        let unresponsive = tracker.check(now);
+        assert_eq!(unresponsive.len(), 1);
+        assert_eq!(unresponsive[0].1.missed, 0);
+    }
+}
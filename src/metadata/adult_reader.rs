@@ -1,6 +1,7 @@
 use sn_routing::XorName;
 
-use crate::Network;
+use crate::{network::PeerProbes, Network};
+use std::sync::Arc;
 
 // Copyright 2021 MaidSafe.net limited.
 //
@@ -36,4 +37,9 @@ impl AdultReader {
             .our_adults_sorted_by_distance_to(name, count)
             .await
     }
+
+    /// The latency of our Adults, as probed.
+    pub(crate) fn probes(&self) -> Arc<PeerProbes> {
+        self.network.probes()
+    }
 }
@@ -8,11 +8,19 @@
 
 use crate::{
     capacity::ChunkHolderDbs,
+    chunks::{
+        ChunkRange, ImportedChunks, OrphanOffer, OrphanRelease, RangedChunkRead,
+        MAX_STORAGE_USAGE_RATIO,
+    },
     error::convert_to_error_message,
+    node_msg::ClientMsg,
     node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
-    Error, Network, Result, ToDbKey,
+    persistence::Transaction,
+    to_db_key::from_db_key,
+    utils, Error, Network, Result, ToDbKey,
 };
 use log::{info, trace, warn};
+use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use sn_data_types::{Blob, BlobAddress, Error as DtError, PublicKey, Result as NdResult};
 use sn_messaging::{
@@ -26,18 +34,43 @@ use sn_messaging::{
 use std::{
     collections::{BTreeMap, BTreeSet},
     fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
 };
 use xor_name::XorName;
 
-use super::adult_reader::AdultReader;
+use super::{
+    adult_capacity::{AdultCapacities, CapacityHistogram, CapacityReport},
+    adult_liveness::{AdultLiveness, LivenessTracker},
+    adult_reader::AdultReader,
+    chunk_deletion::{ChunkDeletionPolicy, MAX_DELETION_BATCH},
+    erasure_coding::{self, ErasureCoding, ShardMap, ShardedReads},
+    range_export::{MetadataRecord, RecordKey},
+    reed_solomon::ReedSolomon,
+    relayed_reads::{self, RelayedChunk, RelayedReads},
+    replication_batch::ReplicationBatch,
+};
 
 // The number of separate copies of a blob chunk which should be maintained.
-const CHUNK_COPY_COUNT: usize = 4;
+pub(crate) const CHUNK_COPY_COUNT: usize = 4;
+// The number of holders a read of a chunk is sent to.
+// Holders take turns, so as to spread the read load.
+const READ_HOLDER_COUNT: usize = 2;
+// The max number of chunks a single repair request can list.
+const MAX_REPAIR_ADDRESSES: usize = 1_000;
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 struct ChunkMetadata {
     holders: BTreeSet<XorName>,
     owner: Option<PublicKey>,
+    // Set when a premium redundancy has been paid for.
+    copies: Option<usize>,
+}
+
+impl ChunkMetadata {
+    fn copy_count(&self) -> usize {
+        self.copies.unwrap_or(CHUNK_COPY_COUNT)
+    }
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -45,15 +78,211 @@ struct HolderMetadata {
     chunks: BTreeSet<BlobAddress>,
 }
 
+/// The state of a chunk found by a repair request, and what was done about it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkRepair {
+    /// All holders are live, and recorded as holding the chunk.
+    Healthy {
+        /// Number of holders.
+        holders: usize,
+    },
+    /// Holders no longer in the section were dropped, missing holder
+    /// records fixed, and new holders asked to copy the chunk from the live ones.
+    Repairing {
+        /// Number of live holders.
+        live: usize,
+        /// Number of holders dropped.
+        lost: usize,
+        /// Number of live holders not recorded as holding the chunk.
+        records_fixed: usize,
+        /// Number of new holders asked to copy the chunk.
+        new_holders: usize,
+    },
+    /// No holder of the chunk is live, so it cannot be repaired by the section.
+    Unrecoverable,
+    /// No chunk at this address is known to the section.
+    NoSuchChunk,
+    /// The chunk is private, and not owned by the requester.
+    AccessDenied,
+}
+
+/// The outcome of a repair request, per chunk listed.
+pub type RepairReport = Vec<(BlobAddress, ChunkRepair)>;
+
 /// Operations over the data type Blob.
 pub(super) struct BlobRegister {
     dbs: ChunkHolderDbs,
     reader: AdultReader,
+    read_rotation: AtomicUsize,
+    // Number of reads failed per holder, as reported by the holders.
+    read_incidents: BTreeMap<XorName, u64>,
+    // Whether the chunks to replicate are sent to each new holder in batches.
+    batch_replication: bool,
+    // The capacity of each of our Adults, as reported by it.
+    capacities: AdultCapacities,
+    // The reads relayed to the holders, if reads are relayed rather than answered by them.
+    relayed_reads: Option<RelayedReads>,
+    // The answers of our Adults to the queries of us, as Elder.
+    liveness: LivenessTracker,
+    // The code new chunks are stored erasure coded by, if they are.
+    erasure_coding: Option<ReedSolomon>,
+    // The reads of erasure coded chunks, pending their shards.
+    sharded_reads: ShardedReads,
 }
 
 impl BlobRegister {
     pub(super) fn new(dbs: ChunkHolderDbs, reader: AdultReader) -> Self {
-        Self { dbs, reader }
+        Self {
+            dbs,
+            reader,
+            read_rotation: AtomicUsize::new(0),
+            read_incidents: BTreeMap::new(),
+            batch_replication: false,
+            capacities: AdultCapacities::default(),
+            relayed_reads: None,
+            liveness: LivenessTracker::default(),
+            erasure_coding: None,
+            sharded_reads: ShardedReads::default(),
+        }
+    }
+
+    /// Sends the chunks to replicate when a holder has left to each new holder in
+    /// batches, rather than in one msg per chunk.
+    pub(super) fn set_batch_replication(&mut self, batch_replication: bool) {
+        self.batch_replication = batch_replication;
+    }
+
+    /// Relays the reads of chunks to the number of holders given at a time, if given.
+    /// Reads pending are dropped when reads stop being relayed.
+    pub(super) fn set_relayed_reads(&mut self, holders_at_once: Option<usize>) {
+        let current = self
+            .relayed_reads
+            .as_ref()
+            .map(RelayedReads::holders_at_once);
+        if current != holders_at_once {
+            self.relayed_reads = holders_at_once.map(RelayedReads::new);
+        }
+    }
+
+    /// Stores new chunks erasure coded into the shards given, if given,
+    /// rather than copied whole to each holder.
+    pub(super) fn set_erasure_coding(&mut self, coding: Option<ErasureCoding>) -> Result<()> {
+        self.erasure_coding = coding
+            .map(|coding| ReedSolomon::new(coding.data_shards, coding.parity_shards))
+            .transpose()?;
+        Ok(())
+    }
+
+    pub(super) fn relay_chunk(
+        &mut self,
+        answer: RelayedChunk,
+        holder: XorName,
+    ) -> Option<NodeDuty> {
+        self.liveness.answered(holder, Instant::now());
+        if self.sharded_reads.is_pending(&answer) {
+            return self.sharded_reads.answer(answer, holder);
+        }
+        self.relayed_reads.as_mut()?.answer(answer, holder)
+    }
+
+    /// Queries the next holders of the relayed reads not answered in time,
+    /// and fails the reads of erasure coded chunks not answered in time.
+    pub(super) fn retry_relayed_reads(&mut self, now: Instant) -> Result<NodeDuties> {
+        let mut duties = self.sharded_reads.expire(now);
+        let retried = match &mut self.relayed_reads {
+            Some(reads) => reads.retry(now)?,
+            None => vec![],
+        };
+        self.record_queried(&retried, now);
+        duties.extend(retried);
+        Ok(duties)
+    }
+
+    /// Records the Adults sent the queries as pending an answer to each,
+    /// for their liveness to be judged by.
+    pub(super) fn record_queried(&mut self, duties: &[NodeDuty], now: Instant) {
+        for duty in duties {
+            if let NodeDuty::SendToNodes { targets, .. } = duty {
+                self.liveness.queried(targets.iter().copied(), now);
+            }
+        }
+    }
+
+    /// Records the Adult as answering the oldest query pending with it.
+    pub(super) fn record_answered(&mut self, adult: XorName, now: Instant) {
+        self.liveness.answered(adult, now);
+    }
+
+    /// The liveness of each of our Adults, as tracked by us.
+    pub(super) fn adult_liveness(&self) -> BTreeMap<XorName, AdultLiveness> {
+        self.liveness.liveness()
+    }
+
+    /// Finds the Adults found unresponsive since last checked, copying their chunks
+    /// to other holders while they may still serve them, as when decommissioned.
+    /// They are not chosen as holders again for a while, unless they leave first.
+    pub(super) async fn check_liveness(
+        &mut self,
+        now: Instant,
+    ) -> Result<(Vec<(XorName, AdultLiveness)>, NodeDuties)> {
+        let unresponsive = self.liveness.check(now);
+        let mut duties = vec![];
+        for (adult, _) in &unresponsive {
+            duties.extend(self.release_holder(*adult).await?);
+        }
+        Ok((unresponsive, duties))
+    }
+
+    /// Records the capacity reported by the Adult, to choose the holders of chunks by,
+    /// persisting its capability, if reported, for as long as it is an Adult of ours.
+    pub(super) async fn record_capacity(
+        &mut self,
+        adult: XorName,
+        report: CapacityReport,
+    ) -> Result<()> {
+        self.capacities.record(adult, report, Instant::now());
+        // an Adult reported full has room again once chunks held by it are deleted
+        if f64::from(report.used_percentage()) < MAX_STORAGE_USAGE_RATIO * 100.0 {
+            if let Ok(node_id) = ed25519_dalek::PublicKey::from_bytes(&adult.0) {
+                let key = PublicKey::from(node_id).to_string();
+                let mut full_adults = self.dbs.full_adults.lock().await;
+                if full_adults.exists(&key) {
+                    info!("{} has room again, no longer counted as full", adult);
+                    let _ = full_adults.rem(&key)?;
+                }
+            }
+        }
+        let score = match report.capability {
+            Some(score) => score,
+            None => return Ok(()),
+        };
+        let key = adult.to_db_key()?;
+        let mut capabilities = self.dbs.capabilities.lock().await;
+        if capabilities.get::<u32>(&key) != Some(score) {
+            capabilities.set(&key, &score)?;
+        }
+        Ok(())
+    }
+
+    // The capabilities of the Adults, of those which reported it.
+    async fn capabilities<'a>(
+        &self,
+        adults: impl Iterator<Item = &'a XorName>,
+    ) -> Result<BTreeMap<XorName, u32>> {
+        let capabilities = self.dbs.capabilities.lock().await;
+        let mut scores = BTreeMap::new();
+        for adult in adults {
+            if let Some(score) = capabilities.get(&adult.to_db_key()?) {
+                let _ = scores.insert(*adult, score);
+            }
+        }
+        Ok(scores)
+    }
+
+    /// The histogram of the capacity of our Adults, as last reported by them.
+    pub(super) async fn capacity_histogram(&mut self) -> CapacityHistogram {
+        let adults = self.reader.our_adults().await.into_iter().collect();
+        self.capacities.histogram(&adults, Instant::now())
     }
 
     pub(super) async fn write(
@@ -61,19 +290,174 @@ impl BlobRegister {
         write: BlobWrite,
         msg_id: MessageId,
         origin: EndUser,
-    ) -> Result<NodeDuty> {
+    ) -> Result<NodeDuties> {
         use BlobWrite::*;
         match write {
-            New(data) => self.store(data, msg_id, origin).await,
-            DeletePrivate(address) => self.delete(address, msg_id, origin).await,
+            New(data) => {
+                let address = *data.address();
+                let is_sharded = self.shard_map(address).await.is_some()
+                    || (self.erasure_coding.is_some()
+                        && self.get_metadata_for(address).await.is_err());
+                if is_sharded {
+                    return self.store_sharded(data, msg_id, origin).await;
+                }
+                self.store(data, msg_id, origin).await.map(NodeDuties::from)
+            }
+            DeletePrivate(address) => {
+                if address.is_private() {
+                    if let Some(map) = self.shard_map(address).await {
+                        return self.delete_sharded(address, map, msg_id, origin).await;
+                    }
+                }
+                self.delete(address, msg_id, origin)
+                    .await
+                    .map(NodeDuties::from)
+            }
+        }
+    }
+
+    // Codes the chunk into shards, each sent to a holder of its own, recording the
+    // shards of the chunk, and each shard as a chunk of a single copy, as the parity
+    // makes up for the shards lost. Chunks are stored whole while the section has
+    // fewer Adults than there are shards.
+    async fn store_sharded(
+        &mut self,
+        data: Blob,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        let address = *data.address();
+        if self.shard_map(address).await.is_some() {
+            if data.is_public() {
+                trace!("{}: All good, {:?}, chunk already exists.", self, address);
+                return Ok(vec![]);
+            }
+            return Ok(vec![
+                self.send_blob_cmd_error(Error::DataExists, msg_id, origin)
+                    .await?,
+            ]);
+        }
+        let code = match &self.erasure_coding {
+            Some(code) => code.clone(),
+            None => return self.store(data, msg_id, origin).await.map(NodeDuties::from),
+        };
+        let holders = self
+            .get_holders_for_chunk(data.name(), code.total_shards())
+            .await;
+        if holders.len() < code.total_shards() {
+            info!(
+                "{} Adults to hold the {} shards of {:?}, storing it whole",
+                holders.len(),
+                code.total_shards(),
+                address
+            );
+            return self.store(data, msg_id, origin).await.map(NodeDuties::from);
         }
+        let (map, shards) = erasure_coding::shard(&code, &data)?;
+        info!("Storing {:?} as {} shards", address, shards.len());
+        let mut duties = vec![];
+        for (index, (shard, holder)) in shards.into_iter().zip(holders).enumerate() {
+            let shard_address = *shard.address();
+            self.set_chunk_holder(shard_address, holder, origin).await?;
+            let db_key = shard_address.to_db_key()?;
+            let mut metadata_db = self.dbs.metadata.lock().await;
+            if let Some(mut metadata) = metadata_db.get::<ChunkMetadata>(&db_key) {
+                metadata.copies = Some(1);
+                metadata_db.set(&db_key, &metadata)?;
+            }
+            duties.push(NodeDuty::SendToNodes {
+                targets: std::iter::once(holder).collect(),
+                msg: Message::NodeCmd {
+                    cmd: NodeCmd::Chunks {
+                        cmd: BlobWrite::New(shard),
+                        origin,
+                    },
+                    id: MessageId::from_content(&(msg_id, index))?,
+                    target_section_pk: None,
+                },
+            });
+        }
+        self.dbs
+            .shard_maps
+            .lock()
+            .await
+            .set(&address.to_db_key()?, &map)?;
+        Ok(duties)
+    }
+
+    // Deletes each of the shards of the chunk, as the owner deletes the chunk.
+    async fn delete_sharded(
+        &mut self,
+        address: BlobAddress,
+        map: ShardMap,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        if map.owner.as_ref() != Some(origin.id()) {
+            return Ok(vec![
+                self.send_blob_cmd_error(
+                    Error::NetworkData(DtError::AccessDenied(*origin.id())),
+                    msg_id,
+                    origin,
+                )
+                .await?,
+            ]);
+        }
+        let mut duties = vec![];
+        for (index, shard) in map.shards.iter().enumerate() {
+            let id = MessageId::from_content(&(msg_id, index))?;
+            duties.extend(NodeDuties::from(self.delete(*shard, id, origin).await?));
+        }
+        let _ = self
+            .dbs
+            .shard_maps
+            .lock()
+            .await
+            .rem(&address.to_db_key()?)?;
+        Ok(duties)
+    }
+
+    // The shards of the chunk, if it is stored erasure coded.
+    async fn shard_map(&self, address: BlobAddress) -> Option<ShardMap> {
+        let db_key = address.to_db_key().ok()?;
+        self.dbs.shard_maps.lock().await.get::<ShardMap>(&db_key)
+    }
+
+    // Queries the holders of all shards of the chunk for them, to recover it from.
+    async fn get_sharded(
+        &mut self,
+        address: BlobAddress,
+        map: ShardMap,
+        range: Option<ChunkRange>,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        if let Some(owner) = map.owner {
+            if &owner != origin.id() {
+                let denied = Err(ErrorMessage::AccessDenied(*origin.id()));
+                return Ok(vec![relayed_reads::response(msg_id, origin, denied)]);
+            }
+        }
+        let mut holders = BTreeMap::new();
+        for shard in &map.shards {
+            if let Ok(metadata) = self.get_metadata_for(*shard).await {
+                let _ = holders.insert(*shard, metadata.holders);
+            }
+        }
+        let now = Instant::now();
+        let duties = self
+            .sharded_reads
+            .start(address, map, range, msg_id, origin, holders, now)?;
+        self.record_queried(&duties, now);
+        Ok(duties)
     }
 
     async fn store(&mut self, data: Blob, msg_id: MessageId, origin: EndUser) -> Result<NodeDuty> {
         // If the data already exist, check the existing no of copies.
         // If no of copies are less then required, then continue with the put request.
         let target_holders = if let Ok(metadata) = self.get_metadata_for(*data.address()).await {
-            if metadata.holders.len() == CHUNK_COPY_COUNT {
+            let copy_count = metadata.copy_count();
+            if metadata.holders.len() >= copy_count {
                 if data.is_public() {
                     trace!("{}: All good, {:?}, chunk already exists.", self, data);
                     return Ok(NodeDuty::NoOp);
@@ -93,7 +477,7 @@ impl BlobRegister {
             } else {
                 let mut existing_holders = metadata.holders;
                 let closest_holders = self
-                    .get_holders_for_chunk(data.name())
+                    .get_holders_for_chunk(data.name(), copy_count)
                     .await
                     .iter()
                     .cloned()
@@ -101,7 +485,7 @@ impl BlobRegister {
 
                 for holder_xorname in closest_holders {
                     if !existing_holders.contains(&holder_xorname)
-                        && existing_holders.len() < CHUNK_COPY_COUNT
+                        && existing_holders.len() < copy_count
                     {
                         let _ = existing_holders.insert(holder_xorname);
                     }
@@ -109,7 +493,8 @@ impl BlobRegister {
                 existing_holders
             }
         } else {
-            self.get_holders_for_chunk(data.name())
+            let copy_count = self.copy_count_for(*data.address()).await?;
+            self.get_holders_for_chunk(data.name(), copy_count)
                 .await
                 .iter()
                 .cloned()
@@ -171,6 +556,20 @@ impl BlobRegister {
         msg_id: MessageId,
         origin: EndUser,
     ) -> Result<NodeDuty> {
+        // public chunks have no owner, so are not deleted by clients,
+        // and their holders must not be forgotten on being asked to
+        if address.is_public() {
+            return self
+                .send_blob_cmd_error(
+                    Error::InvalidOperation(format!(
+                        "Public chunk cannot be deleted: {:?}",
+                        address
+                    )),
+                    msg_id,
+                    origin,
+                )
+                .await;
+        }
         let metadata = match self.get_metadata_for(address).await {
             Ok(metadata) => metadata,
             Err(error) => return self.send_blob_cmd_error(error, msg_id, origin).await,
@@ -224,8 +623,11 @@ impl BlobRegister {
 
         let db_key = blob_address.to_db_key()?;
         let mut metadata = self
-            .get_metadata_for(blob_address)
+            .dbs
+            .metadata
+            .lock()
             .await
+            .get::<ChunkMetadata>(&db_key)
             .unwrap_or_default();
         if blob_address.is_private() {
             metadata.owner = Some(*origin.id());
@@ -233,28 +635,164 @@ impl BlobRegister {
 
         let _ = metadata.holders.insert(holder);
 
-        if let Err(error) = self.dbs.metadata.lock().await.set(&db_key, &metadata) {
-            warn!("{}: Failed to write metadata to DB: {:?}", self, error);
-            return Err(error.into());
-        }
-
         // We're acting as data handler, received request from client handlers
         let mut holders_metadata = self.get_holder(holder).await.unwrap_or_default();
         let _ = holders_metadata.chunks.insert(blob_address);
 
+        // the chunk and its holder are recorded as a whole, so that neither
+        // is left recorded without the other by a crash between them
+        let mut metadata_db = self.dbs.metadata.lock().await;
+        let mut holders_db = self.dbs.holders.lock().await;
+        let mut transaction = Transaction::default();
+        transaction.set(&metadata_db, &db_key, &metadata)?;
+        transaction.set(&holders_db, &holder.to_db_key()?, &holders_metadata)?;
         if let Err(error) = self
             .dbs
+            .transactions
+            .commit(transaction, &mut [&mut metadata_db, &mut holders_db])
+        {
+            warn!("{}: Failed to write metadata to DB: {:?}", self, error);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Registers the holder as holding the chunks it imported, recording the owners of
+    /// those unknown to the section, and has the chunks short of copies replicated.
+    pub(super) async fn register_imported(
+        &mut self,
+        imported: ImportedChunks,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        info!(
+            "{}: Registering {} chunks imported by {}",
+            self,
+            imported.chunks.len(),
+            holder
+        );
+        let mut holders_metadata = self.get_holder(holder).await.unwrap_or_default();
+        let mut transaction = Transaction::default();
+        let mut short_of_copies = vec![];
+        let mut metadata_db = self.dbs.metadata.lock().await;
+        for (address, owner) in imported.chunks {
+            let db_key = address.to_db_key()?;
+            let mut metadata = metadata_db
+                .get::<ChunkMetadata>(&db_key)
+                .unwrap_or_default();
+            if address.is_private() && metadata.owner.is_none() {
+                metadata.owner = owner;
+            }
+            let _ = metadata.holders.insert(holder);
+            transaction.set(&metadata_db, &db_key, &metadata)?;
+            let _ = holders_metadata.chunks.insert(address);
+            if metadata.holders.len() < metadata.copy_count() {
+                short_of_copies.push((address, metadata.holders));
+            }
+        }
+        // the chunks are recorded as held along with the holder as holding them, as a whole
+        let mut holders_db = self.dbs.holders.lock().await;
+        transaction.set(&holders_db, &holder.to_db_key()?, &holders_metadata)?;
+        self.dbs
+            .transactions
+            .commit(transaction, &mut [&mut metadata_db, &mut holders_db])?;
+        drop((metadata_db, holders_db));
+        let mut duties = vec![];
+        for (address, holders) in short_of_copies {
+            duties.extend(self.get_replication_msgs(address, holders).await);
+        }
+        Ok(duties)
+    }
+
+    /// Has the chunk offered by a holder out of our section, holding it as an orphan,
+    /// copied to our Adults, releasing the holder once they are enough to hold it.
+    /// The holder is not registered, as an Adult of another section.
+    pub(super) async fn adopt_orphan(
+        &mut self,
+        offer: OrphanOffer,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        let address = offer.address;
+        let adults: BTreeSet<_> = self.reader.our_adults().await.into_iter().collect();
+        let db_key = address.to_db_key()?;
+        let mut metadata = self
+            .dbs
+            .metadata
+            .lock()
+            .await
+            .get::<ChunkMetadata>(&db_key)
+            .unwrap_or_default();
+        let mut live: BTreeSet<_> = metadata
             .holders
+            .iter()
+            .filter(|holder| adults.contains(holder))
+            .copied()
+            .collect();
+        if live.len() >= metadata.copy_count() {
+            info!(
+                "{}: Releasing {} of orphan {:?}, held by {} of our Adults",
+                self,
+                holder,
+                address,
+                live.len()
+            );
+            return Ok(vec![OrphanRelease::new(&offer)?.msg(holder)]);
+        }
+        if address.is_private() && metadata.owner.is_none() && offer.owner.is_some() {
+            metadata.owner = offer.owner;
+            self.dbs.metadata.lock().await.set(&db_key, &metadata)?;
+        }
+        info!(
+            "{}: Adopting orphan {:?} of {}, held by {} of our Adults",
+            self,
+            address,
+            holder,
+            live.len()
+        );
+        let _ = live.insert(holder);
+        Ok(self.get_replication_msgs(address, live).await)
+    }
+
+    /// Records the redundancy paid for a chunk. Holders are not
+    /// touched here, they are set when the chunk is stored.
+    pub(super) async fn set_redundancy(
+        &mut self,
+        blob_address: BlobAddress,
+        multiplier: u64,
+    ) -> Result<()> {
+        let db_key = blob_address.to_db_key()?;
+        let mut metadata = self
+            .dbs
+            .metadata
             .lock()
             .await
-            .set(&holder.to_db_key()?, &holders_metadata)
-        {
+            .get::<ChunkMetadata>(&db_key)
+            .unwrap_or_default();
+        let copies = CHUNK_COPY_COUNT * multiplier as usize;
+        if copies <= metadata.copy_count() {
+            return Ok(());
+        }
+        info!("{}: Setting {} copies for {:?}", self, copies, blob_address);
+        metadata.copies = Some(copies);
+        if let Err(error) = self.dbs.metadata.lock().await.set(&db_key, &metadata) {
             warn!("{}: Failed to write metadata to DB: {:?}", self, error);
-            return Err(error.into());
+            return Err(error);
         }
         Ok(())
     }
 
+    // Returns the number of copies to be kept of a chunk,
+    // which is above the standard count if premium was paid.
+    async fn copy_count_for(&self, address: BlobAddress) -> Result<usize> {
+        Ok(self
+            .dbs
+            .metadata
+            .lock()
+            .await
+            .get::<ChunkMetadata>(&address.to_db_key()?)
+            .map(|metadata| metadata.copy_count())
+            .unwrap_or(CHUNK_COPY_COUNT))
+    }
+
     async fn remove_chunk_holder(
         &mut self,
         blob_address: BlobAddress,
@@ -309,11 +847,81 @@ impl BlobRegister {
         Ok(())
     }
 
+    /// Removes the records of the chunks matching the deletion policy,
+    /// returning the holders of each, to be told to delete them.
+    pub(super) async fn remove_matching(
+        &mut self,
+        policy: &ChunkDeletionPolicy,
+    ) -> Result<BTreeMap<BlobAddress, BTreeSet<XorName>>> {
+        let addresses: Vec<BlobAddress> = match policy {
+            ChunkDeletionPolicy::Listed(addresses) => addresses.iter().copied().collect(),
+            ChunkDeletionPolicy::OwnedBy(owner) => {
+                let keys = self.dbs.metadata.lock().await.get_all();
+                let mut owned = vec![];
+                for key in keys {
+                    let address: BlobAddress = from_db_key(&key)?;
+                    if let Ok(metadata) = self.get_metadata_for(address).await {
+                        if metadata.owner.as_ref() == Some(owner) {
+                            owned.push(address);
+                        }
+                    }
+                }
+                owned
+            }
+        };
+        if addresses.len() > MAX_DELETION_BATCH {
+            return Err(Error::InvalidOperation(format!(
+                "Chunk deletion matches {} chunks, more than the max of {}",
+                addresses.len(),
+                MAX_DELETION_BATCH
+            )));
+        }
+
+        let mut chunks = BTreeMap::new();
+        for address in addresses {
+            // not known to the section, so nothing to delete
+            let metadata = match self.get_metadata_for(address).await {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            for holder in &metadata.holders {
+                self.remove_chunk_holder(address, *holder).await?;
+            }
+            let _ = chunks.insert(address, metadata.holders);
+        }
+        Ok(chunks)
+    }
+
+    /// Random chunks known to the section, up to the count, with those of their
+    /// holders still in the section, as they are to be challenged.
+    pub(super) async fn random_chunks(
+        &self,
+        count: usize,
+    ) -> Result<Vec<(BlobAddress, BTreeSet<XorName>)>> {
+        let keys = self.dbs.metadata.lock().await.get_all();
+        let adults: BTreeSet<_> = self.reader.our_adults().await.into_iter().collect();
+        let mut chunks = vec![];
+        for key in keys.choose_multiple(&mut rand::thread_rng(), count) {
+            let address: BlobAddress = from_db_key(key)?;
+            if let Ok(metadata) = self.get_metadata_for(address).await {
+                let live: BTreeSet<_> = metadata.holders.intersection(&adults).copied().collect();
+                if !live.is_empty() {
+                    chunks.push((address, live));
+                }
+            }
+        }
+        Ok(chunks)
+    }
+
     pub(super) async fn replicate_chunks(&mut self, holder: XorName) -> Result<NodeDuties> {
+        self.liveness.forget(&holder);
         let chunks_stored = match self.remove_holder(holder).await {
             Ok(chunks) => chunks,
             _ => return Ok(vec![]),
         };
+        if self.batch_replication {
+            return self.get_batched_replication_msgs(chunks_stored).await;
+        }
         let mut cmds = Vec::new();
         for (address, holders) in chunks_stored {
             cmds.extend(self.get_replication_msgs(address, holders).await);
@@ -321,60 +929,266 @@ impl BlobRegister {
         Ok(cmds)
     }
 
+    /// Copies the chunks of a holder about to leave the section, e.g. when decommissioned,
+    /// to new holders, and forgets it as their holder. Unlike when a holder has left, it is
+    /// still serving the chunks, so chunks held by it alone are copied from it too.
+    pub(super) async fn release_holder(&mut self, holder: XorName) -> Result<NodeDuties> {
+        let chunks = match self.get_holder(holder).await {
+            Ok(metadata) => metadata.chunks,
+            Err(_) => return Ok(vec![]),
+        };
+        let cmds = self.copy_chunks_of(holder, chunks).await;
+        let _ = self.remove_holder(holder).await?;
+        Ok(cmds)
+    }
+
+    /// Copies the chunks to new holders while the holder still serves them,
+    /// e.g. when it offloads them by its storage policy, and then no longer
+    /// records it as their holder.
+    pub(super) async fn release_chunks(
+        &mut self,
+        holder: XorName,
+        addresses: Vec<BlobAddress>,
+    ) -> Result<NodeDuties> {
+        let cmds = self.copy_chunks_of(holder, addresses.clone()).await;
+        for address in addresses {
+            self.remove_chunk_holder(address, holder).await?;
+        }
+        Ok(cmds)
+    }
+
+    // Tells new holders to copy the chunks recorded as held by the holder, from the
+    // current holders, the holder included, up to the copy count without it.
+    async fn copy_chunks_of(
+        &self,
+        holder: XorName,
+        chunks: impl IntoIterator<Item = BlobAddress>,
+    ) -> NodeDuties {
+        let mut cmds = Vec::new();
+        for address in chunks {
+            let metadata = match self.get_metadata_for(address).await {
+                Ok(metadata) if metadata.holders.contains(&holder) => metadata,
+                _ => continue,
+            };
+            let copy_count = self
+                .copy_count_for(address)
+                .await
+                .unwrap_or(CHUNK_COPY_COUNT);
+            let others = metadata.holders.iter().filter(|h| **h != holder).count();
+            let new_holders: Vec<_> = self
+                .get_holders_for_chunk(address.name(), copy_count + 1)
+                .await
+                .into_iter()
+                .filter(|adult| *adult != holder && !metadata.holders.contains(adult))
+                .take(copy_count.saturating_sub(others))
+                .collect();
+            for new_holder in new_holders {
+                info!(
+                    "{}: Copying {:?} of released holder {} to {}",
+                    self, address, holder, new_holder
+                );
+                cmds.push(replicate_chunk_msg(
+                    address,
+                    new_holder,
+                    metadata.holders.clone(),
+                ));
+            }
+        }
+        cmds
+    }
+
+    /// Re-verifies the holders of the chunks of the requester, and triggers
+    /// repair of any found to be degraded. Responds with a report per chunk.
+    /// The content of the copies needs no checking here, as the address of
+    /// a chunk is the hash of it, which holders check when replicating.
+    pub(super) async fn repair(
+        &mut self,
+        addresses: Vec<BlobAddress>,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        if addresses.len() > MAX_REPAIR_ADDRESSES {
+            return Err(Error::InvalidOperation(format!(
+                "Repair of {} chunks requested, at most {} per request",
+                addresses.len(),
+                MAX_REPAIR_ADDRESSES
+            )));
+        }
+        let adults: BTreeSet<_> = self.reader.our_adults().await.into_iter().collect();
+        let mut report = RepairReport::new();
+        let mut duties = vec![];
+        for address in addresses {
+            let (repair, replication) = self.repair_chunk(address, &adults, origin.id()).await?;
+            info!("{}: Repair of {:?}: {:?}", self, address, repair);
+            report.push((address, repair));
+            duties.extend(replication);
+        }
+        duties.push(NodeDuty::Send(
+            ClientMsg::RepairReport(report).msg(msg_id, origin)?,
+        ));
+        Ok(duties)
+    }
+
+    async fn repair_chunk(
+        &mut self,
+        address: BlobAddress,
+        adults: &BTreeSet<XorName>,
+        requester: &PublicKey,
+    ) -> Result<(ChunkRepair, NodeDuties)> {
+        let metadata = match self.get_metadata_for(address).await {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok((ChunkRepair::NoSuchChunk, vec![])),
+        };
+        if let Some(owner) = metadata.owner {
+            if &owner != requester {
+                return Ok((ChunkRepair::AccessDenied, vec![]));
+            }
+        }
+        let (live, lost): (BTreeSet<_>, BTreeSet<_>) = metadata
+            .holders
+            .into_iter()
+            .partition(|holder| adults.contains(holder));
+        if live.is_empty() {
+            // the holders are kept on record, should they rejoin
+            return Ok((ChunkRepair::Unrecoverable, vec![]));
+        }
+
+        let mut records_fixed = 0;
+        for holder in &live {
+            let mut holder_metadata = self.get_holder(*holder).await.unwrap_or_default();
+            if holder_metadata.chunks.insert(address) {
+                self.dbs
+                    .holders
+                    .lock()
+                    .await
+                    .set(&holder.to_db_key()?, &holder_metadata)?;
+                records_fixed += 1;
+            }
+        }
+        for holder in &lost {
+            self.remove_chunk_holder(address, *holder).await?;
+        }
+        let replication = self.get_replication_msgs(address, live.clone()).await;
+
+        let repair = if lost.is_empty() && records_fixed == 0 && replication.is_empty() {
+            ChunkRepair::Healthy {
+                holders: live.len(),
+            }
+        } else {
+            ChunkRepair::Repairing {
+                live: live.len(),
+                lost: lost.len(),
+                records_fixed,
+                new_holders: replication.len(),
+            }
+        };
+        Ok((repair, replication))
+    }
+
+    /// Has a holder reported to have failed a read of the chunk copy it from the other
+    /// holders, i.e. read repair. The incident is scored against the holder.
+    pub(super) async fn repair_holder(
+        &mut self,
+        address: BlobAddress,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        let metadata = match self.get_metadata_for(address).await {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(vec![]),
+        };
+        if !metadata.holders.contains(&holder) {
+            warn!(
+                "{}: Failed read of {:?} reported by {}, which is not a holder of it",
+                self, address, holder
+            );
+            return Ok(vec![]);
+        }
+        let incidents = self.read_incidents.entry(holder).or_default();
+        *incidents += 1;
+        let incidents = *incidents;
+        self.capacities.record_failure(holder, Instant::now());
+        warn!(
+            "{}: Holder {} failed a read of {:?} (read incident no. {})",
+            self, holder, address, incidents
+        );
+        let current_holders: BTreeSet<_> = metadata
+            .holders
+            .into_iter()
+            .filter(|other| *other != holder)
+            .collect();
+        if current_holders.is_empty() {
+            warn!(
+                "{}: No other holder of {:?} to repair {} from",
+                self, address, holder
+            );
+            return Ok(vec![]);
+        }
+        info!("{}: Repairing {} on read of {:?}", self, holder, address);
+        Ok(vec![replicate_chunk_msg(address, holder, current_holders)])
+    }
+
     async fn get_replication_msgs(
         &self,
         address: BlobAddress,
         current_holders: BTreeSet<XorName>,
     ) -> NodeDuties {
-        use NodeCmd::*;
-        let mut node_ops = Vec::new();
-        let messages = self
-            .get_new_holders_for_chunk(&address)
+        self.get_new_holders_for_chunk(&address)
             .await
             .into_iter()
             .map(|new_holder| {
-                let message_id = MessageId::combine(vec![*address.name(), new_holder]);
                 info!("Sending replicate-chunk cmd to NewHolder {:?}", new_holder);
-                (
-                    Message::NodeCmd {
-                        cmd: System(NodeSystemCmd::ReplicateChunk {
-                            new_holder,
-                            address,
-                            current_holders: current_holders.clone(),
-                        }),
-                        id: message_id,
-                        target_section_pk: None,
-                    },
-                    new_holder,
-                )
+                replicate_chunk_msg(address, new_holder, current_holders.clone())
             })
-            .collect::<Vec<_>>();
-        for (msg, dst) in messages {
-            node_ops.push(NodeDuty::Send(OutgoingMsg {
-                msg,
-                section_source: true, // i.e. errors go to our section
-                dst: DstLocation::Node(dst),
-                aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
-            }));
+            .collect()
+    }
+
+    async fn get_batched_replication_msgs(
+        &self,
+        chunks_stored: BTreeMap<BlobAddress, BTreeSet<XorName>>,
+    ) -> Result<NodeDuties> {
+        let mut chunks = Vec::new();
+        for (address, current_holders) in chunks_stored {
+            let new_holders = self.get_new_holders_for_chunk(&address).await;
+            chunks.push((address, new_holders, current_holders));
         }
-        node_ops
+        ReplicationBatch::group(chunks)
+            .iter()
+            .map(|batch| {
+                info!(
+                    "Sending {} chunks to replicate to NewHolder {:?}",
+                    batch.chunks.len(),
+                    batch.new_holder
+                );
+                batch.msg()
+            })
+            .collect()
     }
 
+    /// Reads the chunk, answering the client with only the bytes of the range, if one is given.
     pub(super) async fn read(
-        &self,
+        &mut self,
         read: &BlobRead,
+        range: Option<ChunkRange>,
         msg_id: MessageId,
         origin: EndUser,
-    ) -> Result<NodeDuty> {
+    ) -> Result<NodeDuties> {
         use BlobRead::*;
         match read {
-            Get(address) => self.get(*address, msg_id, origin).await,
+            Get(address) => {
+                if let Some(map) = self.shard_map(*address).await {
+                    return self.get_sharded(*address, map, range, msg_id, origin).await;
+                }
+                self.get(*address, range, msg_id, origin)
+                    .await
+                    .map(NodeDuties::from)
+            }
         }
     }
 
     async fn get(
-        &self,
+        &mut self,
         address: BlobAddress,
+        range: Option<ChunkRange>,
         msg_id: MessageId,
         origin: EndUser,
     ) -> Result<NodeDuty> {
@@ -404,6 +1218,39 @@ impl BlobRegister {
                 return query_error(Error::NetworkData(DtError::AccessDenied(*origin.id()))).await;
             }
         };
+        if let Some(holders_at_once) = self
+            .relayed_reads
+            .as_ref()
+            .map(RelayedReads::holders_at_once)
+        {
+            // the holders taking their turn first, the others after them, the fastest first
+            let first = self
+                .next_read_holders(metadata.holders.clone(), holders_at_once)
+                .await?;
+            let others = self
+                .reader
+                .probes()
+                .rank(metadata.holders.difference(&first).copied());
+            let holders = first.iter().copied().chain(others).collect();
+            if let Some(reads) = &mut self.relayed_reads {
+                let now = Instant::now();
+                let duty = reads.start(address, range, msg_id, origin, holders, now)?;
+                self.record_queried(std::slice::from_ref(&duty), now);
+                return Ok(duty);
+            }
+        }
+        let targets = self
+            .next_read_holders(metadata.holders, READ_HOLDER_COUNT)
+            .await?;
+        if let Some(range) = range {
+            let read = RangedChunkRead {
+                address,
+                range,
+                msg_id,
+                origin,
+            };
+            return Ok(read.msg(targets));
+        }
         let msg = Message::NodeQuery {
             query: NodeQuery::Chunks {
                 query: BlobRead::Get(address),
@@ -412,10 +1259,35 @@ impl BlobRegister {
             id: msg_id,
             target_section_pk: None,
         };
-        Ok(NodeDuty::SendToNodes {
-            targets: metadata.holders,
-            msg,
-        })
+        Ok(NodeDuty::SendToNodes { targets, msg })
+    }
+
+    // Returns the number of holders to serve the next read, taking turns
+    // among all holders, the more capable taking more turns. Holders losing
+    // many of our pings sit out, while enough others are left to take turns.
+    async fn next_read_holders(
+        &self,
+        mut holders: BTreeSet<XorName>,
+        count: usize,
+    ) -> Result<BTreeSet<XorName>> {
+        if holders.len() <= count {
+            return Ok(holders);
+        }
+        let probes = self.reader.probes();
+        let reliable: BTreeSet<_> = holders
+            .iter()
+            .filter(|holder| !probes.is_lossy(holder))
+            .copied()
+            .collect();
+        if reliable.len() >= count {
+            holders = reliable;
+        }
+        if holders.len() <= count {
+            return Ok(holders);
+        }
+        let capabilities = self.capabilities(holders.iter()).await?;
+        let turns = AdultCapacities::read_turns(&holders, &capabilities);
+        Ok(next_turns(&turns, &self.read_rotation, count))
     }
 
     #[allow(unused)]
@@ -463,13 +1335,17 @@ impl BlobRegister {
     ) -> Result<BTreeMap<BlobAddress, BTreeSet<XorName>>> {
         let mut blob_addresses: BTreeMap<BlobAddress, BTreeSet<XorName>> = BTreeMap::new();
         let chunk_holder = self.get_holder(node).await;
+        let mut transaction = Transaction::default();
+        let mut metadata_db = self.dbs.metadata.lock().await;
 
         if let Ok(holder) = chunk_holder {
             for chunk_address in holder.chunks {
                 let db_key = chunk_address.to_db_key()?;
-                let chunk_metadata = self.get_metadata_for(chunk_address).await;
+                let chunk_metadata = metadata_db
+                    .get::<ChunkMetadata>(&db_key)
+                    .filter(|metadata| !metadata.holders.is_empty());
 
-                if let Ok(mut metadata) = chunk_metadata {
+                if let Some(mut metadata) = chunk_metadata {
                     if !metadata.holders.remove(&node) {
                         warn!("doesn't contain the holder",);
                     }
@@ -477,20 +1353,24 @@ impl BlobRegister {
                     let _ = blob_addresses.insert(chunk_address, metadata.holders.clone());
 
                     if metadata.holders.is_empty() {
-                        if let Err(error) = self.dbs.metadata.lock().await.rem(&db_key) {
-                            warn!("{}: Failed to write metadata to DB: {:?}", self, error);
-                        }
-                    } else if let Err(error) =
-                        self.dbs.metadata.lock().await.set(&db_key, &metadata)
-                    {
-                        warn!("{}: Failed to write metadata to DB: {:?}", self, error);
+                        transaction.rem(&metadata_db, &db_key);
+                    } else {
+                        transaction.set(&metadata_db, &db_key, &metadata)?;
                     }
                 }
             }
         }
 
-        // Since the node has left the section, remove it from the holders DB
-        if let Err(error) = self.dbs.holders.lock().await.rem(&node.to_db_key()?) {
+        // Since the node has left the section, remove it from the holders DB,
+        // along with the chunks it held, so that no chunk is left recorded as held by it
+        let mut holders_db = self.dbs.holders.lock().await;
+        let mut capabilities_db = self.dbs.capabilities.lock().await;
+        transaction.rem(&holders_db, &node.to_db_key()?);
+        transaction.rem(&capabilities_db, &node.to_db_key()?);
+        if let Err(error) = self.dbs.transactions.commit(
+            transaction,
+            &mut [&mut metadata_db, &mut holders_db, &mut capabilities_db],
+        ) {
             warn!("{}: Failed to delete metadata from DB: {:?}", self, error);
         };
 
@@ -543,19 +1423,134 @@ impl BlobRegister {
         }
     }
 
+    /// The keys of all chunk metadata records.
+    pub(super) async fn record_keys(&self) -> Result<Vec<RecordKey>> {
+        let keys = self.dbs.metadata.lock().await.get_all();
+        keys.iter()
+            .map(|key| Ok(RecordKey::Chunk(from_db_key(key)?)))
+            .collect()
+    }
+
+    /// The metadata record of the chunk, if any.
+    pub(super) async fn record(&self, address: BlobAddress) -> Option<MetadataRecord> {
+        let metadata = self.get_metadata_for(address).await.ok()?;
+        Some(MetadataRecord::Chunk {
+            address,
+            holders: metadata.holders,
+            owner: metadata.owner,
+            copies: metadata.copies,
+        })
+    }
+
+    /// The addresses of all chunks stored erasure coded.
+    pub(super) async fn shard_map_keys(&self) -> Result<Vec<RecordKey>> {
+        let keys = self.dbs.shard_maps.lock().await.get_all();
+        keys.iter()
+            .map(|key| Ok(RecordKey::ShardedChunk(from_db_key(key)?)))
+            .collect()
+    }
+
+    /// The record of the shards of the chunk, if it is stored erasure coded.
+    pub(super) async fn shard_map_record(&self, address: BlobAddress) -> Option<MetadataRecord> {
+        let shards = self.shard_map(address).await?;
+        Some(MetadataRecord::ShardedChunk { address, shards })
+    }
+
+    /// Takes over the record of the shards of the chunk, as held by another Elder.
+    pub(super) async fn take_over_shard_map(
+        &mut self,
+        address: BlobAddress,
+        shards: &ShardMap,
+    ) -> Result<()> {
+        self.dbs
+            .shard_maps
+            .lock()
+            .await
+            .set(&address.to_db_key()?, shards)
+    }
+
+    /// Forgets the shards of the chunk, e.g. once handed over to our sibling.
+    pub(super) async fn remove_shard_map(&mut self, address: BlobAddress) -> Result<()> {
+        let _ = self
+            .dbs
+            .shard_maps
+            .lock()
+            .await
+            .rem(&address.to_db_key()?)?;
+        Ok(())
+    }
+
+    /// Takes over the record of the chunk, as held by another Elder, replacing ours.
+    pub(super) async fn take_over(
+        &mut self,
+        address: BlobAddress,
+        holders: BTreeSet<XorName>,
+        owner: Option<PublicKey>,
+        copies: Option<usize>,
+    ) -> Result<()> {
+        if let Ok(ours) = self.get_metadata_for(address).await {
+            for holder in ours.holders.difference(&holders) {
+                self.remove_chunk_holder(address, *holder).await?;
+            }
+        }
+        for holder in &holders {
+            let mut holder_metadata = self.get_holder(*holder).await.unwrap_or_default();
+            if holder_metadata.chunks.insert(address) {
+                self.dbs
+                    .holders
+                    .lock()
+                    .await
+                    .set(&holder.to_db_key()?, &holder_metadata)?;
+            }
+        }
+        let metadata = ChunkMetadata {
+            holders,
+            owner,
+            copies,
+        };
+        self.dbs
+            .metadata
+            .lock()
+            .await
+            .set(&address.to_db_key()?, &metadata)
+    }
+
+    /// Removes the record of the chunk, as removed at another Elder.
+    pub(super) async fn remove_record(&mut self, address: BlobAddress) -> Result<()> {
+        if let Ok(metadata) = self.get_metadata_for(address).await {
+            for holder in metadata.holders {
+                self.remove_chunk_holder(address, holder).await?;
+            }
+        }
+        Ok(())
+    }
+
     // Returns `XorName`s of the target holders for an Blob chunk.
     // Used to fetch the list of holders for a new chunk.
-    async fn get_holders_for_chunk(&self, target: &XorName) -> Vec<XorName> {
-        self.reader
-            .our_adults_sorted_by_distance_to(&target, CHUNK_COPY_COUNT)
+    // The closest Adults are chosen, skipping those nearly full while others have room,
+    // and those found unresponsive lately.
+    async fn get_holders_for_chunk(&self, target: &XorName, count: usize) -> Vec<XorName> {
+        let adults: Vec<_> = self
+            .reader
+            .our_adults_sorted_by_distance_to(&target, usize::MAX)
             .await
+            .into_iter()
+            .filter(|adult| !self.liveness.is_unresponsive(adult))
+            .collect();
+        let capabilities = self.capabilities(adults.iter()).await.unwrap_or_default();
+        self.capacities
+            .choose_holders(adults, count, &capabilities, Instant::now())
     }
 
     // Returns `XorName`s of the new target holders for an Blob chunk.
     // Used to fetch the additional list of holders for existing chunks.
     async fn get_new_holders_for_chunk(&self, target: &BlobAddress) -> BTreeSet<XorName> {
+        let copy_count = self
+            .copy_count_for(*target)
+            .await
+            .unwrap_or(CHUNK_COPY_COUNT);
         let closest_holders = self
-            .get_holders_for_chunk(target.name())
+            .get_holders_for_chunk(target.name(), copy_count)
             .await
             .iter()
             .cloned()
@@ -570,8 +1565,103 @@ impl BlobRegister {
     }
 }
 
+fn replicate_chunk_msg(
+    address: BlobAddress,
+    new_holder: XorName,
+    current_holders: BTreeSet<XorName>,
+) -> NodeDuty {
+    let message_id = MessageId::combine(vec![*address.name(), new_holder]);
+    NodeDuty::Send(OutgoingMsg {
+        msg: Message::NodeCmd {
+            cmd: NodeCmd::System(NodeSystemCmd::ReplicateChunk {
+                new_holder,
+                address,
+                current_holders,
+            }),
+            id: message_id,
+            target_section_pk: None,
+        },
+        section_source: true, // i.e. errors go to our section
+        dst: DstLocation::Node(new_holder),
+        aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
+    })
+}
+
+// The holders taking the next `count` turns, as counted by the rotation,
+// or all holders if there are no more than `count` of them.
+fn next_turns(turns: &[XorName], rotation: &AtomicUsize, count: usize) -> BTreeSet<XorName> {
+    let holders: BTreeSet<_> = turns.iter().copied().collect();
+    if holders.len() <= count {
+        return holders;
+    }
+    let offset = rotation.fetch_add(1, Ordering::Relaxed) % turns.len();
+    let mut next = BTreeSet::new();
+    for holder in turns.iter().cycle().skip(offset) {
+        let _ = next.insert(*holder);
+        if next.len() == count {
+            break;
+        }
+    }
+    next
+}
+
 impl Display for BlobRegister {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
         write!(formatter, "BlobRegister")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_rotate_across_the_holders() {
+        let rotation = AtomicUsize::new(0);
+        let holders: Vec<_> = (0..3).map(|_| XorName::random()).collect();
+        let reads: Vec<_> = (0..3)
+            .map(|_| next_turns(&holders, &rotation, READ_HOLDER_COUNT))
+            .collect();
+        for read in &reads {
+            assert_eq!(read.len(), READ_HOLDER_COUNT);
+        }
+        // each read starts with the next holder, so all take their turns
+        let expected: Vec<BTreeSet<_>> = (0..3)
+            .map(|turn| {
+                vec![holders[turn], holders[(turn + 1) % 3]]
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        assert_eq!(reads, expected);
+        // the more turns a holder takes, the more reads it serves
+        let turns = vec![holders[0], holders[0], holders[1], holders[2]];
+        let served_by_first = (0..4)
+            .map(|_| next_turns(&turns, &rotation, 1))
+            .filter(|read| read.contains(&holders[0]))
+            .count();
+        assert_eq!(served_by_first, 2);
+    }
+
+    #[test]
+    fn reads_go_to_all_of_fewer_holders() {
+        let rotation = AtomicUsize::new(0);
+        assert!(next_turns(&[], &rotation, READ_HOLDER_COUNT).is_empty());
+
+        let holder = XorName::random();
+        let single: BTreeSet<_> = vec![holder].into_iter().collect();
+        for _ in 0..3 {
+            assert_eq!(
+                next_turns(&[holder, holder], &rotation, READ_HOLDER_COUNT),
+                single
+            );
+        }
+        let holders = vec![XorName::random(), XorName::random()];
+        assert_eq!(
+            next_turns(&holders, &rotation, READ_HOLDER_COUNT),
+            holders.iter().copied().collect()
+        );
+        // the rotation only moves when the holders take turns
+        assert_eq!(rotation.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,518 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::range_export::{MetadataPage, MetadataRangeQuery, MetadataRecord, RecordKey};
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    utils, Result,
+};
+use bls::PublicKeySet;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{PublicKey, SignatureShare};
+use sn_messaging::DstLocation;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::{Prefix, XorName};
+
+/// How often the catch-up of a new Elder on the metadata of the section is checked on.
+pub(crate) const CATCH_UP_TICK: Duration = Duration::from_secs(5);
+// The time an Elder has to answer a catch-up query, before the catch-up
+// is started over with the next Elder.
+const CATCH_UP_TIMEOUT: Duration = Duration::from_secs(20);
+// The rounds of changes caught up on after the snapshot at most, past which those still
+// being made are left to reach the new Elder as the writes made to it.
+const MAX_CHANGE_ROUNDS: usize = 3;
+// The changes remembered at most, for new Elders to catch up on.
+const MAX_CHANGES: usize = 100_000;
+
+/// A query by a newly promoted Elder to another Elder of the section for a page of
+/// its metadata records, either of all of them, as a snapshot, or of those changed
+/// since a mark.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatchUpQuery {
+    /// The range of the section, continued after the last record received.
+    pub range: MetadataRangeQuery,
+    /// The mark of the changes at the Elder already caught up on, if past the snapshot.
+    pub since: Option<u64>,
+    /// The attempt at the catch-up, telling the queries of each apart.
+    pub attempt: u32,
+}
+
+impl CatchUpQuery {
+    /// The msg asking the Elder.
+    pub(crate) fn msg(&self, elder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::CatchUpQuery(self.clone())),
+            dst: DstLocation::Node(elder),
+        }
+    }
+}
+
+/// A page of the metadata records of the section, answering a catch-up query.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CatchUpPage {
+    /// Key of the section, as known to the Elder answering.
+    pub section_key: PublicKey,
+    /// The query answered.
+    pub query: CatchUpQuery,
+    /// The records.
+    pub page: MetadataPage,
+    /// The names changed since the mark of the query, those of them without records in
+    /// the pages of the round having been removed. None when all records are sent,
+    /// as for the snapshot, or when the changes since the mark are no longer known.
+    pub changed: Option<BTreeSet<XorName>>,
+    /// The mark of the changes at the Elder as of the page.
+    pub mark: u64,
+}
+
+/// The page, signed by the Elder answering with its share of the key of the section,
+/// so that only pages of the Elders of the section we know are caught up on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignedCatchUpPage {
+    /// The page.
+    pub page: CatchUpPage,
+    /// The share of the Elder of the signature of the section over the page.
+    pub share: SignatureShare,
+}
+
+impl SignedCatchUpPage {
+    /// The msg answering the new Elder. The page carries the query it answers,
+    /// which the new Elder matches against the query pending.
+    pub(crate) fn msg(&self, elder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::SignedCatchUpPage(self.clone())),
+            dst: DstLocation::Node(elder),
+        }
+    }
+
+    /// Whether the page was signed by an Elder of the section of the key set.
+    pub(crate) fn verify(&self, key_set: &PublicKeySet) -> Result<bool> {
+        Ok(
+            self.page.section_key == PublicKey::Bls(key_set.public_key())
+                && key_set
+                    .public_key_share(self.share.index)
+                    .verify(&self.share.share, utils::serialise(&self.page)?),
+        )
+    }
+}
+
+/// The names of the records changed by the writes to us, by a mark increasing with
+/// each, for new Elders to catch up on the changes made since their snapshot.
+/// The holders of chunks are not logged, as each Elder keeps them up on churn.
+#[derive(Default)]
+pub(super) struct ChangeLog {
+    changes: BTreeMap<u64, XorName>,
+    // the mark of the next change
+    next: u64,
+}
+
+impl ChangeLog {
+    pub(super) fn record(&mut self, name: XorName) {
+        let _ = self.changes.insert(self.next, name);
+        self.next += 1;
+        if self.changes.len() > MAX_CHANGES {
+            let _ = self.changes.pop_first();
+        }
+    }
+
+    /// The mark of the changes as of now.
+    pub(super) fn mark(&self) -> u64 {
+        self.next
+    }
+
+    /// The names changed since the mark, unless the changes since are no longer known,
+    /// either forgotten, or of before we were last started.
+    pub(super) fn changed_since(&self, mark: u64) -> Option<BTreeSet<XorName>> {
+        let first = self.changes.keys().next().copied().unwrap_or(self.next);
+        if mark < first || mark > self.next {
+            return None;
+        }
+        Some(self.changes.range(mark..).map(|(_, name)| *name).collect())
+    }
+}
+
+/// The records of a page to take over, and, at the end of a round, those removed.
+pub(super) struct CaughtUpPage {
+    pub(super) records: Vec<(RecordKey, MetadataRecord)>,
+    pub(super) removed: Option<Removed>,
+}
+
+/// The records of the section removed at the Elder caught up from, as told by those not
+/// received in a round: of the names changed in it, or of all names, in a whole round.
+pub(super) struct Removed {
+    prefix: Prefix,
+    changed: Option<BTreeSet<XorName>>,
+    received: BTreeSet<RecordKey>,
+}
+
+impl Removed {
+    /// Whether our record of the key was removed.
+    pub(super) fn contains(&self, key: &RecordKey) -> bool {
+        self.prefix.matches(key.name())
+            && !self.received.contains(key)
+            && self
+                .changed
+                .as_ref()
+                .is_none_or(|changed| changed.contains(key.name()))
+    }
+}
+
+/// The catch-up of a newly promoted Elder on the metadata of the section, from one other
+/// Elder at a time: a snapshot of all records, followed by rounds of the changes made
+/// since the previous round began, until no more are, before reads are answered by it.
+/// An Elder not answering in time is given up on, and the catch-up started over
+/// with the next, as the marks of the changes of each are their own.
+pub(super) struct CatchUp {
+    // the other Elders, the one caught up from first
+    elders: Vec<XorName>,
+    query: CatchUpQuery,
+    sent_at: Instant,
+    // the mark of the first page of the round, which the changes of the next round are since
+    round_mark: u64,
+    rounds: usize,
+    // whether all records were sent in the round, rather than those changed alone
+    round_whole: bool,
+    changed: BTreeSet<XorName>,
+    received: BTreeSet<RecordKey>,
+}
+
+impl CatchUp {
+    /// The catch-up from the other Elders, with the query to ask the first by,
+    /// unless there are none to catch up from, e.g. at genesis.
+    pub(super) fn new(
+        prefix: Prefix,
+        elders: BTreeSet<XorName>,
+        now: Instant,
+    ) -> Option<(Self, XorName, CatchUpQuery)> {
+        let elders: Vec<_> = elders.into_iter().collect();
+        let first = *elders.first()?;
+        let catch_up = Self {
+            elders,
+            query: snapshot_query(prefix, 0),
+            sent_at: now,
+            round_mark: 0,
+            rounds: 0,
+            round_whole: true,
+            changed: BTreeSet::new(),
+            received: BTreeSet::new(),
+        };
+        let query = catch_up.query.clone();
+        Some((catch_up, first, query))
+    }
+
+    /// The Elder caught up from.
+    pub(super) fn elder(&self) -> Option<XorName> {
+        self.elders.first().copied()
+    }
+
+    /// Takes the page in, if it answers the query pending, returning the records to take
+    /// over, and the query for the next page, unless caught up.
+    pub(super) fn receive(
+        &mut self,
+        elder: XorName,
+        page: CatchUpPage,
+        now: Instant,
+    ) -> Option<(CaughtUpPage, Option<CatchUpQuery>)> {
+        if self.elder() != Some(elder) || page.query != self.query {
+            return None;
+        }
+        if self.query.range.after.is_none() {
+            self.round_mark = page.mark;
+            self.round_whole = false;
+            self.changed.clear();
+            self.received.clear();
+        }
+        match page.changed {
+            Some(names) => self.changed.extend(names),
+            None => self.round_whole = true,
+        }
+        self.received
+            .extend(page.page.records.iter().map(|(key, _)| *key));
+        self.sent_at = now;
+
+        if let Some(range) = page.page.next {
+            self.query.range = range;
+            let caught_up = CaughtUpPage {
+                records: page.page.records,
+                removed: None,
+            };
+            return Some((caught_up, Some(self.query.clone())));
+        }
+
+        // the round is complete
+        let removed = Removed {
+            prefix: self.query.range.prefix,
+            changed: (!self.round_whole).then(|| std::mem::take(&mut self.changed)),
+            received: std::mem::take(&mut self.received),
+        };
+        let no_changes = matches!(&removed.changed, Some(changed) if changed.is_empty());
+        let caught_up = CaughtUpPage {
+            records: page.page.records,
+            removed: Some(removed),
+        };
+        if no_changes || self.rounds >= MAX_CHANGE_ROUNDS {
+            info!(
+                "Caught up on metadata from {} after {} rounds of changes",
+                elder, self.rounds
+            );
+            return Some((caught_up, None));
+        }
+        self.rounds += 1;
+        self.query = CatchUpQuery {
+            range: MetadataRangeQuery {
+                prefix: self.query.range.prefix,
+                after: None,
+            },
+            since: Some(self.round_mark),
+            attempt: self.query.attempt,
+        };
+        Some((caught_up, Some(self.query.clone())))
+    }
+
+    /// The Elder to ask, and the query to ask it by, when the one asked has not
+    /// answered in time, or is no longer an Elder of ours, starting the catch-up over
+    /// with the next. None when there is no one to ask anew, or none left to ask at all,
+    /// as told by `is_stalled`.
+    pub(super) fn check(
+        &mut self,
+        elders: &BTreeSet<XorName>,
+        now: Instant,
+    ) -> Option<(XorName, CatchUpQuery)> {
+        let asked = self.elder()?;
+        let timed_out = now.saturating_duration_since(self.sent_at) >= CATCH_UP_TIMEOUT;
+        if elders.contains(&asked) && !timed_out {
+            return None;
+        }
+        warn!(
+            "Elder {} not answering the catch-up on metadata, starting over with the next",
+            asked
+        );
+        self.elders.rotate_left(1);
+        self.elders.retain(|elder| elders.contains(elder));
+        let next = self.elder()?;
+        self.query = snapshot_query(self.query.range.prefix, self.query.attempt + 1);
+        self.sent_at = now;
+        self.rounds = 0;
+        Some((next, self.query.clone()))
+    }
+
+    /// Whether there are no Elders left to catch up from.
+    pub(super) fn is_stalled(&self) -> bool {
+        self.elders.is_empty()
+    }
+}
+
+fn snapshot_query(prefix: Prefix, attempt: u32) -> CatchUpQuery {
+    CatchUpQuery {
+        range: MetadataRangeQuery {
+            prefix,
+            after: None,
+        },
+        since: None,
+        attempt,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKeySet;
+    use sn_data_types::BlobAddress;
+
+    fn page(
+        query: &CatchUpQuery,
+        records: Vec<RecordKey>,
+        next: Option<RecordKey>,
+        changed: Option<Vec<XorName>>,
+        mark: u64,
+    ) -> CatchUpPage {
+        CatchUpPage {
+            section_key: PublicKey::Bls(bls::SecretKey::random().public_key()),
+            query: query.clone(),
+            page: MetadataPage {
+                records: records
+                    .into_iter()
+                    .map(|key| {
+                        let record = MetadataRecord::Chunk {
+                            address: match key {
+                                RecordKey::Chunk(address) => address,
+                                _ => BlobAddress::Public(*key.name()),
+                            },
+                            holders: BTreeSet::new(),
+                            owner: None,
+                            copies: None,
+                        };
+                        (key, record)
+                    })
+                    .collect(),
+                next: next.map(|after| MetadataRangeQuery {
+                    prefix: query.range.prefix,
+                    after: Some(after),
+                }),
+            },
+            changed: changed.map(|names| names.into_iter().collect()),
+            mark,
+        }
+    }
+
+    #[test]
+    fn changes_are_known_since_the_marks_remembered() {
+        let mut log = ChangeLog::default();
+        let (first, second) = (XorName::random(), XorName::random());
+        log.record(first);
+        let mark = log.mark();
+        log.record(second);
+        log.record(second);
+        assert_eq!(
+            log.changed_since(mark),
+            Some(vec![second].into_iter().collect())
+        );
+        assert_eq!(log.changed_since(log.mark()), Some(BTreeSet::new()));
+        // of before we were started
+        assert_eq!(log.changed_since(log.mark() + 1), None);
+
+        for _ in 0..MAX_CHANGES {
+            log.record(second);
+        }
+        assert_eq!(log.changed_since(0), None);
+    }
+
+    #[test]
+    fn snapshot_is_followed_by_rounds_of_changes_until_none() {
+        let prefix = Prefix::default();
+        let (elder, other) = (XorName::random(), XorName::random());
+        let now = Instant::now();
+        let (mut catch_up, asked, query) =
+            CatchUp::new(prefix, vec![elder, other].into_iter().collect(), now)
+                .expect("Elders to catch up from");
+        assert_eq!(asked, catch_up.elder().expect("an Elder"));
+        assert_eq!(query.since, None);
+
+        // pages of the snapshot, the first marking the changes the next round is of
+        let (low, high) = (
+            RecordKey::Chunk(BlobAddress::Public(XorName([0x01; 32]))),
+            RecordKey::Chunk(BlobAddress::Public(XorName([0xf0; 32]))),
+        );
+        let first = page(&query, vec![low], Some(low), None, 7);
+        // answered by another, or not answering the query pending
+        let wrong = if asked == elder { other } else { elder };
+        assert!(catch_up.receive(wrong, first.clone(), now).is_none());
+        let (caught_up, next) = catch_up.receive(asked, first, now).expect("the page");
+        assert_eq!(caught_up.records.len(), 1);
+        assert!(caught_up.removed.is_none());
+        let next = next.expect("the next page");
+        assert_eq!(next.range.after, Some(low));
+        let (caught_up, next) = catch_up
+            .receive(asked, page(&next, vec![high], None, None, 9), now)
+            .expect("the page");
+        // of all records, those not in the snapshot were removed
+        let removed = caught_up.removed.expect("the records of the snapshot");
+        let stale = RecordKey::DataMap(XorName::random());
+        assert!(removed.contains(&stale));
+        assert!(!removed.contains(&low) && !removed.contains(&high));
+        let changes = next.expect("the changes since the snapshot");
+        assert_eq!(changes.since, Some(7));
+
+        // high changed since, and a name with no record removed
+        let removed = XorName::random();
+        let (caught_up, next) = catch_up
+            .receive(
+                asked,
+                page(
+                    &changes,
+                    vec![high],
+                    None,
+                    Some(vec![*high.name(), removed]),
+                    12,
+                ),
+                now,
+            )
+            .expect("the page");
+        let round = caught_up.removed.expect("the changes of the round");
+        assert!(round.contains(&RecordKey::DataMap(removed)));
+        assert!(!round.contains(&high) && !round.contains(&stale));
+        let changes = next.expect("the changes since the round");
+        assert_eq!(changes.since, Some(12));
+
+        // caught up once there are no more
+        let (_, next) = catch_up
+            .receive(asked, page(&changes, vec![], None, Some(vec![]), 12), now)
+            .expect("the page");
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn catch_up_is_started_over_with_the_next_elder() {
+        let elders: BTreeSet<_> = (0..2).map(|_| XorName::random()).collect();
+        let mut now = Instant::now();
+        let (mut catch_up, asked, _) =
+            CatchUp::new(Prefix::default(), elders.clone(), now).expect("Elders to catch up from");
+        assert!(catch_up.check(&elders, now).is_none());
+
+        now += CATCH_UP_TIMEOUT;
+        let (next, query) = catch_up.check(&elders, now).expect("the next Elder");
+        assert_ne!(next, asked);
+        assert_eq!((query.since, query.attempt), (None, 1));
+
+        // gone, with no one left to ask
+        let remaining = vec![asked].into_iter().collect();
+        assert_eq!(
+            catch_up.check(&remaining, now).map(|(elder, _)| elder),
+            Some(asked)
+        );
+        assert!(catch_up.check(&BTreeSet::new(), now).is_none());
+        assert!(catch_up.is_stalled());
+    }
+
+    #[test]
+    fn pages_of_elders_of_the_section_alone_are_caught_up_on() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let secret_key_set = SecretKeySet::random(1, &mut rng);
+        let key_set = secret_key_set.public_keys();
+        let query = snapshot_query(Prefix::default(), 0);
+        let mut page = page(&query, vec![], None, None, 0);
+        page.section_key = PublicKey::Bls(key_set.public_key());
+        let share = SignatureShare {
+            index: 1,
+            share: secret_key_set
+                .secret_key_share(1)
+                .sign(utils::serialise(&page)?),
+        };
+        let signed = SignedCatchUpPage { page, share };
+        assert!(signed.verify(&key_set)?);
+
+        let other = SecretKeySet::random(1, &mut rng).public_keys();
+        assert!(!signed.verify(&other)?);
+        let mut forged = signed.clone();
+        forged.page.mark = 1;
+        assert!(!forged.verify(&key_set)?);
+
+        // the page is sent to the new Elder, carrying the query it answers
+        let elder = XorName::random();
+        match signed.msg(elder) {
+            NodeDuty::SendNodeMsg {
+                msg:
+                    NodeMsg {
+                        body: NodeMsgBody::SignedCatchUpPage(sent),
+                        ..
+                    },
+                dst: DstLocation::Node(dst),
+            } => {
+                assert_eq!(sent, signed);
+                assert_eq!(sent.page.query, query);
+                assert_eq!(dst, elder);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    chunks::ChunkAccess,
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    Result,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sn_data_types::BlobAddress;
+use sn_messaging::{DstLocation, MessageId};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+use xor_name::XorName;
+
+/// How often the Elders query their Adults for the reads of the chunks they hold.
+pub(crate) const CHUNK_ACCESS_TICK: Duration = Duration::from_secs(10 * 60);
+/// The chunks reported on by a holder at most, the most read first.
+pub const MAX_CHUNK_ACCESS_REPORT: usize = 100;
+
+/// A query by an Elder for the reads of chunks held by an Adult.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkAccessQuery {
+    /// The chunks to report on, or if none, the most read chunks of the holder.
+    pub addresses: Vec<BlobAddress>,
+    /// Tells the queries of rounds apart, which are on the same chunks.
+    pub nonce: u64,
+}
+
+impl ChunkAccessQuery {
+    /// The id of the msg of the query, which the holders answer it under.
+    pub(crate) fn id(&self) -> Result<MessageId> {
+        Ok(self.msg()?.id)
+    }
+
+    // The msg querying the holders, under an id derived from the query,
+    // so that holders know the id it is answered under from the query alone.
+    fn msg(&self) -> Result<NodeMsg> {
+        NodeMsg::derived(NodeMsgBody::ChunkAccessQuery(self.clone()))
+    }
+}
+
+/// The answer of a holder to a query for the reads of its chunks,
+/// listing only the chunks held by it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkAccessReport {
+    /// Id of the msg of the query answered.
+    pub query: MessageId,
+    /// The reads of each chunk.
+    pub chunks: Vec<(BlobAddress, ChunkAccess)>,
+}
+
+impl ChunkAccessReport {
+    /// The msg answering the Elder querying us.
+    pub(crate) fn msg(&self, elder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::ChunkAccessReport(self.clone())),
+            dst: DstLocation::Node(elder),
+        }
+    }
+}
+
+/// The reads of the chunks held by each of our Adults, as last reported by it.
+#[derive(Default)]
+pub(super) struct ChunkPopularity {
+    // the id of the query of the latest round
+    round: Option<MessageId>,
+    reports: BTreeMap<XorName, Vec<(BlobAddress, ChunkAccess)>>,
+}
+
+impl ChunkPopularity {
+    /// Queries the holders for their most read chunks, starting a new round,
+    /// and forgets the reports of those no longer Adults of ours.
+    pub(super) fn query(&mut self, holders: BTreeSet<XorName>) -> Result<Vec<NodeDuty>> {
+        self.reports.retain(|holder, _| holders.contains(holder));
+        if holders.is_empty() {
+            return Ok(vec![]);
+        }
+        let query = ChunkAccessQuery {
+            addresses: vec![],
+            nonce: rand::thread_rng().gen(),
+        };
+        let msg = query.msg()?;
+        self.round = Some(msg.id);
+        Ok(vec![NodeDuty::SendNodeMsgToNodes {
+            targets: holders,
+            msg,
+        }])
+    }
+
+    /// Records the report of the holder, if answering the latest round.
+    pub(super) fn record(&mut self, holder: XorName, report: ChunkAccessReport) -> bool {
+        if self.round != Some(report.query) {
+            return false;
+        }
+        let mut chunks = report.chunks;
+        chunks.truncate(MAX_CHUNK_ACCESS_REPORT);
+        let _ = self.reports.insert(holder, chunks);
+        true
+    }
+
+    pub(super) fn reports(&self) -> BTreeMap<XorName, Vec<(BlobAddress, ChunkAccess)>> {
+        self.reports.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_reports_of_the_latest_round_are_recorded() -> Result<()> {
+        let (holder, gone) = (XorName::random(), XorName::random());
+        let mut popularity = ChunkPopularity::default();
+        let _ = popularity.query(vec![holder, gone].into_iter().collect())?;
+        let first = popularity.round.unwrap();
+        let chunk = (
+            BlobAddress::Public(XorName::random()),
+            ChunkAccess::default(),
+        );
+        assert!(popularity.record(
+            gone,
+            ChunkAccessReport {
+                query: first,
+                chunks: vec![chunk],
+            }
+        ));
+
+        // a new round forgets the holders gone, and ignores late reports
+        let _ = popularity.query(vec![holder].into_iter().collect())?;
+        assert!(!popularity.record(
+            holder,
+            ChunkAccessReport {
+                query: first,
+                chunks: vec![chunk],
+            }
+        ));
+        assert!(popularity.reports().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{to_db_key::ToDbKey, utils, Error, Result};
+use log::{info, warn};
+use pickledb::PickleDb;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{BlobAddress, Error as DtError, PublicKey, Signature};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+use xor_name::XorName;
+
+const CHUNK_DELETIONS_DB_NAME: &str = "chunk_deletions.db";
+/// The max number of chunks a single deletion can match.
+pub(super) const MAX_DELETION_BATCH: usize = 10_000;
+
+/// Selects the chunks to be deleted by a section policy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkDeletionPolicy {
+    /// The chunks listed, e.g. those expired from quarantine, found unreferenced
+    /// by garbage collection, or on the takedown list of a private network.
+    Listed(BTreeSet<BlobAddress>),
+    /// All private chunks of the owner.
+    OwnedBy(PublicKey),
+}
+
+/// A bulk deletion of chunks, carried out once the Elders of the section agree on it.
+/// Each Elder is given the same deletion, and the holders of the chunks only delete
+/// them once enough Elders have signed the deletion as the section.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkDeletion {
+    /// The chunks to delete.
+    pub policy: ChunkDeletionPolicy,
+    /// Why the chunks are deleted, as kept in the audit trail.
+    pub reason: String,
+}
+
+impl ChunkDeletion {
+    /// Id of the deletion, the same at all Elders given it.
+    pub fn id(&self) -> Result<XorName> {
+        Ok(XorName::from_content(&[&utils::serialise(self)?]))
+    }
+}
+
+/// The audit record of a deletion.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletionRecord {
+    /// The deletion carried out.
+    pub deletion: ChunkDeletion,
+    /// Seconds since unix epoch at which the deletion started.
+    pub started_at: u64,
+    /// The holders of each chunk deleted, and whether they have acknowledged deleting it.
+    pub chunks: BTreeMap<BlobAddress, BTreeMap<XorName, bool>>,
+}
+
+impl DeletionRecord {
+    fn is_complete(&self) -> bool {
+        self.chunks
+            .values()
+            .all(|holders| holders.values().all(|acked| *acked))
+    }
+}
+
+/// The audit trail of chunk deletions, kept on disk.
+pub(super) struct DeletionAudit {
+    db: PickleDb,
+}
+
+impl DeletionAudit {
+    pub(super) fn new(path: &Path) -> Result<Self> {
+        Ok(Self {
+            db: utils::new_auto_dump_db(path, CHUNK_DELETIONS_DB_NAME)?,
+        })
+    }
+
+    /// Records the start of the deletion, with the holders asked to delete each chunk.
+    /// A deletion can only be carried out once. `now` is the secs since the unix epoch.
+    pub(super) fn start(
+        &mut self,
+        deletion: ChunkDeletion,
+        chunks: &BTreeMap<BlobAddress, BTreeSet<XorName>>,
+        now: u64,
+    ) -> Result<()> {
+        let key = deletion.id()?.to_db_key()?;
+        if self.db.exists(&key) {
+            return Err(Error::InvalidOperation(format!(
+                "Chunk deletion already carried out: {:?}",
+                deletion
+            )));
+        }
+        info!(
+            "Deleting {} chunks by section policy {:?}, because: {}",
+            chunks.len(),
+            deletion.policy,
+            deletion.reason
+        );
+        let record = DeletionRecord {
+            deletion,
+            started_at: now,
+            chunks: chunks
+                .iter()
+                .map(|(address, holders)| {
+                    let holders = holders.iter().map(|holder| (*holder, false)).collect();
+                    (*address, holders)
+                })
+                .collect(),
+        };
+        self.db.set(&key, &record)?;
+        Ok(())
+    }
+
+    /// Records the holder as having deleted the chunk, given its signature over the chunk address.
+    /// Returns the record of any deletion thereby completed.
+    pub(super) fn record_ack(
+        &mut self,
+        address: BlobAddress,
+        holder: XorName,
+        proof: &Signature,
+    ) -> Result<Vec<DeletionRecord>> {
+        let holder_key = ed25519_dalek::PublicKey::from_bytes(&holder.0)
+            .map_err(|_| Error::InvalidOperation(format!("Invalid holder name: {}", holder)))?;
+        if PublicKey::from(holder_key)
+            .verify(proof, utils::serialise(&address)?)
+            .is_err()
+        {
+            warn!(
+                "Invalid acknowledgement of deleting chunk {:?} by {}",
+                address, holder
+            );
+            return Err(Error::NetworkData(DtError::InvalidSignature));
+        }
+
+        let mut completed = vec![];
+        for key in self.db.get_all() {
+            let mut record = match self.db.get::<DeletionRecord>(&key) {
+                Some(record) => record,
+                None => continue,
+            };
+            let acked = match record
+                .chunks
+                .get_mut(&address)
+                .and_then(|holders| holders.get_mut(&holder))
+            {
+                Some(acked) if !*acked => acked,
+                _ => continue,
+            };
+            *acked = true;
+            info!("{} acknowledged deleting chunk {:?}", holder, address);
+            self.db.set(&key, &record)?;
+            if record.is_complete() {
+                info!(
+                    "Chunk deletion completed, all holders acknowledged: {:?}",
+                    record
+                );
+                completed.push(record);
+            }
+        }
+        Ok(completed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ed25519_dalek::{Keypair, Signer};
+    use tempdir::TempDir;
+
+    #[test]
+    fn deletion_completes_once_all_holders_acknowledged() -> Result<()> {
+        let root = TempDir::new("chunk_deletions")?;
+        let mut audit = DeletionAudit::new(root.path())?;
+        let holders: Vec<_> = (0..2)
+            .map(|_| Keypair::generate(&mut rand::thread_rng()))
+            .collect();
+        let names: Vec<_> = holders
+            .iter()
+            .map(|holder| XorName(holder.public.to_bytes()))
+            .collect();
+        let address = BlobAddress::Public(XorName::random());
+        let chunks = vec![(address, names.iter().copied().collect())]
+            .into_iter()
+            .collect();
+        let deletion = ChunkDeletion {
+            policy: ChunkDeletionPolicy::Listed(vec![address].into_iter().collect()),
+            reason: "takedown".to_string(),
+        };
+        audit.start(deletion.clone(), &chunks, 1_000_000)?;
+        // can only be carried out once
+        assert!(audit.start(deletion, &chunks, 1_000_000).is_err());
+
+        let sign = |holder: &Keypair| -> Result<Signature> {
+            Ok(Signature::Ed25519(
+                holder.sign(&utils::serialise(&address)?),
+            ))
+        };
+        // a signature by another node is rejected
+        assert!(audit
+            .record_ack(address, names[0], &sign(&holders[1])?)
+            .is_err());
+        assert!(audit
+            .record_ack(address, names[0], &sign(&holders[0])?)?
+            .is_empty());
+        let completed = audit.record_ack(address, names[1], &sign(&holders[1])?)?;
+        assert_eq!(completed.len(), 1);
+        assert!(completed[0].is_complete());
+        Ok(())
+    }
+}
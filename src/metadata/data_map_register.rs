@@ -0,0 +1,345 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::range_export::{MetadataRecord, RecordKey};
+use crate::{
+    error::convert_to_error_message,
+    node_ops::{NodeDuty, OutgoingMsg},
+    persistence::LoggedDb,
+    to_db_key::{from_db_key, ToDbKey},
+    utils, Error, Result,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Error as DtError, PublicKey};
+use sn_messaging::{
+    client::{CmdError, Error as ErrorMessage, Message, QueryResponse},
+    Aggregation, DstLocation, EndUser, MessageId,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+use xor_name::XorName;
+
+const DATA_MAPS_DB_NAME: &str = "data_maps.db";
+/// Data maps are small compared to the data they describe,
+/// so they are limited to the size of a chunk.
+const MAX_DATA_MAP_SIZE: usize = 1_000_000;
+
+/// Cmds on client data maps (file manifests).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataMapCmd {
+    /// Stores a new version of the data map, creating it if it doesn't exist.
+    /// If an expected version is given, it must match the next version.
+    Put {
+        /// Name of the data map.
+        name: XorName,
+        /// The serialised data map.
+        data_map: Vec<u8>,
+        /// The version this write is expected to create.
+        expected_version: Option<u64>,
+    },
+    /// Deletes the data map with all its versions.
+    Delete(XorName),
+}
+
+impl DataMapCmd {
+    /// Name of the data map written.
+    pub fn name(&self) -> XorName {
+        match self {
+            Self::Put { name, .. } | Self::Delete(name) => *name,
+        }
+    }
+}
+
+/// Queries on client data maps (file manifests).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataMapQuery {
+    /// Gets a version of the data map, or the latest if none given.
+    Get {
+        /// Name of the data map.
+        name: XorName,
+        /// The version to get.
+        version: Option<u64>,
+    },
+    /// Lists all versions of the data map, oldest first.
+    ListVersions(XorName),
+}
+
+impl DataMapQuery {
+    /// Name of the data map read.
+    pub fn name(&self) -> XorName {
+        match self {
+            Self::Get { name, .. } | Self::ListVersions(name) => *name,
+        }
+    }
+
+    /// The response to the query, with the error.
+    pub fn error(&self, error: ErrorMessage) -> QueryResponse {
+        match self {
+            Self::Get { .. } => QueryResponse::GetSequenceLastEntry(Err(error)),
+            Self::ListVersions(_) => QueryResponse::GetSequenceRange(Err(error)),
+        }
+    }
+}
+
+/// All versions of a data map, and its owner.
+/// The version of a data map is its index.
+#[derive(Debug, Serialize, Deserialize)]
+struct DataMapEntry {
+    owner: PublicKey,
+    versions: Vec<Vec<u8>>,
+}
+
+/// Keeps client data maps as their own metadata type,
+/// with ownership and versioning, rather than as opaque chunks.
+pub(super) struct DataMapRegister {
+    db: LoggedDb,
+}
+
+impl DataMapRegister {
+    pub(super) fn new(path: &Path) -> Result<Self> {
+        Ok(Self {
+            db: LoggedDb::open(path, DATA_MAPS_DB_NAME)?,
+        })
+    }
+
+    pub(super) async fn write(
+        &mut self,
+        cmd: DataMapCmd,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuty> {
+        let result = match cmd {
+            DataMapCmd::Put {
+                name,
+                data_map,
+                expected_version,
+            } => self
+                .put(name, data_map, expected_version, *origin.id())
+                .map(|_| ()),
+            DataMapCmd::Delete(name) => self.delete(name, *origin.id()),
+        };
+        match result {
+            Ok(()) => Ok(NodeDuty::NoOp),
+            Err(error) => {
+                info!("DataMapRegister: Writing data map FAILED: {}", error);
+                Ok(NodeDuty::Send(OutgoingMsg {
+                    msg: Message::CmdError {
+                        error: CmdError::Data(convert_to_error_message(error)?),
+                        id: MessageId::in_response_to(&msg_id),
+                        correlation_id: msg_id,
+                        target_section_pk: None,
+                    },
+                    section_source: false, // strictly this is not correct, but we don't expect responses to a response..
+                    dst: DstLocation::EndUser(origin),
+                    aggregation: Aggregation::None,
+                }))
+            }
+        }
+    }
+
+    pub(super) async fn read(
+        &self,
+        query: &DataMapQuery,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuty> {
+        let response = match query {
+            DataMapQuery::Get { name, version } => {
+                let result = match self.get(name, *version, origin.id()) {
+                    Ok(res) => Ok(res),
+                    Err(error) => Err(convert_to_error_message(error)?),
+                };
+                QueryResponse::GetSequenceLastEntry(result)
+            }
+            DataMapQuery::ListVersions(name) => {
+                let result = match self.versions(name, origin.id()) {
+                    Ok(res) => Ok(res),
+                    Err(error) => Err(convert_to_error_message(error)?),
+                };
+                QueryResponse::GetSequenceRange(result)
+            }
+        };
+        Ok(NodeDuty::Send(OutgoingMsg {
+            msg: Message::QueryResponse {
+                response,
+                id: MessageId::in_response_to(&msg_id),
+                correlation_id: msg_id,
+                target_section_pk: None,
+            },
+            section_source: false, // strictly this is not correct, but we don't expect responses to a response..
+            dst: DstLocation::EndUser(origin),
+            aggregation: Aggregation::None,
+        }))
+    }
+
+    /// Stores a new version of the data map, returning its version.
+    fn put(
+        &mut self,
+        name: XorName,
+        data_map: Vec<u8>,
+        expected_version: Option<u64>,
+        requester: PublicKey,
+    ) -> Result<u64> {
+        if data_map.len() > MAX_DATA_MAP_SIZE {
+            return Err(Error::InvalidOperation(format!(
+                "Data map of {} bytes exceeds max size of {} bytes",
+                data_map.len(),
+                MAX_DATA_MAP_SIZE
+            )));
+        }
+        let key = name.to_db_key()?;
+        let mut entry = match self.db.get::<DataMapEntry>(&key) {
+            Some(entry) => {
+                check_is_owner(&entry, &requester)?;
+                entry
+            }
+            None => DataMapEntry {
+                owner: requester,
+                versions: vec![],
+            },
+        };
+        let version = entry.versions.len() as u64;
+        if let Some(expected) = expected_version {
+            if expected != version {
+                return Err(Error::InvalidOperation(format!(
+                    "Expected to write version {} of data map, but next version is {}",
+                    expected, version
+                )));
+            }
+        }
+        entry.versions.push(data_map);
+        self.db.set(&key, &entry)?;
+        Ok(version)
+    }
+
+    fn delete(&mut self, name: XorName, requester: PublicKey) -> Result<()> {
+        let key = name.to_db_key()?;
+        let entry = self.entry(&name)?;
+        check_is_owner(&entry, &requester)?;
+        let _ = self.db.rem(&key)?;
+        Ok(())
+    }
+
+    /// Returns the version and content of the data map.
+    fn get(
+        &self,
+        name: &XorName,
+        version: Option<u64>,
+        requester: &PublicKey,
+    ) -> Result<(u64, Vec<u8>)> {
+        let entry = self.entry(name)?;
+        check_is_owner(&entry, requester)?;
+        let version = match version {
+            Some(version) => version,
+            None => (entry.versions.len() as u64).saturating_sub(1),
+        };
+        entry
+            .versions
+            .into_iter()
+            .nth(version as usize)
+            .map(|data_map| (version, data_map))
+            .ok_or(Error::NetworkData(DtError::NoSuchEntry))
+    }
+
+    fn versions(&self, name: &XorName, requester: &PublicKey) -> Result<Vec<Vec<u8>>> {
+        let entry = self.entry(name)?;
+        check_is_owner(&entry, requester)?;
+        Ok(entry.versions)
+    }
+
+    /// The keys of all data maps held.
+    pub(super) fn record_keys(&self) -> Result<Vec<RecordKey>> {
+        self.db
+            .get_all()
+            .iter()
+            .map(|key| Ok(RecordKey::DataMap(from_db_key(key)?)))
+            .collect()
+    }
+
+    /// The data map as a metadata record, if held.
+    pub(super) fn record(&self, name: &XorName) -> Option<MetadataRecord> {
+        let entry = self.entry(name).ok()?;
+        Some(MetadataRecord::DataMap {
+            name: *name,
+            owner: entry.owner,
+            versions: entry.versions,
+        })
+    }
+
+    /// Takes over the data map, with all its versions, as held by another Elder.
+    pub(super) fn take_over(
+        &mut self,
+        name: XorName,
+        owner: PublicKey,
+        versions: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        self.db
+            .set(&name.to_db_key()?, &DataMapEntry { owner, versions })
+    }
+
+    /// Removes the data map, as removed at another Elder.
+    pub(super) fn remove_record(&mut self, name: &XorName) -> Result<()> {
+        let _ = self.db.rem(&name.to_db_key()?)?;
+        Ok(())
+    }
+
+    fn entry(&self, name: &XorName) -> Result<DataMapEntry> {
+        self.db
+            .get(&name.to_db_key()?)
+            .ok_or(Error::NoSuchDataMap(*name))
+    }
+}
+
+fn check_is_owner(entry: &DataMapEntry, requester: &PublicKey) -> Result<()> {
+    if &entry.owner == requester {
+        Ok(())
+    } else {
+        Err(Error::NetworkData(DtError::AccessDenied(*requester)))
+    }
+}
+
+impl Display for DataMapRegister {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "DataMapRegister")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use tempdir::TempDir;
+
+    #[test]
+    fn data_map_versions_are_kept_for_owner() -> Result<()> {
+        let root = TempDir::new("data_maps")?;
+        let mut register = DataMapRegister::new(root.path())?;
+        let owner = PublicKey::from(SecretKey::random().public_key());
+        let other = PublicKey::from(SecretKey::random().public_key());
+        let name = XorName::random();
+
+        assert_eq!(register.put(name, vec![1], None, owner)?, 0);
+        assert_eq!(register.put(name, vec![2], Some(1), owner)?, 1);
+        assert!(register.put(name, vec![3], Some(1), owner).is_err());
+        assert!(register.put(name, vec![3], None, other).is_err());
+
+        assert_eq!(register.get(&name, None, &owner)?, (1, vec![2]));
+        assert_eq!(register.get(&name, Some(0), &owner)?, (0, vec![1]));
+        assert!(register.get(&name, Some(2), &owner).is_err());
+        assert!(register.get(&name, None, &other).is_err());
+        assert_eq!(register.versions(&name, &owner)?, vec![vec![1], vec![2]]);
+
+        assert!(register.delete(name, other).is_err());
+        register.delete(name, owner)?;
+        assert!(register.versions(&name, &owner).is_err());
+        Ok(())
+    }
+}
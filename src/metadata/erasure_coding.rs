@@ -0,0 +1,390 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    reed_solomon::ReedSolomon,
+    relayed_reads::{response, RelayedChunk, RelayedRead},
+};
+use crate::{
+    chunks::ChunkRange,
+    node_ops::{NodeDuties, NodeDuty},
+    utils, Error, Result,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Blob, BlobAddress, PrivateBlob, PublicBlob, PublicKey};
+use sn_messaging::{client::Error as ErrorMessage, EndUser, MessageId};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+// The time the holders of the shards of a chunk have to answer a read of it.
+const SHARDED_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The shards new chunks are erasure coded into, rather than each being copied
+/// whole to `CHUNK_COPY_COUNT` holders. A chunk is stored as `data_shards` shards
+/// of its content and `parity_shards` shards of parity, each with a holder of its own,
+/// and is read back from any `data_shards` of them, so that it survives the loss
+/// of as many holders as there are parity shards, at a fraction of the storage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErasureCoding {
+    /// Shards of the content of a chunk, any as many as these recovering it.
+    pub data_shards: usize,
+    /// Shards of parity, as many holders of shards of a chunk as may be lost.
+    pub parity_shards: usize,
+}
+
+impl Default for ErasureCoding {
+    fn default() -> Self {
+        Self {
+            data_shards: 4,
+            parity_shards: 2,
+        }
+    }
+}
+
+// A shard as held by its holder, as a chunk. It names the chunk it is a shard of,
+// so that the shards of chunks of the same content have distinct addresses.
+#[derive(Serialize, Deserialize)]
+struct Shard {
+    chunk: BlobAddress,
+    index: usize,
+    bytes: Vec<u8>,
+}
+
+/// The shards an erasure coded chunk is stored as.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardMap {
+    /// Bytes of the content of the chunk.
+    pub size: usize,
+    /// Shards of the content, any as many as these recovering it.
+    pub data_shards: usize,
+    /// Owner of a private chunk.
+    pub owner: Option<PublicKey>,
+    /// The addresses of the shards, data shards first, as chunks of their own.
+    pub shards: Vec<BlobAddress>,
+}
+
+/// Codes the chunk into its shards, each as a chunk of the same kind, and owner.
+pub(super) fn shard(code: &ReedSolomon, chunk: &Blob) -> Result<(ShardMap, Vec<Blob>)> {
+    let chunk_address = *chunk.address();
+    let owner = match chunk {
+        Blob::Public(_) => None,
+        Blob::Private(chunk) => Some(*chunk.owner()),
+    };
+    let mut shards = vec![];
+    for (index, bytes) in code.encode(chunk.value()).into_iter().enumerate() {
+        let content = utils::serialise(&Shard {
+            chunk: chunk_address,
+            index,
+            bytes,
+        })?
+        .to_vec();
+        shards.push(match owner {
+            None => Blob::Public(PublicBlob::new(content)),
+            Some(owner) => Blob::Private(PrivateBlob::new(content, owner)),
+        });
+    }
+    let map = ShardMap {
+        size: chunk.value().len(),
+        data_shards: code.data_shards(),
+        owner,
+        shards: shards.iter().map(|shard| *shard.address()).collect(),
+    };
+    Ok((map, shards))
+}
+
+// Recovers the chunk from the shards answered, verifying it by its address.
+fn rebuild(
+    address: BlobAddress,
+    map: &ShardMap,
+    shards: &BTreeMap<usize, Vec<u8>>,
+) -> Result<Blob> {
+    let code = ReedSolomon::new(
+        map.data_shards,
+        map.shards.len().saturating_sub(map.data_shards),
+    )?;
+    let indexed: Vec<_> = shards
+        .iter()
+        .map(|(index, bytes)| (*index, bytes.clone()))
+        .collect();
+    let content = code.decode(&indexed, map.size)?;
+    let chunk = match map.owner {
+        None => Blob::Public(PublicBlob::new(content)),
+        Some(owner) => Blob::Private(PrivateBlob::new(content, owner)),
+    };
+    if *chunk.address() != address {
+        return Err(Error::ErasureCoding(
+            "Shards recovered a chunk other than the one read".to_string(),
+        ));
+    }
+    Ok(chunk)
+}
+
+struct PendingRead {
+    address: BlobAddress,
+    map: ShardMap,
+    // the bytes of the chunk answered to the client, if not all of them
+    range: Option<ChunkRange>,
+    origin: EndUser,
+    // the holders queried of each shard, by the address of the shard
+    queried: BTreeMap<BlobAddress, BTreeSet<XorName>>,
+    // the shards answered, by their index
+    answered: BTreeMap<usize, Vec<u8>>,
+    since: Instant,
+}
+
+/// The reads of erasure coded chunks. The holders of all shards of a chunk are
+/// queried at once, as for relayed reads, the chunk being recovered by us from
+/// the first shards answered, and verified, by which it can be.
+#[derive(Default)]
+pub(super) struct ShardedReads {
+    // the reads pending, by the id of the msg of the client
+    pending: BTreeMap<MessageId, PendingRead>,
+}
+
+impl ShardedReads {
+    /// Queries the holders of each of the shards of the chunk, given by the address of
+    /// the shard, for the shards.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn start(
+        &mut self,
+        address: BlobAddress,
+        map: ShardMap,
+        range: Option<ChunkRange>,
+        msg_id: MessageId,
+        origin: EndUser,
+        holders: BTreeMap<BlobAddress, BTreeSet<XorName>>,
+        now: Instant,
+    ) -> Result<NodeDuties> {
+        if holders.len() < map.data_shards {
+            info!(
+                "Holders of {} shards of {:?} known, of the {} needed",
+                holders.len(),
+                address,
+                map.data_shards
+            );
+            return Ok(vec![response(
+                msg_id,
+                origin,
+                Err(ErrorMessage::NoSuchData),
+            )]);
+        }
+        let mut duties = vec![];
+        for (shard, shard_holders) in &holders {
+            let read = RelayedRead {
+                address: *shard,
+                msg_id,
+            };
+            duties.push(read.msg(shard_holders.clone()));
+        }
+        let _ = self.pending.insert(
+            msg_id,
+            PendingRead {
+                address,
+                map,
+                range,
+                origin,
+                queried: holders,
+                answered: BTreeMap::new(),
+                since: now,
+            },
+        );
+        Ok(duties)
+    }
+
+    /// Whether the answer is to a read of ours.
+    pub(super) fn is_pending(&self, answer: &RelayedChunk) -> bool {
+        self.pending.contains_key(&answer.read.msg_id)
+    }
+
+    /// Takes the shard answered by the holder, returning the response to the client
+    /// once enough shards are answered to recover the chunk.
+    pub(super) fn answer(&mut self, answer: RelayedChunk, holder: XorName) -> Option<NodeDuty> {
+        let msg_id = answer.read.msg_id;
+        let pending = self.pending.get_mut(&msg_id)?;
+        let is_queried = matches!(
+            pending.queried.get(&answer.read.address),
+            Some(holders) if holders.contains(&holder)
+        );
+        if !is_queried || !answer.is_intact() {
+            warn!(
+                "Dropping shard relayed by {} for read {:?}, not the shard queried",
+                holder, msg_id
+            );
+            return None;
+        }
+        let shard: Shard = utils::deserialise(answer.chunk.value()).ok()?;
+        let index = pending
+            .map
+            .shards
+            .iter()
+            .position(|address| *address == answer.read.address)?;
+        if shard.chunk != pending.address || shard.index != index {
+            warn!(
+                "Dropping shard relayed by {} for read {:?}, of another chunk",
+                holder, msg_id
+            );
+            return None;
+        }
+        let _ = pending.answered.insert(index, shard.bytes);
+        if pending.answered.len() < pending.map.data_shards {
+            return None;
+        }
+        let pending = self.pending.remove(&msg_id)?;
+        match rebuild(pending.address, &pending.map, &pending.answered) {
+            Ok(chunk) => {
+                let result = match pending.range {
+                    Some(range) => range.slice(&chunk),
+                    None => Ok(chunk),
+                };
+                Some(response(msg_id, pending.origin, result))
+            }
+            Err(error) => {
+                warn!("Failed to recover {:?}: {}", pending.address, error);
+                Some(response(
+                    msg_id,
+                    pending.origin,
+                    Err(ErrorMessage::NoSuchData),
+                ))
+            }
+        }
+    }
+
+    /// Fails the reads of which too few shards were answered in time.
+    pub(super) fn expire(&mut self, now: Instant) -> NodeDuties {
+        let due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, read)| now.saturating_duration_since(read.since) >= SHARDED_READ_TIMEOUT)
+            .map(|(msg_id, _)| *msg_id)
+            .collect();
+        let mut duties = vec![];
+        for msg_id in due {
+            if let Some(read) = self.pending.remove(&msg_id) {
+                info!(
+                    "{} shards of {:?} answered in time, of the {} needed, failing read {:?}",
+                    read.answered.len(),
+                    read.address,
+                    read.map.data_shards,
+                    msg_id
+                );
+                duties.push(response(msg_id, read.origin, Err(ErrorMessage::NoSuchData)));
+            }
+        }
+        duties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::node_ops::OutgoingMsg;
+    use bls::SecretKey;
+    use sn_messaging::client::{Message, QueryResponse};
+
+    fn holders(shards: &[Blob]) -> BTreeMap<BlobAddress, BTreeSet<XorName>> {
+        shards
+            .iter()
+            .map(|shard| {
+                let holder: BTreeSet<_> = vec![XorName::random()].into_iter().collect();
+                (*shard.address(), holder)
+            })
+            .collect()
+    }
+
+    fn answer(shard: &Blob, msg_id: MessageId) -> RelayedChunk {
+        RelayedChunk {
+            read: RelayedRead {
+                address: *shard.address(),
+                msg_id,
+            },
+            chunk: shard.clone(),
+        }
+    }
+
+    #[test]
+    fn chunks_are_read_back_from_enough_shards() -> Result<()> {
+        let code = ReedSolomon::new(2, 1)?;
+        let owner = PublicKey::from(SecretKey::random().public_key());
+        let chunk = Blob::Private(PrivateBlob::new(b"a chunk, erasure coded".to_vec(), owner));
+        let (map, shards) = shard(&code, &chunk)?;
+        assert_eq!(shards.len(), 3);
+        // the shards are owned as the chunk is
+        assert!(shards.iter().all(|shard| shard.address().is_private()));
+
+        let mut reads = ShardedReads::default();
+        let msg_id = MessageId::new();
+        let holders = holders(&shards);
+        let holder_of = |shard: &Blob| {
+            holders[shard.address()]
+                .iter()
+                .next()
+                .copied()
+                .unwrap_or_else(XorName::random)
+        };
+        let origin = EndUser::AllClients(owner);
+        let duties = reads.start(
+            *chunk.address(),
+            map,
+            None,
+            msg_id,
+            origin,
+            holders.clone(),
+            Instant::now(),
+        )?;
+        assert_eq!(duties.len(), 3);
+
+        // shards from holders not queried for them are dropped
+        assert!(reads
+            .answer(answer(&shards[2], msg_id), holder_of(&shards[0]))
+            .is_none());
+        assert!(reads
+            .answer(answer(&shards[2], msg_id), holder_of(&shards[2]))
+            .is_none());
+        let response = reads.answer(answer(&shards[0], msg_id), holder_of(&shards[0]));
+        match response {
+            Some(NodeDuty::Send(OutgoingMsg {
+                msg:
+                    Message::QueryResponse {
+                        response: QueryResponse::GetBlob(Ok(read)),
+                        ..
+                    },
+                ..
+            })) => assert_eq!(read, chunk),
+            other => panic!("Unexpected response: {:?}", other),
+        }
+        assert!(reads.pending.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn reads_short_of_shards_fail_in_time() -> Result<()> {
+        let code = ReedSolomon::new(2, 1)?;
+        let chunk = Blob::Public(PublicBlob::new(b"another chunk".to_vec()));
+        let (map, shards) = shard(&code, &chunk)?;
+        let mut reads = ShardedReads::default();
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let now = Instant::now();
+        let _ = reads.start(
+            *chunk.address(),
+            map,
+            None,
+            MessageId::new(),
+            origin,
+            holders(&shards),
+            now,
+        )?;
+        assert!(reads.expire(now).is_empty());
+        assert_eq!(reads.expire(now + SHARDED_READ_TIMEOUT).len(), 1);
+        assert!(reads.pending.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{node_msg::ClientMsg, node_ops::NodeDuty, utils, Error, Result};
+use bls::PublicKeySet;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{BlobAddress, PublicKey, SignatureShare};
+use sn_messaging::{EndUser, MessageId};
+
+/// How far the time proposed by the client may be off our clock, in seconds.
+const MAX_CLOCK_SKEW: u64 = 5 * 60;
+
+/// A request by a client for the Elders to attest that a chunk exists on the
+/// network at the time proposed, e.g. to notarise a document put as a chunk.
+/// The time is proposed by the client, so that all Elders sign the same statement.
+/// It is sent as a `ClientRequest`, to the section holding the chunk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExistenceQuery {
+    /// The chunk.
+    pub address: BlobAddress,
+    /// Seconds since unix epoch, within a few minutes of the clocks of the Elders.
+    pub timestamp: u64,
+}
+
+/// The statement attested by the Elders, signed by the section.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExistenceStatement {
+    /// Key of the section attesting.
+    pub section_key: PublicKey,
+    /// Seconds since unix epoch.
+    pub timestamp: u64,
+    /// The chunk existing at that time.
+    pub address: BlobAddress,
+}
+
+impl ExistenceStatement {
+    /// The statement to attest, if the time proposed is within the skew of our clock,
+    /// reading the secs since unix epoch given.
+    pub(super) fn new(query: &ExistenceQuery, section_key: PublicKey, now: u64) -> Result<Self> {
+        if query.timestamp.abs_diff(now) > MAX_CLOCK_SKEW {
+            return Err(Error::InvalidOperation(format!(
+                "Proposed time {} is off our clock by more than {}s",
+                query.timestamp, MAX_CLOCK_SKEW
+            )));
+        }
+        Ok(Self {
+            section_key,
+            timestamp: query.timestamp,
+            address: query.address,
+        })
+    }
+}
+
+/// The share of an Elder of the attestation. The client stores the shares of enough
+/// Elders, combined into the signature of the section over the statement.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExistenceAttestation {
+    /// The statement attested.
+    pub statement: ExistenceStatement,
+    /// The share of the Elder of the signature of the section over the statement.
+    pub share: SignatureShare,
+}
+
+impl ExistenceAttestation {
+    /// The msg sending the attestation to the client, answering the request of the msg.
+    pub(crate) fn msg(&self, id: MessageId, origin: EndUser) -> Result<NodeDuty> {
+        Ok(NodeDuty::Send(
+            ClientMsg::ExistenceAttestation(self.clone()).msg(id, origin)?,
+        ))
+    }
+
+    /// Combines the shares of the attestations of the same statement into the
+    /// signature of the section, verified against the key of the statement.
+    pub fn combine(
+        attestations: &[ExistenceAttestation],
+        key_set: &PublicKeySet,
+    ) -> Result<(ExistenceStatement, bls::Signature)> {
+        let statement = match attestations.first() {
+            Some(attestation) => attestation.statement.clone(),
+            None => return Err(Error::CouldNotCombineSignatures),
+        };
+        if attestations.iter().any(|a| a.statement != statement)
+            || statement.section_key != PublicKey::Bls(key_set.public_key())
+        {
+            return Err(Error::CouldNotCombineSignatures);
+        }
+        let signature = key_set
+            .combine_signatures(
+                attestations
+                    .iter()
+                    .map(|attestation| (attestation.share.index, &attestation.share.share)),
+            )
+            .map_err(|_| Error::CouldNotCombineSignatures)?;
+        if !key_set
+            .public_key()
+            .verify(&signature, utils::serialise(&statement)?)
+        {
+            return Err(Error::CouldNotCombineSignatures);
+        }
+        Ok((statement, signature))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKeySet;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use xor_name::XorName;
+
+    #[test]
+    fn shares_of_enough_elders_combine_into_the_section_signature() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let secret_key_set = SecretKeySet::random(1, &mut rng);
+        let key_set = secret_key_set.public_keys();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let query = ExistenceQuery {
+            address: BlobAddress::Public(XorName::random()),
+            timestamp: now,
+        };
+        let statement = ExistenceStatement::new(&query, PublicKey::Bls(key_set.public_key()), now)?;
+        let attestations: Vec<_> = (0..2)
+            .map(|index| -> Result<_> {
+                let share = secret_key_set
+                    .secret_key_share(index)
+                    .sign(utils::serialise(&statement)?);
+                Ok(ExistenceAttestation {
+                    statement: statement.clone(),
+                    share: SignatureShare { index, share },
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        assert!(ExistenceAttestation::combine(&attestations[..1], &key_set).is_err());
+        let (combined, _) = ExistenceAttestation::combine(&attestations, &key_set)?;
+        assert_eq!(combined, statement);
+
+        let stale = ExistenceQuery {
+            timestamp: query.timestamp - 2 * MAX_CLOCK_SKEW,
+            ..query
+        };
+        assert!(ExistenceStatement::new(&stale, statement.section_key, now).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+use sn_data_types::MapEntryActions;
+use sn_messaging::client::{DataCmd, DataQuery, MapRead, MapWrite};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// The reads and writes are reported over this long, up to now.
+pub const HOT_DATA_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// The window slides by this much at a time.
+const BUCKET_SPAN: Duration = Duration::from_secs(60);
+/// Objects and keys counted per bucket at most, so that a flood of distinct
+/// ones does not use up memory. Beyond are only counted by data type.
+const MAX_COUNTED: usize = 10_000;
+/// The most accessed objects and keys reported.
+pub const HOT_DATA_TOP: usize = 20;
+
+/// The type of the data accessed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DataKind {
+    /// Blobs, structured as chunks.
+    Blob,
+    /// Maps.
+    Map,
+    /// Sequences.
+    Sequence,
+}
+
+/// Reads and writes counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Access {
+    /// Times read.
+    pub reads: u64,
+    /// Times written.
+    pub writes: u64,
+}
+
+impl Access {
+    fn total(&self) -> u64 {
+        self.reads + self.writes
+    }
+
+    fn add(&mut self, other: &Access) {
+        self.reads += other.reads;
+        self.writes += other.writes;
+    }
+}
+
+/// An object, or a key of one, among the most accessed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotObject {
+    /// The type of the object.
+    pub kind: DataKind,
+    /// The name of the object.
+    pub name: XorName,
+    /// The key of the object, hex formatted, if the entry of a Map.
+    pub key: Option<String>,
+    /// The reads and writes of it.
+    pub access: Access,
+}
+
+/// The most accessed data of the section, as seen by us, over the window.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotData {
+    /// Seconds of the window reported over, up to now.
+    pub window_secs: u64,
+    /// The reads and writes by data type.
+    pub by_kind: BTreeMap<DataKind, Access>,
+    /// The most accessed objects, the most accessed first.
+    pub objects: Vec<HotObject>,
+    /// The most accessed keys of objects, the most accessed first.
+    pub keys: Vec<HotObject>,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Accessed {
+    kind: DataKind,
+    name: XorName,
+    key: Option<Vec<u8>>,
+}
+
+struct Bucket {
+    started: Instant,
+    by_kind: BTreeMap<DataKind, Access>,
+    accessed: BTreeMap<Accessed, Access>,
+}
+
+/// Counts the reads and writes of the metadata, and of its keys, over a sliding window.
+#[derive(Default)]
+pub(super) struct HotDataTracker {
+    buckets: Mutex<VecDeque<Bucket>>,
+}
+
+impl HotDataTracker {
+    pub(super) fn read(&self, query: &DataQuery, now: Instant) {
+        let (kind, key) = match query {
+            DataQuery::Blob(_) => (DataKind::Blob, None),
+            DataQuery::Map(MapRead::GetValue { key, .. }) => (DataKind::Map, Some(key.clone())),
+            DataQuery::Map(_) => (DataKind::Map, None),
+            DataQuery::Sequence(_) => (DataKind::Sequence, None),
+        };
+        let keys = key.into_iter().collect();
+        self.record(
+            kind,
+            query.dst_address(),
+            keys,
+            Access {
+                reads: 1,
+                writes: 0,
+            },
+            now,
+        );
+    }
+
+    pub(super) fn write(&self, cmd: &DataCmd, now: Instant) {
+        let (kind, keys) = match cmd {
+            DataCmd::Blob(_) => (DataKind::Blob, vec![]),
+            DataCmd::Map(MapWrite::Edit { changes, .. }) => {
+                let keys = match changes {
+                    MapEntryActions::Seq(actions) => actions.actions().keys().cloned().collect(),
+                    MapEntryActions::Unseq(actions) => actions.actions().keys().cloned().collect(),
+                };
+                (DataKind::Map, keys)
+            }
+            DataCmd::Map(_) => (DataKind::Map, vec![]),
+            DataCmd::Sequence(_) => (DataKind::Sequence, vec![]),
+        };
+        self.record(
+            kind,
+            cmd.dst_address(),
+            keys,
+            Access {
+                reads: 0,
+                writes: 1,
+            },
+            now,
+        );
+    }
+
+    fn record(
+        &self,
+        kind: DataKind,
+        name: XorName,
+        keys: Vec<Vec<u8>>,
+        access: Access,
+        now: Instant,
+    ) {
+        let mut buckets = self.lock();
+        expire(&mut buckets, now);
+        let is_current = buckets
+            .back()
+            .is_some_and(|bucket| now.saturating_duration_since(bucket.started) < BUCKET_SPAN);
+        if !is_current {
+            buckets.push_back(Bucket {
+                started: now,
+                by_kind: BTreeMap::new(),
+                accessed: BTreeMap::new(),
+            });
+        }
+        let bucket = match buckets.back_mut() {
+            Some(bucket) => bucket,
+            None => return,
+        };
+        bucket.by_kind.entry(kind).or_default().add(&access);
+        let accessed = std::iter::once(None).chain(keys.into_iter().map(Some));
+        for key in accessed {
+            let accessed = Accessed { kind, name, key };
+            if let Some(counted) = bucket.accessed.get_mut(&accessed) {
+                counted.add(&access);
+            } else if bucket.accessed.len() < MAX_COUNTED {
+                let _ = bucket.accessed.insert(accessed, access);
+            }
+        }
+    }
+
+    /// The most accessed data over the window, up to the number given of objects and of keys.
+    pub(super) fn hot_data(&self, top: usize, now: Instant) -> HotData {
+        let mut buckets = self.lock();
+        expire(&mut buckets, now);
+        let mut by_kind = BTreeMap::<_, Access>::new();
+        let mut accessed = BTreeMap::<_, Access>::new();
+        for bucket in buckets.iter() {
+            for (kind, access) in &bucket.by_kind {
+                by_kind.entry(*kind).or_default().add(access);
+            }
+            for (object, access) in &bucket.accessed {
+                accessed.entry(object.clone()).or_default().add(access);
+            }
+        }
+        let (keys, objects): (Vec<_>, Vec<_>) = accessed
+            .into_iter()
+            .map(|(accessed, access)| HotObject {
+                kind: accessed.kind,
+                name: accessed.name,
+                key: accessed.key.map(hex::encode),
+                access,
+            })
+            .partition(|object| object.key.is_some());
+        HotData {
+            window_secs: HOT_DATA_WINDOW.as_secs(),
+            by_kind,
+            objects: hottest(objects, top),
+            keys: hottest(keys, top),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Bucket>> {
+        // a panic while holding the lock leaves the counters usable
+        match self.buckets.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+fn expire(buckets: &mut VecDeque<Bucket>, now: Instant) {
+    while buckets
+        .front()
+        .is_some_and(|bucket| now.saturating_duration_since(bucket.started) >= HOT_DATA_WINDOW)
+    {
+        let _ = buckets.pop_front();
+    }
+}
+
+fn hottest(mut objects: Vec<HotObject>, top: usize) -> Vec<HotObject> {
+    objects.sort_by_key(|object| std::cmp::Reverse(object.access.total()));
+    objects.truncate(top);
+    objects
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_data_types::{BlobAddress, MapAddress};
+    use sn_messaging::client::BlobRead;
+
+    #[test]
+    fn most_accessed_are_reported_over_the_window() {
+        let tracker = HotDataTracker::default();
+        let now = Instant::now();
+        let blob = |name| DataQuery::Blob(BlobRead::Get(BlobAddress::Public(name)));
+        let map_address = MapAddress::Seq {
+            name: XorName::random(),
+            tag: 15000,
+        };
+        let get_value = DataQuery::Map(MapRead::GetValue {
+            address: map_address,
+            key: b"hot".to_vec(),
+        });
+        let (hot, cold) = (XorName::random(), XorName::random());
+
+        tracker.read(&blob(cold), now);
+        for _ in 0..3 {
+            tracker.read(&blob(hot), now + BUCKET_SPAN);
+        }
+        tracker.read(&get_value, now + BUCKET_SPAN);
+
+        let hot_data = tracker.hot_data(1, now + BUCKET_SPAN);
+        assert_eq!(hot_data.by_kind[&DataKind::Blob].reads, 4);
+        assert_eq!(hot_data.by_kind[&DataKind::Map].reads, 1);
+        assert_eq!(hot_data.objects.len(), 1);
+        assert_eq!(hot_data.objects[0].name, hot);
+        assert_eq!(hot_data.objects[0].access.reads, 3);
+        assert_eq!(hot_data.keys[0].key, Some(hex::encode(b"hot")));
+
+        // the first read slid out of the window
+        let hot_data = tracker.hot_data(HOT_DATA_TOP, now + HOT_DATA_WINDOW);
+        assert_eq!(hot_data.by_kind[&DataKind::Blob].reads, 3);
+        assert!(hot_data.objects.iter().all(|object| object.name != cold));
+    }
+}
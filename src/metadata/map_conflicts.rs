@@ -0,0 +1,293 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use sn_data_types::{
+    Error as DtError, Map, MapAddress, MapEntryActions, MapSeqEntryAction, MapSeqEntryActions,
+    MapSeqValue, MapUnseqEntryAction, MapUnseqEntryActions, PublicKey, Result as NdResult,
+};
+use std::collections::BTreeMap;
+
+/// The reserved entry of a Map naming its conflict strategy.
+/// It is set when the Map is created, and can not be changed after.
+/// A Map without it rejects conflicting writes.
+pub const CONFLICT_STRATEGY_KEY: &[u8] = b"_sn_conflict_strategy";
+
+/// How a write conflicting with the current entries of a Map is resolved,
+/// e.g. when concurrent clients write the same version of an entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// The conflicting write fails, and the client is to retry it.
+    #[default]
+    Reject,
+    /// The conflicting write is applied over the current entry.
+    LastWriteWins,
+    /// The conflicting write is merged with the current entry,
+    /// by the `MapMerge` registered with the node.
+    Merge,
+}
+
+impl ConflictStrategy {
+    /// The name of the strategy, as the value of the reserved entry.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Reject => "reject",
+            Self::LastWriteWins => "last-write-wins",
+            Self::Merge => "merge",
+        }
+    }
+
+    fn from_name(name: &[u8]) -> NdResult<Self> {
+        match name {
+            b"reject" => Ok(Self::Reject),
+            b"last-write-wins" => Ok(Self::LastWriteWins),
+            b"merge" => Ok(Self::Merge),
+            _ => Err(DtError::FailedToParse(format!(
+                "Unknown Map conflict strategy: {}",
+                String::from_utf8_lossy(name)
+            ))),
+        }
+    }
+
+    /// The strategy of the Map, as chosen at its creation.
+    pub fn of(map: &Map) -> NdResult<Self> {
+        let name = match map {
+            Map::Seq(map) => map.get(CONFLICT_STRATEGY_KEY).map(|value| &value.data),
+            Map::Unseq(map) => map.get(CONFLICT_STRATEGY_KEY),
+        };
+        match name {
+            Some(name) => Self::from_name(name),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// Merges conflicting writes to Maps with the `Merge` strategy,
+/// as registered with the node by the application running it.
+pub trait MapMerge: Send + Sync {
+    /// Merges the proposed value of the entry with its current value.
+    /// Returning `None` rejects the write.
+    fn merge(
+        &self,
+        address: &MapAddress,
+        key: &[u8],
+        current: &[u8],
+        proposed: &[u8],
+    ) -> Option<Vec<u8>>;
+}
+
+/// Resolves the actions against the current entries of the Map, by its strategy,
+/// so that they apply without conflict. Actions not in conflict, or not resolved,
+/// are left as they are, to be rejected as before when applied.
+pub(super) fn resolve(
+    map: &Map,
+    actions: MapEntryActions,
+    merge: Option<&dyn MapMerge>,
+    requester: &PublicKey,
+) -> NdResult<MapEntryActions> {
+    let touches_reserved = match &actions {
+        MapEntryActions::Seq(actions) => actions.actions().contains_key(CONFLICT_STRATEGY_KEY),
+        MapEntryActions::Unseq(actions) => actions.actions().contains_key(CONFLICT_STRATEGY_KEY),
+    };
+    if touches_reserved {
+        return Err(DtError::AccessDenied(*requester));
+    }
+    let strategy = ConflictStrategy::of(map)?;
+    let merge = match strategy {
+        ConflictStrategy::Reject => return Ok(actions),
+        ConflictStrategy::LastWriteWins => None,
+        ConflictStrategy::Merge => Some(merge.ok_or(DtError::InvalidOperation)?),
+    };
+    match (map, actions) {
+        (Map::Seq(map), MapEntryActions::Seq(actions)) => {
+            let mut resolved = BTreeMap::new();
+            for (key, action) in actions.into_actions() {
+                let current = map.get(&key);
+                if let Some(action) = resolve_seq(map.address(), &key, current, action, merge) {
+                    let _ = resolved.insert(key, action);
+                }
+            }
+            Ok(MapEntryActions::Seq(MapSeqEntryActions::from(resolved)))
+        }
+        (Map::Unseq(map), MapEntryActions::Unseq(actions)) => {
+            let mut resolved = BTreeMap::new();
+            for (key, action) in actions.into_actions() {
+                let current = map.get(&key);
+                if let Some(action) = resolve_unseq(map.address(), &key, current, action, merge) {
+                    let _ = resolved.insert(key, action);
+                }
+            }
+            Ok(MapEntryActions::Unseq(MapUnseqEntryActions::from(resolved)))
+        }
+        // the kinds not matching is an error of its own, when applied
+        (_, actions) => Ok(actions),
+    }
+}
+
+/// With no merge given, the last write wins.
+/// Returns `None` if the action is no longer needed.
+fn resolve_seq(
+    address: &MapAddress,
+    key: &[u8],
+    current: Option<&MapSeqValue>,
+    action: MapSeqEntryAction,
+    merge: Option<&dyn MapMerge>,
+) -> Option<MapSeqEntryAction> {
+    use MapSeqEntryAction::*;
+    let current = match current {
+        Some(current) => current,
+        None => {
+            return match (action, merge) {
+                (Update(value), None) => Some(Ins(value)),
+                (Del(_), None) => None,
+                (action, _) => Some(action),
+            }
+        }
+    };
+    let next = current.version + 1;
+    let proposed = match &action {
+        Update(value) if value.version == next => return Some(action),
+        Ins(value) | Update(value) => &value.data,
+        Del(version) if *version == next || merge.is_some() => return Some(action),
+        Del(_) => return Some(Del(next)),
+    };
+    let data = match merge {
+        None => proposed.clone(),
+        Some(merge) => match merge.merge(address, key, &current.data, proposed) {
+            Some(data) => data,
+            None => return Some(action),
+        },
+    };
+    Some(Update(MapSeqValue {
+        data,
+        version: next,
+    }))
+}
+
+/// With no merge given, the last write wins.
+/// Returns `None` if the action is no longer needed.
+fn resolve_unseq(
+    address: &MapAddress,
+    key: &[u8],
+    current: Option<&Vec<u8>>,
+    action: MapUnseqEntryAction,
+    merge: Option<&dyn MapMerge>,
+) -> Option<MapUnseqEntryAction> {
+    use MapUnseqEntryAction::*;
+    match (current, action, merge) {
+        (Some(_), Ins(value), None) => Some(Update(value)),
+        (Some(current), Ins(value), Some(merge)) => {
+            match merge.merge(address, key, current, &value) {
+                Some(value) => Some(Update(value)),
+                None => Some(Ins(value)),
+            }
+        }
+        (None, Update(value), None) => Some(Ins(value)),
+        (None, Del, None) => None,
+        (_, action, _) => Some(action),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::{SeqMap, UnseqMap};
+    use xor_name::XorName;
+
+    struct Concat;
+
+    impl MapMerge for Concat {
+        fn merge(
+            &self,
+            _: &MapAddress,
+            _: &[u8],
+            current: &[u8],
+            proposed: &[u8],
+        ) -> Option<Vec<u8>> {
+            Some([current, proposed].concat())
+        }
+    }
+
+    fn seq_map(owner: PublicKey, strategy: Option<ConflictStrategy>) -> NdResult<Map> {
+        let mut map = SeqMap::new(XorName::random(), 1, owner);
+        let mut actions = MapSeqEntryActions::new().ins(b"key".to_vec(), vec![1], 0);
+        if let Some(strategy) = strategy {
+            actions = actions.ins(
+                CONFLICT_STRATEGY_KEY.to_vec(),
+                strategy.name().as_bytes().to_vec(),
+                0,
+            );
+        }
+        map.mutate_entries(actions, &owner)?;
+        Ok(Map::Seq(map))
+    }
+
+    // a concurrent client writing the version already written
+    fn stale_update() -> MapEntryActions {
+        MapEntryActions::Seq(MapSeqEntryActions::new().update(b"key".to_vec(), vec![2], 0))
+    }
+
+    fn value(map: &Map) -> Option<Vec<u8>> {
+        match map {
+            Map::Seq(map) => map.get(b"key").map(|value| value.data.clone()),
+            Map::Unseq(map) => map.get(b"key").cloned(),
+        }
+    }
+
+    #[test]
+    fn conflicting_writes_are_resolved_by_strategy_of_map() -> NdResult<()> {
+        let owner = PublicKey::from(SecretKey::random().public_key());
+
+        let mut map = seq_map(owner, None)?;
+        let actions = resolve(&map, stale_update(), Some(&Concat), &owner)?;
+        assert!(map.mutate_entries(actions, &owner).is_err());
+
+        let mut map = seq_map(owner, Some(ConflictStrategy::LastWriteWins))?;
+        let actions = resolve(&map, stale_update(), None, &owner)?;
+        map.mutate_entries(actions, &owner)?;
+        assert_eq!(value(&map), Some(vec![2]));
+
+        let mut map = seq_map(owner, Some(ConflictStrategy::Merge))?;
+        // merging needs a merge registered
+        assert!(resolve(&map, stale_update(), None, &owner).is_err());
+        let actions = resolve(&map, stale_update(), Some(&Concat), &owner)?;
+        map.mutate_entries(actions, &owner)?;
+        assert_eq!(value(&map), Some(vec![1, 2]));
+
+        // the strategy can not be changed after creation
+        let change = MapEntryActions::Seq(MapSeqEntryActions::new().update(
+            CONFLICT_STRATEGY_KEY.to_vec(),
+            b"reject".to_vec(),
+            1,
+        ));
+        assert!(resolve(&map, change, None, &owner).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn inserts_of_existing_unseq_entries_win_with_last_write_wins() -> NdResult<()> {
+        let owner = PublicKey::from(SecretKey::random().public_key());
+        let mut map = UnseqMap::new(XorName::random(), 1, owner);
+        map.mutate_entries(
+            MapUnseqEntryActions::new()
+                .ins(b"key".to_vec(), vec![1])
+                .ins(CONFLICT_STRATEGY_KEY.to_vec(), b"last-write-wins".to_vec()),
+            &owner,
+        )?;
+        let mut map = Map::Unseq(map);
+        let actions = MapEntryActions::Unseq(
+            MapUnseqEntryActions::new()
+                .ins(b"key".to_vec(), vec![2])
+                .del(b"missing".to_vec()),
+        );
+        let actions = resolve(&map, actions, None, &owner)?;
+        map.mutate_entries(actions, &owner)?;
+        assert_eq!(value(&map), Some(vec![2]));
+        Ok(())
+    }
+}
@@ -6,9 +6,15 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::{
+    map_conflicts::{self, ConflictStrategy, MapMerge},
+    range_export::{MetadataRecord, RecordKey},
+    read_access, write_validation,
+};
 use crate::{
-    chunk_store::{MapChunkStore, UsedSpace},
+    chunk_store::{ChunkCompression, MapChunkStore, UsedSpace},
     error::convert_to_error_message,
+    network::NetworkClock,
     node_ops::{NodeDuty, OutgoingMsg},
     Error, Network, Result,
 };
@@ -23,19 +29,43 @@ use sn_messaging::{
 };
 
 use std::{
+    collections::BTreeSet,
     fmt::{self, Display, Formatter},
     path::Path,
+    sync::Arc,
 };
 
 /// Operations over the data type Map.
 pub(super) struct MapStorage {
     chunks: MapChunkStore,
+    // merges conflicting writes to Maps with the merge strategy
+    merge: Option<Arc<dyn MapMerge>>,
 }
 
 impl MapStorage {
-    pub(super) async fn new(path: &Path, used_space: UsedSpace) -> Result<Self> {
-        let chunks = MapChunkStore::new(path, used_space).await?;
-        Ok(Self { chunks })
+    pub(super) async fn new(
+        path: &Path,
+        used_space: UsedSpace,
+        secure_delete: bool,
+    ) -> Result<Self> {
+        let mut chunks = MapChunkStore::new(path, used_space).await?;
+        chunks.set_secure_delete(secure_delete);
+        Ok(Self {
+            chunks,
+            merge: None,
+        })
+    }
+
+    pub(super) fn set_merge(&mut self, merge: Arc<dyn MapMerge>) {
+        self.merge = Some(merge);
+    }
+
+    pub(super) fn set_compression(&mut self, compression: Option<ChunkCompression>) {
+        self.chunks.set_compression(compression);
+    }
+
+    pub(super) fn set_clock(&mut self, clock: Arc<NetworkClock>) {
+        self.chunks.set_clock(clock);
     }
 
     pub(super) async fn read(
@@ -98,9 +128,11 @@ impl MapStorage {
     /// terminated.
     fn get_chunk(&self, address: &MapAddress, origin: EndUser, action: MapAction) -> Result<Map> {
         self.chunks.get(&address).and_then(move |map| {
-            map.check_permissions(action, origin.id())
-                .map(move |_| map)
-                .map_err(|error| error.into())
+            let permitted = match action {
+                MapAction::Read => read_access::check_map_read(&map, origin.id()),
+                action => map.check_permissions(action, origin.id()),
+            };
+            permitted.map(move |_| map).map_err(|error| error.into())
         })
     }
 
@@ -126,12 +158,22 @@ impl MapStorage {
         self.ok_or_error(result, msg_id, origin).await
     }
 
-    /// Put Map.
+    /// Put Map. Its conflict strategy must be known, and one we can apply.
     async fn create(&mut self, data: &Map, msg_id: MessageId, origin: EndUser) -> Result<NodeDuty> {
         let result = if self.chunks.has(data.address()) {
             Err(Error::DataExists)
+        } else if let Err(error) = write_validation::validate_new_map(data, origin) {
+            Err(error)
         } else {
-            self.chunks.put(&data).await
+            match ConflictStrategy::of(data) {
+                Ok(ConflictStrategy::Merge) if self.merge.is_none() => {
+                    Err(Error::InvalidOperation(
+                        "No merge registered for Maps with the merge strategy".to_string(),
+                    ))
+                }
+                Ok(_) => self.chunks.put(&data).await,
+                Err(error) => Err(error.into()),
+            }
         };
         self.ok_or_error(result, msg_id, origin).await
     }
@@ -194,7 +236,7 @@ impl MapStorage {
         .await
     }
 
-    /// Edit Map.
+    /// Edit Map, resolving conflicts with its current entries by the strategy of the Map.
     async fn edit_entries(
         &mut self,
         address: MapAddress,
@@ -202,7 +244,13 @@ impl MapStorage {
         msg_id: MessageId,
         origin: EndUser,
     ) -> Result<NodeDuty> {
+        if let Err(error) = write_validation::validate_map_changes(&actions) {
+            return self.ok_or_error(Err(error), msg_id, origin).await;
+        }
+        let merge = self.merge.clone();
         self.edit_chunk(&address, origin, msg_id, move |mut data| {
+            read_access::check_readers_change(&data, &actions, origin.id())?;
+            let actions = map_conflicts::resolve(&data, actions, merge.as_deref(), origin.id())?;
             data.mutate_entries(actions, origin.id())?;
             Ok(data)
         })
@@ -500,6 +548,31 @@ impl MapStorage {
             Ok(NodeDuty::NoOp)
         }
     }
+
+    /// The keys of the entries of the Map, if held.
+    pub(super) fn entry_keys(&self, address: &MapAddress) -> Option<BTreeSet<Vec<u8>>> {
+        self.chunks.get(address).ok().map(|map| map.keys())
+    }
+
+    /// The keys of all Maps held.
+    pub(super) fn record_keys(&self) -> Vec<RecordKey> {
+        self.chunks.keys().into_iter().map(RecordKey::Map).collect()
+    }
+
+    /// The Map as a metadata record, if held.
+    pub(super) fn record(&self, address: &MapAddress) -> Option<MetadataRecord> {
+        self.chunks.get(address).ok().map(MetadataRecord::Map)
+    }
+
+    /// Takes over the Map, as held by another Elder.
+    pub(super) async fn take_over(&mut self, map: &Map) -> Result<()> {
+        self.chunks.put(map).await
+    }
+
+    /// Removes the Map, as removed at another Elder.
+    pub(super) async fn remove_record(&mut self, address: &MapAddress) -> Result<()> {
+        self.chunks.delete(address).await
+    }
 }
 
 impl Display for MapStorage {
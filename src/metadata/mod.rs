@@ -6,32 +6,150 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod adult_capacity;
+mod adult_liveness;
 pub mod adult_reader;
 mod blob_register;
+mod catch_up;
+mod chunk_access;
+mod chunk_deletion;
+mod data_map_register;
 mod elder_stores;
+mod erasure_coding;
+mod existence;
+mod hot_data;
+mod map_conflicts;
 mod map_storage;
+mod range_export;
+mod read_access;
 mod reading;
+mod reed_solomon;
+mod relayed_reads;
+mod replication_batch;
+mod section_dump;
 mod sequence_storage;
+mod split_staging;
+mod storage_challenges;
+mod write_acks;
+mod write_dedup;
+mod write_quotas;
+mod write_validation;
 mod writing;
 
+use self::adult_capacity::CAPACITY_REPORT_TICK;
+pub use self::adult_capacity::{CapacityHistogram, CapacityReport, CAPACITY_HISTOGRAM_BUCKET};
+pub use self::adult_liveness::AdultLiveness;
+use self::adult_liveness::LIVENESS_TICK;
 use self::adult_reader::AdultReader;
 use super::node_ops::NodeDuty;
 use crate::{
-    capacity::ChunkHolderDbs, chunk_store::UsedSpace, node_ops::NodeDuties, Network, Result,
+    capacity::ChunkHolderDbs,
+    chunk_store::{ChunkCompression, UsedSpace},
+    chunks::{ChunkAccess, ChunkRange, ImportedChunks, OrphanOffer},
+    network::NetworkClock,
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::{NodeDuties, OutgoingMsg, PeriodicTask},
+    utils, Error, Network, Result,
 };
 use blob_register::BlobRegister;
+pub(crate) use blob_register::CHUNK_COPY_COUNT;
+pub use blob_register::{ChunkRepair, RepairReport};
+use bls::PublicKeySet;
+use catch_up::CATCH_UP_TICK;
+use catch_up::{CatchUp, ChangeLog};
+pub use catch_up::{CatchUpPage, CatchUpQuery, SignedCatchUpPage};
+use chunk_access::ChunkPopularity;
+use chunk_access::CHUNK_ACCESS_TICK;
+pub use chunk_access::{ChunkAccessQuery, ChunkAccessReport, MAX_CHUNK_ACCESS_REPORT};
+use chunk_deletion::DeletionAudit;
+pub use chunk_deletion::{ChunkDeletion, ChunkDeletionPolicy, DeletionRecord};
+use data_map_register::DataMapRegister;
+pub use data_map_register::{DataMapCmd, DataMapQuery};
 use elder_stores::ElderStores;
+pub use erasure_coding::{ErasureCoding, ShardMap};
+pub use existence::{ExistenceAttestation, ExistenceQuery, ExistenceStatement};
+use hot_data::HotDataTracker;
+pub use hot_data::{Access, DataKind, HotData, HotObject, HOT_DATA_TOP, HOT_DATA_WINDOW};
+use log::{info, warn};
+pub use map_conflicts::{ConflictStrategy, MapMerge, CONFLICT_STRATEGY_KEY};
 use map_storage::MapStorage;
+pub use range_export::{MetadataPage, MetadataRangeQuery, MetadataRecord, RecordKey};
+use range_export::{RangeImport, MAX_PAGE_SIZE};
+pub use read_access::READERS_KEY;
+use relayed_reads::RELAYED_READ_TICK;
+pub use relayed_reads::{RelayedChunk, RelayedRead};
+pub use replication_batch::ReplicationBatch;
+pub use section_dump::{MetadataDump, MetadataImports, SignedMetadataDump};
 use sequence_storage::SequenceStorage;
+use sn_data_types::{Blob, BlobAddress, PublicBlob, PublicKey, Signature};
 use sn_messaging::{
-    client::{DataCmd, DataQuery},
-    EndUser, MessageId,
+    client::{
+        BlobRead, BlobWrite, DataCmd, DataQuery, MapWrite, Message, NodeCmd, NodeDataQueryResponse,
+        NodeQueryResponse,
+    },
+    Aggregation, DstLocation, EndUser, MessageId,
 };
+use split_staging::StagedRange;
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fmt::{self, Display, Formatter},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use xor_name::XorName;
+pub use storage_challenges::{StorageChallenge, StorageProof};
+use storage_challenges::{StorageChallenges, CHALLENGES_PER_ROUND};
+use write_acks::WriteAcks;
+pub use write_acks::{AckLevel, WriteAck, WriteAckLevel, ACK_LEVEL};
+use write_dedup::RecentWrites;
+pub use write_quotas::WriteQuotas;
+use write_quotas::{Appends, QuotaTracker};
+use xor_name::{Prefix, XorName};
+
+/// The background work on the metadata, retrying relayed reads only if reads are
+/// relayed, and challenging the storage of the Adults only if it is to be challenged.
+pub(crate) fn periodic_tasks(
+    relayed_reads: bool,
+    storage_challenge_interval: Option<Duration>,
+) -> Vec<PeriodicTask> {
+    let mut tasks = vec![
+        PeriodicTask {
+            name: "adult liveness checks",
+            interval: LIVENESS_TICK,
+            duty: || NodeDuty::CheckAdultLiveness,
+        },
+        PeriodicTask {
+            name: "chunk access queries",
+            interval: CHUNK_ACCESS_TICK,
+            duty: || NodeDuty::QueryChunkAccess,
+        },
+        PeriodicTask {
+            name: "capacity reports",
+            interval: CAPACITY_REPORT_TICK,
+            duty: || NodeDuty::ReportCapacity,
+        },
+        PeriodicTask {
+            name: "metadata catch-up checks",
+            interval: CATCH_UP_TICK,
+            duty: || NodeDuty::CheckMetadataCatchUp,
+        },
+    ];
+    if relayed_reads {
+        tasks.push(PeriodicTask {
+            name: "relayed read retries",
+            interval: RELAYED_READ_TICK,
+            duty: || NodeDuty::RetryRelayedReads,
+        });
+    }
+    if let Some(interval) = storage_challenge_interval {
+        tasks.push(PeriodicTask {
+            name: "storage challenges",
+            interval,
+            duty: || NodeDuty::IssueStorageChallenge,
+        });
+    }
+    tasks
+}
 
 /// This module is called `Metadata`
 /// as a preparation for the responsibilities
@@ -41,6 +159,31 @@ use xor_name::XorName;
 /// all underlying data being chunks stored at `Adults`.
 pub struct Metadata {
     elder_stores: ElderStores,
+    data_maps: DataMapRegister,
+    write_acks: WriteAcks,
+    // the quotas on the writes to each Sequence and Map
+    quotas: QuotaTracker,
+    // the writes applied recently, to not apply those received again
+    recent_writes: RecentWrites,
+    // our name, as the Elder acking writes
+    node_name: XorName,
+    // the chunk deletions by section policy
+    deletions: DeletionAudit,
+    // the reads and writes, to report the hot data by
+    hot_data: HotDataTracker,
+    // the storage challenges issued by us, pending the answers of holders
+    challenges: StorageChallenges,
+    // the reads of the chunks held by our Adults, as reported by them
+    chunk_popularity: ChunkPopularity,
+    root_dir: PathBuf,
+    // the records of our sibling, staged at our last split for it to take over
+    staged: Option<StagedRange>,
+    // the names written to, for new Elders to catch up on
+    changes: ChangeLog,
+    // our catch-up on the records of the section, as newly promoted, until caught up
+    catch_up: Option<CatchUp>,
+    // our import of the records of the section from our sibling, after split, until imported
+    import: Option<RangeImport>,
 }
 
 impl Metadata {
@@ -49,25 +192,829 @@ impl Metadata {
         used_space: &UsedSpace,
         dbs: ChunkHolderDbs,
         reader: AdultReader,
+        node_name: XorName,
+        min_write_acks: u8,
+        secure_delete: bool,
     ) -> Result<Self> {
         let blob_register = BlobRegister::new(dbs, reader);
-        let map_storage = MapStorage::new(path, used_space.clone()).await?;
-        let sequence_storage = SequenceStorage::new(path, used_space.clone()).await?;
+        let map_storage = MapStorage::new(path, used_space.clone(), secure_delete).await?;
+        let sequence_storage =
+            SequenceStorage::new(path, used_space.clone(), secure_delete).await?;
         let elder_stores = ElderStores::new(blob_register, map_storage, sequence_storage);
-        Ok(Self { elder_stores })
+        let data_maps = DataMapRegister::new(path)?;
+        let deletions = DeletionAudit::new(path)?;
+        Ok(Self {
+            elder_stores,
+            data_maps,
+            write_acks: WriteAcks::new(min_write_acks),
+            quotas: QuotaTracker::new(WriteQuotas::default()),
+            recent_writes: RecentWrites::default(),
+            node_name,
+            deletions,
+            hot_data: HotDataTracker::default(),
+            challenges: StorageChallenges::default(),
+            chunk_popularity: ChunkPopularity::default(),
+            root_dir: path.to_path_buf(),
+            staged: None,
+            changes: ChangeLog::default(),
+            catch_up: None,
+            import: None,
+        })
+    }
+
+    pub async fn read(
+        &mut self,
+        query: DataQuery,
+        id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        self.hot_data.read(&query, Instant::now());
+        reading::get_result(query, id, origin, &mut self.elder_stores).await
+    }
+
+    /// Reads only the bytes of the range of a chunk.
+    pub async fn read_range(
+        &mut self,
+        address: BlobAddress,
+        range: ChunkRange,
+        id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        let read = BlobRead::Get(address);
+        self.hot_data
+            .read(&DataQuery::Blob(read.clone()), Instant::now());
+        self.elder_stores
+            .blob_register_mut()
+            .read(&read, Some(range), id, origin)
+            .await
     }
 
-    pub async fn read(&self, query: DataQuery, id: MessageId, origin: EndUser) -> Result<NodeDuty> {
-        reading::get_result(query, id, origin, &self.elder_stores).await
+    /// Takes the chunk read by the holder for a read relayed by us, answering
+    /// the client if it is the first chunk verified to be the one read.
+    pub fn relay_chunk(&mut self, answer: RelayedChunk, holder: XorName) -> Option<NodeDuty> {
+        self.elder_stores
+            .blob_register_mut()
+            .relay_chunk(answer, holder)
     }
 
+    /// Queries the next holders of the relayed reads not answered in time.
+    pub fn retry_relayed_reads(&mut self) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .retry_relayed_reads(Instant::now())
+    }
+
+    /// Applies the write, unless it exceeds the quotas on the writes to Sequences
+    /// and Maps. If writes are to be acknowledged, a successful write is recorded
+    /// as acked by us, and told to the other Elders of our section given. A write
+    /// received again after being applied, by the id of its msg and the name of the
+    /// data written, is not applied again, the responses to the client for it being
+    /// sent again instead.
     pub async fn write(
         &mut self,
         cmd: DataCmd,
         id: MessageId,
         origin: EndUser,
+        our_elders: &BTreeSet<XorName>,
+    ) -> Result<NodeDuties> {
+        let now = Instant::now();
+        let write = (id, cmd.dst_address(), *origin.id());
+        if let Some(responses) = self.recent_writes.received_again(&write, now) {
+            info!("Not applying write {:?} again, received again", id);
+            return Ok(responses);
+        }
+        let map_keys = match &cmd {
+            DataCmd::Map(MapWrite::Edit { address, .. }) => {
+                self.elder_stores.map_storage().entry_keys(address)
+            }
+            _ => None,
+        };
+        if let Err(error) = self.quotas.check(&cmd, map_keys.as_ref(), now) {
+            return Ok(vec![write_quotas::refusal(error, id, origin)?]);
+        }
+        self.hot_data.write(&cmd, now);
+        self.changes.record(cmd.dst_address());
+        let appends = Appends::of(&cmd);
+        let mut duties = writing::get_result(cmd, id, origin, &mut self.elder_stores).await?;
+        let is_error = duties.iter().any(|duty| {
+            matches!(
+                duty,
+                NodeDuty::Send(OutgoingMsg {
+                    msg: Message::CmdError { .. },
+                    ..
+                })
+            )
+        });
+        if !is_error {
+            if let Some(appends) = appends {
+                self.quotas.record(appends, now);
+            }
+            duties.extend(
+                self.write_acks
+                    .applied(id, origin, self.node_name, our_elders),
+            );
+            self.recent_writes.record(write, &duties, now);
+        }
+        Ok(duties)
+    }
+
+    /// Sets the quotas on the writes to each Sequence and Map.
+    pub fn set_write_quotas(&mut self, quotas: WriteQuotas) {
+        self.quotas.set_quotas(quotas);
+    }
+
+    /// The most read and written data over the last `HOT_DATA_WINDOW`.
+    pub fn hot_data(&self, top: usize) -> HotData {
+        self.hot_data.hot_data(top, Instant::now())
+    }
+
+    /// Queries the Adults for the reads of their most read chunks.
+    pub fn query_chunk_access(&mut self, adults: BTreeSet<XorName>) -> Result<NodeDuties> {
+        self.chunk_popularity.query(adults)
+    }
+
+    /// Records the reads of the chunks reported by the holder.
+    pub fn record_chunk_access(&mut self, holder: XorName, report: ChunkAccessReport) {
+        if !self.chunk_popularity.record(holder, report) {
+            info!("Dropping stale chunk access report of {}", holder);
+        }
+    }
+
+    /// The reads of the most read chunks of each of our Adults, as last reported by it.
+    pub fn chunk_access(&self) -> BTreeMap<XorName, Vec<(BlobAddress, ChunkAccess)>> {
+        self.chunk_popularity.reports()
+    }
+
+    /// Records the capacity of the storage reported by the Adult,
+    /// for the holders of new chunks to be chosen by their room and capability.
+    pub async fn record_capacity(&mut self, adult: XorName, report: CapacityReport) -> Result<()> {
+        self.elder_stores
+            .blob_register_mut()
+            .record_capacity(adult, report)
+            .await
+    }
+
+    /// The capacity of the storage of our Adults, as last reported by them.
+    pub async fn capacity_histogram(&mut self) -> CapacityHistogram {
+        self.elder_stores
+            .blob_register_mut()
+            .capacity_histogram()
+            .await
+    }
+
+    /// Registers the merge of conflicting writes to Maps with the merge strategy.
+    pub fn set_map_merge(&mut self, merge: Arc<dyn MapMerge>) {
+        self.elder_stores.map_storage_mut().set_merge(merge);
+    }
+
+    /// Compresses the Maps and Sequences written to disk from then on, if `compression` is set.
+    pub fn set_chunk_compression(&mut self, compression: Option<ChunkCompression>) {
+        self.elder_stores
+            .map_storage_mut()
+            .set_compression(compression);
+        self.elder_stores
+            .sequence_storage_mut()
+            .set_compression(compression);
+    }
+
+    /// Records the accesses of the Maps and Sequences held at the time of the network,
+    /// as told by `clock`, rather than by our own clock.
+    pub(crate) fn set_clock(&mut self, clock: Arc<NetworkClock>) {
+        self.elder_stores.map_storage_mut().set_clock(clock.clone());
+        self.elder_stores.sequence_storage_mut().set_clock(clock);
+    }
+
+    /// Sends the chunks to replicate when a member is lost to each new holder
+    /// in batches, rather than in one msg per chunk.
+    pub fn set_batch_replication(&mut self, batch_replication: bool) {
+        self.elder_stores
+            .blob_register_mut()
+            .set_batch_replication(batch_replication);
+    }
+
+    /// Relays the reads of chunks by clients, querying the number of holders given at
+    /// a time, rather than the holders answering the clients directly, if given.
+    pub fn set_relayed_reads(&mut self, holders_at_once: Option<usize>) {
+        self.elder_stores
+            .blob_register_mut()
+            .set_relayed_reads(holders_at_once);
+    }
+
+    /// Stores new chunks erasure coded into the shards given, if given,
+    /// rather than copied whole to `CHUNK_COPY_COUNT` holders.
+    pub fn set_erasure_coding(&mut self, coding: Option<ErasureCoding>) -> Result<()> {
+        self.elder_stores
+            .blob_register_mut()
+            .set_erasure_coding(coding)
+    }
+
+    /// Records the ack level selected by the client for the write, returning
+    /// the msg acknowledging the write right away at `AckLevel::Accepted`.
+    pub fn select_ack_level(
+        &mut self,
+        id: MessageId,
+        origin: EndUser,
+        level: AckLevel,
+        elder_count: usize,
+    ) -> Option<NodeDuty> {
+        self.write_acks.select(id, origin, level, elder_count)
+    }
+
+    /// Sets the min number of Elders of our section to have applied
+    /// a client write, as pushed by our Elders.
+    pub fn set_min_write_acks(&mut self, min_write_acks: u8) {
+        self.write_acks.set_min_acks(min_write_acks)
+    }
+
+    /// Records an Elder as having applied a client write, acknowledging
+    /// the write to the client once the min number of Elders have.
+    pub fn record_write_ack(
+        &mut self,
+        id: MessageId,
+        origin: EndUser,
+        elder: XorName,
+    ) -> Option<NodeDuty> {
+        self.write_acks.record(id, origin, elder)
+    }
+
+    /// Stores or deletes a client data map.
+    pub async fn write_data_map(
+        &mut self,
+        cmd: DataMapCmd,
+        id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuty> {
+        self.changes.record(cmd.name());
+        self.data_maps.write(cmd, id, origin).await
+    }
+
+    /// Reads a client data map, or lists its versions.
+    pub async fn read_data_map(
+        &self,
+        query: &DataMapQuery,
+        id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuty> {
+        self.data_maps.read(query, id, origin).await
+    }
+
+    /// The statement of the chunk existing at the time proposed, for us to attest,
+    /// if the chunk is held in our section, reading the secs since unix epoch given.
+    pub async fn attest_existence(
+        &self,
+        query: &ExistenceQuery,
+        section_key: PublicKey,
+        now: u64,
+    ) -> Result<ExistenceStatement> {
+        let held = self
+            .elder_stores
+            .blob_register()
+            .record(query.address)
+            .await
+            .is_some();
+        if !held {
+            return Err(Error::NoSuchChunk);
+        }
+        ExistenceStatement::new(query, section_key, now)
+    }
+
+    /// Records the redundancy paid for a chunk,
+    /// to be respected when storing and replicating it.
+    pub async fn set_chunk_redundancy(
+        &mut self,
+        address: BlobAddress,
+        multiplier: u64,
+    ) -> Result<()> {
+        self.elder_stores
+            .blob_register_mut()
+            .set_redundancy(address, multiplier)
+            .await
+    }
+
+    /// Re-verifies the holders of the listed chunks of the
+    /// requester, triggers repairs, and reports back on each chunk.
+    pub async fn repair_chunks(
+        &mut self,
+        addresses: Vec<BlobAddress>,
+        id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .repair(addresses, id, origin)
+            .await
+    }
+
+    /// Challenges the holders of random chunks to prove they hold them. The holders
+    /// failing the challenges expired by then are repaired, as on a failed read.
+    pub async fn issue_storage_challenges(&mut self) -> Result<NodeDuties> {
+        let now = Instant::now();
+        let failed = self.challenges.expire(now);
+        let mut duties = self.repair_challenged(failed).await?;
+        let chunks = self
+            .elder_stores
+            .blob_register()
+            .random_chunks(CHALLENGES_PER_ROUND)
+            .await?;
+        let issued = self.challenges.issue(chunks, now)?;
+        self.elder_stores
+            .blob_register_mut()
+            .record_queried(&issued, now);
+        duties.extend(issued);
+        Ok(duties)
+    }
+
+    /// Records the answer of the holder to a storage challenge,
+    /// repairing the holders failing it once it is concluded.
+    pub async fn verify_storage_proof(
+        &mut self,
+        proof: StorageProof,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .record_answered(holder, Instant::now());
+        let failed = self.challenges.answered(proof, holder);
+        self.repair_challenged(failed).await
+    }
+
+    async fn repair_challenged(
+        &mut self,
+        failed: Vec<(BlobAddress, XorName)>,
+    ) -> Result<NodeDuties> {
+        let mut duties = vec![];
+        for (address, holder) in failed {
+            warn!(
+                "Holder {} failed a storage challenge of {:?}",
+                holder, address
+            );
+            duties.extend(self.repair_chunk_holder(address, holder).await?);
+        }
+        Ok(duties)
+    }
+
+    /// Registers the Adult as holding the chunks it imported.
+    pub async fn register_imported_chunks(
+        &mut self,
+        imported: ImportedChunks,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .register_imported(imported, holder)
+            .await
+    }
+
+    /// Has the chunk offered by a holder out of our section copied to our Adults.
+    pub async fn adopt_orphan_chunk(
+        &mut self,
+        offer: OrphanOffer,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .adopt_orphan(offer, holder)
+            .await
+    }
+
+    /// Has a holder found without a good copy of a chunk when reading it
+    /// copy the chunk from the other holders, scoring the incident.
+    pub async fn repair_chunk_holder(
+        &mut self,
+        address: BlobAddress,
+        holder: XorName,
+    ) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .repair_holder(address, holder)
+            .await
+    }
+
+    /// Deletes the chunks matching the policy of the deletion, recording it
+    /// in the audit trail. The holders are told to delete the chunks by the
+    /// section, so they only do so once enough Elders have sent the same deletion.
+    /// `now` is the secs since the unix epoch, in network time.
+    pub async fn delete_chunks(
+        &mut self,
+        deletion: ChunkDeletion,
+        section_key: PublicKey,
+        now: u64,
+    ) -> Result<NodeDuties> {
+        let batch = deletion.id()?;
+        let chunks = self
+            .elder_stores
+            .blob_register_mut()
+            .remove_matching(&deletion.policy)
+            .await?;
+        self.deletions.start(deletion, &chunks, now)?;
+
+        let mut duties = vec![];
+        for (address, holders) in chunks {
+            // the same at all Elders, for the holders to aggregate the msgs
+            let msg = Message::NodeCmd {
+                cmd: NodeCmd::Chunks {
+                    cmd: BlobWrite::DeletePrivate(address),
+                    origin: EndUser::AllClients(section_key),
+                },
+                id: MessageId::combine(vec![batch, *address.name()]),
+                target_section_pk: None,
+            };
+            duties.extend(holders.into_iter().map(|holder| {
+                NodeDuty::Send(OutgoingMsg {
+                    msg: msg.clone(),
+                    section_source: true,
+                    dst: DstLocation::Node(holder),
+                    aggregation: Aggregation::AtDestination,
+                })
+            }));
+        }
+        Ok(duties)
+    }
+
+    /// Records a holder as having deleted a chunk by section policy.
+    pub fn record_chunk_deleted(
+        &mut self,
+        address: BlobAddress,
+        holder: XorName,
+        proof: &Signature,
+    ) -> Result<Vec<DeletionRecord>> {
+        self.deletions.record_ack(address, holder, proof)
+    }
+
+    /// Sends a page of the metadata records within the range of the query
+    /// to the Elder asking.
+    pub async fn send_range(
+        &self,
+        query: &MetadataRangeQuery,
+        id: MessageId,
+        origin: XorName,
     ) -> Result<NodeDuty> {
-        writing::get_result(cmd, id, origin, &mut self.elder_stores).await
+        let page = self.export_range(query).await?;
+        info!(
+            "Exporting {} metadata records within {:?} to {}",
+            page.records.len(),
+            query.prefix,
+            origin
+        );
+        Ok(NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::MetadataPage { query: id, page }),
+            dst: DstLocation::Node(origin),
+        })
+    }
+
+    /// Stages the metadata records within the prefix of our sibling, on split,
+    /// partitioned off into pages, so that its Elders take them over in bulk,
+    /// rather than having the records filtered by their prefix at each export.
+    pub async fn stage_split(&mut self, sibling_prefix: Prefix) -> Result<usize> {
+        let query = MetadataRangeQuery {
+            prefix: sibling_prefix,
+            after: None,
+        };
+        let mut staged = StagedRange::new(&self.root_dir, sibling_prefix)?;
+        let mut count = 0;
+        for key in range_export::keys_in_range(self.record_keys().await?, &query) {
+            if let Some(record) = self.record(key).await {
+                staged.push(key, record)?;
+                count += 1;
+            }
+        }
+        staged.finish()?;
+        info!(
+            "Staged {} metadata records within {:?} in {} pages",
+            count,
+            sibling_prefix,
+            staged.pages()
+        );
+        self.staged = Some(staged);
+        Ok(count)
+    }
+
+    /// Exports a page of the metadata records within the range of the query,
+    /// for another Elder to take over or reconcile them. The range of our
+    /// sibling is exported as staged at split.
+    pub async fn export_range(&self, query: &MetadataRangeQuery) -> Result<MetadataPage> {
+        if let Some(staged) = &self.staged {
+            if staged.prefix() == &query.prefix {
+                return staged.page(query);
+            }
+        }
+
+        let keys = range_export::keys_in_range(self.record_keys().await?, query);
+        self.export_keys(keys, query).await
+    }
+
+    // Exports a page of the records of the keys, ordered and within the range of the query.
+    async fn export_keys(
+        &self,
+        keys: Vec<RecordKey>,
+        query: &MetadataRangeQuery,
+    ) -> Result<MetadataPage> {
+        let mut records = vec![];
+        let mut page_size = 0;
+        let mut keys = keys.into_iter();
+        for key in &mut keys {
+            // removed since the keys were listed
+            let record = match self.record(key).await {
+                Some(record) => record,
+                None => continue,
+            };
+            page_size += utils::serialise(&record)?.len();
+            records.push((key, record));
+            if page_size >= MAX_PAGE_SIZE {
+                break;
+            }
+        }
+
+        let next = match (keys.next(), records.last()) {
+            (Some(_), Some((last, _))) => Some(MetadataRangeQuery {
+                prefix: query.prefix,
+                after: Some(*last),
+            }),
+            _ => None,
+        };
+        Ok(MetadataPage { records, next })
+    }
+
+    /// Begins catching up on the metadata records of the section from the other Elders,
+    /// as newly promoted, reads not being answered by us until caught up.
+    /// There is nothing to catch up on when there are no other Elders, e.g. at genesis.
+    pub fn begin_catch_up(
+        &mut self,
+        prefix: Prefix,
+        elders: BTreeSet<XorName>,
+    ) -> Result<NodeDuties> {
+        self.catch_up = None;
+        let (catch_up, elder, query) = match CatchUp::new(prefix, elders, Instant::now()) {
+            Some(catch_up) => catch_up,
+            None => return Ok(vec![]),
+        };
+        info!("Catching up on metadata within {:?} from {}", prefix, elder);
+        self.catch_up = Some(catch_up);
+        Ok(vec![query.msg(elder)])
+    }
+
+    /// Whether caught up on the metadata records of the section, to answer reads by.
+    pub fn is_caught_up(&self) -> bool {
+        self.catch_up.is_none() && self.import.is_none()
+    }
+
+    /// Asks the next Elder, when the one asked has not answered in time, or is no longer
+    /// an Elder. With no Elders left to ask, we are considered caught up with what we have.
+    pub fn check_catch_up(&mut self, elders: &BTreeSet<XorName>) -> Result<NodeDuties> {
+        let catch_up = match &mut self.catch_up {
+            Some(catch_up) => catch_up,
+            None => return Ok(vec![]),
+        };
+        if let Some((elder, query)) = catch_up.check(elders, Instant::now()) {
+            return Ok(vec![query.msg(elder)]);
+        }
+        if catch_up.is_stalled() {
+            warn!("No Elders left to catch up on metadata from");
+            self.catch_up = None;
+        }
+        Ok(vec![])
+    }
+
+    /// Begins importing the metadata records within our prefix from the Elders of our
+    /// sibling, as Elders of a section none of which were Elders before its split, and so
+    /// none of which hold its records. Reads are not answered by us until imported.
+    pub fn begin_import(&mut self, prefix: Prefix, elders: BTreeSet<XorName>) -> NodeDuties {
+        self.catch_up = None;
+        self.import = None;
+        let (import, msg) = match RangeImport::new(prefix, elders, Instant::now()) {
+            Some(import) => import,
+            None => return vec![],
+        };
+        info!("Importing metadata within {:?} from our sibling", prefix);
+        self.import = Some(import);
+        vec![msg]
+    }
+
+    /// Takes over the records of the page of our import, if it answers the query pending,
+    /// and asks for the next page, until imported.
+    pub async fn import(
+        &mut self,
+        query: MessageId,
+        page: MetadataPage,
+        elder: XorName,
+    ) -> Result<NodeDuties> {
+        let received = match &mut self.import {
+            Some(import) => import.receive(elder, query, &page, Instant::now()),
+            None => return Ok(vec![]),
+        };
+        // not answering the query pending, e.g. of an Elder given up on
+        let next = match received {
+            Some(next) => next,
+            None => return Ok(vec![]),
+        };
+        info!(
+            "Importing {} metadata records from {}",
+            page.records.len(),
+            elder
+        );
+        for (_, record) in page.records {
+            self.take_over(record).await?;
+        }
+        if next.is_none() {
+            info!("Imported metadata from our sibling");
+            self.import = None;
+        }
+        Ok(next.into_iter().collect())
+    }
+
+    /// Asks the next Elder of our sibling, when the one asked has not answered in time.
+    /// With no Elders left to ask, we are considered imported with what we have.
+    pub fn check_import(&mut self) -> NodeDuties {
+        let import = match &mut self.import {
+            Some(import) => import,
+            None => return vec![],
+        };
+        if let Some(msg) = import.check(Instant::now()) {
+            return vec![msg];
+        }
+        if import.is_stalled() {
+            warn!("No Elders of our sibling left to import metadata from");
+            self.import = None;
+        }
+        vec![]
+    }
+
+    /// A page of our records for a new Elder catching up on them: of all of them, or of
+    /// those changed since the mark of the query, when the changes since are known.
+    pub async fn answer_catch_up(
+        &self,
+        query: CatchUpQuery,
+        section_key: PublicKey,
+    ) -> Result<CatchUpPage> {
+        let mark = self.changes.mark();
+        let changed: Option<BTreeSet<_>> = query
+            .since
+            .and_then(|since| self.changes.changed_since(since))
+            .map(|names| {
+                names
+                    .into_iter()
+                    .filter(|name| query.range.prefix.matches(name))
+                    .collect()
+            });
+        let mut keys = range_export::keys_in_range(self.record_keys().await?, &query.range);
+        if let Some(changed) = &changed {
+            keys.retain(|key| changed.contains(key.name()));
+        }
+        let page = self.export_keys(keys, &query.range).await?;
+        Ok(CatchUpPage {
+            section_key,
+            query,
+            page,
+            changed,
+            mark,
+        })
+    }
+
+    /// Takes over the records of the page of our catch-up, if signed by an Elder of our
+    /// section, removing those removed at the Elder, and asks for the next page,
+    /// until caught up.
+    pub async fn catch_up(
+        &mut self,
+        signed: SignedCatchUpPage,
+        elder: XorName,
+        key_set: &PublicKeySet,
+    ) -> Result<NodeDuties> {
+        if !signed.verify(key_set)? {
+            warn!(
+                "Catch-up page from {} not signed by an Elder of our section",
+                elder
+            );
+            return Ok(vec![]);
+        }
+        let received = match &mut self.catch_up {
+            Some(catch_up) => catch_up.receive(elder, signed.page, Instant::now()),
+            None => return Ok(vec![]),
+        };
+        // not answering the query pending, e.g. of an Elder given up on
+        let (caught_up, next) = match received {
+            Some(received) => received,
+            None => return Ok(vec![]),
+        };
+        for (_, record) in caught_up.records {
+            self.take_over(record).await?;
+        }
+        if let Some(removed) = caught_up.removed {
+            for key in self.record_keys().await? {
+                if removed.contains(&key) {
+                    self.remove_record(key).await?;
+                }
+            }
+        }
+        match next {
+            Some(query) => Ok(vec![query.msg(elder)]),
+            None => {
+                self.catch_up = None;
+                Ok(vec![])
+            }
+        }
+    }
+
+    /// All our records within the prefix, to be dumped for the section to be recovered from.
+    pub async fn dump_records(&self, prefix: Prefix) -> Result<Vec<(RecordKey, MetadataRecord)>> {
+        let query = MetadataRangeQuery {
+            prefix,
+            after: None,
+        };
+        let mut records = vec![];
+        for key in range_export::keys_in_range(self.record_keys().await?, &query) {
+            // removed since the keys were listed
+            if let Some(record) = self.record(key).await {
+                records.push((key, record));
+            }
+        }
+        Ok(records)
+    }
+
+    /// Takes over the records of a dump signed by a section, replacing ours,
+    /// of those within our prefix. Returns the number of records taken over.
+    pub async fn import_dump(&mut self, dump: MetadataDump, our_prefix: Prefix) -> Result<usize> {
+        dump.validate()?;
+        let mut imported = 0;
+        for (key, record) in dump.records {
+            if our_prefix.matches(key.name()) {
+                self.take_over(record).await?;
+                self.changes.record(*key.name());
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+
+    // Takes over the record, as held by another Elder, replacing ours.
+    async fn take_over(&mut self, record: MetadataRecord) -> Result<()> {
+        let stores = &mut self.elder_stores;
+        match record {
+            MetadataRecord::Chunk {
+                address,
+                holders,
+                owner,
+                copies,
+            } => {
+                stores
+                    .blob_register_mut()
+                    .take_over(address, holders, owner, copies)
+                    .await
+            }
+            MetadataRecord::Map(map) => stores.map_storage_mut().take_over(&map).await,
+            MetadataRecord::Sequence(sequence) => {
+                stores.sequence_storage_mut().take_over(&sequence).await
+            }
+            MetadataRecord::DataMap {
+                name,
+                owner,
+                versions,
+            } => self.data_maps.take_over(name, owner, versions),
+            MetadataRecord::ShardedChunk { address, shards } => {
+                stores
+                    .blob_register_mut()
+                    .take_over_shard_map(address, &shards)
+                    .await
+            }
+        }
+    }
+
+    // Removes our record, as removed at another Elder.
+    async fn remove_record(&mut self, key: RecordKey) -> Result<()> {
+        let stores = &mut self.elder_stores;
+        match key {
+            RecordKey::Chunk(address) => stores.blob_register_mut().remove_record(address).await,
+            RecordKey::Map(address) => stores.map_storage_mut().remove_record(&address).await,
+            RecordKey::Sequence(address) => {
+                stores.sequence_storage_mut().remove_record(&address).await
+            }
+            RecordKey::DataMap(name) => self.data_maps.remove_record(&name),
+            RecordKey::ShardedChunk(address) => {
+                stores.blob_register_mut().remove_shard_map(address).await
+            }
+        }
+    }
+
+    async fn record_keys(&self) -> Result<Vec<RecordKey>> {
+        let stores = &self.elder_stores;
+        let mut keys = stores.blob_register().record_keys().await?;
+        keys.extend(stores.map_storage().record_keys());
+        keys.extend(stores.sequence_storage().record_keys());
+        keys.extend(self.data_maps.record_keys()?);
+        keys.extend(stores.blob_register().shard_map_keys().await?);
+        Ok(keys)
+    }
+
+    async fn record(&self, key: RecordKey) -> Option<MetadataRecord> {
+        let stores = &self.elder_stores;
+        match key {
+            RecordKey::Chunk(address) => stores.blob_register().record(address).await,
+            RecordKey::Map(address) => stores.map_storage().record(&address),
+            RecordKey::Sequence(address) => stores.sequence_storage().record(&address),
+            RecordKey::DataMap(name) => self.data_maps.record(&name),
+            RecordKey::ShardedChunk(address) => {
+                stores.blob_register().shard_map_record(address).await
+            }
+        }
     }
 
     // This should be called whenever a node leaves the section. It fetches the list of data that was
@@ -79,6 +1026,47 @@ impl Metadata {
             .replicate_chunks(node)
             .await
     }
+
+    /// The liveness of each of our Adults, by their answers to storage challenges
+    /// and relayed reads.
+    pub fn adult_liveness(&self) -> BTreeMap<XorName, AdultLiveness> {
+        self.elder_stores.blob_register().adult_liveness()
+    }
+
+    /// Finds the Adults unresponsive since last checked, and their liveness,
+    /// and copies the chunks held by them to other holders.
+    pub async fn check_adult_liveness(
+        &mut self,
+    ) -> Result<(Vec<(XorName, AdultLiveness)>, NodeDuties)> {
+        self.elder_stores
+            .blob_register_mut()
+            .check_liveness(Instant::now())
+            .await
+    }
+
+    // This should be called when a node is about to leave the section, e.g. when decommissioned.
+    // Its chunks are copied to new holders while it still serves them, and it is no longer
+    // recorded as their holder.
+    pub async fn release_chunk_holder(&mut self, node: XorName) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .release_holder(node)
+            .await
+    }
+
+    // This should be called when a holder offloads chunks by its storage policy.
+    // They are copied to new holders while it still serves them, and it is no longer
+    // recorded as their holder.
+    pub async fn release_chunks(
+        &mut self,
+        holder: XorName,
+        addresses: Vec<BlobAddress>,
+    ) -> Result<NodeDuties> {
+        self.elder_stores
+            .blob_register_mut()
+            .release_chunks(holder, addresses)
+            .await
+    }
 }
 
 impl Display for Metadata {
@@ -0,0 +1,343 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::erasure_coding::ShardMap;
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{BlobAddress, Map, MapAddress, PublicKey, Sequence, SequenceAddress};
+use sn_messaging::{DstLocation, MessageId};
+use std::{
+    collections::BTreeSet,
+    time::{Duration, Instant},
+};
+use xor_name::{Prefix, XorName};
+
+/// Pages are filled with records up to this many bytes, so that
+/// large ranges are exported over many modest sized messages.
+/// A single record larger than this still makes up a page on its own.
+pub(super) const MAX_PAGE_SIZE: usize = 1_000_000;
+// The time an Elder has to answer a page of an import, before the next is asked.
+const IMPORT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A query by an Elder for all metadata records held by another Elder within a
+/// range of names, e.g. at handover to a sibling after split, or for anti-entropy.
+/// Records are returned in pages, ordered by their key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataRangeQuery {
+    /// The range of names to export records of.
+    pub prefix: Prefix,
+    /// The key of the last record received, if any,
+    /// to continue the export after it.
+    pub after: Option<RecordKey>,
+}
+
+impl MetadataRangeQuery {
+    /// The msg asking the Elder, with the id the page answering it is sent under.
+    pub(crate) fn msg(&self, elder: XorName) -> (MessageId, NodeDuty) {
+        let msg = NodeMsg::new(NodeMsgBody::MetadataRangeQuery(self.clone()));
+        let id = msg.id;
+        let duty = NodeDuty::SendNodeMsg {
+            msg,
+            dst: DstLocation::Node(elder),
+        };
+        (id, duty)
+    }
+}
+
+/// The key of a metadata record.
+/// Records are ordered by their name first, and then by their kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RecordKey {
+    /// The holders of a chunk.
+    Chunk(BlobAddress),
+    /// A Map.
+    Map(MapAddress),
+    /// A Sequence.
+    Sequence(SequenceAddress),
+    /// A client data map.
+    DataMap(XorName),
+    /// The shards of an erasure coded chunk.
+    ShardedChunk(BlobAddress),
+}
+
+impl RecordKey {
+    /// The name of the record.
+    pub fn name(&self) -> &XorName {
+        match self {
+            Self::Chunk(address) => address.name(),
+            Self::Map(address) => address.name(),
+            Self::Sequence(address) => address.name(),
+            Self::DataMap(name) => name,
+            Self::ShardedChunk(address) => address.name(),
+        }
+    }
+}
+
+/// A metadata record held at Elders.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MetadataRecord {
+    /// The holders of a chunk.
+    Chunk {
+        /// Address of the chunk.
+        address: BlobAddress,
+        /// Adults holding the chunk.
+        holders: BTreeSet<XorName>,
+        /// Owner of a private chunk.
+        owner: Option<PublicKey>,
+        /// Number of copies paid for, if above standard.
+        copies: Option<usize>,
+    },
+    /// A Map.
+    Map(Map),
+    /// A Sequence.
+    Sequence(Sequence),
+    /// A client data map, with all its versions.
+    DataMap {
+        /// Name of the data map.
+        name: XorName,
+        /// Owner of the data map.
+        owner: PublicKey,
+        /// The versions, oldest first.
+        versions: Vec<Vec<u8>>,
+    },
+    /// The shards of an erasure coded chunk.
+    ShardedChunk {
+        /// Address of the chunk.
+        address: BlobAddress,
+        /// The shards of the chunk.
+        shards: ShardMap,
+    },
+}
+
+impl MetadataRecord {
+    /// The key of the record.
+    pub fn key(&self) -> RecordKey {
+        match self {
+            Self::Chunk { address, .. } => RecordKey::Chunk(*address),
+            Self::Map(map) => RecordKey::Map(*map.address()),
+            Self::Sequence(sequence) => RecordKey::Sequence(*sequence.address()),
+            Self::DataMap { name, .. } => RecordKey::DataMap(*name),
+            Self::ShardedChunk { address, .. } => RecordKey::ShardedChunk(*address),
+        }
+    }
+}
+
+/// A page of the records within the queried range.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetadataPage {
+    /// The records, ordered by key.
+    pub records: Vec<(RecordKey, MetadataRecord)>,
+    /// The query for the next page, if there are more records.
+    pub next: Option<MetadataRangeQuery>,
+}
+
+/// Our import of the records within a range from the Elders of our sibling, as Elders of
+/// a section none of which were Elders before its split, so none of which hold its records.
+/// They are taken over from the Elders of the sibling, who staged them at the split.
+pub(super) struct RangeImport {
+    // the Elders of the sibling, the one imported from first
+    elders: Vec<XorName>,
+    query: MetadataRangeQuery,
+    // the id of the msg of the query pending
+    query_id: MessageId,
+    sent_at: Instant,
+}
+
+impl RangeImport {
+    /// The import from the Elders, with the msg asking the first,
+    /// unless there are none to import from.
+    pub(super) fn new(
+        prefix: Prefix,
+        elders: BTreeSet<XorName>,
+        now: Instant,
+    ) -> Option<(Self, NodeDuty)> {
+        let elders: Vec<_> = elders.into_iter().collect();
+        let first = *elders.first()?;
+        let query = MetadataRangeQuery {
+            prefix,
+            after: None,
+        };
+        let (query_id, msg) = query.msg(first);
+        let import = Self {
+            elders,
+            query,
+            query_id,
+            sent_at: now,
+        };
+        Some((import, msg))
+    }
+
+    /// Takes the page in, if it answers the query pending, returning the
+    /// msg asking for the next page, unless all pages are imported.
+    pub(super) fn receive(
+        &mut self,
+        elder: XorName,
+        query_id: MessageId,
+        page: &MetadataPage,
+        now: Instant,
+    ) -> Option<Option<NodeDuty>> {
+        if self.elders.first() != Some(&elder) || query_id != self.query_id {
+            return None;
+        }
+        let next = match &page.next {
+            Some(next) => next.clone(),
+            None => return Some(None),
+        };
+        let (query_id, msg) = next.msg(elder);
+        self.query = next;
+        self.query_id = query_id;
+        self.sent_at = now;
+        Some(Some(msg))
+    }
+
+    /// Asks the next Elder for the page pending, when the one asked has not answered in time.
+    /// With no Elders left to ask, the import is given up on, with what was imported.
+    pub(super) fn check(&mut self, now: Instant) -> Option<NodeDuty> {
+        let asked = *self.elders.first()?;
+        if now.saturating_duration_since(self.sent_at) < IMPORT_TIMEOUT {
+            return None;
+        }
+        warn!(
+            "Elder {} not answering the import of metadata, asking the next",
+            asked
+        );
+        let _ = self.elders.remove(0);
+        let next = *self.elders.first()?;
+        let (query_id, msg) = self.query.msg(next);
+        self.query_id = query_id;
+        self.sent_at = now;
+        Some(msg)
+    }
+
+    /// Whether there are no Elders left to import from.
+    pub(super) fn is_stalled(&self) -> bool {
+        self.elders.is_empty()
+    }
+}
+
+/// Orders the keys, keeping those within the range of the query.
+pub(super) fn keys_in_range(keys: Vec<RecordKey>, query: &MetadataRangeQuery) -> Vec<RecordKey> {
+    let mut keys: Vec<_> = keys
+        .into_iter()
+        .filter(|key| query.prefix.matches(key.name()))
+        .filter(|key| match &query.after {
+            Some(after) => sort_key(key) > sort_key(after),
+            None => true,
+        })
+        .collect();
+    keys.sort_by_key(sort_key);
+    keys
+}
+
+pub(super) fn sort_key(key: &RecordKey) -> (XorName, RecordKey) {
+    (*key.name(), *key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keys_are_ordered_by_name_and_continue_after_cursor() {
+        let prefix = Prefix::default().pushed(false);
+        let low = XorName([0x01; 32]);
+        let high = XorName([0x7f; 32]);
+        let outside = XorName([0xff; 32]);
+        let keys = vec![
+            RecordKey::DataMap(high),
+            RecordKey::Map(MapAddress::Seq { name: low, tag: 1 }),
+            RecordKey::Chunk(BlobAddress::Public(outside)),
+            RecordKey::Chunk(BlobAddress::Public(low)),
+        ];
+
+        let mut query = MetadataRangeQuery {
+            prefix,
+            after: None,
+        };
+        assert_eq!(
+            keys_in_range(keys.clone(), &query),
+            vec![
+                RecordKey::Chunk(BlobAddress::Public(low)),
+                RecordKey::Map(MapAddress::Seq { name: low, tag: 1 }),
+                RecordKey::DataMap(high),
+            ]
+        );
+
+        query.after = Some(RecordKey::Map(MapAddress::Seq { name: low, tag: 1 }));
+        assert_eq!(keys_in_range(keys, &query), vec![RecordKey::DataMap(high)]);
+    }
+
+    fn query_of(duty: &NodeDuty) -> (MessageId, MetadataRangeQuery, XorName) {
+        match duty {
+            NodeDuty::SendNodeMsg {
+                msg:
+                    NodeMsg {
+                        id,
+                        body: NodeMsgBody::MetadataRangeQuery(query),
+                    },
+                dst: DstLocation::Node(elder),
+            } => (*id, query.clone(), *elder),
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    #[test]
+    fn ranges_are_imported_page_by_page_from_the_elders_answering() {
+        let prefix = Prefix::default().pushed(true);
+        let elders: BTreeSet<_> = (0..2).map(|_| XorName::random()).collect();
+        let now = Instant::now();
+        let (mut import, msg) = RangeImport::new(prefix, elders.clone(), now).unwrap();
+        let (id, query, first) = query_of(&msg);
+        assert_eq!(query.prefix, prefix);
+        assert_eq!(query.after, None);
+
+        let after = RecordKey::DataMap(XorName::random());
+        let page = MetadataPage {
+            records: vec![],
+            next: Some(MetadataRangeQuery {
+                prefix,
+                after: Some(after),
+            }),
+        };
+        // pages not answering the query pending are not taken in
+        let second = *elders.iter().find(|elder| **elder != first).unwrap();
+        assert!(import.receive(second, id, &page, now).is_none());
+        assert!(import
+            .receive(first, MessageId::new(), &page, now)
+            .is_none());
+
+        let next = import.receive(first, id, &page, now).unwrap().unwrap();
+        let (next_id, next_query, asked) = query_of(&next);
+        assert_eq!(next_query.after, Some(after));
+        assert_eq!(asked, first);
+
+        // the page pending is asked of the next Elder, once timed out
+        assert!(import.check(now).is_none());
+        let retry = import.check(now + IMPORT_TIMEOUT).unwrap();
+        let (retry_id, retry_query, asked) = query_of(&retry);
+        assert_eq!(retry_query, next_query);
+        assert_eq!(asked, second);
+        assert!(import.receive(first, next_id, &page, now).is_none());
+
+        let last = MetadataPage {
+            records: vec![],
+            next: None,
+        };
+        assert_eq!(
+            import
+                .receive(second, retry_id, &last, now)
+                .map(|next| next.is_none()),
+            Some(true)
+        );
+    }
+}
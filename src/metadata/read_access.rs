@@ -0,0 +1,185 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Authorisation of the reads of Maps and Sequences, so that their entries and
+//! policies are only sent to the clients permitted to read them.
+
+use crate::utils;
+use sn_data_types::{
+    Error as DtError, Map, MapAction, MapEntryActions, MapSeqEntryAction, MapUnseqEntryAction,
+    PublicKey, Result as NdResult, Sequence, SequenceAction,
+};
+use std::collections::BTreeSet;
+
+/// The reserved entry of a Map listing the keys permitted to read it, besides its owner
+/// and the users permitted to by its permissions, as the serialised set of the keys.
+/// It is only set by the users permitted to manage the permissions of the Map.
+pub const READERS_KEY: &[u8] = b"_sn_readers";
+
+/// The readers listed by the Map, if any.
+pub(super) fn readers(map: &Map) -> NdResult<BTreeSet<PublicKey>> {
+    let value = match map {
+        Map::Seq(map) => map.get(READERS_KEY).map(|value| &value.data),
+        Map::Unseq(map) => map.get(READERS_KEY),
+    };
+    match value {
+        Some(value) => parse_readers(value),
+        None => Ok(BTreeSet::new()),
+    }
+}
+
+fn parse_readers(value: &[u8]) -> NdResult<BTreeSet<PublicKey>> {
+    utils::deserialise(value)
+        .map_err(|_| DtError::FailedToParse("Invalid list of Map readers".to_string()))
+}
+
+/// A Map is read by its owner, the users its permissions permit to read,
+/// and the readers it lists.
+pub(super) fn check_map_read(map: &Map, requester: &PublicKey) -> NdResult<()> {
+    if map.check_permissions(MapAction::Read, requester).is_ok()
+        || readers(map)?.contains(requester)
+    {
+        Ok(())
+    } else {
+        Err(DtError::AccessDenied(*requester))
+    }
+}
+
+/// A public Sequence is read by anyone, a private one by its owner and
+/// the users its policy permits to read, i.e. the readers the policy lists.
+pub(super) fn check_sequence_read(sequence: &Sequence, requester: &PublicKey) -> NdResult<()> {
+    sequence.check_permission(SequenceAction::Read, Some(*requester))
+}
+
+/// Changes to the readers listed by a Map are made by the users permitted
+/// to manage its permissions, and must list keys.
+pub(super) fn check_readers_change(
+    map: &Map,
+    actions: &MapEntryActions,
+    requester: &PublicKey,
+) -> NdResult<()> {
+    let new_readers = match actions {
+        MapEntryActions::Seq(actions) => match actions.actions().get(READERS_KEY) {
+            Some(MapSeqEntryAction::Ins(value)) | Some(MapSeqEntryAction::Update(value)) => {
+                Some(Some(&value.data))
+            }
+            Some(MapSeqEntryAction::Del(_)) => Some(None),
+            None => None,
+        },
+        MapEntryActions::Unseq(actions) => match actions.actions().get(READERS_KEY) {
+            Some(MapUnseqEntryAction::Ins(value)) | Some(MapUnseqEntryAction::Update(value)) => {
+                Some(Some(value))
+            }
+            Some(MapUnseqEntryAction::Del) => Some(None),
+            None => None,
+        },
+    };
+    let new_readers = match new_readers {
+        Some(new_readers) => new_readers,
+        None => return Ok(()),
+    };
+    map.check_permissions(MapAction::ManagePermissions, requester)?;
+    if let Some(value) = new_readers {
+        let _ = parse_readers(value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::{
+        MapPermissionSet, MapSeqEntryActions, SeqMap, SequencePrivatePermissions,
+        SequencePrivatePolicy,
+    };
+    use std::collections::BTreeMap;
+    use xor_name::XorName;
+
+    fn key() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+
+    fn listing(readers: &BTreeSet<PublicKey>) -> NdResult<Vec<u8>> {
+        utils::serialise(readers)
+            .map(|bytes| bytes.to_vec())
+            .map_err(|error| DtError::Serialisation(error.to_string()))
+    }
+
+    #[test]
+    fn maps_are_read_by_owner_permitted_users_and_listed_readers() -> NdResult<()> {
+        let (owner, permitted, listed, other) = (key(), key(), key(), key());
+        let mut map = SeqMap::new(XorName::random(), 1, owner);
+        map.set_user_permissions(permitted, MapPermissionSet::new().allow(MapAction::Read), 1)?;
+        let readers = vec![listed].into_iter().collect();
+        map.mutate_entries(
+            MapSeqEntryActions::new().ins(READERS_KEY.to_vec(), listing(&readers)?, 0),
+            &owner,
+        )?;
+        let map = Map::Seq(map);
+
+        for reader in &[owner, permitted, listed] {
+            check_map_read(&map, reader)?;
+        }
+        assert_eq!(
+            check_map_read(&map, &other),
+            Err(DtError::AccessDenied(other))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn listed_readers_are_only_changed_by_managers_of_permissions() -> NdResult<()> {
+        let (owner, writer) = (key(), key());
+        let mut map = SeqMap::new(XorName::random(), 1, owner);
+        map.set_user_permissions(writer, MapPermissionSet::new().allow(MapAction::Insert), 1)?;
+        let map = Map::Seq(map);
+
+        let readers = vec![writer].into_iter().collect();
+        let change = MapEntryActions::Seq(MapSeqEntryActions::new().ins(
+            READERS_KEY.to_vec(),
+            listing(&readers)?,
+            0,
+        ));
+        check_readers_change(&map, &change, &owner)?;
+        assert!(check_readers_change(&map, &change, &writer).is_err());
+
+        // and must list keys
+        let garbled = MapEntryActions::Seq(MapSeqEntryActions::new().ins(
+            READERS_KEY.to_vec(),
+            vec![1, 2, 3],
+            0,
+        ));
+        assert!(check_readers_change(&map, &garbled, &owner).is_err());
+
+        // other entries are not checked here
+        let other = MapEntryActions::Seq(MapSeqEntryActions::new().ins(b"key".to_vec(), vec![], 0));
+        check_readers_change(&map, &other, &writer)
+    }
+
+    #[test]
+    fn private_sequences_are_read_by_readers_of_their_policy() -> NdResult<()> {
+        let (owner, reader, other) = (key(), key(), key());
+        let mut permissions = BTreeMap::new();
+        let _ = permissions.insert(reader, SequencePrivatePermissions::new(true, false));
+        let _ = permissions.insert(other, SequencePrivatePermissions::new(false, true));
+        let policy = SequencePrivatePolicy { owner, permissions };
+        let sequence =
+            Sequence::new_private(owner, owner.to_string(), XorName::random(), 1, Some(policy));
+
+        check_sequence_read(&sequence, &owner)?;
+        check_sequence_read(&sequence, &reader)?;
+        assert_eq!(
+            check_sequence_read(&sequence, &other),
+            Err(DtError::AccessDenied(other))
+        );
+
+        let public = Sequence::new_public(owner, owner.to_string(), XorName::random(), 1, None);
+        check_sequence_read(&public, &other)
+    }
+}
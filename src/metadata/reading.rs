@@ -13,7 +13,10 @@ use super::{
     sequence_storage::SequenceStorage,
 };
 use crate::Result;
-use crate::{network::Network, node_ops::NodeDuty};
+use crate::{
+    network::Network,
+    node_ops::{NodeDuties, NodeDuty},
+};
 use sn_messaging::{
     client::{BlobRead, DataQuery, MapRead, SequenceRead},
     EndUser, MessageId,
@@ -23,23 +26,27 @@ pub(super) async fn get_result(
     query: DataQuery,
     msg_id: MessageId,
     origin: EndUser,
-    stores: &ElderStores,
-) -> Result<NodeDuty> {
+    stores: &mut ElderStores,
+) -> Result<NodeDuties> {
     use DataQuery::*;
     match &query {
-        Blob(read) => blob(read, stores.blob_register(), msg_id, origin).await,
-        Map(read) => map(read, stores.map_storage(), msg_id, origin).await,
-        Sequence(read) => sequence(read, stores.sequence_storage(), msg_id, origin).await,
+        Blob(read) => blob(read, stores.blob_register_mut(), msg_id, origin).await,
+        Map(read) => map(read, stores.map_storage(), msg_id, origin)
+            .await
+            .map(NodeDuties::from),
+        Sequence(read) => sequence(read, stores.sequence_storage(), msg_id, origin)
+            .await
+            .map(NodeDuties::from),
     }
 }
 
 async fn blob(
     read: &BlobRead,
-    register: &BlobRegister,
+    register: &mut BlobRegister,
     msg_id: MessageId,
     origin: EndUser,
-) -> Result<NodeDuty> {
-    register.read(read, msg_id, origin).await
+) -> Result<NodeDuties> {
+    register.read(read, None, msg_id, origin).await
 }
 
 async fn map(
@@ -0,0 +1,277 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A systematic Reed-Solomon code over GF(2^8): data is split into `k` data shards,
+//! extended by `m` parity shards, and is recovered from any `k` of the `k + m` shards.
+
+use crate::{Error, Result};
+
+// The polynomial the field is generated by, x^8 + x^4 + x^3 + x^2 + 1.
+const FIELD_POLYNOMIAL: u16 = 0x11d;
+/// Shards a chunk is coded into at most, data and parity, as each is
+/// given a distinct element of the field.
+pub const MAX_SHARDS: usize = 256;
+
+const fn exp_table() -> [u8; 512] {
+    let mut table = [0; 512];
+    let mut element: u16 = 1;
+    let mut power = 0;
+    while power < 255 {
+        table[power] = element as u8;
+        // repeated, so that the sum of two logs needs no reduction
+        table[power + 255] = element as u8;
+        element <<= 1;
+        if element & 0x100 != 0 {
+            element ^= FIELD_POLYNOMIAL;
+        }
+        power += 1;
+    }
+    table
+}
+
+const fn log_table() -> [u8; 256] {
+    let exp = exp_table();
+    let mut table = [0; 256];
+    let mut power = 0;
+    while power < 255 {
+        table[exp[power] as usize] = power as u8;
+        power += 1;
+    }
+    table
+}
+
+const EXP: [u8; 512] = exp_table();
+const LOG: [u8; 256] = log_table();
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+}
+
+// the inverse of a non-zero element
+fn inv(a: u8) -> u8 {
+    EXP[255 - LOG[a as usize] as usize]
+}
+
+fn pow(a: u8, exponent: usize) -> u8 {
+    if exponent == 0 {
+        return 1;
+    }
+    if a == 0 {
+        return 0;
+    }
+    EXP[(LOG[a as usize] as usize * exponent) % 255]
+}
+
+type Matrix = Vec<Vec<u8>>;
+
+fn multiply(left: &[Vec<u8>], right: &[Vec<u8>]) -> Matrix {
+    left.iter()
+        .map(|row| {
+            (0..right[0].len())
+                .map(|col| {
+                    row.iter()
+                        .zip(right)
+                        .fold(0, |sum, (a, right_row)| sum ^ mul(*a, right_row[col]))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Inverts the square matrix by Gauss-Jordan elimination.
+fn invert(matrix: &[Vec<u8>]) -> Result<Matrix> {
+    let size = matrix.len();
+    let mut work: Matrix = matrix
+        .iter()
+        .enumerate()
+        .map(|(row, values)| {
+            let mut extended = values.clone();
+            extended.extend((0..size).map(|col| (row == col) as u8));
+            extended
+        })
+        .collect();
+    for col in 0..size {
+        let pivot = (col..size)
+            .find(|row| work[*row][col] != 0)
+            .ok_or_else(|| Error::ErasureCoding("Singular decoding matrix".to_string()))?;
+        work.swap(col, pivot);
+        let scale = inv(work[col][col]);
+        for value in &mut work[col] {
+            *value = mul(*value, scale);
+        }
+        let pivot_row = work[col].clone();
+        for (row, values) in work.iter_mut().enumerate() {
+            let factor = values[col];
+            if row == col || factor == 0 {
+                continue;
+            }
+            for (value, pivot) in values.iter_mut().zip(&pivot_row) {
+                *value ^= mul(factor, *pivot);
+            }
+        }
+    }
+    Ok(work.into_iter().map(|row| row[size..].to_vec()).collect())
+}
+
+/// The code of a number of data shards and parity shards.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct ReedSolomon {
+    data_shards: usize,
+    // the rows of the coding matrix, the first being the identity,
+    // as the data shards are the data itself
+    matrix: Matrix,
+}
+
+impl ReedSolomon {
+    pub(super) fn new(data_shards: usize, parity_shards: usize) -> Result<Self> {
+        let total = data_shards + parity_shards;
+        if data_shards == 0 || total > MAX_SHARDS {
+            return Err(Error::ErasureCoding(format!(
+                "Cannot code into {} data and {} parity shards",
+                data_shards, parity_shards
+            )));
+        }
+        // any rows of a Vandermonde matrix of distinct elements are independent,
+        // and stay so when it is made systematic by the inverse of its top
+        let vandermonde: Matrix = (0..total)
+            .map(|row| (0..data_shards).map(|col| pow(row as u8, col)).collect())
+            .collect();
+        let top = invert(&vandermonde[..data_shards])?;
+        Ok(Self {
+            data_shards,
+            matrix: multiply(&vandermonde, &top),
+        })
+    }
+
+    /// The number of shards of the data itself.
+    pub(super) fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    /// The number of shards the data is coded into.
+    pub(super) fn total_shards(&self) -> usize {
+        self.matrix.len()
+    }
+
+    /// Codes the data into the shards, of the same size, the data padded to fill them.
+    pub(super) fn encode(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let shard_size = data.len().div_ceil(self.data_shards).max(1);
+        let mut shards: Matrix = (0..self.data_shards)
+            .map(|index| {
+                let start = (index * shard_size).min(data.len());
+                let end = ((index + 1) * shard_size).min(data.len());
+                let mut shard = data[start..end].to_vec();
+                shard.resize(shard_size, 0);
+                shard
+            })
+            .collect();
+        let parity: Matrix = self.matrix[self.data_shards..]
+            .iter()
+            .map(|row| {
+                (0..shard_size)
+                    .map(|byte| {
+                        row.iter()
+                            .zip(&shards)
+                            .fold(0, |sum, (a, shard)| sum ^ mul(*a, shard[byte]))
+                    })
+                    .collect()
+            })
+            .collect();
+        shards.extend(parity);
+        shards
+    }
+
+    /// Recovers the data of the length given from any `k` of the shards, by their index.
+    pub(super) fn decode(&self, shards: &[(usize, Vec<u8>)], len: usize) -> Result<Vec<u8>> {
+        let mut chosen: Vec<&(usize, Vec<u8>)> = vec![];
+        for shard in shards {
+            if shard.0 < self.total_shards() && chosen.iter().all(|other| other.0 != shard.0) {
+                chosen.push(shard);
+            }
+            if chosen.len() == self.data_shards {
+                break;
+            }
+        }
+        if chosen.len() < self.data_shards {
+            return Err(Error::ErasureCoding(format!(
+                "{} distinct shards of the {} needed",
+                chosen.len(),
+                self.data_shards
+            )));
+        }
+        let shard_size = chosen[0].1.len();
+        if chosen.iter().any(|(_, shard)| shard.len() != shard_size)
+            || shard_size * self.data_shards < len
+        {
+            return Err(Error::ErasureCoding(
+                "Shards of mismatched sizes".to_string(),
+            ));
+        }
+        let rows: Matrix = chosen
+            .iter()
+            .map(|(index, _)| self.matrix[*index].clone())
+            .collect();
+        let decoding = invert(&rows)?;
+        let mut data = Vec::with_capacity(shard_size * self.data_shards);
+        for row in &decoding {
+            data.extend((0..shard_size).map(|byte| {
+                row.iter()
+                    .zip(&chosen)
+                    .fold(0, |sum, (a, (_, shard))| sum ^ mul(*a, shard[byte]))
+            }));
+        }
+        data.truncate(len);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn field_inverses_multiply_to_one() {
+        for a in 1..=255 {
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+        assert_eq!(mul(0, 7), 0);
+    }
+
+    #[test]
+    fn data_is_recovered_from_any_k_shards() -> Result<()> {
+        let code = ReedSolomon::new(4, 2)?;
+        let data: Vec<u8> = (0..1001).map(|_| rand::thread_rng().gen()).collect();
+        let shards = code.encode(&data);
+        assert_eq!(shards.len(), 6);
+        // the data shards are the data itself
+        assert_eq!(&shards[0][..], &data[..shards[0].len()]);
+
+        let indexed: Vec<_> = shards.into_iter().enumerate().collect();
+        for lost in &[(0, 1), (2, 5), (4, 5), (1, 3)] {
+            let left: Vec<_> = indexed
+                .iter()
+                .filter(|(index, _)| *index != lost.0 && *index != lost.1)
+                .cloned()
+                .collect();
+            assert_eq!(code.decode(&left, data.len())?, data);
+        }
+        assert!(code.decode(&indexed[..3], data.len()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn codes_beyond_the_field_are_refused() {
+        assert!(ReedSolomon::new(0, 2).is_err());
+        assert!(ReedSolomon::new(200, 57).is_err());
+        assert!(ReedSolomon::new(200, 56).is_ok());
+    }
+}
@@ -0,0 +1,385 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    chunks::ChunkRange,
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
+    Result,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Blob, BlobAddress, PrivateBlob, PublicBlob};
+use sn_messaging::{
+    client::{Error as ErrorMessage, Message, QueryResponse},
+    Aggregation, DstLocation, EndUser, MessageId,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// How often the relayed reads are checked for holders not answering in time.
+pub(crate) const RELAYED_READ_TICK: Duration = Duration::from_secs(1);
+// The time the holders queried have to answer, before the next ones are queried.
+const RELAYED_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A read of a chunk by a client, relayed by an Elder to holders of the chunk,
+/// for them to answer to the Elder rather than to the client.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayedRead {
+    /// The chunk read.
+    pub address: BlobAddress,
+    /// Id of the msg of the client reading it.
+    pub msg_id: MessageId,
+}
+
+impl RelayedRead {
+    /// The msg querying the holders.
+    pub(super) fn msg(&self, holders: BTreeSet<XorName>) -> NodeDuty {
+        NodeDuty::SendNodeMsgToNodes {
+            targets: holders,
+            msg: NodeMsg::new(NodeMsgBody::RelayedRead(self.clone())),
+        }
+    }
+}
+
+/// The chunk read by a holder, answered to the Elder relaying the read.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayedChunk {
+    /// The read answered.
+    pub read: RelayedRead,
+    /// The chunk, as held by the holder.
+    pub chunk: Blob,
+}
+
+impl RelayedChunk {
+    /// The msg answering the Elder relaying the read.
+    pub(crate) fn msg(&self, elder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::RelayedChunk(self.clone())),
+            dst: DstLocation::Node(elder),
+        }
+    }
+
+    // Whether the chunk is the one read, by the address of its content,
+    // as holders are not trusted to answer with the chunk asked for.
+    pub(super) fn is_intact(&self) -> bool {
+        let address = match &self.chunk {
+            Blob::Public(chunk) => *PublicBlob::new(chunk.value().clone()).address(),
+            Blob::Private(chunk) => {
+                *PrivateBlob::new(chunk.value().clone(), *chunk.owner()).address()
+            }
+        };
+        address == self.read.address
+    }
+}
+
+struct PendingRead {
+    address: BlobAddress,
+    // the bytes of the chunk answered to the client, if not all of them
+    range: Option<ChunkRange>,
+    origin: EndUser,
+    // the holders queried so far, any of which may answer
+    queried: BTreeSet<XorName>,
+    // the holders to query next, in turn
+    remaining: Vec<XorName>,
+    since: Instant,
+}
+
+/// The reads of chunks by clients relayed to the holders, a number of them at a time,
+/// rather than the holders answering the clients directly. The first chunk verified
+/// to be the one read is answered to the client, so that a read does not depend on
+/// any single holder being online and honest. Holders not answering in time are
+/// followed by the next ones, and the read is failed once all of them were queried.
+pub(super) struct RelayedReads {
+    holders_at_once: usize,
+    // the reads pending, by the id of the msg of the client
+    pending: BTreeMap<MessageId, PendingRead>,
+}
+
+impl RelayedReads {
+    pub(super) fn new(holders_at_once: usize) -> Self {
+        Self {
+            holders_at_once: holders_at_once.max(1),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// The number of holders queried at a time.
+    pub(super) fn holders_at_once(&self) -> usize {
+        self.holders_at_once
+    }
+
+    /// Relays the read to the first of the holders, in the order given.
+    pub(super) fn start(
+        &mut self,
+        address: BlobAddress,
+        range: Option<ChunkRange>,
+        msg_id: MessageId,
+        origin: EndUser,
+        mut holders: Vec<XorName>,
+        now: Instant,
+    ) -> Result<NodeDuty> {
+        if holders.is_empty() {
+            return Ok(response(msg_id, origin, Err(ErrorMessage::NoSuchData)));
+        }
+        let remaining = holders.split_off(self.holders_at_once.min(holders.len()));
+        let queried: BTreeSet<_> = holders.into_iter().collect();
+        let duty = RelayedRead { address, msg_id }.msg(queried.clone());
+        let _ = self.pending.insert(
+            msg_id,
+            PendingRead {
+                address,
+                range,
+                origin,
+                queried,
+                remaining,
+                since: now,
+            },
+        );
+        Ok(duty)
+    }
+
+    /// Takes the answer of the holder, returning the response to the client
+    /// if it is the first answer verified to carry the chunk read.
+    pub(super) fn answer(&mut self, answer: RelayedChunk, holder: XorName) -> Option<NodeDuty> {
+        let msg_id = answer.read.msg_id;
+        let pending = self.pending.get(&msg_id)?;
+        if pending.address != answer.read.address || !pending.queried.contains(&holder) {
+            warn!(
+                "Dropping chunk relayed by {}, not queried for read {:?}",
+                holder, msg_id
+            );
+            return None;
+        }
+        if !answer.is_intact() {
+            warn!(
+                "Dropping chunk relayed by {} for read {:?}, not the chunk read",
+                holder, msg_id
+            );
+            return None;
+        }
+        let pending = self.pending.remove(&msg_id)?;
+        let result = match pending.range {
+            Some(range) => range.slice(&answer.chunk),
+            None => Ok(answer.chunk),
+        };
+        Some(response(msg_id, pending.origin, result))
+    }
+
+    /// Queries the next holders of the reads not answered in time,
+    /// failing those of which all holders were queried.
+    pub(super) fn retry(&mut self, now: Instant) -> Result<NodeDuties> {
+        let due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, read)| now.saturating_duration_since(read.since) >= RELAYED_READ_TIMEOUT)
+            .map(|(msg_id, _)| *msg_id)
+            .collect();
+        let mut duties = vec![];
+        for msg_id in due {
+            let read = match self.pending.get_mut(&msg_id) {
+                Some(read) => read,
+                None => continue,
+            };
+            if read.remaining.is_empty() {
+                info!(
+                    "No holder answered read {:?} of {:?} in time, failing it",
+                    msg_id, read.address
+                );
+                let origin = read.origin;
+                let _ = self.pending.remove(&msg_id);
+                duties.push(response(msg_id, origin, Err(ErrorMessage::NoSuchData)));
+                continue;
+            }
+            let count = self.holders_at_once.min(read.remaining.len());
+            let next: BTreeSet<_> = read.remaining.drain(..count).collect();
+            read.queried.extend(next.iter().copied());
+            read.since = now;
+            let address = read.address;
+            duties.push(RelayedRead { address, msg_id }.msg(next));
+        }
+        Ok(duties)
+    }
+}
+
+pub(super) fn response(
+    msg_id: MessageId,
+    origin: EndUser,
+    result: std::result::Result<Blob, ErrorMessage>,
+) -> NodeDuty {
+    NodeDuty::Send(OutgoingMsg {
+        msg: Message::QueryResponse {
+            response: QueryResponse::GetBlob(result),
+            id: MessageId::in_response_to(&msg_id),
+            correlation_id: msg_id,
+            target_section_pk: None,
+        },
+        section_source: false, // strictly this is not correct, but we don't expect responses to a response..
+        dst: DstLocation::EndUser(origin),
+        aggregation: Aggregation::None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+
+    fn targets(duty: &NodeDuty) -> BTreeSet<XorName> {
+        match duty {
+            NodeDuty::SendNodeMsgToNodes { targets, .. } => targets.clone(),
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    fn client() -> EndUser {
+        EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()))
+    }
+
+    #[test]
+    fn first_verified_chunk_is_answered_once() -> Result<()> {
+        let chunk = Blob::Public(PublicBlob::new(b"chunk".to_vec()));
+        let holders: Vec<_> = (0..4).map(|_| XorName::random()).collect();
+        let mut reads = RelayedReads::new(2);
+        let msg_id = MessageId::new();
+        let duty = reads.start(
+            *chunk.address(),
+            None,
+            msg_id,
+            client(),
+            holders.clone(),
+            Instant::now(),
+        )?;
+        assert_eq!(targets(&duty), holders[..2].iter().copied().collect());
+
+        let read = RelayedRead {
+            address: *chunk.address(),
+            msg_id,
+        };
+        let forged = RelayedChunk {
+            read: read.clone(),
+            chunk: Blob::Public(PublicBlob::new(b"forged".to_vec())),
+        };
+        assert!(reads.answer(forged, holders[0]).is_none());
+        let answer = RelayedChunk { read, chunk };
+        // holders not queried yet are not taken
+        assert!(reads.answer(answer.clone(), holders[2]).is_none());
+        assert!(reads.answer(answer.clone(), holders[1]).is_some());
+        assert!(reads.answer(answer.clone(), holders[0]).is_none());
+
+        // and the answer is sent to the Elder as the chunk read, not as the read
+        let elder = XorName::random();
+        match answer.msg(elder) {
+            NodeDuty::SendNodeMsg {
+                msg:
+                    NodeMsg {
+                        body: NodeMsgBody::RelayedChunk(sent),
+                        ..
+                    },
+                dst: DstLocation::Node(dst),
+            } => {
+                assert_eq!(sent, answer);
+                assert_eq!(dst, elder);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+        Ok(())
+    }
+
+    // The bytes answered to the client, of the range of the chunk relayed by the holder.
+    fn read_range(
+        reads: &mut RelayedReads,
+        chunk: &Blob,
+        holder: XorName,
+        range: ChunkRange,
+    ) -> Result<std::result::Result<Vec<u8>, ErrorMessage>> {
+        let msg_id = MessageId::new();
+        let address = *chunk.address();
+        let holders = vec![holder];
+        let _ = reads.start(
+            address,
+            Some(range),
+            msg_id,
+            client(),
+            holders,
+            Instant::now(),
+        )?;
+        let answer = RelayedChunk {
+            read: RelayedRead { address, msg_id },
+            chunk: chunk.clone(),
+        };
+        match reads.answer(answer, holder) {
+            Some(NodeDuty::Send(OutgoingMsg {
+                msg:
+                    Message::QueryResponse {
+                        response: QueryResponse::GetBlob(result),
+                        ..
+                    },
+                ..
+            })) => Ok(result.map(|read| read.value().clone())),
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    #[test]
+    fn ranged_reads_are_answered_with_the_bytes_of_the_range() -> Result<()> {
+        let chunk = Blob::Public(PublicBlob::new(b"a chunk".to_vec()));
+        let holder = XorName::random();
+        let mut reads = RelayedReads::new(1);
+        let range = |offset, len| ChunkRange { offset, len };
+        // the whole chunk is verified, before only the range is answered
+        let read = read_range(&mut reads, &chunk, holder, range(2, 3))?;
+        assert_eq!(read, Ok(b"chu".to_vec()));
+        let read = read_range(&mut reads, &chunk, holder, range(2, 50))?;
+        assert_eq!(read, Ok(b"chunk".to_vec()));
+        let read = read_range(&mut reads, &chunk, holder, range(8, 1))?;
+        assert_eq!(read, Err(ErrorMessage::InvalidOperation));
+        Ok(())
+    }
+
+    #[test]
+    fn reads_fall_back_to_the_next_holders() -> Result<()> {
+        let chunk = Blob::Public(PublicBlob::new(b"chunk".to_vec()));
+        let holders: Vec<_> = (0..3).map(|_| XorName::random()).collect();
+        let mut reads = RelayedReads::new(2);
+        let now = Instant::now();
+        let _ = reads.start(
+            *chunk.address(),
+            None,
+            MessageId::new(),
+            client(),
+            holders.clone(),
+            now,
+        )?;
+        assert!(reads.retry(now)?.is_empty());
+
+        let later = now + RELAYED_READ_TIMEOUT;
+        let duties = reads.retry(later)?;
+        assert_eq!(duties.len(), 1);
+        assert_eq!(targets(&duties[0]), vec![holders[2]].into_iter().collect());
+
+        // and is failed once all holders were queried
+        let duties = reads.retry(later + RELAYED_READ_TIMEOUT)?;
+        assert!(matches!(
+            duties.as_slice(),
+            [NodeDuty::Send(OutgoingMsg {
+                msg: Message::QueryResponse {
+                    response: QueryResponse::GetBlob(Err(ErrorMessage::NoSuchData)),
+                    ..
+                },
+                ..
+            })]
+        ));
+        assert!(reads.pending.is_empty());
+        Ok(())
+    }
+}
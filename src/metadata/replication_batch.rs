@@ -0,0 +1,147 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use sn_data_types::BlobAddress;
+use sn_messaging::MessageId;
+use std::collections::{BTreeMap, BTreeSet};
+use xor_name::XorName;
+
+// The max number of chunks a single batch lists, to stay well within the max msg size.
+pub(super) const MAX_REPLICATION_BATCH: usize = 1_000;
+
+/// The chunks a new holder is to copy from their current holders, sent in one msg
+/// rather than one per chunk, so that churn of holders of many chunks is cheap.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationBatch {
+    /// Holder to copy the chunks.
+    pub new_holder: XorName,
+    /// The chunks, with their current holders.
+    pub chunks: BTreeMap<BlobAddress, BTreeSet<XorName>>,
+}
+
+impl ReplicationBatch {
+    /// Groups the chunks by new holder, into batches of at most `MAX_REPLICATION_BATCH`
+    /// chunks. Chunks are given with their new holders, and current holders.
+    pub(super) fn group(
+        chunks: Vec<(BlobAddress, BTreeSet<XorName>, BTreeSet<XorName>)>,
+    ) -> Vec<Self> {
+        let mut by_holder: BTreeMap<XorName, BTreeMap<_, _>> = BTreeMap::new();
+        for (address, new_holders, current_holders) in chunks {
+            for new_holder in new_holders {
+                let _ = by_holder
+                    .entry(new_holder)
+                    .or_default()
+                    .insert(address, current_holders.clone());
+            }
+        }
+        let mut batches = vec![];
+        for (new_holder, chunks) in by_holder {
+            let mut batch = Self {
+                new_holder,
+                chunks: BTreeMap::new(),
+            };
+            for (address, current_holders) in chunks {
+                if batch.chunks.len() == MAX_REPLICATION_BATCH {
+                    let chunks = std::mem::take(&mut batch.chunks);
+                    batches.push(Self { new_holder, chunks });
+                }
+                let _ = batch.chunks.insert(address, current_holders);
+            }
+            batches.push(batch);
+        }
+        batches
+    }
+
+    /// The msg telling the new holder to copy the chunks. Its id is derived from the
+    /// batch, so that the msgs of all Elders are aggregated at the new holder.
+    pub(super) fn msg(&self) -> Result<NodeDuty> {
+        Ok(NodeDuty::SendNodeMsgToNodes {
+            targets: vec![self.new_holder].into_iter().collect(),
+            msg: NodeMsg::derived(NodeMsgBody::ReplicationBatch(self.clone()))?,
+        })
+    }
+
+    /// The replication of each chunk, under the id of the msg for it alone,
+    /// as the copies received are checked by it.
+    pub(crate) fn into_duties(self) -> Vec<NodeDuty> {
+        let new_holder = self.new_holder;
+        self.chunks
+            .into_iter()
+            .map(|(address, current_holders)| NodeDuty::ReplicateChunk {
+                address,
+                current_holders,
+                id: MessageId::combine(vec![*address.name(), new_holder]),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunks_are_batched_per_new_holder() -> Result<()> {
+        let (first, second) = (XorName::random(), XorName::random());
+        let current_holders: BTreeSet<_> = vec![XorName::random()].into_iter().collect();
+        let chunks: Vec<_> = (0..MAX_REPLICATION_BATCH + 1)
+            .map(|i| {
+                let new_holders = if i == 0 {
+                    vec![first, second]
+                } else {
+                    vec![first]
+                };
+                (
+                    BlobAddress::Public(XorName::random()),
+                    new_holders.into_iter().collect(),
+                    current_holders.clone(),
+                )
+            })
+            .collect();
+
+        let batches = ReplicationBatch::group(chunks.clone());
+        let sizes: Vec<_> = batches
+            .iter()
+            .map(|batch| (batch.new_holder, batch.chunks.len()))
+            .collect();
+        let mut expected = vec![(first, MAX_REPLICATION_BATCH), (first, 1), (second, 1)];
+        if second < first {
+            expected.rotate_right(1);
+        }
+        assert_eq!(sizes, expected);
+
+        let batch = batches
+            .into_iter()
+            .find(|batch| batch.new_holder == second)
+            .expect("batched");
+        let msg = match batch.msg()? {
+            NodeDuty::SendNodeMsgToNodes { msg, .. } => msg,
+            duty => panic!("Unexpected duty: {:?}", duty),
+        };
+        assert!(msg.is_derived());
+        let unwrapped = match msg.body {
+            NodeMsgBody::ReplicationBatch(unwrapped) => unwrapped,
+            body => panic!("Unexpected msg: {:?}", body),
+        };
+        assert_eq!(unwrapped, batch);
+        match unwrapped.into_duties().as_slice() {
+            [NodeDuty::ReplicateChunk { address, id, .. }] => {
+                assert_eq!(*address, chunks[0].0);
+                assert_eq!(*id, MessageId::combine(vec![*address.name(), second]));
+            }
+            duties => panic!("Unexpected duties: {:?}", duties),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::range_export::{sort_key, MetadataRecord, RecordKey, MAX_PAGE_SIZE};
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    utils, Error, Result,
+};
+use bls::PublicKeySet;
+use serde::{Deserialize, Serialize};
+use sn_data_types::SignatureShare;
+use sn_messaging::MessageId;
+use std::collections::{BTreeMap, BTreeSet};
+use xor_name::{Prefix, XorName};
+
+// The dumps accumulating shares at most at a time, e.g. those of Elders dumping in turn.
+const MAX_PENDING_DUMPS: usize = 100;
+
+/// A part of the metadata records of a section, dumped for the section to be recovered from,
+/// e.g. after losing its Elders. Each Elder dumping signs the parts with its share of the section
+/// key, and a part is imported once signed by enough of them, so that no single Elder can have
+/// other records imported. The parts are the same at all Elders holding the same records.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetadataDump {
+    /// The key set of the section dumping, its shares verified against.
+    pub key_set: PublicKeySet,
+    /// The prefix of the section dumping.
+    pub prefix: Prefix,
+    /// The index of the part, of the number of them.
+    pub part: (usize, usize),
+    /// The records, ordered by their name, and then their key.
+    pub records: Vec<(RecordKey, MetadataRecord)>,
+}
+
+impl MetadataDump {
+    /// The parts dumping the records, each filled up to `MAX_PAGE_SIZE` bytes of records.
+    pub(crate) fn parts(
+        key_set: PublicKeySet,
+        prefix: Prefix,
+        mut records: Vec<(RecordKey, MetadataRecord)>,
+    ) -> Result<Vec<Self>> {
+        records.sort_by_key(|(key, _)| sort_key(key));
+        let mut batches = vec![vec![]];
+        let mut batch_size = 0;
+        for (key, record) in records {
+            if batch_size >= MAX_PAGE_SIZE {
+                batches.push(vec![]);
+                batch_size = 0;
+            }
+            batch_size += utils::serialise(&record)?.len();
+            if let Some(batch) = batches.last_mut() {
+                batch.push((key, record));
+            }
+        }
+        let count = batches.len();
+        Ok(batches
+            .into_iter()
+            .enumerate()
+            .map(|(index, records)| Self {
+                key_set: key_set.clone(),
+                prefix,
+                part: (index, count),
+                records,
+            })
+            .collect())
+    }
+
+    /// Verifies that each record is of the prefix dumped, and is of its key.
+    pub(crate) fn validate(&self) -> Result<()> {
+        for (key, record) in &self.records {
+            if !self.prefix.matches(key.name()) {
+                return Err(Error::InvalidMetadataDump(format!(
+                    "record {:?} not of the prefix {:?}",
+                    key, self.prefix
+                )));
+            }
+            if &record.key() != key {
+                return Err(Error::InvalidMetadataDump(format!(
+                    "record {:?} dumped as {:?}",
+                    record.key(),
+                    key
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> Result<MessageId> {
+        Ok(MessageId::from_content(self)?)
+    }
+}
+
+/// A part of a dump, signed by an Elder of the section dumping.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignedMetadataDump {
+    /// The part.
+    pub dump: MetadataDump,
+    /// The share of the Elder, of the signature of the section over the part.
+    pub share: SignatureShare,
+}
+
+impl SignedMetadataDump {
+    /// The msg sending the part to the nodes.
+    pub(crate) fn msg(&self, targets: BTreeSet<XorName>) -> NodeDuty {
+        NodeDuty::SendNodeMsgToNodes {
+            targets,
+            msg: NodeMsg::new(NodeMsgBody::SignedMetadataDump(self.clone())),
+        }
+    }
+
+    fn verify_share(&self) -> Result<bool> {
+        Ok(self
+            .dump
+            .key_set
+            .public_key_share(self.share.index)
+            .verify(&self.share.share, utils::serialise(&self.dump)?))
+    }
+}
+
+/// The shares received of the parts of dumps, until enough of them are to import a part.
+#[derive(Default)]
+pub struct MetadataImports {
+    pending: BTreeMap<MessageId, (MetadataDump, BTreeMap<usize, bls::SignatureShare>)>,
+}
+
+impl MetadataImports {
+    /// Adds the share of the part, once verified, returning the part once signed by the section,
+    /// i.e. with more shares than the threshold of its key set, and dropping it from those pending.
+    pub(crate) fn add(&mut self, signed: SignedMetadataDump) -> Result<Option<MetadataDump>> {
+        if !signed.verify_share()? {
+            return Err(Error::InvalidMetadataDump(format!(
+                "share {} does not sign the dump",
+                signed.share.index
+            )));
+        }
+        let id = signed.dump.id()?;
+        if !self.pending.contains_key(&id) && self.pending.len() >= MAX_PENDING_DUMPS {
+            let _ = self.pending.pop_first();
+        }
+        let SignedMetadataDump { dump, share } = signed;
+        let (dump, shares) = self
+            .pending
+            .entry(id)
+            .or_insert_with(|| (dump, BTreeMap::new()));
+        let _ = shares.insert(share.index, share.share);
+        if shares.len() <= dump.key_set.threshold() {
+            return Ok(None);
+        }
+        let signature = dump
+            .key_set
+            .combine_signatures(shares.iter().map(|(index, share)| (*index, share)))
+            .map_err(|e| Error::InvalidMetadataDump(e.to_string()))?;
+        if !dump
+            .key_set
+            .public_key()
+            .verify(&signature, utils::serialise(dump)?)
+        {
+            return Err(Error::InvalidMetadataDump(
+                "shares do not combine into a signature of the section".to_string(),
+            ));
+        }
+        Ok(self.pending.remove(&id).map(|(dump, _)| dump))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKeySet;
+    use sn_data_types::BlobAddress;
+
+    fn signed(
+        secret_keys: &SecretKeySet,
+        index: usize,
+        dump: &MetadataDump,
+    ) -> Result<SignedMetadataDump> {
+        let share = secret_keys
+            .secret_key_share(index)
+            .sign(utils::serialise(dump)?);
+        Ok(SignedMetadataDump {
+            dump: dump.clone(),
+            share: SignatureShare { index, share },
+        })
+    }
+
+    fn chunk_record(name: XorName) -> (RecordKey, MetadataRecord) {
+        let address = BlobAddress::Public(name);
+        let record = MetadataRecord::Chunk {
+            address,
+            holders: vec![XorName::random()].into_iter().collect(),
+            owner: None,
+            copies: None,
+        };
+        (RecordKey::Chunk(address), record)
+    }
+
+    #[test]
+    fn part_is_imported_once_signed_by_enough_elders() -> Result<()> {
+        let secret_keys = SecretKeySet::random(2, &mut rand::thread_rng());
+        let records = vec![
+            chunk_record(XorName::random()),
+            chunk_record(XorName::random()),
+        ];
+        let mut parts = MetadataDump::parts(secret_keys.public_keys(), Prefix::default(), records)?;
+        assert_eq!(parts.len(), 1);
+        let dump = parts.remove(0);
+        dump.validate()?;
+        assert!(dump.records[0].0.name() < dump.records[1].0.name());
+
+        let mut imports = MetadataImports::default();
+        // a share not of the section is refused
+        let forged = signed(&SecretKeySet::random(2, &mut rand::thread_rng()), 0, &dump)?;
+        assert!(imports.add(forged).is_err());
+        // and shares of the same Elder count as one
+        assert_eq!(imports.add(signed(&secret_keys, 0, &dump)?)?, None);
+        assert_eq!(imports.add(signed(&secret_keys, 0, &dump)?)?, None);
+        assert_eq!(imports.add(signed(&secret_keys, 1, &dump)?)?, None);
+        assert_eq!(
+            imports.add(signed(&secret_keys, 2, &dump)?)?,
+            Some(dump.clone())
+        );
+        // and it is imported once
+        assert_eq!(imports.add(signed(&secret_keys, 3, &dump)?)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn records_must_be_of_the_prefix_and_their_key() -> Result<()> {
+        let key_set = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        let prefix = Prefix::default().pushed(true);
+        let name = XorName([0xff; 32]);
+        let mut dump = MetadataDump::parts(key_set, prefix, vec![chunk_record(name)])?.remove(0);
+        dump.validate()?;
+
+        dump.records[0].0 = RecordKey::ShardedChunk(BlobAddress::Public(name));
+        assert!(dump.validate().is_err());
+
+        dump.records = vec![chunk_record(XorName([0x01; 32]))];
+        assert!(dump.validate().is_err());
+        Ok(())
+    }
+}
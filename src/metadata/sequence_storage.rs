@@ -6,9 +6,14 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::{
+    range_export::{MetadataRecord, RecordKey},
+    read_access, write_validation,
+};
 use crate::{
-    chunk_store::{SequenceChunkStore, UsedSpace},
+    chunk_store::{ChunkCompression, SequenceChunkStore, UsedSpace},
     error::convert_to_error_message,
+    network::NetworkClock,
     node_ops::{NodeDuty, OutgoingMsg},
     Error, Network, Result,
 };
@@ -25,6 +30,7 @@ use sn_messaging::{
 use std::{
     fmt::{self, Display, Formatter},
     path::Path,
+    sync::Arc,
 };
 
 /// Operations over the data type Sequence.
@@ -33,11 +39,24 @@ pub(super) struct SequenceStorage {
 }
 
 impl SequenceStorage {
-    pub(super) async fn new(path: &Path, used_space: UsedSpace) -> Result<Self> {
-        let chunks = SequenceChunkStore::new(path, used_space).await?;
+    pub(super) async fn new(
+        path: &Path,
+        used_space: UsedSpace,
+        secure_delete: bool,
+    ) -> Result<Self> {
+        let mut chunks = SequenceChunkStore::new(path, used_space).await?;
+        chunks.set_secure_delete(secure_delete);
         Ok(Self { chunks })
     }
 
+    pub(super) fn set_compression(&mut self, compression: Option<ChunkCompression>) {
+        self.chunks.set_compression(compression);
+    }
+
+    pub(super) fn set_clock(&mut self, clock: Arc<NetworkClock>) {
+        self.chunks.set_clock(clock);
+    }
+
     pub(super) async fn read(
         &self,
         read: &SequenceRead,
@@ -86,7 +105,10 @@ impl SequenceStorage {
         let result = if self.chunks.has(data.address()) {
             Err(Error::DataExists)
         } else {
-            self.chunks.put(&data).await
+            match write_validation::validate_new_sequence(data, origin) {
+                Ok(()) => self.chunks.put(&data).await,
+                Err(error) => Err(error),
+            }
         };
         self.ok_or_error(result, msg_id, origin).await
     }
@@ -121,7 +143,10 @@ impl SequenceStorage {
         origin: EndUser,
     ) -> Result<Sequence> {
         let data = self.chunks.get(&address)?;
-        data.check_permission(action, Some(*origin.id()))?;
+        match action {
+            SequenceAction::Read => read_access::check_sequence_read(&data, origin.id())?,
+            action => data.check_permission(action, Some(*origin.id()))?,
+        }
         Ok(data)
     }
 
@@ -351,17 +376,21 @@ impl SequenceStorage {
     ) -> Result<NodeDuty> {
         let address = write_op.address;
         info!("Editing Sequence chunk");
-        let result = self
-            .edit_chunk(
-                address,
-                SequenceAction::Append,
-                origin,
-                move |mut sequence| {
-                    sequence.apply_op(write_op)?;
-                    Ok(sequence)
-                },
-            )
-            .await;
+        let result = match write_validation::validate_sequence_op(&write_op, origin) {
+            Ok(()) => {
+                self.edit_chunk(
+                    address,
+                    SequenceAction::Append,
+                    origin,
+                    move |mut sequence| {
+                        sequence.apply_op(write_op)?;
+                        Ok(sequence)
+                    },
+                )
+                .await
+            }
+            Err(error) => Err(error),
+        };
         if result.is_ok() {
             info!("Editing Sequence chunk SUCCESSFUL!");
         } else {
@@ -412,6 +441,30 @@ impl SequenceStorage {
             aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
         }))
     }
+
+    /// The keys of all Sequences held.
+    pub(super) fn record_keys(&self) -> Vec<RecordKey> {
+        self.chunks
+            .keys()
+            .into_iter()
+            .map(RecordKey::Sequence)
+            .collect()
+    }
+
+    /// The Sequence as a metadata record, if held.
+    pub(super) fn record(&self, address: &SequenceAddress) -> Option<MetadataRecord> {
+        self.chunks.get(address).ok().map(MetadataRecord::Sequence)
+    }
+
+    /// Takes over the Sequence, as held by another Elder.
+    pub(super) async fn take_over(&mut self, sequence: &Sequence) -> Result<()> {
+        self.chunks.put(sequence).await
+    }
+
+    /// Removes the Sequence, as removed at another Elder.
+    pub(super) async fn remove_record(&mut self, address: &SequenceAddress) -> Result<()> {
+        self.chunks.delete(address).await
+    }
 }
 
 impl Display for SequenceStorage {
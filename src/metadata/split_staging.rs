@@ -0,0 +1,190 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::range_export::{
+    sort_key, MetadataPage, MetadataRangeQuery, MetadataRecord, RecordKey, MAX_PAGE_SIZE,
+};
+use crate::{utils, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use xor_name::Prefix;
+
+const STAGING_DIR: &str = "split_staging";
+
+/// The metadata records within the prefix of our sibling, staged at split
+/// in pages on disk, for its Elders to take over in bulk. The pages are cut
+/// once, at staging, and are then read back as they are, at export.
+pub(super) struct StagedRange {
+    prefix: Prefix,
+    dir: PathBuf,
+    // the keys of the last record of each page, in order
+    last_keys: Vec<RecordKey>,
+    // the records of the page being filled
+    filling: Vec<(RecordKey, MetadataRecord)>,
+    filling_size: usize,
+}
+
+impl StagedRange {
+    /// A new, empty stage for the records within the prefix, under the root dir.
+    /// Any records staged before are discarded.
+    pub(super) fn new(root_dir: &Path, prefix: Prefix) -> Result<Self> {
+        let dir = root_dir.join(STAGING_DIR);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            prefix,
+            dir,
+            last_keys: vec![],
+            filling: vec![],
+            filling_size: 0,
+        })
+    }
+
+    pub(super) fn prefix(&self) -> &Prefix {
+        &self.prefix
+    }
+
+    /// Stages the record, which is to follow the ones staged before in key order.
+    pub(super) fn push(&mut self, key: RecordKey, record: MetadataRecord) -> Result<()> {
+        self.filling_size += utils::serialise(&record)?.len();
+        self.filling.push((key, record));
+        if self.filling_size >= MAX_PAGE_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes out the page being filled, once all records are staged.
+    pub(super) fn finish(&mut self) -> Result<()> {
+        if !self.filling.is_empty() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Number of pages staged.
+    pub(super) fn pages(&self) -> usize {
+        self.last_keys.len()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let last = match self.filling.last() {
+            Some((key, _)) => *key,
+            None => return Ok(()),
+        };
+        let path = self.page_path(self.last_keys.len());
+        fs::write(path, utils::serialise(&self.filling)?)?;
+        self.last_keys.push(last);
+        self.filling.clear();
+        self.filling_size = 0;
+        Ok(())
+    }
+
+    /// The staged page following the record of the query, if any.
+    pub(super) fn page(&self, query: &MetadataRangeQuery) -> Result<MetadataPage> {
+        let index = match &query.after {
+            Some(after) => self
+                .last_keys
+                .partition_point(|last| sort_key(last) <= sort_key(after)),
+            None => 0,
+        };
+        if index >= self.last_keys.len() {
+            return Ok(MetadataPage {
+                records: vec![],
+                next: None,
+            });
+        }
+        let bytes = fs::read(self.page_path(index))?;
+        let records: Vec<(RecordKey, MetadataRecord)> = utils::deserialise(&bytes)?;
+        // a cursor within a page, e.g. of a query started before staging,
+        // continues after it within the page
+        let records = match &query.after {
+            Some(after) => records
+                .into_iter()
+                .filter(|(key, _)| sort_key(key) > sort_key(after))
+                .collect(),
+            None => records,
+        };
+        let next = if index + 1 < self.last_keys.len() {
+            Some(MetadataRangeQuery {
+                prefix: self.prefix,
+                after: Some(self.last_keys[index]),
+            })
+        } else {
+            None
+        };
+        Ok(MetadataPage { records, next })
+    }
+
+    fn page_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("page_{}", index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+    use sn_data_types::BlobAddress;
+    use std::collections::BTreeSet;
+    use tempdir::TempDir;
+    use xor_name::XorName;
+
+    #[test]
+    fn staged_pages_are_read_back_in_order() -> Result<()> {
+        let root =
+            TempDir::new("staging").map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let prefix = Prefix::default().pushed(true);
+        let mut staged = StagedRange::new(root.path(), prefix)?;
+        let mut keys: Vec<_> = (0..3)
+            .map(|_| {
+                RecordKey::Chunk(BlobAddress::Public(
+                    prefix.substituted_in(XorName::random()),
+                ))
+            })
+            .collect();
+        keys.sort_by_key(sort_key);
+        for key in &keys {
+            let address = match key {
+                RecordKey::Chunk(address) => *address,
+                _ => unreachable!(),
+            };
+            let record = MetadataRecord::Chunk {
+                address,
+                holders: BTreeSet::new(),
+                owner: None,
+                copies: None,
+            };
+            staged.push(*key, record)?;
+            // a page per record
+            staged.flush()?;
+        }
+        staged.finish()?;
+        assert_eq!(staged.pages(), 3);
+
+        let mut query = MetadataRangeQuery {
+            prefix,
+            after: None,
+        };
+        let mut exported = vec![];
+        loop {
+            let page = staged.page(&query)?;
+            exported.extend(page.records.into_iter().map(|(key, _)| key));
+            match page.next {
+                Some(next) => query = next,
+                None => break,
+            }
+        }
+        assert_eq!(exported, keys);
+        Ok(())
+    }
+}
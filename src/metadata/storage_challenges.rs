@@ -0,0 +1,290 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    Result,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Blob, BlobAddress};
+use sn_messaging::{DstLocation, MessageId};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+// The bytes of a chunk a challenge is over, at most.
+const CHALLENGE_RANGE: u64 = 4 * 1024;
+// The chunks challenged per round.
+pub(super) const CHALLENGES_PER_ROUND: usize = 10;
+// The time holders have to answer a challenge.
+const CHALLENGE_TIMEOUT: Duration = Duration::from_secs(60);
+// The challenges pending at most, beyond which rounds are skipped.
+const MAX_PENDING_CHALLENGES: usize = 1_000;
+
+/// A challenge for a holder to prove it holds a chunk, by the hash of a range of its
+/// bytes, salted with a nonce, so that it can only be answered from the chunk itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageChallenge {
+    /// The chunk challenged.
+    pub address: BlobAddress,
+    /// Salt of the hash, so that answers cannot be kept in place of the chunk.
+    pub nonce: u64,
+    /// Start of the range, taken modulo the size of the chunk, which Elders do not know.
+    pub offset: u64,
+    /// Bytes of the range at most.
+    pub len: u64,
+}
+
+impl StorageChallenge {
+    fn random(address: BlobAddress) -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            address,
+            nonce: rng.gen(),
+            offset: rng.gen(),
+            len: CHALLENGE_RANGE,
+        }
+    }
+
+    /// The hash of the range of bytes of the chunk challenged.
+    pub(crate) fn answer(&self, blob: &Blob) -> XorName {
+        let value = blob.value();
+        let range = if value.is_empty() {
+            &value[..]
+        } else {
+            let start = (self.offset % value.len() as u64) as usize;
+            let end = start.saturating_add(self.len as usize).min(value.len());
+            &value[start..end]
+        };
+        XorName::from_content(&[&self.nonce.to_le_bytes(), range])
+    }
+
+    /// The msg challenging the holders. Its id is derived from the challenge, so that
+    /// the holders of a chunk are given the one challenge, as Elders do not tell it apart.
+    fn msg(&self) -> Result<NodeMsg> {
+        NodeMsg::derived(NodeMsgBody::StorageChallenge(self.clone()))
+    }
+}
+
+/// The answer of a holder to a storage challenge.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageProof {
+    /// Id of the msg of the challenge answered.
+    pub challenge: MessageId,
+    /// Hash of the range of bytes challenged.
+    pub hash: XorName,
+}
+
+impl StorageProof {
+    /// The msg answering the Elder challenging us.
+    pub(crate) fn msg(&self, elder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::StorageProof(self.clone())),
+            dst: DstLocation::Node(elder),
+        }
+    }
+}
+
+// A challenge issued by us, pending the answers of the holders.
+struct Issued {
+    address: BlobAddress,
+    at: Instant,
+    holders: BTreeSet<XorName>,
+    answers: BTreeMap<XorName, XorName>,
+}
+
+impl Issued {
+    // The holders not answering, or answering another hash than most holders did.
+    // Without a majority, only the holders not answering are known to have failed.
+    fn failed(self) -> Vec<(BlobAddress, XorName)> {
+        let mut counts: BTreeMap<XorName, usize> = BTreeMap::new();
+        for hash in self.answers.values() {
+            *counts.entry(*hash).or_default() += 1;
+        }
+        let majority = counts
+            .into_iter()
+            .find(|(_, count)| count * 2 > self.holders.len())
+            .map(|(hash, _)| hash);
+        let address = self.address;
+        let answers = self.answers;
+        self.holders
+            .into_iter()
+            .filter(|holder| match answers.get(holder) {
+                None => true,
+                Some(hash) => majority.is_some_and(|majority| majority != *hash),
+            })
+            .map(|holder| (address, holder))
+            .collect()
+    }
+}
+
+/// The storage challenges issued by us, as an Elder, to the holders of random chunks.
+/// All holders of a chunk are given the same challenge, as Elders do not hold the
+/// chunks to check the answers against, and are expected to answer the same.
+#[derive(Default)]
+pub(super) struct StorageChallenges {
+    issued: BTreeMap<MessageId, Issued>,
+}
+
+impl StorageChallenges {
+    /// Concludes the challenges not answered by all holders in time,
+    /// returning the holders failed, with the chunk they failed it for.
+    pub(super) fn expire(&mut self, now: Instant) -> Vec<(BlobAddress, XorName)> {
+        let expired: Vec<_> = self
+            .issued
+            .iter()
+            .filter(|(_, issued)| now.saturating_duration_since(issued.at) >= CHALLENGE_TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.issued.remove(&id))
+            .flat_map(Issued::failed)
+            .collect()
+    }
+
+    /// Challenges the holders of the chunks, unless too many challenges are pending.
+    pub(super) fn issue(
+        &mut self,
+        chunks: Vec<(BlobAddress, BTreeSet<XorName>)>,
+        now: Instant,
+    ) -> Result<Vec<NodeDuty>> {
+        let mut duties = vec![];
+        for (address, holders) in chunks {
+            if self.issued.len() >= MAX_PENDING_CHALLENGES {
+                break;
+            }
+            let msg = StorageChallenge::random(address).msg()?;
+            let _ = self.issued.insert(
+                msg.id,
+                Issued {
+                    address,
+                    at: now,
+                    holders: holders.clone(),
+                    answers: BTreeMap::new(),
+                },
+            );
+            duties.push(NodeDuty::SendNodeMsgToNodes {
+                targets: holders,
+                msg,
+            });
+        }
+        Ok(duties)
+    }
+
+    /// Records the answer of the holder. Once all holders have answered, the challenge
+    /// is concluded, returning the holders failed, with the chunk they failed it for.
+    pub(super) fn answered(
+        &mut self,
+        proof: StorageProof,
+        holder: XorName,
+    ) -> Vec<(BlobAddress, XorName)> {
+        let issued = match self.issued.get_mut(&proof.challenge) {
+            Some(issued) if issued.holders.contains(&holder) => issued,
+            _ => return vec![],
+        };
+        let _ = issued.answers.insert(holder, proof.hash);
+        if issued.answers.len() < issued.holders.len() {
+            return vec![];
+        }
+        self.issued
+            .remove(&proof.challenge)
+            .map(Issued::failed)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_data_types::PublicBlob;
+
+    // The challenge of the msg, with the id of the msg.
+    fn challenge_of(duty: &NodeDuty) -> (MessageId, StorageChallenge) {
+        match duty {
+            NodeDuty::SendNodeMsgToNodes {
+                msg:
+                    NodeMsg {
+                        id,
+                        body: NodeMsgBody::StorageChallenge(challenge),
+                    },
+                ..
+            } => (*id, challenge.clone()),
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+    }
+
+    #[test]
+    fn holders_with_another_answer_than_most_fail() -> Result<()> {
+        let blob = Blob::Public(PublicBlob::new(b"chunk".repeat(2000)));
+        let address = *blob.address();
+        let holders: Vec<_> = (0..4).map(|_| XorName::random()).collect();
+        let mut challenges = StorageChallenges::default();
+        let now = Instant::now();
+
+        let duties = challenges.issue(vec![(address, holders.iter().copied().collect())], now)?;
+        let (id, challenge) = challenge_of(&duties[0]);
+        assert_eq!(challenge.address, address);
+
+        let proof = |hash| StorageProof {
+            challenge: id,
+            hash,
+        };
+        let good = challenge.answer(&blob);
+        let bad = challenge.answer(&Blob::Public(PublicBlob::new(b"other".repeat(2000))));
+        assert_ne!(good, bad);
+        assert!(challenges.answered(proof(good), holders[0]).is_empty());
+        assert!(challenges.answered(proof(good), holders[1]).is_empty());
+        assert!(challenges
+            .answered(proof(good), XorName::random())
+            .is_empty());
+        assert!(challenges.answered(proof(bad), holders[2]).is_empty());
+        assert_eq!(
+            challenges.answered(proof(good), holders[3]),
+            vec![(address, holders[2])]
+        );
+        assert!(challenges.expire(now + CHALLENGE_TIMEOUT).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn holders_not_answering_in_time_fail() -> Result<()> {
+        let blob = Blob::Public(PublicBlob::new(b"chunk".to_vec()));
+        let address = *blob.address();
+        let (answering, silent) = (XorName::random(), XorName::random());
+        let mut challenges = StorageChallenges::default();
+        let now = Instant::now();
+
+        let duties = challenges.issue(
+            vec![(address, vec![answering, silent].into_iter().collect())],
+            now,
+        )?;
+        let (id, challenge) = challenge_of(&duties[0]);
+        let proof = StorageProof {
+            challenge: id,
+            hash: challenge.answer(&blob),
+        };
+        // answers to challenges not issued are ignored
+        let unknown = StorageProof {
+            challenge: MessageId::new(),
+            hash: proof.hash,
+        };
+        assert!(challenges.answered(unknown, silent).is_empty());
+        assert!(challenges.answered(proof, answering).is_empty());
+        assert!(challenges.expire(now).is_empty());
+        assert_eq!(
+            challenges.expire(now + CHALLENGE_TIMEOUT),
+            vec![(address, silent)]
+        );
+        Ok(())
+    }
+}
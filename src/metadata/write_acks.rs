@@ -0,0 +1,441 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::{NodeDuty, OutgoingMsg},
+    utils, Result,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_messaging::{
+    client::{Message, QueryResponse},
+    Aggregation, DstLocation, EndUser, MessageId,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// Writes not confirmed by enough Elders within this time are given up on.
+const WRITE_ACK_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+/// The msg of the credit paying for a write starting with this, and the
+/// level, selects when the write is acknowledged, e.g. `ack-level:durable`.
+pub const ACK_LEVEL: &str = "ack-level:";
+
+/// The stage of the write pipeline at which a client write is acknowledged,
+/// as selected by the client, trading latency for confirmation of durability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AckLevel {
+    /// Once the payment for the write has been validated.
+    Accepted,
+    /// Once the min number of Elders of the section have applied the write, or a
+    /// majority of its Elders where writes are not acknowledged otherwise.
+    Durable,
+    /// Once all Elders of the section have applied the write.
+    Replicated,
+}
+
+impl AckLevel {
+    /// The level selected by the msg of the credit paying for a write, if any.
+    pub fn of(msg: &str) -> Option<Self> {
+        match msg.strip_prefix(ACK_LEVEL)?.split_whitespace().next()? {
+            "accepted" => Some(Self::Accepted),
+            "durable" => Some(Self::Durable),
+            "replicated" => Some(Self::Replicated),
+            _ => None,
+        }
+    }
+}
+
+/// The level a client selected for a write it paid for, sent by the Elders taking the
+/// payment to the Elders of the section applying the write, along with the write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteAckLevel {
+    /// Id of the msg of the write.
+    pub id: MessageId,
+    /// The client of the write.
+    pub origin: EndUser,
+    /// The level selected.
+    pub level: AckLevel,
+}
+
+impl WriteAckLevel {
+    /// The msg telling the Elders of the section at the address written to. Its id is
+    /// derived from the level, so that the msgs of all Elders telling it are deduplicated.
+    pub(crate) fn msg(&self, dst_address: XorName) -> Result<NodeDuty> {
+        Ok(NodeDuty::SendNodeMsg {
+            msg: NodeMsg::derived(NodeMsgBody::WriteAckLevel(*self))?,
+            dst: DstLocation::Section(dst_address),
+        })
+    }
+}
+
+/// A client write applied by the Elder sending it, to the other Elders of its section,
+/// each acknowledging the write to the client once enough Elders have applied it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteAck {
+    /// Id of the msg of the write.
+    pub id: MessageId,
+    /// The client of the write.
+    pub origin: EndUser,
+}
+
+struct PendingWrite {
+    origin: EndUser,
+    elders: BTreeSet<XorName>,
+    started: Instant,
+    // the level selected by the client, and the Elders to confirm it, if selected
+    level: Option<(AckLevel, usize)>,
+}
+
+/// Tracks the Elders having applied a client write, so that the client
+/// is only acknowledged once a minimum number of them have confirmed it.
+pub(super) struct WriteAcks {
+    min_acks: usize,
+    pending: BTreeMap<MessageId, PendingWrite>,
+}
+
+impl WriteAcks {
+    /// With a min of 0 acks, writes are not acknowledged.
+    pub(super) fn new(min_acks: u8) -> Self {
+        Self {
+            min_acks: min_acks as usize,
+            pending: Default::default(),
+        }
+    }
+
+    pub(super) fn is_enabled(&self) -> bool {
+        self.min_acks > 0
+    }
+
+    /// Sets the min number of Elders of our section to have applied a write.
+    pub(super) fn set_min_acks(&mut self, min_acks: u8) {
+        self.min_acks = min_acks as usize;
+    }
+
+    /// Records the level selected by the client for the write, paid for and about
+    /// to be applied, with the Elders of our section. At `AckLevel::Accepted`,
+    /// returns the msg acknowledging the write to the client right away.
+    pub(super) fn select(
+        &mut self,
+        msg_id: MessageId,
+        origin: EndUser,
+        level: AckLevel,
+        elder_count: usize,
+    ) -> Option<NodeDuty> {
+        self.expire();
+        let required = match level {
+            AckLevel::Accepted => 0,
+            AckLevel::Durable if self.is_enabled() => self.min_acks.min(elder_count).max(1),
+            AckLevel::Durable => elder_count / 2 + 1,
+            AckLevel::Replicated => elder_count.max(1),
+        };
+        let write = self.pending.entry(msg_id).or_insert_with(|| PendingWrite {
+            origin,
+            elders: Default::default(),
+            started: Instant::now(),
+            level: None,
+        });
+        write.level = Some((level, required));
+        if level == AckLevel::Accepted {
+            info!("Write {:?} accepted, acknowledging it", msg_id);
+            Some(ack(msg_id, write))
+        } else if write.elders.len() >= required {
+            // applied by enough Elders before the level was selected here
+            info!(
+                "Write {:?} applied by {} Elders, acknowledging it",
+                msg_id,
+                write.elders.len()
+            );
+            Some(ack(msg_id, write))
+        } else {
+            None
+        }
+    }
+
+    /// Records the write as applied by us. If writes are acknowledged, or the client
+    /// selected a level for it, returns the msg telling the other Elders given, along
+    /// with the msg acknowledging the write to the client, if we were the last required.
+    pub(super) fn applied(
+        &mut self,
+        msg_id: MessageId,
+        origin: EndUser,
+        our_name: XorName,
+        elders: &BTreeSet<XorName>,
+    ) -> Vec<NodeDuty> {
+        if !self.is_enabled() && !self.pending.contains_key(&msg_id) {
+            return vec![];
+        }
+        let mut duties: Vec<_> = self.record(msg_id, origin, our_name).into_iter().collect();
+        let targets: BTreeSet<_> = elders
+            .iter()
+            .filter(|elder| **elder != our_name)
+            .copied()
+            .collect();
+        if !targets.is_empty() {
+            duties.push(NodeDuty::SendNodeMsgToNodes {
+                targets,
+                msg: NodeMsg::new(NodeMsgBody::WriteAck(WriteAck { id: msg_id, origin })),
+            });
+        }
+        duties
+    }
+
+    /// Records the Elder as having applied the write. Once the number of Elders
+    /// required by the level selected, or else the min number of Elders, have,
+    /// returns the msg acknowledging the write to the client. The Elders applying
+    /// a write before it is applied, or its level selected, here are kept until then.
+    pub(super) fn record(
+        &mut self,
+        msg_id: MessageId,
+        origin: EndUser,
+        elder: XorName,
+    ) -> Option<NodeDuty> {
+        self.expire();
+        let write = self.pending.entry(msg_id).or_insert_with(|| PendingWrite {
+            origin,
+            elders: Default::default(),
+            started: Instant::now(),
+            level: None,
+        });
+        let required = write.required(self.min_acks);
+        // only counting the first time the min is reached
+        if !write.elders.insert(elder) || required == 0 || write.elders.len() != required {
+            return None;
+        }
+        info!(
+            "Write {:?} applied by {} Elders, acknowledging it",
+            msg_id, required
+        );
+        Some(ack(msg_id, write))
+    }
+
+    fn expire(&mut self) {
+        let min_acks = self.min_acks;
+        self.pending.retain(|msg_id, write| {
+            let keep = write.started.elapsed() < WRITE_ACK_TIMEOUT;
+            let required = write.required(min_acks);
+            if !keep && write.elders.len() < required {
+                warn!(
+                    "Write {:?} only confirmed by {} of {} Elders required",
+                    msg_id,
+                    write.elders.len(),
+                    required
+                );
+            }
+            keep
+        });
+    }
+}
+
+impl PendingWrite {
+    fn required(&self, min_acks: usize) -> usize {
+        self.level.map(|(_, required)| required).unwrap_or(min_acks)
+    }
+}
+
+fn ack(msg_id: MessageId, write: &PendingWrite) -> NodeDuty {
+    // the level acked at, if selected by the client
+    let level = match write.level {
+        Some((level, _)) => utils::serialise(&level)
+            .map(|bytes| bytes.to_vec())
+            .unwrap_or_default(),
+        None => vec![],
+    };
+    NodeDuty::Send(OutgoingMsg {
+        // there is no dedicated ack msg yet, the ack
+        // is the number of Elders confirming the write
+        msg: Message::QueryResponse {
+            response: QueryResponse::GetSequenceLastEntry(Ok((write.elders.len() as u64, level))),
+            id: MessageId::in_response_to(&msg_id),
+            correlation_id: msg_id,
+            target_section_pk: None,
+        },
+        section_source: true,
+        dst: DstLocation::EndUser(write.origin),
+        aggregation: Aggregation::AtDestination,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+
+    #[test]
+    fn write_is_acknowledged_once_min_elders_have_confirmed() {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let msg_id = MessageId::new();
+        let elders: Vec<_> = (0..4).map(|_| XorName::random()).collect();
+        let mut acks = WriteAcks::new(3);
+
+        assert!(acks.record(msg_id, origin, elders[0]).is_none());
+        assert!(acks.record(msg_id, origin, elders[1]).is_none());
+        // the same Elder is only counted once
+        assert!(acks.record(msg_id, origin, elders[1]).is_none());
+        assert!(acks.record(msg_id, origin, elders[2]).is_some());
+        // only acknowledged once
+        assert!(acks.record(msg_id, origin, elders[3]).is_none());
+    }
+
+    #[test]
+    fn write_is_acknowledged_at_the_level_selected() {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let elders: Vec<_> = (0..3).map(|_| XorName::random()).collect();
+        let mut acks = WriteAcks::new(0);
+
+        let accepted = MessageId::new();
+        assert!(acks
+            .select(accepted, origin, AckLevel::Accepted, 3)
+            .is_some());
+        assert!(acks.record(accepted, origin, elders[0]).is_none());
+
+        let replicated = MessageId::new();
+        assert!(acks
+            .select(replicated, origin, AckLevel::Replicated, 3)
+            .is_none());
+        assert!(acks.record(replicated, origin, elders[0]).is_none());
+        assert!(acks.record(replicated, origin, elders[1]).is_none());
+        assert!(acks.record(replicated, origin, elders[2]).is_some());
+
+        assert_eq!(AckLevel::of("ack-level:durable"), Some(AckLevel::Durable));
+        assert_eq!(AckLevel::of("ack-level:soon"), None);
+        assert_eq!(AckLevel::of("payment"), None);
+    }
+
+    #[test]
+    fn writes_applied_by_several_elders_are_acknowledged_by_each() {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let msg_id = MessageId::new();
+        let names: BTreeSet<_> = (0..3).map(|_| XorName::random()).collect();
+        let mut elders: BTreeMap<_, _> = names
+            .iter()
+            .map(|name| (*name, WriteAcks::new(2)))
+            .collect();
+        let mut acked = BTreeSet::new();
+        for name in &names {
+            let acks = elders.get_mut(name).expect("an Elder");
+            for duty in acks.applied(msg_id, origin, *name, &names) {
+                match duty {
+                    NodeDuty::Send(_) => assert!(acked.insert(*name)),
+                    NodeDuty::SendNodeMsgToNodes { targets, msg } => {
+                        assert!(!targets.contains(name));
+                        assert_eq!(targets.len(), names.len() - 1);
+                        let ack = match msg.body {
+                            NodeMsgBody::WriteAck(ack) => ack,
+                            body => panic!("Unexpected msg: {:?}", body),
+                        };
+                        for target in targets {
+                            let acks = elders.get_mut(&target).expect("an Elder");
+                            if acks.record(ack.id, ack.origin, *name).is_some() {
+                                assert!(acked.insert(target));
+                            }
+                        }
+                    }
+                    duty => panic!("Unexpected duty: {:?}", duty),
+                }
+            }
+        }
+        // each Elder acknowledged the write once, be it on applying
+        // it or on being told another Elder applied it
+        assert_eq!(acked, names);
+    }
+
+    // The msg sent, as read back by its recipients.
+    fn received(msg: NodeMsg) -> Result<NodeMsgBody> {
+        match NodeMsg::from_content(&msg.serialise()?) {
+            Some(msg) => Ok(msg?.body),
+            None => panic!("Not a node msg: {:?}", msg),
+        }
+    }
+
+    // The number of Elders to have applied a write when its client is first acked,
+    // given the level told by the Elders taking the payment for it, if ever acked.
+    fn applied_when_acked(
+        level: AckLevel,
+        min_acks: u8,
+        elder_count: usize,
+    ) -> Result<Option<usize>> {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let names: BTreeSet<_> = (0..elder_count).map(|_| XorName::random()).collect();
+        let mut elders: BTreeMap<_, _> = names
+            .iter()
+            .map(|name| (*name, WriteAcks::new(min_acks)))
+            .collect();
+        let selected = WriteAckLevel {
+            id: MessageId::new(),
+            origin,
+            level,
+        };
+        let address = XorName::random();
+        let selected = match selected.msg(address)? {
+            NodeDuty::SendNodeMsg {
+                msg,
+                dst: DstLocation::Section(dst),
+            } if dst == address => match received(msg)? {
+                NodeMsgBody::WriteAckLevel(selected) => selected,
+                body => panic!("Unexpected msg: {:?}", body),
+            },
+            duty => panic!("Unexpected duty: {:?}", duty),
+        };
+        let mut acked = None;
+        for acks in elders.values_mut() {
+            let ack = acks.select(selected.id, selected.origin, selected.level, elder_count);
+            if ack.is_some() {
+                acked = acked.or(Some(0));
+            }
+        }
+        for (applied, name) in names.iter().enumerate() {
+            let acks = elders.get_mut(name).expect("an Elder");
+            for duty in acks.applied(selected.id, origin, *name, &names) {
+                match duty {
+                    NodeDuty::Send(_) => acked = acked.or(Some(applied + 1)),
+                    NodeDuty::SendNodeMsgToNodes { targets, msg } => {
+                        let ack = match received(msg)? {
+                            NodeMsgBody::WriteAck(ack) => ack,
+                            body => panic!("Unexpected msg: {:?}", body),
+                        };
+                        for target in targets {
+                            let acks = elders.get_mut(&target).expect("an Elder");
+                            if acks.record(ack.id, ack.origin, *name).is_some() {
+                                acked = acked.or(Some(applied + 1));
+                            }
+                        }
+                    }
+                    duty => panic!("Unexpected duty: {:?}", duty),
+                }
+            }
+        }
+        Ok(acked)
+    }
+
+    #[test]
+    fn each_level_is_acknowledged_once_its_elders_applied_the_write() -> Result<()> {
+        assert_eq!(applied_when_acked(AckLevel::Accepted, 0, 4)?, Some(0));
+        // a majority of the Elders, where writes are not acknowledged otherwise
+        assert_eq!(applied_when_acked(AckLevel::Durable, 0, 4)?, Some(3));
+        assert_eq!(applied_when_acked(AckLevel::Durable, 2, 4)?, Some(2));
+        // never more than the Elders there are
+        assert_eq!(applied_when_acked(AckLevel::Durable, 7, 4)?, Some(4));
+        assert_eq!(applied_when_acked(AckLevel::Replicated, 2, 4)?, Some(4));
+        assert_eq!(applied_when_acked(AckLevel::Replicated, 0, 1)?, Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn writes_are_not_acknowledged_when_disabled() {
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let mut acks = WriteAcks::new(0);
+        assert!(acks
+            .record(MessageId::new(), origin, XorName::random())
+            .is_none());
+    }
+}
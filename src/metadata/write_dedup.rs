@@ -0,0 +1,151 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Suppression of the writes received again, e.g. as retried by clients timing out
+//! on the response, so that a write is applied, and counted, only once.
+
+use crate::node_ops::{NodeDuties, NodeDuty, OutgoingMsg};
+use sn_data_types::PublicKey;
+use sn_messaging::{DstLocation, MessageId};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+// Writes are recognised when received again for this long after being applied.
+const RECENT_WRITE_TTL: Duration = Duration::from_secs(10 * 60);
+// Writes remembered at most, the oldest being forgotten first.
+const MAX_RECENT_WRITES: usize = 10_000;
+
+/// A write, by the id of its msg, the name of the data written and the key of the
+/// client writing it, so that clients reusing an id do not suppress each other's writes.
+pub(super) type WriteKey = (MessageId, XorName, PublicKey);
+
+/// The writes applied recently, with the responses sent to the clients for them.
+#[derive(Default)]
+pub(super) struct RecentWrites {
+    writes: BTreeMap<WriteKey, Vec<OutgoingMsg>>,
+    // the writes in the order applied, to forget them by
+    applied: VecDeque<(Instant, WriteKey)>,
+}
+
+impl RecentWrites {
+    /// The responses to the client to send again, if the write was applied recently.
+    pub(super) fn received_again(&mut self, key: &WriteKey, now: Instant) -> Option<NodeDuties> {
+        self.forget_expired(now);
+        self.writes
+            .get(key)
+            .map(|responses| responses.iter().cloned().map(NodeDuty::Send).collect())
+    }
+
+    /// Records the write as applied, along with the responses to the client among its duties.
+    pub(super) fn record(&mut self, key: WriteKey, duties: &[NodeDuty], now: Instant) {
+        self.forget_expired(now);
+        if self.writes.contains_key(&key) {
+            return;
+        }
+        while self.writes.len() >= MAX_RECENT_WRITES {
+            match self.applied.pop_front() {
+                Some((_, oldest)) => {
+                    let _ = self.writes.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        let responses = duties
+            .iter()
+            .filter_map(|duty| match duty {
+                NodeDuty::Send(msg) if matches!(msg.dst, DstLocation::EndUser(_)) => {
+                    Some(msg.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        let _ = self.writes.insert(key, responses);
+        self.applied.push_back((now, key));
+    }
+
+    fn forget_expired(&mut self, now: Instant) {
+        while let Some((applied, key)) = self.applied.front() {
+            if now.duration_since(*applied) < RECENT_WRITE_TTL {
+                break;
+            }
+            let _ = self.writes.remove(key);
+            let _ = self.applied.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_messaging::{
+        client::{Message, QueryResponse},
+        Aggregation, EndUser,
+    };
+
+    fn client() -> PublicKey {
+        PublicKey::from(bls::SecretKey::random().public_key())
+    }
+
+    fn response(id: MessageId, client: PublicKey) -> NodeDuty {
+        NodeDuty::Send(OutgoingMsg {
+            msg: Message::QueryResponse {
+                response: QueryResponse::GetBalance(Err(sn_messaging::client::Error::NoSuchData)),
+                id: MessageId::in_response_to(&id),
+                correlation_id: id,
+                target_section_pk: None,
+            },
+            section_source: false,
+            dst: DstLocation::EndUser(EndUser::AllClients(client)),
+            aggregation: Aggregation::None,
+        })
+    }
+
+    #[test]
+    fn writes_are_recognised_until_expired() {
+        let mut writes = RecentWrites::default();
+        let now = Instant::now();
+        let key = (MessageId::new(), XorName::random(), client());
+        assert!(writes.received_again(&key, now).is_none());
+
+        let duties = vec![response(key.0, key.2), NodeDuty::NoOp];
+        writes.record(key, &duties, now);
+        // only the responses to the client are sent again
+        let again = writes.received_again(&key, now + Duration::from_secs(1));
+        assert!(matches!(again.as_deref(), Some([NodeDuty::Send(_)])));
+        // the same msg writing other data is another write
+        assert!(writes
+            .received_again(&(key.0, XorName::random(), key.2), now)
+            .is_none());
+        // another client reusing the id is not suppressed
+        assert!(writes
+            .received_again(&(key.0, key.1, client()), now)
+            .is_none());
+
+        assert!(writes
+            .received_again(&key, now + RECENT_WRITE_TTL)
+            .is_none());
+    }
+
+    #[test]
+    fn oldest_writes_are_forgotten_first() {
+        let mut writes = RecentWrites::default();
+        let now = Instant::now();
+        let keys: Vec<_> = (0..=MAX_RECENT_WRITES)
+            .map(|_| (MessageId::new(), XorName::random(), client()))
+            .collect();
+        for key in &keys {
+            writes.record(*key, &[], now);
+        }
+        assert_eq!(writes.writes.len(), MAX_RECENT_WRITES);
+        assert!(writes.received_again(&keys[0], now).is_none());
+        assert!(writes.received_again(&keys[1], now).is_some());
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Quotas on the writes to each Sequence and Map, so that no client
+//! can grow the data held by the Elders without bound.
+
+use crate::{
+    error::convert_to_error_message,
+    node_ops::{NodeDuty, OutgoingMsg},
+    Error, Result,
+};
+use crdts::lseq::Op;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{
+    Map, MapEntryActions, MapSeqEntryAction, MapUnseqEntryAction, SequenceAddress,
+};
+use sn_messaging::{
+    client::{CmdError, DataCmd, MapWrite, Message, SequenceWrite},
+    Aggregation, DstLocation, EndUser, MessageId,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Entries a Map holds at most, if not configured.
+pub const DEFAULT_MAX_MAP_ENTRIES: usize = 10_000;
+/// Entries appended to a Sequence at most per hour, if not configured.
+pub const DEFAULT_MAX_SEQUENCE_APPENDS_PER_HOUR: usize = 3_600;
+/// Bytes of an entry at most, key and value, if not configured.
+pub const DEFAULT_MAX_ENTRY_SIZE: usize = 1024 * 1024;
+// The window the appends to a Sequence are counted over.
+const APPEND_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// The limits on the writes to each Sequence and Map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteQuotas {
+    /// Entries a Map holds at most.
+    pub max_map_entries: usize,
+    /// Entries appended to a Sequence at most over the last hour.
+    pub max_sequence_appends_per_hour: usize,
+    /// Bytes of an entry of a Sequence or Map at most, key and value.
+    pub max_entry_size: usize,
+}
+
+impl Default for WriteQuotas {
+    fn default() -> Self {
+        Self {
+            max_map_entries: DEFAULT_MAX_MAP_ENTRIES,
+            max_sequence_appends_per_hour: DEFAULT_MAX_SEQUENCE_APPENDS_PER_HOUR,
+            max_entry_size: DEFAULT_MAX_ENTRY_SIZE,
+        }
+    }
+}
+
+/// How a write changes the appends counted against a Sequence.
+pub(super) enum Appends {
+    /// An entry was appended to the Sequence.
+    Added(SequenceAddress),
+    /// The Sequence was deleted.
+    Cleared(SequenceAddress),
+}
+
+impl Appends {
+    pub(super) fn of(cmd: &DataCmd) -> Option<Self> {
+        match cmd {
+            DataCmd::Sequence(SequenceWrite::Edit(op)) => match op.crdt_op {
+                Op::Insert { .. } => Some(Self::Added(op.address)),
+                Op::Delete { .. } => None,
+            },
+            DataCmd::Sequence(SequenceWrite::Delete(address)) => Some(Self::Cleared(*address)),
+            _ => None,
+        }
+    }
+}
+
+/// Enforces the quotas, tracking the recent appends to each Sequence.
+pub(super) struct QuotaTracker {
+    quotas: WriteQuotas,
+    // when each entry was appended to a Sequence within the window, the oldest first
+    appends: BTreeMap<SequenceAddress, VecDeque<Instant>>,
+}
+
+impl QuotaTracker {
+    pub(super) fn new(quotas: WriteQuotas) -> Self {
+        Self {
+            quotas,
+            appends: BTreeMap::new(),
+        }
+    }
+
+    pub(super) fn set_quotas(&mut self, quotas: WriteQuotas) {
+        self.quotas = quotas;
+    }
+
+    /// Whether the write is within the quotas. The keys of the Map written
+    /// to are given for an edit of a Map, if it exists.
+    pub(super) fn check(
+        &self,
+        cmd: &DataCmd,
+        map_keys: Option<&BTreeSet<Vec<u8>>>,
+        now: Instant,
+    ) -> Result<()> {
+        match cmd {
+            DataCmd::Map(MapWrite::New(data)) => {
+                for size in map_entry_sizes(data) {
+                    self.check_entry_size(size)?;
+                }
+                self.check_map_entries(0, data.keys().len())
+            }
+            DataCmd::Map(MapWrite::Edit { changes, .. }) => {
+                let current = map_keys.map(BTreeSet::len).unwrap_or_default();
+                let mut entries = current;
+                match changes {
+                    MapEntryActions::Seq(actions) => {
+                        for (key, action) in actions.actions() {
+                            let is_held = matches!(map_keys, Some(keys) if keys.contains(key));
+                            match action {
+                                MapSeqEntryAction::Ins(value)
+                                | MapSeqEntryAction::Update(value) => {
+                                    self.check_entry_size(key.len() + value.data.len())?;
+                                    if !is_held {
+                                        entries += 1;
+                                    }
+                                }
+                                MapSeqEntryAction::Del(_) if is_held => entries -= 1,
+                                MapSeqEntryAction::Del(_) => (),
+                            }
+                        }
+                    }
+                    MapEntryActions::Unseq(actions) => {
+                        for (key, action) in actions.actions() {
+                            let is_held = matches!(map_keys, Some(keys) if keys.contains(key));
+                            match action {
+                                MapUnseqEntryAction::Ins(value)
+                                | MapUnseqEntryAction::Update(value) => {
+                                    self.check_entry_size(key.len() + value.len())?;
+                                    if !is_held {
+                                        entries += 1;
+                                    }
+                                }
+                                MapUnseqEntryAction::Del if is_held => entries -= 1,
+                                MapUnseqEntryAction::Del => (),
+                            }
+                        }
+                    }
+                }
+                self.check_map_entries(current, entries)
+            }
+            DataCmd::Sequence(SequenceWrite::Edit(op)) => match &op.crdt_op {
+                Op::Insert { val, .. } => {
+                    self.check_entry_size(val.len())?;
+                    let appended = self.appended_within_window(&op.address, now);
+                    if appended >= self.quotas.max_sequence_appends_per_hour {
+                        return Err(Error::SequenceAppendsExceeded {
+                            max: self.quotas.max_sequence_appends_per_hour,
+                        });
+                    }
+                    Ok(())
+                }
+                Op::Delete { .. } => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Records the appends of a write applied.
+    pub(super) fn record(&mut self, appends: Appends, now: Instant) {
+        match appends {
+            Appends::Added(address) => {
+                let appends = self.appends.entry(address).or_default();
+                while let Some(appended) = appends.front() {
+                    if now.saturating_duration_since(*appended) < APPEND_WINDOW {
+                        break;
+                    }
+                    let _ = appends.pop_front();
+                }
+                appends.push_back(now);
+            }
+            Appends::Cleared(address) => {
+                let _ = self.appends.remove(&address);
+            }
+        }
+    }
+
+    fn appended_within_window(&self, address: &SequenceAddress, now: Instant) -> usize {
+        self.appends.get(address).map_or(0, |appends| {
+            appends
+                .iter()
+                .filter(|appended| now.saturating_duration_since(**appended) < APPEND_WINDOW)
+                .count()
+        })
+    }
+
+    fn check_entry_size(&self, size: usize) -> Result<()> {
+        if size > self.quotas.max_entry_size {
+            return Err(Error::EntryTooLarge {
+                size,
+                max: self.quotas.max_entry_size,
+            });
+        }
+        Ok(())
+    }
+
+    // A Map held with more entries than the max, e.g. as the quota was lowered since,
+    // may still be edited, as long as the edit does not add to its entries.
+    fn check_map_entries(&self, current: usize, entries: usize) -> Result<()> {
+        if entries > self.quotas.max_map_entries && entries > current {
+            return Err(Error::MapEntriesExceeded {
+                entries,
+                max: self.quotas.max_map_entries,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn map_entry_sizes(data: &Map) -> Vec<usize> {
+    match data {
+        Map::Seq(data) => data
+            .entries()
+            .iter()
+            .map(|(key, value)| key.len() + value.data.len())
+            .collect(),
+        Map::Unseq(data) => data
+            .entries()
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .collect(),
+    }
+}
+
+/// The error answering the client, of the write exceeding a quota.
+pub(super) fn refusal(error: Error, msg_id: MessageId, origin: EndUser) -> Result<NodeDuty> {
+    info!("Write refused, as over quota: {}", error);
+    Ok(NodeDuty::Send(OutgoingMsg {
+        msg: Message::CmdError {
+            error: CmdError::Data(convert_to_error_message(error)?),
+            id: MessageId::in_response_to(&msg_id),
+            correlation_id: msg_id,
+            target_section_pk: None,
+        },
+        section_source: false, // strictly this is not correct, but we don't expect responses to an error..
+        dst: DstLocation::EndUser(origin),
+        aggregation: Aggregation::None,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+    use sn_data_types::{Keypair, MapAddress, MapSeqEntryActions, Sequence};
+    use xor_name::XorName;
+
+    fn quotas() -> WriteQuotas {
+        WriteQuotas {
+            max_map_entries: 2,
+            max_sequence_appends_per_hour: 2,
+            max_entry_size: 8,
+        }
+    }
+
+    fn insert(key: &[u8], value: &[u8]) -> MapSeqEntryActions {
+        MapSeqEntryActions::new().ins(key.to_vec(), value.to_vec(), 0)
+    }
+
+    fn edit(changes: MapSeqEntryActions) -> DataCmd {
+        DataCmd::Map(MapWrite::Edit {
+            address: MapAddress::Seq {
+                name: XorName::random(),
+                tag: 10,
+            },
+            changes: MapEntryActions::Seq(changes),
+        })
+    }
+
+    #[test]
+    fn maps_hold_entries_within_quota() {
+        let tracker = QuotaTracker::new(quotas());
+        let now = Instant::now();
+        let keys: BTreeSet<_> = vec![b"a".to_vec(), b"b".to_vec()].into_iter().collect();
+
+        assert!(tracker.check(&edit(insert(b"a", b"1")), None, now).is_ok());
+        // a new key is one too many, but a key held is only updated
+        assert!(matches!(
+            tracker.check(&edit(insert(b"c", b"1")), Some(&keys), now),
+            Err(Error::MapEntriesExceeded { entries: 3, max: 2 })
+        ));
+        assert!(tracker
+            .check(&edit(insert(b"a", b"2")), Some(&keys), now)
+            .is_ok());
+        // as is a new key in place of one deleted
+        let replaced = insert(b"c", b"1").del(b"a".to_vec(), 1);
+        assert!(tracker.check(&edit(replaced), Some(&keys), now).is_ok());
+
+        assert!(matches!(
+            tracker.check(&edit(insert(b"a", b"too large")), None, now),
+            Err(Error::EntryTooLarge { size: 10, max: 8 })
+        ));
+    }
+
+    #[test]
+    fn sequences_are_appended_to_within_quota() -> Result<()> {
+        let mut tracker = QuotaTracker::new(quotas());
+        let now = Instant::now();
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let owner = keypair.public_key();
+        let mut data = Sequence::new_public(owner, owner.to_string(), XorName::random(), 10, None);
+        let mut append = |entry: &[u8]| -> Result<DataCmd> {
+            let mut op = data.create_unsigned_append_op(entry.to_vec())?;
+            op.signature = Some(keypair.sign(&utils::serialise(&op.crdt_op)?));
+            data.apply_op(op.clone())?;
+            Ok(DataCmd::Sequence(SequenceWrite::Edit(op)))
+        };
+
+        assert!(matches!(
+            tracker.check(&append(b"too large")?, None, now),
+            Err(Error::EntryTooLarge { .. })
+        ));
+        for _ in 0..2 {
+            let cmd = append(b"entry")?;
+            tracker.check(&cmd, None, now)?;
+            if let Some(appends) = Appends::of(&cmd) {
+                tracker.record(appends, now);
+            }
+        }
+        let cmd = append(b"entry")?;
+        assert!(matches!(
+            tracker.check(&cmd, None, now),
+            Err(Error::SequenceAppendsExceeded { max: 2 })
+        ));
+        // until the appends age out of the window
+        tracker.check(&cmd, None, now + APPEND_WINDOW)
+    }
+}
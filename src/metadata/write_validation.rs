@@ -0,0 +1,123 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Validation of each op written to Sequences and Maps, before it is applied,
+//! of what the data types do not check themselves when applying it.
+
+use super::read_access;
+use crate::{Error, Result};
+use sn_data_types::{Error as DtError, Map, MapEntryActions, Sequence, SequenceEntry, SequenceOp};
+use sn_messaging::EndUser;
+
+/// A new Sequence must be owned by the client creating it, and be empty,
+/// as its entries are only appended by ops permitted by its policy.
+pub(super) fn validate_new_sequence(data: &Sequence, origin: EndUser) -> Result<()> {
+    let public_key = *origin.id();
+    if data.owner() != public_key {
+        return Err(Error::InvalidOwners(public_key));
+    }
+    if !data.is_empty(Some(public_key))? {
+        return Err(Error::InvalidOperation(
+            "A new Sequence must not hold any entries".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// An op to a Sequence must be made and signed by the client writing it.
+/// Its signature, and the permission of the client, are verified on applying it.
+pub(super) fn validate_sequence_op(op: &SequenceOp<SequenceEntry>, origin: EndUser) -> Result<()> {
+    if op.source != *origin.id() {
+        return Err(Error::NetworkData(DtError::AccessDenied(*origin.id())));
+    }
+    if op.signature.is_none() {
+        return Err(Error::NetworkData(DtError::CrdtMissingOpSignature));
+    }
+    Ok(())
+}
+
+/// A new Map must be owned by the client creating it, and list its readers as keys, if any.
+pub(super) fn validate_new_map(data: &Map, origin: EndUser) -> Result<()> {
+    let public_key = *origin.id();
+    if data.owner() != public_key {
+        return Err(Error::InvalidOwners(public_key));
+    }
+    let _ = read_access::readers(data)?;
+    Ok(())
+}
+
+/// An edit of a Map must change at least one of its entries.
+pub(super) fn validate_map_changes(changes: &MapEntryActions) -> Result<()> {
+    let is_empty = match changes {
+        MapEntryActions::Seq(actions) => actions.actions().is_empty(),
+        MapEntryActions::Unseq(actions) => actions.actions().is_empty(),
+    };
+    if is_empty {
+        return Err(Error::InvalidOperation(
+            "An edit of a Map must change at least one entry".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+    use sn_data_types::{Keypair, MapSeqEntryActions, PublicKey, UnseqMap};
+    use xor_name::XorName;
+
+    fn client() -> (Keypair, EndUser) {
+        let keypair = Keypair::new_ed25519(&mut rand::thread_rng());
+        let origin = EndUser::AllClients(keypair.public_key());
+        (keypair, origin)
+    }
+
+    fn sequence(owner: PublicKey) -> Sequence {
+        Sequence::new_public(owner, owner.to_string(), XorName::random(), 10, None)
+    }
+
+    #[test]
+    fn new_data_must_be_owned_by_its_creator() -> Result<()> {
+        let (keypair, origin) = client();
+        let (_, other) = client();
+        let owner = keypair.public_key();
+        validate_new_sequence(&sequence(owner), origin)?;
+        assert!(validate_new_sequence(&sequence(owner), other).is_err());
+
+        // and a new Sequence holds no entries
+        let mut filled = sequence(owner);
+        let mut op = filled.create_unsigned_append_op(b"entry".to_vec())?;
+        op.signature = Some(keypair.sign(&utils::serialise(&op.crdt_op)?));
+        filled.apply_op(op)?;
+        assert!(validate_new_sequence(&filled, origin).is_err());
+
+        let map = Map::Unseq(UnseqMap::new(XorName::random(), 10, owner));
+        validate_new_map(&map, origin)?;
+        assert!(validate_new_map(&map, other).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ops_must_be_signed_by_their_writer() -> Result<()> {
+        let (keypair, origin) = client();
+        let (_, other) = client();
+        let mut data = sequence(keypair.public_key());
+        let mut op = data.create_unsigned_append_op(b"entry".to_vec())?;
+        assert!(validate_sequence_op(&op, origin).is_err());
+        op.signature = Some(keypair.sign(&utils::serialise(&op.crdt_op)?));
+        validate_sequence_op(&op, origin)?;
+        assert!(validate_sequence_op(&op, other).is_err());
+
+        let changes = MapEntryActions::Seq(MapSeqEntryActions::new());
+        assert!(validate_map_changes(&changes).is_err());
+        let changes =
+            MapEntryActions::Seq(MapSeqEntryActions::new().ins(b"key".to_vec(), vec![], 0));
+        validate_map_changes(&changes)
+    }
+}
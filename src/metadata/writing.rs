@@ -11,7 +11,10 @@ use super::{
     sequence_storage::SequenceStorage,
 };
 use crate::Result;
-use crate::{network::Network, node_ops::NodeDuty};
+use crate::{
+    network::Network,
+    node_ops::{NodeDuties, NodeDuty},
+};
 use log::info;
 use sn_messaging::{
     client::{BlobWrite, DataCmd, MapWrite, SequenceWrite},
@@ -23,7 +26,7 @@ pub(super) async fn get_result(
     msg_id: MessageId,
     origin: EndUser,
     stores: &mut ElderStores,
-) -> Result<NodeDuty> {
+) -> Result<NodeDuties> {
     use DataCmd::*;
     info!("Writing Data");
     match cmd {
@@ -33,11 +36,15 @@ pub(super) async fn get_result(
         }
         Map(write) => {
             info!("Writing Map");
-            map(write, stores.map_storage_mut(), msg_id, origin).await
+            map(write, stores.map_storage_mut(), msg_id, origin)
+                .await
+                .map(NodeDuties::from)
         }
         Sequence(write) => {
             info!("Writing Sequence");
-            sequence(write, stores.sequence_storage_mut(), msg_id, origin).await
+            sequence(write, stores.sequence_storage_mut(), msg_id, origin)
+                .await
+                .map(NodeDuties::from)
         }
     }
 }
@@ -47,7 +54,7 @@ async fn blob(
     register: &mut BlobRegister,
     msg_id: MessageId,
     origin: EndUser,
-) -> Result<NodeDuty> {
+) -> Result<NodeDuties> {
     register.write(write, msg_id, origin).await
 }
 
@@ -0,0 +1,165 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+use log::{debug, info, warn};
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::time;
+
+/// Attempts made at a call before it is counted as failed.
+const CALL_ATTEMPTS: usize = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Consecutive failed calls tripping the breaker.
+const FAILURE_THRESHOLD: usize = 5;
+/// While tripped, calls fail right away, until a
+/// trial call is let through after this long.
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct BreakerState {
+    failures: usize,
+    tripped_at: Option<Instant>,
+}
+
+/// Retries calls to routing on transient errors, and trips once calls keep failing,
+/// so that the node is known to be degraded rather than failing duty after duty.
+pub(super) struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    threshold: usize,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub(super) fn new() -> Self {
+        Self::with_limits(FAILURE_THRESHOLD, COOLDOWN)
+    }
+
+    fn with_limits(threshold: usize, cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(BreakerState::default()),
+            threshold,
+            cooldown,
+        }
+    }
+
+    /// Whether calls have kept failing, and the breaker is tripped.
+    pub(super) fn is_tripped(&self) -> bool {
+        self.lock().tripped_at.is_some()
+    }
+
+    /// Makes the call, retrying it on error. Fails right away while the
+    /// breaker is tripped, but for a trial call once the cooldown has passed.
+    pub(super) async fn call<T, F, Fut>(&self, name: &str, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if let Some(tripped_at) = self.lock().tripped_at {
+            if tripped_at.elapsed() < self.cooldown {
+                return Err(Error::NetworkDegraded(name.to_string()));
+            }
+        }
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Ok(result) => {
+                    self.record_success();
+                    return Ok(result);
+                }
+                Err(error) if attempt < CALL_ATTEMPTS => {
+                    debug!(
+                        "Network call {} failed (attempt {} of {}): {}",
+                        name, attempt, CALL_ATTEMPTS, error
+                    );
+                    attempt += 1;
+                    time::sleep(RETRY_DELAY).await;
+                }
+                Err(error) => {
+                    self.record_failure(name);
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.lock();
+        if state.tripped_at.is_some() {
+            info!("Network calls succeeding again, no longer degraded");
+        }
+        *state = BreakerState::default();
+    }
+
+    fn record_failure(&self, name: &str) {
+        let mut state = self.lock();
+        state.failures += 1;
+        if state.failures >= self.threshold {
+            if state.tripped_at.is_none() {
+                warn!(
+                    "{} consecutive network calls failed, last was {}. Node is degraded.",
+                    state.failures, name
+                );
+            }
+            // a failed trial call starts the cooldown over
+            state.tripped_at = Some(Instant::now());
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        // the state is always left consistent, so a poisoned lock is still usable
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn breaker_trips_on_persistent_failures_and_recovers() {
+        let breaker = CircuitBreaker::with_limits(2, Duration::from_millis(50));
+        let failing = || async { Err::<(), _>(Error::NoSectionPublicKey) };
+
+        // transient errors are retried
+        let mut attempts = 0;
+        let result = breaker
+            .call("flaky", || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < CALL_ATTEMPTS {
+                        Err(Error::NoSectionPublicKey)
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.ok(), Some(CALL_ATTEMPTS));
+
+        assert!(breaker.call("failing", failing).await.is_err());
+        assert!(!breaker.is_tripped());
+        assert!(breaker.call("failing", failing).await.is_err());
+        assert!(breaker.is_tripped());
+        assert!(matches!(
+            breaker.call("other", || async { Ok(()) }).await,
+            Err(Error::NetworkDegraded(_))
+        ));
+
+        // a trial call is let through after the cooldown
+        time::sleep(Duration::from_millis(60)).await;
+        assert!(breaker.call("other", || async { Ok(()) }).await.is_ok());
+        assert!(!breaker.is_tripped());
+    }
+}
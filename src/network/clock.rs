@@ -0,0 +1,180 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use xor_name::XorName;
+
+/// Times reported by peers are compared with ours for this long.
+const PEER_TIME_TTL: Duration = Duration::from_secs(10 * 60);
+/// Peers to have reported their time before our clock is judged by them.
+const MIN_PEER_TIMES: usize = 3;
+/// Skew of our clock up to this is tolerated, and not corrected for.
+pub(crate) const CLOCK_SKEW_TOLERANCE_SECS: u64 = 5;
+/// Skew of our clock beyond this is alerted on, as our clock is to be fixed.
+pub(crate) const CLOCK_SKEW_ALERT_SECS: u64 = 60;
+
+/// The time of the network, as reported by peers, for the timestamps which are
+/// to agree with those of the peers, e.g. of the expiry of time-locked credits.
+/// With our clock skewed beyond the tolerance from the median of the times
+/// reported, the skew is corrected for, until our clock is fixed.
+#[derive(Debug, Default)]
+pub(crate) struct NetworkClock {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    // by peer, the secs their clock was ahead of ours, and when reported
+    skews: BTreeMap<XorName, (i64, Instant)>,
+    // secs added to our clock
+    correction: i64,
+}
+
+/// Our clock, as judged by the times reported by peers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ClockSkew {
+    /// Secs our clock is behind the median of the peers, or ahead of it, if negative.
+    pub(crate) behind_secs: i64,
+    /// Peers reporting.
+    pub(crate) peers: usize,
+}
+
+impl ClockSkew {
+    /// Whether the skew is to be alerted on.
+    pub(crate) fn is_alerting(&self) -> bool {
+        self.behind_secs.unsigned_abs() > CLOCK_SKEW_ALERT_SECS
+    }
+}
+
+impl NetworkClock {
+    /// The peer reported their time, as secs since the unix epoch.
+    pub(crate) fn peer_time(&self, peer: XorName, secs: u64, now: Instant) {
+        self.record(peer, secs, local_secs(), now)
+    }
+
+    fn record(&self, peer: XorName, secs: u64, local_secs: u64, now: Instant) {
+        let behind = secs as i64 - local_secs as i64;
+        let _ = self.lock().skews.insert(peer, (behind, now));
+    }
+
+    /// Judges our clock by the times reported by peers, correcting for the skew
+    /// of it beyond the tolerance. Returns the skew, unless too few peers reported.
+    pub(crate) fn check(&self, now: Instant) -> Option<ClockSkew> {
+        let mut inner = self.lock();
+        inner
+            .skews
+            .retain(|_, (_, reported)| now.saturating_duration_since(*reported) < PEER_TIME_TTL);
+        if inner.skews.len() < MIN_PEER_TIMES {
+            inner.correction = 0;
+            return None;
+        }
+        let mut skews: Vec<_> = inner.skews.values().map(|(behind, _)| *behind).collect();
+        skews.sort_unstable();
+        let behind_secs = skews[skews.len() / 2];
+        inner.correction = if behind_secs.unsigned_abs() > CLOCK_SKEW_TOLERANCE_SECS {
+            behind_secs
+        } else {
+            0
+        };
+        Some(ClockSkew {
+            behind_secs,
+            peers: skews.len(),
+        })
+    }
+
+    /// Secs corrected for, as of the last check.
+    pub(crate) fn correction(&self) -> i64 {
+        self.lock().correction
+    }
+
+    /// Secs since the unix epoch, in network time.
+    pub(crate) fn now_secs(&self) -> u64 {
+        corrected(local_secs(), self.correction())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // a panic while holding the lock leaves the times usable
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Secs since the unix epoch, by our clock, uncorrected.
+pub(super) fn local_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+fn corrected(secs: u64, correction: i64) -> u64 {
+    if correction < 0 {
+        secs.saturating_sub(correction.unsigned_abs())
+    } else {
+        secs.saturating_add(correction as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn skew_beyond_tolerance_is_corrected_for() {
+        let clock = NetworkClock::default();
+        let now = Instant::now();
+        let local = 1_000_000;
+
+        clock.record(XorName::random(), local + 120, local, now);
+        clock.record(XorName::random(), local + 118, local, now);
+        assert_eq!(clock.check(now), None);
+        assert_eq!(clock.correction(), 0);
+
+        // one far off peer does not skew the median
+        clock.record(XorName::random(), local + 100_000, local, now);
+        let skew = clock.check(now);
+        assert_eq!(
+            skew,
+            Some(ClockSkew {
+                behind_secs: 120,
+                peers: 3,
+            })
+        );
+        assert!(skew.is_some_and(|skew| skew.is_alerting()));
+        assert_eq!(corrected(local, clock.correction()), local + 120);
+
+        // reports expire, after which our clock is trusted again
+        assert_eq!(clock.check(now + PEER_TIME_TTL), None);
+        assert_eq!(clock.correction(), 0);
+    }
+
+    #[test]
+    fn skew_within_tolerance_is_not_corrected_for() {
+        let clock = NetworkClock::default();
+        let now = Instant::now();
+        let local = 1_000_000;
+        for behind in &[-3, 2, 4] {
+            clock.record(
+                XorName::random(),
+                (local as i64 + behind) as u64,
+                local,
+                now,
+            );
+        }
+        let skew = clock.check(now);
+        assert_eq!(skew.map(|skew| skew.behind_secs), Some(2));
+        assert!(!skew.is_some_and(|skew| skew.is_alerting()));
+        assert_eq!(clock.correction(), 0);
+    }
+}
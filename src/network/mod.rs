@@ -6,10 +6,23 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::node_ops::OutgoingMsg;
+mod circuit_breaker;
+mod clock;
+mod probes;
+mod sibling;
+mod verification_cache;
+
+pub(crate) use self::clock::NetworkClock;
+pub use self::probes::PeerLatency;
+use self::probes::PROBE_TICK;
+pub(crate) use self::probes::{PeerPing, PeerPong, PeerProbes};
+pub use self::sibling::SiblingContact;
+pub(crate) use self::verification_cache::VerificationCache;
+use self::{circuit_breaker::CircuitBreaker, sibling::SiblingCache};
+use crate::node_ops::{NodeDuty, OutgoingMsg, PeriodicTask};
 use crate::{utils, Config as NodeConfig, Error, Result};
 use bytes::Bytes;
-use ed25519_dalek::PublicKey as Ed25519PublicKey;
+use ed25519_dalek::{Keypair, PublicKey as Ed25519PublicKey};
 
 // TODO: use only sn_data_types
 use bls::{PublicKeySet, PublicKeyShare as BlsPublicKeyShare};
@@ -23,29 +36,60 @@ use sn_routing::{
     SectionChain,
 };
 use std::sync::Arc;
+use std::time::Duration;
 use std::{collections::BTreeMap, net::SocketAddr};
 use std::{collections::BTreeSet, path::PathBuf};
 use xor_name::{Prefix, XorName};
 
+// How often our clock is checked against the network time.
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The background work on our connections to the network.
+pub(crate) fn periodic_tasks() -> Vec<PeriodicTask> {
+    vec![
+        PeriodicTask {
+            name: "clock check",
+            interval: CLOCK_CHECK_INTERVAL,
+            duty: || NodeDuty::CheckClock,
+        },
+        PeriodicTask {
+            name: "peer probes",
+            interval: PROBE_TICK,
+            duty: || NodeDuty::ProbePeers,
+        },
+    ]
+}
+
 ///
 #[derive(Clone)]
 pub struct Network {
     routing: Arc<RoutingNode>,
+    breaker: Arc<CircuitBreaker>,
+    sibling: Arc<SiblingCache>,
+    verified: Arc<VerificationCache>,
+    clock: Arc<NetworkClock>,
+    probes: Arc<PeerProbes>,
 }
 
 #[allow(missing_docs)]
 impl Network {
-    pub async fn new(config: &NodeConfig) -> Result<(Self, EventStream)> {
+    /// Joins the network, with the keypair given, or a random one.
+    pub async fn new(config: &NodeConfig, keypair: Option<Keypair>) -> Result<(Self, EventStream)> {
         let node_config = RoutingConfig {
             first: config.is_first(),
+            keypair,
             transport_config: config.network_config().clone(),
-            ..Default::default()
         };
         let (routing, event_stream) = RoutingNode::new(node_config).await?;
 
         Ok((
             Self {
                 routing: Arc::new(routing),
+                breaker: Arc::new(CircuitBreaker::new()),
+                sibling: Arc::new(SiblingCache::default()),
+                verified: Arc::new(VerificationCache::default()),
+                clock: Arc::new(NetworkClock::default()),
+                probes: Arc::new(PeerProbes::default()),
             },
             event_stream,
         ))
@@ -106,17 +150,29 @@ impl Network {
         self.routing.public_key().await
     }
 
+    /// Whether calls to routing keep failing.
+    pub fn is_degraded(&self) -> bool {
+        self.breaker.is_tripped()
+    }
+
     pub async fn section_public_key(&self) -> Result<PublicKey> {
-        Ok(PublicKey::Bls(
-            self.routing
-                .public_key_set()
-                .await
-                .map_err(|_| Error::NoSectionPublicKey)?
-                .public_key(),
-        ))
+        self.breaker
+            .call("section_public_key", || async {
+                Ok(PublicKey::Bls(
+                    self.routing
+                        .public_key_set()
+                        .await
+                        .map_err(|_| Error::NoSectionPublicKey)?
+                        .public_key(),
+                ))
+            })
+            .await
     }
 
     pub async fn sibling_public_key(&self) -> Option<PublicKey> {
+        if let Some(sibling) = self.sibling.get() {
+            return Some(sibling.key);
+        }
         let sibling_prefix = self.our_prefix().await.sibling();
         self.routing
             .section_key(&sibling_prefix)
@@ -124,16 +180,68 @@ impl Network {
             .map(PublicKey::Bls)
     }
 
+    /// Our sibling section, as of our last split.
+    pub fn sibling(&self) -> Option<SiblingContact> {
+        self.sibling.get()
+    }
+
+    /// Keeps the contact details of our sibling, on split. The Elders given are
+    /// those known of it beforehand, added to any the routing layer knows of.
+    pub async fn cache_sibling(&self, key: PublicKey, mut elders: BTreeSet<XorName>) {
+        let prefix = self.our_prefix().await.sibling();
+        if let Some((_, known)) = self.known_sibling(&prefix).await {
+            elders.extend(known);
+        }
+        self.sibling.set(SiblingContact {
+            prefix,
+            key,
+            elders,
+        });
+    }
+
+    /// Refreshes our sibling with what the routing layer knows of it,
+    /// dropping it once it is no longer our sibling.
+    pub async fn refresh_sibling(&self) {
+        let our_prefix = self.our_prefix().await;
+        let known = self.known_sibling(&our_prefix.sibling()).await;
+        self.sibling.refresh(&our_prefix, known);
+    }
+
+    async fn known_sibling(&self, prefix: &Prefix) -> Option<(PublicKey, BTreeSet<XorName>)> {
+        let key = self.routing.section_key(prefix).await?;
+        let elders = self
+            .routing
+            .other_sections()
+            .await
+            .into_iter()
+            .find(|info| info.prefix == *prefix)?
+            .elders
+            .keys()
+            .copied()
+            .collect();
+        Some((PublicKey::Bls(key), elders))
+    }
+
     pub async fn matching_section(&self, name: &XorName) -> Option<bls::PublicKey> {
+        if let Some(PublicKey::Bls(key)) = self.sibling.matching(name).map(|sibling| sibling.key) {
+            return Some(key);
+        }
         let (key, _) = self.routing.matching_section(&name).await;
         key
     }
 
     pub async fn our_public_key_set(&self) -> Result<PublicKeySet> {
-        self.routing.public_key_set().await.map_err(Error::Routing)
+        self.breaker
+            .call("our_public_key_set", || async {
+                self.routing.public_key_set().await.map_err(Error::Routing)
+            })
+            .await
     }
 
     pub async fn get_section_pk_by_name(&self, name: &XorName) -> Result<PublicKey> {
+        if let Some(sibling) = self.sibling.matching(name) {
+            return Ok(sibling.key);
+        }
         let (pk, elders) = self.routing.matching_section(name).await;
         if let Some(pk) = pk {
             let pk = PublicKey::from(pk);
@@ -197,7 +305,53 @@ impl Network {
 
     /// BLS key index in routing for key shares
     pub async fn our_index(&self) -> Result<usize> {
-        self.routing.our_index().await.map_err(Error::Routing)
+        self.breaker
+            .call("our_index", || async {
+                self.routing.our_index().await.map_err(Error::Routing)
+            })
+            .await
+    }
+
+    /// The signatures verified as valid, shared by the duties validating them.
+    pub(crate) fn verification_cache(&self) -> Arc<VerificationCache> {
+        self.verified.clone()
+    }
+
+    /// The time of the network, as reported by peers, shared by the duties timestamping by it.
+    pub(crate) fn clock(&self) -> Arc<NetworkClock> {
+        self.clock.clone()
+    }
+
+    /// The latency of the peers of our section, as probed, shared by the duties choosing
+    /// between peers.
+    pub(crate) fn probes(&self) -> Arc<PeerProbes> {
+        self.probes.clone()
+    }
+
+    /// Whether the node is an Elder of our section, or of any other section known to us.
+    pub async fn is_known_elder(&self, name: &XorName) -> bool {
+        if self.our_elder_names().await.contains(name) {
+            return true;
+        }
+        if let Some(sibling) = self.sibling.matching(name) {
+            if sibling.elders.contains(name) {
+                return true;
+            }
+        }
+        self.routing
+            .other_sections()
+            .await
+            .iter()
+            .any(|info| info.elders.contains_key(name))
+    }
+
+    /// The prefix and Elders known of the section matching the name, if any.
+    pub async fn section_of(&self, name: &XorName) -> Option<(Prefix, BTreeSet<XorName>)> {
+        if let Some(sibling) = self.sibling.matching(name) {
+            return Some((sibling.prefix, sibling.elders));
+        }
+        let (_, info) = self.routing.matching_section(name).await;
+        info.map(|info| (info.prefix, info.elders.keys().copied().collect()))
     }
 
     pub async fn our_elder_names(&self) -> BTreeSet<XorName> {
@@ -276,6 +430,25 @@ impl Network {
             .collect::<BTreeMap<XorName, u8>>()
     }
 
+    /// The oldest Adults, being next in line for promotion, oldest first.
+    pub async fn our_oldest_adults(&self, count: usize) -> Vec<XorName> {
+        let mut adults: Vec<_> = self
+            .routing
+            .our_adults()
+            .await
+            .into_iter()
+            .map(|peer| (peer.age(), *peer.name()))
+            .collect();
+        adults.sort_by(|(age, name), (other_age, other_name)| {
+            other_age.cmp(age).then(name.cmp(other_name))
+        });
+        adults
+            .into_iter()
+            .take(count)
+            .map(|(_, name)| name)
+            .collect()
+    }
+
     pub async fn our_adults(&self) -> Vec<XorName> {
         self.routing
             .our_adults()
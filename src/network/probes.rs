@@ -0,0 +1,320 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::clock;
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    Result,
+};
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+use sn_messaging::DstLocation;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    convert::TryFrom,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// How often a sample of the peers of our section is pinged.
+pub(crate) const PROBE_TICK: Duration = Duration::from_secs(10);
+/// Peers pinged at most per round.
+const PROBE_SAMPLE: usize = 8;
+// Pings not answered within this time are lost.
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+// The answers the mean round trip time is over, roughly.
+const RTT_SAMPLES: u64 = 8;
+// The loss of a peer is judged by its last this many pings.
+const LOSS_WINDOW: usize = 10;
+// Peers having lost at least this share of their last pings are lossy...
+const LOSSY_PERCENTAGE: u8 = 50;
+// ...once pinged at least this many times.
+const MIN_LOSS_SAMPLES: usize = 4;
+
+/// A ping of a peer of our section, for the round trip time to it,
+/// carrying our time, which the peer judges their clock by.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerPing {
+    pub(crate) nonce: u64,
+    /// Secs since the unix epoch, by the clock of the peer pinging.
+    pub(crate) secs: u64,
+}
+
+impl PeerPing {
+    // The msg pinging the peers.
+    fn msg(&self, peers: BTreeSet<XorName>) -> NodeDuty {
+        NodeDuty::SendNodeMsgToNodes {
+            targets: peers,
+            msg: NodeMsg::new(NodeMsgBody::PeerPing(self.clone())),
+        }
+    }
+
+    /// The answer to the ping, carrying our time, which peers judge their clock by.
+    pub(crate) fn pong(self) -> PeerPong {
+        PeerPong {
+            ping: self,
+            secs: clock::local_secs(),
+        }
+    }
+}
+
+/// The answer of a peer to a ping.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerPong {
+    pub(crate) ping: PeerPing,
+    /// Secs since the unix epoch, by the clock of the peer.
+    pub(crate) secs: u64,
+}
+
+impl PeerPong {
+    /// The msg answering the peer pinging us.
+    pub(crate) fn msg(&self, peer: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::PeerPong(self.clone())),
+            dst: DstLocation::Node(peer),
+        }
+    }
+}
+
+/// The round trip time to a peer of our section, and the pings it lost, as probed by us.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerLatency {
+    /// Pings sent.
+    pub pinged: u64,
+    /// Pings answered.
+    pub answered: u64,
+    /// Pings not answered in time.
+    pub lost: u64,
+    /// Moving mean of the millis taken to answer.
+    pub mean_rtt_ms: u64,
+    /// The share of the last pings lost, from 0 to 100.
+    pub loss_percentage: u8,
+}
+
+#[derive(Default)]
+struct Probed {
+    latency: PeerLatency,
+    // the nonce of the ping pending an answer, and when it was sent
+    pending: Option<(u64, Instant)>,
+    // whether each of the last pings was lost, the oldest first
+    outcomes: VecDeque<bool>,
+}
+
+impl Probed {
+    fn outcome(&mut self, lost: bool) {
+        if self.outcomes.len() == LOSS_WINDOW {
+            let _ = self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(lost);
+        let lost = self.outcomes.iter().filter(|lost| **lost).count();
+        self.latency.loss_percentage = (lost * 100 / self.outcomes.len()) as u8;
+    }
+
+    fn is_lossy(&self) -> bool {
+        self.outcomes.len() >= MIN_LOSS_SAMPLES && self.latency.loss_percentage >= LOSSY_PERCENTAGE
+    }
+}
+
+/// The latency and loss of the peers of our section, as actively probed by pinging a
+/// sample of them each round, rather than only judged by their answers to other msgs.
+/// Shared by the duties choosing between peers, e.g. the holders to read a chunk from.
+#[derive(Default)]
+pub(crate) struct PeerProbes {
+    inner: Mutex<BTreeMap<XorName, Probed>>,
+}
+
+impl PeerProbes {
+    /// Counts the pings not answered in time as lost, and pings a random sample of the
+    /// peers given, forgetting those no longer among them.
+    pub(crate) fn probe(&self, peers: BTreeSet<XorName>, now: Instant) -> Result<Option<NodeDuty>> {
+        let mut probed = self.lock();
+        probed.retain(|peer, _| peers.contains(peer));
+        for probed in probed.values_mut() {
+            if let Some((_, sent)) = probed.pending {
+                if now.saturating_duration_since(sent) >= PING_TIMEOUT {
+                    probed.pending = None;
+                    probed.latency.lost += 1;
+                    probed.outcome(true);
+                }
+            }
+        }
+        let idle = peers.into_iter().filter(|peer| {
+            !matches!(
+                probed.get(peer),
+                Some(Probed {
+                    pending: Some(_),
+                    ..
+                })
+            )
+        });
+        let sample: BTreeSet<_> = idle
+            .choose_multiple(&mut rand::thread_rng(), PROBE_SAMPLE)
+            .into_iter()
+            .collect();
+        if sample.is_empty() {
+            return Ok(None);
+        }
+        let ping = PeerPing {
+            nonce: rand::random(),
+            secs: clock::local_secs(),
+        };
+        for peer in &sample {
+            let probed = probed.entry(*peer).or_default();
+            probed.pending = Some((ping.nonce, now));
+            probed.latency.pinged += 1;
+        }
+        Ok(Some(ping.msg(sample)))
+    }
+
+    /// The peer answered the ping, if it is the one pending with it.
+    pub(crate) fn answered(&self, peer: XorName, pong: &PeerPong, now: Instant) -> bool {
+        let mut probed = self.lock();
+        let probed = match probed.get_mut(&peer) {
+            Some(probed) => probed,
+            None => return false,
+        };
+        let sent = match probed.pending {
+            Some((nonce, sent)) if nonce == pong.ping.nonce => sent,
+            _ => return false,
+        };
+        probed.pending = None;
+        let rtt =
+            u64::try_from(now.saturating_duration_since(sent).as_millis()).unwrap_or(u64::MAX);
+        let latency = &mut probed.latency;
+        latency.mean_rtt_ms = if latency.answered == 0 {
+            rtt
+        } else {
+            let weight = (latency.answered + 1).min(RTT_SAMPLES);
+            (latency.mean_rtt_ms * (weight - 1) + rtt) / weight
+        };
+        latency.answered += 1;
+        probed.outcome(false);
+        true
+    }
+
+    /// Orders the peers by their mean round trip time, the fastest first, those
+    /// not answered yet after those answered, and those lossy last.
+    pub(crate) fn rank(&self, peers: impl IntoIterator<Item = XorName>) -> Vec<XorName> {
+        let probed = self.lock();
+        let mut peers: Vec<_> = peers.into_iter().collect();
+        peers.sort_by_key(|peer| match probed.get(peer) {
+            Some(probed) if probed.is_lossy() => (2, 0),
+            Some(probed) if probed.latency.answered > 0 => (0, probed.latency.mean_rtt_ms),
+            _ => (1, 0),
+        });
+        peers
+    }
+
+    /// Whether the peer lost too many of its last pings to be relied upon.
+    pub(crate) fn is_lossy(&self, peer: &XorName) -> bool {
+        self.lock().get(peer).is_some_and(Probed::is_lossy)
+    }
+
+    /// The latency of each of the peers probed.
+    pub(crate) fn latencies(&self) -> BTreeMap<XorName, PeerLatency> {
+        self.lock()
+            .iter()
+            .map(|(peer, probed)| (*peer, probed.latency))
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<XorName, Probed>> {
+        // a panic while holding the lock leaves the stats usable
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pinged(duty: Option<NodeDuty>) -> Result<(BTreeSet<XorName>, PeerPing)> {
+        match duty {
+            Some(NodeDuty::SendNodeMsgToNodes {
+                targets,
+                msg:
+                    NodeMsg {
+                        body: NodeMsgBody::PeerPing(ping),
+                        ..
+                    },
+            }) => Ok((targets, ping)),
+            other => panic!("Unexpected duty: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn peers_answering_are_ranked_by_rtt_and_those_lossy_last() -> Result<()> {
+        let probes = PeerProbes::default();
+        let (fast, slow, silent) = (XorName::random(), XorName::random(), XorName::random());
+        let peers: BTreeSet<_> = vec![fast, slow, silent].into_iter().collect();
+        let mut now = Instant::now();
+        for _ in 0..MIN_LOSS_SAMPLES {
+            let (targets, ping) = pinged(probes.probe(peers.clone(), now)?)?;
+            assert_eq!(targets, peers);
+            let pong = ping.clone().pong();
+            assert!(probes.answered(fast, &pong, now + Duration::from_millis(20)));
+            assert!(probes.answered(slow, &pong, now + Duration::from_millis(200)));
+            // answered once only
+            assert!(!probes.answered(slow, &pong, now + Duration::from_millis(200)));
+            now += PING_TIMEOUT;
+        }
+        // the last round of pings is only counted lost on the next
+        let _ = probes.probe(peers.clone(), now)?;
+
+        let latencies = probes.latencies();
+        assert_eq!(latencies[&fast].mean_rtt_ms, 20);
+        assert_eq!(latencies[&slow].answered, MIN_LOSS_SAMPLES as u64);
+        assert_eq!(latencies[&silent].lost, MIN_LOSS_SAMPLES as u64);
+        assert_eq!(latencies[&silent].loss_percentage, 100);
+        assert!(probes.is_lossy(&silent));
+        assert!(!probes.is_lossy(&fast));
+
+        let unprobed = XorName::random();
+        assert_eq!(
+            probes.rank(vec![silent, unprobed, slow, fast]),
+            vec![fast, slow, unprobed, silent]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pongs_answer_the_peer_pinging() -> Result<()> {
+        let probes = PeerProbes::default();
+        let peer = XorName::random();
+        let peers: BTreeSet<_> = vec![peer].into_iter().collect();
+        let (_, ping) = pinged(probes.probe(peers.clone(), Instant::now())?)?;
+        // no other ping while one is pending
+        assert!(probes.probe(peers, Instant::now())?.is_none());
+        // pings and pongs both carry the time of the peer sending them
+        let local = clock::local_secs();
+        assert!(local.saturating_sub(ping.secs) <= 1);
+
+        let pong = ping.pong();
+        match pong.msg(peer) {
+            NodeDuty::SendNodeMsg {
+                msg:
+                    NodeMsg {
+                        body: NodeMsgBody::PeerPong(sent),
+                        ..
+                    },
+                dst: DstLocation::Node(dst),
+            } => {
+                assert_eq!(sent, pong);
+                assert_eq!(dst, peer);
+                assert!(sent.secs >= local);
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use log::debug;
+use sn_data_types::PublicKey;
+use std::{collections::BTreeSet, sync::Mutex};
+use xor_name::{Prefix, XorName};
+
+/// The contact details of our sibling section, as of the split.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SiblingContact {
+    /// Prefix of the sibling.
+    pub prefix: Prefix,
+    /// The latest key of the sibling known to us.
+    pub key: PublicKey,
+    /// The Elders of the sibling.
+    pub elders: BTreeSet<XorName>,
+}
+
+/// Keeps the contact details of our sibling from the split on, as the routing
+/// layer may not know of the sibling right after it, while credits and data
+/// to hand over are to be sent to it. Refreshed from what the routing layer
+/// knows once it does, and dropped once the sibling is no longer ours.
+#[derive(Default)]
+pub(super) struct SiblingCache {
+    contact: Mutex<Option<SiblingContact>>,
+}
+
+impl SiblingCache {
+    pub(super) fn get(&self) -> Option<SiblingContact> {
+        self.lock().clone()
+    }
+
+    pub(super) fn set(&self, contact: SiblingContact) {
+        debug!(
+            "Caching sibling {:?}, with {} elders",
+            contact.prefix,
+            contact.elders.len()
+        );
+        *self.lock() = Some(contact);
+    }
+
+    /// The sibling, if the name is of it.
+    pub(super) fn matching(&self, name: &XorName) -> Option<SiblingContact> {
+        self.get().filter(|contact| contact.prefix.matches(name))
+    }
+
+    /// Updates the sibling with what the routing layer knows of it, if anything,
+    /// or drops it once our prefix changed, and it is no longer our sibling.
+    pub(super) fn refresh(
+        &self,
+        our_prefix: &Prefix,
+        known: Option<(PublicKey, BTreeSet<XorName>)>,
+    ) {
+        let mut contact = self.lock();
+        let cached = match contact.as_mut() {
+            Some(cached) => cached,
+            None => return,
+        };
+        if cached.prefix != our_prefix.sibling() {
+            debug!("Dropping cached sibling {:?}", cached.prefix);
+            *contact = None;
+        } else if let Some((key, elders)) = known {
+            cached.key = key;
+            cached.elders = elders;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<SiblingContact>> {
+        // a panic while holding the lock leaves the contact usable
+        match self.contact.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+
+    #[test]
+    fn sibling_is_refreshed_until_no_longer_ours() {
+        let our_prefix = Prefix::default().pushed(false);
+        let key = PublicKey::from(SecretKey::random().public_key());
+        let cache = SiblingCache::default();
+        cache.set(SiblingContact {
+            prefix: our_prefix.sibling(),
+            key,
+            elders: BTreeSet::new(),
+        });
+        let sibling_name = our_prefix.sibling().substituted_in(XorName::random());
+        assert_eq!(cache.matching(&sibling_name).map(|c| c.key), Some(key));
+        assert!(cache
+            .matching(&our_prefix.substituted_in(XorName::random()))
+            .is_none());
+
+        // kept as is while the routing layer does not know of the sibling
+        cache.refresh(&our_prefix, None);
+        assert_eq!(cache.get().map(|c| c.key), Some(key));
+
+        let new_key = PublicKey::from(SecretKey::random().public_key());
+        let elders: BTreeSet<_> = vec![sibling_name].into_iter().collect();
+        cache.refresh(&our_prefix, Some((new_key, elders.clone())));
+        assert_eq!(
+            cache.get().map(|c| (c.key, c.elders)),
+            Some((new_key, elders))
+        );
+
+        // we split again
+        cache.refresh(&our_prefix.pushed(true), None);
+        assert!(cache.get().is_none());
+    }
+}
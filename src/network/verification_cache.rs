@@ -0,0 +1,151 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::Serialize;
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Mutex, MutexGuard},
+};
+use xor_name::XorName;
+
+/// Signatures verified beyond this many evict the earliest verified.
+pub(crate) const VERIFICATION_CACHE_SIZE: usize = 4096;
+
+/// The signatures verified as valid, by key, signed bytes and signature, so that
+/// the same signature, e.g. the section signature on a credit proof handled by
+/// several duties, or propagated to us by several Elders, is paired once.
+/// Only valid signatures are kept, the invalid ones are verified every time.
+#[derive(Debug)]
+pub(crate) struct VerificationCache {
+    capacity: usize,
+    verified: Mutex<Verified>,
+}
+
+#[derive(Debug, Default)]
+struct Verified {
+    // in the order verified, to evict the earliest
+    order: VecDeque<XorName>,
+    set: HashSet<XorName>,
+}
+
+impl Default for VerificationCache {
+    fn default() -> Self {
+        Self::new(VERIFICATION_CACHE_SIZE)
+    }
+}
+
+impl VerificationCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            verified: Mutex::new(Verified::default()),
+        }
+    }
+
+    /// Whether the signature by the key over the bytes has been verified as valid.
+    pub(crate) fn contains<K: Serialize, S: Serialize>(
+        &self,
+        key: &K,
+        sig: &S,
+        bytes: &[u8],
+    ) -> bool {
+        match fingerprint(key, sig, bytes) {
+            Some(fingerprint) => self.lock().set.contains(&fingerprint),
+            None => false,
+        }
+    }
+
+    /// Keeps the signature by the key over the bytes as verified.
+    pub(crate) fn insert<K: Serialize, S: Serialize>(&self, key: &K, sig: &S, bytes: &[u8]) {
+        let fingerprint = match fingerprint(key, sig, bytes) {
+            Some(fingerprint) => fingerprint,
+            None => return,
+        };
+        let mut verified = self.lock();
+        if !verified.set.insert(fingerprint) {
+            return;
+        }
+        verified.order.push_back(fingerprint);
+        while verified.order.len() > self.capacity {
+            if let Some(earliest) = verified.order.pop_front() {
+                let _ = verified.set.remove(&earliest);
+            }
+        }
+    }
+
+    /// Verifies the signature by the key over the bytes with `verify`,
+    /// unless it has been verified as valid before.
+    pub(crate) fn verify<K: Serialize, S: Serialize>(
+        &self,
+        key: &K,
+        sig: &S,
+        bytes: &[u8],
+        verify: impl FnOnce() -> bool,
+    ) -> bool {
+        if self.contains(key, sig, bytes) {
+            return true;
+        }
+        let valid = verify();
+        if valid {
+            self.insert(key, sig, bytes);
+        }
+        valid
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Verified> {
+        // a panic while holding the lock leaves the signatures usable
+        match self.verified.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+fn fingerprint<K: Serialize, S: Serialize>(key: &K, sig: &S, bytes: &[u8]) -> Option<XorName> {
+    let key = bincode::serialize(key).ok()?;
+    let sig = bincode::serialize(sig).ok()?;
+    Some(XorName::from_content(&[&key, bytes, &sig]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+
+    #[test]
+    fn verified_signatures_are_kept_up_to_capacity() {
+        let secret_key = SecretKey::random();
+        let key = secret_key.public_key();
+        let signed = |bytes: &[u8]| (bytes.to_vec(), secret_key.sign(bytes));
+        let cache = VerificationCache::new(2);
+
+        let (first, first_sig) = signed(b"first");
+        let mut pairings = 0;
+        for _ in 0..2 {
+            assert!(cache.verify(&key, &first_sig, &first, || {
+                pairings += 1;
+                key.verify(&first_sig, &first)
+            }));
+        }
+        assert_eq!(pairings, 1);
+
+        // keyed by the bytes signed too
+        assert!(!cache.contains(&key, &first_sig, b"other"));
+        // invalid ones are not kept
+        assert!(!cache.verify(&key, &first_sig, b"other", || false));
+        assert!(!cache.contains(&key, &first_sig, b"other"));
+
+        let (second, second_sig) = signed(b"second");
+        let (third, third_sig) = signed(b"third");
+        cache.insert(&key, &second_sig, &second);
+        cache.insert(&key, &third_sig, &third);
+        assert!(!cache.contains(&key, &first_sig, &first));
+        assert!(cache.contains(&key, &second_sig, &second));
+        assert!(cache.contains(&key, &third_sig, &third));
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::convert_to_error_message,
+    node_msg::{ClientMsg, ClientRequest},
+    node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
+    Error, Node, Result,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_messaging::{
+    client::{DataQuery, Error as ErrorMessage, Message, QueryResponse},
+    EndUser, MessageId,
+};
+
+/// Queries of a batch beyond this many are refused.
+pub const MAX_BATCH_QUERIES: usize = 64;
+
+/// The outcome of a query of a batch.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchItem {
+    /// Answered by us, with the response, or the error, to the query.
+    Answered(QueryResponse),
+    /// Passed on to the holders of the data, e.g. the Adults holding a chunk,
+    /// or the section of the data, who respond to the query by its own id.
+    Forwarded(MessageId),
+}
+
+/// The response to a batch of queries, with the outcome of each, in the order queried.
+pub type BatchQueryResponse = Vec<BatchItem>;
+
+/// The id a query of a batch is handled by, and answered by when forwarded.
+pub fn batch_item_id(batch_id: MessageId, index: usize) -> Result<MessageId> {
+    Ok(MessageId::from_content(&(batch_id, index as u64))?)
+}
+
+impl Node {
+    /// Processes independent reads at once, answering those we can in a single response,
+    /// rather than one per read, which reports per read whether it succeeded, or
+    /// was passed on to the holders of the data, to be answered by them.
+    pub(crate) async fn process_read_batch(
+        &mut self,
+        queries: Vec<DataQuery>,
+        id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        if queries.len() > MAX_BATCH_QUERIES {
+            return Err(Error::InvalidOperation(format!(
+                "Batch of {} queries, more than the {} allowed",
+                queries.len(),
+                MAX_BATCH_QUERIES
+            )));
+        }
+        info!("Processing batch of {} reads", queries.len());
+        let mut items = BatchQueryResponse::new();
+        let mut duties = NodeDuties::new();
+        for (index, query) in queries.into_iter().enumerate() {
+            let item_id = batch_item_id(id, index)?;
+            // client requests are not read as data, but sent on their own
+            if ClientRequest::from_query(&query).is_some() {
+                items.push(BatchItem::Answered(
+                    query.error(ErrorMessage::InvalidOperation),
+                ));
+                continue;
+            }
+            let item = match self.process_read(query.clone(), item_id, origin).await {
+                Ok(read) => match read.as_slice() {
+                    [NodeDuty::Send(OutgoingMsg {
+                        msg: Message::QueryResponse { response, .. },
+                        ..
+                    })] => BatchItem::Answered(response.clone()),
+                    _ => {
+                        duties.extend(read);
+                        BatchItem::Forwarded(item_id)
+                    }
+                },
+                Err(error) => BatchItem::Answered(query.error(
+                    convert_to_error_message(error).unwrap_or(ErrorMessage::InvalidOperation),
+                )),
+            };
+            items.push(item);
+        }
+        duties.push(NodeDuty::Send(
+            ClientMsg::BatchResponse(items).msg(id, origin)?,
+        ));
+        Ok(duties)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use xor_name::XorName;
+
+    #[test]
+    fn items_of_a_batch_have_distinct_ids() -> Result<()> {
+        let batch_id = MessageId(XorName::random());
+        let first = batch_item_id(batch_id, 0)?;
+        assert_eq!(first, batch_item_id(batch_id, 0)?);
+        assert_ne!(first, batch_item_id(batch_id, 1)?);
+        assert_ne!(first, batch_id);
+        Ok(())
+    }
+}
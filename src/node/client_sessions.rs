@@ -0,0 +1,129 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::ClientMsg,
+    node_ops::{NodeDuties, NodeDuty},
+    Node, Result,
+};
+use bls::PublicKeySet;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_messaging::{EndUser, MessageId};
+use sn_routing::SectionChain;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use xor_name::{Prefix, XorName};
+
+/// Clients not heard from in this long are no longer taken to be connected to us.
+pub(crate) const CLIENT_SESSION: Duration = Duration::from_secs(10 * 60);
+/// Clients kept track of at most, those heard from the longest ago dropped beyond.
+pub(crate) const MAX_CLIENT_SESSIONS: usize = 10_000;
+
+/// The section info pushed to the connected clients when our Elders changed,
+/// so that they keep working across churn, rather than learning of it by
+/// their next request failing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionUpdate {
+    /// Prefix of our section.
+    pub prefix: Prefix,
+    /// The new key set of our section.
+    pub pk_set: PublicKeySet,
+    /// Our new Elders, by name.
+    pub elders: BTreeMap<XorName, SocketAddr>,
+    /// The end of our section chain, proving the new key by the one prior.
+    pub proof: SectionChain,
+}
+
+/// The clients connected to us, as of when we last heard from them.
+#[derive(Default)]
+pub(crate) struct ClientSessions {
+    clients: HashMap<EndUser, Instant>,
+}
+
+impl ClientSessions {
+    pub(crate) fn seen(&mut self, client: EndUser, now: Instant) {
+        let _ = self.clients.insert(client, now);
+        if self.clients.len() > MAX_CLIENT_SESSIONS {
+            self.expire(now);
+        }
+        if self.clients.len() > MAX_CLIENT_SESSIONS {
+            let earliest = self
+                .clients
+                .iter()
+                .min_by_key(|(_, seen)| **seen)
+                .map(|(client, _)| *client);
+            if let Some(earliest) = earliest {
+                let _ = self.clients.remove(&earliest);
+            }
+        }
+    }
+
+    /// The clients heard from within the session, dropping the others.
+    pub(crate) fn connected(&mut self, now: Instant) -> Vec<EndUser> {
+        self.expire(now);
+        self.clients.keys().copied().collect()
+    }
+
+    fn expire(&mut self, now: Instant) {
+        self.clients
+            .retain(|_, seen| now.saturating_duration_since(*seen) < CLIENT_SESSION);
+    }
+}
+
+impl Node {
+    /// Pushes our new section info to the clients connected to us.
+    pub(crate) async fn push_section_update(&mut self, msg_id: MessageId) -> Result<NodeDuties> {
+        let clients = self.client_sessions.connected(Instant::now());
+        if clients.is_empty() {
+            return Ok(vec![]);
+        }
+        let update = SectionUpdate {
+            prefix: self.network_api.our_prefix().await,
+            pk_set: self.network_api.our_public_key_set().await?,
+            elders: self
+                .network_api
+                .our_elder_addresses()
+                .await
+                .into_iter()
+                .collect(),
+            proof: self.network_api.section_chain().await.truncate(2),
+        };
+        info!(
+            "Pushing our new Elders to {} connected clients",
+            clients.len()
+        );
+        let update = ClientMsg::SectionUpdate(update);
+        clients
+            .into_iter()
+            .map(|client| Ok(NodeDuty::Send(update.msg(msg_id, client)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+
+    #[test]
+    fn clients_not_heard_from_are_dropped() {
+        let client = || EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let (first, second) = (client(), client());
+        let mut sessions = ClientSessions::default();
+        let now = Instant::now();
+
+        sessions.seen(first, now);
+        sessions.seen(second, now + CLIENT_SESSION);
+        assert_eq!(sessions.connected(now + CLIENT_SESSION), vec![second]);
+    }
+}
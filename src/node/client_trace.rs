@@ -0,0 +1,105 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{event_mapping::MsgContext, Error};
+use log::{debug, warn};
+use sn_messaging::{EndUser, MessageId, SrcLocation};
+use std::time::Instant;
+
+/// The token a client traces its msg by, being the id it gave the msg.
+/// The id is echoed in all responses to the msg, as their correlation id,
+/// so a client can pick it, e.g. by `MessageId::from_content` of its own
+/// trace id, to correlate its requests with the logs of the node.
+pub(crate) fn trace_token(id: &MessageId) -> String {
+    hex::encode(id.0 .0)
+}
+
+/// Logs the handling of a msg from a client, and the duties resulting
+/// from it, tagged with its trace token.
+pub(crate) struct ClientTrace {
+    token: String,
+    origin: EndUser,
+    started: Instant,
+    duties: usize,
+}
+
+impl ClientTrace {
+    /// Starts tracing the msg, if it is from a client.
+    pub(crate) fn of(ctx: Option<&MsgContext>) -> Option<Self> {
+        let (msg, origin) = match ctx? {
+            MsgContext::Msg {
+                msg,
+                src: SrcLocation::EndUser(origin),
+            } => (msg, *origin),
+            _ => return None,
+        };
+        let token = trace_token(&msg.id());
+        debug!("[trace {}] Handling msg from {:?}", token, origin);
+        Some(Self {
+            token,
+            origin,
+            started: Instant::now(),
+            duties: 0,
+        })
+    }
+
+    pub(crate) fn duty(&mut self, name: &str) {
+        self.duties += 1;
+        debug!("[trace {}] Handling duty {}", self.token, name);
+    }
+
+    pub(crate) fn error(&self, error: &Error) {
+        warn!("[trace {}] Failed: {}", self.token, error);
+    }
+}
+
+impl Drop for ClientTrace {
+    fn drop(&mut self) {
+        debug!(
+            "[trace {}] Handled msg from {:?} in {} duties, after {:?}",
+            self.token,
+            self.origin,
+            self.duties,
+            self.started.elapsed()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+    use sn_messaging::client::{Message, Query, TransferQuery};
+    use xor_name::XorName;
+
+    #[test]
+    fn only_msgs_from_clients_are_traced_by_their_id() {
+        let client = PublicKey::from(SecretKey::random().public_key());
+        let id = MessageId(XorName([7; 32]));
+        let msg = Message::Query {
+            query: Query::Transfer(TransferQuery::GetBalance(client)),
+            id,
+            target_section_pk: None,
+        };
+
+        let from_client = MsgContext::Msg {
+            msg: msg.clone(),
+            src: SrcLocation::EndUser(EndUser::AllClients(client)),
+        };
+        let trace = ClientTrace::of(Some(&from_client)).map(|trace| trace.token.clone());
+        assert_eq!(trace, Some("07".repeat(32)));
+
+        let from_node = MsgContext::Msg {
+            msg,
+            src: SrcLocation::Node(XorName::random()),
+        };
+        assert!(ClientTrace::of(Some(&from_node)).is_none());
+        assert!(ClientTrace::of(None).is_none());
+    }
+}
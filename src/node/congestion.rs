@@ -0,0 +1,121 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{node_msg::ClientMsg, node_ops::NodeDuty, Node, Result};
+use serde::{Deserialize, Serialize};
+use sn_messaging::{EndUser, MessageId};
+use std::{convert::TryFrom, time::Duration};
+
+/// New client cmds are refused as busy once this much work is queued at the node.
+pub const MAX_QUEUE_DEPTH: usize = 512;
+// The time each item of work queued is expected to take us.
+const QUEUED_ITEM_TIME: Duration = Duration::from_millis(20);
+const MIN_RETRY_AFTER: Duration = Duration::from_secs(1);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The answer to a client cmd refused as we are congested, of how long the client
+/// is to back off before sending it again, rather than the cmd being left unanswered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Busy {
+    /// The time to wait before retrying, for the work queued at the node to be done.
+    pub retry_after: Duration,
+}
+
+impl Busy {
+    /// Whether we are busy with this much work queued, and if so,
+    /// for about the time it takes us to work through the queue.
+    pub(crate) fn at_depth(depth: usize) -> Option<Self> {
+        if depth < MAX_QUEUE_DEPTH {
+            return None;
+        }
+        let depth = u32::try_from(depth).unwrap_or(u32::MAX);
+        let retry_after = QUEUED_ITEM_TIME
+            .checked_mul(depth)
+            .unwrap_or(MAX_RETRY_AFTER)
+            .clamp(MIN_RETRY_AFTER, MAX_RETRY_AFTER);
+        Some(Self { retry_after })
+    }
+
+    /// The correlation id of the answer, derived from the id of the cmd, rather than
+    /// being it, so that clients not expecting it take the error sent along for the response.
+    pub fn correlation_id(cmd_id: MessageId) -> MessageId {
+        MessageId::in_response_to(&cmd_id)
+    }
+
+    /// The msg answering the client.
+    pub(crate) fn msg(&self, cmd_id: MessageId, origin: EndUser) -> Result<NodeDuty> {
+        let correlation_id = Self::correlation_id(cmd_id);
+        Ok(NodeDuty::Send(
+            ClientMsg::Busy(*self).msg(correlation_id, origin)?,
+        ))
+    }
+}
+
+impl Node {
+    /// The work queued at the node: responses to clients held back by shaping,
+    /// queries forwarded and not yet answered, and chunks being replicated.
+    pub(crate) fn queue_depth(&self) -> usize {
+        self.egress.held_back()
+            + self.pending_queries.stats().pending
+            + self.pending_work.replicating()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::node_ops::OutgoingMsg;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+    use sn_messaging::client::Message;
+
+    #[test]
+    fn retry_after_grows_with_the_queue() {
+        assert_eq!(Busy::at_depth(0), None);
+        assert_eq!(Busy::at_depth(MAX_QUEUE_DEPTH - 1), None);
+
+        let busy = Busy::at_depth(MAX_QUEUE_DEPTH).map(|busy| busy.retry_after);
+        let busier = Busy::at_depth(2 * MAX_QUEUE_DEPTH).map(|busy| busy.retry_after);
+        assert!(busy >= Some(MIN_RETRY_AFTER));
+        assert!(busier > busy);
+        // and is bounded, however deep the queue
+        assert_eq!(
+            Busy::at_depth(usize::MAX).map(|busy| busy.retry_after),
+            Some(MAX_RETRY_AFTER)
+        );
+    }
+
+    #[test]
+    fn answer_is_not_taken_for_the_response_to_the_cmd() -> Result<()> {
+        let busy = Busy {
+            retry_after: Duration::from_secs(3),
+        };
+        let cmd_id = MessageId::new();
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let msg = match busy.msg(cmd_id, origin)? {
+            NodeDuty::Send(OutgoingMsg { msg, .. }) => msg,
+            duty => panic!("Unexpected duty: {:?}", duty),
+        };
+        match msg {
+            Message::QueryResponse {
+                response,
+                correlation_id,
+                ..
+            } => {
+                assert_ne!(correlation_id, cmd_id);
+                assert_eq!(correlation_id, Busy::correlation_id(cmd_id));
+                assert_eq!(
+                    ClientMsg::from_response(&response).transpose()?,
+                    Some(ClientMsg::Busy(busy))
+                );
+            }
+            msg => panic!("Unexpected msg: {:?}", msg),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::events::NodeEvent;
+use crate::{node_ops::NodeDuties, Node, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{ActorHistory, BlobAddress, PublicKey};
+use std::{collections::BTreeSet, path::PathBuf};
+use xor_name::XorName;
+
+/// The earnings records are exported to this file under the root dir.
+const EARNINGS_EXPORT_FILE: &str = "decommission_earnings.json";
+
+/// The earnings of the node, as exported when decommissioned.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EarningsRecord {
+    /// Name of the node.
+    pub node_name: XorName,
+    /// The key the node received its rewards to.
+    pub reward_key: PublicKey,
+    /// Age of the node.
+    pub age: u8,
+    /// Secs since the unix epoch, in network time, when exported.
+    pub exported_at: u64,
+    /// The history of the reward wallet, if held by our section while we are an Elder.
+    pub reward_wallet: Option<ActorHistory>,
+}
+
+/// The progress of decommissioning the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecommissionStatus {
+    /// Chunks held, yet to be copied by other holders.
+    pub chunks_pending: usize,
+    /// Chunks copied by other holders.
+    pub chunks_handed_over: usize,
+    /// The file the earnings records were exported to.
+    pub earnings_export: PathBuf,
+    /// Whether the node is safe to destroy, no data being held by it alone.
+    pub safe_to_destroy: bool,
+}
+
+/// The chunks still to be handed over, before the node is safe to destroy.
+pub(crate) struct Decommission {
+    pending: BTreeSet<BlobAddress>,
+    handed_over: usize,
+    earnings_export: PathBuf,
+}
+
+impl Decommission {
+    fn new(chunks: Vec<BlobAddress>, earnings_export: PathBuf) -> Self {
+        Self {
+            pending: chunks.into_iter().collect(),
+            handed_over: 0,
+            earnings_export,
+        }
+    }
+
+    /// A new holder copied the chunk from us. Returns whether
+    /// this was the last chunk to be handed over.
+    fn handed_over(&mut self, address: &BlobAddress) -> bool {
+        if !self.pending.remove(address) {
+            return false;
+        }
+        self.handed_over += 1;
+        self.pending.is_empty()
+    }
+
+    fn status(&self) -> DecommissionStatus {
+        DecommissionStatus {
+            chunks_pending: self.pending.len(),
+            chunks_handed_over: self.handed_over,
+            earnings_export: self.earnings_export.clone(),
+            safe_to_destroy: self.pending.is_empty(),
+        }
+    }
+}
+
+impl Node {
+    /// Starts decommissioning the node: data writes are refused from now on,
+    /// the earnings records are exported, and the chunks held are tracked
+    /// until copied by other holders, after which the node is safe to destroy.
+    pub(crate) async fn decommission(&mut self) -> Result<NodeDuties> {
+        if self.decommission.is_some() {
+            info!("Already decommissioning");
+            return Ok(vec![]);
+        }
+        self.maintenance_mode = true;
+        let earnings_export = self.export_earnings().await?;
+        let chunks = self
+            .role
+            .chunks()
+            .map(|chunks| chunks.keys())
+            .unwrap_or_default();
+        info!(
+            "Decommissioning, with {} chunks to hand over, and earnings exported to {:?}",
+            chunks.len(),
+            earnings_export
+        );
+        let decommission = Decommission::new(chunks, earnings_export);
+        if decommission.pending.is_empty() {
+            self.events.emit(NodeEvent::Decommissioned {
+                chunks_handed_over: 0,
+            });
+        }
+        self.decommission = Some(decommission);
+        // the Elders copy the chunks to other holders, and deregister
+        // our reward wallet, on `NodeDuty::ProcessDecommission`
+        Ok(vec![])
+    }
+
+    async fn export_earnings(&self) -> Result<PathBuf> {
+        let reward_key = self.node_info.reward_key;
+        let record = EarningsRecord {
+            node_name: self.node_info.node_name,
+            reward_key,
+            age: self.network_api.age().await,
+            exported_at: self.network_api.clock().now_secs(),
+            reward_wallet: self
+                .role
+                .transfers()
+                .ok()
+                .and_then(|transfers| transfers.wallet_history(reward_key)),
+        };
+        let path = self.node_info.path().join(EARNINGS_EXPORT_FILE);
+        std::fs::write(&path, serde_json::to_vec_pretty(&record)?)?;
+        Ok(path)
+    }
+
+    /// A new holder copied the chunk from us, which is
+    /// thus handed over, if we are being decommissioned.
+    pub(crate) fn chunk_handed_over(&mut self, address: &BlobAddress) {
+        let decommission = match &mut self.decommission {
+            Some(decommission) => decommission,
+            None => return,
+        };
+        if decommission.handed_over(address) {
+            info!("All chunks handed over, the node is safe to destroy");
+            self.events.emit(NodeEvent::Decommissioned {
+                chunks_handed_over: decommission.handed_over,
+            });
+        }
+    }
+
+    /// The progress of decommissioning the node, if started.
+    pub fn decommission_status(&self) -> Option<DecommissionStatus> {
+        self.decommission.as_ref().map(Decommission::status)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn node_is_safe_to_destroy_once_all_chunks_are_handed_over() {
+        let first = BlobAddress::Public(XorName::random());
+        let second = BlobAddress::Private(XorName::random());
+        let mut decommission = Decommission::new(vec![first, second], PathBuf::new());
+        assert!(!decommission.status().safe_to_destroy);
+
+        assert!(!decommission.handed_over(&BlobAddress::Public(XorName::random())));
+        assert!(!decommission.handed_over(&first));
+        // copied again, by another new holder
+        assert!(!decommission.handed_over(&first));
+        assert!(decommission.handed_over(&second));
+
+        let status = decommission.status();
+        assert_eq!(status.chunks_pending, 0);
+        assert_eq!(status.chunks_handed_over, 2);
+        assert!(status.safe_to_destroy);
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::events::NodeEvent;
+use crate::{
+    event_mapping::MsgContext,
+    node_ops::{NodeDuty, METADATA, SECTION_FUNDS, TRANSFERS},
+    Error, Node, Result,
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sn_messaging::SrcLocation;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// Why a duty triggered by a msg was refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DutyRefusal {
+    /// The duty is for Elders, and the node is not one.
+    NotAnElder,
+    /// The duty is to be triggered by Elders, and the msg is not from one.
+    NotFromAnElder,
+}
+
+impl Display for DutyRefusal {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::NotAnElder => write!(formatter, "we are not an Elder"),
+            Self::NotFromAnElder => write!(formatter, "not sent by an Elder"),
+        }
+    }
+}
+
+/// The duties refused since the node started, by duty and refusal.
+pub type RefusedDuties = BTreeMap<String, BTreeMap<DutyRefusal, u64>>;
+
+/// Counts the refused duties.
+#[derive(Default)]
+pub(crate) struct DutyAudit {
+    refused: RefusedDuties,
+}
+
+impl DutyAudit {
+    pub(crate) fn record(&mut self, duty: &str, refusal: DutyRefusal) {
+        *self
+            .refused
+            .entry(duty.to_string())
+            .or_default()
+            .entry(refusal)
+            .or_default() += 1;
+    }
+
+    pub(crate) fn refused(&self) -> RefusedDuties {
+        self.refused.clone()
+    }
+}
+
+/// Whether the duty is only to be triggered by msgs from Elders,
+/// of our own or of other sections.
+fn requires_elder_src(duty: &NodeDuty) -> bool {
+    matches!(
+        duty,
+        NodeDuty::ReceiveRewardProposal(_)
+            | NodeDuty::ReceiveRewardAccumulation(_)
+            | NodeDuty::GetTransferReplicaEvents { .. }
+            | NodeDuty::RecordWriteAck { .. }
+            | NodeDuty::SetWriteAckLevel { .. }
+            | NodeDuty::ProcessMetadataExport { .. }
+            | NodeDuty::AnswerMetadataCatchUp { .. }
+            | NodeDuty::CatchUpOnMetadata { .. }
+            | NodeDuty::ImportMetadataRange { .. }
+            | NodeDuty::TakeOverPayments { .. }
+            | NodeDuty::SynchState { .. }
+            | NodeDuty::SetMaxChunkSize { .. }
+            | NodeDuty::SetSectionParams { .. }
+    )
+}
+
+/// The authority of the msg being handled, checked against each duty resulting from it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MsgAuthority {
+    src: SrcLocation,
+    from_elder: bool,
+}
+
+impl Node {
+    /// The authority of the msg, if the duties are triggered by one.
+    pub(crate) async fn msg_authority(&self, ctx: Option<&MsgContext>) -> Option<MsgAuthority> {
+        let src = match ctx {
+            Some(MsgContext::Msg { src, .. })
+            | Some(MsgContext::Bytes { src, .. })
+            | Some(MsgContext::Node { src, .. }) => *src,
+            // not triggered by a msg
+            None => return None,
+        };
+        Some(MsgAuthority {
+            src,
+            from_elder: self.is_elder_src(&src).await,
+        })
+    }
+
+    /// Asserts that a duty triggered by the msg, directly or as the result of other
+    /// duties, is one we have the authority for, as an Elder, and that the msg has the
+    /// authority to trigger it. Duties refused are counted, logged and reported to
+    /// subscribers.
+    pub(crate) fn audit_duty(&mut self, duty: &NodeDuty, msg: &MsgAuthority) -> Result<()> {
+        let holds_subsystem = match duty.context().subsystem {
+            TRANSFERS | METADATA | SECTION_FUNDS => self.role.is_elder(),
+            _ => true,
+        };
+        let refusal = if !holds_subsystem {
+            DutyRefusal::NotAnElder
+        } else if requires_elder_src(duty) && !msg.from_elder {
+            DutyRefusal::NotFromAnElder
+        } else {
+            return Ok(());
+        };
+        warn!(
+            "Refusing duty {} from {:?}, as {}",
+            duty.name(),
+            msg.src,
+            refusal
+        );
+        self.duty_audit.record(duty.name(), refusal);
+        self.events.emit(NodeEvent::DutyRefused {
+            duty: duty.name().to_string(),
+            origin: msg.src,
+            refusal,
+        });
+        Err(Error::DutyRefused(refusal))
+    }
+
+    async fn is_elder_src(&self, src: &SrcLocation) -> bool {
+        match src {
+            // signed by the section, as accumulated by routing
+            SrcLocation::Section(_) => true,
+            SrcLocation::Node(name) => self.network_api.is_known_elder(name).await,
+            SrcLocation::EndUser(_) => false,
+        }
+    }
+
+    /// The duties refused since the node started.
+    pub fn refused_duties(&self) -> RefusedDuties {
+        self.duty_audit.refused()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_messaging::MessageId;
+    use xor_name::XorName;
+
+    #[test]
+    fn refused_duties_are_counted_by_refusal() {
+        let mut audit = DutyAudit::default();
+        audit.record("GetBalance", DutyRefusal::NotAnElder);
+        audit.record("GetBalance", DutyRefusal::NotAnElder);
+        audit.record("SynchState", DutyRefusal::NotFromAnElder);
+
+        let refused = audit.refused();
+        assert_eq!(refused["GetBalance"][&DutyRefusal::NotAnElder], 2);
+        assert_eq!(refused["SynchState"][&DutyRefusal::NotFromAnElder], 1);
+
+        assert!(requires_elder_src(&NodeDuty::SynchState {
+            node_rewards: BTreeMap::new(),
+            user_wallets: BTreeMap::new(),
+            msg_id: MessageId::new(),
+            origin: SrcLocation::Node(XorName::random()),
+        }));
+        assert!(requires_elder_src(&NodeDuty::SetMaxChunkSize {
+            max_chunk_size: 1024,
+            elder: XorName::random(),
+        }));
+        assert!(!requires_elder_src(&NodeDuty::NoOp));
+    }
+}
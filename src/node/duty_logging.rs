@@ -0,0 +1,157 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Filters of the duties logged in full, set by the operator while the node runs, so
+//! that a single flow, e.g. the rewards, can be followed without the logs of all others.
+
+use crate::{node_ops::DutyContext, Node};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sn_messaging::SrcLocation;
+
+/// A filter of the duties to log in full. A duty passes the filter when it meets each of
+/// the criteria set. Patterns match the whole text, ignoring case, with `*` matching any
+/// run of characters, e.g. `Reward*`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DutyLogFilter {
+    /// Pattern of the name of the duty, e.g. `ProcessRead`, or of the subsystem
+    /// handling it, e.g. `section funds`.
+    pub duty: Option<String>,
+    /// Pattern of the id of the msg leading to the duty, in hex.
+    pub msg_id: Option<String>,
+    /// Pattern of the origin of the msg leading to the duty, in hex, i.e. the name
+    /// of the node or section, or the key of the client.
+    pub origin: Option<String>,
+}
+
+impl DutyLogFilter {
+    /// Whether the duty of the context passes the filter.
+    pub(crate) fn passes(&self, context: &DutyContext) -> bool {
+        let duty = match &self.duty {
+            Some(pattern) => matches(pattern, context.duty) || matches(pattern, context.subsystem),
+            None => true,
+        };
+        let msg_id = match (&self.msg_id, context.msg_id) {
+            (Some(pattern), Some(id)) => matches(pattern, &format!("{:x}", id.0)),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        let origin = match (&self.origin, &context.origin) {
+            (Some(pattern), Some(origin)) => matches(pattern, &hex_of(origin)),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        duty && msg_id && origin
+    }
+}
+
+fn hex_of(origin: &SrcLocation) -> String {
+    match origin {
+        SrcLocation::EndUser(user) => format!("{:x}", user.id()),
+        SrcLocation::Node(name) | SrcLocation::Section(name) => format!("{:x}", name),
+    }
+}
+
+// Whether the pattern matches the whole text, ignoring case, `*` matching any run of characters.
+fn matches(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.to_lowercase(), text.to_lowercase());
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<_> = parts.collect();
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        // no wildcard, so the text must be the pattern
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl Node {
+    /// Logs in full only the duties passing any of the filters, the others only by name
+    /// at trace level. All duties are logged in full when there are no filters.
+    pub(crate) fn set_duty_log_filters(&mut self, filters: Vec<DutyLogFilter>) {
+        info!("Logging in full the duties passing: {:?}", filters);
+        self.duty_log_filters = filters;
+    }
+
+    /// The filters of the duties logged in full.
+    pub fn duty_log_filters(&self) -> Vec<DutyLogFilter> {
+        self.duty_log_filters.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::node_ops::ErrorResponse;
+    use sn_messaging::MessageId;
+    use xor_name::XorName;
+
+    fn context(duty: &'static str, subsystem: &'static str, origin: XorName) -> DutyContext {
+        DutyContext {
+            duty,
+            subsystem,
+            msg_id: Some(MessageId(XorName([0xab; 32]))),
+            origin: Some(SrcLocation::Node(origin)),
+            response: ErrorResponse::None,
+        }
+    }
+
+    #[test]
+    fn patterns_match_the_whole_text() {
+        assert!(matches("Reward*", "RewardPayoutValidated"));
+        assert!(matches("*payout*", "RewardPayoutValidated"));
+        assert!(matches("r*w*d", "ReWarD"));
+        assert!(matches("*", ""));
+        assert!(!matches("Reward", "RewardPayoutValidated"));
+        assert!(!matches("*Read", "ReadChunk"));
+        assert!(!matches("a*b*b", "ab"));
+    }
+
+    #[test]
+    fn duties_pass_filters_meeting_each_criterion() {
+        let node = XorName([0x12; 32]);
+        let reward = context("ReceiveRewardProposal", "section funds", node);
+        let read = context("ReadChunk", "chunks", node);
+
+        let by_duty = DutyLogFilter {
+            duty: Some("*Reward*".to_string()),
+            ..Default::default()
+        };
+        assert!(by_duty.passes(&reward));
+        assert!(!by_duty.passes(&read));
+
+        let by_subsystem = DutyLogFilter {
+            duty: Some("section funds".to_string()),
+            ..Default::default()
+        };
+        assert!(by_subsystem.passes(&reward));
+
+        let by_msg_and_origin = DutyLogFilter {
+            duty: None,
+            msg_id: Some("abab*".to_string()),
+            origin: Some("1212*".to_string()),
+        };
+        assert!(by_msg_and_origin.passes(&read));
+        let other_origin = DutyLogFilter {
+            origin: Some("ffff*".to_string()),
+            ..by_msg_and_origin
+        };
+        assert!(!other_origin.passes(&read));
+    }
+}
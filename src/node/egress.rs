@@ -0,0 +1,326 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{node_ops::OutgoingMsg, Result};
+use log::{debug, warn};
+use sn_data_types::PublicKey;
+use sn_messaging::DstLocation;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Responses to a client beyond this many waiting to be sent are dropped,
+/// for the client to retry them.
+const MAX_QUEUED_PER_CLIENT: usize = 256;
+
+/// Counters of the responses to clients, and of those held back by shaping.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EgressStats {
+    /// Responses sent.
+    pub sent_msgs: u64,
+    /// Bytes of responses sent.
+    pub sent_bytes: u64,
+    /// Responses held back for their client having used up its share.
+    pub delayed_msgs: u64,
+    /// Responses dropped for too many being held back for their client.
+    pub dropped_msgs: u64,
+}
+
+/// Refills at the rate given, up to a second worth of bytes.
+struct TokenBucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, now: Instant) -> Self {
+        Self {
+            tokens: rate as f64,
+            refilled: now,
+        }
+    }
+
+    fn refill(&mut self, rate: u64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate as f64).min(rate as f64);
+        self.refilled = now;
+    }
+
+    fn is_full(&self, rate: u64) -> bool {
+        self.tokens >= rate as f64
+    }
+
+    /// Time until the bucket holds the bytes, or is full, whichever is first,
+    /// so that a msg larger than the bucket is still sent once it is full.
+    fn wait(&self, bytes: usize, rate: u64) -> Duration {
+        let needed = (bytes as f64).min(rate as f64) - self.tokens;
+        // not waiting on a fraction of a byte
+        if needed < 1.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_micros((needed * 1_000_000.0 / rate as f64).ceil() as u64)
+        }
+    }
+}
+
+struct HeldMsg {
+    msg: OutgoingMsg,
+    bytes: usize,
+    // whether counted as delayed
+    delayed: bool,
+}
+
+struct ClientQueue {
+    bucket: TokenBucket,
+    msgs: VecDeque<HeldMsg>,
+}
+
+/// Shapes the responses to clients, so that a single client can not take up
+/// the uplink of the node. Each client is given a token bucket, refilling at
+/// a fair share of the total rate among the clients currently served, and
+/// at most at the rate per client. Clients are served in turns.
+pub(crate) struct EgressShaper {
+    // bytes per second for all clients, shaping is off if none
+    rate: Option<u64>,
+    // bytes per second for any single client
+    client_rate: u64,
+    clients: BTreeMap<PublicKey, ClientQueue>,
+    // the order clients with held back responses are served in
+    turns: VecDeque<PublicKey>,
+    stats: EgressStats,
+}
+
+impl EgressShaper {
+    pub(crate) fn new(rate: Option<u64>, client_rate: Option<u64>) -> Self {
+        let rate = rate.filter(|rate| *rate > 0);
+        let client_rate = match (rate, client_rate) {
+            (Some(rate), Some(client_rate)) => client_rate.clamp(1, rate),
+            (Some(rate), None) => rate,
+            (None, _) => 0,
+        };
+        Self {
+            rate,
+            client_rate,
+            clients: BTreeMap::new(),
+            turns: VecDeque::new(),
+            stats: EgressStats::default(),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> EgressStats {
+        self.stats
+    }
+
+    /// The number of responses held back.
+    pub(crate) fn held_back(&self) -> usize {
+        self.clients.values().map(|queue| queue.msgs.len()).sum()
+    }
+
+    /// The share of the total rate of each client.
+    fn fair_rate(&self) -> u64 {
+        let rate = self.rate.unwrap_or_default();
+        let clients = self.clients.len().max(1) as u64;
+        (rate / clients).min(self.client_rate).max(1)
+    }
+
+    /// Takes in a msg to send. Msgs other than responses to clients,
+    /// and all msgs if shaping is off, are returned to be sent right away.
+    /// Otherwise the msg is sent when returned by `next_ready`.
+    pub(crate) fn enqueue(
+        &mut self,
+        msg: OutgoingMsg,
+        now: Instant,
+    ) -> Result<Option<OutgoingMsg>> {
+        if !matches!(msg.dst, DstLocation::EndUser(_)) {
+            return Ok(Some(msg));
+        }
+        let bytes = msg.msg.serialize()?.len();
+        Ok(self.enqueue_response(msg, bytes, now))
+    }
+
+    fn enqueue_response(
+        &mut self,
+        msg: OutgoingMsg,
+        bytes: usize,
+        now: Instant,
+    ) -> Option<OutgoingMsg> {
+        let client = match (self.rate, &msg.dst) {
+            (Some(_), DstLocation::EndUser(origin)) => *origin.id(),
+            _ => {
+                self.record_sent(bytes);
+                return Some(msg);
+            }
+        };
+        let rate = self.fair_rate();
+        let queue = self.clients.entry(client).or_insert_with(|| ClientQueue {
+            bucket: TokenBucket::new(rate, now),
+            msgs: VecDeque::new(),
+        });
+        if queue.msgs.len() >= MAX_QUEUED_PER_CLIENT {
+            warn!(
+                "Dropping response {:?} to {}, as {} responses are held back for it",
+                msg.id(),
+                client,
+                MAX_QUEUED_PER_CLIENT
+            );
+            self.stats.dropped_msgs += 1;
+            return None;
+        }
+        if queue.msgs.is_empty() {
+            self.turns.push_back(client);
+        }
+        queue.msgs.push_back(HeldMsg {
+            msg,
+            bytes,
+            delayed: false,
+        });
+        None
+    }
+
+    /// The next msg to send, taking turns between the clients whose share allows it.
+    pub(crate) fn next_ready(&mut self, now: Instant) -> Option<OutgoingMsg> {
+        let rate = self.fair_rate();
+        for _ in 0..self.turns.len() {
+            let client = self.turns.pop_front()?;
+            let queue = match self.clients.get_mut(&client) {
+                Some(queue) => queue,
+                None => continue,
+            };
+            queue.bucket.refill(rate, now);
+            let front = match queue.msgs.front_mut() {
+                Some(front) => front,
+                None => continue,
+            };
+            if queue.bucket.wait(front.bytes, rate) > Duration::from_secs(0) {
+                if !front.delayed {
+                    front.delayed = true;
+                    self.stats.delayed_msgs += 1;
+                    debug!(
+                        "Holding back response {:?} to {}, over its share",
+                        front.msg.id(),
+                        client
+                    );
+                }
+                self.turns.push_back(client);
+                continue;
+            }
+            let HeldMsg { msg, bytes, .. } = queue.msgs.pop_front()?;
+            queue.bucket.tokens -= bytes as f64;
+            if !queue.msgs.is_empty() {
+                self.turns.push_back(client);
+            }
+            self.record_sent(bytes);
+            self.forget_idle(rate, now);
+            return Some(msg);
+        }
+        self.forget_idle(rate, now);
+        None
+    }
+
+    /// Time until the next held back msg can be sent, if any is held back.
+    pub(crate) fn wait(&self, now: Instant) -> Option<Duration> {
+        let rate = self.fair_rate();
+        self.clients
+            .values()
+            .filter_map(|queue| {
+                let front = queue.msgs.front()?;
+                let elapsed = now.saturating_duration_since(queue.bucket.refilled);
+                Some(queue.bucket.wait(front.bytes, rate).saturating_sub(elapsed))
+            })
+            .min()
+    }
+
+    // clients with nothing held back, and a full bucket, need not be kept
+    fn forget_idle(&mut self, rate: u64, now: Instant) {
+        self.clients.retain(|_, queue| {
+            queue.bucket.refill(rate, now);
+            !queue.msgs.is_empty() || !queue.bucket.is_full(rate)
+        });
+    }
+
+    fn record_sent(&mut self, bytes: usize) {
+        self.stats.sent_msgs += 1;
+        self.stats.sent_bytes += bytes as u64;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_messaging::{
+        client::{Message, QueryResponse},
+        Aggregation, EndUser, MessageId,
+    };
+
+    fn response_to(client: PublicKey) -> OutgoingMsg {
+        OutgoingMsg {
+            msg: Message::QueryResponse {
+                response: QueryResponse::GetSequenceLastEntry(Ok((0, vec![]))),
+                id: MessageId::new(),
+                correlation_id: MessageId::new(),
+                target_section_pk: None,
+            },
+            section_source: false,
+            dst: DstLocation::EndUser(EndUser::AllClients(client)),
+            aggregation: Aggregation::None,
+        }
+    }
+
+    fn dst_of(msg: &OutgoingMsg) -> PublicKey {
+        match &msg.dst {
+            DstLocation::EndUser(origin) => *origin.id(),
+            _ => panic!("not a response to a client"),
+        }
+    }
+
+    #[test]
+    fn clients_share_the_rate_in_turns() {
+        let greedy = PublicKey::from(SecretKey::random().public_key());
+        let other = PublicKey::from(SecretKey::random().public_key());
+        let mut shaper = EgressShaper::new(Some(1000), None);
+        let now = Instant::now();
+
+        for _ in 0..4 {
+            assert!(shaper
+                .enqueue_response(response_to(greedy), 300, now)
+                .is_none());
+        }
+        assert!(shaper
+            .enqueue_response(response_to(other), 300, now)
+            .is_none());
+
+        // the other client is served before the greedy one is done
+        let sent: Vec<_> = std::iter::from_fn(|| shaper.next_ready(now))
+            .map(|msg| dst_of(&msg))
+            .collect();
+        assert_eq!(sent, vec![greedy, other]);
+        assert_eq!(shaper.stats().delayed_msgs, 1);
+
+        // the rest is sent as the share of the greedy client refills
+        let mut now = now;
+        while let Some(wait) = shaper.wait(now) {
+            assert!(wait > Duration::from_secs(0));
+            now += wait;
+            assert_eq!(shaper.next_ready(now).map(|msg| dst_of(&msg)), Some(greedy));
+        }
+        assert_eq!(shaper.stats().sent_msgs, 5);
+        assert_eq!(shaper.stats().sent_bytes, 1500);
+    }
+
+    #[test]
+    fn msgs_are_not_held_back_when_shaping_is_off() {
+        let client = PublicKey::from(SecretKey::random().public_key());
+        let mut shaper = EgressShaper::new(None, Some(10));
+        assert!(shaper
+            .enqueue_response(response_to(client), 1_000_000, Instant::now())
+            .is_some());
+        assert_eq!(shaper.stats().sent_bytes, 1_000_000);
+    }
+}
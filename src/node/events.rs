@@ -0,0 +1,161 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::duty_audit::DutyRefusal;
+use crate::{metadata::AdultLiveness, transfers::InvariantViolation};
+use sn_data_types::{CreditAgreementProof, CreditId, PublicKey, Token};
+use sn_messaging::SrcLocation;
+use sn_routing::Prefix;
+use std::collections::VecDeque;
+use tokio::sync::broadcast;
+use xor_name::XorName;
+
+/// Events not yet received by a subscriber are dropped beyond this
+/// many, with the subscriber told how many it missed.
+const EVENT_BUFFER: usize = 64;
+/// The rewards seen recently, so that a reward both paid out
+/// and propagated at this node is only announced once.
+const SEEN_REWARDS: usize = 64;
+
+/// Lifecycle events of the node, for applications running it as a library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum NodeEvent {
+    /// The node became an Elder of the section.
+    PromotedToElder {
+        /// Prefix of the section.
+        prefix: Prefix,
+    },
+    /// The node is no longer an Elder.
+    DemotedFromElder,
+    /// The section of the node split.
+    SectionSplit {
+        /// Prefix of the section the node is in after the split.
+        prefix: Prefix,
+    },
+    /// A reward was credited to the reward key of the node.
+    RewardReceived {
+        /// Id of the credit.
+        credit_id: CreditId,
+        /// The amount rewarded.
+        amount: Token,
+    },
+    /// The storage of the node is close to its max capacity.
+    StorageWarning {
+        /// Bytes used.
+        used: u64,
+        /// Bytes the node can store at most.
+        max_capacity: u64,
+    },
+    /// The clock of the node is skewed from the times reported by peers, beyond what
+    /// is tolerated, and is corrected for in network time until fixed by the operator.
+    ClockSkewed {
+        /// Secs the clock is behind the peers, or ahead of them, if negative.
+        behind_secs: i64,
+        /// Peers the clock was judged by.
+        peers: usize,
+    },
+    /// The node was decommissioned, with all the chunks held by it copied by other
+    /// holders, and the earnings records exported, so it is safe to destroy.
+    Decommissioned {
+        /// Chunks copied by other holders.
+        chunks_handed_over: usize,
+    },
+    /// A duty triggered by a msg was refused, for lack of authority.
+    DutyRefused {
+        /// Name of the duty.
+        duty: String,
+        /// The sender of the msg.
+        origin: SrcLocation,
+        /// Why it was refused.
+        refusal: DutyRefusal,
+    },
+    /// One of our Adults stopped answering our queries in time, as Elder,
+    /// and the chunks held by it are being copied to other holders.
+    AdultUnresponsive {
+        /// The Adult, for possible relocation or penalty.
+        adult: XorName,
+        /// Its answers to our queries, by which it was found unresponsive.
+        liveness: AdultLiveness,
+    },
+    /// The wallets held by the replicas of the node breach their invariants.
+    TransferInvariantsViolated {
+        /// The breaches found.
+        violations: Vec<InvariantViolation>,
+    },
+}
+
+/// Broadcasts node events to all subscribers.
+pub(crate) struct NodeEvents {
+    sender: broadcast::Sender<NodeEvent>,
+    seen_rewards: VecDeque<CreditId>,
+}
+
+impl NodeEvents {
+    pub(crate) fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            sender,
+            seen_rewards: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.sender.subscribe()
+    }
+
+    pub(crate) fn emit(&self, event: NodeEvent) {
+        // fails only when there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    /// Emits the credit as a reward, if it is to the reward key and not already emitted.
+    pub(crate) fn emit_if_reward(&mut self, credit: &CreditAgreementProof, reward_key: PublicKey) {
+        if credit.recipient() != reward_key || self.seen_rewards.contains(credit.id()) {
+            return;
+        }
+        if self.seen_rewards.len() == SEEN_REWARDS {
+            let _ = self.seen_rewards.pop_front();
+        }
+        self.seen_rewards.push_back(*credit.id());
+        self.emit(NodeEvent::RewardReceived {
+            credit_id: *credit.id(),
+            amount: credit.amount(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{transfers::test_utils::get_random_genesis, Result};
+    use bls::SecretKey;
+
+    #[test]
+    fn rewards_to_our_key_are_emitted_once() -> Result<()> {
+        let reward_key = PublicKey::from(SecretKey::random().public_key());
+        let other_key = PublicKey::from(SecretKey::random().public_key());
+        let mut events = NodeEvents::new();
+        let mut subscriber = events.subscribe();
+
+        let reward = get_random_genesis(10, reward_key)?;
+        events.emit_if_reward(&reward, reward_key);
+        events.emit_if_reward(&reward, reward_key);
+        events.emit_if_reward(&get_random_genesis(10, other_key)?, reward_key);
+
+        assert_eq!(
+            subscriber.try_recv().ok(),
+            Some(NodeEvent::RewardReceived {
+                credit_id: *reward.id(),
+                amount: Token::from_nano(10),
+            })
+        );
+        assert!(subscriber.try_recv().is_err());
+        Ok(())
+    }
+}
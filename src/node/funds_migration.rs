@@ -0,0 +1,80 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_ops::NodeDuties,
+    section_funds::{FundsExport, SignedFundsExport},
+    Error, Node, Result,
+};
+use log::info;
+use sn_data_types::PublicKey;
+use xor_name::XorName;
+
+impl Node {
+    /// The reward wallets and pending payments of our section, in parts signed by us.
+    pub(crate) async fn export_section_funds(&self) -> Result<Vec<SignedFundsExport>> {
+        let section_funds = self.role.section_funds()?;
+        let key_set = self.network_api.our_public_key_set().await?;
+        let prefix = self.network_api.our_prefix().await;
+        let parts = FundsExport::parts(
+            key_set,
+            prefix,
+            section_funds.node_wallets(),
+            section_funds.payments()?,
+        );
+        let mut signed = vec![];
+        for export in parts {
+            let share = self.network_api.sign_as_elder(&export).await?;
+            signed.push(SignedFundsExport { export, share });
+        }
+        Ok(signed)
+    }
+
+    /// Adds the share of the part of an export, importing the part once signed by enough of the
+    /// Elders of the section exporting. Unless authorised by the operator, the section must be
+    /// ours, as of any key of our chain. The wallets and payments within our prefix are imported.
+    pub(crate) async fn import_section_funds(
+        &mut self,
+        signed: SignedFundsExport,
+        by_operator: bool,
+    ) -> Result<NodeDuties> {
+        let section_key = signed.export.key_set.public_key();
+        if !by_operator && !self.network_api.section_chain().await.has_key(&section_key) {
+            return Err(Error::InvalidFundsExport(format!(
+                "exported by a section of key {:?}, not ours",
+                PublicKey::Bls(section_key)
+            )));
+        }
+        let export = match self.funds_imports.add(signed)? {
+            Some(export) => export,
+            None => return Ok(vec![]),
+        };
+        export.validate()?;
+        let our_prefix = self.network_api.our_prefix().await;
+        let section_funds = self.role.section_funds_mut()?;
+        let mut wallets = 0;
+        for (node_name, (age, wallet)) in export.wallets {
+            if our_prefix.matches(&node_name) {
+                section_funds.set_node_wallet(node_name, wallet, age);
+                wallets += 1;
+            }
+        }
+        let mut payments = 0;
+        for credit in export.payments {
+            if our_prefix.matches(&XorName(*credit.id())) {
+                section_funds.add_payment(credit)?;
+                payments += 1;
+            }
+        }
+        info!(
+            "Imported {} wallets and {} payments of part {} of {} exported by {:?}",
+            wallets, payments, export.part.0, export.part.1, export.prefix
+        );
+        Ok(vec![])
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+use log::info;
+use sn_routing::SectionChain;
+
+/// Verifies that the section chain of the network joined is anchored to the trusted genesis key,
+/// i.e. that our current section key is signed by a chain of keys from it.
+/// Each key of the chain is signed by its parent, which routing verified when receiving it.
+pub(crate) fn verify_section_chain(
+    chain: &SectionChain,
+    trusted_genesis_key: &bls::PublicKey,
+) -> Result<()> {
+    if !chain.check_trust(Some(trusted_genesis_key)) {
+        return Err(Error::UntrustedNetwork(format!(
+            "section chain with root key {:?} and last key {:?} is not anchored to genesis key {:?}",
+            chain.root_key(),
+            chain.last_key(),
+            trusted_genesis_key
+        )));
+    }
+    info!(
+        "Section chain of {} keys, to section key {:?}, is anchored to trusted genesis key {:?}",
+        chain.len(),
+        chain.last_key(),
+        trusted_genesis_key
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils;
+    use bls::SecretKey;
+
+    #[test]
+    fn only_chains_from_the_genesis_key_are_trusted() -> Result<()> {
+        let genesis = SecretKey::random();
+        let section = SecretKey::random();
+        let mut chain = SectionChain::new(genesis.public_key());
+        let signature = genesis.sign(&utils::serialise(&section.public_key())?);
+        chain
+            .insert(&genesis.public_key(), section.public_key(), signature)
+            .map_err(|e| Error::Logic(e.to_string()))?;
+
+        verify_section_chain(&chain, &genesis.public_key())?;
+
+        let imposter = SectionChain::new(SecretKey::random().public_key());
+        assert!(matches!(
+            verify_section_chain(&imposter, &genesis.public_key()),
+            Err(Error::UntrustedNetwork(_))
+        ));
+        Ok(())
+    }
+}
@@ -6,37 +6,50 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::messaging::{send, send_to_nodes};
+use super::{
+    events::NodeEvent,
+    messaging::{forward, send, send_node_msg, send_node_msg_to_nodes, send_to_nodes},
+    open_chunks,
+    role::AdultState,
+    StatePushAck,
+};
 use crate::{
-    chunks::Chunks,
-    metadata::Metadata,
+    capacity::MIN_CHUNK_SIZE,
+    metadata::{ExistenceAttestation, SignedCatchUpPage, CHUNK_COPY_COUNT},
+    node_msg::{ClientMsg, ClientRequest},
     node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
     section_funds::{
-        reward_process::RewardProcess,
         reward_stage::{CreditAccumulation, RewardStage},
-        reward_wallets::RewardWallets,
         Credits, SectionFunds,
     },
-    transfers::Transfers,
+    transfers::{SignedStoreCostEstimate, StoreCostEstimate},
     Error, Node, Result,
 };
 use dashmap::DashMap;
-use log::{debug, info};
-use sn_data_types::{CreditAgreementProof, CreditId, PublicKey, SectionElders, WalletHistory};
+use log::{debug, error, info, warn};
+use sn_data_types::{
+    BlobAddress, CreditAgreementProof, CreditId, PublicKey, SectionElders, WalletHistory,
+};
 use sn_messaging::{
-    client::{Message, NodeCmd, NodeQuery, Query},
-    Aggregation, DstLocation, MessageId,
+    client::{DataQuery, Message, NodeCmd, NodeQuery, Query},
+    Aggregation, DstLocation, EndUser, MessageId, SrcLocation,
 };
-use std::collections::{BTreeMap, VecDeque};
-use xor_name::XorName;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    time::Instant,
+};
+use xor_name::{Prefix, XorName};
 
 impl Node {
     ///
     pub async fn handle(&mut self, duty: NodeDuty) -> Result<NodeDuties> {
-        info!("Handling NodeDuty: {:?}", duty);
         match duty {
             NodeDuty::Genesis => {
                 self.level_up().await?;
+                self.cache_section_elders().await;
+                self.events.emit(NodeEvent::PromotedToElder {
+                    prefix: self.network_api.our_prefix().await,
+                });
                 Ok(vec![])
             }
             NodeDuty::EldersChanged {
@@ -44,16 +57,30 @@ impl Node {
                 our_prefix,
                 newbie,
             } => {
+                let former_elders = self
+                    .section_elders
+                    .as_ref()
+                    .map(|elders| elders.names.clone())
+                    .unwrap_or_default();
+                self.cache_section_elders().await;
+                self.network_api.refresh_sibling().await;
                 if newbie {
                     info!("Promoted to Elder on Churn");
                     self.level_up().await?;
-                    Ok(vec![])
+                    self.events
+                        .emit(NodeEvent::PromotedToElder { prefix: our_prefix });
+                    self.catch_up_on_metadata().await
                 } else {
                     info!("Updating our replicas on Churn");
                     self.update_replicas().await?;
                     let msg_id =
                         MessageId::combine(vec![our_prefix.name(), XorName::from(our_key)]);
-                    Ok(vec![self.push_state(our_prefix, msg_id)])
+                    let mut ops = self
+                        .push_state_to_new_elders(our_prefix, our_key, former_elders)
+                        .await;
+                    ops.extend(self.push_standby_state(our_prefix, msg_id).await);
+                    ops.extend(self.push_section_update(msg_id).await?);
+                    Ok(ops)
                 }
             }
             NodeDuty::SectionSplit {
@@ -62,10 +89,35 @@ impl Node {
                 sibling_key,
                 newbie,
             } => {
+                // the Elders before the split matching the sibling are its Elders
+                let former_elders = self
+                    .section_elders
+                    .as_ref()
+                    .map(|elders| elders.names.clone())
+                    .unwrap_or_default();
+                self.cache_section_elders().await;
+                let sibling_prefix = our_prefix.sibling();
+                let (former_sibling_elders, former_our_elders): (BTreeSet<_>, BTreeSet<_>) =
+                    former_elders
+                        .into_iter()
+                        .partition(|name| sibling_prefix.matches(name));
+                self.network_api
+                    .cache_sibling(sibling_key, former_sibling_elders.clone())
+                    .await;
+                self.events
+                    .emit(NodeEvent::SectionSplit { prefix: our_prefix });
                 if newbie {
                     info!("Beginning split as Newbie");
+                    self.events
+                        .emit(NodeEvent::PromotedToElder { prefix: our_prefix });
                     self.begin_split_as_newbie(our_key, our_prefix).await?;
-                    Ok(vec![])
+                    if former_our_elders.is_empty() {
+                        // none of our Elders hold our records, staged for us by our sibling
+                        let meta_data = self.role.meta_data_mut()?;
+                        Ok(meta_data.begin_import(our_prefix, former_sibling_elders))
+                    } else {
+                        self.catch_up_on_metadata().await
+                    }
                 } else {
                     info!("Beginning split as Oldie");
                     self.begin_split_as_oldie(our_prefix, our_key, sibling_key)
@@ -77,7 +129,7 @@ impl Node {
                 Ok(vec![self.get_section_elders(msg_id, origin).await?])
             }
             NodeDuty::ReceiveRewardProposal(proposal) => {
-                if let Ok((churn_process, _, _)) = self.get_churning_funds() {
+                if let Ok((churn_process, _, _)) = self.role.churning_funds() {
                     info!("Handling Churn proposal as an Elder");
                     Ok(vec![churn_process.receive_churn_proposal(proposal).await?])
                 } else {
@@ -85,8 +137,13 @@ impl Node {
                     Ok(vec![])
                 }
             }
+            NodeDuty::ProcessRewardStatusQuery { id, origin } => {
+                Ok(vec![self.send_reward_status(id, origin).await?])
+            }
             NodeDuty::ReceiveRewardAccumulation(accumulation) => {
-                if let Ok((churn_process, reward_wallets, payments)) = self.get_churning_funds() {
+                let reward_key = self.node_info.reward_key;
+                let our_prefix = self.network_api.our_prefix().await;
+                if let Ok((churn_process, reward_wallets, payments)) = self.role.churning_funds() {
                     let mut ops = vec![
                         churn_process
                             .receive_wallet_accumulation(accumulation)
@@ -95,17 +152,26 @@ impl Node {
 
                     if let RewardStage::Completed(credit_proofs) = churn_process.stage().clone() {
                         let reward_sum = credit_proofs.sum();
-                        ops.extend(Self::propagate_credits(credit_proofs)?);
+                        let our_rewards: Vec<_> = credit_proofs
+                            .values()
+                            .filter(|credit| credit.recipient() == reward_key)
+                            .cloned()
+                            .collect();
+                        ops.extend(Self::propagate_credits(credit_proofs, &our_prefix)?);
                         // update state
-                        self.section_funds = Some(SectionFunds::KeepingNodeWallets {
+                        let section_funds = SectionFunds::KeepingNodeWallets {
                             wallets: reward_wallets.clone(),
                             payments: payments.clone(),
-                        });
+                        };
+                        *self.role.section_funds_mut()? = section_funds;
                         let section_key = &self.network_api.section_public_key().await?;
                         info!(
                             "COMPLETED SPLIT. New section: ({}). Total rewards paid: {}.",
                             section_key, reward_sum
                         );
+                        for credit in &our_rewards {
+                            self.events.emit_if_reward(credit, reward_key);
+                        }
                     }
 
                     Ok(ops)
@@ -123,7 +189,7 @@ impl Node {
                 origin,
             } => {
                 let members = self.network_api.our_members().await;
-                let rewards = self.get_section_funds()?;
+                let rewards = self.role.section_funds_mut()?;
                 if let Some(age) = members.get(&node_id) {
                     rewards.set_node_wallet(node_id, wallet_id, *age);
                     Ok(vec![])
@@ -144,37 +210,122 @@ impl Node {
             } => Ok(vec![]),
             NodeDuty::ProcessLostMember { name, age } => {
                 info!("Member Lost: {:?}", name);
-                let rewards = self.get_section_funds()?;
+                let rewards = self.role.section_funds_mut()?;
                 rewards.remove_node_wallet(name);
 
-                let metadata = self.get_metadata()?;
+                let metadata = self.role.meta_data_mut()?;
                 Ok(metadata.trigger_chunk_replication(name).await?)
             }
+            NodeDuty::ProcessDecommission { node } => {
+                info!("Member decommissioned: {:?}", node);
+                let rewards = self.role.section_funds_mut()?;
+                rewards.remove_node_wallet(node);
+
+                let metadata = self.role.meta_data_mut()?;
+                Ok(metadata.release_chunk_holder(node).await?)
+            }
+            NodeDuty::ProcessChunkOffload { holder, addresses } => {
+                info!("Holder {:?} offloading {} chunks", holder, addresses.len());
+                let metadata = self.role.meta_data_mut()?;
+                Ok(metadata.release_chunks(holder, addresses).await?)
+            }
             //
             // ---------- Levelling --------------
             NodeDuty::SynchState {
                 node_rewards,
                 user_wallets,
-            } => Ok(vec![self.synch_state(node_rewards, user_wallets).await?]),
+                msg_id,
+                origin,
+            } => {
+                let mut ops = vec![self.synch_state(node_rewards, user_wallets).await?];
+                // acknowledge the state pushed to us alone
+                if let SrcLocation::Node(elder) = origin {
+                    ops.push(StatePushAck { id: msg_id }.msg(elder));
+                }
+                Ok(ops)
+            }
+            NodeDuty::SetMaxChunkSize {
+                max_chunk_size,
+                elder,
+            } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!(
+                        "Ignoring max chunk size pushed by {}, not one of our Elders",
+                        elder
+                    );
+                    return Ok(vec![]);
+                }
+                if max_chunk_size < MIN_CHUNK_SIZE {
+                    warn!(
+                        "Ignoring max chunk size {} pushed by {}, below the least chunk size of {}",
+                        max_chunk_size, elder, MIN_CHUNK_SIZE
+                    );
+                    return Ok(vec![]);
+                }
+                self.set_max_chunk_size(max_chunk_size);
+                Ok(vec![])
+            }
+            NodeDuty::SetSectionParams { params, elder } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!(
+                        "Ignoring section parameters pushed by {}, not one of our Elders",
+                        elder
+                    );
+                    return Ok(vec![]);
+                }
+                if let Err(error) = params.validate() {
+                    warn!("Ignoring section parameters pushed by {}: {}", elder, error);
+                    return Ok(vec![]);
+                }
+                self.set_section_params(params);
+                Ok(vec![])
+            }
+            NodeDuty::ReceiveStatePushAck { ack, elder } => {
+                if self.state_pushes.acked(&elder, &ack) {
+                    info!("State push acknowledged by {}", elder);
+                }
+                Ok(vec![])
+            }
+            NodeDuty::RetryStatePushes => {
+                if !self.role.is_elder() {
+                    return Ok(vec![]);
+                }
+                let elders = self.network_api.our_elder_names().await;
+                let mut ops = vec![];
+                for (elder, prefix, id) in self.state_pushes.due(&elders, Instant::now()) {
+                    let targets = vec![elder].into_iter().collect();
+                    ops.extend(self.push_wallet_summaries(prefix, &targets));
+                    ops.extend(self.push_state_to(DstLocation::Node(elder), prefix, id));
+                }
+                Ok(ops)
+            }
+            NodeDuty::ReceiveWalletSummaries { push, elder } => {
+                self.receive_wallet_summaries(push, elder).await
+            }
             NodeDuty::LevelDown => {
                 info!("Getting Demoted");
-                self.meta_data = None;
-                self.transfers = None;
-                self.section_funds = None;
-                self.chunks = Some(
-                    Chunks::new(
-                        self.node_info.node_name,
-                        self.node_info.root_dir.as_path(),
-                        self.used_space.clone(),
-                    )
-                    .await?,
-                );
+                self.events.emit(NodeEvent::DemotedFromElder);
+                let plugged = self.role.begin_demotion()?;
+                let mut chunks = open_chunks(
+                    &self.node_info,
+                    &self.used_space,
+                    plugged,
+                    self.network_api.clock(),
+                )
+                .await?;
+                if let Some(observer) = &self.replication_observer {
+                    chunks.set_replication_observer(observer.clone());
+                }
+                self.role.become_adult(AdultState {
+                    chunks,
+                    standby: self.node_info.warm_standby.then(Default::default),
+                })?;
                 Ok(vec![])
             }
             //
             // ----------- Transfers -----------
             NodeDuty::GetTransferReplicaEvents { msg_id, origin } => {
-                let transfers = self.get_transfers()?;
+                let transfers = self.role.transfers_mut()?;
                 Ok(vec![transfers.all_events(msg_id, origin).await?])
             }
             NodeDuty::PropagateTransfer {
@@ -182,17 +333,29 @@ impl Node {
                 msg_id,
                 origin,
             } => {
-                let transfers = self.get_transfers()?;
-                Ok(vec![
-                    transfers.receive_propagated(&proof, msg_id, origin).await?,
-                ])
+                let transfers = self.role.transfers_mut()?;
+                let duty = transfers.receive_propagated(&proof, msg_id, origin).await?;
+                if matches!(duty, NodeDuty::NoOp) {
+                    self.events
+                        .emit_if_reward(&proof, self.node_info.reward_key);
+                }
+                Ok(vec![duty])
+            }
+            NodeDuty::RegisterCreditBatch(credit_proofs) => {
+                let transfers = self.role.transfers_mut()?;
+                let credited = transfers.receive_propagated_batch(&credit_proofs).await?;
+                for credit in &credited {
+                    self.events
+                        .emit_if_reward(credit, self.node_info.reward_key);
+                }
+                Ok(vec![])
             }
             NodeDuty::ValidateClientTransfer {
                 signed_transfer,
                 msg_id,
                 origin,
             } => {
-                let transfers = self.get_transfers()?;
+                let transfers = self.role.transfers_mut()?;
                 Ok(vec![
                     transfers.validate(signed_transfer, msg_id, origin).await?,
                 ])
@@ -202,7 +365,7 @@ impl Node {
                 msg_id,
                 origin,
             } => {
-                let transfers = self.get_transfers()?;
+                let transfers = self.role.transfers_mut()?;
                 Ok(vec![transfers.credit_without_proof(transfer).await?])
             }
             NodeDuty::GetTransfersHistory {
@@ -212,11 +375,28 @@ impl Node {
                 origin,
             } => {
                 debug!(">>>> TODO: GET TRANSFER HISTORY, ADD limit with since_version....");
-                let transfers = self.get_transfers()?;
+                let transfers = self.role.transfers_mut()?;
                 Ok(vec![transfers.history(&at, msg_id, origin).await?])
             }
+            NodeDuty::GetTransferById {
+                query,
+                msg_id,
+                origin,
+            } => {
+                let wallet = XorName::from(query.wallet);
+                if !self.network_api.our_prefix().await.matches(&wallet) {
+                    let forwarded = ClientRequest::TransferStatus(query).query()?;
+                    return self.process_read(forwarded, msg_id, origin).await;
+                }
+                let transfers = self.role.transfers_mut()?;
+                Ok(vec![
+                    transfers
+                        .send_transfer_status(query, msg_id, origin)
+                        .await?,
+                ])
+            }
             NodeDuty::GetBalance { at, msg_id, origin } => {
-                let transfers = self.get_transfers()?;
+                let transfers = self.role.transfers_mut()?;
                 Ok(vec![transfers.balance(at, msg_id, origin).await?])
             }
             NodeDuty::GetStoreCost {
@@ -225,17 +405,49 @@ impl Node {
                 msg_id,
                 origin,
             } => {
-                let transfers = self.get_transfers()?;
-                Ok(transfers.get_store_cost(bytes, msg_id, origin).await)
+                let histogram = self.role.meta_data_mut()?.capacity_histogram().await;
+                let transfers = self.role.transfers_mut()?;
+                let mut ops = transfers
+                    .get_store_cost(requester, bytes, msg_id, origin)
+                    .await?;
+                // under an id derived from that of the query, so that clients not
+                // expecting it don't take it for the response
+                if let SrcLocation::EndUser(user) = origin {
+                    let fullness = transfers.section_fullness(&histogram).await;
+                    let correlation_id = MessageId::in_response_to(&msg_id);
+                    ops.push(NodeDuty::Send(
+                        ClientMsg::SectionFullness(fullness).msg(correlation_id, user)?,
+                    ));
+                    // the itemized cost of uploading the bytes in chunks
+                    let max_chunk_size = self.node_info.max_chunk_size();
+                    match transfers.estimate_store_cost(bytes, max_chunk_size).await {
+                        Ok(items) => {
+                            let estimate = StoreCostEstimate::new(
+                                self.network_api.section_public_key().await?,
+                                items,
+                                self.node_info.section_params.treasury_percentage,
+                                fullness,
+                                self.network_api.clock().now_secs(),
+                            )?;
+                            let share = self.network_api.sign_as_elder(&estimate).await?;
+                            ops.push(
+                                SignedStoreCostEstimate { estimate, share }.msg(msg_id, user)?,
+                            );
+                        }
+                        Err(error) => info!("Not estimating store cost: {:?}", error),
+                    }
+                }
+                Ok(ops)
             }
             NodeDuty::RegisterTransfer { proof, msg_id } => {
-                let transfers = self.get_transfers()?;
+                let transfers = self.role.transfers_mut()?;
                 Ok(vec![transfers.register(&proof, msg_id).await?])
             }
             //
             // -------- Immutable chunks --------
             NodeDuty::ReadChunk {
                 read,
+                range,
                 msg_id,
                 origin,
             } => {
@@ -248,13 +460,12 @@ impl Node {
                     .await
                     .matches(&&data_section_addr)
                 {
-                    let chunks = self.get_chunks()?;
-                    let read = chunks.read(&read, msg_id, origin).await?;
-                    let mut ops = chunks.check_storage().await?;
-                    ops.insert(0, read);
+                    let chunks = self.role.chunks_mut()?;
+                    let mut ops = chunks.read(&read, range.as_ref(), msg_id, origin).await?;
+                    ops.extend(chunks.check_storage().await?);
                     Ok(ops)
                 } else {
-                    Ok(vec![NodeDuty::Send(OutgoingMsg {
+                    let mut ops = vec![NodeDuty::Send(OutgoingMsg {
                         msg: Message::NodeQuery {
                             query: NodeQuery::Chunks {
                                 query: read,
@@ -267,7 +478,12 @@ impl Node {
                         // TBD
                         section_source: false,
                         aggregation: Aggregation::None,
-                    })])
+                    })];
+                    ops.extend(
+                        self.redirect_hint(data_section_addr, msg_id, origin)
+                            .await?,
+                    );
+                    Ok(ops)
                 }
             }
             NodeDuty::WriteChunk {
@@ -275,67 +491,447 @@ impl Node {
                 msg_id,
                 origin,
             } => {
-                let chunks = self.get_chunks()?;
+                self.ensure_not_in_maintenance()?;
+                let chunks = self.role.chunks_mut()?;
                 Ok(vec![chunks.write(&write, msg_id, origin).await?])
             }
-            NodeDuty::ReachingMaxCapacity => Ok(vec![self.notify_section_of_our_storage().await?]),
+            NodeDuty::DeleteChunk {
+                address,
+                msg_id,
+                origin,
+                elder,
+            } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!("Chunk deletion sent by {}, not an Elder of ours", elder);
+                    return Ok(vec![]);
+                }
+                let chunks = self.role.chunks_mut()?;
+                Ok(vec![chunks.delete(address, msg_id, origin).await?])
+            }
+            NodeDuty::DeleteChunkBySection {
+                address, section, ..
+            } => {
+                let proof = self.network_api.sign_as_node(&address).await?;
+                let chunks = self.role.chunks_mut()?;
+                Ok(vec![
+                    chunks.delete_by_section(address, proof, section).await?,
+                ])
+            }
+            NodeDuty::ForwardUnknownMsg { bytes, dst } => {
+                forward(bytes, dst, &self.network_api).await?;
+                Ok(vec![])
+            }
+            NodeDuty::RejectUnsupportedMsg { msg, src } => {
+                info!(
+                    "Answering unsupported msg {:?} of variant {:?} from {:?}",
+                    msg.msg_id, msg.variant, src
+                );
+                Ok(vec![NodeDuty::Send(msg.response(src)?)])
+            }
+            NodeDuty::CheckStorage => match self.role.chunks_mut() {
+                Ok(chunks) => chunks.check_storage().await,
+                // not an adult, so nothing to check
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::ScrubChunks => match self.role.chunks_mut() {
+                Ok(chunks) => {
+                    let corrupt = chunks.scrub().await?;
+                    self.repair_corrupt_chunks(corrupt).await
+                }
+                // not an adult, so nothing to verify
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::MigrateChunks => match self.role.chunks_mut() {
+                Ok(chunks) => chunks.migrate().await,
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::ReplicateQueuedChunks => match self.role.chunks_mut() {
+                Ok(chunks) => chunks.replicate_next().await,
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::ImportChunks => match self.role.chunks_mut() {
+                Ok(chunks) => {
+                    let elders = self.network_api.our_elder_names().await;
+                    chunks.import(elders).await
+                }
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::CollectOrphanChunks { our_prefix } => match self.role.chunks_mut() {
+                Ok(chunks) => {
+                    chunks.collect_orphans(&our_prefix);
+                    Ok(vec![])
+                }
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::OfferOrphanChunks => match self.role.chunks_mut() {
+                Ok(chunks) => chunks.offer_orphans(),
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::ReachingMaxCapacity => {
+                self.events.emit(NodeEvent::StorageWarning {
+                    used: self.used_space.total().await,
+                    max_capacity: self.used_space.max_capacity().await,
+                });
+                Ok(vec![self.notify_section_of_our_storage().await?])
+            }
             //
             // ------- Misc ------------
+            NodeDuty::CheckTransferInvariants => {
+                // only Elders hold wallets
+                let violations = match self.role.transfers() {
+                    Ok(transfers) => transfers.verify_invariants(),
+                    Err(_) => return Ok(vec![]),
+                };
+                if violations.is_empty() {
+                    debug!("Transfer invariants hold");
+                    return Ok(vec![]);
+                }
+                for violation in &violations {
+                    error!("Transfer invariant violated: {}", violation);
+                }
+                self.events
+                    .emit(NodeEvent::TransferInvariantsViolated { violations });
+                Ok(vec![])
+            }
+            NodeDuty::CompactWalletHistories => self.compact_wallet_histories().await,
             NodeDuty::IncrementFullNodeCount { node_id } => {
-                let transfers = self.get_transfers()?;
+                let transfers = self.role.transfers_mut()?;
                 transfers.increase_full_node_count(node_id).await?;
                 Ok(vec![])
             }
             NodeDuty::Send(msg) => {
-                send(msg, &self.network_api).await?;
+                let id = msg.id();
+                if let Some(msg) = self.egress.enqueue(msg, Instant::now())? {
+                    if let Err(error) = send(msg, &self.network_api).await {
+                        // a forwarded query of which no send is left is failed to the client
+                        return match self.pending_queries.failed(&id) {
+                            Some(timed_out) => Ok(vec![timed_out]),
+                            None => Err(error),
+                        };
+                    }
+                }
+                self.send_held_back().await;
                 Ok(vec![])
             }
             NodeDuty::SendToNodes { targets, msg } => {
                 send_to_nodes(targets, &msg, &self.network_api).await?;
                 Ok(vec![])
             }
+            NodeDuty::SendNodeMsg { msg, dst } => {
+                send_node_msg(&msg, dst, &self.network_api).await?;
+                Ok(vec![])
+            }
+            NodeDuty::SendNodeMsgToNodes { targets, msg } => {
+                send_node_msg_to_nodes(targets, &msg, &self.network_api).await?;
+                Ok(vec![])
+            }
+            NodeDuty::RunOperatorCommand(cmd) => self.run_operator_command(cmd).await,
+            NodeDuty::RunOperatorQuery { query, reply } => {
+                self.run_operator_query(query, reply).await?;
+                Ok(vec![])
+            }
             NodeDuty::SetNodeJoinsAllowed(joins_allowed) => {
                 self.network_api.set_joins_allowed(joins_allowed).await?;
                 Ok(vec![])
             }
+            NodeDuty::ReceivePeerTime { peer, secs } => {
+                self.network_api
+                    .clock()
+                    .peer_time(peer, secs, Instant::now());
+                Ok(vec![])
+            }
+            NodeDuty::CheckClock => {
+                self.check_clock();
+                Ok(vec![])
+            }
+            NodeDuty::ProbePeers => {
+                let our_name = self.network_api.our_name().await;
+                let peers = self
+                    .network_api
+                    .our_members()
+                    .await
+                    .into_keys()
+                    .filter(|peer| *peer != our_name)
+                    .collect();
+                let probes = self.network_api.probes();
+                Ok(probes.probe(peers, Instant::now())?.into_iter().collect())
+            }
+            NodeDuty::AnswerPing { ping, peer } => {
+                if !self.network_api.our_members().await.contains_key(&peer) {
+                    warn!("Pinged by {}, not a member of our section", peer);
+                    return Ok(vec![]);
+                }
+                let secs = ping.secs;
+                Ok(vec![
+                    ping.pong().msg(peer),
+                    NodeDuty::ReceivePeerTime { peer, secs },
+                ])
+            }
+            NodeDuty::RecordPong { pong, peer } => {
+                let now = Instant::now();
+                if !self.network_api.probes().answered(peer, &pong, now) {
+                    debug!("Dropping pong of {}, not pinged by us", peer);
+                    return Ok(vec![]);
+                }
+                Ok(vec![NodeDuty::ReceivePeerTime {
+                    peer,
+                    secs: pong.secs,
+                }])
+            }
+            NodeDuty::HedgeQueries => Ok(self.hedge_queries().await),
+            NodeDuty::PurgePendingQueries => {
+                self.pending_queries.purge(Instant::now());
+                Ok(vec![])
+            }
             //
             // ------- Data ------------
+            NodeDuty::ProcessRead { id, .. } if self.is_catching_up() => {
+                info!("Not answering read {:?}, catching up on metadata", id);
+                Ok(vec![])
+            }
+            NodeDuty::ProcessRangedRead { id, .. } if self.is_catching_up() => {
+                info!("Not answering read {:?}, catching up on metadata", id);
+                Ok(vec![])
+            }
+            NodeDuty::ProcessReadBatch { id, .. } if self.is_catching_up() => {
+                info!(
+                    "Not answering reads of batch {:?}, catching up on metadata",
+                    id
+                );
+                Ok(vec![])
+            }
             NodeDuty::ProcessRead { query, id, origin } => {
-                // TODO: remove this conditional branching
-                // routing should take care of this
-                let data_section_addr = query.dst_address();
-                if self
+                let name = query.dst_address();
+                let mut ops = self.process_read(query, id, origin).await?;
+                ops.extend(self.redirect_hint(name, id, origin).await?);
+                Ok(ops)
+            }
+            NodeDuty::ProcessRangedRead {
+                address,
+                range,
+                id,
+                origin,
+            } => {
+                if !self.network_api.our_prefix().await.matches(address.name()) {
+                    let forwarded = ClientRequest::ChunkRange { address, range }.query()?;
+                    return self.process_read(forwarded, id, origin).await;
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.read_range(address, range, id, origin).await
+            }
+            NodeDuty::ProcessReadBatch {
+                queries,
+                id,
+                origin,
+            } => self.process_read_batch(queries, id, origin).await,
+            NodeDuty::ProcessWrite { cmd, id, origin } => {
+                self.ensure_not_in_maintenance()?;
+                let elders = self.network_api.our_elder_names().await;
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.write(cmd, id, origin, &elders).await
+            }
+            NodeDuty::RecordWriteAck { id, origin, elder } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!(
+                        "Ignoring ack of write {:?} by {}, not one of our Elders",
+                        id, elder
+                    );
+                    return Ok(vec![]);
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                Ok(meta_data
+                    .record_write_ack(id, origin, elder)
+                    .into_iter()
+                    .collect())
+            }
+            NodeDuty::ProcessDataMapWrite { cmd, id, origin } => {
+                self.ensure_not_in_maintenance()?;
+                let meta_data = self.role.meta_data_mut()?;
+                Ok(vec![meta_data.write_data_map(cmd, id, origin).await?])
+            }
+            NodeDuty::ProcessExistenceQuery { query, id, origin } => {
+                if !self
                     .network_api
                     .our_prefix()
                     .await
-                    .matches(&data_section_addr)
+                    .matches(query.address.name())
                 {
-                    let meta_data = self.get_metadata()?;
-                    Ok(vec![meta_data.read(query, id, origin).await?])
-                } else {
-                    Ok(vec![NodeDuty::Send(OutgoingMsg {
-                        msg: Message::NodeQuery {
-                            query: NodeQuery::Metadata { query, origin },
-                            id,
-                            target_section_pk: None,
-                        },
-                        dst: DstLocation::Section(data_section_addr),
-                        // TBD
-                        section_source: false,
-                        aggregation: Aggregation::None,
-                    })])
+                    let forwarded = ClientRequest::Existence(query).query()?;
+                    return self.process_read(forwarded, id, origin).await;
                 }
+                let section_key = self.network_api.section_public_key().await?;
+                let now = self.network_api.clock().now_secs();
+                let meta_data = self.role.meta_data()?;
+                let statement = meta_data.attest_existence(&query, section_key, now).await?;
+                let share = self.network_api.sign_as_elder(&statement).await?;
+                Ok(vec![
+                    ExistenceAttestation { statement, share }.msg(id, origin)?
+                ])
             }
-            NodeDuty::ProcessWrite { cmd, id, origin } => {
-                let meta_data = self.get_metadata()?;
-                Ok(vec![meta_data.write(cmd, id, origin).await?])
+            NodeDuty::ProcessDataMapRead { query, id, origin } => {
+                if !self.network_api.our_prefix().await.matches(&query.name()) {
+                    let forwarded = ClientRequest::DataMap(query).query()?;
+                    return self.process_read(forwarded, id, origin).await;
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                Ok(vec![meta_data.read_data_map(&query, id, origin).await?])
+            }
+            NodeDuty::ProcessChunkDeletion(deletion) => {
+                let section_key = self.network_api.section_public_key().await?;
+                let now = self.network_api.clock().now_secs();
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.delete_chunks(deletion, section_key, now).await
+            }
+            NodeDuty::RecordChunkDeleted {
+                address,
+                holder,
+                proof,
+            } => {
+                let meta_data = self.role.meta_data_mut()?;
+                let _ = meta_data.record_chunk_deleted(address, holder, &proof)?;
+                Ok(vec![])
+            }
+            NodeDuty::RepairChunkHolder { address, holder } => {
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.repair_chunk_holder(address, holder).await
+            }
+            NodeDuty::ProcessChunkRepair {
+                addresses,
+                id,
+                origin,
+            } => {
+                self.ensure_not_in_maintenance()?;
+                if let Some(address) = addresses.first() {
+                    if !self.network_api.our_prefix().await.matches(address.name()) {
+                        let forwarded = ClientRequest::ChunkRepair(addresses).query()?;
+                        return self.process_read(forwarded, id, origin).await;
+                    }
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.repair_chunks(addresses, id, origin).await
+            }
+            NodeDuty::AnswerMetadataCatchUp { query, elder } => {
+                let section_key = self.network_api.section_public_key().await?;
+                let meta_data = self.role.meta_data()?;
+                let page = meta_data.answer_catch_up(query, section_key).await?;
+                info!(
+                    "Sending {} metadata records to {}, catching up on them",
+                    page.page.records.len(),
+                    elder
+                );
+                let share = self.network_api.sign_as_elder(&page).await?;
+                Ok(vec![SignedCatchUpPage { page, share }.msg(elder)])
+            }
+            NodeDuty::CatchUpOnMetadata { page, elder } => {
+                let key_set = self.network_api.our_public_key_set().await?;
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.catch_up(page, elder, &key_set).await
+            }
+            NodeDuty::CheckMetadataCatchUp => {
+                let elders = self.network_api.our_elder_names().await;
+                match self.role.meta_data_mut() {
+                    Ok(meta_data) => {
+                        let mut duties = meta_data.check_catch_up(&elders)?;
+                        duties.extend(meta_data.check_import());
+                        Ok(duties)
+                    }
+                    // not an Elder, so nothing to catch up on
+                    Err(_) => Ok(vec![]),
+                }
+            }
+            NodeDuty::ImportMetadataRange { query, page, elder } => {
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.import(query, page, elder).await
+            }
+            NodeDuty::ProcessMetadataExport { query, id, origin } => {
+                // exported to the Elders of our section and of our sibling only
+                let of_sibling = matches!(
+                    self.network_api.sibling(),
+                    Some(sibling) if sibling.elders.contains(&origin)
+                );
+                if !of_sibling && !self.network_api.our_elder_names().await.contains(&origin) {
+                    warn!(
+                        "Not exporting metadata within {:?} to {}, not an Elder of ours or of our sibling",
+                        query.prefix, origin
+                    );
+                    return Ok(vec![]);
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                Ok(vec![meta_data.send_range(&query, id, origin).await?])
             }
             NodeDuty::ProcessDataPayment { msg, origin } => {
-                let transfers = self.get_transfers()?;
+                self.ensure_not_in_maintenance()?;
+                let transfers = self.role.transfers_mut()?;
                 transfers.process_payment(&msg, origin).await
             }
             NodeDuty::AddPayment(credit) => {
-                self.get_section_funds()?.add_payment(credit);
+                self.role.section_funds_mut()?.add_payment(credit)?;
+                Ok(vec![])
+            }
+            NodeDuty::TakeOverPayments { handover, elder } => {
+                let our_prefix = self.network_api.our_prefix().await;
+                if handover.prefix != our_prefix {
+                    warn!(
+                        "Dropping payments handed over by {} for {:?}, not our prefix",
+                        elder, handover.prefix
+                    );
+                    return Ok(vec![]);
+                }
+                // handed over by the Elders of our sibling, or of our own section
+                let of_sibling = matches!(
+                    self.network_api.sibling(),
+                    Some(sibling) if sibling.elders.contains(&elder)
+                );
+                if !of_sibling && !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!(
+                        "Dropping payments handed over by {}, not an Elder of ours or of our sibling",
+                        elder
+                    );
+                    return Ok(vec![]);
+                }
+                let section_funds = self.role.section_funds_mut()?;
+                let mut taken_over = 0;
+                for credit in handover.payments {
+                    // only those within our prefix, as of our own split
+                    if !our_prefix.matches(&XorName(*credit.id())) {
+                        continue;
+                    }
+                    match section_funds.add_payment(credit) {
+                        Ok(()) => taken_over += 1,
+                        Err(e) => warn!("Dropping payment handed over by {}: {}", elder, e),
+                    }
+                }
+                info!("Took over {} payments handed over by {}", taken_over, elder);
+                Ok(vec![])
+            }
+            NodeDuty::ImportSectionFunds { export, elder } => {
+                debug!("Share of funds export received from {}", elder);
+                self.import_section_funds(export, false).await
+            }
+            NodeDuty::ImportSectionMetadata { dump, elder } => {
+                debug!("Share of metadata dump received from {}", elder);
+                self.import_section_metadata(dump, false).await
+            }
+            NodeDuty::SetWriteAckLevel {
+                id,
+                origin,
+                level,
+                elder,
+            } => {
+                debug!("Ack level {:?} of write {:?} told by {}", level, id, elder);
+                let elder_count = self.network_api.our_elder_names().await.len();
+                let meta_data = self.role.meta_data_mut()?;
+                Ok(meta_data
+                    .select_ack_level(id, origin, level, elder_count)
+                    .into_iter()
+                    .collect())
+            }
+            NodeDuty::SetChunkRedundancy {
+                address,
+                multiplier,
+            } => {
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.set_chunk_redundancy(address, multiplier).await?;
                 Ok(vec![])
             }
             NodeDuty::ReplicateChunk {
@@ -343,92 +939,268 @@ impl Node {
                 address,
                 id,
             } => {
-                let chunks = self.get_chunks()?;
-                Ok(vec![
-                    chunks.replicate_chunk(address, current_holders, id).await?,
-                ])
+                let chunks = self.role.chunks_mut()?;
+                chunks.replicate_chunk(address, current_holders, id).await
+            }
+            NodeDuty::ReplicateChunks { batch, elder } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!(
+                        "Chunks batched for replication by {}, not an Elder of ours",
+                        elder
+                    );
+                    return Ok(vec![]);
+                }
+                if batch.new_holder != self.network_api.our_name().await {
+                    warn!("Chunks batched for replication at another holder");
+                    return Ok(vec![]);
+                }
+                info!("Replicating {} chunks batched for us", batch.chunks.len());
+                Ok(batch.into_duties())
+            }
+            NodeDuty::IssueStorageChallenge => match self.role.meta_data_mut() {
+                Ok(meta_data) => meta_data.issue_storage_challenges().await,
+                // not an Elder, so nothing to challenge
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::AnswerStorageChallenge {
+                challenge,
+                id,
+                elder,
+            } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!("Storage challenge by {}, not an Elder of ours", elder);
+                    return Ok(vec![]);
+                }
+                let chunks = self.role.chunks_mut()?;
+                Ok(vec![chunks.answer_storage_challenge(&challenge, id, elder)?])
+            }
+            NodeDuty::QueryChunkAccess => match self.role.meta_data_mut() {
+                Ok(meta_data) => {
+                    let adults = self.network_api.our_adults().await.into_iter().collect();
+                    meta_data.query_chunk_access(adults)
+                }
+                // not an Elder, so no Adults to query
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::ReportChunkAccess { query, elder } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!("Chunk access query by {}, not an Elder of ours", elder);
+                    return Ok(vec![]);
+                }
+                let chunks = self.role.chunks()?;
+                Ok(vec![chunks.report_chunk_access(&query, elder)?])
+            }
+            NodeDuty::RecordChunkAccess { report, holder } => {
+                if !self.network_api.our_adults().await.contains(&holder) {
+                    warn!("Chunk access reported by {}, not an Adult of ours", holder);
+                    return Ok(vec![]);
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.record_chunk_access(holder, report);
+                Ok(vec![])
+            }
+            NodeDuty::ReportCapacity => match self.role.chunks() {
+                Ok(chunks) => {
+                    let elders = self.network_api.our_elder_names().await;
+                    Ok(vec![chunks.report_capacity(elders).await?])
+                }
+                // not an Adult, so no chunks stored
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::RecordCapacity { report, adult } => {
+                if !self.network_api.our_adults().await.contains(&adult) {
+                    warn!("Capacity reported by {}, not an Adult of ours", adult);
+                    return Ok(vec![]);
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.record_capacity(adult, report).await?;
+                Ok(vec![])
+            }
+            NodeDuty::RegisterImportedChunks { imported, holder } => {
+                if !self.network_api.our_adults().await.contains(&holder) {
+                    warn!("Chunks imported by {}, not an Adult of ours", holder);
+                    return Ok(vec![]);
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.register_imported_chunks(imported, holder).await
+            }
+            NodeDuty::AdoptOrphanChunk { offer, holder } => {
+                if !self
+                    .network_api
+                    .matches_our_prefix(*offer.address.name())
+                    .await
+                {
+                    warn!("Orphan chunk offered by {}, not ours", holder);
+                    return Ok(vec![]);
+                }
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.adopt_orphan_chunk(offer, holder).await
+            }
+            NodeDuty::ReleaseOrphanChunk { release, elder } => {
+                // only the section owning the chunk can release it
+                let owners = self.network_api.section_of(release.address.name()).await;
+                if !matches!(owners, Some((_, elders)) if elders.contains(&elder)) {
+                    warn!("Orphan chunk released by {}, not an Elder owning it", elder);
+                    return Ok(vec![]);
+                }
+                let chunks = self.role.chunks_mut()?;
+                chunks.release_orphan(release).await
+            }
+            NodeDuty::VerifyStorageProof { proof, holder } => {
+                let meta_data = self.role.meta_data_mut()?;
+                meta_data.verify_storage_proof(proof, holder).await
+            }
+            NodeDuty::ReadChunkForElder { read, elder } => {
+                if !self.network_api.our_elder_names().await.contains(&elder) {
+                    warn!("Chunk read relayed by {}, not an Elder of ours", elder);
+                    return Ok(vec![]);
+                }
+                let chunks = self.role.chunks_mut()?;
+                Ok(vec![chunks.read_for_elder(read, elder)?])
+            }
+            NodeDuty::RelayChunk { answer, holder } => {
+                let meta_data = self.role.meta_data_mut()?;
+                Ok(meta_data.relay_chunk(answer, holder).into_iter().collect())
+            }
+            NodeDuty::RetryRelayedReads => match self.role.meta_data_mut() {
+                Ok(meta_data) => meta_data.retry_relayed_reads(),
+                // not an Elder, so no reads relayed
+                Err(_) => Ok(vec![]),
+            },
+            NodeDuty::CheckAdultLiveness => {
+                let (unresponsive, duties) = match self.role.meta_data_mut() {
+                    Ok(meta_data) => meta_data.check_adult_liveness().await?,
+                    // not an Elder, so no Adults queried
+                    Err(_) => return Ok(vec![]),
+                };
+                for (adult, liveness) in unresponsive {
+                    warn!(
+                        "Adult {} is unresponsive, with {} queries missed in a row, answering in {}ms on average",
+                        adult, liveness.missed_in_a_row, liveness.mean_latency_ms
+                    );
+                    self.events
+                        .emit(NodeEvent::AdultUnresponsive { adult, liveness });
+                }
+                Ok(duties)
             }
             NodeDuty::GetChunkForReplication {
                 address,
                 new_holder,
                 id,
             } => {
-                let chunks = self.get_chunks()?;
-                Ok(vec![
-                    chunks
-                        .get_chunk_for_replication(address, id, new_holder)
-                        .await?,
-                ])
+                let chunks = self.role.chunks_mut()?;
+                let duty = chunks
+                    .get_chunk_for_replication(address, id, new_holder)
+                    .await?;
+                if matches!(duty, NodeDuty::Send(_)) {
+                    self.chunk_handed_over(&address);
+                }
+                Ok(vec![duty])
             }
             NodeDuty::StoreChunkForReplication {
                 data,
                 correlation_id,
+                holder,
             } => {
                 // Recreate original MessageId from Section
                 let msg_id = MessageId::combine(vec![
                     *data.address().name(),
                     self.network_api.our_name().await,
                 ]);
-                if msg_id == correlation_id {
-                    let chunks = self.get_chunks()?;
-                    Ok(vec![chunks.store_replicated_chunk(data).await?])
+                let chunks = self.role.chunks_mut()?;
+                // a chunk asked for is verified against its address as it is stored
+                if msg_id == correlation_id || chunks.is_replicating(correlation_id) {
+                    chunks
+                        .store_replicated_chunk(data, correlation_id, holder)
+                        .await
                 } else {
                     log::warn!("Invalid message ID");
                     Ok(vec![])
                 }
             }
+            NodeDuty::VerifyReplicatedChunk { failed, new_holder } => {
+                let chunks = self.role.chunks_mut()?;
+                if chunks.verify_replicated_chunk(&failed).await? {
+                    self.repair_corrupt_chunks(vec![failed.address]).await
+                } else {
+                    warn!(
+                        "Chunk {:?} found corrupt by {}, though intact here",
+                        failed.address, new_holder
+                    );
+                    Ok(vec![])
+                }
+            }
             NodeDuty::NoOp => Ok(vec![]),
         }
     }
 
-    fn get_chunks(&mut self) -> Result<&mut Chunks> {
-        if let Some(chunks) = &mut self.chunks {
-            Ok(chunks)
-        } else {
-            Err(Error::NoChunks)
-        }
-    }
-
-    fn get_metadata(&mut self) -> Result<&mut Metadata> {
-        if let Some(meta_data) = &mut self.meta_data {
-            Ok(meta_data)
-        } else {
-            Err(Error::NoMetadata)
-        }
-    }
-
-    pub(crate) fn get_transfers(&mut self) -> Result<&mut Transfers> {
-        if let Some(transfers) = &mut self.transfers {
-            Ok(transfers)
-        } else {
-            Err(Error::NoTransfers)
-        }
-    }
-
-    fn get_section_funds(&mut self) -> Result<&mut SectionFunds> {
-        if let Some(section_funds) = &mut self.section_funds {
-            Ok(section_funds)
+    pub(crate) async fn process_read(
+        &mut self,
+        query: DataQuery,
+        id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuties> {
+        // TODO: remove this conditional branching
+        // routing should take care of this
+        let data_section_addr = query.dst_address();
+        if self
+            .network_api
+            .our_prefix()
+            .await
+            .matches(&data_section_addr)
+        {
+            let meta_data = self.role.meta_data_mut()?;
+            meta_data.read(query, id, origin).await
         } else {
-            Err(Error::NoSectionFunds)
+            let msg = Message::NodeQuery {
+                query: NodeQuery::Metadata { query, origin },
+                id,
+                target_section_pk: None,
+            };
+            // sections not known to us are counted under the empty prefix
+            let section = match self.network_api.section_of(&data_section_addr).await {
+                Some((prefix, _)) => prefix,
+                None => Prefix::default(),
+            };
+            self.pending_queries
+                .forwarded(msg.clone(), data_section_addr, section, Instant::now());
+            Ok(vec![NodeDuty::Send(OutgoingMsg {
+                msg,
+                dst: DstLocation::Section(data_section_addr),
+                // TBD
+                section_source: false,
+                aggregation: Aggregation::None,
+            })])
         }
     }
 
-    fn get_churning_funds(
-        &mut self,
-    ) -> Result<(
-        &mut RewardProcess,
-        &mut RewardWallets,
-        &mut DashMap<CreditId, CreditAgreementProof>,
-    )> {
-        if let Some(SectionFunds::Churning {
-            process,
-            wallets,
-            payments,
-        }) = &mut self.section_funds
-        {
-            Ok((process, wallets, payments))
-        } else {
-            Err(Error::NotChurningFunds)
+    /// Copies the chunks found corrupt, and quarantined, over again from the Adults
+    /// closest to them, i.e. the other holders of them.
+    async fn repair_corrupt_chunks(&self, corrupt: Vec<BlobAddress>) -> Result<NodeDuties> {
+        let our_name = self.network_api.our_name().await;
+        let mut duties = vec![];
+        for address in corrupt {
+            let current_holders: BTreeSet<_> = self
+                .network_api
+                .our_adults_sorted_by_distance_to(address.name(), CHUNK_COPY_COUNT)
+                .await
+                .into_iter()
+                .filter(|holder| *holder != our_name)
+                .collect();
+            if current_holders.is_empty() {
+                warn!(
+                    "No other holders to repair corrupt chunk {:?} from",
+                    address
+                );
+                continue;
+            }
+            duties.push(NodeDuty::ReplicateChunk {
+                address,
+                current_holders,
+                // repaired once, however many times found corrupt meanwhile
+                id: MessageId::from_content(&address)?,
+            });
         }
+        Ok(duties)
     }
 }
@@ -6,7 +6,9 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::{redirect::SectionRedirect, standby::WARM_STANDBY_COUNT};
 use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
     node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
     section_funds::{self, SectionFunds},
     transfers::{
@@ -16,7 +18,7 @@ use crate::{
     Error, Node, Result,
 };
 use dashmap::DashMap;
-use log::{debug, info};
+use log::{debug, info, warn};
 use section_funds::{
     elder_signing::ElderSigning,
     reward_process::{OurSection, RewardProcess},
@@ -33,21 +35,36 @@ use sn_messaging::{
         Message, NodeCmd, NodeEvent, NodeQuery, NodeQueryResponse, NodeSystemCmd, NodeSystemQuery,
         NodeSystemQueryResponse, NodeTransferCmd,
     },
-    Aggregation, DstLocation, MessageId, SrcLocation,
+    Aggregation, DstLocation, EndUser, MessageId, SrcLocation,
 };
 use sn_routing::{Prefix, XorName};
 use sn_transfers::TransferActor;
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Instant,
+};
 
 impl Node {
+    /// Propagates the credits to the sections of their recipients. The credits to wallets
+    /// of our own section are paid out by each of our Elders, so rather than being sent
+    /// to ourselves one msg per credit, they are registered at once, as a batch.
     pub(crate) fn propagate_credits(
         credit_proofs: BTreeMap<CreditId, CreditAgreementProof>,
+        our_prefix: &Prefix,
     ) -> Result<NodeDuties> {
         use NodeCmd::*;
         use NodeTransferCmd::*;
+        let (ours, theirs): (Vec<_>, Vec<_>) = credit_proofs
+            .into_values()
+            .partition(|credit_proof| our_prefix.matches(&XorName::from(credit_proof.recipient())));
         let mut ops = vec![];
+        if !ours.is_empty() {
+            ops.push(NodeDuty::RegisterCreditBatch(ours));
+        }
 
-        for (_, credit_proof) in credit_proofs {
+        // there is no msg for a batch of credits yet, so
+        // those to other sections are sent one by one
+        for credit_proof in theirs {
             let location = XorName::from(credit_proof.recipient());
             let msg_id = MessageId::from_content(&credit_proof.debiting_replicas_sig)?;
             ops.push(NodeDuty::Send(OutgoingMsg {
@@ -67,14 +84,13 @@ impl Node {
     /// https://github.com/rust-lang/rust-clippy/issues?q=is%3Aissue+is%3Aopen+eval_order_dependence
     #[allow(clippy::eval_order_dependence)]
     pub(crate) async fn get_section_elders(
-        &self,
+        &mut self,
         msg_id: MessageId,
         origin: SrcLocation,
     ) -> Result<NodeDuty> {
-        let elders = SectionElders {
-            prefix: self.network_api.our_prefix().await,
-            names: self.network_api.our_elder_names().await,
-            key_set: self.network_api.our_public_key_set().await?,
+        let elders = match &self.section_elders {
+            Some(elders) => elders.clone(),
+            None => self.refresh_section_elders().await?,
         };
         Ok(NodeDuty::Send(OutgoingMsg {
             msg: Message::NodeQueryResponse {
@@ -91,6 +107,54 @@ impl Node {
         }))
     }
 
+    /// Computes our section elders, to answer `GetSectionElders` queries
+    /// from until the elders change again. Called whenever they change,
+    /// since the query is frequent, and computing it takes several reads
+    /// of the routing state.
+    pub(crate) async fn refresh_section_elders(&mut self) -> Result<SectionElders> {
+        let elders = SectionElders {
+            prefix: self.network_api.our_prefix().await,
+            names: self.network_api.our_elder_names().await,
+            key_set: self.network_api.our_public_key_set().await?,
+        };
+        debug!("Caching section elders of {:?}", elders.prefix);
+        self.section_elders = Some(elders.clone());
+        Ok(elders)
+    }
+
+    /// Refreshes the cached section elders, dropping
+    /// them if they could not be computed.
+    pub(crate) async fn cache_section_elders(&mut self) {
+        if let Err(error) = self.refresh_section_elders().await {
+            warn!("Could not cache section elders: {}", error);
+            self.section_elders = None;
+        }
+    }
+
+    /// The hint to the client querying the name, when it is of another section, of the
+    /// prefix and Elders of that section, if known to us, for it to query directly next time.
+    pub(crate) async fn redirect_hint(
+        &self,
+        name: XorName,
+        query_id: MessageId,
+        origin: EndUser,
+    ) -> Result<Option<NodeDuty>> {
+        if self.network_api.our_prefix().await.matches(&name) {
+            return Ok(None);
+        }
+        match self.network_api.section_of(&name).await {
+            Some((prefix, elders)) => {
+                let redirect = SectionRedirect {
+                    name,
+                    prefix,
+                    elders,
+                };
+                Ok(Some(redirect.msg(query_id, origin)?))
+            }
+            None => Ok(None),
+        }
+    }
+
     ///
     pub(crate) async fn notify_section_of_our_storage(&mut self) -> Result<NodeDuty> {
         let node_id = PublicKey::from(self.network_api.public_key().await);
@@ -125,43 +189,104 @@ impl Node {
     }
 
     /// Push our state to the given dst
-    pub fn push_state(&self, prefix: Prefix, msg_id: MessageId) -> NodeDuty {
-        let dst = DstLocation::Section(prefix.name());
+    pub fn push_state(&self, prefix: Prefix, msg_id: MessageId) -> NodeDuties {
+        self.push_state_to(DstLocation::Section(prefix.name()), prefix, msg_id)
+    }
+
+    /// Push our state to each of our Elders not among the former ones, tracking the
+    /// push to each apart until acknowledged, or else to the section if the former
+    /// Elders are not known.
+    pub async fn push_state_to_new_elders(
+        &mut self,
+        prefix: Prefix,
+        our_key: PublicKey,
+        former_elders: BTreeSet<XorName>,
+    ) -> NodeDuties {
+        if former_elders.is_empty() {
+            let msg_id = MessageId::combine(vec![prefix.name(), XorName::from(our_key)]);
+            return self.push_state(prefix, msg_id);
+        }
+        let our_name = self.network_api.our_name().await;
+        let new_elders = self
+            .network_api
+            .our_elder_names()
+            .await
+            .into_iter()
+            .filter(|name| !former_elders.contains(name) && *name != our_name)
+            .collect::<BTreeSet<_>>();
+        // the summaries of the wallet histories compacted, before the histories
+        let mut ops = self.push_wallet_summaries(prefix, &new_elders);
+        for elder in new_elders {
+            // each Elder pushing gets its own ack
+            let msg_id =
+                MessageId::combine(vec![prefix.name(), XorName::from(our_key), elder, our_name]);
+            self.state_pushes
+                .pushed(elder, prefix, msg_id, Instant::now());
+            ops.extend(self.push_state_to(DstLocation::Node(elder), prefix, msg_id));
+        }
+        ops
+    }
+
+    /// Push our state to the oldest Adults, being next in line for promotion,
+    /// for those opted in to keep a warm copy of it.
+    pub async fn push_standby_state(&self, prefix: Prefix, msg_id: MessageId) -> NodeDuties {
+        self.network_api
+            .our_oldest_adults(WARM_STANDBY_COUNT)
+            .await
+            .into_iter()
+            .flat_map(|name| self.push_state_to(DstLocation::Node(name), prefix, msg_id))
+            .collect()
+    }
 
-        let user_wallets = if let Some(transfers) = &self.transfers {
+    /// Push our state to the dst, along with the max chunk size and parameters of our section.
+    pub(crate) fn push_state_to(
+        &self,
+        dst: DstLocation,
+        prefix: Prefix,
+        msg_id: MessageId,
+    ) -> NodeDuties {
+        let user_wallets = if let Ok(transfers) = self.role.transfers() {
             transfers.user_wallets()
         } else {
             Default::default()
         };
 
-        let node_rewards = match &self.section_funds {
-            Some(SectionFunds::KeepingNodeWallets { wallets, .. })
-            | Some(SectionFunds::Churning { wallets, .. }) => wallets.node_wallets(),
-            None => Default::default(),
+        let node_rewards = match self.role.section_funds() {
+            Ok(SectionFunds::KeepingNodeWallets { wallets, .. })
+            | Ok(SectionFunds::Churning { wallets, .. }) => wallets.node_wallets(),
+            Err(_) => Default::default(),
         };
 
         // only push that what should be in dst
         let user_wallets = user_wallets
             .into_iter()
-            .filter(|(key, _)| dst.contains(&XorName::from(*key), &prefix))
+            .filter(|(key, _)| prefix.matches(&XorName::from(*key)))
             .collect();
         let node_rewards = node_rewards
             .into_iter()
-            .filter(|(name, _)| dst.contains(name, &prefix))
+            .filter(|(name, _)| prefix.matches(name))
             .collect();
 
-        NodeDuty::Send(OutgoingMsg {
-            msg: Message::NodeCmd {
-                cmd: NodeCmd::System(NodeSystemCmd::ReceiveExistingData {
-                    node_rewards,
-                    user_wallets,
-                }),
-                id: msg_id,
-                target_section_pk: None,
+        let max_chunk_size = NodeMsgBody::MaxChunkSize(self.node_info.max_chunk_size());
+        vec![
+            NodeDuty::Send(OutgoingMsg {
+                msg: Message::NodeCmd {
+                    cmd: NodeCmd::System(NodeSystemCmd::ReceiveExistingData {
+                        node_rewards,
+                        user_wallets,
+                    }),
+                    id: msg_id,
+                    target_section_pk: None,
+                },
+                section_source: false, // strictly this is not correct, but we don't expect responses to an event..
+                dst,
+                aggregation: Aggregation::None,
+            }),
+            NodeDuty::SendNodeMsg {
+                msg: NodeMsg::new(max_chunk_size),
+                dst,
             },
-            section_source: false, // strictly this is not correct, but we don't expect responses to an event..
-            dst,
-            aggregation: Aggregation::None,
-        })
+            self.node_info.section_params.msg(dst),
+        ]
     }
 }
@@ -6,11 +6,12 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::role::ElderState;
 use crate::{
     capacity::{Capacity, ChunkHolderDbs, RateLimit},
     metadata::{adult_reader::AdultReader, Metadata},
-    node_ops::NodeDuty,
-    section_funds::{reward_wallets::RewardWallets, SectionFunds},
+    node_ops::{NodeDuties, NodeDuty},
+    section_funds::{reward_wallets::RewardWallets, Payments, SectionFunds},
     transfers::get_replicas::{replica_info, transfer_replicas},
     transfers::Transfers,
     Error, Node, Result,
@@ -33,7 +34,7 @@ impl Node {
     /// If we are an oldie we'll have a transfer instance,
     /// This updates the replica info on it.
     pub async fn update_replicas(&mut self) -> Result<()> {
-        if let Some(ref mut transfers) = self.transfers {
+        if let Ok(transfers) = self.role.transfers_mut() {
             let info = replica_info(&self.node_info, &self.network_api).await?;
             transfers.update_replica_info(info);
         }
@@ -44,33 +45,86 @@ impl Node {
     pub async fn level_up(&mut self) -> Result<()> {
         //
         // do not hande immutable chunks anymore
-        self.chunks = None;
+        let adult = self.role.begin_promotion()?;
+        let plugged_chunk_store = adult.chunks.into_store();
         self.used_space.reset().await;
 
         //
         // start handling metadata
+        // opened once, as the changes to them are logged by a single writer
         let dbs = ChunkHolderDbs::new(self.node_info.path())?;
         let reader = AdultReader::new(self.network_api.clone());
-        let meta_data =
-            Metadata::new(&self.node_info.path(), &self.used_space, dbs, reader).await?;
-        self.meta_data = Some(meta_data);
+        let mut meta_data = Metadata::new(
+            &self.node_info.path(),
+            &self.used_space,
+            dbs.clone(),
+            reader,
+            self.node_info.node_name,
+            self.node_info.section_params.min_write_acks,
+            self.node_info.secure_delete,
+        )
+        .await?;
+        if let Some(merge) = &self.map_merge {
+            meta_data.set_map_merge(merge.clone());
+        }
+        meta_data.set_batch_replication(self.node_info.batch_chunk_replication);
+        meta_data.set_relayed_reads(self.node_info.relayed_read_holders);
+        meta_data.set_write_quotas(self.node_info.write_quotas);
+        meta_data.set_erasure_coding(self.node_info.erasure_coding)?;
+        meta_data.set_chunk_compression(self.node_info.chunk_compression);
+        meta_data.set_clock(self.network_api.clock());
 
         //
         // start handling transfers
-        let dbs = ChunkHolderDbs::new(self.node_info.root_dir.as_path())?;
-        let rate_limit = RateLimit::new(self.network_api.clone(), Capacity::new(dbs.clone()));
-        let user_wallets = BTreeMap::<PublicKey, ActorHistory>::new();
+        let rate_limit = RateLimit::new(
+            self.network_api.clone(),
+            Capacity::new(dbs.clone()),
+            self.node_info.section_params.economy,
+        );
+        // a warm copy held while next in line for promotion is started out with,
+        // so that only the changes since are to be caught up on
+        let (node_wallets, user_wallets) = match adult.standby {
+            Some(standby) => standby.into_state(),
+            None => (BTreeMap::new(), BTreeMap::<PublicKey, ActorHistory>::new()),
+        };
         let replicas = transfer_replicas(&self.node_info, &self.network_api, user_wallets).await?;
-        self.transfers = Some(Transfers::new(replicas, rate_limit));
+        let transfers = Transfers::new(replicas, rate_limit);
 
         //
         // start handling node rewards
-        self.section_funds = Some(SectionFunds::KeepingNodeWallets {
-            wallets: RewardWallets::new(BTreeMap::<XorName, (NodeAge, PublicKey)>::new()),
-            payments: Default::default(),
-        });
+        let section_funds = SectionFunds::KeepingNodeWallets {
+            wallets: RewardWallets::new(node_wallets),
+            payments: Payments::new(self.node_info.path()),
+        };
 
-        Ok(())
+        self.role.become_elder(ElderState {
+            meta_data,
+            transfers,
+            section_funds,
+            plugged_chunk_store,
+        })
+    }
+
+    /// Catch up on the metadata of the section from the other Elders, once levelled up,
+    /// before answering reads, so that they are not answered by incomplete records.
+    pub async fn catch_up_on_metadata(&mut self) -> Result<NodeDuties> {
+        let our_name = self.network_api.our_name().await;
+        let prefix = self.network_api.our_prefix().await;
+        let elders = self
+            .network_api
+            .our_elder_names()
+            .await
+            .into_iter()
+            .filter(|elder| *elder != our_name)
+            .collect();
+        self.role.meta_data_mut()?.begin_catch_up(prefix, elders)
+    }
+
+    /// Whether catching up on the metadata of the section, not answering reads meanwhile.
+    pub(crate) fn is_catching_up(&self) -> bool {
+        self.role
+            .meta_data()
+            .is_ok_and(|meta_data| !meta_data.is_caught_up())
     }
 
     /// Continue the level up and handle more responsibilities.
@@ -79,20 +133,26 @@ impl Node {
         node_wallets: BTreeMap<XorName, (NodeAge, PublicKey)>,
         user_wallets: BTreeMap<PublicKey, ActorHistory>,
     ) -> Result<NodeDuty> {
+        // as an Adult, only kept if opted in to being a warm standby
+        if let Some(standby) = self.role.standby_mut() {
+            standby.update(node_wallets, user_wallets);
+            return Ok(NodeDuty::NoOp);
+        }
+
         // merge in provided user wallets
-        if let Some(transfers) = &mut self.transfers {
+        if let Ok(transfers) = self.role.transfers_mut() {
             transfers.merge(user_wallets)
         }
 
         //  merge in provided node reward stages
-        match &mut self.section_funds {
-            Some(SectionFunds::KeepingNodeWallets { wallets, .. })
-            | Some(SectionFunds::Churning { wallets, .. }) => {
+        match self.role.section_funds_mut() {
+            Ok(SectionFunds::KeepingNodeWallets { wallets, .. })
+            | Ok(SectionFunds::Churning { wallets, .. }) => {
                 for (key, (age, wallet)) in &node_wallets {
                     wallets.set_node_wallet(*key, *age, *wallet);
                 }
             }
-            None => {
+            Err(_) => {
                 return Err(Error::InvalidOperation(
                     "Invalid section funds stage".to_string(),
                 ))
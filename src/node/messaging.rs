@@ -6,8 +6,9 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{node_ops::OutgoingMsg, Error};
+use crate::{node_msg::NodeMsg, node_ops::OutgoingMsg, Error};
 use crate::{Network, Result};
+use bytes::Bytes;
 use log::{error, trace};
 use sn_messaging::{client::Message, Aggregation, DstLocation, Itinerary, SrcLocation};
 use sn_routing::XorName;
@@ -50,8 +51,46 @@ pub(crate) async fn send_to_nodes(
         msg
     );
 
+    send_bytes_to_nodes(targets, msg.serialize()?, network).await
+}
+
+/// Sends the msg between nodes, as a single node.
+pub(crate) async fn send_node_msg(
+    msg: &NodeMsg,
+    dst: DstLocation,
+    network: &Network,
+) -> Result<()> {
+    trace!("Sending node msg to {:?}: {:?}", dst, msg);
+    let itinerary = Itinerary {
+        src: SrcLocation::Node(network.our_name().await),
+        dst,
+        aggregation: Aggregation::None,
+    };
+    network
+        .send_message(itinerary, msg.serialise()?)
+        .await
+        .map_err(|err| {
+            error!("Unable to send node msg: {:?}", err);
+            Error::Logic(format!("Unable to send node msg: {:?}", msg.id))
+        })
+}
+
+/// Sends the same msg between nodes to each of the nodes.
+pub(crate) async fn send_node_msg_to_nodes(
+    targets: BTreeSet<XorName>,
+    msg: &NodeMsg,
+    network: &Network,
+) -> Result<()> {
+    trace!("Sending node msg to nodes: {:?}: {:?}", targets, msg);
+    send_bytes_to_nodes(targets, msg.serialise()?, network).await
+}
+
+async fn send_bytes_to_nodes(
+    targets: BTreeSet<XorName>,
+    bytes: Bytes,
+    network: &Network,
+) -> Result<()> {
     let name = network.our_name().await;
-    let bytes = &msg.serialize()?;
     for target in targets {
         network
             .send_message(
@@ -72,3 +111,17 @@ pub(crate) async fn send_to_nodes(
     }
     Ok(())
 }
+
+/// Passes on the msg as is, e.g. one we could not parse, as we only relay it.
+pub(crate) async fn forward(bytes: Bytes, dst: DstLocation, network: &Network) -> Result<()> {
+    trace!("Forwarding msg of {} bytes to {:?}", bytes.len(), dst);
+    let itinerary = Itinerary {
+        src: SrcLocation::Node(network.our_name().await),
+        dst,
+        aggregation: Aggregation::None,
+    };
+    network.send_message(itinerary, bytes).await.map_err(|err| {
+        error!("Unable to forward msg: {:?}", err);
+        Error::Logic(format!("Unable to forward msg to {:?}", dst))
+    })
+}
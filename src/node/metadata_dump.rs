@@ -0,0 +1,64 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    metadata::{MetadataDump, SignedMetadataDump},
+    node_ops::NodeDuties,
+    Error, Node, Result,
+};
+use log::info;
+use sn_data_types::PublicKey;
+
+impl Node {
+    /// The metadata records of our section, in parts signed by us. Only Elders hold them.
+    pub(crate) async fn dump_section_metadata(&self) -> Result<Vec<SignedMetadataDump>> {
+        let meta_data = self.role.meta_data()?;
+        let key_set = self.network_api.our_public_key_set().await?;
+        let prefix = self.network_api.our_prefix().await;
+        let records = meta_data.dump_records(prefix).await?;
+        let mut signed = vec![];
+        for dump in MetadataDump::parts(key_set, prefix, records)? {
+            let share = self.network_api.sign_as_elder(&dump).await?;
+            signed.push(SignedMetadataDump { dump, share });
+        }
+        Ok(signed)
+    }
+
+    /// Adds the share of the part of a dump, importing the part once signed by enough of the
+    /// Elders of the section dumping. Unless authorised by the operator, the section must be
+    /// ours, as of any key of our chain. The records within our prefix are imported.
+    pub(crate) async fn import_section_metadata(
+        &mut self,
+        signed: SignedMetadataDump,
+        by_operator: bool,
+    ) -> Result<NodeDuties> {
+        let section_key = signed.dump.key_set.public_key();
+        if !by_operator && !self.network_api.section_chain().await.has_key(&section_key) {
+            return Err(Error::InvalidMetadataDump(format!(
+                "dumped by a section of key {:?}, not ours",
+                PublicKey::Bls(section_key)
+            )));
+        }
+        let dump = match self.metadata_imports.add(signed)? {
+            Some(dump) => dump,
+            None => return Ok(vec![]),
+        };
+        let our_prefix = self.network_api.our_prefix().await;
+        let (part, prefix) = (dump.part, dump.prefix);
+        let imported = self
+            .role
+            .meta_data_mut()?
+            .import_dump(dump, our_prefix)
+            .await?;
+        info!(
+            "Imported {} metadata records of part {} of {} dumped by {:?}",
+            imported, part.0, part.1, prefix
+        );
+        Ok(vec![])
+    }
+}
@@ -0,0 +1,454 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{client_trace::ClientTrace, congestion::Busy, duty_audit::MsgAuthority, pending_work};
+use crate::{
+    event_mapping::MsgContext,
+    node_ops::{NodeDuties, NodeDuty},
+    Error, Node,
+};
+use async_trait::async_trait;
+use log::{info, trace};
+use serde::{Deserialize, Serialize};
+use sn_data_types::PublicKey;
+use sn_messaging::{client::Message, SrcLocation};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// Clients not heard from in this long are forgotten by the rate limiting.
+const CLIENT_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// What a middleware makes of a duty about to be handled.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Admission {
+    /// To be handled.
+    Handle,
+    /// To be dropped, as a no-op.
+    Drop,
+    /// To be refused with the error, reported back to the origin of the msg.
+    Refuse(Error),
+}
+
+/// A concern applied around the handling of every duty, e.g. logging or
+/// authorisation, rather than inlined into the handling of each duty.
+/// Middlewares are applied in the order of the chain, until one of them
+/// does not admit the duty. All of them are told the outcome.
+#[async_trait]
+pub(crate) trait DutyMiddleware: Send + Sync {
+    /// A msg is about to be handled, starting with the duty it maps to.
+    async fn msg_started(
+        &mut self,
+        _node: &mut Node,
+        _duty: &NodeDuty,
+        _ctx: Option<&MsgContext>,
+    ) -> Admission {
+        Admission::Handle
+    }
+
+    /// The duties resulting from the last ones are about to be handled.
+    fn batch(&mut self, _duties: &mut Vec<NodeDuty>) {}
+
+    /// The duty is about to be handled.
+    fn before(&mut self, _node: &mut Node, _duty: &NodeDuty) -> Admission {
+        Admission::Handle
+    }
+
+    /// The duty has been handled, or refused.
+    fn after(&mut self, _node: &mut Node, _duty: &str, _outcome: Result<&NodeDuties, &Error>) {}
+
+    /// The msg, and all duties resulting from it, have been handled.
+    fn msg_completed(&mut self) {}
+}
+
+/// The middlewares applied around the handling of duties, in order.
+#[derive(Default)]
+pub(crate) struct MiddlewareChain {
+    middlewares: Vec<Box<dyn DutyMiddleware>>,
+}
+
+impl MiddlewareChain {
+    /// The chain of the node: tracing, metrics, tracking of the connected clients,
+    /// rate limiting of clients if configured, shedding of client cmds when congested,
+    /// authorisation, idempotency and logging of the duties handled, in that order.
+    pub(crate) fn new(client_msg_rate: Option<u32>) -> Self {
+        let mut chain = Self::default();
+        chain.push(Tracing::default());
+        chain.push(Metrics::default());
+        chain.push(Sessions);
+        if let Some(rate) = client_msg_rate {
+            chain.push(ClientRateLimit::new(rate));
+        }
+        chain.push(Congestion);
+        chain.push(Authority::default());
+        chain.push(Idempotency);
+        chain.push(DutyLogging::default());
+        chain
+    }
+
+    pub(crate) fn push(&mut self, middleware: impl DutyMiddleware + 'static) {
+        self.middlewares.push(Box::new(middleware));
+    }
+
+    pub(crate) async fn msg_started(
+        &mut self,
+        node: &mut Node,
+        duty: &NodeDuty,
+        ctx: Option<&MsgContext>,
+    ) -> Admission {
+        for middleware in &mut self.middlewares {
+            match middleware.msg_started(node, duty, ctx).await {
+                Admission::Handle => (),
+                other => return other,
+            }
+        }
+        Admission::Handle
+    }
+
+    pub(crate) fn batch(&mut self, duties: &mut Vec<NodeDuty>) {
+        for middleware in &mut self.middlewares {
+            middleware.batch(duties);
+        }
+    }
+
+    pub(crate) fn before(&mut self, node: &mut Node, duty: &NodeDuty) -> Admission {
+        for middleware in &mut self.middlewares {
+            match middleware.before(node, duty) {
+                Admission::Handle => (),
+                other => return other,
+            }
+        }
+        Admission::Handle
+    }
+
+    pub(crate) fn after(
+        &mut self,
+        node: &mut Node,
+        duty: &str,
+        outcome: Result<&NodeDuties, &Error>,
+    ) {
+        for middleware in &mut self.middlewares {
+            middleware.after(node, duty, outcome);
+        }
+    }
+
+    pub(crate) fn msg_completed(&mut self) {
+        for middleware in &mut self.middlewares {
+            middleware.msg_completed();
+        }
+    }
+}
+
+/// Logs the handling of msgs from clients, by their trace token.
+#[derive(Default)]
+struct Tracing {
+    trace: Option<ClientTrace>,
+}
+
+#[async_trait]
+impl DutyMiddleware for Tracing {
+    async fn msg_started(
+        &mut self,
+        _node: &mut Node,
+        _duty: &NodeDuty,
+        ctx: Option<&MsgContext>,
+    ) -> Admission {
+        self.trace = ClientTrace::of(ctx);
+        Admission::Handle
+    }
+
+    fn before(&mut self, _node: &mut Node, duty: &NodeDuty) -> Admission {
+        if let Some(trace) = &mut self.trace {
+            trace.duty(duty.name());
+        }
+        Admission::Handle
+    }
+
+    fn after(&mut self, _node: &mut Node, _duty: &str, outcome: Result<&NodeDuties, &Error>) {
+        if let (Some(trace), Err(error)) = (&self.trace, outcome) {
+            trace.error(error);
+        }
+    }
+
+    fn msg_completed(&mut self) {
+        self.trace = None;
+    }
+}
+
+/// Counters of the handling of a duty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DutyStats {
+    /// Times handled, or refused.
+    pub handled: u64,
+    /// Times failed, or refused.
+    pub failed: u64,
+    /// Milliseconds spent handling it, in all.
+    pub total_millis: u64,
+}
+
+/// The handling of the duties since the node started, by duty.
+pub type DutyMetrics = BTreeMap<String, DutyStats>;
+
+fn record(metrics: &mut DutyMetrics, duty: &str, failed: bool, elapsed: Duration) {
+    let stats = metrics.entry(duty.to_string()).or_default();
+    stats.handled += 1;
+    if failed {
+        stats.failed += 1;
+    }
+    stats.total_millis += elapsed.as_millis() as u64;
+}
+
+/// Counts and times the handling of duties.
+#[derive(Default)]
+struct Metrics {
+    started: Option<Instant>,
+}
+
+#[async_trait]
+impl DutyMiddleware for Metrics {
+    fn before(&mut self, _node: &mut Node, _duty: &NodeDuty) -> Admission {
+        self.started = Some(Instant::now());
+        Admission::Handle
+    }
+
+    fn after(&mut self, node: &mut Node, duty: &str, outcome: Result<&NodeDuties, &Error>) {
+        let elapsed = self
+            .started
+            .take()
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        record(&mut node.duty_metrics, duty, outcome.is_err(), elapsed);
+    }
+}
+
+/// Keeps track of the clients connected to us, by their msgs.
+struct Sessions;
+
+#[async_trait]
+impl DutyMiddleware for Sessions {
+    async fn msg_started(
+        &mut self,
+        node: &mut Node,
+        _duty: &NodeDuty,
+        ctx: Option<&MsgContext>,
+    ) -> Admission {
+        if let Some(MsgContext::Msg {
+            src: SrcLocation::EndUser(client),
+            ..
+        }) = ctx
+        {
+            node.client_sessions.seen(*client, Instant::now());
+        }
+        Admission::Handle
+    }
+}
+
+/// Refuses msgs from a client beyond the rate given, per second.
+struct ClientRateLimit {
+    rate: u32,
+    // msgs seen per client, since the start of its window
+    clients: BTreeMap<PublicKey, (Instant, u32)>,
+}
+
+impl ClientRateLimit {
+    fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            clients: BTreeMap::new(),
+        }
+    }
+
+    fn admit(&mut self, client: PublicKey, now: Instant) -> bool {
+        self.clients
+            .retain(|_, (started, _)| now.saturating_duration_since(*started) < CLIENT_RATE_WINDOW);
+        let (_, count) = self.clients.entry(client).or_insert((now, 0));
+        *count += 1;
+        *count <= self.rate
+    }
+}
+
+#[async_trait]
+impl DutyMiddleware for ClientRateLimit {
+    async fn msg_started(
+        &mut self,
+        _node: &mut Node,
+        _duty: &NodeDuty,
+        ctx: Option<&MsgContext>,
+    ) -> Admission {
+        let client = match ctx {
+            Some(MsgContext::Msg {
+                src: SrcLocation::EndUser(user),
+                ..
+            }) => *user.id(),
+            _ => return Admission::Handle,
+        };
+        if self.admit(client, Instant::now()) {
+            Admission::Handle
+        } else {
+            Admission::Refuse(Error::ClientRateExceeded(client))
+        }
+    }
+}
+
+/// Refuses new cmds from clients while the node is congested, telling them
+/// when to retry by the work queued, rather than taking on more of it.
+struct Congestion;
+
+#[async_trait]
+impl DutyMiddleware for Congestion {
+    async fn msg_started(
+        &mut self,
+        node: &mut Node,
+        _duty: &NodeDuty,
+        ctx: Option<&MsgContext>,
+    ) -> Admission {
+        if !matches!(
+            ctx,
+            Some(MsgContext::Msg {
+                msg: Message::Cmd { .. },
+                src: SrcLocation::EndUser(_),
+            })
+        ) {
+            return Admission::Handle;
+        }
+        match Busy::at_depth(node.queue_depth()) {
+            Some(busy) => Admission::Refuse(Error::Busy(busy)),
+            None => Admission::Handle,
+        }
+    }
+}
+
+/// Refuses the duties of msgs without the authority for them, be they the duty the msg
+/// maps to or those resulting from it.
+#[derive(Default)]
+struct Authority {
+    // the authority of the msg being handled, if the duties result from one
+    msg: Option<MsgAuthority>,
+}
+
+#[async_trait]
+impl DutyMiddleware for Authority {
+    async fn msg_started(
+        &mut self,
+        node: &mut Node,
+        _duty: &NodeDuty,
+        ctx: Option<&MsgContext>,
+    ) -> Admission {
+        self.msg = node.msg_authority(ctx).await;
+        Admission::Handle
+    }
+
+    fn before(&mut self, node: &mut Node, duty: &NodeDuty) -> Admission {
+        let msg = match &self.msg {
+            Some(msg) => msg,
+            None => return Admission::Handle,
+        };
+        match node.audit_duty(duty, msg) {
+            Ok(()) => Admission::Handle,
+            Err(error) => Admission::Refuse(error),
+        }
+    }
+
+    fn msg_completed(&mut self) {
+        self.msg = None;
+    }
+}
+
+/// Drops the duties superseded by, or cancelled by, others.
+struct Idempotency;
+
+#[async_trait]
+impl DutyMiddleware for Idempotency {
+    fn batch(&mut self, duties: &mut Vec<NodeDuty>) {
+        pending_work::drop_superseded(duties);
+    }
+
+    fn before(&mut self, node: &mut Node, duty: &NodeDuty) -> Admission {
+        if node.pending_work.admit(duty) {
+            Admission::Handle
+        } else {
+            Admission::Drop
+        }
+    }
+}
+
+/// Logs the duties handled, in full only those passing the filters set by the operator,
+/// along with their outcome, if any filters are set.
+#[derive(Default)]
+struct DutyLogging {
+    // whether the duty being handled passed the filters set
+    filtered: bool,
+}
+
+#[async_trait]
+impl DutyMiddleware for DutyLogging {
+    fn before(&mut self, node: &mut Node, duty: &NodeDuty) -> Admission {
+        let filters = &node.duty_log_filters;
+        self.filtered = !filters.is_empty() && {
+            let context = duty.context();
+            filters.iter().any(|filter| filter.passes(&context))
+        };
+        if filters.is_empty() || self.filtered {
+            info!("Handling NodeDuty: {:?}", duty);
+        } else {
+            trace!("Handling NodeDuty: {}", duty.name());
+        }
+        Admission::Handle
+    }
+
+    fn after(&mut self, _node: &mut Node, duty: &str, outcome: Result<&NodeDuties, &Error>) {
+        if !std::mem::take(&mut self.filtered) {
+            return;
+        }
+        match outcome {
+            Ok(duties) => info!(
+                "{} resulted in: {:?}",
+                duty,
+                duties.iter().map(NodeDuty::name).collect::<Vec<_>>()
+            ),
+            Err(error) => info!("{} failed: {}", duty, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+
+    #[test]
+    fn clients_are_limited_per_window() {
+        let client = PublicKey::from(SecretKey::random().public_key());
+        let other = PublicKey::from(SecretKey::random().public_key());
+        let mut limit = ClientRateLimit::new(2);
+        let now = Instant::now();
+
+        assert!(limit.admit(client, now));
+        assert!(limit.admit(client, now));
+        assert!(!limit.admit(client, now));
+        assert!(limit.admit(other, now));
+        assert!(limit.admit(client, now + CLIENT_RATE_WINDOW));
+    }
+
+    #[test]
+    fn duties_are_counted_with_failures() {
+        let mut metrics = DutyMetrics::new();
+        record(&mut metrics, "GetBalance", false, Duration::from_millis(3));
+        record(&mut metrics, "GetBalance", true, Duration::from_millis(2));
+
+        assert_eq!(
+            metrics["GetBalance"],
+            DutyStats {
+                handled: 2,
+                failed: 1,
+                total_millis: 5,
+            }
+        );
+    }
+}
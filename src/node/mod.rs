@@ -6,34 +6,108 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod batch_read;
+mod client_sessions;
+mod client_trace;
+mod congestion;
+mod decommission;
+mod duty_audit;
+mod duty_logging;
+mod egress;
+mod events;
+mod funds_migration;
+mod genesis;
 mod handle;
 mod interaction;
 mod member_churn;
 mod messaging;
+mod metadata_dump;
+mod middleware;
+mod operator_commands;
+mod pending_queries;
+mod pending_work;
+mod redirect;
+mod restarts;
+mod role;
+mod section_params;
 mod split;
+mod standby;
+mod state_push;
+mod status;
+mod supervisor;
+mod wallet_compaction;
 
+pub use self::{
+    batch_read::{batch_item_id, BatchItem, BatchQueryResponse, MAX_BATCH_QUERIES},
+    client_sessions::SectionUpdate,
+    congestion::{Busy, MAX_QUEUE_DEPTH},
+    decommission::{DecommissionStatus, EarningsRecord},
+    duty_audit::{DutyRefusal, RefusedDuties},
+    duty_logging::DutyLogFilter,
+    egress::EgressStats,
+    events::NodeEvent,
+    middleware::{DutyMetrics, DutyStats},
+    pending_queries::{PendingQueryStats, SectionQueryStats, QUERY_TIMED_OUT},
+    redirect::SectionRedirect,
+    restarts::{RestartBackoff, MAX_RESTART_DELAY, MIN_RESTART_DELAY, STABLE_RUN},
+    section_params::SectionParams,
+    state_push::StatePushAck,
+    status::NodeStatus,
+};
+use self::{
+    client_sessions::ClientSessions,
+    decommission::Decommission,
+    duty_audit::DutyAudit,
+    egress::EgressShaper,
+    events::NodeEvents,
+    middleware::{Admission, MiddlewareChain},
+    pending_queries::PendingQueries,
+    pending_work::PendingWork,
+    role::{AdultState, Role},
+    standby::WarmStandby,
+    state_push::StatePushes,
+    supervisor::TaskHealth,
+};
 use crate::{
-    capacity::{Capacity, ChunkHolderDbs, RateLimit},
-    chunk_store::UsedSpace,
-    chunks::Chunks,
+    capacity::{
+        Capacity, ChunkHolderDbs, ChunkSizeAdvice, EconomyConfig, RateLimit, MIN_CHUNK_SIZE,
+    },
+    chunk_store::{ChunkCompression, UsedSpace},
+    chunks::{
+        self, Capability, ChunkAccess, ChunkImportReport, ChunkStore, Chunks, ReadCacheStats,
+        ReplicationObserver, ScrubSchedule, StoragePolicy,
+    },
+    error::convert_to_error_message,
     event_mapping::{map_routing_event, LazyError, Mapping, MsgContext},
-    metadata::{adult_reader::AdultReader, Metadata},
-    node_ops::{NodeDuties, NodeDuty},
-    section_funds::SectionFunds,
-    state_db::store_new_reward_keypair,
-    transfers::get_replicas::transfer_replicas,
-    transfers::Transfers,
-    Config, Error, Network, Result,
+    metadata::{
+        self, adult_reader::AdultReader, AdultLiveness, CapacityHistogram, ErasureCoding, HotData,
+        MapMerge, MetadataImports, WriteQuotas, HOT_DATA_TOP,
+    },
+    network::{self, NetworkClock, PeerLatency},
+    node_msg::{ClientMsg, NodeMsg, NodeMsgBody},
+    node_ops::{NodeDuties, NodeDuty, OutgoingMsg, PeriodicTask},
+    operator::{inbox, OperatorAuth},
+    persistence,
+    section_funds::{reward_stage::RewardStatus, FundsImports},
+    state_db::{get_or_create_node_keypair, get_reward_public_key, store_new_reward_keypair},
+    transfers::{self, get_replicas::transfer_replicas},
+    utils, Config, Error, Network, Result,
 };
 use bls::SecretKey;
 use ed25519_dalek::PublicKey as Ed25519PublicKey;
 use futures::lock::Mutex;
 use hex_fmt::HexFmt;
 use log::{debug, error, info, trace, warn};
-use sn_data_types::{ActorHistory, PublicKey, TransferPropagated, WalletHistory};
-use sn_messaging::{client::Message, DstLocation, SrcLocation};
+use sn_data_types::{
+    ActorHistory, Blob, BlobAddress, PublicBlob, PublicKey, SectionElders, TransferPropagated,
+    WalletHistory,
+};
+use sn_messaging::{
+    client::{Error as ErrorMessage, Message, NodeDataQueryResponse, NodeQueryResponse},
+    Aggregation, DstLocation, MessageId, SrcLocation,
+};
 use sn_routing::{Event as RoutingEvent, EventStream, NodeElderChange, MIN_AGE};
-use sn_routing::{Prefix, XorName, ELDER_SIZE as GENESIS_ELDER_COUNT};
+use sn_routing::{Prefix, SectionChain, XorName, ELDER_SIZE as GENESIS_ELDER_COUNT};
 use sn_transfers::{TransferActor, Wallet};
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
@@ -41,7 +115,16 @@ use std::sync::Arc;
 use std::{
     fmt::{self, Display, Formatter},
     net::SocketAddr,
+    time::{Duration, Instant},
 };
+use tokio::{
+    sync::{broadcast, mpsc},
+    time,
+};
+
+// Duties from background tasks waiting to be processed.
+const BACKGROUND_DUTY_BUFFER: usize = 64;
+const OPERATOR_INBOX_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Static info about the node.
 #[derive(Clone)]
@@ -56,6 +139,42 @@ pub struct NodeInfo {
     pub node_id: Ed25519PublicKey,
     /// The key used by the node to receive earned rewards.
     pub reward_key: PublicKey,
+    /// The parameters of our section, as set at genesis and pushed by its Elders.
+    pub section_params: SectionParams,
+    /// Bytes of a msg at most, if limited.
+    pub max_msg_size: Option<u32>,
+    /// Whether to keep a warm copy of the Elder state while next in line for promotion.
+    pub warm_standby: bool,
+    /// When to verify the stored chunks, if at all.
+    pub scrubbing: Option<ScrubSchedule>,
+    /// Times restarted by the supervisor of the node.
+    pub restarts: u32,
+    /// Whether removed chunks are overwritten before unlinking them.
+    pub secure_delete: bool,
+    /// Whether the chunks to replicate are sent to each new holder in batches.
+    pub batch_chunk_replication: bool,
+    /// Bytes buffered at most when writing a chunk to disk, if configured.
+    pub chunk_write_buffer: Option<usize>,
+    /// How the chunks written to disk are compressed, if configured.
+    pub chunk_compression: Option<ChunkCompression>,
+    /// Bytes of a chunk at most accepted by our section, if configured or pushed by its Elders.
+    pub max_chunk_size: Option<u64>,
+    /// How much disk the chunks stored may use, if bounded by a policy.
+    pub storage_policy: Option<StoragePolicy>,
+    /// Bytes of chunks read to keep in memory at most, if they are cached.
+    pub chunk_read_cache: Option<u64>,
+    /// Time between the rounds of storage challenges issued while an Elder, if issued.
+    pub storage_challenge_interval: Option<Duration>,
+    /// Chunks asked for at most at a time while replicating them to us, if configured.
+    pub replication_concurrency: Option<usize>,
+    /// Holders queried at a time by the reads relayed while an Elder, if reads are relayed.
+    pub relayed_read_holders: Option<usize>,
+    /// The quotas on the writes to each Sequence and Map while an Elder.
+    pub write_quotas: WriteQuotas,
+    /// The shards new chunks are erasure coded into while an Elder, if they are.
+    pub erasure_coding: Option<ErasureCoding>,
+    /// The capability of the node, as benchmarked on its first join, unless that failed.
+    pub capability: Option<Capability>,
 }
 
 impl NodeInfo {
@@ -63,6 +182,20 @@ impl NodeInfo {
     pub fn path(&self) -> &Path {
         self.root_dir.as_path()
     }
+
+    /// Bytes of a chunk at most accepted by our section, by default the max
+    /// advised for the store cost of our economy and the max msg size.
+    /// Never below the least size data is chunked into.
+    pub fn max_chunk_size(&self) -> u64 {
+        self.max_chunk_size
+            .unwrap_or_else(|| {
+                self.section_params
+                    .economy
+                    .chunk_size_advice(self.max_msg_size)
+                    .max_chunk_size
+            })
+            .max(MIN_CHUNK_SIZE)
+    }
 }
 
 /// Main node struct.
@@ -72,26 +205,82 @@ pub struct Node {
     node_info: NodeInfo,
     used_space: UsedSpace,
     prefix: Prefix,
-    // immutable chunks
-    chunks: Option<Chunks>,
-    // data operations
-    meta_data: Option<Metadata>,
-    // transfers
-    transfers: Option<Transfers>,
-    // reward payouts
-    section_funds: Option<SectionFunds>,
+    // the state of our role: immutable chunks as an Adult, or
+    // data operations, transfers and reward payouts as an Elder
+    role: Role,
+    // authorisation of operator commands
+    operator_auth: OperatorAuth,
+    // address and certificates dir of the operator control socket, if opened
+    operator_rpc: Option<(SocketAddr, PathBuf)>,
+    maintenance_mode: bool,
+    shutting_down: bool,
+    // work in progress, which later duties may cancel
+    pending_work: PendingWork,
+    // answer to `GetSectionElders`, refreshed when elders change
+    section_elders: Option<SectionElders>,
+    // lifecycle events to subscribers
+    events: NodeEvents,
+    // the section chain joined, if verified against a trusted genesis key
+    verified_chain: Option<SectionChain>,
+    // merges conflicting writes to Maps with the merge strategy, if registered
+    map_merge: Option<Arc<dyn MapMerge>>,
+    // told of the replication of chunks to us, if registered
+    replication_observer: Option<Arc<dyn ReplicationObserver>>,
+    // shapes the responses to clients
+    egress: EgressShaper,
+    // duties refused for lack of authority
+    duty_audit: DutyAudit,
+    // clients connected to us, to push section updates to
+    client_sessions: ClientSessions,
+    // applied around the handling of every duty
+    middleware: MiddlewareChain,
+    // counters of the duties handled
+    duty_metrics: DutyMetrics,
+    // the backoff of background tasks whose work panicked
+    task_health: TaskHealth,
+    // the duties logged in full, all if none
+    duty_log_filters: Vec<DutyLogFilter>,
+    // whether our clock was last found skewed beyond what is alerted on
+    clock_skewed: bool,
+    // the chunks yet to be handed over, if being decommissioned
+    decommission: Option<Decommission>,
+    // the queries forwarded to other sections, until timed out
+    pending_queries: PendingQueries,
+    // the pushes of our state to new Elders, until acknowledged
+    state_pushes: StatePushes,
+    // the shares of the exports of section funds received, until enough to import them
+    funds_imports: FundsImports,
+    // the shares of the dumps of section metadata received, until enough to import them
+    metadata_imports: MetadataImports,
 }
 
 impl Node {
-    /// Initialize a new node.
+    /// Initialize a new node, storing chunks on disk under its root dir.
+    pub async fn new(config: &Config) -> Result<Self> {
+        Self::start(config, None).await
+    }
+
+    /// Initialize a new node, storing chunks in the store given, rather than on disk.
+    /// The store is kept while the node is an Elder, to store chunks in again if demoted.
+    pub async fn with_chunk_store(
+        config: &Config,
+        chunk_store: Box<dyn ChunkStore>,
+    ) -> Result<Self> {
+        Self::start(config, Some(chunk_store)).await
+    }
+
     /// https://github.com/rust-lang/rust-clippy/issues?q=is%3Aissue+is%3Aopen+eval_order_dependence
     #[allow(clippy::eval_order_dependence)]
-    pub async fn new(config: &Config) -> Result<Self> {
+    async fn start(config: &Config, chunk_store: Option<Box<dyn ChunkStore>>) -> Result<Self> {
         // TODO: STARTUP all things
         let root_dir_buf = config.root_dir()?;
         let root_dir = root_dir_buf.as_path();
         std::fs::create_dir_all(root_dir)?;
+        // the changes of a duty interrupted by a crash are applied in full, before any db is opened
+        persistence::recover(root_dir)?;
 
+        // restarted by the supervisor, thus keeping the keys of the node
+        let supervised = config.restarts().is_some();
         let reward_key_task = async move {
             let res: Result<PublicKey>;
             match config.wallet_id() {
@@ -99,9 +288,20 @@ impl Node {
                     res = Ok(PublicKey::Bls(crate::state_db::pk_from_hex(public_key)?));
                 }
                 None => {
-                    let secret = SecretKey::random();
-                    let public = secret.public_key();
-                    store_new_reward_keypair(root_dir, &secret, &public).await?;
+                    let stored = if supervised {
+                        get_reward_public_key(root_dir).await?
+                    } else {
+                        None
+                    };
+                    let public = match stored {
+                        Some(public) => public,
+                        None => {
+                            let secret = SecretKey::random();
+                            let public = secret.public_key();
+                            store_new_reward_keypair(root_dir, &secret, &public).await?;
+                            public
+                        }
+                    };
                     res = Ok(PublicKey::Bls(public));
                 }
             };
@@ -110,7 +310,31 @@ impl Node {
         .await;
 
         let reward_key = reward_key_task?;
-        let (network_api, network_events) = Network::new(config).await?;
+        let keypair = if supervised {
+            Some(get_or_create_node_keypair(root_dir).await?)
+        } else {
+            None
+        };
+        let (network_api, network_events) = Network::new(config, keypair).await?;
+
+        // benchmarked once joined, as the benchmark is kept for subsequent joins
+        let capability = match Capability::of_node(&root_dir_buf) {
+            Ok(capability) => Some(capability),
+            Err(error) => {
+                warn!("Could not benchmark the capability of the node: {}", error);
+                None
+            }
+        };
+
+        // refuse to take part in a network not of the trusted genesis key
+        let verified_chain = match config.genesis_key() {
+            Some(key) if !config.is_first() => {
+                let chain = network_api.section_chain().await;
+                genesis::verify_section_chain(&chain, &crate::state_db::pk_from_hex(key)?)?;
+                Some(chain)
+            }
+            _ => None,
+        };
 
         let node_info = NodeInfo {
             genesis: config.is_first(),
@@ -118,29 +342,87 @@ impl Node {
             node_name: network_api.our_name().await,
             node_id: network_api.public_key().await,
             reward_key,
+            // taken from our Elders when joining, unless we are the genesis node
+            section_params: if config.is_first() {
+                SectionParams::genesis(config)
+            } else {
+                SectionParams::default()
+            },
+            max_msg_size: config.network_config().max_msg_size_allowed,
+            warm_standby: config.warm_standby(),
+            scrubbing: config.scrubbing(),
+            restarts: config.restarts().unwrap_or_default(),
+            secure_delete: config.secure_delete(),
+            batch_chunk_replication: config.batch_chunk_replication(),
+            chunk_write_buffer: config.chunk_write_buffer(),
+            chunk_compression: config.chunk_compression(),
+            storage_challenge_interval: config
+                .storage_challenge_interval_msec()
+                .map(Duration::from_millis),
+            max_chunk_size: config.max_chunk_size(),
+            storage_policy: config.storage_policy(),
+            chunk_read_cache: config.chunk_read_cache(),
+            replication_concurrency: config.replication_concurrency(),
+            relayed_read_holders: config.relayed_read_holders(),
+            write_quotas: config.write_quotas(),
+            erasure_coding: config.erasure_coding(),
+            capability,
         };
 
         let used_space = UsedSpace::new(config.max_capacity());
+        for (category, limit) in config.used_space_limits() {
+            used_space.set_limit(category, limit).await;
+        }
+
+        let operator_key = match config.operator_key() {
+            Some(key) => Some(crate::state_db::pk_from_hex(key)?),
+            None => None,
+        };
+        let operator_auth = OperatorAuth::new(node_info.path(), operator_key, network_api.clock())?;
+        let operator_rpc = match config.operator_rpc_addr() {
+            Some(addr) => Some((addr, config.operator_tls_dir()?)),
+            None => None,
+        };
 
-        let node = Self {
+        let chunks = open_chunks(&node_info, &used_space, chunk_store, network_api.clock()).await?;
+        let mut role = Role::Infant;
+        role.become_adult(AdultState {
+            chunks,
+            standby: node_info.warm_standby.then(WarmStandby::default),
+        })?;
+        let mut node = Self {
             prefix: network_api.our_prefix().await,
-            chunks: Some(
-                Chunks::new(
-                    node_info.node_name,
-                    node_info.root_dir.as_path(),
-                    used_space.clone(),
-                )
-                .await?,
-            ),
+            role,
             node_info,
             used_space,
             network_api,
             network_events,
-            meta_data: None,
-            transfers: None,
-            section_funds: None,
+            operator_auth,
+            operator_rpc,
+            maintenance_mode: false,
+            shutting_down: false,
+            pending_work: PendingWork::default(),
+            section_elders: None,
+            events: NodeEvents::new(),
+            verified_chain,
+            map_merge: None,
+            replication_observer: None,
+            egress: EgressShaper::new(config.egress_rate(), config.egress_client_rate()),
+            duty_audit: DutyAudit::default(),
+            client_sessions: ClientSessions::default(),
+            middleware: MiddlewareChain::new(config.client_msg_rate()),
+            duty_metrics: DutyMetrics::new(),
+            task_health: TaskHealth::default(),
+            duty_log_filters: vec![],
+            clock_skewed: false,
+            decommission: None,
+            pending_queries: PendingQueries::new(
+                config.query_hedge_after_msec().map(Duration::from_millis),
+            ),
+            state_pushes: StatePushes::default(),
+            funds_imports: FundsImports::default(),
+            metadata_imports: MetadataImports::default(),
         };
-
         messaging::send(node.register_wallet().await, &node.network_api).await;
 
         Ok(node)
@@ -161,35 +443,356 @@ impl Node {
         self.network_api.our_prefix().await
     }
 
+    /// Subscribes to the lifecycle events of the node.
+    /// Events are only received from the time of subscribing.
+    pub fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+
+    /// The section chain of the network joined, if verified
+    /// to be anchored to the configured genesis key.
+    pub fn verified_section_chain(&self) -> Option<&SectionChain> {
+        self.verified_chain.as_ref()
+    }
+
+    /// Registers the merge of conflicting writes to Maps created with the
+    /// merge strategy. Without it, such Maps can not be created at this node.
+    pub fn set_map_merge(&mut self, merge: Arc<dyn MapMerge>) {
+        if let Ok(meta_data) = self.role.meta_data_mut() {
+            meta_data.set_map_merge(merge.clone());
+        }
+        self.map_merge = Some(merge);
+    }
+
+    /// Registers the observer of the replication of chunks to the node, told of each step
+    /// of it, e.g. for test harnesses to track the data moved during churn.
+    #[cfg(feature = "replication-events")]
+    pub fn set_replication_observer(&mut self, observer: Arc<dyn ReplicationObserver>) {
+        if let Ok(chunks) = self.role.chunks_mut() {
+            chunks.set_replication_observer(observer.clone());
+        }
+        self.replication_observer = Some(observer);
+    }
+
+    /// The progress of the reward process of our section, when we are an Elder,
+    /// naming the Elders yet to sign the credits being paid out.
+    pub async fn reward_status(&self) -> Result<RewardStatus> {
+        let section_funds = self.role.section_funds()?;
+        // in order of key share index
+        let elders: Vec<_> = self
+            .network_api
+            .our_elder_names()
+            .await
+            .into_iter()
+            .collect();
+        Ok(section_funds.reward_status(&elders))
+    }
+
+    /// Counters of the responses to clients, and of those held back
+    /// for their client having used up its share of the uplink.
+    pub fn egress_stats(&self) -> EgressStats {
+        self.egress.stats()
+    }
+
+    /// The most read and written data of our section over the last `HOT_DATA_WINDOW`,
+    /// when we are an Elder, as seen by us.
+    pub fn hot_data(&self) -> Result<HotData> {
+        let meta_data = self.role.meta_data()?;
+        Ok(meta_data.hot_data(HOT_DATA_TOP))
+    }
+
+    /// The reads of the most read chunks of each of our Adults, as last reported
+    /// by it, when we are an Elder.
+    pub fn chunk_access(&self) -> Result<BTreeMap<XorName, Vec<(BlobAddress, ChunkAccess)>>> {
+        let meta_data = self.role.meta_data()?;
+        Ok(meta_data.chunk_access())
+    }
+
+    /// The liveness of each of our Adults, by their answers to our queries,
+    /// when we are an Elder.
+    pub fn adult_liveness(&self) -> Result<BTreeMap<XorName, AdultLiveness>> {
+        let meta_data = self.role.meta_data()?;
+        Ok(meta_data.adult_liveness())
+    }
+
+    /// The capacity of the storage of our Adults, as last reported by them,
+    /// when we are an Elder.
+    pub async fn capacity_histogram(&mut self) -> Result<CapacityHistogram> {
+        let meta_data = self.role.meta_data_mut()?;
+        Ok(meta_data.capacity_histogram().await)
+    }
+
+    /// Counters of the chunk read cache, when we are an Adult, if chunks are cached.
+    pub fn chunk_read_cache_stats(&self) -> Result<Option<ReadCacheStats>> {
+        let chunks = self.role.chunks()?;
+        Ok(chunks.read_cache_stats())
+    }
+
+    /// The progress of the last import of chunks by the operator, if any.
+    pub fn chunk_import_report(&self) -> Result<Option<ChunkImportReport>> {
+        let chunks = self.role.chunks()?;
+        Ok(chunks.import_report())
+    }
+
+    /// The queries forwarded to other sections and pending, and
+    /// the counters of those forwarded to each section.
+    pub fn pending_query_stats(&self) -> PendingQueryStats {
+        self.pending_queries.stats()
+    }
+
+    /// Counters of the duties handled since the node started.
+    pub fn duty_metrics(&self) -> DutyMetrics {
+        self.duty_metrics.clone()
+    }
+
+    /// The round trip time to each of the peers of our section probed, and the pings
+    /// they lost.
+    pub fn peer_latency(&self) -> BTreeMap<XorName, PeerLatency> {
+        self.network_api.probes().latencies()
+    }
+
+    /// The sizes of chunks for clients to self-encrypt data into, for the store
+    /// cost of our economy and the max msg size, within the max chunk size of our section.
+    pub fn chunk_size_advice(&self) -> ChunkSizeAdvice {
+        let mut advice = self
+            .node_info
+            .section_params
+            .economy
+            .chunk_size_advice(self.node_info.max_msg_size);
+        advice.max_chunk_size = self.node_info.max_chunk_size();
+        advice.min_chunk_size = advice.min_chunk_size.min(advice.max_chunk_size);
+        advice
+    }
+
+    /// Sets the parameters of our section, as pushed by its Elders.
+    pub(crate) fn set_section_params(&mut self, params: SectionParams) {
+        if self.node_info.section_params != params {
+            info!("Parameters of our section set to {:?}", params);
+        }
+        self.node_info.section_params = params;
+        if let Ok(meta_data) = self.role.meta_data_mut() {
+            meta_data.set_min_write_acks(params.min_write_acks);
+        }
+        if let Ok(transfers) = self.role.transfers_mut() {
+            transfers.set_economy(params.economy);
+        }
+    }
+
+    /// Sets the max chunk size of our section, as pushed by its Elders.
+    pub(crate) fn set_max_chunk_size(&mut self, max_chunk_size: u64) {
+        if self.node_info.max_chunk_size() != max_chunk_size {
+            info!("Max chunk size of our section set to {}", max_chunk_size);
+        }
+        self.node_info.max_chunk_size = Some(max_chunk_size);
+        if let Ok(chunks) = self.role.chunks_mut() {
+            chunks.set_max_chunk_size(max_chunk_size);
+        }
+    }
+
+    /// Whether calls to the routing layer keep failing,
+    /// and the node is degraded until they succeed again.
+    pub fn is_network_degraded(&self) -> bool {
+        self.network_api.is_degraded()
+    }
+
+    /// Judges our clock by the times reported by peers, correcting for its skew in
+    /// network time. Skew beyond what is alerted on is reported to subscribers,
+    /// once per time our clock is found skewed.
+    fn check_clock(&mut self) {
+        let skew = self.network_api.clock().check(Instant::now());
+        let alerting = skew.filter(|skew| skew.is_alerting());
+        if let Some(skew) = alerting {
+            warn!(
+                "Our clock is {} secs behind the median of {} peers, correcting for it",
+                skew.behind_secs, skew.peers
+            );
+            if !self.clock_skewed {
+                self.events.emit(NodeEvent::ClockSkewed {
+                    behind_secs: skew.behind_secs,
+                    peers: skew.peers,
+                });
+            }
+        }
+        self.clock_skewed = alerting.is_some();
+    }
+
+    /// The background work of the node, as set up by the modules owning it.
+    fn periodic_tasks(&self) -> Vec<PeriodicTask> {
+        let mut tasks = chunks::periodic_tasks(self.node_info.scrubbing.is_some());
+        tasks.extend(metadata::periodic_tasks(
+            self.node_info.relayed_read_holders.is_some(),
+            self.node_info.storage_challenge_interval,
+        ));
+        tasks.extend(network::periodic_tasks());
+        tasks.extend(transfers::periodic_tasks());
+        tasks.extend(self.pending_queries.periodic_tasks());
+        tasks.extend(state_push::periodic_tasks());
+        tasks
+    }
+
     /// Starts the node, and runs the main event loop.
     /// Blocks until the node is terminated, which is done
-    /// by client sending in a `Command` to free it.
+    /// by the operator sending in a signed `Shutdown` command.
     pub async fn run(&mut self) -> Result<()> {
-        while let Some(event) = self.network_events.next().await {
-            // tokio spawn should only be needed around intensive tasks, ie sign/verify
-            match map_routing_event(event, &self.network_api).await {
-                Mapping::Ok { op, ctx } => self.process_while_any(op, ctx).await,
-                Mapping::Error(error) => handle_error(error),
+        let (duty_sender, mut background_duties) = mpsc::channel(BACKGROUND_DUTY_BUFFER);
+        // the work of the periodic tasks is received apart, as it alone is supervised
+        let (periodic_sender, mut periodic_duties) = mpsc::channel(BACKGROUND_DUTY_BUFFER);
+        let _periodic_tasks: Vec<_> = self
+            .periodic_tasks()
+            .into_iter()
+            .map(|task| {
+                supervisor::spawn_periodic(
+                    task.name,
+                    task.interval,
+                    periodic_sender.clone(),
+                    self.task_health.clone(),
+                    task.duty,
+                )
+            })
+            .collect();
+        let _operator_inbox = supervisor::spawn_operator_inbox(
+            inbox::inbox_dir(self.node_info.path()),
+            OPERATOR_INBOX_INTERVAL,
+            duty_sender.clone(),
+        );
+        let _operator_rpc = self
+            .operator_rpc
+            .clone()
+            .map(|(addr, tls_dir)| supervisor::spawn_operator_rpc(addr, tls_dir, duty_sender));
+
+        info!(
+            "Operator commands are read from {:?}",
+            inbox::inbox_dir(self.node_info.path())
+        );
+        while !self.shutting_down {
+            let egress_wait = self.egress.wait(Instant::now());
+            tokio::select! {
+                event = self.network_events.next() => {
+                    let event = match event {
+                        Some(event) => event,
+                        None => break,
+                    };
+                    // tokio spawn should only be needed around intensive tasks, ie sign/verify
+                    match map_routing_event(event, &self.network_api).await {
+                        Mapping::Ok { op, ctx } => self.process_while_any(op, ctx, false).await,
+                        Mapping::Error(error) => handle_error(error),
+                    }
+                }
+                Some(duty) = periodic_duties.recv() => self.process_while_any(duty, None, true).await,
+                Some(duty) = background_duties.recv() => {
+                    self.process_while_any(duty, None, false).await
+                }
+                _ = time::sleep(egress_wait.unwrap_or_default()), if egress_wait.is_some() => {
+                    self.send_held_back().await
+                }
             }
         }
 
+        info!("Node shutting down");
         Ok(())
     }
 
+    /// Answers a query by another Elder of ours for the progress of our reward process,
+    /// or by a node of ours for the progress of the credits to its wallet alone.
+    async fn send_reward_status(&self, id: MessageId, origin: XorName) -> Result<NodeDuty> {
+        let status = self.reward_status().await?;
+        let status = if self.network_api.our_elder_names().await.contains(&origin) {
+            status
+        } else if let Some(wallet) = self.role.section_funds()?.get_node_wallet(&origin) {
+            status.of_recipient(&wallet)
+        } else {
+            warn!(
+                "Not sending reward status to {}, neither an Elder nor a node of ours",
+                origin
+            );
+            return Ok(NodeDuty::NoOp);
+        };
+        info!("Sending reward status {:?} to {}", status.stage, origin);
+        Ok(NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::RewardStatus { query: id, status }),
+            dst: DstLocation::Node(origin),
+        })
+    }
+
+    /// Sends the responses to clients held back by shaping, as their share allows.
+    async fn send_held_back(&mut self) {
+        while let Some(msg) = self.egress.next_ready(Instant::now()) {
+            if let Err(e) = messaging::send(msg, &self.network_api).await {
+                error!("Unable to send held back response: {:?}", e);
+            }
+        }
+    }
+
     /// Keeps processing resulting node operations.
-    async fn process_while_any(&mut self, op: NodeDuty, ctx: Option<MsgContext>) {
-        let mut next_ops = vec![op];
+    /// The duty passed by a periodic task, if `periodic`, is supervised, so that a panic
+    /// handling it does not take the node down, but backs the task off. The duties
+    /// resulting from it, as all others, are not, e.g. the churn of the section.
+    async fn process_while_any(&mut self, op: NodeDuty, ctx: Option<MsgContext>, periodic: bool) {
+        let task = if periodic { Some(op.name()) } else { None };
+        // the duty of the task, until handled
+        let mut supervised = None;
+        let mut panicked = false;
+        // the chain is handed the node, so it is taken out of it meanwhile
+        let mut chain = std::mem::take(&mut self.middleware);
+        let mut next_ops = match chain.msg_started(self, &op, ctx.as_ref()).await {
+            Admission::Handle => {
+                supervised = task;
+                vec![op]
+            }
+            Admission::Drop => vec![],
+            Admission::Refuse(error) => {
+                let error = error.with_context(op.context());
+                chain.after(self, op.name(), Err(&error));
+                error_response(error)
+            }
+        };
 
         while !next_ops.is_empty() {
             let mut pending_node_ops: Vec<NodeDuty> = vec![];
+            chain.batch(&mut next_ops);
             for duty in next_ops {
-                match self.handle(duty).await {
-                    Ok(new_ops) => pending_node_ops.extend(new_ops),
-                    Err(e) => try_handle_error(e, ctx.clone()),
+                let context = duty.context();
+                let name = duty.name();
+                // the duty of the task is the first one, if admitted
+                let task = supervised.take();
+                match chain.before(self, &duty) {
+                    Admission::Handle => (),
+                    Admission::Drop => continue,
+                    Admission::Refuse(error) => {
+                        let error = error.with_context(context);
+                        chain.after(self, name, Err(&error));
+                        pending_node_ops.extend(error_response(error));
+                        continue;
+                    }
+                }
+                let outcome = match task {
+                    Some(task) => {
+                        let (outcome, panic) = self.handle_supervised(task, duty).await;
+                        panicked |= panic;
+                        outcome
+                    }
+                    None => self.handle(duty).await,
+                };
+                match outcome {
+                    Ok(new_ops) => {
+                        chain.after(self, name, Ok(&new_ops));
+                        pending_node_ops.extend(new_ops);
+                    }
+                    Err(e) => {
+                        let error = e.with_context(context);
+                        chain.after(self, name, Err(&error));
+                        try_handle_error(&error, ctx.clone());
+                        pending_node_ops.extend(error_response(error));
+                    }
                 };
             }
             next_ops = pending_node_ops;
         }
+        chain.msg_completed();
+        self.middleware = chain;
+        if let (Some(task), false) = (task, panicked) {
+            self.task_health.completed(task);
+        }
     }
 }
 
@@ -205,7 +808,50 @@ fn handle_error(err: LazyError) {
     }
 }
 
-fn try_handle_error(err: Error, ctx: Option<MsgContext>) {
+// Reports the error back to the origin of the failed duty, when it is a client.
+fn error_response(error: Error) -> NodeDuties {
+    let (context, source) = match error {
+        Error::Duty { context, source } => (context, source),
+        _ => return vec![],
+    };
+    let busy = match *source {
+        Error::Busy(busy) => Some(busy),
+        _ => None,
+    };
+    let conflict = match *source {
+        Error::DataConflict(address) => Some(address),
+        _ => None,
+    };
+    // errors without a mapping are still reported, albeit unspecific
+    let message_error = convert_to_error_message(*source).unwrap_or(ErrorMessage::InvalidOperation);
+    let mut duties: NodeDuties = context
+        .error_response(message_error)
+        .map(NodeDuty::Send)
+        .into_iter()
+        .collect();
+    // and clients refused as we are busy are told when to retry
+    if let (Some(busy), Some(msg_id), Some(SrcLocation::EndUser(origin))) =
+        (busy, context.msg_id, context.origin)
+    {
+        match busy.msg(msg_id, origin) {
+            Ok(duty) => duties.push(duty),
+            Err(e) => error!("Unable to tell client when to retry: {:?}", e),
+        }
+    }
+    // and clients writing other content to an existing chunk are told of the conflict,
+    // under an id derived from that of the cmd, as the error is sent for the response
+    if let (Some(address), Some(msg_id), Some(SrcLocation::EndUser(origin))) =
+        (conflict, context.msg_id, context.origin)
+    {
+        match ClientMsg::DataConflict(address).msg(MessageId::in_response_to(&msg_id), origin) {
+            Ok(msg) => duties.push(NodeDuty::Send(msg)),
+            Err(e) => error!("Unable to tell client of the conflict: {:?}", e),
+        }
+    }
+    duties
+}
+
+fn try_handle_error(err: &Error, ctx: Option<MsgContext>) {
     use std::error::Error;
     if let Some(source) = err.source() {
         if let Some(ctx) = ctx {
@@ -230,3 +876,48 @@ impl Display for Node {
         write!(formatter, "Node")
     }
 }
+
+/// Opens the chunks of the node in the store plugged in, if any, or else on disk.
+pub(crate) async fn open_chunks(
+    node_info: &NodeInfo,
+    used_space: &UsedSpace,
+    plugged: Option<Box<dyn ChunkStore>>,
+    clock: Arc<NetworkClock>,
+) -> Result<Chunks> {
+    let mut chunks = match plugged {
+        Some(store) => Chunks::with_store(
+            node_info.node_name,
+            node_info.path(),
+            store,
+            node_info.scrubbing.clone(),
+            clock,
+        ),
+        None => {
+            Chunks::new(
+                node_info.node_name,
+                node_info.path(),
+                used_space.clone(),
+                node_info.scrubbing.clone(),
+                node_info.secure_delete,
+                node_info.chunk_write_buffer,
+                node_info.chunk_compression,
+                clock,
+            )
+            .await
+        }
+    }?;
+    if let Some(policy) = node_info.storage_policy.clone() {
+        chunks.set_storage_policy(policy);
+    }
+    chunks.set_max_chunk_size(node_info.max_chunk_size());
+    if let Some(budget) = node_info.chunk_read_cache {
+        chunks.set_read_cache(budget);
+    }
+    if let Some(concurrency) = node_info.replication_concurrency {
+        chunks.set_replication_concurrency(concurrency);
+    }
+    if let Some(capability) = node_info.capability {
+        chunks.set_capability(capability);
+    }
+    Ok(chunks)
+}
@@ -0,0 +1,138 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    metadata::SignedMetadataDump,
+    node_ops::{NodeDuties, NodeDuty},
+    operator::{OperatorCommand, OperatorQuery, SignedOperatorCommand},
+    section_funds::SignedFundsExport,
+    Error, Node, Result,
+};
+use log::{debug, info, warn};
+use std::fs;
+use tokio::sync::oneshot;
+
+impl Node {
+    /// Runs a command of the node operator, once authorised by the operator key.
+    pub(crate) async fn run_operator_command(
+        &mut self,
+        cmd: SignedOperatorCommand,
+    ) -> Result<NodeDuties> {
+        let our_name = self.network_api.our_name().await;
+        if let Err(error) = self.operator_auth.authorise(&cmd, our_name) {
+            warn!("Rejected operator command {:?}: {}", cmd.command, error);
+            return Err(error);
+        }
+        info!("Running operator command: {:?}", cmd.command);
+        match cmd.command {
+            OperatorCommand::SetRewardKey(reward_key) => {
+                self.node_info.reward_key = reward_key;
+                Ok(vec![NodeDuty::Send(self.register_wallet().await)])
+            }
+            OperatorCommand::SetMaintenanceMode(enabled) => {
+                self.maintenance_mode = enabled;
+                Ok(vec![])
+            }
+            OperatorCommand::Shutdown => {
+                self.shutting_down = true;
+                Ok(vec![])
+            }
+            OperatorCommand::DeleteChunks(deletion) => {
+                Ok(vec![NodeDuty::ProcessChunkDeletion(deletion)])
+            }
+            OperatorCommand::RelocateChunks(root) => {
+                let chunks = self.role.chunks_mut()?;
+                chunks.relocate(root).await?;
+                Ok(vec![])
+            }
+            OperatorCommand::Decommission => self.decommission().await,
+            OperatorCommand::ImportChunks(source) => {
+                let chunks = self.role.chunks_mut()?;
+                chunks.start_import(source)?;
+                Ok(vec![])
+            }
+            OperatorCommand::SendSectionFunds(targets) => Ok(self
+                .export_section_funds()
+                .await?
+                .iter()
+                .map(|export| export.msg(targets.clone()))
+                .collect()),
+            OperatorCommand::ImportSectionFunds(source) => {
+                let exports: Vec<SignedFundsExport> = serde_json::from_slice(&fs::read(source)?)?;
+                let mut duties = vec![];
+                for export in exports {
+                    duties.extend(self.import_section_funds(export, true).await?);
+                }
+                Ok(duties)
+            }
+            OperatorCommand::SendSectionMetadata(targets) => Ok(self
+                .dump_section_metadata()
+                .await?
+                .iter()
+                .map(|dump| dump.msg(targets.clone()))
+                .collect()),
+            OperatorCommand::ImportSectionMetadata(source) => {
+                let dumps: Vec<SignedMetadataDump> = serde_json::from_slice(&fs::read(source)?)?;
+                let mut duties = vec![];
+                for dump in dumps {
+                    duties.extend(self.import_section_metadata(dump, true).await?);
+                }
+                Ok(duties)
+            }
+            OperatorCommand::SetDutyLogFilters(filters) => {
+                self.set_duty_log_filters(filters);
+                Ok(vec![])
+            }
+        }
+    }
+
+    /// Answers a query of the node operator with the json of the answer.
+    pub(crate) async fn run_operator_query(
+        &self,
+        query: OperatorQuery,
+        reply: oneshot::Sender<String>,
+    ) -> Result<()> {
+        let answer = match query {
+            OperatorQuery::RewardStatus => serde_json::to_string(&self.reward_status().await?)?,
+            OperatorQuery::RefusedDuties => serde_json::to_string(&self.refused_duties())?,
+            OperatorQuery::ChunkSizeAdvice => serde_json::to_string(&self.chunk_size_advice())?,
+            OperatorQuery::DutyMetrics => serde_json::to_string(&self.duty_metrics())?,
+            OperatorQuery::PeerLatency => serde_json::to_string(&self.peer_latency())?,
+            OperatorQuery::HotData => serde_json::to_string(&self.hot_data()?)?,
+            OperatorQuery::NodeStatus => serde_json::to_string(&self.status().await)?,
+            OperatorQuery::DecommissionStatus => {
+                serde_json::to_string(&self.decommission_status())?
+            }
+            OperatorQuery::ChunkReadCache => {
+                serde_json::to_string(&self.chunk_read_cache_stats()?)?
+            }
+            OperatorQuery::PendingQueries => serde_json::to_string(&self.pending_query_stats())?,
+            OperatorQuery::ChunkImport => serde_json::to_string(&self.chunk_import_report()?)?,
+            OperatorQuery::SectionFunds => {
+                serde_json::to_string(&self.export_section_funds().await?)?
+            }
+            OperatorQuery::SectionMetadata => {
+                serde_json::to_string(&self.dump_section_metadata().await?)?
+            }
+            OperatorQuery::DutyLogFilters => serde_json::to_string(&self.duty_log_filters())?,
+        };
+        if reply.send(answer).is_err() {
+            debug!("Operator no longer awaiting answer to {:?}", query);
+        }
+        Ok(())
+    }
+
+    /// Data writes are refused while in maintenance mode.
+    pub(crate) fn ensure_not_in_maintenance(&self) -> Result<()> {
+        if self.maintenance_mode {
+            Err(Error::InMaintenance)
+        } else {
+            Ok(())
+        }
+    }
+}
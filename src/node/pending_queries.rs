@@ -0,0 +1,389 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_ops::{NodeDuties, NodeDuty, OutgoingMsg, PeriodicTask},
+    Node,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_messaging::{
+    client::{Error as ErrorMessage, Message, NodeQuery},
+    Aggregation, DstLocation, MessageId,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::{Prefix, XorName};
+
+/// The error of a query forwarded to another section that could not be reached starts
+/// with this, followed by the prefix of the section, as there is no dedicated error for
+/// it yet, e.g. `query-timed-out:Prefix(01)`.
+pub const QUERY_TIMED_OUT: &str = "query-timed-out:";
+
+/// Interval at which the forwarded queries are checked for being due a hedge.
+const HEDGE_TICK: Duration = Duration::from_millis(100);
+/// Interval at which the queries pending beyond `PENDING_QUERY_TIMEOUT` are purged.
+const QUERY_PURGE_INTERVAL: Duration = Duration::from_secs(10);
+// Queries pending for longer are forgotten. Answers go straight to the
+// client, so whether a query has been answered is never known to us.
+const PENDING_QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+// The max number of forwarded queries tracked, to bound the memory used.
+const MAX_PENDING_QUERIES: usize = 10_000;
+
+/// Counters of the queries forwarded to a section, to spot unhealthy neighbours.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SectionQueryStats {
+    /// Prefix of the section, or the empty prefix for sections not known to us.
+    pub prefix: Prefix,
+    /// Queries forwarded to it.
+    pub forwarded: u64,
+    /// Queries also sent to an alternate Elder of it.
+    pub hedged: u64,
+    /// Queries failed to the client, as no send of them to the section succeeded.
+    pub timed_out: u64,
+    /// Share of the queries forwarded that timed out.
+    pub timeout_rate: f64,
+}
+
+/// The queries forwarded to other sections and still pending, and the counters
+/// of those forwarded to each section since the node started.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PendingQueryStats {
+    /// Number of queries pending.
+    pub pending: usize,
+    /// Counters of the queries forwarded to each section.
+    pub sections: Vec<SectionQueryStats>,
+}
+
+/// The queries forwarded to other sections, until timed out. Those still outstanding
+/// after a partial timeout are hedged, if so configured, by sending them to an alternate
+/// Elder of the section, keeping the first request outstanding. The client takes
+/// whichever answer comes first, as it does of the answers of each Elder. A query of
+/// which every send failed is failed to the client.
+pub(crate) struct PendingQueries {
+    hedge_after: Option<Duration>,
+    // the queries forwarded, by id
+    pending: BTreeMap<MessageId, PendingQuery>,
+    sections: BTreeMap<Prefix, SectionQueryStats>,
+}
+
+struct PendingQuery {
+    msg: Message,
+    // the name the query was forwarded to the section of
+    dst: XorName,
+    section: Prefix,
+    at: Instant,
+    hedged: bool,
+    // the sends of the query not failed
+    sends: u8,
+}
+
+impl PendingQueries {
+    pub(crate) fn new(hedge_after: Option<Duration>) -> Self {
+        Self {
+            hedge_after,
+            pending: BTreeMap::new(),
+            sections: BTreeMap::new(),
+        }
+    }
+
+    /// The background work on the pending queries, hedging them only if they are hedged.
+    pub(crate) fn periodic_tasks(&self) -> Vec<PeriodicTask> {
+        let mut tasks = vec![PeriodicTask {
+            name: "pending query purge",
+            interval: QUERY_PURGE_INTERVAL,
+            duty: || NodeDuty::PurgePendingQueries,
+        }];
+        if self.hedge_after.is_some() {
+            tasks.push(PeriodicTask {
+                name: "query hedging",
+                interval: HEDGE_TICK,
+                duty: || NodeDuty::HedgeQueries,
+            });
+        }
+        tasks
+    }
+
+    /// Records the query as forwarded to the section of the name.
+    pub(crate) fn forwarded(&mut self, msg: Message, dst: XorName, section: Prefix, now: Instant) {
+        if self.pending.len() >= MAX_PENDING_QUERIES {
+            warn!("Too many pending queries, not tracking {:?}", msg.id());
+            return;
+        }
+        self.section(section).forwarded += 1;
+        let _ = self.pending.insert(
+            msg.id(),
+            PendingQuery {
+                msg,
+                dst,
+                section,
+                at: now,
+                hedged: false,
+                sends: 1,
+            },
+        );
+    }
+
+    /// The queries forwarded beyond the partial timeout and not hedged yet, with
+    /// the name they were forwarded to the section of. Each is only due once.
+    pub(crate) fn due(&mut self, now: Instant) -> Vec<(Message, XorName)> {
+        let after = match self.hedge_after {
+            Some(after) => after,
+            None => return vec![],
+        };
+        self.pending
+            .values_mut()
+            .filter(|query| !query.hedged && now.saturating_duration_since(query.at) >= after)
+            .map(|query| {
+                query.hedged = true;
+                (query.msg.clone(), query.dst)
+            })
+            .collect()
+    }
+
+    /// Records the query as also sent to an alternate Elder.
+    pub(crate) fn hedged(&mut self, id: &MessageId) {
+        let section = match self.pending.get_mut(id) {
+            Some(query) => {
+                query.sends += 1;
+                query.section
+            }
+            None => return,
+        };
+        self.section(section).hedged += 1;
+    }
+
+    /// Records a send of the msg as failed. If it is a pending query of which no send is
+    /// left, it is abandoned, and the final error to the client returned.
+    pub(crate) fn failed(&mut self, id: &MessageId) -> Option<NodeDuty> {
+        let query = self.pending.get_mut(id)?;
+        query.sends = query.sends.saturating_sub(1);
+        if query.sends > 0 {
+            return None;
+        }
+        let query = self.pending.remove(id)?;
+        self.section(query.section).timed_out += 1;
+        info!(
+            "Query {:?} to section {:?} abandoned, failing it to the client",
+            id, query.section
+        );
+        timed_out(query.msg, query.section)
+    }
+
+    /// Forgets the queries pending beyond `PENDING_QUERY_TIMEOUT`.
+    pub(crate) fn purge(&mut self, now: Instant) {
+        self.pending
+            .retain(|_, query| now.saturating_duration_since(query.at) < PENDING_QUERY_TIMEOUT);
+    }
+
+    pub(crate) fn stats(&self) -> PendingQueryStats {
+        PendingQueryStats {
+            pending: self.pending.len(),
+            sections: self
+                .sections
+                .values()
+                .map(|stats| SectionQueryStats {
+                    timeout_rate: if stats.forwarded == 0 {
+                        0.0
+                    } else {
+                        stats.timed_out as f64 / stats.forwarded as f64
+                    },
+                    ..stats.clone()
+                })
+                .collect(),
+        }
+    }
+
+    fn section(&mut self, prefix: Prefix) -> &mut SectionQueryStats {
+        self.sections
+            .entry(prefix)
+            .or_insert_with(|| SectionQueryStats {
+                prefix,
+                ..Default::default()
+            })
+    }
+}
+
+// The final error to the client of the query, which timed out as the section could not be reached.
+fn timed_out(msg: Message, section: Prefix) -> Option<NodeDuty> {
+    let (query, origin, id) = match msg {
+        Message::NodeQuery {
+            query: NodeQuery::Metadata { query, origin },
+            id,
+            ..
+        } => (query, origin, id),
+        _ => return None,
+    };
+    let error = ErrorMessage::Serialization(format!("{}{:?}", QUERY_TIMED_OUT, section));
+    Some(NodeDuty::Send(OutgoingMsg {
+        msg: Message::QueryResponse {
+            response: query.error(error),
+            id: MessageId::in_response_to(&id),
+            correlation_id: id,
+            target_section_pk: None,
+        },
+        section_source: false, // sent as single node
+        dst: DstLocation::EndUser(origin),
+        aggregation: Aggregation::None,
+    }))
+}
+
+impl Node {
+    /// Sends the forwarded queries due a hedge to an alternate Elder of the section
+    /// they were forwarded to, keeping the first request outstanding.
+    pub(crate) async fn hedge_queries(&mut self) -> NodeDuties {
+        let due = self.pending_queries.due(Instant::now());
+        let our_name = self.network_api.our_name().await;
+        let mut duties = vec![];
+        for (msg, dst) in due {
+            let elders = match self.network_api.section_of(&dst).await {
+                Some((_, elders)) => elders,
+                None => continue,
+            };
+            let elder = match alternate_elder(&elders, &dst, &our_name) {
+                Some(elder) => elder,
+                None => continue,
+            };
+            info!("Hedging query {:?} with Elder {:?}", msg.id(), elder);
+            self.pending_queries.hedged(&msg.id());
+            duties.push(NodeDuty::Send(OutgoingMsg {
+                msg,
+                dst: DstLocation::Node(elder),
+                section_source: false,
+                aggregation: Aggregation::None,
+            }));
+        }
+        duties
+    }
+}
+
+/// The Elder of the section to hedge a query forwarded to it with. The query went to
+/// the Elders closest to the name, so the one furthest from it is picked, bar us.
+pub(crate) fn alternate_elder(
+    elders: &BTreeSet<XorName>,
+    dst: &XorName,
+    our_name: &XorName,
+) -> Option<XorName> {
+    elders
+        .iter()
+        .filter(|elder| *elder != our_name)
+        .max_by(|lhs, rhs| dst.cmp_distance(lhs, rhs))
+        .copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::{BlobAddress, PublicKey};
+    use sn_messaging::{
+        client::{BlobRead, DataQuery, QueryResponse},
+        EndUser,
+    };
+
+    fn query() -> Message {
+        Message::NodeQuery {
+            query: NodeQuery::Metadata {
+                query: DataQuery::Blob(BlobRead::Get(BlobAddress::Public(XorName::random()))),
+                origin: EndUser::AllClients(PublicKey::from(SecretKey::random().public_key())),
+            },
+            id: MessageId::new(),
+            target_section_pk: None,
+        }
+    }
+
+    #[test]
+    fn queries_are_hedged_once_after_the_partial_timeout() {
+        let mut queries = PendingQueries::new(Some(Duration::from_millis(500)));
+        let now = Instant::now();
+        let (first, second) = (query(), query());
+        let dst = XorName::random();
+        queries.forwarded(first.clone(), dst, Prefix::default(), now);
+        queries.forwarded(
+            second,
+            dst,
+            Prefix::default(),
+            now + Duration::from_millis(300),
+        );
+
+        assert!(queries.due(now + Duration::from_millis(499)).is_empty());
+        assert_eq!(
+            queries.due(now + Duration::from_millis(500)),
+            vec![(first, dst)]
+        );
+        assert!(queries.due(now + Duration::from_millis(700)).is_empty());
+        assert_eq!(queries.due(now + Duration::from_millis(800)).len(), 1);
+        // kept pending until timed out
+        assert_eq!(queries.stats().pending, 2);
+    }
+
+    #[test]
+    fn queries_of_which_every_send_failed_are_failed_to_the_client() {
+        let mut queries = PendingQueries::new(None);
+        let now = Instant::now();
+        let section = Prefix::default().pushed(true);
+        let (hedged, unhedged) = (query(), query());
+        queries.forwarded(hedged.clone(), XorName::random(), section, now);
+        queries.forwarded(unhedged.clone(), XorName::random(), section, now);
+        queries.hedged(&hedged.id());
+
+        // the first request is still outstanding
+        assert!(queries.failed(&hedged.id()).is_none());
+        match queries.failed(&unhedged.id()) {
+            Some(NodeDuty::Send(OutgoingMsg {
+                msg:
+                    Message::QueryResponse {
+                        response: QueryResponse::GetBlob(Err(ErrorMessage::Serialization(error))),
+                        correlation_id,
+                        ..
+                    },
+                ..
+            })) => {
+                assert_eq!(correlation_id, unhedged.id());
+                assert!(error.starts_with(QUERY_TIMED_OUT));
+            }
+            duty => panic!("Unexpected duty: {:?}", duty),
+        }
+        assert!(queries.failed(&unhedged.id()).is_none());
+        assert_eq!(
+            queries.stats(),
+            PendingQueryStats {
+                pending: 1,
+                sections: vec![SectionQueryStats {
+                    prefix: section,
+                    forwarded: 2,
+                    hedged: 1,
+                    timed_out: 1,
+                    timeout_rate: 0.5,
+                }],
+            }
+        );
+
+        queries.purge(now + PENDING_QUERY_TIMEOUT);
+        assert_eq!(queries.stats().pending, 0);
+    }
+
+    #[test]
+    fn the_elder_furthest_from_the_name_is_the_alternate() {
+        let dst = XorName::random();
+        let elders: BTreeSet<_> = (0..7).map(|_| XorName::random()).collect();
+        let mut by_distance: Vec<_> = elders.iter().copied().collect();
+        by_distance.sort_by(|lhs, rhs| dst.cmp_distance(lhs, rhs));
+
+        let furthest = by_distance[6];
+        let our_name = XorName::random();
+        assert_eq!(alternate_elder(&elders, &dst, &our_name), Some(furthest));
+        assert_eq!(
+            alternate_elder(&elders, &dst, &furthest),
+            Some(by_distance[5])
+        );
+        assert_eq!(alternate_elder(&BTreeSet::new(), &dst, &our_name), None);
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::node_ops::NodeDuty;
+use log::info;
+use sn_data_types::{Blob, BlobAddress, PublicKey};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// Work not completed within this time is forgotten.
+const PENDING_WORK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Registry of work in progress, keyed by the address of the data it concerns,
+/// so that later duties can cancel earlier work they have made pointless,
+/// e.g. a chunk delete cancelling a pending replication of the same chunk.
+#[derive(Default)]
+pub(crate) struct PendingWork {
+    replications: BTreeMap<BlobAddress, Instant>,
+    // the requester of the delete, which
+    // must be the owner for it to cancel anything
+    cancelled: BTreeMap<BlobAddress, (PublicKey, Instant)>,
+}
+
+impl PendingWork {
+    /// Registers the work started by the duty, and cancels
+    /// any pending work superseded by it. Returns false if
+    /// the duty itself has been cancelled, and should be dropped.
+    pub(crate) fn admit(&mut self, duty: &NodeDuty) -> bool {
+        self.expire();
+        match duty {
+            NodeDuty::ReplicateChunk { address, .. } => {
+                let _ = self.cancelled.remove(address);
+                let _ = self.replications.insert(*address, Instant::now());
+                true
+            }
+            NodeDuty::StoreChunkForReplication { data, .. } => {
+                let address = data.address();
+                let _ = self.replications.remove(address);
+                match self.cancelled.remove(address) {
+                    Some((requester, _)) if is_owner(data, &requester) => {
+                        info!(
+                            "Dropping replicated chunk {:?}, as it has been deleted",
+                            address
+                        );
+                        false
+                    }
+                    _ => true,
+                }
+            }
+            NodeDuty::DeleteChunk {
+                address, origin, ..
+            } => {
+                if self.replications.remove(address).is_some() {
+                    info!(
+                        "Cancelling pending replication of deleted chunk {:?}",
+                        address
+                    );
+                    let _ = self
+                        .cancelled
+                        .insert(*address, (*origin.id(), Instant::now()));
+                }
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// The number of chunks being replicated.
+    pub(crate) fn replicating(&self) -> usize {
+        self.replications.len()
+    }
+
+    fn expire(&mut self) {
+        self.replications
+            .retain(|_, started| started.elapsed() < PENDING_WORK_TIMEOUT);
+        self.cancelled
+            .retain(|_, (_, cancelled)| cancelled.elapsed() < PENDING_WORK_TIMEOUT);
+    }
+}
+
+/// Removes the duties of a batch, which are superseded by later duties of it.
+pub(crate) fn drop_superseded(duties: &mut Vec<NodeDuty>) {
+    let mut deleted = vec![];
+    let mut keep = vec![true; duties.len()];
+    for (i, duty) in duties.iter().enumerate().rev() {
+        match duty {
+            NodeDuty::DeleteChunk {
+                address, origin, ..
+            } => deleted.push((*address, *origin.id())),
+            NodeDuty::StoreChunkForReplication { data, .. }
+                if deleted.iter().any(|(address, requester)| {
+                    address == data.address() && is_owner(data, requester)
+                }) =>
+            {
+                info!(
+                    "Dropping replication of chunk {:?} deleted later on",
+                    data.address()
+                );
+                keep[i] = false;
+            }
+            _ => (),
+        }
+    }
+    let mut keep = keep.into_iter();
+    duties.retain(|_| keep.next().unwrap_or(true));
+}
+
+// Only the owner can delete a chunk, so only
+// the owner's delete can cancel work on it.
+fn is_owner(data: &Blob, requester: &PublicKey) -> bool {
+    match data {
+        Blob::Private(data) => data.owner() == requester,
+        Blob::Public(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::PrivateBlob;
+    use sn_messaging::{EndUser, MessageId};
+    use xor_name::XorName;
+
+    #[test]
+    fn delete_cancels_pending_replication() {
+        let owner = PublicKey::from(SecretKey::random().public_key());
+        let blob = Blob::Private(PrivateBlob::new(vec![1, 2, 3], owner));
+        let address = *blob.address();
+        let mut pending = PendingWork::default();
+
+        assert!(pending.admit(&replicate(address)));
+        assert!(pending.admit(&delete(address, owner)));
+        assert!(!pending.admit(&store(blob.clone())));
+
+        // a new replication is not affected
+        assert!(pending.admit(&replicate(address)));
+        assert!(pending.admit(&store(blob)));
+    }
+
+    #[test]
+    fn delete_by_non_owner_cancels_nothing() {
+        let owner = PublicKey::from(SecretKey::random().public_key());
+        let other = PublicKey::from(SecretKey::random().public_key());
+        let blob = Blob::Private(PrivateBlob::new(vec![1, 2, 3], owner));
+        let mut pending = PendingWork::default();
+
+        assert!(pending.admit(&replicate(*blob.address())));
+        assert!(pending.admit(&delete(*blob.address(), other)));
+        assert!(pending.admit(&store(blob)));
+    }
+
+    #[test]
+    fn later_delete_drops_earlier_replication_in_batch() {
+        let owner = PublicKey::from(SecretKey::random().public_key());
+        let blob = Blob::Private(PrivateBlob::new(vec![1, 2, 3], owner));
+        let other = Blob::Private(PrivateBlob::new(vec![4, 5, 6], owner));
+        let mut duties = vec![
+            store(blob.clone()),
+            store(other.clone()),
+            delete(*blob.address(), owner),
+            store(blob.clone()),
+        ];
+        drop_superseded(&mut duties);
+        assert_eq!(duties.len(), 3);
+        assert!(
+            matches!(&duties[0], NodeDuty::StoreChunkForReplication { data, .. } if data == &other)
+        );
+        assert!(
+            matches!(&duties[2], NodeDuty::StoreChunkForReplication { data, .. } if data == &blob)
+        );
+    }
+
+    fn store(data: Blob) -> NodeDuty {
+        NodeDuty::StoreChunkForReplication {
+            data,
+            correlation_id: MessageId::new(),
+            holder: XorName::random(),
+        }
+    }
+
+    fn replicate(address: BlobAddress) -> NodeDuty {
+        NodeDuty::ReplicateChunk {
+            address,
+            current_holders: Default::default(),
+            id: MessageId::new(),
+        }
+    }
+
+    fn delete(address: BlobAddress, owner: PublicKey) -> NodeDuty {
+        NodeDuty::DeleteChunk {
+            address,
+            msg_id: MessageId::new(),
+            origin: EndUser::AllClients(owner),
+            elder: XorName::random(),
+        }
+    }
+}
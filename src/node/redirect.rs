@@ -0,0 +1,81 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{node_msg::ClientMsg, node_ops::NodeDuty, Result};
+use serde::{Deserialize, Serialize};
+use sn_messaging::{EndUser, MessageId};
+use std::collections::BTreeSet;
+use xor_name::{Prefix, XorName};
+
+/// The hint to a client querying data of another section, forwarded to it by us,
+/// of the section to query directly next time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionRedirect {
+    /// The name the query was addressed to.
+    pub name: XorName,
+    /// Prefix of the section matching the name.
+    pub prefix: Prefix,
+    /// The Elders of that section, as known to us.
+    pub elders: BTreeSet<XorName>,
+}
+
+impl SectionRedirect {
+    /// The correlation id of the hint, derived from the id of the query, rather than
+    /// being it, so that clients not expecting hints don't take it for the response.
+    pub fn correlation_id(query_id: MessageId) -> MessageId {
+        MessageId::in_response_to(&query_id)
+    }
+
+    /// The msg hinting the client.
+    pub(crate) fn msg(&self, query_id: MessageId, origin: EndUser) -> Result<NodeDuty> {
+        let correlation_id = Self::correlation_id(query_id);
+        Ok(NodeDuty::Send(
+            ClientMsg::SectionRedirect(self.clone()).msg(correlation_id, origin)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::node_ops::OutgoingMsg;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+    use sn_messaging::client::Message;
+
+    #[test]
+    fn hint_is_not_taken_for_the_response_to_the_query() -> Result<()> {
+        let redirect = SectionRedirect {
+            name: XorName::random(),
+            prefix: Prefix::default().pushed(true),
+            elders: vec![XorName::random()].into_iter().collect(),
+        };
+        let query_id = MessageId::new();
+        let origin = EndUser::AllClients(PublicKey::from(SecretKey::random().public_key()));
+        let msg = match redirect.msg(query_id, origin)? {
+            NodeDuty::Send(OutgoingMsg { msg, .. }) => msg,
+            duty => panic!("Unexpected duty: {:?}", duty),
+        };
+        match msg {
+            Message::QueryResponse {
+                response,
+                correlation_id,
+                ..
+            } => {
+                assert_ne!(correlation_id, query_id);
+                assert_eq!(correlation_id, SectionRedirect::correlation_id(query_id));
+                assert_eq!(
+                    ClientMsg::from_response(&response).transpose()?,
+                    Some(ClientMsg::SectionRedirect(redirect))
+                );
+            }
+            msg => panic!("Unexpected msg: {:?}", msg),
+        }
+        Ok(())
+    }
+}
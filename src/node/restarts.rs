@@ -0,0 +1,73 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use std::time::Duration;
+
+/// Delay before restarting a node after its first crash in a row.
+pub const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+/// Delay before restarting a node at most, however many times in a row it crashed.
+pub const MAX_RESTART_DELAY: Duration = Duration::from_secs(5 * 60);
+/// A node that ran this long before crashing is not considered crash looping.
+pub const STABLE_RUN: Duration = Duration::from_secs(10 * 60);
+
+/// The backoff of a supervisor from restarting a node crashing again and again.
+/// The delay doubles at each crash in a row, up to the max, and is back to the
+/// min once the node ran stable before crashing.
+#[derive(Debug)]
+pub struct RestartBackoff {
+    delay: Duration,
+    restarts: u32,
+}
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        Self {
+            delay: MIN_RESTART_DELAY,
+            restarts: 0,
+        }
+    }
+}
+
+impl RestartBackoff {
+    /// The delay before restarting the node, having crashed after running for the duration given.
+    pub fn crashed(&mut self, ran_for: Duration) -> Duration {
+        if ran_for >= STABLE_RUN {
+            self.delay = MIN_RESTART_DELAY;
+        }
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(MAX_RESTART_DELAY);
+        self.restarts += 1;
+        delay
+    }
+
+    /// Times the node has been restarted.
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn restarts_back_off_while_crash_looping() {
+        let mut backoff = RestartBackoff::default();
+        let quickly = Duration::from_secs(1);
+        assert_eq!(backoff.crashed(quickly), MIN_RESTART_DELAY);
+        assert_eq!(backoff.crashed(quickly), MIN_RESTART_DELAY * 2);
+        assert_eq!(backoff.crashed(quickly), MIN_RESTART_DELAY * 4);
+        for _ in 0..10 {
+            let _ = backoff.crashed(quickly);
+        }
+        assert_eq!(backoff.crashed(quickly), MAX_RESTART_DELAY);
+
+        assert_eq!(backoff.crashed(STABLE_RUN), MIN_RESTART_DELAY);
+        assert_eq!(backoff.restarts(), 15);
+    }
+}
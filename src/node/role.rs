@@ -0,0 +1,247 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::standby::WarmStandby;
+use crate::{
+    chunks::{ChunkStore, Chunks},
+    metadata::Metadata,
+    section_funds::{
+        reward_process::RewardProcess, reward_wallets::RewardWallets, Payments, SectionFunds,
+    },
+    transfers::Transfers,
+    Error, Result,
+};
+use log::info;
+use std::{
+    fmt::{self, Display, Formatter},
+    mem,
+};
+
+/// The state of an Adult.
+pub(crate) struct AdultState {
+    /// The chunks stored.
+    pub(crate) chunks: Chunks,
+    /// The warm copy of the Elder state, if opted into.
+    pub(crate) standby: Option<WarmStandby>,
+}
+
+/// The state of an Elder. It is set up and torn down as a whole, so that an Elder
+/// never serves transfers without its replicas, nor metadata without the section funds.
+pub(crate) struct ElderState {
+    pub(crate) meta_data: Metadata,
+    pub(crate) transfers: Transfers,
+    pub(crate) section_funds: SectionFunds,
+    /// The chunk store plugged in, held on to until demoted, to store chunks in again.
+    pub(crate) plugged_chunk_store: Option<Box<dyn ChunkStore>>,
+}
+
+/// The role of the node in its section, holding the state of the duties of the role.
+///
+/// The role only changes by the transitions below, each refused from any other role
+/// than the one it is from:
+///
+/// - `Infant` to `Adult`, on joining, or on demotion, once the chunks are opened
+/// - `Adult` to `Infant`, on promotion, handing over the state of the Adult
+/// - `Infant` to `Elder`, once the state of the Elder is set up
+/// - `Elder` to `Infant`, on demotion, handing back the chunk store plugged in, if any
+pub(crate) enum Role {
+    /// Holding no state: while joining, or between roles while the state of the
+    /// next one is set up. The duties of all subsystems are refused meanwhile.
+    Infant,
+    Adult(Box<AdultState>),
+    Elder(Box<ElderState>),
+}
+
+impl Role {
+    /// To `Adult`, from `Infant`.
+    pub(crate) fn become_adult(&mut self, state: AdultState) -> Result<()> {
+        match self {
+            Self::Infant => {
+                info!("Taking on the duties of an Adult");
+                *self = Self::Adult(Box::new(state));
+                Ok(())
+            }
+            _ => Err(self.refused("become an Adult")),
+        }
+    }
+
+    /// To `Infant`, from `Adult`, handing over the state of the Adult.
+    pub(crate) fn begin_promotion(&mut self) -> Result<AdultState> {
+        match mem::replace(self, Self::Infant) {
+            Self::Adult(state) => Ok(*state),
+            role => {
+                *self = role;
+                Err(self.refused("be promoted"))
+            }
+        }
+    }
+
+    /// To `Elder`, from `Infant`.
+    pub(crate) fn become_elder(&mut self, state: ElderState) -> Result<()> {
+        match self {
+            Self::Infant => {
+                info!("Taking on the duties of an Elder");
+                *self = Self::Elder(Box::new(state));
+                Ok(())
+            }
+            _ => Err(self.refused("become an Elder")),
+        }
+    }
+
+    /// To `Infant`, from `Elder`, handing back the chunk store plugged in, if any.
+    pub(crate) fn begin_demotion(&mut self) -> Result<Option<Box<dyn ChunkStore>>> {
+        match mem::replace(self, Self::Infant) {
+            Self::Elder(state) => Ok(state.plugged_chunk_store),
+            role => {
+                *self = role;
+                Err(self.refused("be demoted"))
+            }
+        }
+    }
+
+    fn refused(&self, transition: &str) -> Error {
+        Error::InvalidOperation(format!("Unable to {} as {}", transition, self))
+    }
+
+    pub(crate) fn is_elder(&self) -> bool {
+        matches!(self, Self::Elder(_))
+    }
+
+    pub(crate) fn chunks(&self) -> Result<&Chunks> {
+        match self {
+            Self::Adult(state) => Ok(&state.chunks),
+            _ => Err(Error::NoChunks),
+        }
+    }
+
+    pub(crate) fn chunks_mut(&mut self) -> Result<&mut Chunks> {
+        match self {
+            Self::Adult(state) => Ok(&mut state.chunks),
+            _ => Err(Error::NoChunks),
+        }
+    }
+
+    /// The warm copy of the Elder state, if an Adult opted into keeping it.
+    pub(crate) fn standby_mut(&mut self) -> Option<&mut WarmStandby> {
+        match self {
+            Self::Adult(state) => state.standby.as_mut(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn meta_data(&self) -> Result<&Metadata> {
+        match self {
+            Self::Elder(state) => Ok(&state.meta_data),
+            _ => Err(Error::NoMetadata),
+        }
+    }
+
+    pub(crate) fn meta_data_mut(&mut self) -> Result<&mut Metadata> {
+        match self {
+            Self::Elder(state) => Ok(&mut state.meta_data),
+            _ => Err(Error::NoMetadata),
+        }
+    }
+
+    pub(crate) fn transfers(&self) -> Result<&Transfers> {
+        match self {
+            Self::Elder(state) => Ok(&state.transfers),
+            _ => Err(Error::NoTransfers),
+        }
+    }
+
+    pub(crate) fn transfers_mut(&mut self) -> Result<&mut Transfers> {
+        match self {
+            Self::Elder(state) => Ok(&mut state.transfers),
+            _ => Err(Error::NoTransfers),
+        }
+    }
+
+    pub(crate) fn section_funds(&self) -> Result<&SectionFunds> {
+        match self {
+            Self::Elder(state) => Ok(&state.section_funds),
+            _ => Err(Error::NoSectionFunds),
+        }
+    }
+
+    pub(crate) fn section_funds_mut(&mut self) -> Result<&mut SectionFunds> {
+        match self {
+            Self::Elder(state) => Ok(&mut state.section_funds),
+            _ => Err(Error::NoSectionFunds),
+        }
+    }
+
+    /// The section funds, while churning through a reward process.
+    pub(crate) fn churning_funds(
+        &mut self,
+    ) -> Result<(&mut RewardProcess, &mut RewardWallets, &mut Payments)> {
+        match self.section_funds_mut()? {
+            SectionFunds::Churning {
+                process,
+                wallets,
+                payments,
+            } => Ok((process, wallets, payments)),
+            _ => Err(Error::NotChurningFunds),
+        }
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Infant => write!(formatter, "an Infant"),
+            Self::Adult(_) => write!(formatter, "an Adult"),
+            Self::Elder(_) => write!(formatter, "an Elder"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chunk_store::UsedSpace;
+    use std::sync::Arc;
+    use tempdir::TempDir;
+    use xor_name::XorName;
+
+    #[tokio::test]
+    async fn transitions_are_refused_from_other_roles() -> Result<()> {
+        let root = TempDir::new("role").map_err(|e| Error::TempDirCreationFailed(e.to_string()))?;
+        let chunks = Chunks::new(
+            XorName::random(),
+            root.path(),
+            UsedSpace::new(u64::MAX),
+            None,
+            false,
+            None,
+            None,
+            Arc::default(),
+        )
+        .await?;
+        let mut role = Role::Infant;
+        assert!(role.begin_promotion().is_err());
+        assert!(role.begin_demotion().is_err());
+        assert!(matches!(role.chunks(), Err(Error::NoChunks)));
+
+        role.become_adult(AdultState {
+            chunks,
+            standby: None,
+        })?;
+        assert!(role.chunks().is_ok());
+        assert!(matches!(role.transfers(), Err(Error::NoTransfers)));
+        assert!(role.begin_demotion().is_err());
+        assert!(role.chunks().is_ok());
+
+        let adult = role.begin_promotion()?;
+        assert!(matches!(role, Role::Infant));
+        assert!(role.begin_promotion().is_err());
+        role.become_adult(adult)?;
+        assert!(!role.is_elder());
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    capacity::EconomyConfig,
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    Config, Error, Result,
+};
+use serde::{Deserialize, Serialize};
+use sn_data_types::NodeAge;
+use sn_messaging::DstLocation;
+
+/// The parameters all nodes of a section go by. They are set at genesis from the config
+/// of the genesis node, and pushed by the Elders of each section to its nodes along with
+/// their state, the nodes taking them from their own Elders only.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionParams {
+    /// Number of Elders to have applied a client write before it is acknowledged,
+    /// 0 where writes are not acknowledged unless the client selects a level.
+    pub min_write_acks: u8,
+    /// Percentage of each churn payout retained in the section wallet, rather than
+    /// distributed to nodes. The Elders only agree on payouts computed alike.
+    pub treasury_percentage: u8,
+    /// Age nodes are to have reached to take part in reward distribution. The Elders
+    /// only agree on the credits of a payout if they reward the same nodes.
+    pub min_reward_age: NodeAge,
+    /// The parameters of minting, rewards and store cost.
+    pub economy: EconomyConfig,
+}
+
+impl SectionParams {
+    /// The parameters of the network, as configured on the genesis node.
+    pub(crate) fn genesis(config: &Config) -> Self {
+        Self {
+            min_write_acks: config.min_write_acks(),
+            treasury_percentage: config.treasury_percentage(),
+            min_reward_age: config.min_reward_age(),
+            economy: config.economy(),
+        }
+    }
+
+    /// Whether the parameters can be gone by, as pushed by Elders.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.treasury_percentage > 100 {
+            return Err(Error::InvalidOperation(format!(
+                "Treasury percentage {} is over 100",
+                self.treasury_percentage
+            )));
+        }
+        let economy = &self.economy;
+        if economy.cost_chunk_size == 0
+            || economy.premium_redundancy_multiplier == 0
+            || economy.max_full_nodes_percentage > 100
+        {
+            return Err(Error::InvalidOperation(format!(
+                "Invalid economy {:?}",
+                economy
+            )));
+        }
+        Ok(())
+    }
+
+    /// The msg pushing the parameters to the dst.
+    pub(crate) fn msg(&self, dst: DstLocation) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::SectionParams(*self)),
+            dst,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn genesis_params_are_those_of_the_config() {
+        let config = Config {
+            min_write_acks: Some(2),
+            treasury_percentage: Some(150),
+            min_reward_age: Some(3),
+            economy: Some(EconomyConfig {
+                cost_chunk_size: 500_000,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let params = SectionParams::genesis(&config);
+        assert_eq!(params.min_write_acks, 2);
+        assert_eq!(params.treasury_percentage, 100);
+        assert_eq!(params.min_reward_age, 3);
+        assert_eq!(params.economy.cost_chunk_size, 500_000);
+        assert!(params.validate().is_ok());
+    }
+
+    #[test]
+    fn treasury_percentage_over_100_is_invalid() {
+        let params = |treasury_percentage| SectionParams {
+            treasury_percentage,
+            ..Default::default()
+        };
+        assert!(params(0).validate().is_ok());
+        assert!(params(100).validate().is_ok());
+        assert!(params(101).validate().is_err());
+    }
+
+    #[test]
+    fn economy_not_to_be_computed_by_is_invalid() {
+        let params = |economy| SectionParams {
+            economy,
+            ..Default::default()
+        };
+        assert!(params(EconomyConfig::default()).validate().is_ok());
+        let invalid = [
+            EconomyConfig {
+                cost_chunk_size: 0,
+                ..Default::default()
+            },
+            EconomyConfig {
+                premium_redundancy_multiplier: 0,
+                ..Default::default()
+            },
+            EconomyConfig {
+                max_full_nodes_percentage: 101,
+                ..Default::default()
+            },
+        ];
+        for economy in invalid.iter() {
+            assert!(params(*economy).validate().is_err());
+        }
+    }
+}
@@ -8,7 +8,7 @@
 
 use crate::{
     node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
-    section_funds::{self, SectionFunds},
+    section_funds::{self, PaymentHandover, SectionFunds},
     transfers::{
         get_replicas::replica_info,
         replica_signing::ReplicaSigningImpl,
@@ -17,13 +17,13 @@ use crate::{
     Error, Node, Result,
 };
 use dashmap::DashMap;
-use log::{debug, info};
+use log::{debug, info, warn};
 use section_funds::{
     elder_signing::ElderSigning,
     reward_process::{OurSection, RewardProcess},
     reward_stage::RewardStage,
     reward_wallets::RewardWallets,
-    Credits,
+    Credits, Payments,
 };
 use sn_data_types::{
     ActorHistory, CreditAgreementProof, CreditId, NodeAge, PublicKey, SectionElders, Token,
@@ -64,16 +64,19 @@ impl Node {
             our_key,
         };
 
-        let mut process =
-            RewardProcess::new(section, ElderSigning::new(self.network_api.clone()).await?);
+        let mut process = RewardProcess::new(
+            section,
+            ElderSigning::new(self.network_api.clone()).await?,
+            self.node_info.section_params,
+        );
 
         let wallets = RewardWallets::new(BTreeMap::<XorName, (NodeAge, PublicKey)>::new());
 
-        self.section_funds = Some(SectionFunds::Churning {
+        *self.role.section_funds_mut()? = SectionFunds::Churning {
             process,
             wallets,
-            payments: Default::default(),
-        });
+            payments: Payments::new(self.node_info.path()),
+        };
 
         Ok(())
     }
@@ -85,24 +88,41 @@ impl Node {
         our_key: PublicKey,
         sibling_key: PublicKey,
     ) -> Result<NodeDuties> {
-        let user_wallets = if let Some(transfers) = &mut self.transfers {
-            let info = replica_info(&self.node_info, &self.network_api).await?;
-            transfers.update_replica_info(info);
-            transfers.user_wallets()
-        } else {
-            return Err(Error::Logic("No transfers on this node".to_string()));
+        let transfers = self.role.transfers_mut()?;
+        // read before the replicas move on to the new section wallet
+        let section_balance = transfers.section_balance().await?;
+        let info = replica_info(&self.node_info, &self.network_api).await?;
+        transfers.update_replica_info(info);
+        let user_wallets = transfers.user_wallets();
+
+        let sibling_prefix = our_prefix.sibling();
+
+        // the payments and metadata within our sibling are partitioned off before paying out,
+        // so that they are handed over in bulk, rather than filtered at each send
+        let staged_payments = match self.role.section_funds()? {
+            SectionFunds::KeepingNodeWallets { payments, .. }
+            | SectionFunds::Churning { payments, .. } => {
+                payments.split_off(&sibling_prefix, self.node_info.path())?
+            }
         };
+        let staged_records = self
+            .role
+            .meta_data_mut()?
+            .stage_split(sibling_prefix)
+            .await?;
+        debug!(
+            "Staged {} payments and {} metadata records for our sibling",
+            staged_payments.len(),
+            staged_records
+        );
 
-        let (wallets, payments) = match &mut self.section_funds {
-            Some(SectionFunds::KeepingNodeWallets { wallets, payments })
-            | Some(SectionFunds::Churning {
+        let (wallets, payments, archived) = match self.role.section_funds()? {
+            SectionFunds::KeepingNodeWallets { wallets, payments }
+            | SectionFunds::Churning {
                 wallets, payments, ..
-            }) => (wallets.clone(), payments.sum()),
-            None => return Err(Error::NoSectionFunds),
+            } => (wallets.clone(), payments.sum(), payments.archived()),
         };
 
-        let sibling_prefix = our_prefix.sibling();
-
         debug!(
             "@@@@@@ SPLIT: Our prefix: {:?}, neighbour: {:?}",
             our_prefix, sibling_prefix,
@@ -115,10 +135,10 @@ impl Node {
         let mut ops = vec![];
 
         if payments > Token::zero() {
-            let section_managed = self.get_transfers()?.managed_amount().await?;
+            let section_managed = self.role.transfers_mut()?.managed_amount().await?;
 
             // payments made since last churn
-            debug!("Payments: {}", payments);
+            debug!("Payments: {} ({} archived)", payments, archived);
             // total amount in wallets
             debug!("Managed amount: {}", section_managed);
 
@@ -129,28 +149,60 @@ impl Node {
                     our_key,
                 },
                 ElderSigning::new(self.network_api.clone()).await?,
+                self.node_info.section_params,
             );
 
-            ops.push(
-                process
-                    .reward_and_mint(payments, section_managed, wallets.node_wallets())
-                    .await?,
-            );
-
-            self.section_funds = Some(SectionFunds::Churning {
-                process,
-                wallets: wallets.clone(),
-                payments: Default::default(), // clear old payments
-            });
+            match process
+                .reward_and_mint(
+                    payments,
+                    section_managed,
+                    section_balance,
+                    wallets.node_wallets(),
+                )
+                .await
+            {
+                Ok(op) => {
+                    ops.push(op);
+                    *self.role.section_funds_mut()? = SectionFunds::Churning {
+                        process,
+                        wallets: wallets.clone(),
+                        payments: Payments::new(self.node_info.path()), // clear old payments
+                    };
+                }
+                // the payments are kept, to be paid out at next split
+                Err(Error::PayoutNotCovered(diagnostic)) => {
+                    warn!("Not paying out rewards at split, {}", diagnostic)
+                }
+                Err(e) => return Err(e),
+            }
         } else {
             debug!("Not paying out rewards, as no payments have been received since last split.");
         }
 
         let msg_id = MessageId::combine(vec![our_prefix.name(), XorName::from(our_key)]);
-        ops.push(self.push_state(our_prefix, msg_id));
+        ops.extend(self.push_state(our_prefix, msg_id));
 
+        // sent to the sibling Elders directly if known, as the
+        // routing layer may not know of the sibling this soon
         let msg_id = MessageId::combine(vec![sibling_prefix.name(), XorName::from(sibling_key)]);
-        ops.push(self.push_state(sibling_prefix, msg_id));
+        let sibling_elders = self
+            .network_api
+            .sibling()
+            .map(|sibling| sibling.elders)
+            .unwrap_or_default();
+        if sibling_elders.is_empty() {
+            ops.extend(self.push_state(sibling_prefix, msg_id))
+        } else {
+            ops.extend(self.push_wallet_summaries(sibling_prefix, &sibling_elders));
+            ops.extend(sibling_elders.iter().flat_map(|elder| {
+                self.push_state_to(DstLocation::Node(*elder), sibling_prefix, msg_id)
+            }))
+        }
+        ops.extend(PaymentHandover::msgs(
+            &staged_payments,
+            sibling_prefix,
+            sibling_elders,
+        )?);
 
         Ok(ops)
     }
@@ -0,0 +1,83 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use log::info;
+use sn_data_types::{ActorHistory, NodeAge, PublicKey};
+use std::collections::BTreeMap;
+use xor_name::XorName;
+
+/// The number of oldest Adults, being next in line for promotion,
+/// which Elders keep a warm copy of their state at.
+pub(crate) const WARM_STANDBY_COUNT: usize = 2;
+
+/// A warm copy of the Elder state, kept by an Adult next in line for promotion
+/// which opted into it, so that it starts out as an Elder with the state of
+/// the last push, and only the changes since are to be caught up on.
+#[derive(Default)]
+pub(crate) struct WarmStandby {
+    node_wallets: BTreeMap<XorName, (NodeAge, PublicKey)>,
+    user_wallets: BTreeMap<PublicKey, ActorHistory>,
+}
+
+impl WarmStandby {
+    /// Updates the copy with the state pushed by the Elders.
+    /// The latest state of each wallet supersedes the one held.
+    pub(crate) fn update(
+        &mut self,
+        node_wallets: BTreeMap<XorName, (NodeAge, PublicKey)>,
+        user_wallets: BTreeMap<PublicKey, ActorHistory>,
+    ) {
+        info!(
+            "Keeping warm copy of {} node wallets and {} user wallets",
+            node_wallets.len(),
+            user_wallets.len()
+        );
+        self.node_wallets.extend(node_wallets);
+        self.user_wallets.extend(user_wallets);
+    }
+
+    /// The state to start out with as an Elder.
+    pub(crate) fn into_state(
+        self,
+    ) -> (
+        BTreeMap<XorName, (NodeAge, PublicKey)>,
+        BTreeMap<PublicKey, ActorHistory>,
+    ) {
+        (self.node_wallets, self.user_wallets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+
+    #[test]
+    fn latest_pushed_wallets_supersede_those_held() {
+        let name = XorName::random();
+        let old_key = PublicKey::from(SecretKey::random().public_key());
+        let new_key = PublicKey::from(SecretKey::random().public_key());
+        let other = XorName::random();
+        let mut standby = WarmStandby::default();
+
+        standby.update(
+            vec![(name, (5, old_key)), (other, (6, old_key))]
+                .into_iter()
+                .collect(),
+            BTreeMap::new(),
+        );
+        standby.update(
+            vec![(name, (6, new_key))].into_iter().collect(),
+            BTreeMap::new(),
+        );
+
+        let (node_wallets, _) = standby.into_state();
+        assert_eq!(node_wallets.get(&name), Some(&(6, new_key)));
+        assert_eq!(node_wallets.get(&other), Some(&(6, old_key)));
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::{NodeDuty, PeriodicTask},
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sn_messaging::{DstLocation, MessageId};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+use xor_name::{Prefix, XorName};
+
+/// How often the state pushes not acknowledged in time are retried.
+const STATE_PUSH_TICK: Duration = Duration::from_secs(5);
+// The time a new Elder has to acknowledge the state pushed to it, before it is pushed again.
+const STATE_PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+// The times the state is pushed to a new Elder at most, before it is given up on.
+const MAX_STATE_PUSH_ATTEMPTS: usize = 5;
+
+/// The acknowledgment by a new Elder of the state pushed to it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatePushAck {
+    /// Id of the msg pushing the state.
+    pub id: MessageId,
+}
+
+impl StatePushAck {
+    /// The msg acknowledging the push to the Elder pushing it.
+    pub(crate) fn msg(&self, elder: XorName) -> NodeDuty {
+        NodeDuty::SendNodeMsg {
+            msg: NodeMsg::new(NodeMsgBody::StatePushAck(self.clone())),
+            dst: DstLocation::Node(elder),
+        }
+    }
+}
+
+// The state pushed to a new Elder, until acknowledged.
+struct StatePush {
+    prefix: Prefix,
+    // the ids of the msgs pushing it, one per attempt
+    ids: Vec<MessageId>,
+    sent_at: Instant,
+}
+
+/// The background work on the pushes of our state, retrying them.
+pub(crate) fn periodic_tasks() -> Vec<PeriodicTask> {
+    vec![PeriodicTask {
+        name: "state push retries",
+        interval: STATE_PUSH_TICK,
+        duty: || NodeDuty::RetryStatePushes,
+    }]
+}
+
+/// The pushes of our state to each new Elder after churn, tracked apart, so that each
+/// is pushed again until acknowledged, and an unreachable one holds up no other.
+#[derive(Default)]
+pub(crate) struct StatePushes {
+    pending: BTreeMap<XorName, StatePush>,
+}
+
+impl StatePushes {
+    /// Records the state as pushed to the Elder by the msg, replacing any push pending.
+    pub(crate) fn pushed(&mut self, elder: XorName, prefix: Prefix, id: MessageId, now: Instant) {
+        let _ = self.pending.insert(
+            elder,
+            StatePush {
+                prefix,
+                ids: vec![id],
+                sent_at: now,
+            },
+        );
+    }
+
+    /// Concludes the push to the Elder, if the ack answers any msg of it.
+    pub(crate) fn acked(&mut self, elder: &XorName, ack: &StatePushAck) -> bool {
+        match self.pending.get(elder) {
+            Some(push) if push.ids.contains(&ack.id) => {
+                let _ = self.pending.remove(elder);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The Elders to push the state to again, with the prefix of the state and the id
+    /// of the msg to push it by, dropping those no longer Elders of ours, and those
+    /// given up on past the max attempts.
+    pub(crate) fn due(
+        &mut self,
+        elders: &BTreeSet<XorName>,
+        now: Instant,
+    ) -> Vec<(XorName, Prefix, MessageId)> {
+        self.pending.retain(|elder, _| elders.contains(elder));
+        let mut due = vec![];
+        let mut given_up = vec![];
+        for (elder, push) in &mut self.pending {
+            if now.saturating_duration_since(push.sent_at) < STATE_PUSH_TIMEOUT {
+                continue;
+            }
+            if push.ids.len() >= MAX_STATE_PUSH_ATTEMPTS {
+                given_up.push(*elder);
+                continue;
+            }
+            info!(
+                "State push to {} not acknowledged, pushing again (attempt no. {})",
+                elder,
+                push.ids.len() + 1
+            );
+            let id = MessageId::new();
+            push.ids.push(id);
+            push.sent_at = now;
+            due.push((*elder, push.prefix, id));
+        }
+        for elder in given_up {
+            warn!(
+                "State push to {} given up after {} attempts",
+                elder, MAX_STATE_PUSH_ATTEMPTS
+            );
+            let _ = self.pending.remove(&elder);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn each_elder_is_pushed_to_again_until_acknowledged() {
+        let prefix = Prefix::default();
+        let (reachable, unreachable) = (XorName::random(), XorName::random());
+        let elders: BTreeSet<_> = vec![reachable, unreachable].into_iter().collect();
+        let mut pushes = StatePushes::default();
+        let mut now = Instant::now();
+        let first = MessageId::new();
+        pushes.pushed(reachable, prefix, first, now);
+        pushes.pushed(unreachable, prefix, MessageId::new(), now);
+        assert!(pushes.due(&elders, now).is_empty());
+
+        // the ack of an earlier attempt concludes the push, while others are retried
+        now += STATE_PUSH_TIMEOUT;
+        assert_eq!(pushes.due(&elders, now).len(), 2);
+        assert!(!pushes.acked(
+            &reachable,
+            &StatePushAck {
+                id: MessageId::new()
+            }
+        ));
+        assert!(pushes.acked(&reachable, &StatePushAck { id: first }));
+        for _ in 2..MAX_STATE_PUSH_ATTEMPTS {
+            now += STATE_PUSH_TIMEOUT;
+            match pushes.due(&elders, now).as_slice() {
+                [(elder, ..)] => assert_eq!(*elder, unreachable),
+                due => panic!("Unexpected pushes due: {:?}", due),
+            }
+        }
+
+        // given up past the max attempts
+        now += STATE_PUSH_TIMEOUT;
+        assert!(pushes.due(&elders, now).is_empty());
+        assert!(pushes.pending.is_empty());
+    }
+}
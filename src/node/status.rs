@@ -0,0 +1,42 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::Node;
+use serde::{Deserialize, Serialize};
+use xor_name::{Prefix, XorName};
+
+/// The status of the node, as reported to its operator.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    /// Name of the node.
+    pub name: XorName,
+    /// Prefix of our section.
+    pub prefix: Prefix,
+    /// Age of the node.
+    pub age: u8,
+    /// Whether the node is an Elder.
+    pub is_elder: bool,
+    /// Times restarted by the supervisor of the node, after crashing.
+    pub restarts: u32,
+    /// Secs our clock is corrected by, for being skewed from the times reported by peers.
+    pub clock_correction_secs: i64,
+}
+
+impl Node {
+    /// The status of the node.
+    pub async fn status(&self) -> NodeStatus {
+        NodeStatus {
+            name: self.network_api.our_name().await,
+            prefix: self.network_api.our_prefix().await,
+            age: self.network_api.age().await,
+            is_elder: self.network_api.is_elder().await,
+            restarts: self.node_info.restarts,
+            clock_correction_secs: self.network_api.clock().correction(),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_ops::{NodeDuties, NodeDuty},
+    operator::{inbox, rpc},
+    Error, Node, Result,
+};
+use futures::FutureExt;
+use log::{debug, error, info};
+use std::{
+    any::Any,
+    cmp::min,
+    collections::BTreeMap,
+    future::Future,
+    net::SocketAddr,
+    panic::AssertUnwindSafe,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{sync::mpsc::Sender, task::JoinHandle, time};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Spawns a background task, which is restarted
+/// with an exponential backoff whenever it panics.
+/// A task returning normally is not restarted.
+pub(crate) fn spawn_supervised<F, Fut>(name: &'static str, task: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    spawn_with_backoff(name, task, INITIAL_BACKOFF)
+}
+
+/// Spawns a supervised task, which at every interval passes a duty to the node,
+/// the node doing the work of the task. While the work is backing off from a
+/// panic, as told by the health of the tasks, no duty is passed.
+/// The task ends when the node stops receiving duties.
+pub(crate) fn spawn_periodic<F>(
+    name: &'static str,
+    interval: Duration,
+    duties: Sender<NodeDuty>,
+    health: TaskHealth,
+    duty: F,
+) -> JoinHandle<()>
+where
+    F: Fn() -> NodeDuty + Send + Sync + 'static,
+{
+    let duty = Arc::new(duty);
+    spawn_supervised(name, move || {
+        let duties = duties.clone();
+        let health = health.clone();
+        let duty = duty.clone();
+        async move {
+            let mut ticks = time::interval(interval);
+            // the first tick completes immediately
+            let _ = ticks.tick().await;
+            loop {
+                let _ = ticks.tick().await;
+                let duty = duty();
+                if let Some(backoff) = health.backoff(duty.name(), Instant::now()) {
+                    time::sleep(backoff).await;
+                    info!("Restarting the work of background task {}", name);
+                    // rather than catching up on the ticks missed
+                    ticks = time::interval_at(time::Instant::now() + interval, interval);
+                }
+                if duties.send(duty).await.is_err() {
+                    debug!("Node no longer receiving duties from {}", name);
+                    return;
+                }
+            }
+        }
+    })
+}
+
+/// The backoff of the periodic tasks whose work panicked,
+/// by the duty doing the work, shared with their tickers.
+#[derive(Clone, Default)]
+pub(crate) struct TaskHealth {
+    backoffs: Arc<Mutex<BTreeMap<&'static str, Backoff>>>,
+}
+
+struct Backoff {
+    // when the work is done again
+    resume: Instant,
+    // the backoff after the next panic
+    next: Duration,
+}
+
+impl TaskHealth {
+    /// Records the work of the task panicking, for it to be restarted after
+    /// a backoff, doubling with every panic until the work completes again.
+    /// Returns the backoff.
+    pub(crate) fn panicked(&self, duty: &'static str, now: Instant) -> Duration {
+        let mut backoffs = self.lock();
+        let backoff = backoffs.entry(duty).or_insert(Backoff {
+            resume: now,
+            next: INITIAL_BACKOFF,
+        });
+        let wait = backoff.next;
+        backoff.resume = now + wait;
+        backoff.next = min(wait * 2, MAX_BACKOFF);
+        wait
+    }
+
+    /// Records the work of the task completing, so that it starts over
+    /// with a short backoff if it panics again.
+    pub(crate) fn completed(&self, duty: &'static str) {
+        let _ = self.lock().remove(duty);
+    }
+
+    /// How long the work of the task is yet to back off for, if it is backing off.
+    fn backoff(&self, duty: &'static str, now: Instant) -> Option<Duration> {
+        self.lock()
+            .get(duty)
+            .map(|backoff| backoff.resume.saturating_duration_since(now))
+            .filter(|wait| *wait > Duration::from_secs(0))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, Backoff>> {
+        // the map is left consistent by all holding the lock
+        self.backoffs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Node {
+    /// Handles a duty of the work of a background task, catching its panics, so that
+    /// the task is restarted with a backoff rather than the node going down with it.
+    pub(super) async fn handle_supervised(
+        &mut self,
+        task: &'static str,
+        duty: NodeDuty,
+    ) -> (Result<NodeDuties>, bool) {
+        let name = duty.name();
+        match AssertUnwindSafe(self.handle(duty)).catch_unwind().await {
+            Ok(outcome) => (outcome, false),
+            Err(panic) => {
+                let panic = panic_message(panic);
+                let backoff = self.task_health.panicked(task, Instant::now());
+                error!(
+                    "Background task {} panicked handling {}: {}. Restarting it in {:?}.",
+                    task, name, panic, backoff
+                );
+                let error = Error::DutyPanicked {
+                    duty: name.to_string(),
+                    panic,
+                };
+                (Err(error), true)
+            }
+        }
+    }
+}
+
+/// Spawns a supervised task, which passes the
+/// operator commands dropped into the inbox to the node.
+pub(crate) fn spawn_operator_inbox(
+    inbox: PathBuf,
+    interval: Duration,
+    duties: Sender<NodeDuty>,
+) -> JoinHandle<()> {
+    spawn_supervised("operator inbox", move || {
+        let inbox = inbox.clone();
+        let duties = duties.clone();
+        async move {
+            let mut interval = time::interval(interval);
+            loop {
+                let _ = interval.tick().await;
+                for cmd in inbox::take_commands(&inbox) {
+                    if duties
+                        .send(NodeDuty::RunOperatorCommand(cmd))
+                        .await
+                        .is_err()
+                    {
+                        debug!("Node no longer receiving operator commands");
+                        return;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Spawns a supervised task, serving the operator
+/// control socket at the address over mutual TLS.
+pub(crate) fn spawn_operator_rpc(
+    addr: SocketAddr,
+    tls_dir: PathBuf,
+    duties: Sender<NodeDuty>,
+) -> JoinHandle<()> {
+    spawn_supervised("operator rpc", move || {
+        rpc::serve(addr, tls_dir.clone(), duties.clone())
+    })
+}
+
+fn spawn_with_backoff<F, Fut>(
+    name: &'static str,
+    task: F,
+    initial_backoff: Duration,
+) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut backoff = initial_backoff;
+        let mut restarts = 0_u64;
+        loop {
+            let started = Instant::now();
+            match tokio::spawn(task()).await {
+                Ok(()) => {
+                    debug!("Background task {} completed", name);
+                    return;
+                }
+                Err(error) if error.is_panic() => {
+                    // a task that has been running fine for
+                    // a while starts over with a short backoff
+                    if started.elapsed() > MAX_BACKOFF {
+                        backoff = initial_backoff;
+                    }
+                    restarts += 1;
+                    error!(
+                        "Background task {} panicked: {}. Restarting in {:?} (restart no. {}).",
+                        name,
+                        panic_message(error.into_panic()),
+                        backoff,
+                        restarts
+                    );
+                    time::sleep(backoff).await;
+                    backoff = min(backoff * 2, MAX_BACKOFF);
+                    info!("Restarting background task {}", name);
+                }
+                Err(_) => {
+                    debug!("Background task {} was cancelled", name);
+                    return;
+                }
+            }
+        }
+    })
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn panicking_task_is_restarted() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let handle = spawn_with_backoff(
+            "test",
+            move || {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                        panic!("failing on purpose");
+                    }
+                }
+            },
+            Duration::from_millis(1),
+        );
+        assert!(handle.await.is_ok());
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn panicking_work_backs_off_until_completed() {
+        let health = TaskHealth::default();
+        let now = Instant::now();
+        assert_eq!(health.backoff("ScrubChunks", now), None);
+
+        assert_eq!(health.panicked("ScrubChunks", now), INITIAL_BACKOFF);
+        assert_eq!(health.backoff("ScrubChunks", now), Some(INITIAL_BACKOFF));
+        assert_eq!(health.backoff("MigrateChunks", now), None);
+        // the backoff doubles with every panic
+        assert_eq!(health.panicked("ScrubChunks", now), INITIAL_BACKOFF * 2);
+        assert_eq!(
+            health.backoff("ScrubChunks", now + INITIAL_BACKOFF * 2),
+            None
+        );
+
+        health.completed("ScrubChunks");
+        assert_eq!(health.panicked("ScrubChunks", now), INITIAL_BACKOFF);
+    }
+}
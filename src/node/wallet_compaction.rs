@@ -0,0 +1,92 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_ops::NodeDuties,
+    transfers::{SignedWalletSummary, WalletSummaryPush},
+    Node, Result,
+};
+use log::{debug, info, warn};
+use sn_routing::{Prefix, XorName};
+use std::collections::BTreeSet;
+
+impl Node {
+    /// Compacts the histories of the node reward wallets held by our replicas,
+    /// which are credited at every churn. Only Elders hold them.
+    pub(crate) async fn compact_wallet_histories(&self) -> Result<NodeDuties> {
+        let (transfers, section_funds) = match (self.role.transfers(), self.role.section_funds()) {
+            (Ok(transfers), Ok(section_funds)) => (transfers, section_funds),
+            _ => return Ok(vec![]),
+        };
+        let mut compacted = 0;
+        for (_, wallet) in section_funds.node_wallets().values() {
+            match transfers.compact_history(*wallet).await {
+                Ok(Some(_)) => compacted += 1,
+                Ok(None) => (),
+                Err(e) => warn!("Could not compact the history of wallet {}: {}", wallet, e),
+            }
+        }
+        if compacted > 0 {
+            info!(
+                "Compacted the histories of {} node reward wallets",
+                compacted
+            );
+        }
+        Ok(vec![])
+    }
+
+    /// The msgs pushing the summaries of the compacted histories of the node reward wallets
+    /// within the prefix to the Elders, along with the state pushed to them.
+    pub(crate) fn push_wallet_summaries(
+        &self,
+        prefix: Prefix,
+        elders: &BTreeSet<XorName>,
+    ) -> NodeDuties {
+        let (transfers, section_funds) = match (self.role.transfers(), self.role.section_funds()) {
+            (Ok(transfers), Ok(section_funds)) => (transfers, section_funds),
+            _ => return vec![],
+        };
+        let summaries: Vec<SignedWalletSummary> = section_funds
+            .node_wallets()
+            .values()
+            .map(|(_, wallet)| *wallet)
+            .filter(|wallet| prefix.matches(&XorName::from(*wallet)))
+            .filter_map(|wallet| transfers.wallet_summary(wallet))
+            .collect();
+        WalletSummaryPush::msgs(summaries, elders)
+    }
+
+    /// Imports the summaries of the wallet histories within our prefix, pushed by an Elder.
+    pub(crate) async fn receive_wallet_summaries(
+        &self,
+        push: WalletSummaryPush,
+        elder: XorName,
+    ) -> Result<NodeDuties> {
+        // only Elders hold wallets
+        let transfers = match self.role.transfers() {
+            Ok(transfers) => transfers,
+            Err(_) => return Ok(vec![]),
+        };
+        let prefix = self.network_api.our_prefix().await;
+        for signed in push.summaries {
+            let wallet = signed.summary.wallet;
+            if !prefix.matches(&XorName::from(wallet)) {
+                continue;
+            }
+            match transfers.import_wallet_summary(signed).await {
+                Ok(true) => debug!("Imported the summary of wallet {} from {}", wallet, elder),
+                Ok(false) => (),
+                Err(e) => warn!(
+                    "Not importing the summary of wallet {} from {}: {}",
+                    wallet, elder, e
+                ),
+            }
+        }
+        Ok(vec![])
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The msgs between nodes, and from nodes to clients, which sn_messaging has no msgs for
+//! yet. They are sent behind a tag and a version, so that they are told apart from the
+//! msgs of sn_messaging, and from each other, by what they are rather than by trying to
+//! read them as one kind of msg after the other.
+
+use crate::{
+    capacity::{SectionFullness, StoreCostQuote},
+    chunks::{
+        ChunkRange, ImportedChunks, OrphanOffer, OrphanRelease, RangedChunkRead, ReplicationFailed,
+    },
+    metadata::{
+        CapacityReport, CatchUpQuery, ChunkAccessQuery, ChunkAccessReport, DataMapCmd,
+        DataMapQuery, ExistenceAttestation, ExistenceQuery, MetadataPage, MetadataRangeQuery,
+        RelayedChunk, RelayedRead, RepairReport, ReplicationBatch, SignedCatchUpPage,
+        SignedMetadataDump, StorageChallenge, StorageProof, WriteAck, WriteAckLevel,
+    },
+    network::{PeerPing, PeerPong},
+    node::{BatchQueryResponse, Busy, SectionParams, SectionRedirect, SectionUpdate, StatePushAck},
+    node_ops::OutgoingMsg,
+    section_funds::{reward_stage::RewardStatus, PaymentHandover, SignedFundsExport},
+    transfers::{SignedStoreCostEstimate, TransferStatus, TransferStatusQuery, WalletSummaryPush},
+    utils, Error, Result,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sn_data_types::{
+    Blob, BlobAddress, MapAddress, MapEntryActions, MapUnseqEntryActions, PublicBlob, Signature,
+};
+use sn_messaging::{
+    client::{DataCmd, DataQuery, MapRead, MapWrite, Message, QueryResponse},
+    Aggregation, DstLocation, EndUser, MessageId,
+};
+use xor_name::XorName;
+
+/// Tags the content of routing msgs which are a `NodeMsg`. Read as the header size of a
+/// msg of sn_messaging, it is beyond the size of any header, so neither is taken for the other.
+const NODE_MSG_TAG: &[u8] = b"sn_node::NodeMsg";
+/// Tags the chunks read by clients which are a `ClientMsg`.
+const CLIENT_MSG_TAG: &[u8] = b"sn_node::ClientMsg";
+/// Tags the Map keys queried by clients which are a `ClientRequest`.
+const CLIENT_REQUEST_TAG: &[u8] = b"sn_node::ClientRequest";
+/// Tags the Map entry keys written by clients which are a `ClientCmd`.
+const CLIENT_CMD_TAG: &[u8] = b"sn_node::ClientCmd";
+/// The version of the encoding of `NodeMsg` and `ClientMsg`, following the tag.
+/// Msgs of other versions are rejected as unsupported.
+pub const NODE_MSG_VERSION: u8 = 1;
+
+/// A msg between nodes, by the id it is sent under.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeMsg {
+    /// Id of the msg.
+    pub id: MessageId,
+    /// What the msg is.
+    pub body: NodeMsgBody,
+}
+
+/// The msgs between nodes.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum NodeMsgBody {
+    /// Query by an Elder for a page of the metadata records within a range of names.
+    MetadataRangeQuery(MetadataRangeQuery),
+    /// A page of the metadata records, answering the query of the msg of the id.
+    MetadataPage {
+        /// The id of the msg of the query answered.
+        query: MessageId,
+        /// The records.
+        page: MetadataPage,
+    },
+    /// Offer by a holder out of the section owning the chunk, for it to take the chunk on.
+    OrphanOffer(OrphanOffer),
+    /// The answer of an Elder to an offer of an orphan chunk, releasing its holder.
+    OrphanRelease(OrphanRelease),
+    /// The chunk sent by a holder for replication, found corrupt by the new holder.
+    ReplicationFailed(ReplicationFailed),
+    /// The chunks imported by a holder, for our Elders to register it as holding them.
+    ImportedChunks(ImportedChunks),
+    /// The report by a holder of a failed read of a chunk, for our Elders to repair its copy.
+    ChunkReadFailed(BlobAddress),
+    /// The acknowledgment by a holder of a chunk deleted as told by our section.
+    ChunkDeleted {
+        /// The chunk deleted.
+        address: BlobAddress,
+        /// Signature of the holder over the chunk address.
+        proof: Signature,
+    },
+    /// Challenge by an Elder for the holders of a chunk to prove they hold it.
+    StorageChallenge(StorageChallenge),
+    /// The answer of a holder to the challenge of an Elder.
+    StorageProof(StorageProof),
+    /// Ping of a peer of our section, probing its round trip time.
+    PeerPing(PeerPing),
+    /// The answer of a peer to a ping, carrying its time.
+    PeerPong(PeerPong),
+    /// The capacity of the storage of an Adult, reported to our Elders.
+    CapacityReport(CapacityReport),
+    /// Query by a new Elder for a page of the metadata records of another Elder.
+    CatchUpQuery(CatchUpQuery),
+    /// A page of the metadata records, signed by the Elder answering the catch-up query.
+    SignedCatchUpPage(SignedCatchUpPage),
+    /// Query by an Elder for the reads of the chunks held by an Adult.
+    ChunkAccessQuery(ChunkAccessQuery),
+    /// The answer of a holder to the query of an Elder for the reads of its chunks.
+    ChunkAccessReport(ChunkAccessReport),
+    /// A read of a chunk by a client, relayed by an Elder to holders of the chunk.
+    RelayedRead(RelayedRead),
+    /// The chunk read by a holder, answered to the Elder relaying the read.
+    RelayedChunk(RelayedChunk),
+    /// A read of a range of a chunk by a client, sent by an Elder to holders of the chunk.
+    RangedChunkRead(RangedChunkRead),
+    /// A part of the metadata records of a section, signed by an Elder dumping them.
+    SignedMetadataDump(SignedMetadataDump),
+    /// The chunks an Elder batched for the new holder to copy.
+    ReplicationBatch(ReplicationBatch),
+    /// A part of the funds of a section, signed by an Elder exporting them.
+    SignedFundsExport(SignedFundsExport),
+    /// The payments to our sibling, handed over by its Elders at split.
+    PaymentHandover(PaymentHandover),
+    /// The max chunk size of our section, pushed by an Elder along with its state.
+    MaxChunkSize(u64),
+    /// The parameters of our section, pushed by an Elder along with its state.
+    SectionParams(SectionParams),
+    /// A client write applied by an Elder, to the other Elders of its section.
+    WriteAck(WriteAck),
+    /// The ack level selected by a client for a write, to the Elders applying it.
+    WriteAckLevel(WriteAckLevel),
+    /// The acknowledgment by a new Elder of the state pushed to it.
+    StatePushAck(StatePushAck),
+    /// The summaries of the wallet histories within the prefix of the Elders pushed to.
+    WalletSummaryPush(WalletSummaryPush),
+    /// Query by an Elder of ours for the progress of our reward process,
+    /// or by a node of ours for that of the credits to its wallet.
+    RewardStatusQuery,
+    /// The progress of the reward process, answering the query of the msg of the id.
+    RewardStatus {
+        /// The id of the msg of the query answered.
+        query: MessageId,
+        /// The progress.
+        status: RewardStatus,
+    },
+}
+
+impl NodeMsg {
+    /// The msg under a new id.
+    pub(crate) fn new(body: NodeMsgBody) -> Self {
+        Self {
+            id: MessageId::new(),
+            body,
+        }
+    }
+
+    /// The msg under an id derived from what it is, so that the msgs of all nodes
+    /// sending the same, e.g. the Elders of a section, are one and the same at destination.
+    pub(crate) fn derived(body: NodeMsgBody) -> Result<Self> {
+        Ok(Self {
+            id: MessageId::from_content(&body)?,
+            body,
+        })
+    }
+
+    /// Whether the msg is under the id derived from what it is.
+    pub(crate) fn is_derived(&self) -> bool {
+        MessageId::from_content(&self.body).ok() == Some(self.id)
+    }
+
+    /// The msg as the content of a routing msg: the tag, the version and the msg.
+    pub(crate) fn serialise(&self) -> Result<Bytes> {
+        tagged(NODE_MSG_TAG, self)
+    }
+
+    /// The msg in the content of a routing msg, if it is tagged as one. Msgs tagged
+    /// as one, which cannot be read, e.g. of another version, are an error.
+    pub(crate) fn from_content(content: &[u8]) -> Option<Result<Self>> {
+        untagged(NODE_MSG_TAG, content)
+    }
+}
+
+/// The msgs from nodes to clients. Clients only take msgs of sn_messaging, so they
+/// are sent as the chunk read by a `GetBlob` response, under the correlation id of
+/// the msg of the client they answer.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientMsg {
+    /// The response to a batch of queries.
+    BatchResponse(BatchQueryResponse),
+    /// Our new section info, pushed to the connected clients when our Elders changed,
+    /// under the correlation id of the msg of the change.
+    SectionUpdate(SectionUpdate),
+    /// The answer to a client cmd refused as we are busy, of when to send it again.
+    Busy(Busy),
+    /// The chunk the client wrote other content to, refused as a chunk is written once.
+    DataConflict(BlobAddress),
+    /// The hint to the client, of the section to send its query to directly next time.
+    SectionRedirect(SectionRedirect),
+    /// How full our section is, sent along with the store cost quoted to the client,
+    /// under the id the response to the store cost query has.
+    SectionFullness(SectionFullness),
+    /// The store cost quoted to the client, at the standard redundancy and at the premium,
+    /// under the id the response to the store cost query has. Its payment is charged as quoted.
+    StoreCostQuote(StoreCostQuote),
+    /// The share of an Elder of the attestation of a chunk existing, answering the
+    /// existence query of the client. The client combines those of a majority of Elders.
+    ExistenceAttestation(ExistenceAttestation),
+    /// The itemized store cost of an upload of the bytes of the store cost query of the
+    /// client, in chunks of at most the max chunk size of our section, with the share of
+    /// the Elder of the signature of the section, under the id the response to the query has.
+    StoreCostEstimate(SignedStoreCostEstimate),
+    /// The state of each chunk of the repair request of the data owner, and what was done about it.
+    RepairReport(RepairReport),
+    /// The registration status of the credit or debit of the transfer status query of the client,
+    /// with its agreement proof when registered.
+    TransferStatus(TransferStatus),
+}
+
+impl ClientMsg {
+    /// The msg to the client, answering its msg of the id. The id of the msg is derived
+    /// from both, so that it differs from that of any other response to the same msg.
+    pub(crate) fn msg(&self, correlation_id: MessageId, origin: EndUser) -> Result<OutgoingMsg> {
+        let blob = Blob::Public(PublicBlob::new(tagged(CLIENT_MSG_TAG, self)?.to_vec()));
+        Ok(OutgoingMsg {
+            msg: Message::QueryResponse {
+                response: QueryResponse::GetBlob(Ok(blob)),
+                id: MessageId::from_content(&(correlation_id, self))?,
+                correlation_id,
+                target_section_pk: None,
+            },
+            section_source: false, // sent as single node
+            dst: DstLocation::EndUser(origin),
+            aggregation: Aggregation::None,
+        })
+    }
+
+    /// The msg in the response, if it is tagged as one. Msgs tagged
+    /// as one, which cannot be read, e.g. of another version, are an error.
+    pub fn from_response(response: &QueryResponse) -> Option<Result<Self>> {
+        match response {
+            QueryResponse::GetBlob(Ok(blob)) => untagged(CLIENT_MSG_TAG, blob.value()),
+            _ => None,
+        }
+    }
+}
+
+/// The requests from clients to nodes, which sn_messaging has no msgs for yet. Clients only
+/// send msgs of sn_messaging, so they are sent as the key read by a `MapRead::GetValue`
+/// query, of a Map at the name of the section the request is for. They are answered by
+/// a `ClientMsg`, or by an error response to the query, under the id of its msg.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientRequest {
+    /// Request for the Elders holding a chunk to attest it exists.
+    Existence(ExistenceQuery),
+    /// Read of only the bytes of the range of a chunk, answered as a `GetBlob` response.
+    ChunkRange {
+        /// The chunk read.
+        address: BlobAddress,
+        /// The bytes of the chunk read.
+        range: ChunkRange,
+    },
+    /// Request by a data owner to re-verify and repair the holders of its chunks, e.g. after a
+    /// network incident. The chunks are to be of the section of the first one, those of other
+    /// sections being reported as unknown to it.
+    ChunkRepair(Vec<BlobAddress>),
+    /// Read of a client data map (file manifest), or of the list of its versions, answered
+    /// as a `GetSequenceLastEntry` or a `GetSequenceRange` response.
+    DataMap(DataMapQuery),
+    /// Independent reads of data at once, answered by a `ClientMsg::BatchResponse` with the
+    /// outcome of each. Client requests are not read in a batch, but refused. They are read
+    /// by the section of the first, which forwards the others to the sections of their data,
+    /// as it would have them sent one by one.
+    ReadBatch(Vec<DataQuery>),
+    /// Query for the status of a specific credit or debit of a wallet, e.g. to confirm a payment
+    /// landed without reading the full history, answered by a `ClientMsg::TransferStatus`.
+    TransferStatus(TransferStatusQuery),
+}
+
+impl ClientRequest {
+    /// The name of the section the request is for.
+    pub fn name(&self) -> XorName {
+        match self {
+            Self::Existence(query) => *query.address.name(),
+            Self::ChunkRange { address, .. } => *address.name(),
+            Self::ChunkRepair(addresses) => addresses
+                .first()
+                .map(|address| *address.name())
+                .unwrap_or_default(),
+            Self::DataMap(query) => query.name(),
+            Self::ReadBatch(queries) => queries
+                .first()
+                .map(|query| query.dst_address())
+                .unwrap_or_default(),
+            Self::TransferStatus(query) => XorName::from(query.wallet),
+        }
+    }
+
+    /// The query sending the request.
+    pub fn query(&self) -> Result<DataQuery> {
+        Ok(DataQuery::Map(MapRead::GetValue {
+            address: MapAddress::Unseq {
+                name: self.name(),
+                tag: 0,
+            },
+            key: tagged(CLIENT_REQUEST_TAG, self)?.to_vec(),
+        }))
+    }
+
+    /// The request sent as the query, if it is one. Requests which
+    /// cannot be read, e.g. of another version, are an error.
+    pub(crate) fn from_query(query: &DataQuery) -> Option<Result<Self>> {
+        match query {
+            DataQuery::Map(MapRead::GetValue { key, .. }) => untagged(CLIENT_REQUEST_TAG, key),
+            _ => None,
+        }
+    }
+}
+
+/// The cmds from clients to nodes, which sn_messaging has no cmds for yet. Clients only send
+/// cmds of sn_messaging, so they are sent as the key of the entry inserted by a `MapWrite::Edit`
+/// of a Map at the name the cmd is for, paid for as any write is. Errors are answered as those
+/// of the write, under the id of its msg.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientCmd {
+    /// Write of a client data map (file manifest).
+    DataMap(DataMapCmd),
+}
+
+impl ClientCmd {
+    /// The name of the section the cmd is for.
+    pub fn name(&self) -> XorName {
+        match self {
+            Self::DataMap(cmd) => cmd.name(),
+        }
+    }
+
+    /// The write sending the cmd.
+    pub fn cmd(&self) -> Result<DataCmd> {
+        let key = tagged(CLIENT_CMD_TAG, self)?.to_vec();
+        Ok(DataCmd::Map(MapWrite::Edit {
+            address: MapAddress::Unseq {
+                name: self.name(),
+                tag: 0,
+            },
+            changes: MapEntryActions::Unseq(MapUnseqEntryActions::new().ins(key, vec![])),
+        }))
+    }
+
+    /// The cmd sent as the write, if it is one. Cmds which
+    /// cannot be read, e.g. of another version, are an error.
+    pub(crate) fn from_cmd(cmd: &DataCmd) -> Option<Result<Self>> {
+        match cmd {
+            DataCmd::Map(MapWrite::Edit {
+                changes: MapEntryActions::Unseq(actions),
+                ..
+            }) => actions
+                .actions()
+                .keys()
+                .find_map(|key| untagged(CLIENT_CMD_TAG, key)),
+            _ => None,
+        }
+    }
+}
+
+// The tag, the version and the msg.
+fn tagged<T: Serialize>(tag: &[u8], msg: &T) -> Result<Bytes> {
+    let msg = utils::serialise(msg)?;
+    let mut bytes = BytesMut::with_capacity(tag.len() + 1 + msg.len());
+    bytes.put_slice(tag);
+    bytes.put_u8(NODE_MSG_VERSION);
+    bytes.put_slice(&msg);
+    Ok(bytes.freeze())
+}
+
+// The msg behind the tag, if tagged, which is an error if of another version.
+fn untagged<T: DeserializeOwned>(tag: &[u8], bytes: &[u8]) -> Option<Result<T>> {
+    let versioned = bytes.strip_prefix(tag)?;
+    Some(match versioned.split_first() {
+        Some((&NODE_MSG_VERSION, msg)) => utils::deserialise(msg),
+        Some((&version, _)) => Err(Error::UnsupportedNodeMsgVersion(version)),
+        None => Err(Error::UnsupportedNodeMsgVersion(0)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sn_routing::Prefix;
+
+    fn query() -> NodeMsgBody {
+        NodeMsgBody::MetadataRangeQuery(MetadataRangeQuery {
+            prefix: Prefix::default(),
+            after: None,
+        })
+    }
+
+    #[test]
+    fn msgs_are_read_back_by_their_tag() -> Result<()> {
+        let msg = NodeMsg::new(query());
+        let content = msg.serialise()?;
+        match NodeMsg::from_content(&content) {
+            Some(Ok(NodeMsg {
+                id,
+                body: NodeMsgBody::MetadataRangeQuery(_),
+            })) => assert_eq!(id, msg.id),
+            other => panic!("Unexpected msg: {:?}", other),
+        }
+        // content without the tag is not one
+        assert!(NodeMsg::from_content(&content[1..]).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn msgs_of_other_versions_are_unsupported() -> Result<()> {
+        let mut content = NodeMsg::new(query()).serialise()?.to_vec();
+        content[NODE_MSG_TAG.len()] = NODE_MSG_VERSION + 1;
+        assert!(matches!(
+            NodeMsg::from_content(&content),
+            Some(Err(Error::UnsupportedNodeMsgVersion(version))) if version == NODE_MSG_VERSION + 1
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn client_msgs_are_read_back_from_the_response() -> Result<()> {
+        let client = EndUser::AllClients(sn_data_types::PublicKey::from(
+            bls::SecretKey::random().public_key(),
+        ));
+        let msg = ClientMsg::BatchResponse(vec![]);
+        let correlation_id = MessageId::new();
+        match msg.msg(correlation_id, client)?.msg {
+            Message::QueryResponse {
+                response,
+                id,
+                correlation_id: answered,
+                ..
+            } => {
+                assert_eq!(answered, correlation_id);
+                // the id is not that of the standard response to the msg
+                assert_ne!(id, MessageId::in_response_to(&correlation_id));
+                assert_eq!(ClientMsg::from_response(&response).transpose()?, Some(msg));
+            }
+            other => panic!("Unexpected msg: {:?}", other),
+        }
+        // other chunks are not one
+        let chunk = Blob::Public(PublicBlob::new(b"chunk".to_vec()));
+        assert!(ClientMsg::from_response(&QueryResponse::GetBlob(Ok(chunk))).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn client_cmds_are_read_back_from_the_write() -> Result<()> {
+        let cmd = ClientCmd::DataMap(DataMapCmd::Delete(XorName::random()));
+        let write = cmd.cmd()?;
+        assert_eq!(write.dst_address(), cmd.name());
+        assert_eq!(ClientCmd::from_cmd(&write).transpose()?, Some(cmd));
+        // other Map edits are not one
+        let edit = DataCmd::Map(MapWrite::Edit {
+            address: MapAddress::Unseq {
+                name: XorName::random(),
+                tag: 0,
+            },
+            changes: MapEntryActions::Unseq(
+                MapUnseqEntryActions::new().ins(b"key".to_vec(), b"value".to_vec()),
+            ),
+        });
+        assert!(ClientCmd::from_cmd(&edit).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_status_queries_are_read_back() -> Result<()> {
+        use crate::transfers::TransferId;
+        let wallet = sn_data_types::PublicKey::from(bls::SecretKey::random().public_key());
+        let id = TransferId::Debit(crdts::Dot::new(wallet, 3));
+        let request = ClientRequest::TransferStatus(TransferStatusQuery { wallet, id });
+        // sent to the section of the wallet
+        assert_eq!(request.name(), XorName::from(wallet));
+        assert_eq!(
+            ClientRequest::from_query(&request.query()?).transpose()?,
+            Some(request)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn derived_ids_are_the_same_for_the_same_msg() -> Result<()> {
+        assert_eq!(NodeMsg::derived(query())?.id, NodeMsg::derived(query())?.id);
+        assert_ne!(NodeMsg::new(query()).id, NodeMsg::new(query()).id);
+        assert!(NodeMsg::derived(query())?.is_derived());
+        assert!(!NodeMsg::new(query()).is_derived());
+        Ok(())
+    }
+}
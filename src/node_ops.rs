@@ -6,24 +6,46 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::{
+    chunks::{ChunkRange, ImportedChunks, OrphanOffer, OrphanRelease, ReplicationFailed},
+    event_mapping::UnsupportedMessage,
+    metadata::{
+        AckLevel, CapacityReport, CatchUpQuery, ChunkAccessQuery, ChunkAccessReport, ChunkDeletion,
+        DataMapCmd, DataMapQuery, ExistenceQuery, MetadataPage, MetadataRangeQuery, RelayedChunk,
+        RelayedRead, ReplicationBatch, SignedCatchUpPage, SignedMetadataDump, StorageChallenge,
+        StorageProof,
+    },
+    network::{PeerPing, PeerPong},
+    node::{SectionParams, StatePushAck},
+    node_msg::NodeMsg,
+    operator::{OperatorQuery, SignedOperatorCommand},
+    section_funds::{PaymentHandover, SignedFundsExport},
+    transfers::{TransferStatusQuery, WalletSummaryPush},
+};
 use bls::PublicKeySet;
+use bytes::Bytes;
 #[cfg(feature = "simulated-payouts")]
 use sn_data_types::Transfer;
 use sn_data_types::{
     ActorHistory, Blob, BlobAddress, Credit, CreditAgreementProof, NodeAge, PublicKey,
-    ReplicaEvent, RewardAccumulation, RewardProposal, SectionElders, SignatureShare, SignedCredit,
-    SignedTransfer, SignedTransferShare, Token, TransferAgreementProof, TransferValidated,
-    WalletHistory,
+    ReplicaEvent, RewardAccumulation, RewardProposal, SectionElders, Signature, SignatureShare,
+    SignedCredit, SignedTransfer, SignedTransferShare, Token, TransferAgreementProof,
+    TransferValidated, WalletHistory,
 };
 use sn_messaging::{
-    client::{BlobRead, BlobWrite, Message, NodeSystemCmd},
+    client::{
+        BlobRead, BlobWrite, CmdError, DataQuery, Error as ErrorMessage, Message, NodeSystemCmd,
+        QueryResponse, TransferError,
+    },
     Aggregation, DstLocation, EndUser, MessageId, SrcLocation,
 };
 use sn_routing::{NodeElderChange, Prefix};
 use std::{
     collections::{BTreeMap, BTreeSet},
-    fmt::{Debug, Formatter},
+    fmt::{Debug, Display, Formatter},
+    time::Duration,
 };
+use tokio::sync::oneshot;
 use xor_name::XorName;
 
 /// Internal messages are what is passed along
@@ -42,10 +64,37 @@ use xor_name::XorName;
 /// Vec of NodeDuty
 pub type NodeDuties = Vec<NodeDuty>;
 
+/// Work done in the background, by passing its duty to the node at every interval.
+pub(crate) struct PeriodicTask {
+    /// The name of the task, as logged.
+    pub(crate) name: &'static str,
+    /// How often the work is done.
+    pub(crate) interval: Duration,
+    /// The duty doing the work.
+    pub(crate) duty: fn() -> NodeDuty,
+}
+
 /// Common duties run by all nodes.
 #[allow(clippy::large_enum_variant)]
 pub enum NodeDuty {
     AddPayment(CreditAgreementProof),
+    /// Take over the payments handed over by an Elder of our sibling at split.
+    TakeOverPayments {
+        handover: PaymentHandover,
+        elder: XorName,
+    },
+    /// Import a part of the reward wallets and pending payments of a section, as exported
+    /// by one of its Elders, once enough of them have sent the same part.
+    ImportSectionFunds {
+        export: SignedFundsExport,
+        elder: XorName,
+    },
+    /// Import a part of the metadata records of a section, as dumped by
+    /// one of its Elders, once enough of them have sent the same part.
+    ImportSectionMetadata {
+        dump: SignedMetadataDump,
+        elder: XorName,
+    },
     GetNodeWalletKey {
         node_name: XorName,
         msg_id: MessageId,
@@ -56,6 +105,8 @@ pub enum NodeDuty {
         msg_id: MessageId,
         origin: SrcLocation,
     },
+    /// Register a batch of credits to wallets of our section at once.
+    RegisterCreditBatch(Vec<CreditAgreementProof>),
     SetNodeWallet {
         wallet_id: PublicKey,
         node_id: XorName,
@@ -85,6 +136,8 @@ pub enum NodeDuty {
     },
     ReadChunk {
         read: BlobRead,
+        /// The bytes of the chunk to read, if not all of them.
+        range: Option<ChunkRange>,
         msg_id: MessageId,
         origin: EndUser,
     },
@@ -93,6 +146,15 @@ pub enum NodeDuty {
         msg_id: MessageId,
         origin: EndUser,
     },
+    /// Delete a chunk of a client, as told by the Elder
+    /// which checked the client owns it.
+    DeleteChunk {
+        address: BlobAddress,
+        msg_id: MessageId,
+        origin: EndUser,
+        /// The Elder telling us to.
+        elder: XorName,
+    },
     /// Get section elders.
     GetSectionElders {
         msg_id: MessageId,
@@ -107,6 +169,12 @@ pub enum NodeDuty {
         msg_id: MessageId,
         origin: SrcLocation,
     },
+    /// Get the status of a specific credit or debit of a wallet, for the querying client.
+    GetTransferById {
+        query: TransferStatusQuery,
+        msg_id: MessageId,
+        origin: EndUser,
+    },
     /// Get Balance at a specific key
     GetBalance {
         at: PublicKey,
@@ -125,6 +193,12 @@ pub enum NodeDuty {
     ReceiveRewardProposal(RewardProposal),
     /// Accumulation of payout of rewards.
     ReceiveRewardAccumulation(RewardAccumulation),
+    /// Process a query by another Elder of ours for the progress of our
+    /// reward process, or by a node of ours for that of its credits.
+    ProcessRewardStatusQuery {
+        id: MessageId,
+        origin: XorName,
+    },
     Genesis,
     EldersChanged {
         /// Our section prefix.
@@ -152,6 +226,32 @@ pub enum NodeDuty {
         node_rewards: BTreeMap<XorName, (NodeAge, PublicKey)>,
         /// The wallets of users on the network.
         user_wallets: BTreeMap<PublicKey, ActorHistory>,
+        /// Id of the msg pushing the state.
+        msg_id: MessageId,
+        /// The Elder pushing the state to us, or its section.
+        origin: SrcLocation,
+    },
+    /// Refuse chunks beyond the max chunk size of our section, as pushed by an Elder.
+    SetMaxChunkSize {
+        max_chunk_size: u64,
+        elder: XorName,
+    },
+    /// Go by the parameters of our section, as pushed by an Elder.
+    SetSectionParams {
+        params: SectionParams,
+        elder: XorName,
+    },
+    /// Conclude the push of our state to the new Elder acknowledging it.
+    ReceiveStatePushAck {
+        ack: StatePushAck,
+        elder: XorName,
+    },
+    /// Push our state again to the new Elders not acknowledging it in time.
+    RetryStatePushes,
+    /// Import the summaries of the wallet histories compacted by an Elder pushing its state to us.
+    ReceiveWalletSummaries {
+        push: WalletSummaryPush,
+        elder: XorName,
     },
     /// As members are lost for various reasons
     /// there are certain things nodes need
@@ -160,6 +260,46 @@ pub enum NodeDuty {
         name: XorName,
         age: u8,
     },
+    /// A member is being decommissioned, so its chunks are to be copied
+    /// to other holders while it still serves them, and its reward wallet
+    /// deregistered, before it leaves.
+    ProcessDecommission {
+        node: XorName,
+    },
+    /// A holder is offloading the chunks by its storage policy, so they are to be
+    /// copied to other holders while it still serves them, and it released of them.
+    ProcessChunkOffload {
+        holder: XorName,
+        addresses: Vec<BlobAddress>,
+    },
+    /// Pass on a msg we could not parse, e.g. of a
+    /// variant only newer nodes know of, as we only relay it.
+    ForwardUnknownMsg {
+        bytes: Bytes,
+        dst: DstLocation,
+    },
+    /// Answer a msg for us which we could not parse,
+    /// e.g. of a variant only newer nodes know of.
+    RejectUnsupportedMsg {
+        msg: UnsupportedMessage,
+        src: SrcLocation,
+    },
+    /// Check whether our storage is reaching max capacity.
+    CheckStorage,
+    /// Verify the stored chunks due for scrubbing, as scheduled.
+    ScrubChunks,
+    /// Migrate the next batch of chunks, while relocating them.
+    MigrateChunks,
+    /// Ask for the chunks queued for replication to us, as slots are free.
+    ReplicateQueuedChunks,
+    /// Import the next batch of chunks, while importing them.
+    ImportChunks,
+    /// Collect the chunks held out of our prefix, to offer them to the sections owning them.
+    CollectOrphanChunks {
+        our_prefix: Prefix,
+    },
+    /// Offer the next batch of orphan chunks, while collecting them.
+    OfferOrphanChunks,
     /// Storage reaching max capacity.
     ReachingMaxCapacity,
     /// Increment count of full nodes in the network
@@ -167,8 +307,43 @@ pub enum NodeDuty {
         /// Node ID of node that reached max capacity.
         node_id: PublicKey,
     },
+    /// Verify the invariants of the wallets held by our replicas.
+    CheckTransferInvariants,
+    /// Compact the histories of the node reward wallets held by our replicas.
+    CompactWalletHistories,
+    /// Run a signed command of the node operator.
+    RunOperatorCommand(SignedOperatorCommand),
+    /// Answer a query of the node operator, with the json of the answer.
+    RunOperatorQuery {
+        query: OperatorQuery,
+        reply: oneshot::Sender<String>,
+    },
     /// Sets joining allowed to true or false.
     SetNodeJoinsAllowed(bool),
+    /// A peer reported their time, in secs since the unix epoch.
+    ReceivePeerTime {
+        peer: XorName,
+        secs: u64,
+    },
+    /// Judge our clock by the times reported by peers.
+    CheckClock,
+    /// Ping a sample of the peers of our section, for their latency.
+    ProbePeers,
+    /// Answer the ping of a peer of our section.
+    AnswerPing {
+        ping: PeerPing,
+        peer: XorName,
+    },
+    /// Record the answer of a peer to our ping, and the time it reported.
+    RecordPong {
+        pong: PeerPong,
+        peer: XorName,
+    },
+    /// Hedge the queries forwarded to other sections which
+    /// are outstanding beyond the partial timeout.
+    HedgeQueries,
+    /// Forget the queries forwarded to other sections which have timed out.
+    PurgePendingQueries,
     /// Send a message to the specified dst.
     Send(OutgoingMsg),
     /// Send the same request to each individual node.
@@ -176,9 +351,32 @@ pub enum NodeDuty {
         targets: BTreeSet<XorName>,
         msg: Message,
     },
+    /// Send a msg between nodes to the specified dst, as a single node.
+    SendNodeMsg {
+        msg: NodeMsg,
+        dst: DstLocation,
+    },
+    /// Send the same msg between nodes to each individual node.
+    SendNodeMsgToNodes {
+        targets: BTreeSet<XorName>,
+        msg: NodeMsg,
+    },
     /// Process read of data
     ProcessRead {
-        query: sn_messaging::client::DataQuery,
+        query: DataQuery,
+        id: MessageId,
+        origin: EndUser,
+    },
+    /// Process read of only the bytes of the range of a chunk.
+    ProcessRangedRead {
+        address: BlobAddress,
+        range: ChunkRange,
+        id: MessageId,
+        origin: EndUser,
+    },
+    /// Process independent reads of data at once.
+    ProcessReadBatch {
+        queries: Vec<DataQuery>,
         id: MessageId,
         origin: EndUser,
     },
@@ -188,11 +386,104 @@ pub enum NodeDuty {
         id: MessageId,
         origin: EndUser,
     },
+    /// Record that an Elder has applied a client write.
+    RecordWriteAck {
+        id: MessageId,
+        origin: EndUser,
+        elder: XorName,
+    },
+    /// Process write of a client data map.
+    ProcessDataMapWrite {
+        cmd: DataMapCmd,
+        id: MessageId,
+        origin: EndUser,
+    },
+    /// Process read of a client data map.
+    ProcessDataMapRead {
+        query: DataMapQuery,
+        id: MessageId,
+        origin: EndUser,
+    },
+    /// Process a request by a client for the Elders holding
+    /// a chunk to attest it exists at the time proposed.
+    ProcessExistenceQuery {
+        query: ExistenceQuery,
+        id: MessageId,
+        origin: EndUser,
+    },
+    /// Process a request by a data owner to re-verify
+    /// and repair their chunks, e.g. after a network incident.
+    ProcessChunkRepair {
+        addresses: Vec<BlobAddress>,
+        id: MessageId,
+        origin: EndUser,
+    },
+    /// Process a deletion of chunks by section policy.
+    ProcessChunkDeletion(ChunkDeletion),
+    /// Record that a holder has deleted a chunk by section policy.
+    RecordChunkDeleted {
+        address: BlobAddress,
+        holder: XorName,
+        /// Signature of the holder over the chunk address.
+        proof: Signature,
+    },
+    /// Have a holder found without a good copy of a chunk
+    /// when reading it copy the chunk from the other holders.
+    RepairChunkHolder {
+        address: BlobAddress,
+        holder: XorName,
+    },
+    /// Process a query by another Elder for a page of
+    /// the metadata records within a range of names.
+    ProcessMetadataExport {
+        query: MetadataRangeQuery,
+        id: MessageId,
+        origin: XorName,
+    },
+    /// Answer the query of a new Elder catching up on
+    /// the metadata records of our section.
+    AnswerMetadataCatchUp {
+        query: CatchUpQuery,
+        elder: XorName,
+    },
+    /// Take over a page of the metadata records of our section,
+    /// as a new Elder catching up on them.
+    CatchUpOnMetadata {
+        page: SignedCatchUpPage,
+        elder: XorName,
+    },
+    /// Take over a page of the metadata records of our section,
+    /// as a new Elder importing them from an Elder of our sibling after split.
+    ImportMetadataRange {
+        /// The id of the msg of the query answered.
+        query: MessageId,
+        page: MetadataPage,
+        elder: XorName,
+    },
+    /// Ask the next Elder to catch up on, or import, metadata from,
+    /// when the one asked has not answered in time.
+    CheckMetadataCatchUp,
     /// Process Payment for a DataCmd
     ProcessDataPayment {
         msg: Message,
         origin: EndUser,
     },
+    /// Record the ack level selected by the client for a write,
+    /// as told by an Elder of the section taking the payment for it.
+    SetWriteAckLevel {
+        /// The id of the write, as forwarded to the Elders.
+        id: MessageId,
+        origin: EndUser,
+        level: AckLevel,
+        elder: XorName,
+    },
+    /// Record the redundancy paid for
+    /// a chunk, ahead of it being stored.
+    SetChunkRedundancy {
+        address: BlobAddress,
+        /// Multiple of the standard number of copies.
+        multiplier: u64,
+    },
     /// Process replication of a chunk on `MemberLeft`
     /// This is run at the node which is the new holder
     /// of a chunk
@@ -201,6 +492,77 @@ pub enum NodeDuty {
         current_holders: BTreeSet<XorName>,
         id: MessageId,
     },
+    /// Challenge the holders of random chunks to prove they hold them,
+    /// repairing those failing the challenges expired by then.
+    IssueStorageChallenge,
+    /// Answer the challenge of an Elder with the hash of the range of the chunk.
+    AnswerStorageChallenge {
+        challenge: StorageChallenge,
+        id: MessageId,
+        elder: XorName,
+    },
+    /// Register the holder as holding the chunks it imported.
+    RegisterImportedChunks {
+        imported: ImportedChunks,
+        holder: XorName,
+    },
+    /// Have the chunk offered by a holder out of our section copied to our Adults.
+    AdoptOrphanChunk {
+        offer: OrphanOffer,
+        holder: XorName,
+    },
+    /// Delete the orphan chunk released by an Elder of the section owning it.
+    ReleaseOrphanChunk {
+        release: OrphanRelease,
+        elder: XorName,
+    },
+    /// Record the answer of a holder to a storage challenge we issued.
+    VerifyStorageProof {
+        proof: StorageProof,
+        holder: XorName,
+    },
+    /// Answer the read of a chunk relayed by an Elder with the chunk.
+    ReadChunkForElder {
+        read: RelayedRead,
+        elder: XorName,
+    },
+    /// Answer the client with the chunk read by a holder for a read relayed by us,
+    /// if it is the first chunk verified to be the one read.
+    RelayChunk {
+        answer: RelayedChunk,
+        holder: XorName,
+    },
+    /// Query the next holders of the relayed reads not answered in time.
+    RetryRelayedReads,
+    /// Copy the chunks of the Adults found unresponsive to our queries to other holders,
+    /// and report them.
+    CheckAdultLiveness,
+    /// Report the capacity of our storage to our Elders.
+    ReportCapacity,
+    /// Record the capacity of the storage reported by one of our Adults.
+    RecordCapacity {
+        report: CapacityReport,
+        adult: XorName,
+    },
+    /// Query our Adults for the reads of their most read chunks.
+    QueryChunkAccess,
+    /// Report the reads of our chunks to the Elder querying them.
+    ReportChunkAccess {
+        query: ChunkAccessQuery,
+        elder: XorName,
+    },
+    /// Record the reads of the chunks reported by the holder.
+    RecordChunkAccess {
+        report: ChunkAccessReport,
+        holder: XorName,
+    },
+    /// Process replication of the chunks batched for us
+    /// as their new holder, each as by `ReplicateChunk`.
+    ReplicateChunks {
+        batch: ReplicationBatch,
+        /// Elder sending the batch.
+        elder: XorName,
+    },
     /// Process a GetChunk operation
     /// and send it back to to the requesting node
     /// for replication
@@ -209,11 +571,25 @@ pub enum NodeDuty {
         new_holder: XorName,
         id: MessageId,
     },
+    /// Delete a chunk as told by the section, without the
+    /// owner check of a delete by a client.
+    DeleteChunkBySection {
+        address: BlobAddress,
+        msg_id: MessageId,
+        section: SrcLocation,
+    },
     /// Store a chunk that is a result of data replication
     /// on `MemberLeft`
     StoreChunkForReplication {
         data: Blob,
         correlation_id: MessageId,
+        /// The holder which sent the chunk.
+        holder: XorName,
+    },
+    /// Verify our copy of the chunk found corrupt by the new holder we sent it to.
+    VerifyReplicatedChunk {
+        failed: ReplicationFailed,
+        new_holder: XorName,
     },
     NoOp,
 }
@@ -231,45 +607,519 @@ impl From<NodeDuty> for NodeDuties {
 impl Debug for NodeDuty {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Genesis { .. } => write!(f, "Genesis"),
-            Self::AddPayment { .. } => write!(f, "AddPayment"),
-            Self::GetNodeWalletKey { .. } => write!(f, "GetNodeWalletKey"),
-            Self::PropagateTransfer { .. } => write!(f, "PropagateTransfer"),
-            Self::SetNodeWallet { .. } => write!(f, "SetNodeWallet"),
-            Self::GetTransferReplicaEvents { .. } => write!(f, "GetTransferReplicaEvents"),
-            Self::ValidateClientTransfer { .. } => write!(f, "ValidateClientTransfer"),
-            Self::RegisterTransfer { .. } => write!(f, "RegisterTransfer"),
-            Self::GetBalance { .. } => write!(f, "GetBalance"),
-            Self::GetStoreCost { .. } => write!(f, "GetStoreCost"),
-            Self::SimulatePayout { .. } => write!(f, "SimulatePayout"),
-            Self::GetTransfersHistory { .. } => write!(f, "GetTransfersHistory"),
-            Self::ReadChunk { .. } => write!(f, "ReadChunk"),
-            Self::WriteChunk { .. } => write!(f, "WriteChunk"),
-            Self::ReceiveRewardProposal { .. } => write!(f, "ReceiveRewardProposal"),
-            Self::ReceiveRewardAccumulation { .. } => write!(f, "ReceiveRewardAccumulation"),
-            // ------
-            Self::LevelDown => write!(f, "LevelDown"),
-            Self::SynchState { .. } => write!(f, "SynchState"),
-            Self::EldersChanged { .. } => write!(f, "EldersChanged"),
-            Self::SectionSplit { .. } => write!(f, "SectionSplit"),
-            Self::GetSectionElders { .. } => write!(f, "GetSectionElders"),
-
             Self::NoOp => write!(f, "No op."),
-            Self::ReachingMaxCapacity => write!(f, "ReachingMaxCapacity"),
-            Self::ProcessLostMember { .. } => write!(f, "ProcessLostMember"),
-            //Self::ProcessRelocatedMember { .. } => write!(f, "ProcessRelocatedMember"),
-            Self::IncrementFullNodeCount { .. } => write!(f, "IncrementFullNodeCount"),
-            Self::SetNodeJoinsAllowed(_) => write!(f, "SetNodeJoinsAllowed"),
             Self::Send(msg) => write!(f, "Send [ msg: {:?} ]", msg),
             Self::SendToNodes { targets, msg } => {
                 write!(f, "SendToNodes [ targets: {:?}, msg: {:?} ]", targets, msg)
             }
-            Self::ProcessRead { .. } => write!(f, "ProcessRead"),
-            Self::ProcessWrite { .. } => write!(f, "ProcessWrite"),
-            Self::ProcessDataPayment { .. } => write!(f, "ProcessDataPayment"),
-            Self::ReplicateChunk { .. } => write!(f, "ReplicateChunk"),
-            Self::GetChunkForReplication { .. } => write!(f, "GetChunkForReplication"),
-            Self::StoreChunkForReplication { .. } => write!(f, "StoreChunkForReplication"),
+            Self::SendNodeMsg { msg, dst } => {
+                write!(f, "SendNodeMsg [ dst: {:?}, msg: {:?} ]", dst, msg)
+            }
+            Self::SendNodeMsgToNodes { targets, msg } => {
+                write!(
+                    f,
+                    "SendNodeMsgToNodes [ targets: {:?}, msg: {:?} ]",
+                    targets, msg
+                )
+            }
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+// --------------- Error context ---------------
+
+/// The subsystems handling duties.
+pub(crate) const NODE: &str = "node";
+pub(crate) const TRANSFERS: &str = "transfers";
+pub(crate) const CHUNKS: &str = "chunks";
+pub(crate) const METADATA: &str = "metadata";
+pub(crate) const SECTION_FUNDS: &str = "section funds";
+
+/// Context of a duty, attached to any
+/// error returned when handling it, so that
+/// the error can be reported back to its origin.
+#[derive(Clone, Debug)]
+pub struct DutyContext {
+    /// The kind of duty.
+    pub duty: &'static str,
+    /// The subsystem handling the duty.
+    pub subsystem: &'static str,
+    /// The id of the msg which led to the duty.
+    pub msg_id: Option<MessageId>,
+    /// The sender of the msg which led to the duty.
+    pub origin: Option<SrcLocation>,
+    /// How an error is reported back to the origin.
+    pub response: ErrorResponse,
+}
+
+/// The msg by which an error is reported back to the origin.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum ErrorResponse {
+    /// Errors are not reported back.
+    None,
+    /// As a data cmd error.
+    DataCmd,
+    /// As a transfer validation error.
+    TransferValidation,
+    /// As the error response to the data query.
+    DataQuery(DataQuery),
+    /// As the error response to the chunk read.
+    BlobRead(BlobRead),
+    /// As the error response to the data map query.
+    DataMapQuery(DataMapQuery),
+    /// As the error response to the query sending a client request.
+    ClientRequest,
+    /// As the error response to a balance query.
+    Balance,
+    /// As the error response to a history query.
+    History,
+    /// As the error response to a store cost query.
+    StoreCost,
+}
+
+impl ErrorResponse {
+    /// The msg reporting the error, if any.
+    pub fn to_msg(&self, error: ErrorMessage, msg_id: MessageId) -> Option<Message> {
+        use ErrorResponse::*;
+        let id = MessageId::in_response_to(&msg_id);
+        let response = match self {
+            None => return Option::None,
+            DataCmd => {
+                return Some(Message::CmdError {
+                    error: CmdError::Data(error),
+                    id,
+                    correlation_id: msg_id,
+                    target_section_pk: Option::None,
+                })
+            }
+            TransferValidation => {
+                return Some(Message::CmdError {
+                    error: CmdError::Transfer(TransferError::TransferValidation(error)),
+                    id,
+                    correlation_id: msg_id,
+                    target_section_pk: Option::None,
+                })
+            }
+            DataQuery(query) => query.error(error),
+            BlobRead(read) => read.error(error),
+            DataMapQuery(query) => query.error(error),
+            ClientRequest => QueryResponse::GetMapValue(Err(error)),
+            Balance => QueryResponse::GetBalance(Err(error)),
+            History => QueryResponse::GetHistory(Err(error)),
+            StoreCost => QueryResponse::GetStoreCost(Err(error)),
+        };
+        Some(Message::QueryResponse {
+            response,
+            id,
+            correlation_id: msg_id,
+            target_section_pk: Option::None,
+        })
+    }
+}
+
+impl DutyContext {
+    fn new(duty: &'static str, subsystem: &'static str) -> Self {
+        Self {
+            duty,
+            subsystem,
+            msg_id: None,
+            origin: None,
+            response: ErrorResponse::None,
+        }
+    }
+
+    fn msg(mut self, msg_id: MessageId, origin: SrcLocation) -> Self {
+        self.msg_id = Some(msg_id);
+        self.origin = Some(origin);
+        self
+    }
+
+    fn respond_with(mut self, response: ErrorResponse) -> Self {
+        self.response = response;
+        self
+    }
+
+    /// The msg reporting the error back to the origin,
+    /// provided the origin is a client.
+    pub fn error_response(&self, error: ErrorMessage) -> Option<OutgoingMsg> {
+        let (msg_id, origin) = match (self.msg_id, self.origin) {
+            (Some(msg_id), Some(origin)) if origin.is_user() => (msg_id, origin),
+            _ => return None,
+        };
+        let msg = self.response.to_msg(error, msg_id)?;
+        Some(OutgoingMsg {
+            msg,
+            section_source: false, // strictly this is not correct, but we don't expect responses to an error..
+            dst: origin.to_dst(),
+            aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
+        })
+    }
+}
+
+impl Display for DutyContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.duty, self.subsystem)?;
+        if let Some(msg_id) = self.msg_id {
+            write!(f, ", msg_id: {:?}", msg_id)?;
+        }
+        if let Some(origin) = self.origin {
+            write!(f, ", origin: {:?}", origin)?;
+        }
+        Ok(())
+    }
+}
+
+impl NodeDuty {
+    /// The context of this duty, for error reporting.
+    pub fn context(&self) -> DutyContext {
+        use ErrorResponse::*;
+        let name = self.name();
+        match self {
+            Self::GetNodeWalletKey { msg_id, origin, .. }
+            | Self::SetNodeWallet { msg_id, origin, .. } => {
+                DutyContext::new(name, SECTION_FUNDS).msg(*msg_id, *origin)
+            }
+            Self::AddPayment(_)
+            | Self::ReceiveRewardProposal(_)
+            | Self::ReceiveRewardAccumulation(_) => DutyContext::new(name, SECTION_FUNDS),
+            Self::ProcessRewardStatusQuery { id, origin } => {
+                DutyContext::new(name, SECTION_FUNDS).msg(*id, SrcLocation::Node(*origin))
+            }
+            Self::SetMaxChunkSize { elder, .. }
+            | Self::SetSectionParams { elder, .. }
+            | Self::ReceiveStatePushAck { elder, .. } => {
+                let mut context = DutyContext::new(name, NODE);
+                context.origin = Some(SrcLocation::Node(*elder));
+                context
+            }
+            Self::TakeOverPayments { elder, .. } | Self::ImportSectionFunds { elder, .. } => {
+                let mut context = DutyContext::new(name, SECTION_FUNDS);
+                context.origin = Some(SrcLocation::Node(*elder));
+                context
+            }
+            Self::ImportSectionMetadata { elder, .. } => {
+                let mut context = DutyContext::new(name, METADATA);
+                context.origin = Some(SrcLocation::Node(*elder));
+                context
+            }
+            Self::ReceiveWalletSummaries { elder, .. } => {
+                let mut context = DutyContext::new(name, TRANSFERS);
+                context.origin = Some(SrcLocation::Node(*elder));
+                context
+            }
+            Self::RegisterCreditBatch(_) => DutyContext::new(name, TRANSFERS),
+            Self::PropagateTransfer { msg_id, origin, .. }
+            | Self::GetTransferReplicaEvents { msg_id, origin }
+            | Self::SimulatePayout { msg_id, origin, .. } => {
+                DutyContext::new(name, TRANSFERS).msg(*msg_id, *origin)
+            }
+            Self::ValidateClientTransfer { msg_id, origin, .. } => {
+                DutyContext::new(name, TRANSFERS)
+                    .msg(*msg_id, *origin)
+                    .respond_with(TransferValidation)
+            }
+            Self::GetTransfersHistory { msg_id, origin, .. } => DutyContext::new(name, TRANSFERS)
+                .msg(*msg_id, *origin)
+                .respond_with(History),
+            Self::GetTransferById { msg_id, origin, .. } => DutyContext::new(name, TRANSFERS)
+                .msg(*msg_id, SrcLocation::EndUser(*origin))
+                .respond_with(ClientRequest),
+            Self::GetBalance { msg_id, origin, .. } => DutyContext::new(name, TRANSFERS)
+                .msg(*msg_id, *origin)
+                .respond_with(Balance),
+            Self::GetStoreCost { msg_id, origin, .. } => DutyContext::new(name, TRANSFERS)
+                .msg(*msg_id, *origin)
+                .respond_with(StoreCost),
+            Self::RegisterTransfer { msg_id, .. } => {
+                let mut context = DutyContext::new(name, TRANSFERS);
+                context.msg_id = Some(*msg_id);
+                context
+            }
+            Self::IncrementFullNodeCount { .. }
+            | Self::CheckTransferInvariants
+            | Self::CompactWalletHistories => DutyContext::new(name, TRANSFERS),
+            Self::ReadChunk {
+                read,
+                msg_id,
+                origin,
+                ..
+            } => DutyContext::new(name, CHUNKS)
+                .msg(*msg_id, SrcLocation::EndUser(*origin))
+                .respond_with(BlobRead(read.clone())),
+            Self::WriteChunk { msg_id, origin, .. } | Self::DeleteChunk { msg_id, origin, .. } => {
+                DutyContext::new(name, CHUNKS)
+                    .msg(*msg_id, SrcLocation::EndUser(*origin))
+                    .respond_with(DataCmd)
+            }
+            Self::ReplicateChunk { id, .. } | Self::GetChunkForReplication { id, .. } => {
+                let mut context = DutyContext::new(name, CHUNKS);
+                context.msg_id = Some(*id);
+                context
+            }
+            Self::AnswerStorageChallenge { id, elder, .. } => {
+                DutyContext::new(name, CHUNKS).msg(*id, SrcLocation::Node(*elder))
+            }
+            Self::ReplicateChunks { elder, .. }
+            | Self::ReleaseOrphanChunk { elder, .. }
+            | Self::ReportChunkAccess { elder, .. }
+            | Self::ReadChunkForElder { elder, .. } => {
+                let mut context = DutyContext::new(name, CHUNKS);
+                context.origin = Some(SrcLocation::Node(*elder));
+                context
+            }
+            Self::DeleteChunkBySection {
+                msg_id, section, ..
+            } => DutyContext::new(name, CHUNKS).msg(*msg_id, *section),
+            Self::StoreChunkForReplication {
+                correlation_id,
+                holder,
+                ..
+            } => DutyContext::new(name, CHUNKS).msg(*correlation_id, SrcLocation::Node(*holder)),
+            Self::VerifyReplicatedChunk { failed, new_holder } => {
+                DutyContext::new(name, CHUNKS).msg(failed.id, SrcLocation::Node(*new_holder))
+            }
+            Self::CheckStorage
+            | Self::ScrubChunks
+            | Self::MigrateChunks
+            | Self::ReplicateQueuedChunks
+            | Self::ImportChunks
+            | Self::CollectOrphanChunks { .. }
+            | Self::OfferOrphanChunks
+            | Self::ReachingMaxCapacity => DutyContext::new(name, CHUNKS),
+            Self::ProcessRead { query, id, origin } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(DataQuery(query.clone())),
+            Self::ProcessRangedRead {
+                address,
+                id,
+                origin,
+                ..
+            } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(BlobRead(sn_messaging::client::BlobRead::Get(*address))),
+            Self::ProcessReadBatch { id, origin, .. } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(ClientRequest),
+            Self::ProcessWrite { id, origin, .. } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(DataCmd),
+            Self::RecordWriteAck { id, elder, .. } => {
+                DutyContext::new(name, METADATA).msg(*id, SrcLocation::Node(*elder))
+            }
+            Self::ProcessDataMapWrite { id, origin, .. } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(DataCmd),
+            Self::ProcessDataMapRead { query, id, origin } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(DataMapQuery(query.clone())),
+            Self::ProcessExistenceQuery { id, origin, .. } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(ClientRequest),
+            Self::ProcessChunkRepair { id, origin, .. } => DutyContext::new(name, METADATA)
+                .msg(*id, SrcLocation::EndUser(*origin))
+                .respond_with(ClientRequest),
+            Self::ProcessChunkDeletion(_) => DutyContext::new(name, METADATA),
+            Self::RecordChunkDeleted { holder, .. } => {
+                let mut context = DutyContext::new(name, METADATA);
+                context.origin = Some(SrcLocation::Node(*holder));
+                context
+            }
+            Self::RepairChunkHolder { holder, .. }
+            | Self::VerifyStorageProof { holder, .. }
+            | Self::RelayChunk { holder, .. }
+            | Self::RecordChunkAccess { holder, .. }
+            | Self::RegisterImportedChunks { holder, .. }
+            | Self::AdoptOrphanChunk { holder, .. } => {
+                let mut context = DutyContext::new(name, METADATA);
+                context.origin = Some(SrcLocation::Node(*holder));
+                context
+            }
+            Self::ProcessMetadataExport { id, origin, .. } => {
+                DutyContext::new(name, METADATA).msg(*id, SrcLocation::Node(*origin))
+            }
+            Self::AnswerMetadataCatchUp { elder, .. }
+            | Self::CatchUpOnMetadata { elder, .. }
+            | Self::ImportMetadataRange { elder, .. } => {
+                let mut context = DutyContext::new(name, METADATA);
+                context.origin = Some(SrcLocation::Node(*elder));
+                context
+            }
+            Self::ProcessDataPayment { msg, origin } => DutyContext::new(name, TRANSFERS)
+                .msg(msg.id(), SrcLocation::EndUser(*origin))
+                .respond_with(DataCmd),
+            Self::SetWriteAckLevel { id, elder, .. } => {
+                DutyContext::new(name, METADATA).msg(*id, SrcLocation::Node(*elder))
+            }
+            Self::SetChunkRedundancy { .. }
+            | Self::ProcessLostMember { .. }
+            | Self::ProcessDecommission { .. }
+            | Self::ProcessChunkOffload { .. }
+            | Self::IssueStorageChallenge
+            | Self::RetryRelayedReads
+            | Self::CheckAdultLiveness
+            | Self::CheckMetadataCatchUp
+            | Self::QueryChunkAccess => DutyContext::new(name, METADATA),
+            Self::ReportCapacity => DutyContext::new(name, CHUNKS),
+            Self::RecordCapacity { adult, .. } => {
+                let mut context = DutyContext::new(name, METADATA);
+                context.origin = Some(SrcLocation::Node(*adult));
+                context
+            }
+            Self::GetSectionElders { msg_id, origin } => {
+                DutyContext::new(name, NODE).msg(*msg_id, *origin)
+            }
+            Self::AnswerPing { peer, .. } | Self::RecordPong { peer, .. } => {
+                let mut context = DutyContext::new(name, NODE);
+                context.origin = Some(SrcLocation::Node(*peer));
+                context
+            }
+            Self::Send(msg) => {
+                let mut context = DutyContext::new(name, NODE);
+                context.msg_id = Some(msg.id());
+                context
+            }
+            Self::SendToNodes { msg, .. } => {
+                let mut context = DutyContext::new(name, NODE);
+                context.msg_id = Some(msg.id());
+                context
+            }
+            Self::SendNodeMsg { msg, .. } | Self::SendNodeMsgToNodes { msg, .. } => {
+                let mut context = DutyContext::new(name, NODE);
+                context.msg_id = Some(msg.id);
+                context
+            }
+            Self::RejectUnsupportedMsg { msg, src } => {
+                DutyContext::new(name, NODE).msg(msg.msg_id, *src)
+            }
+            Self::Genesis
+            | Self::ForwardUnknownMsg { .. }
+            | Self::RunOperatorCommand(_)
+            | Self::RunOperatorQuery { .. }
+            | Self::EldersChanged { .. }
+            | Self::SectionSplit { .. }
+            | Self::LevelDown
+            | Self::SynchState { .. }
+            | Self::RetryStatePushes
+            | Self::SetNodeJoinsAllowed(_)
+            | Self::ReceivePeerTime { .. }
+            | Self::CheckClock
+            | Self::ProbePeers
+            | Self::HedgeQueries
+            | Self::PurgePendingQueries
+            | Self::NoOp => DutyContext::new(name, NODE),
+        }
+    }
+
+    /// The name of the duty variant.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Genesis { .. } => "Genesis",
+            Self::AddPayment { .. } => "AddPayment",
+            Self::TakeOverPayments { .. } => "TakeOverPayments",
+            Self::ImportSectionFunds { .. } => "ImportSectionFunds",
+            Self::ImportSectionMetadata { .. } => "ImportSectionMetadata",
+            Self::GetNodeWalletKey { .. } => "GetNodeWalletKey",
+            Self::PropagateTransfer { .. } => "PropagateTransfer",
+            Self::RegisterCreditBatch(_) => "RegisterCreditBatch",
+            Self::SetNodeWallet { .. } => "SetNodeWallet",
+            Self::GetTransferReplicaEvents { .. } => "GetTransferReplicaEvents",
+            Self::ValidateClientTransfer { .. } => "ValidateClientTransfer",
+            Self::RegisterTransfer { .. } => "RegisterTransfer",
+            Self::GetBalance { .. } => "GetBalance",
+            Self::GetStoreCost { .. } => "GetStoreCost",
+            Self::SimulatePayout { .. } => "SimulatePayout",
+            Self::GetTransfersHistory { .. } => "GetTransfersHistory",
+            Self::GetTransferById { .. } => "GetTransferById",
+            Self::ReadChunk { .. } => "ReadChunk",
+            Self::WriteChunk { .. } => "WriteChunk",
+            Self::DeleteChunk { .. } => "DeleteChunk",
+            Self::ReceiveRewardProposal { .. } => "ReceiveRewardProposal",
+            Self::ReceiveRewardAccumulation { .. } => "ReceiveRewardAccumulation",
+            Self::ProcessRewardStatusQuery { .. } => "ProcessRewardStatusQuery",
+            Self::LevelDown => "LevelDown",
+            Self::SynchState { .. } => "SynchState",
+            Self::SetMaxChunkSize { .. } => "SetMaxChunkSize",
+            Self::SetSectionParams { .. } => "SetSectionParams",
+            Self::ReceiveStatePushAck { .. } => "ReceiveStatePushAck",
+            Self::RetryStatePushes => "RetryStatePushes",
+            Self::ReceiveWalletSummaries { .. } => "ReceiveWalletSummaries",
+            Self::EldersChanged { .. } => "EldersChanged",
+            Self::SectionSplit { .. } => "SectionSplit",
+            Self::GetSectionElders { .. } => "GetSectionElders",
+            Self::NoOp => "NoOp",
+            Self::CheckStorage => "CheckStorage",
+            Self::ScrubChunks => "ScrubChunks",
+            Self::MigrateChunks => "MigrateChunks",
+            Self::ReplicateQueuedChunks => "ReplicateQueuedChunks",
+            Self::ImportChunks => "ImportChunks",
+            Self::CollectOrphanChunks { .. } => "CollectOrphanChunks",
+            Self::OfferOrphanChunks => "OfferOrphanChunks",
+            Self::ReachingMaxCapacity => "ReachingMaxCapacity",
+            Self::ProcessLostMember { .. } => "ProcessLostMember",
+            Self::ProcessDecommission { .. } => "ProcessDecommission",
+            Self::ProcessChunkOffload { .. } => "ProcessChunkOffload",
+            Self::ForwardUnknownMsg { .. } => "ForwardUnknownMsg",
+            Self::RejectUnsupportedMsg { .. } => "RejectUnsupportedMsg",
+            Self::IncrementFullNodeCount { .. } => "IncrementFullNodeCount",
+            Self::CheckTransferInvariants => "CheckTransferInvariants",
+            Self::CompactWalletHistories => "CompactWalletHistories",
+            Self::SetNodeJoinsAllowed(_) => "SetNodeJoinsAllowed",
+            Self::ReceivePeerTime { .. } => "ReceivePeerTime",
+            Self::CheckClock => "CheckClock",
+            Self::ProbePeers => "ProbePeers",
+            Self::AnswerPing { .. } => "AnswerPing",
+            Self::RecordPong { .. } => "RecordPong",
+            Self::HedgeQueries => "HedgeQueries",
+            Self::PurgePendingQueries => "PurgePendingQueries",
+            Self::RunOperatorCommand(_) => "RunOperatorCommand",
+            Self::RunOperatorQuery { .. } => "RunOperatorQuery",
+            Self::Send(_) => "Send",
+            Self::SendToNodes { .. } => "SendToNodes",
+            Self::SendNodeMsg { .. } => "SendNodeMsg",
+            Self::SendNodeMsgToNodes { .. } => "SendNodeMsgToNodes",
+            Self::ProcessRead { .. } => "ProcessRead",
+            Self::ProcessRangedRead { .. } => "ProcessRangedRead",
+            Self::ProcessReadBatch { .. } => "ProcessReadBatch",
+            Self::ProcessWrite { .. } => "ProcessWrite",
+            Self::ProcessDataMapWrite { .. } => "ProcessDataMapWrite",
+            Self::ProcessDataMapRead { .. } => "ProcessDataMapRead",
+            Self::RecordWriteAck { .. } => "RecordWriteAck",
+            Self::ProcessExistenceQuery { .. } => "ProcessExistenceQuery",
+            Self::ProcessChunkRepair { .. } => "ProcessChunkRepair",
+            Self::ProcessChunkDeletion(_) => "ProcessChunkDeletion",
+            Self::RecordChunkDeleted { .. } => "RecordChunkDeleted",
+            Self::RepairChunkHolder { .. } => "RepairChunkHolder",
+            Self::ProcessMetadataExport { .. } => "ProcessMetadataExport",
+            Self::AnswerMetadataCatchUp { .. } => "AnswerMetadataCatchUp",
+            Self::CatchUpOnMetadata { .. } => "CatchUpOnMetadata",
+            Self::ImportMetadataRange { .. } => "ImportMetadataRange",
+            Self::CheckMetadataCatchUp => "CheckMetadataCatchUp",
+            Self::ProcessDataPayment { .. } => "ProcessDataPayment",
+            Self::SetChunkRedundancy { .. } => "SetChunkRedundancy",
+            Self::SetWriteAckLevel { .. } => "SetWriteAckLevel",
+            Self::ReplicateChunk { .. } => "ReplicateChunk",
+            Self::ReplicateChunks { .. } => "ReplicateChunks",
+            Self::IssueStorageChallenge => "IssueStorageChallenge",
+            Self::AnswerStorageChallenge { .. } => "AnswerStorageChallenge",
+            Self::VerifyStorageProof { .. } => "VerifyStorageProof",
+            Self::ReadChunkForElder { .. } => "ReadChunkForElder",
+            Self::RelayChunk { .. } => "RelayChunk",
+            Self::RetryRelayedReads => "RetryRelayedReads",
+            Self::CheckAdultLiveness => "CheckAdultLiveness",
+            Self::ReportCapacity => "ReportCapacity",
+            Self::RecordCapacity { .. } => "RecordCapacity",
+            Self::QueryChunkAccess => "QueryChunkAccess",
+            Self::ReportChunkAccess { .. } => "ReportChunkAccess",
+            Self::RecordChunkAccess { .. } => "RecordChunkAccess",
+            Self::RegisterImportedChunks { .. } => "RegisterImportedChunks",
+            Self::AdoptOrphanChunk { .. } => "AdoptOrphanChunk",
+            Self::ReleaseOrphanChunk { .. } => "ReleaseOrphanChunk",
+            Self::GetChunkForReplication { .. } => "GetChunkForReplication",
+            Self::DeleteChunkBySection { .. } => "DeleteChunkBySection",
+            Self::StoreChunkForReplication { .. } => "StoreChunkForReplication",
+            Self::VerifyReplicatedChunk { .. } => "VerifyReplicatedChunk",
         }
     }
 }
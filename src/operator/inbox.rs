@@ -0,0 +1,64 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::SignedOperatorCommand;
+use log::{debug, warn};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+/// Directory within the root dir, into which
+/// signed operator commands can be dropped as json files.
+pub(crate) const INBOX_DIR: &str = "operator_inbox";
+
+/// Returns the inbox dir for the given root dir.
+pub(crate) fn inbox_dir(root_dir: &Path) -> PathBuf {
+    root_dir.join(INBOX_DIR)
+}
+
+/// Reads all commands in the inbox, removing them from it.
+/// Files that can't be parsed are removed as well.
+pub(crate) fn take_commands(inbox: &Path) -> Vec<SignedOperatorCommand> {
+    let entries = match fs::read_dir(inbox) {
+        Ok(entries) => entries,
+        // no inbox, no commands
+        Err(_) => return vec![],
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    // commands are run in the order of their file names
+    paths.sort();
+
+    let mut commands = vec![];
+    for path in paths {
+        match read_command(&path) {
+            Ok(cmd) => commands.push(cmd),
+            Err(error) => warn!("Invalid operator command at {}: {}", path.display(), error),
+        }
+        if let Err(error) = fs::remove_file(&path) {
+            warn!(
+                "Could not remove operator command at {}: {}",
+                path.display(),
+                error
+            );
+        } else {
+            debug!("Took operator command from {}", path.display());
+        }
+    }
+    commands
+}
+
+fn read_command(path: &Path) -> serde_json::Result<SignedOperatorCommand> {
+    let file = File::open(path).map_err(serde_json::Error::io)?;
+    serde_json::from_reader(BufReader::new(file))
+}
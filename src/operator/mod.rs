@@ -0,0 +1,351 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Commands by which the operator of a node controls it while it is running.
+//!
+//! Commands mutating node state must be signed by the operator key configured
+//! at startup. The signature is detached from the transport, so commands can
+//! be signed offline by fleet tooling and delivered later.
+
+pub(crate) mod inbox;
+pub(crate) mod rpc;
+
+use crate::{
+    metadata::ChunkDeletion, network::NetworkClock, node::DutyLogFilter, utils, Error, Result,
+};
+use bls::{PublicKey as BlsPublicKey, SecretKey, Signature};
+use pickledb::PickleDb;
+use serde::{Deserialize, Serialize};
+use sn_data_types::PublicKey;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use xor_name::XorName;
+
+const USED_NONCES_DB_NAME: &str = "operator_nonces.db";
+
+/// A command mutating the state of a running node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperatorCommand {
+    /// Receive rewards to a new wallet.
+    SetRewardKey(PublicKey),
+    /// Enter or leave maintenance mode, in which data writes are refused.
+    SetMaintenanceMode(bool),
+    /// Stop the node.
+    Shutdown,
+    /// Delete the chunks matching a section policy. Only carried out
+    /// once enough Elders of the section have been given the same command.
+    DeleteChunks(ChunkDeletion),
+    /// Move the stored chunks to the root dir, e.g. on another disk, while they
+    /// keep being served. New chunks are stored there right away, while the
+    /// chunks at the old location are migrated over in the background.
+    RelocateChunks(PathBuf),
+    /// Prepare the node for being destroyed: writes are refused, the Elders are asked
+    /// to copy the chunks held to other holders and to deregister the reward wallet,
+    /// and the earnings records are exported. The `DecommissionStatus` query tells
+    /// when the node is safe to destroy.
+    Decommission,
+    /// Import the chunks of a dir, e.g. a chunk store restored from a backup, or of a tar
+    /// archive of one, gzipped or not. Each chunk is verified against the address its
+    /// file is named by, stored, and registered with our Elders as held by us, in
+    /// batches in the background. The `ChunkImport` query reports the progress.
+    ImportChunks(PathBuf),
+    /// Send the reward wallets and pending payments of our section, signed by us, to the
+    /// nodes, e.g. the Elders of upgraded nodes taking our section over in a coordinated
+    /// network upgrade, which import them once signed by enough of our Elders.
+    SendSectionFunds(BTreeSet<XorName>),
+    /// Import the reward wallets and pending payments of a section from a json file of
+    /// the `SectionFunds` answers of enough of its Elders, e.g. to migrate the state of
+    /// a section of a private network. The section need not be ours, as the command is
+    /// authorised by the operator key.
+    ImportSectionFunds(PathBuf),
+    /// Send the metadata records of our section, signed by us, to the nodes, e.g. the
+    /// Elders of our section recovering it, which import them once signed by enough
+    /// of our Elders.
+    SendSectionMetadata(BTreeSet<XorName>),
+    /// Import the metadata records of a section from a json file of the `SectionMetadata`
+    /// answers of enough of its Elders, e.g. to recover the data map of the section after
+    /// losing it. The section need not be ours, as the command is authorised by the
+    /// operator key.
+    ImportSectionMetadata(PathBuf),
+    /// Log in full only the duties passing any of the filters, e.g. by the name of the duty,
+    /// the id of the msg or the origin, the others only by name at trace level. All duties
+    /// are logged in full again once the filters are cleared.
+    SetDutyLogFilters(Vec<DutyLogFilter>),
+}
+
+/// A query of the state of a running node. Queries do not mutate node state,
+/// so they need not be signed, and are only answered over the control socket.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperatorQuery {
+    /// The progress of the reward process, as a `RewardStatus`.
+    RewardStatus,
+    /// The duties refused for lack of authority, as `RefusedDuties`.
+    RefusedDuties,
+    /// The sizes of chunks for clients to store, as a `ChunkSizeAdvice`.
+    ChunkSizeAdvice,
+    /// Counters of the duties handled, as `DutyMetrics`.
+    DutyMetrics,
+    /// The latency of the peers of our section, as probed, as `PeerLatency` by peer.
+    PeerLatency,
+    /// The most read and written data of our section, as `HotData`.
+    HotData,
+    /// The status of the node, with the times it has been restarted, as `NodeStatus`.
+    NodeStatus,
+    /// The progress of decommissioning the node, if started, as a `DecommissionStatus`.
+    DecommissionStatus,
+    /// Counters of the chunk read cache, if chunks are cached, as `ReadCacheStats`.
+    ChunkReadCache,
+    /// The queries forwarded to other sections and pending, and the counters by
+    /// section of those forwarded, as `PendingQueryStats`.
+    PendingQueries,
+    /// The progress of the last import of chunks, if any, as a `ChunkImportReport`.
+    ChunkImport,
+    /// The reward wallets and pending payments of our section, signed by us, as parts
+    /// of `SignedFundsExport`, for the `ImportSectionFunds` command.
+    SectionFunds,
+    /// The metadata records of our section, signed by us, as parts of
+    /// `SignedMetadataDump`, for the `ImportSectionMetadata` command.
+    SectionMetadata,
+    /// The filters of the duties logged in full, as `DutyLogFilter`s.
+    DutyLogFilters,
+}
+
+/// An operator command, authorised by a signature of the operator key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedOperatorCommand {
+    /// The command to run.
+    pub command: OperatorCommand,
+    /// The name of the node the command is for, so that a command for one node
+    /// of an operator can't be run on the others.
+    pub node: XorName,
+    /// Unique per command, so that it can only be run once.
+    pub nonce: u64,
+    /// Seconds since unix epoch after which the command is rejected, if any.
+    pub expires_at: Option<u64>,
+    /// Signature of the operator key over command, node, nonce and expiry.
+    pub signature: Signature,
+}
+
+impl SignedOperatorCommand {
+    /// Signs the command for the node with the operator key.
+    pub fn sign(
+        command: OperatorCommand,
+        node: XorName,
+        nonce: u64,
+        expires_at: Option<u64>,
+        operator_key: &SecretKey,
+    ) -> Result<Self> {
+        let signature = operator_key.sign(&signed_bytes(&command, node, nonce, expires_at)?);
+        Ok(Self {
+            command,
+            node,
+            nonce,
+            expires_at,
+            signature,
+        })
+    }
+
+    fn is_signed_by(&self, key: &BlsPublicKey) -> Result<bool> {
+        let bytes = signed_bytes(&self.command, self.node, self.nonce, self.expires_at)?;
+        Ok(key.verify(&self.signature, bytes))
+    }
+}
+
+fn signed_bytes(
+    command: &OperatorCommand,
+    node: XorName,
+    nonce: u64,
+    expires_at: Option<u64>,
+) -> Result<Vec<u8>> {
+    Ok(utils::serialise(&(command, node, nonce, expires_at))?.to_vec())
+}
+
+/// Authorises operator commands against the configured operator key.
+/// Nonces of run commands are kept on disk, so that commands can't be
+/// replayed after a restart either, until the commands expire.
+pub(crate) struct OperatorAuth {
+    key: Option<BlsPublicKey>,
+    used_nonces: PickleDb,
+    // the time the expiry of commands is judged by
+    clock: Arc<NetworkClock>,
+}
+
+impl OperatorAuth {
+    pub(crate) fn new(
+        root_dir: &Path,
+        key: Option<BlsPublicKey>,
+        clock: Arc<NetworkClock>,
+    ) -> Result<Self> {
+        Ok(Self {
+            key,
+            used_nonces: utils::new_auto_dump_db(root_dir, USED_NONCES_DB_NAME)?,
+            clock,
+        })
+    }
+
+    /// Verifies that the command was signed by the operator for us, named `our_name`,
+    /// has not expired and has not been run before, and marks it as run.
+    pub(crate) fn authorise(
+        &mut self,
+        cmd: &SignedOperatorCommand,
+        our_name: XorName,
+    ) -> Result<()> {
+        let key = self.key.as_ref().ok_or_else(|| {
+            Error::OperatorAuthorisation("No operator key configured".to_string())
+        })?;
+        if !cmd.is_signed_by(key)? {
+            return Err(Error::OperatorAuthorisation(
+                "Invalid operator signature".to_string(),
+            ));
+        }
+        if cmd.node != our_name {
+            return Err(Error::OperatorAuthorisation(format!(
+                "Command is for node {}, not us",
+                cmd.node
+            )));
+        }
+        let now = self.clock.now_secs();
+        self.prune_nonces(now)?;
+        if let Some(expires_at) = cmd.expires_at {
+            if now > expires_at {
+                return Err(Error::OperatorAuthorisation(format!(
+                    "Command expired at {}",
+                    expires_at
+                )));
+            }
+        }
+        let nonce_key = cmd.nonce.to_string();
+        if self.used_nonces.exists(&nonce_key) {
+            return Err(Error::OperatorAuthorisation(format!(
+                "Nonce {} already used",
+                cmd.nonce
+            )));
+        }
+        self.used_nonces.set(&nonce_key, &cmd.expires_at)?;
+        Ok(())
+    }
+
+    // Forgets the nonces of the commands expired, which are rejected as such.
+    // The nonces of the commands not expiring are kept.
+    fn prune_nonces(&mut self, now: u64) -> Result<()> {
+        let expired: Vec<_> = self
+            .used_nonces
+            .get_all()
+            .into_iter()
+            .filter(|nonce_key| {
+                matches!(
+                    self.used_nonces.get::<Option<u64>>(nonce_key),
+                    Some(Some(expires_at)) if expires_at < now
+                )
+            })
+            .collect();
+        for nonce_key in expired {
+            let _ = self.used_nonces.rem(&nonce_key)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn auth(root: &TempDir, operator: &SecretKey) -> Result<OperatorAuth> {
+        OperatorAuth::new(root.path(), Some(operator.public_key()), Arc::default())
+    }
+
+    #[test]
+    fn only_commands_signed_by_operator_are_authorised() -> Result<()> {
+        let root = TempDir::new("operator")?;
+        let operator = SecretKey::random();
+        let mut auth = auth(&root, &operator)?;
+        let node = XorName::random();
+
+        let cmd = SignedOperatorCommand::sign(OperatorCommand::Shutdown, node, 1, None, &operator)?;
+        auth.authorise(&cmd, node)?;
+
+        let other = SecretKey::random();
+        let cmd = SignedOperatorCommand::sign(OperatorCommand::Shutdown, node, 2, None, &other)?;
+        assert!(auth.authorise(&cmd, node).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn commands_are_only_run_on_the_node_they_are_for() -> Result<()> {
+        let root = TempDir::new("operator")?;
+        let operator = SecretKey::random();
+        let mut auth = auth(&root, &operator)?;
+        let (node, other) = (XorName::random(), XorName::random());
+
+        let cmd =
+            SignedOperatorCommand::sign(OperatorCommand::Shutdown, other, 1, None, &operator)?;
+        assert!(auth.authorise(&cmd, node).is_err());
+
+        // the node is covered by the signature
+        let mut forged = cmd;
+        forged.node = node;
+        assert!(auth.authorise(&forged, node).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn commands_cannot_be_replayed_or_run_after_expiry() -> Result<()> {
+        let root = TempDir::new("operator")?;
+        let operator = SecretKey::random();
+        let mut auth = auth(&root, &operator)?;
+        let node = XorName::random();
+
+        let cmd = SignedOperatorCommand::sign(
+            OperatorCommand::SetMaintenanceMode(true),
+            node,
+            1,
+            None,
+            &operator,
+        )?;
+        auth.authorise(&cmd, node)?;
+        assert!(auth.authorise(&cmd, node).is_err());
+
+        let expired =
+            SignedOperatorCommand::sign(OperatorCommand::Shutdown, node, 2, Some(1), &operator)?;
+        assert!(auth.authorise(&expired, node).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn nonces_of_expired_commands_are_forgotten() -> Result<()> {
+        let root = TempDir::new("operator")?;
+        let operator = SecretKey::random();
+        let mut auth = auth(&root, &operator)?;
+        let node = XorName::random();
+        let now = auth.clock.now_secs();
+
+        let expiring = SignedOperatorCommand::sign(
+            OperatorCommand::Shutdown,
+            node,
+            1,
+            Some(now + 60),
+            &operator,
+        )?;
+        let lasting =
+            SignedOperatorCommand::sign(OperatorCommand::Shutdown, node, 2, None, &operator)?;
+        auth.authorise(&expiring, node)?;
+        auth.authorise(&lasting, node)?;
+
+        auth.prune_nonces(now + 30)?;
+        assert_eq!(auth.used_nonces.total_keys(), 2);
+        auth.prune_nonces(now + 61)?;
+        assert!(!auth.used_nonces.exists("1"));
+        // commands not expiring could be replayed if their nonces were forgotten
+        assert!(auth.used_nonces.exists("2"));
+        Ok(())
+    }
+}
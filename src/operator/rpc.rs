@@ -0,0 +1,393 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The operator control socket, secured by mutual TLS.
+//!
+//! Only operators presenting a certificate issued by the configured operator CA
+//! can connect. Each line sent over the connection is a json `SignedOperatorCommand`,
+//! which is passed on to the node, and acknowledged with a line of `accepted`,
+//! or a json `OperatorQuery`, which is answered with a line of json.
+//! The certificates are reloaded when changed on disk, so they can be rotated
+//! without restarting the node.
+
+use super::{OperatorQuery, SignedOperatorCommand};
+use crate::{node_ops::NodeDuty, Error, Result};
+use log::{debug, info, warn};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tokio::{
+    io::{
+        AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+        BufReader as AsyncBufReader,
+    },
+    net::TcpListener,
+    sync::{mpsc::Sender, oneshot},
+};
+use tokio_rustls::{
+    rustls::{
+        internal::pemfile::{certs, pkcs8_private_keys},
+        AllowAnyAuthenticatedClient, RootCertStore, ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+/// The certificate chain presented by the node.
+pub(crate) const NODE_CERT_FILE: &str = "node_cert.pem";
+/// The pkcs8 private key of the node certificate.
+pub(crate) const NODE_KEY_FILE: &str = "node_key.pem";
+/// The CA certificate(s) operator certificates must be issued by.
+pub(crate) const OPERATOR_CA_FILE: &str = "operator_ca.pem";
+
+const ACCEPTED: &[u8] = b"accepted\n";
+/// Bytes of a command line at most, room for a full chunk deletion batch,
+/// so that a connection can't have the node buffer without bound.
+const MAX_LINE: u64 = 4 * 1024 * 1024;
+
+/// The TLS acceptor, reloaded whenever the certificates change on disk.
+struct Acceptor {
+    dir: PathBuf,
+    acceptor: TlsAcceptor,
+    loaded_at: Option<SystemTime>,
+}
+
+impl Acceptor {
+    fn load(dir: &Path) -> Result<Self> {
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            acceptor: tls_acceptor(dir)?,
+            loaded_at: last_modified(dir),
+        })
+    }
+
+    fn reload_if_rotated(&mut self) {
+        let modified = last_modified(&self.dir);
+        if modified == self.loaded_at {
+            return;
+        }
+        match tls_acceptor(&self.dir) {
+            Ok(acceptor) => {
+                info!("Operator control socket certificates rotated");
+                self.acceptor = acceptor;
+                self.loaded_at = modified;
+            }
+            // e.g. only some of the files have been replaced yet
+            Err(error) => warn!(
+                "Could not reload operator certificates, keeping the previous: {}",
+                error
+            ),
+        }
+    }
+}
+
+/// Serves the operator control socket at the address, passing
+/// received commands to the node, until it stops receiving them.
+pub(crate) async fn serve(addr: SocketAddr, tls_dir: PathBuf, duties: Sender<NodeDuty>) {
+    let mut acceptor = match Acceptor::load(&tls_dir) {
+        Ok(acceptor) => acceptor,
+        Err(error) => {
+            warn!("Operator control socket not started: {}", error);
+            return;
+        }
+    };
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            warn!(
+                "Could not bind operator control socket to {}: {}",
+                addr, error
+            );
+            return;
+        }
+    };
+    info!("Operator control socket listening at {}", addr);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(error) => {
+                warn!("Operator control socket failed to accept: {}", error);
+                continue;
+            }
+        };
+        if duties.is_closed() {
+            debug!("Node no longer receiving operator commands");
+            return;
+        }
+        acceptor.reload_if_rotated();
+        let tls = acceptor.acceptor.clone();
+        let duties = duties.clone();
+        let _connection = tokio::spawn(async move {
+            match tls.accept(stream).await {
+                Ok(stream) => handle_connection(stream, duties).await,
+                Err(error) => warn!("Rejected operator connection from {}: {}", peer, error),
+            }
+        });
+    }
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(stream: S, duties: Sender<NodeDuty>) {
+    let mut stream = AsyncBufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match (&mut stream).take(MAX_LINE).read_line(&mut line).await {
+            Ok(0) => return,
+            Ok(read) if read as u64 == MAX_LINE && !line.ends_with('\n') => {
+                warn!("Operator connection closed: line over {} bytes", MAX_LINE);
+                let _ = stream.get_mut().write_all(b"line too long\n").await;
+                return;
+            }
+            Ok(_) => (),
+            Err(error) => {
+                debug!("Operator connection closed: {}", error);
+                return;
+            }
+        }
+        let reply = match serde_json::from_str::<SignedOperatorCommand>(line.trim()) {
+            Ok(cmd) => {
+                if duties
+                    .send(NodeDuty::RunOperatorCommand(cmd))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                ACCEPTED.to_vec()
+            }
+            Err(error) => match serde_json::from_str::<OperatorQuery>(line.trim()) {
+                Ok(query) => {
+                    let (reply, answer) = oneshot::channel();
+                    if duties
+                        .send(NodeDuty::RunOperatorQuery { query, reply })
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    match answer.await {
+                        Ok(answer) => format!("{}\n", answer).into_bytes(),
+                        // the node failed to answer, and logged why
+                        Err(_) => b"query failed\n".to_vec(),
+                    }
+                }
+                Err(_) => format!("invalid command: {}\n", error).into_bytes(),
+            },
+        };
+        if stream.get_mut().write_all(&reply).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn tls_acceptor(dir: &Path) -> Result<TlsAcceptor> {
+    let mut roots = RootCertStore::empty();
+    let (added, _) = roots
+        .add_pem_file(&mut reader(&dir.join(OPERATOR_CA_FILE))?)
+        .map_err(|_| tls_error("Invalid operator CA certificate"))?;
+    if added == 0 {
+        return Err(tls_error("No operator CA certificate"));
+    }
+    let cert_chain = certs(&mut reader(&dir.join(NODE_CERT_FILE))?)
+        .map_err(|_| tls_error("Invalid node certificate"))?;
+    let key = pkcs8_private_keys(&mut reader(&dir.join(NODE_KEY_FILE))?)
+        .map_err(|_| tls_error("Invalid node key"))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| tls_error("No node key"))?;
+
+    let mut config = ServerConfig::new(AllowAnyAuthenticatedClient::new(roots));
+    config
+        .set_single_cert(cert_chain, key)
+        .map_err(|error| tls_error(&error.to_string()))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn reader(path: &Path) -> Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(path)?))
+}
+
+// The latest modification of any of the certificate files.
+fn last_modified(dir: &Path) -> Option<SystemTime> {
+    [NODE_CERT_FILE, NODE_KEY_FILE, OPERATOR_CA_FILE]
+        .iter()
+        .filter_map(|file| fs::metadata(dir.join(file)).ok()?.modified().ok())
+        .max()
+}
+
+fn tls_error(msg: &str) -> Error {
+    Error::OperatorRpc(msg.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::operator::OperatorCommand;
+    use bls::SecretKey;
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+    use std::time::Duration;
+    use tempdir::TempDir;
+    use tokio::{net::TcpStream, sync::mpsc, time};
+    use tokio_rustls::{
+        rustls::{Certificate as TlsCertificate, ClientConfig},
+        webpki::DNSNameRef,
+        TlsConnector,
+    };
+    use xor_name::XorName;
+
+    #[tokio::test]
+    async fn only_operators_with_certificate_of_operator_ca_can_send_commands() -> Result<()> {
+        let tls_dir = TempDir::new("operator_tls")?;
+        let node_ca = ca()?;
+        let operator_ca = ca()?;
+        let node = leaf()?;
+        fs::write(tls_dir.path().join(NODE_CERT_FILE), sign(&node, &node_ca)?)?;
+        fs::write(
+            tls_dir.path().join(NODE_KEY_FILE),
+            node.serialize_private_key_pem(),
+        )?;
+        fs::write(tls_dir.path().join(OPERATOR_CA_FILE), pem(&operator_ca)?)?;
+
+        let addr: SocketAddr = ([127, 0, 0, 1], free_port()?).into();
+        let (duties, mut received) = mpsc::channel(1);
+        let _server = tokio::spawn(serve(addr, tls_dir.path().to_path_buf(), duties));
+        time::sleep(Duration::from_millis(200)).await;
+
+        let cmd = SignedOperatorCommand::sign(
+            OperatorCommand::Shutdown,
+            XorName::random(),
+            1,
+            None,
+            &SecretKey::random(),
+        )?;
+        let line = serde_json::to_string(&cmd)? + "\n";
+
+        // an operator with a certificate of the operator CA is accepted
+        let operator = leaf()?;
+        let reply = send(addr, &node_ca, &operator, &operator_ca, &line).await?;
+        assert_eq!(reply.as_bytes(), ACCEPTED);
+        assert!(matches!(
+            received.recv().await,
+            Some(NodeDuty::RunOperatorCommand(received)) if received == cmd
+        ));
+
+        // queries are answered by the node
+        let operator_query = async {
+            let line = serde_json::to_string(&OperatorQuery::RewardStatus)? + "\n";
+            send(addr, &node_ca, &operator, &operator_ca, &line).await
+        };
+        let node = async {
+            match received.recv().await {
+                Some(NodeDuty::RunOperatorQuery { query, reply }) => {
+                    assert_eq!(query, OperatorQuery::RewardStatus);
+                    let _ = reply.send("\"status\"".to_string());
+                }
+                _ => panic!("query not passed on to the node"),
+            }
+        };
+        let (reply, ()) = tokio::join!(operator_query, node);
+        assert_eq!(reply?, "\"status\"\n");
+
+        // any other certificate is rejected
+        let other_ca = ca()?;
+        let intruder = leaf()?;
+        assert!(send(addr, &node_ca, &intruder, &other_ca, &line)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lines_over_the_max_are_refused() -> Result<()> {
+        let (mut operator, node) = tokio::io::duplex(2 * MAX_LINE as usize);
+        let (duties, mut received) = mpsc::channel(1);
+        let connection = tokio::spawn(handle_connection(node, duties));
+
+        operator
+            .write_all(&vec![b'x'; MAX_LINE as usize + 1])
+            .await?;
+        let mut reply = String::new();
+        let _ = AsyncBufReader::new(&mut operator)
+            .read_line(&mut reply)
+            .await?;
+        assert_eq!(reply, "line too long\n");
+        // the connection is closed, with nothing passed on to the node
+        connection
+            .await
+            .map_err(|error| tls_error(&error.to_string()))?;
+        assert!(received.recv().await.is_none());
+        Ok(())
+    }
+
+    async fn send(
+        addr: SocketAddr,
+        node_ca: &Certificate,
+        operator: &Certificate,
+        operator_ca: &Certificate,
+        line: &str,
+    ) -> Result<String> {
+        let mut config = ClientConfig::new();
+        config
+            .root_store
+            .add(&TlsCertificate(
+                node_ca.serialize_der().map_err(rcgen_error)?,
+            ))
+            .map_err(|error| tls_error(&error.to_string()))?;
+        let chain =
+            certs(&mut sign(operator, operator_ca)?.as_bytes()).map_err(|_| tls_error("cert"))?;
+        let key = pkcs8_private_keys(&mut operator.serialize_private_key_pem().as_bytes())
+            .map_err(|_| tls_error("key"))?
+            .remove(0);
+        config
+            .set_single_client_cert(chain, key)
+            .map_err(|error| tls_error(&error.to_string()))?;
+        let connector = TlsConnector::from(Arc::new(config));
+        let stream = TcpStream::connect(addr).await?;
+        let name = DNSNameRef::try_from_ascii_str("localhost").map_err(|_| tls_error("name"))?;
+        let mut stream = AsyncBufReader::new(connector.connect(name, stream).await?);
+        stream.get_mut().write_all(line.as_bytes()).await?;
+        let mut reply = String::new();
+        if stream.read_line(&mut reply).await? == 0 {
+            return Err(tls_error("connection closed"));
+        }
+        Ok(reply)
+    }
+
+    fn ca() -> Result<Certificate> {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        Certificate::from_params(params).map_err(rcgen_error)
+    }
+
+    fn leaf() -> Result<Certificate> {
+        Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()]))
+            .map_err(rcgen_error)
+    }
+
+    fn sign(cert: &Certificate, ca: &Certificate) -> Result<String> {
+        cert.serialize_pem_with_signer(ca).map_err(rcgen_error)
+    }
+
+    fn pem(cert: &Certificate) -> Result<String> {
+        cert.serialize_pem().map_err(rcgen_error)
+    }
+
+    fn rcgen_error(error: rcgen::RcgenError) -> Error {
+        tls_error(&error.to_string())
+    }
+
+    fn free_port() -> Result<u16> {
+        Ok(std::net::TcpListener::bind("127.0.0.1:0")?
+            .local_addr()?
+            .port())
+    }
+}
@@ -0,0 +1,402 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{utils, Result};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+// The log of a db is kept beside its snapshot, under its name with this extension.
+const LOG_EXTENSION: &str = "log";
+// A new snapshot is written under this extension, until it replaces the last.
+const SNAPSHOT_TEMP_EXTENSION: &str = "snapshot";
+// A snapshot which cannot be read is set aside under this extension.
+const CORRUPT_EXTENSION: &str = "corrupt";
+// The changes logged at most before the log is compacted into a new snapshot.
+const SNAPSHOT_INTERVAL: usize = 1_000;
+// Each change is logged after the length of it, in this many bytes.
+const LENGTH_PREFIX: usize = 4;
+
+// The entries of a db, as in the files of PickleDb, with its lists, which are not used.
+type Snapshot = (HashMap<String, Vec<u8>>, HashMap<String, Vec<Vec<u8>>>);
+
+// A change to the db, as logged. Values are serialised, as they are held.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) enum LogEntry {
+    Set { key: String, value: Vec<u8> },
+    Rem { key: String },
+    // changes logged as one, e.g. those of a transaction
+    Batch(Vec<LogEntry>),
+}
+
+/// A db of the node, kept on disk as a snapshot and an append-only log of the changes since.
+/// Each change is synced to the log before it is applied, and the log is compacted into a new
+/// snapshot every `SNAPSHOT_INTERVAL` changes, so that on a restart, after a crash or not,
+/// the db is rebuilt from disk, rather than synced from peers all over again.
+/// Changes to several dbs are committed as a whole by a `Transaction`.
+/// Snapshots are in the format of the PickleDb files dbs were kept in before,
+/// so that those are read as the snapshot of a db with nothing logged yet.
+pub(crate) struct LoggedDb {
+    entries: HashMap<String, Vec<u8>>,
+    snapshot_path: PathBuf,
+    log: File,
+    // bytes of the log, up to the last change logged in full
+    log_len: u64,
+    // changes logged since the snapshot
+    logged: usize,
+}
+
+impl LoggedDb {
+    /// Opens the db of the name in the dir, rebuilding it from its snapshot and log, if any.
+    pub(crate) fn open<D: AsRef<Path>, N: AsRef<Path>>(db_dir: D, db_name: N) -> Result<Self> {
+        fs::create_dir_all(&db_dir)?;
+        let snapshot_path = db_dir.as_ref().join(db_name);
+        let mut entries = read_snapshot(&snapshot_path)?;
+        let log_path = snapshot_path.with_extension(LOG_EXTENSION);
+        let (logged, log_len) = match fs::read(&log_path) {
+            Ok(bytes) => replay(&mut entries, &bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (0, 0),
+            Err(e) => return Err(e.into()),
+        };
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+        // drops a change not logged in full, e.g. by a crash mid-way through it
+        if log.metadata()?.len() > log_len {
+            warn!(
+                "Dropping the change not logged in full at the end of {}",
+                log_path.display()
+            );
+            log.set_len(log_len)?;
+            log.sync_all()?;
+        }
+        if logged > 0 {
+            info!(
+                "Replayed {} changes logged to {}",
+                logged,
+                log_path.display()
+            );
+        }
+        let mut db = Self {
+            entries,
+            snapshot_path,
+            log,
+            log_len,
+            logged,
+        };
+        db.snapshot_if_due()?;
+        Ok(db)
+    }
+
+    /// The values of the db of the name in the dir, as of its snapshot and log, read without
+    /// opening it to changes, so that it can be read while open elsewhere.
+    /// A db not created yet reads as empty.
+    pub(crate) fn read<V: DeserializeOwned, D: AsRef<Path>, N: AsRef<Path>>(
+        db_dir: D,
+        db_name: N,
+    ) -> Result<Vec<(String, V)>> {
+        let snapshot_path = db_dir.as_ref().join(db_name);
+        // a snapshot which cannot be read is set aside on opening the db, so it is not read here
+        let mut entries = match fs::read(&snapshot_path) {
+            Ok(bytes) => utils::deserialise::<Snapshot>(&bytes)
+                .map(|(entries, _)| entries)
+                .unwrap_or_default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        match fs::read(snapshot_path.with_extension(LOG_EXTENSION)) {
+            Ok(bytes) => {
+                let _ = replay(&mut entries, &bytes);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(entries
+            .into_iter()
+            .filter_map(|(key, value)| utils::deserialise(&value).ok().map(|value| (key, value)))
+            .collect())
+    }
+
+    pub(crate) fn get<V: DeserializeOwned>(&self, key: &str) -> Option<V> {
+        self.entries
+            .get(key)
+            .and_then(|value| utils::deserialise(value).ok())
+    }
+
+    pub(crate) fn exists(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub(crate) fn get_all(&self) -> Vec<String> {
+        self.entries.keys().cloned().collect()
+    }
+
+    pub(crate) fn total_keys(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The path of the snapshot of the db, which identifies it.
+    pub(super) fn path(&self) -> &Path {
+        &self.snapshot_path
+    }
+
+    /// Sets the value of the key, once the change is logged.
+    pub(crate) fn set<V: Serialize>(&mut self, key: &str, value: &V) -> Result<()> {
+        let value = utils::serialise(value)?.to_vec();
+        self.append(&LogEntry::Set {
+            key: key.to_string(),
+            value: value.clone(),
+        })?;
+        let _ = self.entries.insert(key.to_string(), value);
+        self.snapshot_if_due()
+    }
+
+    /// Removes the key, once the change is logged, returning whether it was there.
+    pub(crate) fn rem(&mut self, key: &str) -> Result<bool> {
+        if !self.entries.contains_key(key) {
+            return Ok(false);
+        }
+        self.append(&LogEntry::Rem {
+            key: key.to_string(),
+        })?;
+        let _ = self.entries.remove(key);
+        self.snapshot_if_due()?;
+        Ok(true)
+    }
+
+    /// Applies the changes as a whole, once logged as one.
+    pub(super) fn apply(&mut self, entries: Vec<LogEntry>) -> Result<()> {
+        let entry = LogEntry::Batch(entries);
+        self.append(&entry)?;
+        apply_entry(&mut self.entries, entry);
+        self.snapshot_if_due()
+    }
+
+    fn append(&mut self, entry: &LogEntry) -> Result<()> {
+        let bytes = utils::serialise(entry)?;
+        let mut record = Vec::with_capacity(LENGTH_PREFIX + bytes.len());
+        record.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        record.extend_from_slice(&bytes);
+        if let Err(error) = self
+            .log
+            .write_all(&record)
+            .and_then(|_| self.log.sync_data())
+        {
+            // so that no change logged after it is lost behind a change logged in part
+            let _ = self.log.set_len(self.log_len);
+            return Err(error.into());
+        }
+        self.log_len += record.len() as u64;
+        self.logged += 1;
+        Ok(())
+    }
+
+    fn snapshot_if_due(&mut self) -> Result<()> {
+        if self.logged < SNAPSHOT_INTERVAL {
+            return Ok(());
+        }
+        self.snapshot()
+    }
+
+    /// Compacts the log into a new snapshot of the db, which replaces the last as a whole.
+    pub(crate) fn snapshot(&mut self) -> Result<()> {
+        let snapshot: (_, HashMap<String, Vec<Vec<u8>>>) = (&self.entries, HashMap::new());
+        let temp_path = self.snapshot_path.with_extension(SNAPSHOT_TEMP_EXTENSION);
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&utils::serialise(&snapshot)?)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.snapshot_path)?;
+        // the rename is only durable once the dir is, where dirs can be synced
+        if let Some(dir) = self.snapshot_path.parent() {
+            let _ = File::open(dir).and_then(|dir| dir.sync_all());
+        }
+        // the changes logged are in the snapshot now, so were we to crash
+        // before the log is emptied, replaying them again changes nothing
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        self.log_len = 0;
+        self.logged = 0;
+        Ok(())
+    }
+}
+
+// The entries of the snapshot, if any. A snapshot which cannot be read, e.g. one left truncated
+// by a crash of a version of the node writing it without syncing it, is set aside, so that the
+// db is rebuilt from the changes logged, and those missing are synced from peers.
+fn read_snapshot(path: &Path) -> Result<HashMap<String, Vec<u8>>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    match utils::deserialise::<Snapshot>(&bytes) {
+        Ok((entries, _)) => Ok(entries),
+        Err(error) => {
+            let corrupt_path = path.with_extension(CORRUPT_EXTENSION);
+            warn!(
+                "Snapshot {} cannot be read ({}), setting it aside as {}",
+                path.display(),
+                error,
+                corrupt_path.display()
+            );
+            fs::rename(path, &corrupt_path)?;
+            Ok(HashMap::new())
+        }
+    }
+}
+
+// Applies the changes logged, up to the first not logged in full, returning
+// the number of them, and the bytes of the log up to the end of the last.
+fn replay(entries: &mut HashMap<String, Vec<u8>>, log: &[u8]) -> (usize, u64) {
+    let (mut replayed, mut offset) = (0, 0);
+    while let Some(prefix) = log.get(offset..offset + LENGTH_PREFIX) {
+        let len = match prefix.try_into() {
+            Ok(prefix) => u32::from_le_bytes(prefix) as usize,
+            Err(_) => break,
+        };
+        let start = offset + LENGTH_PREFIX;
+        let entry = match log
+            .get(start..start + len)
+            .and_then(|bytes| utils::deserialise::<LogEntry>(bytes).ok())
+        {
+            Some(entry) => entry,
+            None => break,
+        };
+        apply_entry(entries, entry);
+        replayed += 1;
+        offset = start + len;
+    }
+    (replayed, offset as u64)
+}
+
+fn apply_entry(entries: &mut HashMap<String, Vec<u8>>, entry: LogEntry) {
+    match entry {
+        LogEntry::Set { key, value } => {
+            let _ = entries.insert(key, value);
+        }
+        LogEntry::Rem { key } => {
+            let _ = entries.remove(&key);
+        }
+        LogEntry::Batch(batch) => {
+            for entry in batch {
+                apply_entry(entries, entry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Error;
+    use pickledb::{PickleDb, PickleDbDumpPolicy};
+    use tempdir::TempDir;
+
+    const DB_NAME: &str = "test.db";
+
+    fn temp_dir() -> Result<TempDir> {
+        TempDir::new("logged_db").map_err(|e| Error::TempDirCreationFailed(e.to_string()))
+    }
+
+    #[test]
+    fn changes_are_rebuilt_on_reopening() -> Result<()> {
+        let dir = temp_dir()?;
+        let mut db = LoggedDb::open(dir.path(), DB_NAME)?;
+        db.set("a", &1_u64)?;
+        db.set("b", &2_u64)?;
+        db.set("a", &3_u64)?;
+        assert!(db.rem("b")?);
+        assert!(!db.rem("b")?);
+        drop(db);
+
+        let db = LoggedDb::open(dir.path(), DB_NAME)?;
+        assert_eq!(db.get::<u64>("a"), Some(3));
+        assert!(!db.exists("b"));
+        assert_eq!(db.total_keys(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn db_open_elsewhere_is_read_as_of_its_log() -> Result<()> {
+        let dir = temp_dir()?;
+        assert!(LoggedDb::read::<u64, _, _>(dir.path(), DB_NAME)?.is_empty());
+        let mut db = LoggedDb::open(dir.path(), DB_NAME)?;
+        db.set("a", &1_u64)?;
+        db.snapshot()?;
+        db.set("b", &2_u64)?;
+        let mut read = LoggedDb::read::<u64, _, _>(dir.path(), DB_NAME)?;
+        read.sort();
+        assert_eq!(read, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn a_change_logged_in_part_is_dropped() -> Result<()> {
+        let dir = temp_dir()?;
+        let mut db = LoggedDb::open(dir.path(), DB_NAME)?;
+        db.set("a", &1_u64)?;
+        drop(db);
+        // as by a crash mid-way through logging a change
+        let log_path = dir.path().join(DB_NAME).with_extension(LOG_EXTENSION);
+        let mut log = OpenOptions::new().append(true).open(&log_path)?;
+        log.write_all(&[200, 0, 0, 0, 1, 2])?;
+        drop(log);
+
+        let mut db = LoggedDb::open(dir.path(), DB_NAME)?;
+        assert_eq!(db.get::<u64>("a"), Some(1));
+        // and those logged after it are not lost behind it
+        db.set("b", &2_u64)?;
+        drop(db);
+        let db = LoggedDb::open(dir.path(), DB_NAME)?;
+        assert_eq!(db.get::<u64>("b"), Some(2));
+        assert_eq!(db.total_keys(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn log_is_compacted_into_snapshots_readable_as_before() -> Result<()> {
+        let dir = temp_dir()?;
+        let mut db = LoggedDb::open(dir.path(), DB_NAME)?;
+        for n in 0..=SNAPSHOT_INTERVAL as u64 {
+            db.set(&n.to_string(), &n)?;
+        }
+        assert_eq!(db.logged, 1);
+        drop(db);
+
+        let db = LoggedDb::open(dir.path(), DB_NAME)?;
+        assert_eq!(db.total_keys(), SNAPSHOT_INTERVAL + 1);
+        // the snapshot is a PickleDb file, and PickleDb files are snapshots
+        let pickle = PickleDb::load_bin(dir.path().join(DB_NAME), PickleDbDumpPolicy::NeverDump)?;
+        assert_eq!(pickle.get::<u64>("0"), Some(0));
+        assert_eq!(pickle.total_keys(), SNAPSHOT_INTERVAL);
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_not_readable_is_set_aside() -> Result<()> {
+        let dir = temp_dir()?;
+        let snapshot_path = dir.path().join(DB_NAME);
+        fs::write(&snapshot_path, [1, 2, 3])?;
+        let mut db = LoggedDb::open(dir.path(), DB_NAME)?;
+        assert_eq!(db.total_keys(), 0);
+        assert!(snapshot_path.with_extension(CORRUPT_EXTENSION).exists());
+        db.set("a", &1_u64)?;
+        db.snapshot()?;
+        drop(db);
+        let db = LoggedDb::open(dir.path(), DB_NAME)?;
+        assert_eq!(db.get::<u64>("a"), Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::logged_db::{LogEntry, LoggedDb};
+use crate::{utils, Error, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+// The transactions being committed are recorded in this dir of the root dir.
+const TRANSACTIONS_DIR_NAME: &str = "transactions";
+// A transaction is recorded under this extension once recorded in full, which commits it.
+const COMMITTED_EXTENSION: &str = "txn";
+// And under this one while being recorded, so that one recorded in part is not committed.
+const RECORDING_EXTENSION: &str = "recording";
+
+// The changes of a transaction, by the path of the db they are to, as recorded.
+#[derive(Serialize, Deserialize)]
+struct TransactionRecord {
+    changes: Vec<(PathBuf, Vec<LogEntry>)>,
+}
+
+/// Changes to any number of dbs, staged to be committed as a whole by `Transactions::commit`.
+#[derive(Default)]
+pub(crate) struct Transaction {
+    changes: Vec<(PathBuf, Vec<LogEntry>)>,
+}
+
+impl Transaction {
+    /// Stages setting the value of the key in the db.
+    pub(crate) fn set<V: Serialize>(&mut self, db: &LoggedDb, key: &str, value: &V) -> Result<()> {
+        let entry = LogEntry::Set {
+            key: key.to_string(),
+            value: utils::serialise(value)?.to_vec(),
+        };
+        self.stage(db, entry);
+        Ok(())
+    }
+
+    /// Stages removing the key from the db.
+    pub(crate) fn rem(&mut self, db: &LoggedDb, key: &str) {
+        let entry = LogEntry::Rem {
+            key: key.to_string(),
+        };
+        self.stage(db, entry)
+    }
+
+    fn stage(&mut self, db: &LoggedDb, entry: LogEntry) {
+        match self.changes.iter_mut().find(|(path, _)| path == db.path()) {
+            Some((_, entries)) => entries.push(entry),
+            None => self.changes.push((db.path().to_path_buf(), vec![entry])),
+        }
+    }
+}
+
+/// Commits transactions over the dbs of the node, e.g. registering a payment to the wallets of
+/// both the payer and the section, or recording a chunk to both its metadata and its holders,
+/// so that a crash mid-way through a duty does not leave its changes applied in part.
+/// A transaction over several dbs is recorded in full before any of its changes is applied,
+/// and replayed by `recover` at the next start of the node if it was not applied in full.
+#[derive(Clone, Debug)]
+pub(crate) struct Transactions {
+    dir: PathBuf,
+}
+
+impl Transactions {
+    /// The transactions of the node of the root dir.
+    pub(crate) fn new(root_dir: &Path) -> Self {
+        Self {
+            dir: root_dir.join(TRANSACTIONS_DIR_NAME),
+        }
+    }
+
+    /// Commits the changes staged, to the dbs, which must include every db they are to.
+    /// The changes to each db are logged to it as one, so that those to a single db
+    /// are committed as a whole without being recorded here first.
+    pub(crate) fn commit(&self, transaction: Transaction, dbs: &mut [&mut LoggedDb]) -> Result<()> {
+        let mut changes = transaction.changes;
+        if let Some((path, _)) = changes
+            .iter()
+            .find(|(path, _)| !dbs.iter().any(|db| db.path() == path))
+        {
+            return Err(Error::Logic(format!(
+                "Transaction changes {}, which is not among the dbs committed to",
+                path.display()
+            )));
+        }
+        let record = if changes.len() > 1 {
+            Some(self.record(&changes)?)
+        } else {
+            None
+        };
+        for db in dbs.iter_mut() {
+            if let Some(index) = changes.iter().position(|(path, _)| path == db.path()) {
+                let (_, entries) = changes.swap_remove(index);
+                // were we to fail here, the transaction is replayed in full at the next start
+                db.apply(entries)?;
+            }
+        }
+        if let Some(record) = record {
+            fs::remove_file(record)?;
+        }
+        Ok(())
+    }
+
+    // Records the changes, returning the path of the record once committed.
+    fn record(&self, changes: &[(PathBuf, Vec<LogEntry>)]) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let name = format!("{:016x}", rand::random::<u64>());
+        let recording_path = self.dir.join(&name).with_extension(RECORDING_EXTENSION);
+        let committed_path = recording_path.with_extension(COMMITTED_EXTENSION);
+        let record = TransactionRecord {
+            changes: changes.to_vec(),
+        };
+        let mut file = File::create(&recording_path)?;
+        file.write_all(&utils::serialise(&record)?)?;
+        file.sync_all()?;
+        fs::rename(&recording_path, &committed_path)?;
+        // the rename is only durable once the dir is, where dirs can be synced
+        let _ = File::open(&self.dir).and_then(|dir| dir.sync_all());
+        Ok(committed_path)
+    }
+}
+
+/// Applies the transactions committed but not applied in full, as of a crash, to the dbs of
+/// the node of the root dir. To be run on starting the node, before any of its dbs is opened.
+pub(crate) fn recover(root_dir: &Path) -> Result<()> {
+    let dir = root_dir.join(TRANSACTIONS_DIR_NAME);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(COMMITTED_EXTENSION) => (),
+            // one recorded in part was not committed, so none of its changes was applied
+            Some(RECORDING_EXTENSION) => {
+                warn!(
+                    "Dropping the transaction not recorded in full: {}",
+                    path.display()
+                );
+                fs::remove_file(&path)?;
+                continue;
+            }
+            _ => continue,
+        }
+        let record: TransactionRecord = utils::deserialise(&fs::read(&path)?)?;
+        for (db_path, entries) in record.changes {
+            let (db_dir, db_name) = match (db_path.parent(), db_path.file_name()) {
+                (Some(db_dir), Some(db_name)) => (db_dir, db_name),
+                _ => continue,
+            };
+            // the changes are values set and keys removed, so applying them again changes nothing
+            LoggedDb::open(db_dir, db_name)?.apply(entries)?;
+        }
+        fs::remove_file(&path)?;
+        info!("Replayed the transaction committed to {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn temp_dir() -> Result<TempDir> {
+        TempDir::new("transaction").map_err(|e| Error::TempDirCreationFailed(e.to_string()))
+    }
+
+    #[test]
+    fn changes_to_several_dbs_are_committed_as_a_whole() -> Result<()> {
+        let root = temp_dir()?;
+        let transactions = Transactions::new(root.path());
+        let mut first = LoggedDb::open(root.path(), "first.db")?;
+        let mut second = LoggedDb::open(root.path().join("nested"), "second.db")?;
+        first.set("gone", &0_u64)?;
+
+        let mut transaction = Transaction::default();
+        transaction.set(&first, "a", &1_u64)?;
+        transaction.rem(&first, "gone");
+        transaction.set(&second, "b", &2_u64)?;
+        transactions.commit(transaction, &mut [&mut first, &mut second])?;
+        assert_eq!(first.get::<u64>("a"), Some(1));
+        assert!(!first.exists("gone"));
+        assert_eq!(second.get::<u64>("b"), Some(2));
+        // nothing is left to replay
+        assert_eq!(
+            fs::read_dir(root.path().join(TRANSACTIONS_DIR_NAME))?.count(),
+            0
+        );
+
+        // and a db not given is refused, before anything is changed
+        let mut transaction = Transaction::default();
+        transaction.set(&first, "a", &3_u64)?;
+        transaction.set(&second, "b", &3_u64)?;
+        assert!(transactions.commit(transaction, &mut [&mut first]).is_err());
+        assert_eq!(first.get::<u64>("a"), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn transaction_applied_in_part_is_replayed_on_recovery() -> Result<()> {
+        let root = temp_dir()?;
+        let transactions = Transactions::new(root.path());
+        let mut first = LoggedDb::open(root.path(), "first.db")?;
+        let second = LoggedDb::open(root.path(), "second.db")?;
+        let mut transaction = Transaction::default();
+        transaction.set(&first, "a", &1_u64)?;
+        transaction.set(&second, "b", &2_u64)?;
+        // as by a crash once the transaction is committed, and applied to the first db only
+        let _ = transactions.record(&transaction.changes)?;
+        first.apply(transaction.changes[0].1.clone())?;
+        drop((first, second));
+        // and one recorded in part, as by a crash before it was committed
+        fs::write(
+            root.path()
+                .join(TRANSACTIONS_DIR_NAME)
+                .join("partial")
+                .with_extension(RECORDING_EXTENSION),
+            [1, 2, 3],
+        )?;
+
+        recover(root.path())?;
+        let first = LoggedDb::open(root.path(), "first.db")?;
+        let second = LoggedDb::open(root.path(), "second.db")?;
+        assert_eq!(first.get::<u64>("a"), Some(1));
+        assert_eq!(second.get::<u64>("b"), Some(2));
+        assert_eq!(
+            fs::read_dir(root.path().join(TRANSACTIONS_DIR_NAME))?.count(),
+            0
+        );
+        Ok(())
+    }
+}
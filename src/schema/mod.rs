@@ -0,0 +1,747 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Snapshots of the serialised form of messages and other types the node
+//! exchanges or persists, compared against the golden files in `golden/`.
+//!
+//! A failing comparison means wire (or disk) compatibility has been broken,
+//! e.g. by a dependency bump or a reordered enum. If the change is intended,
+//! regenerate the golden files by running the tests with
+//! `UPDATE_SCHEMA_SNAPSHOTS=1` and commit them.
+
+use crate::{
+    capacity::{EconomyConfig, SectionFullness, StoreCostQuote},
+    chunks::{
+        ChunkAccess, ChunkRange, ImportedChunks, OrphanOffer, OrphanRelease, RangedChunkRead,
+        ReplicationFailed,
+    },
+    metadata::{
+        AckLevel, CapacityReport, CatchUpPage, CatchUpQuery, ChunkAccessQuery, ChunkAccessReport,
+        ChunkRepair, DataMapCmd, DataMapQuery, ExistenceAttestation, ExistenceQuery,
+        ExistenceStatement, MetadataDump, MetadataPage, MetadataRangeQuery, MetadataRecord,
+        RecordKey, RelayedChunk, RelayedRead, ReplicationBatch, ShardMap, SignedCatchUpPage,
+        SignedMetadataDump, StorageChallenge, StorageProof, WriteAck, WriteAckLevel,
+    },
+    network::{PeerPing, PeerPong},
+    node::{BatchItem, Busy, SectionParams, SectionRedirect, SectionUpdate, StatePushAck},
+    node_msg::{ClientCmd, ClientMsg, ClientRequest, NodeMsg, NodeMsgBody},
+    operator::{OperatorCommand, SignedOperatorCommand},
+    section_funds::{
+        reward_stage::{CreditStatus, RewardStageKind, RewardStatus},
+        FundsExport, PaymentHandover, SignedFundsExport,
+    },
+    transfers::{
+        test_utils::get_genesis, SignedStoreCostEstimate, SignedWalletSummary, StoreCostEstimate,
+        StoreCostItem, TransferId, TransferStatus, TransferStatusQuery, WalletSummary,
+        WalletSummaryPush,
+    },
+    utils, Result,
+};
+use bls::{SecretKey, SecretKeySet};
+use ed25519_dalek::{
+    Keypair, PublicKey as Ed25519PublicKey, SecretKey as Ed25519SecretKey, Signer,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use serde::Serialize;
+use sn_data_types::{
+    ActorHistory, Blob, BlobAddress, CreditAgreementProof, PublicBlob, PublicKey, Signature,
+    SignatureShare, Token,
+};
+use sn_messaging::{
+    client::{
+        BlobRead, CmdError, DataQuery, Error as ErrorMessage, Message, NodeCmd, NodeEvent,
+        NodeQuery, NodeRewardQuery, NodeSystemCmd, Query, QueryResponse, TransferQuery,
+    },
+    EndUser, MessageId,
+};
+use sn_routing::{Prefix, SectionChain};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use xor_name::XorName;
+
+const UPDATE_ENV_VAR: &str = "UPDATE_SCHEMA_SNAPSHOTS";
+const GOLDEN_EXTENSION: &str = "hex";
+
+/// The registry of sampled types, by snapshot name.
+fn samples() -> Result<Vec<(&'static str, Vec<u8>)>> {
+    let user = ed25519_key(1);
+    let node = ed25519_key(2);
+    let section = PublicKey::Bls(bls_key(3).public_key());
+    let address = BlobAddress::Public(name(4));
+    let id = MessageId(name(5));
+    let correlation_id = MessageId(name(6));
+
+    let wire = |msg: Message| -> Result<Vec<u8>> { Ok(msg.serialize()?.to_vec()) };
+
+    let mut samples = vec![
+        (
+            "query_get_blob",
+            wire(Message::Query {
+                query: Query::Data(DataQuery::Blob(BlobRead::Get(address))),
+                id,
+                target_section_pk: Some(section),
+            })?,
+        ),
+        (
+            "query_get_balance",
+            wire(Message::Query {
+                query: Query::Transfer(TransferQuery::GetBalance(public_key(&user))),
+                id,
+                target_section_pk: None,
+            })?,
+        ),
+        (
+            "query_response_get_balance",
+            wire(Message::QueryResponse {
+                response: QueryResponse::GetBalance(Ok(Token::from_nano(1_000))),
+                id,
+                correlation_id,
+                target_section_pk: None,
+            })?,
+        ),
+        (
+            "cmd_error_data",
+            wire(Message::CmdError {
+                error: CmdError::Data(ErrorMessage::NoSuchData),
+                id,
+                correlation_id,
+                target_section_pk: Some(section),
+            })?,
+        ),
+        (
+            "node_cmd_register_wallet",
+            wire(Message::NodeCmd {
+                cmd: NodeCmd::System(NodeSystemCmd::RegisterWallet(section)),
+                id,
+                target_section_pk: Some(section),
+            })?,
+        ),
+        (
+            "node_cmd_replicate_chunk",
+            wire(Message::NodeCmd {
+                cmd: NodeCmd::System(NodeSystemCmd::ReplicateChunk {
+                    new_holder: name(7),
+                    address,
+                    current_holders: vec![name(8), name(9)].into_iter().collect(),
+                }),
+                id,
+                target_section_pk: None,
+            })?,
+        ),
+        (
+            "node_query_chunks",
+            wire(Message::NodeQuery {
+                query: NodeQuery::Chunks {
+                    query: BlobRead::Get(address),
+                    origin: EndUser::AllClients(public_key(&user)),
+                },
+                id,
+                target_section_pk: None,
+            })?,
+        ),
+        (
+            "node_query_node_wallet_key",
+            wire(Message::NodeQuery {
+                query: NodeQuery::Rewards(NodeRewardQuery::GetNodeWalletKey(name(10))),
+                id,
+                target_section_pk: Some(section),
+            })?,
+        ),
+        (
+            "node_event_replication_completed",
+            wire(Message::NodeEvent {
+                event: NodeEvent::ReplicationCompleted {
+                    chunk: address,
+                    proof: Signature::Ed25519(node.sign(address.name())),
+                },
+                id,
+                correlation_id,
+                target_section_pk: None,
+            })?,
+        ),
+        (
+            "operator_command_shutdown",
+            // operator commands are dropped into the inbox as json
+            serde_json::to_vec(&SignedOperatorCommand::sign(
+                OperatorCommand::Shutdown,
+                name(12),
+                1,
+                Some(1_600_000_000),
+                &bls_key(11),
+            )?)?,
+        ),
+    ];
+    for body in node_msg_bodies()? {
+        let msg = NodeMsg { id, body };
+        samples.push((node_msg_name(&msg.body), msg.serialise()?.to_vec()));
+    }
+    for msg in client_msgs()? {
+        let origin = EndUser::AllClients(public_key(&user));
+        let response = msg.msg(correlation_id, origin)?.msg;
+        samples.push((client_msg_name(&msg), wire(response)?));
+    }
+    for request in client_requests()? {
+        let query = Message::Query {
+            query: Query::Data(request.query()?),
+            id,
+            target_section_pk: Some(section),
+        };
+        samples.push((client_request_name(&request), wire(query)?));
+    }
+    for cmd in client_cmds() {
+        // the write alone, as its payment is sampled by the transfer msgs
+        let write = utils::serialise(&cmd.cmd()?)?;
+        samples.push((client_cmd_name(&cmd), write.to_vec()));
+    }
+    Ok(samples)
+}
+
+// The snapshot name of each msg between nodes. A variant added to `NodeMsgBody`
+// fails to compile here, until it is named and sampled in `node_msg_bodies`.
+fn node_msg_name(body: &NodeMsgBody) -> &'static str {
+    use NodeMsgBody::*;
+    match body {
+        MetadataRangeQuery(_) => "node_msg_metadata_range_query",
+        MetadataPage { .. } => "node_msg_metadata_page",
+        OrphanOffer(_) => "node_msg_orphan_offer",
+        OrphanRelease(_) => "node_msg_orphan_release",
+        ReplicationFailed(_) => "node_msg_replication_failed",
+        ImportedChunks(_) => "node_msg_imported_chunks",
+        ChunkReadFailed(_) => "node_msg_chunk_read_failed",
+        ChunkDeleted { .. } => "node_msg_chunk_deleted",
+        StorageChallenge(_) => "node_msg_storage_challenge",
+        StorageProof(_) => "node_msg_storage_proof",
+        PeerPing(_) => "node_msg_peer_ping",
+        PeerPong(_) => "node_msg_peer_pong",
+        CapacityReport(_) => "node_msg_capacity_report",
+        CatchUpQuery(_) => "node_msg_catch_up_query",
+        SignedCatchUpPage(_) => "node_msg_signed_catch_up_page",
+        ChunkAccessQuery(_) => "node_msg_chunk_access_query",
+        ChunkAccessReport(_) => "node_msg_chunk_access_report",
+        RelayedRead(_) => "node_msg_relayed_read",
+        RelayedChunk(_) => "node_msg_relayed_chunk",
+        RangedChunkRead(_) => "node_msg_ranged_chunk_read",
+        SignedMetadataDump(_) => "node_msg_signed_metadata_dump",
+        ReplicationBatch(_) => "node_msg_replication_batch",
+        SignedFundsExport(_) => "node_msg_signed_funds_export",
+        PaymentHandover(_) => "node_msg_payment_handover",
+        MaxChunkSize(_) => "node_msg_max_chunk_size",
+        SectionParams(_) => "node_msg_section_params",
+        WriteAck(_) => "node_msg_write_ack",
+        WriteAckLevel(_) => "node_msg_write_ack_level",
+        StatePushAck(_) => "node_msg_state_push_ack",
+        WalletSummaryPush(_) => "node_msg_wallet_summary_push",
+        RewardStatusQuery => "node_msg_reward_status_query",
+        RewardStatus { .. } => "node_msg_reward_status",
+    }
+}
+
+// A sample of every msg between nodes.
+fn node_msg_bodies() -> Result<Vec<NodeMsgBody>> {
+    let user = ed25519_key(1);
+    let address = BlobAddress::Public(name(4));
+    let id = MessageId(name(5));
+    let origin = EndUser::AllClients(public_key(&user));
+    let key_set = bls_key_set(12);
+    let prefix = Prefix::default().pushed(true);
+    let range = MetadataRangeQuery {
+        prefix,
+        after: Some(RecordKey::Chunk(address)),
+    };
+    let records = vec![
+        (
+            RecordKey::Chunk(address),
+            MetadataRecord::Chunk {
+                address,
+                holders: vec![name(7), name(8)].into_iter().collect(),
+                owner: None,
+                copies: Some(4),
+            },
+        ),
+        (
+            RecordKey::DataMap(name(13)),
+            MetadataRecord::DataMap {
+                name: name(13),
+                owner: public_key(&user),
+                versions: vec![b"data map".to_vec()],
+            },
+        ),
+        (
+            RecordKey::ShardedChunk(address),
+            MetadataRecord::ShardedChunk {
+                address,
+                shards: ShardMap {
+                    size: 1_024,
+                    data_shards: 1,
+                    owner: None,
+                    shards: vec![BlobAddress::Public(name(14))],
+                },
+            },
+        ),
+    ];
+    let page = MetadataPage {
+        records: records.clone(),
+        next: Some(range.clone()),
+    };
+    let catch_up_query = CatchUpQuery {
+        range: range.clone(),
+        since: Some(1_600_000_000),
+        attempt: 1,
+    };
+    let catch_up_page = CatchUpPage {
+        section_key: PublicKey::Bls(key_set.public_keys().public_key()),
+        query: catch_up_query.clone(),
+        page: page.clone(),
+        changed: Some(vec![name(13)].into_iter().collect()),
+        mark: 2,
+    };
+    let dump = MetadataDump {
+        key_set: key_set.public_keys(),
+        prefix,
+        part: (0, 1),
+        records,
+    };
+    let export = FundsExport {
+        key_set: key_set.public_keys(),
+        prefix,
+        part: (0, 1),
+        wallets: vec![(name(7), (5, public_key(&user)))]
+            .into_iter()
+            .collect(),
+        payments: vec![credit_proof(&key_set)?],
+    };
+    let summary = WalletSummary {
+        wallet: public_key(&user),
+        balance: Token::from_nano(1_000),
+        debit_version: 3,
+        credit_ids: vec![[15; 32]].into_iter().collect(),
+    };
+    let ping = PeerPing {
+        nonce: 16,
+        secs: 1_600_000_000,
+    };
+    Ok(vec![
+        NodeMsgBody::MetadataRangeQuery(range.clone()),
+        NodeMsgBody::MetadataPage { query: id, page },
+        NodeMsgBody::OrphanOffer(OrphanOffer {
+            address,
+            owner: Some(public_key(&user)),
+        }),
+        NodeMsgBody::OrphanRelease(OrphanRelease { offer: id, address }),
+        NodeMsgBody::ReplicationFailed(ReplicationFailed { address, id }),
+        NodeMsgBody::ImportedChunks(ImportedChunks {
+            chunks: vec![(address, None)].into_iter().collect(),
+        }),
+        NodeMsgBody::ChunkReadFailed(address),
+        NodeMsgBody::ChunkDeleted {
+            address,
+            proof: Signature::Ed25519(ed25519_key(2).sign(address.name())),
+        },
+        NodeMsgBody::StorageChallenge(StorageChallenge {
+            address,
+            nonce: 17,
+            offset: 0,
+            len: 64,
+        }),
+        NodeMsgBody::StorageProof(StorageProof {
+            challenge: id,
+            hash: name(18),
+        }),
+        NodeMsgBody::PeerPing(ping.clone()),
+        NodeMsgBody::PeerPong(PeerPong {
+            ping,
+            secs: 1_600_000_001,
+        }),
+        NodeMsgBody::CapacityReport(CapacityReport {
+            used: 1_000,
+            total: 10_000,
+            chunks: 10,
+            reported_at: 1_600_000_000,
+            capability: Some(100),
+        }),
+        NodeMsgBody::CatchUpQuery(catch_up_query),
+        NodeMsgBody::SignedCatchUpPage(SignedCatchUpPage {
+            share: share(&key_set, &catch_up_page)?,
+            page: catch_up_page,
+        }),
+        NodeMsgBody::ChunkAccessQuery(ChunkAccessQuery {
+            addresses: vec![address],
+            nonce: 19,
+        }),
+        NodeMsgBody::ChunkAccessReport(ChunkAccessReport {
+            query: id,
+            chunks: vec![(
+                address,
+                ChunkAccess {
+                    reads: 3,
+                    last_read: 1_600_000_000,
+                },
+            )],
+        }),
+        NodeMsgBody::RelayedRead(RelayedRead {
+            address,
+            msg_id: id,
+        }),
+        NodeMsgBody::RelayedChunk(RelayedChunk {
+            read: RelayedRead {
+                address,
+                msg_id: id,
+            },
+            chunk: Blob::Public(PublicBlob::new(b"chunk".to_vec())),
+        }),
+        NodeMsgBody::RangedChunkRead(RangedChunkRead {
+            address,
+            range: ChunkRange { offset: 2, len: 3 },
+            msg_id: id,
+            origin,
+        }),
+        NodeMsgBody::SignedMetadataDump(SignedMetadataDump {
+            share: share(&key_set, &dump)?,
+            dump,
+        }),
+        NodeMsgBody::ReplicationBatch(ReplicationBatch {
+            new_holder: name(7),
+            chunks: vec![(address, vec![name(8)].into_iter().collect())]
+                .into_iter()
+                .collect(),
+        }),
+        NodeMsgBody::SignedFundsExport(SignedFundsExport {
+            share: share(&key_set, &export)?,
+            export,
+        }),
+        NodeMsgBody::PaymentHandover(PaymentHandover {
+            prefix,
+            payments: vec![credit_proof(&key_set)?],
+        }),
+        NodeMsgBody::MaxChunkSize(1_024 * 1_024),
+        NodeMsgBody::SectionParams(SectionParams {
+            min_write_acks: 2,
+            treasury_percentage: 10,
+            min_reward_age: 5,
+            economy: EconomyConfig::default(),
+        }),
+        NodeMsgBody::WriteAck(WriteAck { id, origin }),
+        NodeMsgBody::WriteAckLevel(WriteAckLevel {
+            id,
+            origin,
+            level: AckLevel::Durable,
+        }),
+        NodeMsgBody::StatePushAck(StatePushAck { id }),
+        NodeMsgBody::WalletSummaryPush(WalletSummaryPush {
+            summaries: vec![SignedWalletSummary {
+                share: share(&key_set, &summary)?,
+                summary,
+                key_set: key_set.public_keys(),
+            }],
+        }),
+        NodeMsgBody::RewardStatusQuery,
+        NodeMsgBody::RewardStatus {
+            query: id,
+            status: RewardStatus {
+                stage: RewardStageKind::AccumulatingCredits,
+                min_signatures: Some(3),
+                credits: vec![CreditStatus {
+                    id: [15; 32],
+                    recipient: public_key(&user),
+                    amount: Token::from_nano(1_000),
+                    signed_by: vec![name(7)].into_iter().collect(),
+                    missing: vec![name(8)].into_iter().collect(),
+                    agreed: false,
+                }],
+            },
+        },
+    ])
+}
+
+// The snapshot name of each msg from nodes to clients, sent in the envelope of a
+// `GetBlob` response. A variant added to `ClientMsg` fails to compile here.
+fn client_msg_name(msg: &ClientMsg) -> &'static str {
+    use ClientMsg::*;
+    match msg {
+        BatchResponse(_) => "client_msg_batch_response",
+        SectionUpdate(_) => "client_msg_section_update",
+        Busy(_) => "client_msg_busy",
+        DataConflict(_) => "client_msg_data_conflict",
+        SectionRedirect(_) => "client_msg_section_redirect",
+        SectionFullness(_) => "client_msg_section_fullness",
+        StoreCostQuote(_) => "client_msg_store_cost_quote",
+        ExistenceAttestation(_) => "client_msg_existence_attestation",
+        StoreCostEstimate(_) => "client_msg_store_cost_estimate",
+        RepairReport(_) => "client_msg_repair_report",
+        TransferStatus(_) => "client_msg_transfer_status",
+    }
+}
+
+// A sample of every msg from nodes to clients.
+fn client_msgs() -> Result<Vec<ClientMsg>> {
+    let user = ed25519_key(1);
+    let address = BlobAddress::Public(name(4));
+    let key_set = bls_key_set(12);
+    let section_key = PublicKey::Bls(key_set.public_keys().public_key());
+    let prefix = Prefix::default().pushed(true);
+    let fullness = SectionFullness {
+        used_percentage: Some(40),
+        adults: 10,
+        full_adults: 1,
+        joins_allowed: true,
+    };
+    let statement = ExistenceStatement {
+        section_key,
+        timestamp: 1_600_000_000,
+        address,
+    };
+    let estimate = StoreCostEstimate {
+        section_key,
+        items: vec![StoreCostItem {
+            bytes: 1_024,
+            chunks: 2,
+            cost: Token::from_nano(10),
+            premium_cost: Token::from_nano(15),
+        }],
+        total: Token::from_nano(20),
+        premium_total: Token::from_nano(30),
+        treasury_fee: Token::from_nano(2),
+        expires_at: 1_600_000_600,
+        fullness,
+    };
+    let elders: BTreeMap<_, SocketAddr> = vec![(name(7), ([127, 0, 0, 1], 12_000).into())]
+        .into_iter()
+        .collect();
+    Ok(vec![
+        ClientMsg::BatchResponse(vec![
+            BatchItem::Answered(QueryResponse::GetBalance(Ok(Token::from_nano(1_000)))),
+            BatchItem::Forwarded(MessageId(name(5))),
+        ]),
+        ClientMsg::SectionUpdate(SectionUpdate {
+            prefix,
+            pk_set: key_set.public_keys(),
+            elders,
+            proof: SectionChain::new(key_set.public_keys().public_key()),
+        }),
+        ClientMsg::Busy(Busy {
+            retry_after: Duration::from_millis(500),
+        }),
+        ClientMsg::DataConflict(address),
+        ClientMsg::SectionRedirect(SectionRedirect {
+            name: name(13),
+            prefix,
+            elders: vec![name(7), name(8)].into_iter().collect(),
+        }),
+        ClientMsg::SectionFullness(fullness),
+        ClientMsg::StoreCostQuote(StoreCostQuote {
+            standard: Token::from_nano(10),
+            premium: Token::from_nano(15),
+            premium_redundancy: 8,
+        }),
+        ClientMsg::ExistenceAttestation(ExistenceAttestation {
+            share: share(&key_set, &statement)?,
+            statement,
+        }),
+        ClientMsg::StoreCostEstimate(SignedStoreCostEstimate {
+            share: share(&key_set, &estimate)?,
+            estimate,
+        }),
+        ClientMsg::RepairReport(vec![
+            (address, ChunkRepair::Healthy { holders: 4 }),
+            (
+                BlobAddress::Public(name(14)),
+                ChunkRepair::Repairing {
+                    live: 2,
+                    lost: 2,
+                    records_fixed: 1,
+                    new_holders: 2,
+                },
+            ),
+        ]),
+        ClientMsg::TransferStatus(TransferStatus::Registered(ActorHistory {
+            credits: vec![credit_proof(&key_set)?],
+            debits: vec![],
+        })),
+    ])
+}
+
+// The snapshot name of each request from clients to nodes, sent as the key of a
+// `MapRead::GetValue` query. A variant added to `ClientRequest` fails to compile here.
+fn client_request_name(request: &ClientRequest) -> &'static str {
+    use ClientRequest::*;
+    match request {
+        Existence(_) => "client_request_existence",
+        ChunkRange { .. } => "client_request_chunk_range",
+        ChunkRepair(_) => "client_request_chunk_repair",
+        DataMap(_) => "client_request_data_map",
+        ReadBatch(_) => "client_request_read_batch",
+        TransferStatus(_) => "client_request_transfer_status",
+    }
+}
+
+// A sample of every request from clients to nodes.
+fn client_requests() -> Result<Vec<ClientRequest>> {
+    let user = ed25519_key(1);
+    let address = BlobAddress::Public(name(4));
+    Ok(vec![
+        ClientRequest::Existence(ExistenceQuery {
+            address,
+            timestamp: 1_600_000_000,
+        }),
+        ClientRequest::ChunkRange {
+            address,
+            range: ChunkRange { offset: 2, len: 3 },
+        },
+        ClientRequest::ChunkRepair(vec![address]),
+        ClientRequest::DataMap(DataMapQuery::Get {
+            name: name(13),
+            version: Some(1),
+        }),
+        ClientRequest::ReadBatch(vec![DataQuery::Blob(BlobRead::Get(address))]),
+        ClientRequest::TransferStatus(TransferStatusQuery {
+            wallet: public_key(&user),
+            id: TransferId::Credit([15; 32]),
+        }),
+    ])
+}
+
+// The snapshot name of each cmd from clients to nodes, sent as the key of the entry
+// inserted by a `MapWrite::Edit`. A variant added to `ClientCmd` fails to compile here.
+fn client_cmd_name(cmd: &ClientCmd) -> &'static str {
+    match cmd {
+        ClientCmd::DataMap(_) => "client_cmd_data_map",
+    }
+}
+
+// A sample of every cmd from clients to nodes.
+fn client_cmds() -> Vec<ClientCmd> {
+    vec![ClientCmd::DataMap(DataMapCmd::Put {
+        name: name(13),
+        data_map: b"data map".to_vec(),
+        expected_version: Some(1),
+    })]
+}
+
+fn name(seed: u8) -> XorName {
+    XorName([seed; 32])
+}
+
+fn bls_key(seed: u64) -> SecretKey {
+    ChaChaRng::seed_from_u64(seed).gen()
+}
+
+fn bls_key_set(seed: u64) -> SecretKeySet {
+    SecretKeySet::random(0, &mut ChaChaRng::seed_from_u64(seed))
+}
+
+// The share of the only key of the set, over the serialised item.
+fn share<T: Serialize>(key_set: &SecretKeySet, item: &T) -> Result<SignatureShare> {
+    Ok(SignatureShare {
+        index: 0,
+        share: key_set.secret_key_share(0).sign(utils::serialise(item)?),
+    })
+}
+
+fn credit_proof(key_set: &SecretKeySet) -> Result<CreditAgreementProof> {
+    get_genesis(
+        1_000,
+        PublicKey::Bls(bls_key(20).public_key()),
+        key_set.public_keys(),
+        key_set.secret_key_share(0),
+    )
+}
+
+fn ed25519_key(seed: u8) -> Keypair {
+    let secret = Ed25519SecretKey::from_bytes(&[seed; 32]).expect("valid key bytes");
+    let public = Ed25519PublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+fn public_key(keypair: &Keypair) -> PublicKey {
+    PublicKey::Ed25519(keypair.public)
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("schema")
+        .join("golden")
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    golden_dir().join(format!("{}.{}", name, GOLDEN_EXTENSION))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serialised_samples_match_golden_files() -> Result<()> {
+        let samples = samples()?;
+        if env::var(UPDATE_ENV_VAR).is_ok() {
+            fs::create_dir_all(golden_dir())?;
+            for (name, bytes) in &samples {
+                fs::write(golden_path(name), hex::encode(bytes) + "\n")?;
+            }
+        }
+
+        let mut mismatches = vec![];
+        for (name, bytes) in &samples {
+            match fs::read_to_string(golden_path(name)) {
+                Ok(golden) if golden.trim() == hex::encode(bytes) => (),
+                Ok(_) => mismatches.push(format!("{} differs from its golden file", name)),
+                Err(_) => mismatches.push(format!("{} has no golden file", name)),
+            }
+        }
+        assert!(
+            mismatches.is_empty(),
+            "Serialised forms changed, run with {}=1 if intended: {:?}",
+            UPDATE_ENV_VAR,
+            mismatches
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn every_golden_file_has_a_sample() -> Result<()> {
+        let names: BTreeSet<_> = samples()?
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        for entry in fs::read_dir(golden_dir())? {
+            let path = entry?.path();
+            let stem = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            assert!(
+                names.contains(&stem),
+                "Golden file {} has no sample in the registry",
+                path.display()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn every_sample_has_a_name_of_its_own() -> Result<()> {
+        let samples = samples()?;
+        let names: BTreeSet<_> = samples.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names.len(), samples.len());
+        Ok(())
+    }
+
+    #[test]
+    fn samples_are_deterministic() -> Result<()> {
+        assert_eq!(samples()?, samples()?);
+        Ok(())
+    }
+}
@@ -6,21 +6,25 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{Error, Network, Result};
+use crate::{network::VerificationCache, Error, Network, Result};
 use bls::PublicKeySet;
 use futures::executor::block_on as block;
 use sn_data_types::{OwnerType, Result as DtResult, SignatureShare, Signing};
+use sn_routing::SectionChain;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct ElderSigning {
     id: OwnerType,
     network: Network,
+    verified: Arc<VerificationCache>,
 }
 
 impl ElderSigning {
     pub async fn new(network: Network) -> Result<Self> {
         Ok(Self {
             id: OwnerType::Multi(network.our_public_key_set().await?),
+            verified: network.verification_cache(),
             network,
         })
     }
@@ -38,6 +42,11 @@ impl ElderSigning {
             .await
             .map_err(|_| Error::NoSectionPublicKeySet)
     }
+
+    /// The chain of our section keys, as known to us.
+    pub async fn section_chain(&self) -> SectionChain {
+        self.network.section_chain().await
+    }
 }
 
 impl Signing for ElderSigning {
@@ -57,29 +66,37 @@ impl Signing for ElderSigning {
             Ok(data) => data,
             Err(_) => return false,
         };
-        use sn_data_types::Signature::*;
-        match sig {
-            Bls(sig) => {
-                if let OwnerType::Multi(set) = self.id() {
-                    set.public_key().verify(&sig, data)
-                } else {
-                    false
-                }
+        let id = self.id();
+        // the key set too, as the shares are verified by it
+        let key = (id.public_key(), id.public_key_set().ok());
+        self.verified
+            .verify(&key, sig, &data, || verify_uncached(&id, sig, &data))
+    }
+}
+
+fn verify_uncached(id: &OwnerType, sig: &sn_data_types::Signature, data: &[u8]) -> bool {
+    use sn_data_types::Signature::*;
+    match sig {
+        Bls(sig) => {
+            if let OwnerType::Multi(set) = id {
+                set.public_key().verify(&sig, data)
+            } else {
+                false
             }
-            Ed25519(_) => {
-                if let OwnerType::Single(public_key) = self.id() {
-                    public_key.verify(sig, data).is_ok()
-                } else {
-                    false
-                }
+        }
+        Ed25519(_) => {
+            if let OwnerType::Single(public_key) = id {
+                public_key.verify(sig, data).is_ok()
+            } else {
+                false
             }
-            BlsShare(share) => {
-                if let OwnerType::Multi(set) = self.id() {
-                    let pubkey_share = set.public_key_share(share.index);
-                    pubkey_share.verify(&share.share, data)
-                } else {
-                    false
-                }
+        }
+        BlsShare(share) => {
+            if let OwnerType::Multi(set) = id {
+                let pubkey_share = set.public_key_share(share.index);
+                pubkey_share.verify(&share.share, data)
+            } else {
+                false
             }
         }
     }
@@ -0,0 +1,227 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    utils, Error, Result,
+};
+use bls::PublicKeySet;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{CreditAgreementProof, NodeAge, PublicKey, SignatureShare};
+use sn_messaging::MessageId;
+use std::collections::{BTreeMap, BTreeSet};
+use xor_name::{Prefix, XorName};
+
+// The max number of payments a single part of an export carries, to stay well within the max msg size.
+const MAX_EXPORT_PAYMENTS: usize = 1_000;
+// The exports accumulating shares at most at a time, e.g. those of Elders exporting in turn.
+const MAX_PENDING_EXPORTS: usize = 100;
+
+/// A part of the reward wallets and pending payments of a section, exported for other nodes
+/// to take them over, e.g. those of a coordinated network upgrade. Each Elder exporting signs
+/// the parts with its share of the section key, and a part is imported once signed by enough
+/// of them, so that no single Elder can have other wallets or payments imported.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FundsExport {
+    /// The key set of the section exporting, its shares verified against.
+    pub key_set: PublicKeySet,
+    /// The prefix of the section exporting.
+    pub prefix: Prefix,
+    /// The index of the part, of the number of them.
+    pub part: (usize, usize),
+    /// The reward wallets of the nodes of the section, by their name, carried by every part.
+    pub wallets: BTreeMap<XorName, (NodeAge, PublicKey)>,
+    /// The payments received by the section since last churn, in the order of their id.
+    pub payments: Vec<CreditAgreementProof>,
+}
+
+impl FundsExport {
+    /// The parts exporting the wallets and payments, of `MAX_EXPORT_PAYMENTS` payments at most,
+    /// the same at all Elders exporting the same wallets and payments.
+    pub(crate) fn parts(
+        key_set: PublicKeySet,
+        prefix: Prefix,
+        wallets: BTreeMap<XorName, (NodeAge, PublicKey)>,
+        mut payments: Vec<CreditAgreementProof>,
+    ) -> Vec<Self> {
+        payments.sort_by_key(|credit| *credit.id());
+        let batches: Vec<_> = if payments.is_empty() {
+            vec![vec![]]
+        } else {
+            payments
+                .chunks(MAX_EXPORT_PAYMENTS)
+                .map(|batch| batch.to_vec())
+                .collect()
+        };
+        let count = batches.len();
+        batches
+            .into_iter()
+            .enumerate()
+            .map(|(index, payments)| Self {
+                key_set: key_set.clone(),
+                prefix,
+                part: (index, count),
+                wallets: wallets.clone(),
+                payments,
+            })
+            .collect()
+    }
+
+    /// Verifies that each payment was signed by the replicas debiting it.
+    pub(crate) fn validate(&self) -> Result<()> {
+        for credit in &self.payments {
+            let bytes = utils::serialise(&credit.signed_credit)?;
+            let key = PublicKey::Bls(credit.debiting_replicas_keys.public_key());
+            if key.verify(&credit.debiting_replicas_sig, &bytes).is_err() {
+                return Err(Error::InvalidFundsExport(format!(
+                    "payment {:?} not signed by its debiting replicas",
+                    credit.id()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> Result<MessageId> {
+        Ok(MessageId::from_content(self)?)
+    }
+}
+
+/// A part of an export, signed by an Elder of the section exporting.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignedFundsExport {
+    /// The part.
+    pub export: FundsExport,
+    /// The share of the Elder, of the signature of the section over the part.
+    pub share: SignatureShare,
+}
+
+impl SignedFundsExport {
+    /// The msg sending the part to the nodes.
+    pub(crate) fn msg(&self, targets: BTreeSet<XorName>) -> NodeDuty {
+        NodeDuty::SendNodeMsgToNodes {
+            targets,
+            msg: NodeMsg::new(NodeMsgBody::SignedFundsExport(self.clone())),
+        }
+    }
+
+    fn verify_share(&self) -> Result<bool> {
+        Ok(self
+            .export
+            .key_set
+            .public_key_share(self.share.index)
+            .verify(&self.share.share, utils::serialise(&self.export)?))
+    }
+}
+
+/// The shares received of the parts of exports, until enough of them are to import a part.
+#[derive(Default)]
+pub struct FundsImports {
+    pending: BTreeMap<MessageId, (FundsExport, BTreeMap<usize, bls::SignatureShare>)>,
+}
+
+impl FundsImports {
+    /// Adds the share of the part, once verified, returning the part once signed by the section,
+    /// i.e. with more shares than the threshold of its key set, and dropping it from those pending.
+    pub(crate) fn add(&mut self, signed: SignedFundsExport) -> Result<Option<FundsExport>> {
+        if !signed.verify_share()? {
+            return Err(Error::InvalidFundsExport(format!(
+                "share {} does not sign the export",
+                signed.share.index
+            )));
+        }
+        let id = signed.export.id()?;
+        if !self.pending.contains_key(&id) && self.pending.len() >= MAX_PENDING_EXPORTS {
+            let _ = self.pending.pop_first();
+        }
+        let SignedFundsExport { export, share } = signed;
+        let (export, shares) = self
+            .pending
+            .entry(id)
+            .or_insert_with(|| (export, BTreeMap::new()));
+        let _ = shares.insert(share.index, share.share);
+        if shares.len() <= export.key_set.threshold() {
+            return Ok(None);
+        }
+        let signature = export
+            .key_set
+            .combine_signatures(shares.iter().map(|(index, share)| (*index, share)))
+            .map_err(|e| Error::InvalidFundsExport(e.to_string()))?;
+        if !export
+            .key_set
+            .public_key()
+            .verify(&signature, utils::serialise(export)?)
+        {
+            return Err(Error::InvalidFundsExport(
+                "shares do not combine into a signature of the section".to_string(),
+            ));
+        }
+        Ok(self.pending.remove(&id).map(|(export, _)| export))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKeySet;
+
+    fn signed(
+        secret_keys: &SecretKeySet,
+        index: usize,
+        export: &FundsExport,
+    ) -> Result<SignedFundsExport> {
+        let share = secret_keys
+            .secret_key_share(index)
+            .sign(utils::serialise(export)?);
+        Ok(SignedFundsExport {
+            export: export.clone(),
+            share: SignatureShare { index, share },
+        })
+    }
+
+    #[test]
+    fn part_is_imported_once_signed_by_enough_elders() -> Result<()> {
+        let secret_keys = SecretKeySet::random(2, &mut rand::thread_rng());
+        let wallet = PublicKey::Bls(bls::SecretKey::random().public_key());
+        let wallets = vec![(XorName::random(), (5, wallet))].into_iter().collect();
+        let mut parts = FundsExport::parts(
+            secret_keys.public_keys(),
+            Prefix::default(),
+            wallets,
+            vec![],
+        );
+        assert_eq!(parts.len(), 1);
+        let export = parts.remove(0);
+        export.validate()?;
+
+        let mut imports = FundsImports::default();
+        // a share not of the section is refused
+        let mut forged = signed(
+            &SecretKeySet::random(2, &mut rand::thread_rng()),
+            0,
+            &export,
+        )?;
+        assert!(imports.add(forged.clone()).is_err());
+        // and shares of the same Elder count as one
+        assert_eq!(imports.add(signed(&secret_keys, 0, &export)?)?, None);
+        assert_eq!(imports.add(signed(&secret_keys, 0, &export)?)?, None);
+        assert_eq!(imports.add(signed(&secret_keys, 1, &export)?)?, None);
+        // a part changed by an Elder is not signed by the others
+        forged = signed(&secret_keys, 2, &export)?;
+        forged.export.wallets.clear();
+        assert!(imports.add(forged).is_err());
+        assert_eq!(
+            imports.add(signed(&secret_keys, 2, &export)?)?,
+            Some(export.clone())
+        );
+        // and it is imported once
+        assert_eq!(imports.add(signed(&secret_keys, 3, &export)?)?, None);
+        Ok(())
+    }
+}
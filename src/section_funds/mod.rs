@@ -7,15 +7,25 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 pub mod elder_signing;
+mod funds_export;
+mod payment_handover;
+mod payments;
 mod reward_calc;
 pub mod reward_process;
 pub mod reward_stage;
 pub mod reward_wallets;
 
-use self::{reward_process::RewardProcess, reward_wallets::RewardWallets};
+pub(crate) use self::reward_calc::treasury_share;
+pub use self::{
+    funds_export::{FundsExport, FundsImports, SignedFundsExport},
+    payment_handover::PaymentHandover,
+    payments::Payments,
+};
+use self::{
+    reward_process::RewardProcess, reward_stage::RewardStatus, reward_wallets::RewardWallets,
+};
 use super::node_ops::{NodeDuty, OutgoingMsg};
-use crate::Result;
-use dashmap::DashMap;
+use crate::{utils, Error, Result};
 use sn_data_types::{CreditAgreementProof, CreditId, NodeAge, PublicKey, SectionElders, Token};
 use sn_messaging::{
     client::{Message, NodeQuery, NodeSystemQuery},
@@ -40,16 +50,39 @@ pub enum SectionFunds {
 }
 
 impl SectionFunds {
-    /// Adds payment
-    pub fn add_payment(&self, credit: CreditAgreementProof) {
-        // todo: validate
+    /// Adds the payment, if signed by the replicas debiting it.
+    pub fn add_payment(&self, credit: CreditAgreementProof) -> Result<()> {
+        let bytes = utils::serialise(&credit.signed_credit)?;
+        let key = PublicKey::Bls(credit.debiting_replicas_keys.public_key());
+        if key.verify(&credit.debiting_replicas_sig, &bytes).is_err() {
+            return Err(Error::InvalidPropagatedTransfer(credit));
+        }
+        match &self {
+            Self::Churning { payments, .. } | Self::KeepingNodeWallets { payments, .. } => {
+                payments.insert(credit);
+            }
+        }
+        Ok(())
+    }
+
+    /// All payments received since last churn, reading archived ones back from disk.
+    pub fn payments(&self) -> Result<Vec<CreditAgreementProof>> {
         match &self {
             Self::Churning { payments, .. } | Self::KeepingNodeWallets { payments, .. } => {
-                let _ = payments.insert(*credit.id(), credit);
+                payments.all()
             }
         }
     }
 
+    /// The progress of the reward process, with the Elders
+    /// given in the order of their key share index.
+    pub fn reward_status(&self, elders: &[XorName]) -> RewardStatus {
+        match &self {
+            Self::Churning { process, .. } => process.stage().status(elders),
+            Self::KeepingNodeWallets { .. } => RewardStatus::not_churning(),
+        }
+    }
+
     /// Returns registered wallet key of a node.
     pub fn get_node_wallet(&self, node_name: &XorName) -> Option<PublicKey> {
         match &self {
@@ -89,19 +122,12 @@ impl SectionFunds {
     }
 }
 
-type Payments = DashMap<CreditId, CreditAgreementProof>;
 type Rewards = BTreeMap<CreditId, CreditAgreementProof>;
 
 pub trait Credits {
     fn sum(&self) -> Token;
 }
 
-impl Credits for Payments {
-    fn sum(&self) -> Token {
-        Token::from_nano(self.iter().map(|c| (*c).amount().as_nano()).sum())
-    }
-}
-
 impl Credits for Rewards {
     fn sum(&self) -> Token {
         Token::from_nano(self.iter().map(|(_, c)| c.amount().as_nano()).sum())
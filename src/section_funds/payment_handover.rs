@@ -0,0 +1,69 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::Payments;
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use sn_data_types::CreditAgreementProof;
+use sn_messaging::DstLocation;
+use std::collections::BTreeSet;
+use xor_name::{Prefix, XorName};
+
+// The max number of payments a single handover carries, to stay well within the max msg size.
+const MAX_HANDOVER_BATCH: usize = 1_000;
+
+/// The payments received by us within the prefix of our sibling since last churn,
+/// split off at split, and handed over to its Elders in bulk, to be paid out by it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaymentHandover {
+    /// The prefix of the sibling the payments are handed over to.
+    pub prefix: Prefix,
+    /// The payments.
+    pub payments: Vec<CreditAgreementProof>,
+}
+
+impl PaymentHandover {
+    /// The msgs handing over the staged payments, in batches of at most
+    /// `MAX_HANDOVER_BATCH`, to the Elders given, or else to the section.
+    pub(crate) fn msgs(
+        staged: &Payments,
+        prefix: Prefix,
+        elders: BTreeSet<XorName>,
+    ) -> Result<Vec<NodeDuty>> {
+        let payments = staged.all()?;
+        let mut duties = vec![];
+        for batch in payments.chunks(MAX_HANDOVER_BATCH) {
+            let handover = Self {
+                prefix,
+                payments: batch.to_vec(),
+            };
+            duties.push(handover.msg(&elders)?);
+        }
+        Ok(duties)
+    }
+
+    /// The msg handing over the payments. Its id is derived from the handover, so that
+    /// the msgs of all Elders handing over the same payments are deduplicated at destination.
+    fn msg(&self, elders: &BTreeSet<XorName>) -> Result<NodeDuty> {
+        let msg = NodeMsg::derived(NodeMsgBody::PaymentHandover(self.clone()))?;
+        if elders.is_empty() {
+            return Ok(NodeDuty::SendNodeMsg {
+                msg,
+                dst: DstLocation::Section(self.prefix.name()),
+            });
+        }
+        Ok(NodeDuty::SendNodeMsgToNodes {
+            targets: elders.clone(),
+            msg,
+        })
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::Credits;
+use crate::{to_db_key::ToDbKey, utils, Result};
+use dashmap::DashMap;
+use log::{debug, warn};
+use sn_data_types::{CreditAgreementProof, CreditId, Token};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use xor_name::{Prefix, XorName};
+
+const ARCHIVE_DIR: &str = "section_payments";
+const STAGED_ARCHIVE_DIR: &str = "sibling_payments";
+/// Max number of payment proofs kept in memory between churns.
+const MAX_PAYMENTS_IN_MEMORY: usize = 10_000;
+/// Payment proofs older than this are archived.
+const MAX_PAYMENT_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// The payments received by the section since last churn.
+/// To bound memory on busy sections, old payment proofs
+/// are archived to disk, where they are still counted in the totals.
+pub struct Payments {
+    recent: DashMap<CreditId, (CreditAgreementProof, Instant)>,
+    archive: PathBuf,
+    archived_count: AtomicU64,
+    archived_nanos: AtomicU64,
+    max_in_memory: usize,
+    max_age: Duration,
+}
+
+impl Payments {
+    /// A new, empty set of payments, archived under the root dir.
+    /// Any payments archived before are discarded.
+    pub fn new(root_dir: &Path) -> Self {
+        Self::with_limits(
+            root_dir.join(ARCHIVE_DIR),
+            MAX_PAYMENTS_IN_MEMORY,
+            MAX_PAYMENT_AGE,
+        )
+    }
+
+    fn with_limits(archive: PathBuf, max_in_memory: usize, max_age: Duration) -> Self {
+        if archive.exists() {
+            if let Err(error) = fs::remove_dir_all(&archive) {
+                warn!("Could not clear payment archive: {}", error);
+            }
+        }
+        Self {
+            recent: Default::default(),
+            archive,
+            archived_count: AtomicU64::new(0),
+            archived_nanos: AtomicU64::new(0),
+            max_in_memory,
+            max_age,
+        }
+    }
+
+    /// Adds a payment, archiving old ones if limits are exceeded.
+    pub fn insert(&self, credit: CreditAgreementProof) {
+        let id = *credit.id();
+        if self.recent.contains_key(&id) || self.archive_path(&id).is_ok_and(|p| p.exists()) {
+            return;
+        }
+        let _ = self.recent.insert(id, (credit, Instant::now()));
+        self.prune();
+    }
+
+    /// Number of payments, including archived ones.
+    pub fn len(&self) -> usize {
+        self.recent.len() + self.archived_count.load(Ordering::SeqCst) as usize
+    }
+
+    /// Returns true if no payments have been received.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of payments archived to disk.
+    pub fn archived(&self) -> usize {
+        self.archived_count.load(Ordering::SeqCst) as usize
+    }
+
+    /// Returns all payment proofs, reading archived ones back from disk.
+    pub fn all(&self) -> Result<Vec<CreditAgreementProof>> {
+        let mut proofs: Vec<_> = self
+            .recent
+            .iter()
+            .map(|entry| entry.value().0.clone())
+            .collect();
+        if self.archived() > 0 {
+            for entry in fs::read_dir(&self.archive)? {
+                let bytes = fs::read(entry?.path())?;
+                proofs.push(utils::deserialise(&bytes)?);
+            }
+        }
+        Ok(proofs)
+    }
+
+    /// Moves the payments within the prefix off into a separate store, archived under
+    /// the root dir, e.g. those of our sibling at split, for it to take them over.
+    /// Payments are placed by the name of their credit id.
+    /// Any payments staged before are discarded.
+    pub fn split_off(&self, prefix: &Prefix, root_dir: &Path) -> Result<Self> {
+        let staged = Self::with_limits(
+            root_dir.join(STAGED_ARCHIVE_DIR),
+            self.max_in_memory,
+            self.max_age,
+        );
+        let ids: Vec<_> = self
+            .recent
+            .iter()
+            .map(|entry| *entry.key())
+            .filter(|id| prefix.matches(&XorName(*id)))
+            .collect();
+        for id in ids {
+            if let Some((id, payment)) = self.recent.remove(&id) {
+                let _ = staged.recent.insert(id, payment);
+            }
+        }
+        if self.archived() > 0 {
+            for entry in fs::read_dir(&self.archive)? {
+                let path = entry?.path();
+                let credit: CreditAgreementProof = utils::deserialise(&fs::read(&path)?)?;
+                if !prefix.matches(&XorName(*credit.id())) {
+                    continue;
+                }
+                staged.write_archive(&credit)?;
+                fs::remove_file(&path)?;
+                let _ = self.archived_count.fetch_sub(1, Ordering::SeqCst);
+                let _ = self
+                    .archived_nanos
+                    .fetch_sub(credit.amount().as_nano(), Ordering::SeqCst);
+            }
+        }
+        staged.prune();
+        Ok(staged)
+    }
+
+    /// Archives payments older than max age, and then
+    /// the oldest ones until within max number in memory.
+    fn prune(&self) {
+        let mut by_age: Vec<_> = self
+            .recent
+            .iter()
+            .map(|entry| (entry.value().1, *entry.key()))
+            .collect();
+        by_age.sort();
+        let excess = by_age.len().saturating_sub(self.max_in_memory);
+        let to_archive = by_age
+            .into_iter()
+            .enumerate()
+            .take_while(|(i, (received, _))| *i < excess || received.elapsed() > self.max_age)
+            .map(|(_, (_, id))| id);
+        for id in to_archive {
+            if let Err(error) = self.archive_payment(&id) {
+                warn!("Could not archive payment {:?}: {}", id, error);
+                // keep it in memory, and retry at next prune
+                break;
+            }
+        }
+    }
+
+    fn archive_payment(&self, id: &CreditId) -> Result<()> {
+        let (credit, _) = match self.recent.get(id) {
+            Some(entry) => entry.value().clone(),
+            None => return Ok(()),
+        };
+        self.write_archive(&credit)?;
+        let _ = self.recent.remove(id);
+        debug!("Archived payment {:?}", id);
+        Ok(())
+    }
+
+    fn write_archive(&self, credit: &CreditAgreementProof) -> Result<()> {
+        fs::create_dir_all(&self.archive)?;
+        fs::write(self.archive_path(credit.id())?, utils::serialise(credit)?)?;
+        let _ = self.archived_count.fetch_add(1, Ordering::SeqCst);
+        let _ = self
+            .archived_nanos
+            .fetch_add(credit.amount().as_nano(), Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn archive_path(&self, id: &CreditId) -> Result<PathBuf> {
+        Ok(self.archive.join(id.to_db_key()?))
+    }
+}
+
+impl Clone for Payments {
+    fn clone(&self) -> Self {
+        Self {
+            recent: self.recent.clone(),
+            archive: self.archive.clone(),
+            archived_count: AtomicU64::new(self.archived_count.load(Ordering::SeqCst)),
+            archived_nanos: AtomicU64::new(self.archived_nanos.load(Ordering::SeqCst)),
+            max_in_memory: self.max_in_memory,
+            max_age: self.max_age,
+        }
+    }
+}
+
+impl Credits for Payments {
+    fn sum(&self) -> Token {
+        let recent: u64 = self
+            .recent
+            .iter()
+            .map(|entry| entry.value().0.amount().as_nano())
+            .sum();
+        Token::from_nano(recent + self.archived_nanos.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transfers::test_utils::get_random_genesis;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+    use tempdir::TempDir;
+
+    #[test]
+    fn archived_payments_are_counted_and_rehydrated() -> Result<()> {
+        let root = TempDir::new("payments")?;
+        let payments = Payments::with_limits(root.path().join(ARCHIVE_DIR), 2, MAX_PAYMENT_AGE);
+        for i in 0..5 {
+            payments.insert(get_credit_proof(i + 1)?);
+        }
+        assert_eq!(payments.len(), 5);
+        assert_eq!(payments.archived(), 3);
+        assert_eq!(payments.sum(), Token::from_nano(15));
+        assert_eq!(payments.all()?.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn payments_within_prefix_are_split_off() -> Result<()> {
+        let root = TempDir::new("payments")?;
+        let payments = Payments::with_limits(root.path().join(ARCHIVE_DIR), 2, MAX_PAYMENT_AGE);
+        let sibling = Prefix::default().pushed(true);
+        for i in 0..6 {
+            let mut proof = get_credit_proof(i + 1)?;
+            // every other one within the sibling
+            let name = Prefix::default()
+                .pushed(i % 2 == 0)
+                .substituted_in(XorName(proof.signed_credit.credit.id));
+            proof.signed_credit.credit.id = name.0;
+            payments.insert(proof);
+        }
+        let staged = payments.split_off(&sibling, root.path())?;
+        assert_eq!(payments.len(), 3);
+        assert_eq!(staged.len(), 3);
+        assert_eq!(payments.sum(), Token::from_nano(2 + 4 + 6));
+        assert_eq!(staged.sum(), Token::from_nano(1 + 3 + 5));
+        assert!(staged
+            .all()?
+            .iter()
+            .all(|proof| sibling.matches(&XorName(*proof.id()))));
+        Ok(())
+    }
+
+    fn get_credit_proof(amount: u64) -> Result<CreditAgreementProof> {
+        let recipient = PublicKey::from(SecretKey::random().public_key());
+        let mut proof = get_random_genesis(amount, recipient)?;
+        proof.signed_credit.credit.id = XorName::random().0;
+        Ok(proof)
+    }
+}
@@ -6,18 +6,16 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::capacity::MAX_SUPPLY;
+use crate::{Error, Result};
 use log::debug;
 use sn_data_types::{NodeAge, PublicKey, Token};
 use sn_routing::{Prefix, XorName};
 use std::collections::{BTreeMap, BTreeSet};
 
-const MIN_REWARD_AGE: u8 = 6;
-
 ///  -----  MINTING  -----
 /// This is the minting of new coins happening;
-/// the size being the sum of payments to parent section,
-/// i.e. it at most doubles the amount paid into section,
+/// the size being the mint percentage of the payments to parent section,
+/// i.e. by default it at most doubles the amount paid into section,
 /// or else what's left until we've reached max supply.
 /// Max supply is the proportional supply for a section in
 /// a network of a certain size, i.e. max _total_ supply (2^32) divided by number of sections.
@@ -25,13 +23,16 @@ pub fn get_reward_and_mint_amount(
     payments: Token,
     section_managed: Token,
     max_supply: Token,
+    mint_percentage: u64,
 ) -> Token {
     let payments = payments.as_nano();
     let section_managed = section_managed.as_nano();
     let max_supply = max_supply.as_nano();
     let reward_nanos = if max_supply > section_managed {
-        // an amount at most equal to `payments` will be minted
-        let to_be_minted = u64::min(payments, max_supply - section_managed);
+        // an amount at most the mint percentage of `payments` will be minted
+        let mintable =
+            (payments as u128 * mint_percentage as u128 / 100).min(u64::MAX as u128) as u64;
+        let to_be_minted = u64::min(mintable, max_supply - section_managed);
         // reward = payments + newly minted tokens
         payments + to_be_minted
     } else {
@@ -49,23 +50,79 @@ pub fn get_reward_and_mint_amount(
     Token::from_nano(reward_nanos)
 }
 
+/// The part of a payout retained by the section, given
+/// as a percentage (capped at 100) of the total amount.
+pub fn treasury_share(amount: Token, percentage: u8) -> Token {
+    let percentage = u64::from(percentage.min(100));
+    Token::from_nano((amount.as_nano() as u128 * percentage as u128 / 100) as u64)
+}
+
+/// The books of a reward payout, checked before proposing it,
+/// so that a payout is never only partly credited mid-split.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PayoutBooks {
+    /// Balance of the section wallet paying out.
+    pub balance: Token,
+    /// Newly minted tokens included in the payout, not drawn from the wallet.
+    pub minted: Token,
+    /// Credits to nodes staying in our section.
+    pub our_payouts: Token,
+    /// Credits to nodes going to the sibling section, on split.
+    pub sibling_payouts: Token,
+}
+
+impl PayoutBooks {
+    /// Ok if the wallet balance covers all credits, but for those minted.
+    pub fn validate(&self) -> Result<()> {
+        let credited = self.our_payouts.as_nano() as u128 + self.sibling_payouts.as_nano() as u128;
+        let required = credited.saturating_sub(self.minted.as_nano() as u128);
+        if required <= self.balance.as_nano() as u128 {
+            return Ok(());
+        }
+        Err(Error::PayoutNotCovered(format!(
+            "wallet balance {} is short of {} nanos, for {} to our nodes and {} to sibling nodes, of which {} minted",
+            self.balance,
+            required - self.balance.as_nano() as u128,
+            self.our_payouts,
+            self.sibling_payouts,
+            self.minted
+        )))
+    }
+}
+
 /// Calculates reward for each public key
 /// proportional to the age of its node,
 /// out of the total amount supplied.
+/// Nodes below `min_age` are not rewarded.
 pub fn distribute_rewards(
     amount: Token,
     nodes: BTreeMap<XorName, (NodeAge, PublicKey)>,
+    min_age: NodeAge,
 ) -> BTreeMap<XorName, (NodeAge, PublicKey, Token)> {
-    let reward_buckets = get_buckets(nodes);
+    let reward_buckets = get_buckets(nodes, min_age);
     distribute(amount, reward_buckets)
 }
 
+/// Returns the nodes (and their age) which are
+/// too young to take part in reward distribution.
+pub fn excluded_from_rewards(
+    nodes: &BTreeMap<XorName, (NodeAge, PublicKey)>,
+    min_age: NodeAge,
+) -> BTreeMap<XorName, NodeAge> {
+    nodes
+        .iter()
+        .filter(|(_, (age, _))| *age < min_age)
+        .map(|(name, (age, _))| (*name, *age))
+        .collect()
+}
+
 fn get_buckets(
     nodes: BTreeMap<XorName, (NodeAge, PublicKey)>,
+    min_age: NodeAge,
 ) -> BTreeMap<NodeAge, BTreeMap<XorName, PublicKey>> {
     let mut reward_buckets = BTreeMap::new();
     for (node_name, (age, wallet)) in nodes {
-        if age >= MIN_REWARD_AGE {
+        if age >= min_age {
             let _ = reward_buckets
                 .entry(age)
                 .or_insert_with(BTreeMap::new)
@@ -152,6 +209,54 @@ mod test {
 
     use super::*;
 
+    const MIN_AGE: NodeAge = 6;
+
+    #[test]
+    fn nodes_below_min_age_are_not_rewarded() {
+        let amount = Token::from_nano(1_000_000_000);
+        let mut nodes = BTreeMap::<XorName, (NodeAge, PublicKey)>::new();
+        let infant = XorName::random();
+        let adult = XorName::random();
+        let _ = nodes.insert(infant, (MIN_AGE - 1, get_random_pk()));
+        let _ = nodes.insert(adult, (MIN_AGE, get_random_pk()));
+
+        let excluded = excluded_from_rewards(&nodes, MIN_AGE);
+        assert_eq!(excluded.len(), 1);
+        assert_eq!(excluded.get(&infant), Some(&(MIN_AGE - 1)));
+
+        let rewards = distribute_rewards(amount, nodes, MIN_AGE);
+        assert!(!rewards.contains_key(&infant));
+        assert_eq!(
+            rewards.get(&adult).map(|(_, _, amount)| *amount),
+            Some(amount)
+        );
+    }
+
+    #[test]
+    fn treasury_retains_percentage_of_payout() {
+        let amount = Token::from_nano(1_000);
+        assert_eq!(treasury_share(amount, 0), Token::zero());
+        assert_eq!(treasury_share(amount, 15), Token::from_nano(150));
+        assert_eq!(treasury_share(amount, 100), amount);
+        assert_eq!(treasury_share(amount, 200), amount);
+        let max = Token::from_nano(u64::MAX);
+        assert_eq!(treasury_share(max, 100), max);
+    }
+
+    #[test]
+    fn minting_is_the_mint_percentage_of_payments_up_to_max_supply() {
+        let payments = Token::from_nano(1_000);
+        let max_supply = Token::from_nano(10_000);
+        let reward = |managed, percentage| {
+            get_reward_and_mint_amount(payments, Token::from_nano(managed), max_supply, percentage)
+                .as_nano()
+        };
+        assert_eq!(reward(0, 100), 2_000);
+        assert_eq!(reward(0, 50), 1_500);
+        assert_eq!(reward(0, 0), 1_000);
+        assert_eq!(reward(9_800, 100), 1_200);
+    }
+
     #[test]
     fn calculates_reward_distribution() {
         // setup
@@ -162,9 +267,9 @@ mod test {
         let iters = 10;
         let mut nodes = BTreeMap::<XorName, (NodeAge, PublicKey)>::new();
         for i in 0..iters {
-            let _ = nodes.insert(XorName::random(), (i + MIN_REWARD_AGE - 1, get_random_pk()));
-            let _ = nodes.insert(XorName::random(), (i + MIN_REWARD_AGE, get_random_pk()));
-            let _ = nodes.insert(XorName::random(), (i + MIN_REWARD_AGE, get_random_pk()));
+            let _ = nodes.insert(XorName::random(), (i + MIN_AGE - 1, get_random_pk()));
+            let _ = nodes.insert(XorName::random(), (i + MIN_AGE, get_random_pk()));
+            let _ = nodes.insert(XorName::random(), (i + MIN_AGE, get_random_pk()));
         }
 
         println!("Added {} nodes", nodes.len());
@@ -173,7 +278,7 @@ mod test {
         let now = std::time::Instant::now();
 
         // calc
-        let rewards = distribute_rewards(amount, nodes);
+        let rewards = distribute_rewards(amount, nodes, MIN_AGE);
 
         // stop timer
         let duration = now.elapsed();
@@ -194,6 +299,25 @@ mod test {
         println!("Total rewards: {:?}", total);
     }
 
+    #[test]
+    fn payout_must_be_covered_by_wallet_but_for_minted() {
+        let mut books = PayoutBooks {
+            balance: Token::from_nano(100),
+            minted: Token::from_nano(50),
+            our_payouts: Token::from_nano(90),
+            sibling_payouts: Token::from_nano(60),
+        };
+        assert!(books.validate().is_ok());
+
+        books.minted = Token::zero();
+        assert!(matches!(books.validate(), Err(Error::PayoutNotCovered(_))));
+
+        // minted tokens exceeding the credits need no balance
+        books.balance = Token::zero();
+        books.minted = Token::from_nano(200);
+        assert!(books.validate().is_ok());
+    }
+
     fn get_random_pk() -> PublicKey {
         PublicKey::from(bls::SecretKey::random().public_key())
     }
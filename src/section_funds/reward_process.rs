@@ -8,16 +8,19 @@
 
 use super::{
     elder_signing::ElderSigning,
-    reward_calc::{distribute_rewards, get_reward_and_mint_amount},
+    reward_calc::{
+        distribute_rewards, excluded_from_rewards, get_reward_and_mint_amount, treasury_share,
+        PayoutBooks,
+    },
     reward_stage::{
         CreditAccumulation, CreditProposal, RewardAccumulationDetails, RewardProposalDetails,
         RewardStage,
     },
 };
 use crate::{
-    capacity::MAX_SUPPLY,
+    node::SectionParams,
     node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
-    Error, Result,
+    utils, Error, Result,
 };
 use log::{debug, info, warn};
 use sn_data_types::{
@@ -28,6 +31,7 @@ use sn_messaging::{
     client::{Message, NodeCmd, NodeQuery, NodeSystemCmd, NodeSystemQuery},
     Aggregation, DstLocation, MessageId,
 };
+use sn_routing::SectionChain;
 use std::collections::{BTreeMap, BTreeSet};
 use xor_name::{Prefix, XorName};
 
@@ -37,6 +41,7 @@ pub struct RewardProcess {
     section: OurSection,
     stage: RewardStage,
     signing: ElderSigning,
+    params: SectionParams,
 }
 
 ///
@@ -61,11 +66,12 @@ impl OurSection {
 }
 
 impl RewardProcess {
-    pub fn new(section: OurSection, signing: ElderSigning) -> Self {
+    pub fn new(section: OurSection, signing: ElderSigning, params: SectionParams) -> Self {
         Self {
             section,
             signing,
             stage: RewardStage::AwaitingThreshold,
+            params,
         }
     }
 
@@ -77,20 +83,55 @@ impl RewardProcess {
     /// proportional to the age of it,
     /// out of the total payments received.
     /// Additionally adds newly minted tokens, unless max supply has been reached.
+    /// The treasury percentage of it is retained in the section wallet.
     pub async fn reward_and_mint(
         &mut self,
         payments: Token,
         section_managed: Token,
+        section_balance: Token,
         our_nodes: BTreeMap<XorName, (NodeAge, PublicKey)>,
     ) -> Result<NodeDuty> {
         /// Max supply is the proportional supply for a section in a network of a certain size.
         /// The network size is derived from the prefix len.
-        let max_supply =
-            Token::from_nano(MAX_SUPPLY / 2_u64.pow(self.section.our_prefix.bit_count() as u32));
+        let max_supply = Token::from_nano(
+            self.params
+                .economy
+                .max_section_nanos(self.section.our_prefix.bit_count()),
+        );
         // derive an amount to pay out in rewards, i.e. payments + newly minted tokens
-        let rewards = get_reward_and_mint_amount(payments, section_managed, max_supply);
+        let total = get_reward_and_mint_amount(
+            payments,
+            section_managed,
+            max_supply,
+            self.params.economy.mint_percentage,
+        );
+        let (rewards, retained) = split_payout(total, &self.params);
+        info!(
+            "Reward distribution: {} in total, {} to nodes, {} retained by section treasury ({}%)",
+            total, rewards, retained, self.params.treasury_percentage
+        );
+        // nodes not matching our prefix are going to the sibling
+        let sibling_wallets: BTreeSet<PublicKey> = our_nodes
+            .iter()
+            .filter(|(name, _)| !self.section.our_prefix.matches(name))
+            .map(|(_, (_, wallet))| *wallet)
+            .collect();
         // generate proposal
         let reward_credits = self.get_reward_credits(rewards, self.section.our_key, our_nodes);
+
+        let (sibling_credits, our_credits): (Vec<_>, Vec<_>) = reward_credits
+            .iter()
+            .map(|credit| &credit.proposal)
+            .partition(|credit| sibling_wallets.contains(&credit.recipient));
+        let books = PayoutBooks {
+            balance: section_balance,
+            minted: Token::from_nano(total.as_nano().saturating_sub(payments.as_nano())),
+            our_payouts: sum_credits(&our_credits),
+            sibling_payouts: sum_credits(&sibling_credits),
+        };
+        debug!("Reward payout books: {:?}", books);
+        books.validate()?;
+
         let proposal_details = self.sign_proposed_rewards(reward_credits).await?;
         let proposal = proposal_details
             .get_proposal(self.section.wallet_key(), self.signing.our_index().await?);
@@ -147,8 +188,19 @@ impl RewardProcess {
         section_key: PublicKey,
         nodes: BTreeMap<XorName, (NodeAge, PublicKey)>,
     ) -> Vec<CreditProposal> {
+        let min_age = self.params.min_reward_age;
+        let excluded = excluded_from_rewards(&nodes, min_age);
+        if !excluded.is_empty() {
+            info!(
+                "Reward audit: {} of {} nodes below min reward age {} excluded from payout: {:?}",
+                excluded.len(),
+                nodes.len(),
+                min_age,
+                excluded
+            );
+        }
         // create reward distribution
-        distribute_rewards(rewards, nodes)
+        distribute_rewards(rewards, nodes, min_age)
             .into_iter()
             .map(|(node, (age, wallet, amount))| {
                 let id = MessageId::combine(vec![node, XorName::from(section_key)])
@@ -250,7 +302,7 @@ impl RewardProcess {
         new_acc: RewardAccumulation,
     ) -> Result<NodeDuty> {
         if new_acc.section_key != self.section.wallet_key() {
-            return Err(Error::Transfer(sn_transfers::Error::InvalidOwner));
+            return self.receive_previous_accumulation(new_acc).await;
         }
         match self.stage.clone() {
             RewardStage::AwaitingThreshold => {
@@ -328,6 +380,75 @@ impl RewardProcess {
             RewardStage::None => Err(Error::InvalidRewardStage),
         }
     }
+
+    /// Receives an accumulation by Elders still signing with the section key
+    /// preceding ours, as at churn not all Elders move on to our key at once.
+    /// The credits of it are validated against that key, and accumulated by us
+    /// with our key, but the signature shares of it, being of the previous Elders,
+    /// are not combined with those of ours.
+    async fn receive_previous_accumulation(
+        &mut self,
+        new_acc: RewardAccumulation,
+    ) -> Result<NodeDuty> {
+        let chain = self.signing.section_chain().await;
+        let (previous, ours) = match (new_acc.section_key.bls(), self.section.our_key.bls()) {
+            (Some(previous), Some(ours)) if is_previous_key(&chain, &previous, &ours) => {
+                (previous, ours)
+            }
+            _ => return Err(Error::Transfer(sn_transfers::Error::InvalidOwner)),
+        };
+        for reward in &new_acc.rewards {
+            let credit = &reward.signed_credit;
+            PublicKey::Bls(previous)
+                .verify(&credit.actor_signature, utils::serialise(&credit.credit)?)
+                .map_err(|_| Error::Transfer(sn_transfers::Error::InvalidSignature))?;
+        }
+        info!(
+            "Accumulating {} rewards proposed with the previous section key {:?}, with ours {:?}",
+            new_acc.rewards.len(),
+            previous,
+            ours
+        );
+        match self.stage.clone() {
+            RewardStage::AwaitingThreshold | RewardStage::ProposingCredits(_) => {
+                let rewards = new_acc
+                    .rewards
+                    .iter()
+                    .map(|reward| CreditAccumulation {
+                        agreed_proposal: reward.signed_credit.clone(),
+                        signatures: Default::default(),
+                        pending_agreement: None,
+                    })
+                    .collect();
+
+                let our_acc = self.sign_accumulating_rewards(rewards).await?;
+                let to_send = our_acc
+                    .get_accumulation(self.section.wallet_key(), self.signing.our_index().await?);
+
+                self.stage = RewardStage::AccumulatingCredits(our_acc);
+
+                Ok(send_acc_msg(to_send, self.section.address()))
+            }
+            // our shares are already out, with our key
+            RewardStage::AccumulatingCredits(_) | RewardStage::Completed(_) => Ok(NodeDuty::NoOp),
+            RewardStage::None => Err(Error::InvalidRewardStage),
+        }
+    }
+}
+
+/// Whether `key` is the section key right before `ours`, i.e. within one epoch of it,
+/// as proven by the chain of our section keys.
+fn is_previous_key(chain: &SectionChain, key: &bls::PublicKey, ours: &bls::PublicKey) -> bool {
+    match chain.minimize(vec![key, ours]) {
+        Ok(between) => {
+            between.len() == 2 && between.root_key() == key && between.last_key() == ours
+        }
+        Err(_) => false,
+    }
+}
+
+fn sum_credits(credits: &[&Credit]) -> Token {
+    Token::from_nano(credits.iter().map(|credit| credit.amount.as_nano()).sum())
 }
 
 fn send_prop_msg(proposal: RewardProposal, our_elders: XorName) -> NodeDuty {
@@ -355,3 +476,60 @@ fn send_acc_msg(accumulation: RewardAccumulation, our_elders: XorName) -> NodeDu
         aggregation: Aggregation::None,
     })
 }
+
+/// The rewards to nodes of the total of a payout, and the share of it
+/// retained in the section wallet, by the treasury percentage of our section.
+fn split_payout(total: Token, params: &SectionParams) -> (Token, Token) {
+    let retained = treasury_share(total, params.treasury_percentage);
+    (
+        Token::from_nano(total.as_nano() - retained.as_nano()),
+        retained,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+
+    #[test]
+    fn treasury_share_of_our_section_is_retained_of_payouts() {
+        let total = Token::from_nano(1_000);
+        let params = |treasury_percentage| SectionParams {
+            treasury_percentage,
+            ..Default::default()
+        };
+        assert_eq!(split_payout(total, &params(0)), (total, Token::zero()));
+        assert_eq!(
+            split_payout(total, &params(15)),
+            (Token::from_nano(850), Token::from_nano(150))
+        );
+        assert_eq!(split_payout(total, &params(100)), (Token::zero(), total));
+    }
+
+    #[test]
+    fn only_the_key_right_before_ours_is_previous() -> Result<()> {
+        let keys: Vec<_> = (0..3).map(|_| SecretKey::random()).collect();
+        let mut chain = SectionChain::new(keys[0].public_key());
+        for pair in keys.windows(2) {
+            let child = pair[1].public_key();
+            let signature = pair[0].sign(&utils::serialise(&child)?);
+            chain
+                .insert(&pair[0].public_key(), child, signature)
+                .map_err(|_| Error::Logic("Invalid chain".to_string()))?;
+        }
+        let key = |index: usize| keys[index].public_key();
+
+        assert!(is_previous_key(&chain, &key(1), &key(2)));
+        assert!(is_previous_key(&chain, &key(0), &key(1)));
+        // more than one epoch before ours
+        assert!(!is_previous_key(&chain, &key(0), &key(2)));
+        assert!(!is_previous_key(&chain, &key(2), &key(1)));
+        assert!(!is_previous_key(
+            &chain,
+            &SecretKey::random().public_key(),
+            &key(2)
+        ));
+        Ok(())
+    }
+}
@@ -8,11 +8,13 @@
 
 use crate::{Error, Result};
 use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use sn_data_types::{
     Credit, CreditAgreementProof, CreditId, PublicKey, ReplicaPublicKeySet, SignatureShare,
     SignedCredit, SignedCreditShare, Token, TransferPropagated,
 };
 use std::collections::{BTreeMap, BTreeSet};
+use xor_name::XorName;
 
 #[derive(Clone)]
 #[allow(clippy::large_enum_variant)]
@@ -24,6 +26,146 @@ pub enum RewardStage {
     Completed(BTreeMap<CreditId, CreditAgreementProof>),
 }
 
+/// The stage of the reward process, as reported for diagnosis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RewardStageKind {
+    /// The section is not churning, no rewards are paid out.
+    NotChurning,
+    /// The process has not been started.
+    None,
+    /// Awaiting the threshold of proposals by other Elders.
+    AwaitingThreshold,
+    /// Signing the proposed credits.
+    ProposingCredits,
+    /// Signing the agreed credits.
+    AccumulatingCredits,
+    /// All credits agreed.
+    Completed,
+}
+
+/// The progress of signing a reward credit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreditStatus {
+    /// Id of the credit.
+    pub id: CreditId,
+    /// The rewarded wallet.
+    pub recipient: PublicKey,
+    /// The amount rewarded.
+    pub amount: Token,
+    /// The Elders whose signature share has been received.
+    pub signed_by: BTreeSet<XorName>,
+    /// The Elders whose signature share has not been received.
+    pub missing: BTreeSet<XorName>,
+    /// Whether enough shares have been received to agree on the credit.
+    pub agreed: bool,
+}
+
+/// The progress of the reward process at an Elder, so that a stalled
+/// payout can be told apart by the Elders not having contributed to it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardStatus {
+    /// The stage of the process.
+    pub stage: RewardStageKind,
+    /// The number of signature shares needed to agree on a credit, if signing.
+    pub min_signatures: Option<usize>,
+    /// The credits of the stage.
+    pub credits: Vec<CreditStatus>,
+}
+
+impl RewardStatus {
+    /// The status when no rewards are paid out.
+    pub fn not_churning() -> Self {
+        Self {
+            stage: RewardStageKind::NotChurning,
+            min_signatures: None,
+            credits: vec![],
+        }
+    }
+
+    /// The status of the credits to the wallet alone, for the node rewarded to it.
+    pub fn of_recipient(mut self, wallet: &PublicKey) -> Self {
+        self.credits.retain(|credit| &credit.recipient == wallet);
+        self
+    }
+}
+
+impl RewardStage {
+    /// The progress of the stage. The Elders are given in
+    /// the order of their key share index.
+    pub fn status(&self, elders: &[XorName]) -> RewardStatus {
+        let (stage, min_signatures, credits) = match self {
+            Self::None => (RewardStageKind::None, None, vec![]),
+            Self::AwaitingThreshold => (RewardStageKind::AwaitingThreshold, None, vec![]),
+            Self::ProposingCredits(details) => (
+                RewardStageKind::ProposingCredits,
+                Some(details.pk_set.threshold() + 1),
+                details
+                    .rewards
+                    .values()
+                    .map(|credit| {
+                        credit_status(
+                            &credit.proposal,
+                            &credit.signatures,
+                            credit.pending_agreement.is_some(),
+                            elders,
+                        )
+                    })
+                    .collect(),
+            ),
+            Self::AccumulatingCredits(details) => (
+                RewardStageKind::AccumulatingCredits,
+                Some(details.pk_set.threshold() + 1),
+                details
+                    .rewards
+                    .values()
+                    .map(|credit| {
+                        credit_status(
+                            &credit.agreed_proposal.credit,
+                            &credit.signatures,
+                            credit.pending_agreement.is_some(),
+                            elders,
+                        )
+                    })
+                    .collect(),
+            ),
+            Self::Completed(proofs) => (
+                RewardStageKind::Completed,
+                None,
+                proofs
+                    .values()
+                    // the shares are no longer kept
+                    .map(|proof| credit_status(&proof.signed_credit.credit, &BTreeMap::new(), true, &[]))
+                    .collect(),
+            ),
+        };
+        RewardStatus {
+            stage,
+            min_signatures,
+            credits,
+        }
+    }
+}
+
+fn credit_status(
+    credit: &Credit,
+    signatures: &BTreeMap<usize, bls::SignatureShare>,
+    agreed: bool,
+    elders: &[XorName],
+) -> CreditStatus {
+    let (signed_by, missing) = elders
+        .iter()
+        .enumerate()
+        .partition::<Vec<_>, _>(|(index, _)| signatures.contains_key(index));
+    CreditStatus {
+        id: *credit.id(),
+        recipient: credit.recipient(),
+        amount: credit.amount(),
+        signed_by: signed_by.into_iter().map(|(_, name)| *name).collect(),
+        missing: missing.into_iter().map(|(_, name)| *name).collect(),
+        agreed,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RewardProposalDetails {
     pub pk_set: ReplicaPublicKeySet,
@@ -227,3 +369,55 @@ impl RewardAccumulationDetails {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKeySet;
+
+    #[test]
+    fn status_names_elders_yet_to_sign_credits() {
+        let secret_set = SecretKeySet::random(1, &mut rand::thread_rng());
+        let pk_set = secret_set.public_keys();
+        let credit = Credit {
+            id: Default::default(),
+            amount: Token::from_nano(10),
+            recipient: PublicKey::from(bls::SecretKey::random().public_key()),
+            msg: "reward".to_string(),
+        };
+        let mut signatures = BTreeMap::new();
+        let _ = signatures.insert(1, secret_set.secret_key_share(1).sign(b"credit"));
+        let stage = RewardStage::ProposingCredits(RewardProposalDetails {
+            pk_set,
+            rewards: vec![(
+                *credit.id(),
+                CreditProposal {
+                    proposal: credit,
+                    signatures,
+                    pending_agreement: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        });
+
+        let elders: Vec<_> = (0..3).map(|_| XorName::random()).collect();
+        let status = stage.status(&elders);
+        assert_eq!(status.stage, RewardStageKind::ProposingCredits);
+        assert_eq!(status.min_signatures, Some(2));
+        assert_eq!(status.credits.len(), 1);
+        let credit = &status.credits[0];
+        assert!(!credit.agreed);
+        assert_eq!(credit.signed_by, vec![elders[1]].into_iter().collect());
+        assert_eq!(
+            credit.missing,
+            vec![elders[0], elders[2]].into_iter().collect()
+        );
+
+        // other nodes are told of the credits to their own wallet alone
+        let recipient = credit.recipient;
+        assert_eq!(status.clone().of_recipient(&recipient).credits.len(), 1);
+        let other = PublicKey::from(bls::SecretKey::random().public_key());
+        assert!(status.of_recipient(&other).credits.is_empty());
+    }
+}
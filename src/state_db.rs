@@ -8,6 +8,7 @@
 
 use crate::{utils, Error, Result};
 use bls::{self, serde_impl::SerdeSecret, PublicKey, SecretKey, PK_SIZE};
+use ed25519_dalek::Keypair;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::fs;
@@ -15,6 +16,7 @@ use tokio::fs;
 const AGE_GROUP_FILENAME: &str = "age_group";
 const REWARD_PUBLIC_KEY_FILENAME: &str = "reward_public_key";
 const REWARD_SECRET_KEY_FILENAME: &str = "reward_secret_key";
+const NODE_KEYPAIR_FILENAME: &str = "node_keypair";
 
 /// Writes the public and secret key to different locations at disk.
 pub async fn store_new_reward_keypair(
@@ -29,6 +31,30 @@ pub async fn store_new_reward_keypair(
     Ok(())
 }
 
+/// Returns the public key stored by `store_new_reward_keypair`, if any.
+pub async fn get_reward_public_key(root_dir: &Path) -> Result<Option<PublicKey>> {
+    let path = root_dir.join(REWARD_PUBLIC_KEY_FILENAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let hex = fs::read_to_string(path).await?;
+    Ok(Some(pk_from_hex(&hex)?))
+}
+
+/// Returns the keypair of the node stored in the root dir,
+/// or stores a new one there if there is none, so that it is kept at restarts.
+pub async fn get_or_create_node_keypair(root_dir: &Path) -> Result<Keypair> {
+    let path = root_dir.join(NODE_KEYPAIR_FILENAME);
+    if path.is_file() {
+        let hex = fs::read_to_string(path).await?;
+        return Keypair::from_bytes(&parse_hex(&hex))
+            .map_err(|_| Error::Logic("Invalid node keypair bytes".to_string()));
+    }
+    let keypair = Keypair::generate(&mut rand::thread_rng());
+    fs::write(path, vec_to_hex(keypair.to_bytes().to_vec())).await?;
+    Ok(keypair)
+}
+
 // /// Writes the info to disk.
 // pub async fn store_age_group(root_dir: &Path, age_group: &AgeGroup) -> Result<()> {
 //     let path = root_dir.join(AGE_GROUP_FILENAME);
@@ -105,6 +131,15 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn node_keypair_is_kept() -> Result<()> {
+        let root_dir = tempdir::TempDir::new("node_keypair")?;
+        let keypair = get_or_create_node_keypair(root_dir.path()).await?;
+        let kept = get_or_create_node_keypair(root_dir.path()).await?;
+        assert_eq!(keypair.to_bytes().to_vec(), kept.to_bytes().to_vec());
+        Ok(())
+    }
+
     fn gen_key() -> PublicKey {
         SecretKey::random().public_key()
     }
@@ -39,5 +39,6 @@ pub async fn replica_info(
         peer_replicas,
         section_chain: network.section_chain().await,
         signing,
+        verified: network.verification_cache(),
     })
 }
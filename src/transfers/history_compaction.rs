@@ -0,0 +1,325 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Compaction of the histories of the wallets held by the replicas. The node reward wallets
+//! are credited at every churn, for as long as the nodes keep running, so the transfers
+//! confirmed long ago are collapsed into a summary of the wallet, signed by the replica,
+//! and pruned from its store, keeping both the store and the state pushed at churn bounded.
+
+use crate::{
+    node_msg::{NodeMsg, NodeMsgBody},
+    node_ops::NodeDuty,
+    utils, Error, Result,
+};
+use bls::PublicKeySet;
+use serde::{Deserialize, Serialize};
+use sn_data_types::{CreditId, OwnerType, PublicKey, ReplicaEvent, SignatureShare, Token};
+use sn_transfers::Wallet;
+use std::{collections::BTreeSet, time::Duration};
+use xor_name::XorName;
+
+/// How often the replicas compact the histories of the node reward wallets.
+pub(crate) const COMPACTION_TICK: Duration = Duration::from_secs(60 * 60);
+/// The events last stored for a wallet, kept as they are when compacting its history.
+pub(crate) const KEEP_RECENT_EVENTS: usize = 100;
+// The summaries pushed at most in a msg.
+const MAX_PUSH_BATCH: usize = 50;
+
+/// The state of a wallet as of the transfers collapsed into it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletSummary {
+    /// The wallet.
+    pub wallet: PublicKey,
+    /// The balance of the wallet.
+    pub balance: Token,
+    /// The version of the next debit of the wallet, i.e. the number of its debits.
+    pub debit_version: u64,
+    /// The ids of the credits to the wallet.
+    pub credit_ids: BTreeSet<CreditId>,
+}
+
+impl WalletSummary {
+    /// The summary of a wallet with no transfers collapsed yet.
+    pub(crate) fn new(wallet: PublicKey) -> Self {
+        Self {
+            wallet,
+            balance: Token::zero(),
+            debit_version: 0,
+            credit_ids: BTreeSet::new(),
+        }
+    }
+
+    /// Whether the event is of a transfer collapsed into the summary.
+    pub(crate) fn covers(&self, event: &ReplicaEvent) -> bool {
+        match event {
+            ReplicaEvent::TransferPropagated(e) => self.credit_ids.contains(e.credit_proof.id()),
+            ReplicaEvent::TransferRegistered(e) => {
+                e.transfer_proof.id().counter < self.debit_version
+            }
+            ReplicaEvent::TransferValidated(e) => {
+                e.signed_debit.debit.id.counter < self.debit_version
+            }
+            ReplicaEvent::TransferValidationProposed(e) => {
+                e.signed_debit.debit.id.counter < self.debit_version
+            }
+        }
+    }
+
+    /// Whether the summary collapses every transfer the other does.
+    pub(crate) fn supersedes(&self, other: &Self) -> bool {
+        self.wallet == other.wallet
+            && self.debit_version >= other.debit_version
+            && self.credit_ids.is_superset(&other.credit_ids)
+    }
+
+    /// Collapses the confirmed transfers of the events, in the order stored, into the
+    /// summary, returning the events not collapsed, e.g. of debits not registered yet.
+    pub(crate) fn collapse(&mut self, events: Vec<ReplicaEvent>) -> Result<Vec<ReplicaEvent>> {
+        let mut kept = vec![];
+        for event in events {
+            match &event {
+                ReplicaEvent::TransferPropagated(e) if !self.covers(&event) => {
+                    let credit = &e.credit_proof.signed_credit.credit;
+                    self.balance = self.balance.checked_add(credit.amount).ok_or_else(|| {
+                        Error::InvalidOperation(format!(
+                            "Credit {} overflows the balance of wallet {}",
+                            hex::encode(credit.id),
+                            self.wallet
+                        ))
+                    })?;
+                    let _ = self.credit_ids.insert(credit.id);
+                }
+                ReplicaEvent::TransferRegistered(e)
+                    if e.transfer_proof.id().counter == self.debit_version =>
+                {
+                    let amount = e.transfer_proof.amount();
+                    self.balance = self.balance.checked_sub(amount).ok_or_else(|| {
+                        Error::InvalidOperation(format!(
+                            "Debit {} overdraws wallet {}",
+                            self.debit_version, self.wallet
+                        ))
+                    })?;
+                    self.debit_version += 1;
+                }
+                _ => kept.push(event),
+            }
+        }
+        Ok(kept.into_iter().filter(|e| !self.covers(e)).collect())
+    }
+
+    /// The wallet as of the summary, for the events not collapsed to be applied to.
+    pub(crate) fn to_wallet(&self) -> Wallet {
+        Wallet::from(
+            OwnerType::Single(self.wallet),
+            self.balance,
+            self.debit_version,
+            self.credit_ids.iter().copied().collect(),
+        )
+    }
+}
+
+/// A summary, signed by the replica compacting the history of the wallet
+/// with its share of the key of the section.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignedWalletSummary {
+    /// The summary.
+    pub summary: WalletSummary,
+    /// The key set of the section of the replica, its share verified against.
+    pub key_set: PublicKeySet,
+    /// The share of the replica, of the signature of the section over the summary.
+    pub share: SignatureShare,
+}
+
+impl SignedWalletSummary {
+    /// Whether the share signs the summary.
+    pub(crate) fn verify(&self) -> Result<bool> {
+        Ok(self
+            .key_set
+            .public_key_share(self.share.index)
+            .verify(&self.share.share, utils::serialise(&self.summary)?))
+    }
+}
+
+/// The summaries of the wallets compacted by an Elder, pushed to the new Elders along with
+/// the histories of the wallets, so that the transfers pruned are not lost to them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WalletSummaryPush {
+    /// The summaries.
+    pub summaries: Vec<SignedWalletSummary>,
+}
+
+impl WalletSummaryPush {
+    /// The msgs pushing the summaries to the Elders, in batches of at most `MAX_PUSH_BATCH`.
+    pub(crate) fn msgs(
+        summaries: Vec<SignedWalletSummary>,
+        elders: &BTreeSet<XorName>,
+    ) -> Vec<NodeDuty> {
+        if elders.is_empty() {
+            return vec![];
+        }
+        summaries
+            .chunks(MAX_PUSH_BATCH)
+            .map(|batch| NodeDuty::SendNodeMsgToNodes {
+                targets: elders.clone(),
+                msg: NodeMsg::new(NodeMsgBody::WalletSummaryPush(Self {
+                    summaries: batch.to_vec(),
+                })),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test_utils::get_random_genesis;
+    use super::*;
+    use bls::{SecretKey, SecretKeySet};
+    use crdts::Dot;
+    use sn_data_types::{
+        Credit, CreditAgreementProof, Debit, Signature, SignedCredit, SignedDebit,
+        TransferAgreementProof, TransferPropagated, TransferRegistered, TransferValidated,
+    };
+
+    fn signature() -> Signature {
+        Signature::Bls(SecretKey::random().sign(b"any"))
+    }
+
+    fn credit(wallet: PublicKey, amount: u64) -> Result<ReplicaEvent> {
+        let mut credit_proof: CreditAgreementProof = get_random_genesis(amount, wallet)?;
+        credit_proof.signed_credit.credit.id = rand::random();
+        Ok(ReplicaEvent::TransferPropagated(TransferPropagated {
+            credit_proof,
+        }))
+    }
+
+    fn transfer_proof(wallet: PublicKey, version: u64, amount: u64) -> TransferAgreementProof {
+        let debit = Debit {
+            id: Dot::new(wallet, version),
+            amount: Token::from_nano(amount),
+        };
+        let credit = Credit {
+            id: debit.credit_id().unwrap_or_default(),
+            amount: Token::from_nano(amount),
+            recipient: PublicKey::from(SecretKey::random().public_key()),
+            msg: String::new(),
+        };
+        TransferAgreementProof {
+            signed_debit: SignedDebit {
+                debit,
+                actor_signature: signature(),
+            },
+            signed_credit: SignedCredit {
+                credit,
+                actor_signature: signature(),
+            },
+            debit_sig: signature(),
+            credit_sig: signature(),
+            debiting_replicas_keys: SecretKeySet::random(0, &mut rand::thread_rng()).public_keys(),
+        }
+    }
+
+    fn debit(wallet: PublicKey, version: u64, amount: u64) -> ReplicaEvent {
+        ReplicaEvent::TransferRegistered(TransferRegistered {
+            transfer_proof: transfer_proof(wallet, version, amount),
+        })
+    }
+
+    fn validated(wallet: PublicKey, version: u64, amount: u64) -> ReplicaEvent {
+        let proof = transfer_proof(wallet, version, amount);
+        let secret_keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let share = SignatureShare {
+            index: 0,
+            share: secret_keys.secret_key_share(0).sign(b"any"),
+        };
+        ReplicaEvent::TransferValidated(TransferValidated {
+            signed_debit: proof.signed_debit,
+            signed_credit: proof.signed_credit,
+            replica_debit_sig: share.clone(),
+            replica_credit_sig: share,
+            replicas: secret_keys.public_keys(),
+        })
+    }
+
+    #[test]
+    fn confirmed_transfers_are_collapsed_and_pending_ones_kept() -> Result<()> {
+        let wallet = PublicKey::from(SecretKey::random().public_key());
+        let first_credit = credit(wallet, 10)?;
+        let pending = validated(wallet, 2, 1);
+        let events = vec![
+            first_credit.clone(),
+            credit(wallet, 5)?,
+            validated(wallet, 0, 3),
+            debit(wallet, 0, 3),
+            debit(wallet, 1, 4),
+            pending.clone(),
+            // as merged again from the state pushed at churn
+            first_credit.clone(),
+        ];
+
+        let mut summary = WalletSummary::new(wallet);
+        let kept = summary.collapse(events)?;
+        assert_eq!(kept, vec![pending.clone()]);
+        assert_eq!(summary.balance, Token::from_nano(8));
+        assert_eq!(summary.debit_version, 2);
+        assert_eq!(summary.credit_ids.len(), 2);
+        assert!(summary.covers(&first_credit));
+        assert!(!summary.covers(&pending));
+        assert_eq!(summary.to_wallet().balance(), Token::from_nano(8));
+        assert_eq!(summary.to_wallet().next_debit(), 2);
+
+        // a later summary supersedes an earlier one
+        let earlier = summary.clone();
+        let _ = summary.collapse(vec![credit(wallet, 1)?])?;
+        assert!(summary.supersedes(&earlier));
+        assert!(!earlier.supersedes(&summary));
+
+        // and debits not covered by the balance are not collapsed
+        assert!(summary.collapse(vec![debit(wallet, 2, 10)]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn summaries_are_pushed_signed_by_a_replica() -> Result<()> {
+        let wallet = PublicKey::from(SecretKey::random().public_key());
+        let mut summary = WalletSummary::new(wallet);
+        let _ = summary.collapse(vec![credit(wallet, 10)?])?;
+        let secret_keys = SecretKeySet::random(2, &mut rand::thread_rng());
+        let share = SignatureShare {
+            index: 1,
+            share: secret_keys
+                .secret_key_share(1)
+                .sign(utils::serialise(&summary)?),
+        };
+        let signed = SignedWalletSummary {
+            summary,
+            key_set: secret_keys.public_keys(),
+            share,
+        };
+        assert!(signed.verify()?);
+        let mut forged = signed.clone();
+        forged.summary.balance = Token::from_nano(11);
+        assert!(!forged.verify()?);
+
+        let elders = vec![XorName::random()].into_iter().collect();
+        match WalletSummaryPush::msgs(vec![signed.clone()], &elders).as_slice() {
+            [NodeDuty::SendNodeMsgToNodes {
+                targets,
+                msg:
+                    NodeMsg {
+                        body: NodeMsgBody::WalletSummaryPush(push),
+                        ..
+                    },
+            }] => {
+                assert_eq!(targets, &elders);
+                assert_eq!(push.summaries, vec![signed]);
+            }
+            msgs => panic!("Expected a single push: {:?}", msgs),
+        }
+        Ok(())
+    }
+}
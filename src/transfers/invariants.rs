@@ -0,0 +1,440 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::history_compaction::WalletSummary;
+use sn_data_types::{
+    CreditAgreementProof, CreditId, PublicKey, ReplicaEvent, Token, TransferAgreementProof,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// How often the replicas verify the invariants of the wallets held by them.
+pub(crate) const INVARIANT_CHECK_TICK: Duration = Duration::from_secs(30 * 60);
+
+/// A breach of the invariants of a wallet held by the replicas.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// The wallet was debited more than it was credited.
+    Overdrawn {
+        /// The wallet.
+        wallet: PublicKey,
+        /// Total of the credits to the wallet.
+        credited: Token,
+        /// Total of the debits of the wallet.
+        debited: Token,
+    },
+    /// The wallet was credited twice under the same id, with different credits.
+    DuplicateCredit {
+        /// The wallet.
+        wallet: PublicKey,
+        /// Id of the credits.
+        id: CreditId,
+    },
+    /// The wallet was debited twice at the same version, with different debits.
+    DuplicateDebit {
+        /// The wallet.
+        wallet: PublicKey,
+        /// The version of the debits.
+        version: u64,
+    },
+    /// The wallet was debited at a version other than the one following its last debit.
+    VersionOutOfOrder {
+        /// The wallet.
+        wallet: PublicKey,
+        /// The version following the last debit.
+        expected: u64,
+        /// The version of the debit.
+        found: u64,
+    },
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Overdrawn {
+                wallet,
+                credited,
+                debited,
+            } => write!(
+                formatter,
+                "Wallet {} is debited {} while credited {}",
+                wallet, debited, credited
+            ),
+            Self::DuplicateCredit { wallet, id } => write!(
+                formatter,
+                "Wallet {} is credited twice under id {}",
+                wallet,
+                hex::encode(id)
+            ),
+            Self::DuplicateDebit { wallet, version } => write!(
+                formatter,
+                "Wallet {} is debited twice at version {}",
+                wallet, version
+            ),
+            Self::VersionOutOfOrder {
+                wallet,
+                expected,
+                found,
+            } => write!(
+                formatter,
+                "Wallet {} is debited at version {}, expected {}",
+                wallet, found, expected
+            ),
+        }
+    }
+}
+
+/// Verifies the invariants of the wallet over the events stored for it:
+/// its credits cover its debits, no two credits or debits share an id,
+/// and the versions of its debits follow each other from zero.
+/// Events repeated as they are are not breaches, as e.g. merging the
+/// state pushed to us at churn stores the events already stored again.
+/// The events compacted are verified as of the summary they were collapsed into.
+pub(super) fn verify_wallet(
+    wallet: PublicKey,
+    compacted: Option<&WalletSummary>,
+    events: &[ReplicaEvent],
+) -> Vec<InvariantViolation> {
+    let mut violations = vec![];
+    let mut credits: BTreeMap<CreditId, &CreditAgreementProof> = BTreeMap::new();
+    let mut debits: BTreeMap<u64, &TransferAgreementProof> = BTreeMap::new();
+    let mut credited = compacted
+        .map(|summary| u128::from(summary.balance.as_nano()))
+        .unwrap_or_default();
+    let mut debited = 0_u128;
+    let first_version = compacted
+        .map(|summary| summary.debit_version)
+        .unwrap_or_default();
+    for event in events {
+        match event {
+            ReplicaEvent::TransferPropagated(propagated) => {
+                let proof = &propagated.credit_proof;
+                match credits.get(proof.id()) {
+                    Some(known) if *known == proof => (),
+                    Some(_) => violations.push(InvariantViolation::DuplicateCredit {
+                        wallet,
+                        id: *proof.id(),
+                    }),
+                    None => {
+                        credited += u128::from(proof.amount().as_nano());
+                        let _ = credits.insert(*proof.id(), proof);
+                    }
+                }
+            }
+            ReplicaEvent::TransferRegistered(registered) => {
+                let proof = &registered.transfer_proof;
+                let version = proof.id().counter;
+                match debits.get(&version) {
+                    Some(known) if *known == proof => (),
+                    Some(_) => {
+                        violations.push(InvariantViolation::DuplicateDebit { wallet, version })
+                    }
+                    None => {
+                        let expected = first_version + debits.len() as u64;
+                        if version != expected {
+                            violations.push(InvariantViolation::VersionOutOfOrder {
+                                wallet,
+                                expected,
+                                found: version,
+                            });
+                        }
+                        debited += u128::from(proof.amount().as_nano());
+                        let _ = debits.insert(version, proof);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    if debited > credited {
+        let token = |nanos: u128| Token::from_nano(nanos.min(u128::from(u64::MAX)) as u64);
+        violations.push(InvariantViolation::Overdrawn {
+            wallet,
+            credited: token(credited),
+            debited: token(debited),
+        });
+    }
+    violations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::{SecretKey, SecretKeySet};
+    use crdts::Dot;
+    use quickcheck::{QuickCheck, StdThreadGen, TestResult};
+    use sn_data_types::{
+        Credit, Debit, Signature, SignedCredit, SignedDebit, TransferPropagated, TransferRegistered,
+    };
+
+    // A step of a random sequence of transfers of the wallet.
+    #[derive(Clone, Copy, Debug)]
+    enum Step {
+        Credit(u32),
+        Debit(u32),
+    }
+
+    impl quickcheck::Arbitrary for Step {
+        fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+            if bool::arbitrary(g) {
+                Step::Credit(u32::arbitrary(g))
+            } else {
+                Step::Debit(u32::arbitrary(g))
+            }
+        }
+    }
+
+    // The ways a sequence of valid transfers is made invalid.
+    #[derive(Clone, Copy, Debug)]
+    enum Tamper {
+        Overdraw,
+        DuplicateCredit,
+        DuplicateDebit,
+        SkipVersion,
+    }
+
+    impl quickcheck::Arbitrary for Tamper {
+        fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+            match u8::arbitrary(g) % 4 {
+                0 => Tamper::Overdraw,
+                1 => Tamper::DuplicateCredit,
+                2 => Tamper::DuplicateDebit,
+                _ => Tamper::SkipVersion,
+            }
+        }
+    }
+
+    // The keys and signature the events of all wallets are built with, made once, as
+    // BLS keys and signatures are slow to make in debug builds.
+    struct Keys {
+        wallet: PublicKey,
+        recipient: PublicKey,
+        signature: Signature,
+        replica_keys: bls::PublicKeySet,
+    }
+
+    lazy_static::lazy_static! {
+        static ref KEYS: Keys = {
+            let secret = SecretKey::random();
+            Keys {
+                wallet: PublicKey::from(secret.public_key()),
+                recipient: PublicKey::from(SecretKey::random().public_key()),
+                signature: Signature::Bls(secret.sign(b"any")),
+                replica_keys: SecretKeySet::random(0, &mut rand::thread_rng()).public_keys(),
+            }
+        };
+    }
+
+    // Steps of a sequence of transfers at most, and tests of each property, so that
+    // they run in seconds.
+    const MAX_STEPS: usize = 20;
+    const TESTS: u64 = 100;
+
+    // Builds the events of the wallet, as the replicas register the transfers.
+    // Signatures are not verified by the invariants, so the same one is used for all.
+    struct Wallet {
+        key: PublicKey,
+        events: Vec<ReplicaEvent>,
+        balance: u64,
+        version: u64,
+        credits: u64,
+    }
+
+    impl Wallet {
+        fn new() -> Self {
+            Self {
+                key: KEYS.wallet,
+                events: vec![],
+                balance: 0,
+                version: 0,
+                credits: 0,
+            }
+        }
+
+        fn signature(&self) -> Signature {
+            KEYS.signature.clone()
+        }
+
+        fn credit(&mut self, amount: u64) -> ReplicaEvent {
+            self.credits += 1;
+            let credit = Credit {
+                id: credit_id(self.credits),
+                amount: Token::from_nano(amount),
+                recipient: self.key,
+                msg: String::new(),
+            };
+            ReplicaEvent::TransferPropagated(TransferPropagated {
+                credit_proof: CreditAgreementProof {
+                    signed_credit: SignedCredit {
+                        credit,
+                        actor_signature: self.signature(),
+                    },
+                    debiting_replicas_sig: self.signature(),
+                    debiting_replicas_keys: KEYS.replica_keys.clone(),
+                },
+            })
+        }
+
+        fn debit(&self, version: u64, amount: u64) -> ReplicaEvent {
+            let debit = Debit {
+                id: Dot::new(self.key, version),
+                amount: Token::from_nano(amount),
+            };
+            let credit = Credit {
+                id: debit.credit_id().unwrap_or_default(),
+                amount: Token::from_nano(amount),
+                recipient: KEYS.recipient,
+                msg: String::new(),
+            };
+            ReplicaEvent::TransferRegistered(TransferRegistered {
+                transfer_proof: TransferAgreementProof {
+                    signed_debit: SignedDebit {
+                        debit,
+                        actor_signature: self.signature(),
+                    },
+                    signed_credit: SignedCredit {
+                        credit,
+                        actor_signature: self.signature(),
+                    },
+                    debit_sig: self.signature(),
+                    credit_sig: self.signature(),
+                    debiting_replicas_keys: KEYS.replica_keys.clone(),
+                },
+            })
+        }
+
+        // Applies the step as the replicas would, dropping debits not covered.
+        fn apply(&mut self, step: Step) {
+            match step {
+                Step::Credit(amount) => {
+                    let event = self.credit(u64::from(amount));
+                    self.balance += u64::from(amount);
+                    self.events.push(event);
+                }
+                Step::Debit(amount) if u64::from(amount) <= self.balance => {
+                    let event = self.debit(self.version, u64::from(amount));
+                    self.balance -= u64::from(amount);
+                    self.version += 1;
+                    self.events.push(event);
+                }
+                Step::Debit(_) => (),
+            }
+        }
+    }
+
+    fn credit_id(n: u64) -> CreditId {
+        let mut id = [0; 32];
+        id[..8].copy_from_slice(&n.to_le_bytes());
+        id
+    }
+
+    fn valid_transfers_keep_the_invariants(steps: Vec<Step>) -> bool {
+        let mut wallet = Wallet::new();
+        for step in steps {
+            wallet.apply(step);
+        }
+        // as on merging the state pushed at churn
+        let mut events = wallet.events.clone();
+        events.extend(wallet.events.clone());
+        verify_wallet(wallet.key, None, &events).is_empty()
+    }
+
+    fn compacted_transfers_keep_the_invariants(steps: Vec<Step>, compacted: usize) -> bool {
+        let mut wallet = Wallet::new();
+        for step in steps {
+            wallet.apply(step);
+        }
+        let cut = compacted % (wallet.events.len() + 1);
+        let mut summary = WalletSummary::new(wallet.key);
+        let mut events = match summary.collapse(wallet.events[..cut].to_vec()) {
+            Ok(kept) => kept,
+            Err(_) => return false,
+        };
+        events.extend(wallet.events[cut..].iter().cloned());
+        verify_wallet(wallet.key, Some(&summary), &events).is_empty()
+    }
+
+    fn invalid_transfers_are_caught(steps: Vec<Step>, tamper: Tamper) -> TestResult {
+        let mut wallet = Wallet::new();
+        wallet.apply(Step::Credit(1));
+        for step in steps {
+            wallet.apply(step);
+        }
+        let mut events = wallet.events.clone();
+        let caught = |violation: &InvariantViolation| -> bool {
+            matches!(
+                (tamper, violation),
+                (Tamper::Overdraw, InvariantViolation::Overdrawn { .. })
+                    | (
+                        Tamper::DuplicateCredit,
+                        InvariantViolation::DuplicateCredit { .. }
+                    )
+                    | (
+                        Tamper::DuplicateDebit,
+                        InvariantViolation::DuplicateDebit { .. }
+                    )
+                    | (
+                        Tamper::SkipVersion,
+                        InvariantViolation::VersionOutOfOrder { .. }
+                    )
+            )
+        };
+        match tamper {
+            Tamper::Overdraw => {
+                events.push(wallet.debit(wallet.version, wallet.balance + 1));
+            }
+            Tamper::DuplicateCredit => {
+                // the same id, for another amount
+                let mut credit = match &events[0] {
+                    ReplicaEvent::TransferPropagated(credit) => credit.clone(),
+                    _ => return TestResult::discard(),
+                };
+                credit.credit_proof.signed_credit.credit.amount = Token::from_nano(0);
+                events.push(ReplicaEvent::TransferPropagated(credit));
+            }
+            Tamper::DuplicateDebit => {
+                if wallet.version == 0 {
+                    return TestResult::discard();
+                }
+                // for an amount no step debits, so that it differs from the debit at the version
+                events.push(wallet.debit(wallet.version - 1, u64::from(u32::MAX) + 1));
+            }
+            Tamper::SkipVersion => {
+                events.push(wallet.debit(wallet.version + 1, 0));
+            }
+        }
+        TestResult::from_bool(verify_wallet(wallet.key, None, &events).iter().any(caught))
+    }
+
+    fn quickcheck() -> QuickCheck<StdThreadGen> {
+        QuickCheck::new()
+            .gen(StdThreadGen::new(MAX_STEPS))
+            .tests(TESTS)
+    }
+
+    #[test]
+    fn invariants_hold_for_random_valid_transfers() {
+        let property: fn(Vec<Step>) -> bool = valid_transfers_keep_the_invariants;
+        quickcheck().quickcheck(property);
+    }
+
+    #[test]
+    fn invariants_hold_for_random_compacted_transfers() {
+        let property: fn(Vec<Step>, usize) -> bool = compacted_transfers_keep_the_invariants;
+        quickcheck().quickcheck(property);
+    }
+
+    #[test]
+    fn invariants_catch_random_invalid_transfers() {
+        let property: fn(Vec<Step>, Tamper) -> TestResult = invalid_transfers_are_caught;
+        quickcheck().quickcheck(property);
+    }
+}
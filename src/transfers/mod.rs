@@ -7,19 +7,40 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 pub mod get_replicas;
+mod history_compaction;
+mod invariants;
+mod recent_validations;
 pub mod replica_signing;
 pub mod replicas;
+mod snapshot;
 pub mod store;
-mod test_utils;
-
+mod store_cost_estimate;
+mod store_cost_quotes;
+pub(crate) mod test_utils;
+mod time_lock;
+
+use self::history_compaction::COMPACTION_TICK;
+pub use self::history_compaction::{SignedWalletSummary, WalletSummary, WalletSummaryPush};
+pub use self::invariants::InvariantViolation;
+use self::invariants::INVARIANT_CHECK_TICK;
+use self::recent_validations::RecentValidations;
+use self::store_cost_estimate::chunk_sizes;
+pub use self::store_cost_estimate::{
+    SignedStoreCostEstimate, StoreCostEstimate, StoreCostItem, ESTIMATE_VALIDITY,
+    MAX_ESTIMATED_CHUNKS,
+};
+use self::store_cost_quotes::StoreCostQuotes;
+pub use self::time_lock::{TimeLock, TIME_LOCK_TAG};
 use self::{
     replica_signing::ReplicaSigning,
     replicas::{ReplicaInfo, Replicas},
 };
 use crate::{
-    capacity::RateLimit,
+    capacity::{EconomyConfig, RateLimit, SectionFullness},
     error::{convert_dt_error_to_error_message, convert_to_error_message},
-    node_ops::{NodeDuties, NodeDuty, OutgoingMsg},
+    metadata::{AckLevel, CapacityHistogram, WriteAckLevel},
+    node_msg::{ClientMsg, NodeMsg, NodeMsgBody},
+    node_ops::{NodeDuties, NodeDuty, OutgoingMsg, PeriodicTask},
     utils, Error, Result,
 };
 use log::{debug, error, info, trace, warn};
@@ -27,23 +48,25 @@ use replica_signing::ReplicaSigningImpl;
 #[cfg(feature = "simulated-payouts")]
 use sn_data_types::Transfer;
 use sn_routing::XorName;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::BTreeMap;
 
 use futures::lock::Mutex;
+use serde::{Deserialize, Serialize};
 use sn_data_types::{
-    ActorHistory, CreditAgreementProof, DebitId, PublicKey, ReplicaEvent, SignedTransfer,
+    ActorHistory, CreditAgreementProof, CreditId, DebitId, PublicKey, ReplicaEvent, SignedTransfer,
     SignedTransferShare, Token, TransferAgreementProof, TransferPropagated,
 };
 use sn_messaging::{
     client::{
-        Cmd, CmdError, Error as ErrorMessage, Event, Message, NodeCmd, NodeCmdError, NodeEvent,
-        NodeQueryResponse, NodeTransferCmd, NodeTransferError, NodeTransferQueryResponse,
-        QueryResponse, TransferError,
+        BlobWrite, Cmd, CmdError, DataCmd, Error as ErrorMessage, Event, Message, NodeCmd,
+        NodeCmdError, NodeEvent, NodeQueryResponse, NodeTransferCmd, NodeTransferError,
+        NodeTransferQueryResponse, QueryResponse, TransferError,
     },
     Aggregation, DstLocation, EndUser, MessageId, SrcLocation,
 };
 use std::fmt::{self, Display, Formatter};
 use std::sync::Arc;
+use std::time::Instant;
 use xor_name::Prefix;
 
 /*
@@ -72,14 +95,61 @@ apply operations that has a valid proof of agreement from the group.
 Replicas don't initiate transfers or drive the algo - only Actors do.
 */
 
+/// The background work on the wallets of the section.
+pub(crate) fn periodic_tasks() -> Vec<PeriodicTask> {
+    vec![
+        PeriodicTask {
+            name: "transfer invariant checks",
+            interval: INVARIANT_CHECK_TICK,
+            duty: || NodeDuty::CheckTransferInvariants,
+        },
+        PeriodicTask {
+            name: "wallet history compaction",
+            interval: COMPACTION_TICK,
+            duty: || NodeDuty::CompactWalletHistories,
+        },
+    ]
+}
+
+/// Identifies a single credit or debit of a wallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferId {
+    /// A credit to the wallet.
+    Credit(CreditId),
+    /// A debit from the wallet.
+    Debit(DebitId),
+}
+
+/// Query by a client for the status of a specific credit or debit of a wallet,
+/// e.g. to confirm a payment landed, without reading the full history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferStatusQuery {
+    /// The wallet key.
+    pub wallet: PublicKey,
+    /// The credit or debit to look up.
+    pub id: TransferId,
+}
+
+/// The registration status of a transfer.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferStatus {
+    /// Registered, with its agreement proof.
+    Registered(ActorHistory),
+    /// Validated by us, but not yet registered.
+    Validated,
+    /// Not known to us.
+    Unknown,
+}
+
 /// Transfers is the layer that manages
 /// interaction with an AT2 Replica.
 #[derive(Clone)]
 pub struct Transfers {
     replicas: Replicas<ReplicaSigningImpl>,
     rate_limit: RateLimit,
-    // TODO: limit this? where do we store it
-    recently_validated_transfers: Arc<Mutex<HashSet<DebitId>>>,
+    recently_validated_transfers: Arc<Mutex<RecentValidations>>,
+    store_cost_quotes: Arc<Mutex<StoreCostQuotes>>,
 }
 
 impl Transfers {
@@ -88,24 +158,69 @@ impl Transfers {
             replicas,
             rate_limit,
             recently_validated_transfers: Default::default(),
+            store_cost_quotes: Default::default(),
         }
     }
 
+    /// Charges store costs by the economy from now on, as pushed with the section parameters.
+    pub fn set_economy(&mut self, economy: EconomyConfig) {
+        self.rate_limit.set_economy(economy);
+    }
+
     /// The total amount in wallets managed
     /// by the replicas in this section.
     pub async fn managed_amount(&self) -> Result<Token> {
         self.replicas.managed_amount().await
     }
 
+    /// The balance of the section wallet,
+    /// i.e. the payments not yet paid out as rewards.
+    pub async fn section_balance(&self) -> Result<Token> {
+        self.replicas.balance(self.section_wallet_id()).await
+    }
+
     ///
     pub fn user_wallets(&self) -> BTreeMap<PublicKey, ActorHistory> {
         self.replicas.user_wallets()
     }
 
+    /// The history of the wallet, if held by the replicas of our section.
+    pub fn wallet_history(&self, wallet_id: PublicKey) -> Option<ActorHistory> {
+        self.replicas.history(wallet_id).ok()
+    }
+
+    /// The summary the history of the wallet was compacted into, if held by
+    /// the replicas of our section and compacted.
+    pub fn wallet_summary(&self, wallet_id: PublicKey) -> Option<SignedWalletSummary> {
+        self.replicas.wallet_summary(wallet_id)
+    }
+
+    /// Compacts the history of the wallet, if held by the replicas of
+    /// our section, returning the summary it was compacted into, if any.
+    pub async fn compact_history(
+        &self,
+        wallet_id: PublicKey,
+    ) -> Result<Option<SignedWalletSummary>> {
+        self.replicas.compact(wallet_id).await
+    }
+
+    /// Imports the summary of a wallet compacted by another replica of our
+    /// section, returning whether it superseded ours.
+    pub async fn import_wallet_summary(&self, summary: SignedWalletSummary) -> Result<bool> {
+        self.replicas.import_summary(summary).await
+    }
+
     pub fn merge(&mut self, user_wallets: BTreeMap<PublicKey, ActorHistory>) {
         self.replicas.merge(user_wallets);
     }
 
+    /// The breaches of the invariants of the wallets held by the replicas:
+    /// credits cover debits, no credit or debit is duplicated,
+    /// and the versions of the debits of each wallet increase one by one.
+    pub fn verify_invariants(&self) -> Vec<InvariantViolation> {
+        self.replicas.verify_invariants()
+    }
+
     /// When section splits, the Replicas in either resulting section
     /// also split the responsibility of the accounts.
     /// Thus, both Replica groups need to drop the accounts that
@@ -122,18 +237,27 @@ impl Transfers {
 
     /// Get latest StoreCost for the given number of bytes.
     /// Also check for Section storage capacity and report accordingly.
+    /// The response is the cost at standard redundancy. Clients are also sent the
+    /// full quote, with the cost at premium redundancy, and their payments for the
+    /// bytes are charged as quoted.
     pub async fn get_store_cost(
         &mut self,
+        requester: PublicKey,
         bytes: u64,
         msg_id: MessageId,
         origin: SrcLocation,
-    ) -> NodeDuties {
+    ) -> Result<NodeDuties> {
         let mut ops = vec![];
-        let result = self.rate_limit.from(bytes).await;
-        info!("StoreCost for {:?} bytes: {}", bytes, result);
+        let quote = self.rate_limit.quote(bytes).await;
+        info!("StoreCost for {:?} bytes: {:?}", bytes, quote);
+        self.store_cost_quotes.lock().await.record(
+            (XorName::from(requester), bytes),
+            quote,
+            Instant::now(),
+        );
         let response = NodeDuty::Send(OutgoingMsg {
             msg: Message::QueryResponse {
-                response: QueryResponse::GetStoreCost(Ok(result)),
+                response: QueryResponse::GetStoreCost(Ok(quote.standard)),
                 id: MessageId::in_response_to(&msg_id),
                 correlation_id: msg_id,
                 target_section_pk: None,
@@ -144,11 +268,44 @@ impl Transfers {
         });
 
         ops.push(response);
+        // under an id derived from that of the query, so that clients not
+        // expecting it don't take it for the response
+        if let SrcLocation::EndUser(user) = origin {
+            let correlation_id = MessageId::in_response_to(&msg_id);
+            ops.push(NodeDuty::Send(
+                ClientMsg::StoreCostQuote(quote).msg(correlation_id, user)?,
+            ));
+        }
         ops.push(NodeDuty::SetNodeJoinsAllowed(
             self.rate_limit.more_nodes_required().await,
         ));
 
-        ops
+        Ok(ops)
+    }
+
+    /// The store cost of an upload of the bytes, split into chunks of at most the max
+    /// chunk size, itemized by chunk size.
+    pub async fn estimate_store_cost(
+        &self,
+        bytes: u64,
+        max_chunk_size: u64,
+    ) -> Result<Vec<StoreCostItem>> {
+        let mut items = vec![];
+        for (bytes, chunks) in chunk_sizes(bytes, max_chunk_size)? {
+            let quote = self.rate_limit.quote(bytes).await;
+            items.push(StoreCostItem {
+                bytes,
+                chunks,
+                cost: quote.standard,
+                premium_cost: quote.premium,
+            });
+        }
+        Ok(items)
+    }
+
+    /// How full the section is, by the capacity of its Adults, as last reported by them.
+    pub async fn section_fullness(&self, histogram: &CapacityHistogram) -> SectionFullness {
+        self.rate_limit.fullness(histogram.used_percentage()).await
     }
 
     ///
@@ -204,21 +361,20 @@ impl Transfers {
                 aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
             })]);
         }
-        let registration = self.replicas.register(&payment).await;
-        let result = match registration {
-            Ok(_) => match self
-                .replicas
-                .receive_propagated(payment.sender().into(), &payment.credit_proof())
-                .await
-            {
-                Ok(e) => Ok(e),
-                Err(error) => Err(error),
-            },
-            Err(error) => Err(error), // not using TransferPropagation error, since that is for NodeCmds, so wouldn't be returned to client.
-        };
+        // not using TransferPropagation error, since that is for NodeCmds, so wouldn't be returned to client.
+        let result = self.replicas.register_payment(&payment).await;
         match result {
             Ok(e) => {
-                let total_cost = self.rate_limit.from(num_bytes).await;
+                // charged as quoted to the client, if it was quoted for the bytes lately
+                let quoted = self.store_cost_quotes.lock().await.quoted(
+                    &(XorName::from(payment.sender()), num_bytes),
+                    Instant::now(),
+                );
+                let quote = match quoted {
+                    Some(quote) => quote,
+                    None => self.rate_limit.quote(num_bytes).await,
+                };
+                let total_cost = quote.standard;
                 info!("Payment: registration and propagation succeeded. (Store cost: {}, paid amount: {}.)", total_cost, payment.amount());
                 info!(
                     "Section balance: {}",
@@ -250,6 +406,26 @@ impl Transfers {
                     }));
                     return Ok(ops);
                 }
+                let multiplier = quote.redundancy_paid_for(payment.amount());
+                if let DataCmd::Blob(BlobWrite::New(data)) = data_cmd {
+                    if multiplier > 1 {
+                        info!("Payment: premium redundancy paid for {:?}", data.address());
+                        ops.push(NodeDuty::SetChunkRedundancy {
+                            address: *data.address(),
+                            multiplier,
+                        });
+                    }
+                }
+                let credit = &payment.credit_proof().signed_credit.credit;
+                // the Elders applying the write are told the level selected
+                if let Some(level) = AckLevel::of(&credit.msg) {
+                    let selected = WriteAckLevel {
+                        id: MessageId::in_response_to(&msg.id()),
+                        origin,
+                        level,
+                    };
+                    ops.push(selected.msg(dst_address)?);
+                }
                 info!("Payment: forwarding data..");
                 // consider having the section actor be
                 // informed of this transfer as well..
@@ -326,9 +502,9 @@ impl Transfers {
     ) -> Result<NodeDuty> {
         debug!("Getting balance for {:?}", wallet_id);
 
-        // validate signature
-        let result = match self.replicas.balance(wallet_id).await {
-            Ok(res) => Ok(res),
+        // served from a snapshot, so as not to read the store per query
+        let result = match self.replicas.snapshot(wallet_id) {
+            Ok(snapshot) => Ok(snapshot.balance),
             Err(error) => Err(convert_to_error_message(error)?),
         };
 
@@ -352,10 +528,11 @@ impl Transfers {
         origin: SrcLocation,
     ) -> Result<NodeDuty> {
         trace!("Handling GetHistory");
-        // validate signature
+        // served from a snapshot, so as not to read the store per query
         let result = self
             .replicas
-            .history(*wallet_id)
+            .snapshot(*wallet_id)
+            .map(|snapshot| snapshot.history.clone())
             .map_err(|_e| ErrorMessage::NoHistoryForPublicKey(*wallet_id));
 
         Ok(NodeDuty::Send(OutgoingMsg {
@@ -371,6 +548,62 @@ impl Transfers {
         }))
     }
 
+    /// Looks up a specific credit or debit of a wallet.
+    pub async fn transfer_status(
+        &self,
+        wallet_id: PublicKey,
+        id: TransferId,
+    ) -> Result<TransferStatus> {
+        let history = self.replicas.snapshot(wallet_id)?.history.clone();
+        let found = match id {
+            TransferId::Credit(credit_id) => history
+                .credits
+                .into_iter()
+                .find(|proof| proof.id() == &credit_id)
+                .map(|proof| ActorHistory {
+                    credits: vec![proof],
+                    debits: vec![],
+                }),
+            TransferId::Debit(debit_id) => history
+                .debits
+                .into_iter()
+                .find(|proof| proof.id() == debit_id)
+                .map(|proof| ActorHistory {
+                    credits: vec![],
+                    debits: vec![proof],
+                }),
+        };
+        if let Some(history) = found {
+            return Ok(TransferStatus::Registered(history));
+        }
+        if let TransferId::Debit(debit_id) = id {
+            if self
+                .recently_validated_transfers
+                .lock()
+                .await
+                .contains(&debit_id)
+            {
+                return Ok(TransferStatus::Validated);
+            }
+        }
+        Ok(TransferStatus::Unknown)
+    }
+
+    /// Answers the client querying the status of the specific transfer, with its
+    /// agreement proof when registered.
+    pub async fn send_transfer_status(
+        &self,
+        query: TransferStatusQuery,
+        msg_id: MessageId,
+        origin: EndUser,
+    ) -> Result<NodeDuty> {
+        trace!("Handling transfer status query: {:?}", query);
+        let status = self.transfer_status(query.wallet, query.id).await?;
+        Ok(NodeDuty::Send(
+            ClientMsg::TransferStatus(status).msg(msg_id, origin)?,
+        ))
+    }
+
     /// This validation will render a signature over the
     /// original request (ValidateTransfer), giving a partial
     /// proof by this individual Elder, that the transfer is valid.
@@ -381,18 +614,25 @@ impl Transfers {
         origin: SrcLocation,
     ) -> Result<NodeDuty> {
         debug!("Validating a transfer from msg_id: {:?}", msg_id);
+        let debit_id = transfer.id();
         match self.replicas.validate(transfer).await {
-            Ok(event) => Ok(NodeDuty::Send(OutgoingMsg {
-                msg: Message::Event {
-                    event: Event::TransferValidated { event },
-                    id: MessageId::new(),
-                    correlation_id: msg_id,
-                    target_section_pk: None,
-                },
-                section_source: false, // strictly this is not correct, but we don't expect responses to an event..
-                dst: origin.to_dst(),
-                aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
-            })),
+            Ok(event) => {
+                self.recently_validated_transfers
+                    .lock()
+                    .await
+                    .record(debit_id);
+                Ok(NodeDuty::Send(OutgoingMsg {
+                    msg: Message::Event {
+                        event: Event::TransferValidated { event },
+                        id: MessageId::new(),
+                        correlation_id: msg_id,
+                        target_section_pk: None,
+                    },
+                    section_source: false, // strictly this is not correct, but we don't expect responses to an event..
+                    dst: origin.to_dst(),
+                    aggregation: Aggregation::None, // TODO: to_be_aggregated: Aggregation::AtDestination,
+                }))
+            }
             Err(e) => {
                 let message_error = convert_to_error_message(e)?;
                 Ok(NodeDuty::Send(OutgoingMsg {
@@ -422,6 +662,10 @@ impl Transfers {
         use NodeTransferCmd::*;
         match self.replicas.register(proof).await {
             Ok(event) => {
+                self.recently_validated_transfers
+                    .lock()
+                    .await
+                    .registered(&proof.id());
                 let location = event.transfer_proof.recipient().into();
                 Ok(NodeDuty::Send(OutgoingMsg {
                     msg: Message::NodeCmd {
@@ -453,6 +697,23 @@ impl Transfers {
         }
     }
 
+    /// Registers a batch of credits at once, returning those not already known.
+    pub async fn receive_propagated_batch(
+        &self,
+        credit_proofs: &[CreditAgreementProof],
+    ) -> Result<Vec<CreditAgreementProof>> {
+        let credited = self
+            .replicas
+            .receive_propagated_batch(credit_proofs)
+            .await?;
+        info!(
+            "Registered {} out of a batch of {} credits",
+            credited.len(),
+            credit_proofs.len()
+        );
+        Ok(credited)
+    }
+
     /// The only step that is triggered by a Replica.
     /// (See fn register_transfer).
     /// After a successful registration of a transfer at
@@ -0,0 +1,91 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The debits validated by us and not yet registered, so that the status of such a
+//! debit is told as validated rather than unknown when queried meanwhile.
+
+use sn_data_types::DebitId;
+use std::collections::{HashSet, VecDeque};
+
+// Debits remembered at most, the oldest being forgotten first.
+const MAX_RECENT_VALIDATIONS: usize = 10_000;
+
+/// The debits validated recently, by the order validated.
+#[derive(Default)]
+pub(super) struct RecentValidations {
+    debits: HashSet<DebitId>,
+    // the debits in the order validated, to forget them by, holding
+    // those registered since too, until they are forgotten
+    validated: VecDeque<DebitId>,
+}
+
+impl RecentValidations {
+    /// Records the debit as validated, forgetting the oldest when at the max.
+    pub(super) fn record(&mut self, debit: DebitId) {
+        if !self.debits.insert(debit) {
+            return;
+        }
+        self.validated.push_back(debit);
+        while self.validated.len() > MAX_RECENT_VALIDATIONS {
+            if let Some(oldest) = self.validated.pop_front() {
+                let _ = self.debits.remove(&oldest);
+            }
+        }
+    }
+
+    /// Forgets the debit, as registered.
+    pub(super) fn registered(&mut self, debit: &DebitId) {
+        let _ = self.debits.remove(debit);
+    }
+
+    /// Whether the debit is validated and not yet registered.
+    pub(super) fn contains(&self, debit: &DebitId) -> bool {
+        self.debits.contains(debit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use sn_data_types::PublicKey;
+
+    fn debits(count: usize) -> Vec<DebitId> {
+        let wallet = PublicKey::from(bls::SecretKey::random().public_key());
+        (0..count as u64).map(|i| Dot::new(wallet, i)).collect()
+    }
+
+    #[test]
+    fn registered_debits_are_forgotten() {
+        let mut validations = RecentValidations::default();
+        let debit = debits(1)[0];
+        validations.record(debit);
+        assert!(validations.contains(&debit));
+        validations.registered(&debit);
+        assert!(!validations.contains(&debit));
+    }
+
+    #[test]
+    fn oldest_debits_are_forgotten_first() {
+        let mut validations = RecentValidations::default();
+        let debits = debits(MAX_RECENT_VALIDATIONS + 1);
+        for debit in &debits {
+            validations.record(*debit);
+        }
+        assert_eq!(validations.debits.len(), MAX_RECENT_VALIDATIONS);
+        assert!(!validations.contains(&debits[0]));
+        assert!(validations.contains(&debits[1]));
+        // a registered debit is held in the order until forgotten,
+        // sparing those validated after it
+        validations.registered(&debits[1]);
+        validations.record(debits[0]);
+        assert_eq!(validations.validated.len(), MAX_RECENT_VALIDATIONS);
+        assert!(validations.contains(&debits[0]));
+        assert!(validations.contains(&debits[2]));
+    }
+}
@@ -6,6 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::history_compaction::WalletSummary;
 use crate::{Network, Result};
 use async_trait::async_trait;
 use bls::PublicKeySet;
@@ -29,6 +30,8 @@ pub trait ReplicaSigning {
 
     async fn sign_credit_proof(&self, proof: &CreditAgreementProof) -> Result<SignatureShare>;
 
+    async fn sign_wallet_summary(&self, summary: &WalletSummary) -> Result<SignatureShare>;
+
     async fn known_replicas(
         &self,
         wallet_name: &sn_routing::XorName,
@@ -84,6 +87,10 @@ impl ReplicaSigning for ReplicaSigningImpl {
         Ok(self.network.sign_as_elder(&proof).await?)
     }
 
+    async fn sign_wallet_summary(&self, summary: &WalletSummary) -> Result<SignatureShare> {
+        Ok(self.network.sign_as_elder(&summary).await?)
+    }
+
     /// Brittle validation of provided section key (once) being
     /// a valid section, since the query returns the current key..
     async fn known_replicas(
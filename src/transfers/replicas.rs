@@ -6,12 +6,23 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::{replica_signing::ReplicaSigning, store::TransferStore};
-use crate::{Error, Result};
+use super::{
+    history_compaction::{SignedWalletSummary, WalletSummary, KEEP_RECENT_EVENTS},
+    invariants::{self, InvariantViolation},
+    replica_signing::ReplicaSigning,
+    snapshot::{WalletSnapshot, WalletSnapshots},
+    store::TransferStore,
+    time_lock::{self, TimeLock},
+};
+use crate::{
+    network::VerificationCache,
+    persistence::{Transaction, Transactions},
+    Error, Result,
+};
 use bls::PublicKeySet;
 use dashmap::DashMap;
 use futures::lock::Mutex;
-use log::info;
+use log::{info, warn};
 use sn_data_types::{
     ActorHistory, CreditAgreementProof, OwnerType, PublicKey, ReplicaEvent, SignedTransfer,
     SignedTransferShare, Token, TransferAgreementProof, TransferPropagated, TransferRegistered,
@@ -42,6 +53,7 @@ where
     pub peer_replicas: PublicKeySet,
     pub section_chain: sn_routing::SectionChain,
     pub signing: T,
+    pub(crate) verified: Arc<VerificationCache>,
 }
 
 #[derive(Clone)]
@@ -52,7 +64,9 @@ where
     root_dir: PathBuf,
     info: ReplicaInfo<T>,
     locks: WalletLocks,
+    transactions: Transactions,
     self_lock: Arc<Mutex<usize>>,
+    snapshots: Arc<WalletSnapshots>,
 }
 
 impl<T: ReplicaSigning> Replicas<T> {
@@ -62,10 +76,12 @@ impl<T: ReplicaSigning> Replicas<T> {
         user_wallets: BTreeMap<PublicKey, ActorHistory>,
     ) -> Result<Self> {
         let instance = Self {
+            transactions: Transactions::new(&root_dir),
             root_dir,
             info,
             locks: Default::default(),
             self_lock: Arc::new(Mutex::new(0)),
+            snapshots: Default::default(),
         };
         instance.setup(user_wallets).await?;
         Ok(instance)
@@ -97,6 +113,7 @@ impl<T: ReplicaSigning> Replicas<T> {
                 let mut store = key_lock.lock().await;
                 // Access to the specific wallet is now serialised!
                 store.try_insert(e.to_owned())?;
+                self.snapshots.invalidate(&id);
             }
             for transfer_proof in wallet.debits {
                 let id = transfer_proof.sender();
@@ -106,6 +123,7 @@ impl<T: ReplicaSigning> Replicas<T> {
                 let mut store = key_lock.lock().await;
                 // Access to the specific wallet is now serialised!
                 store.try_insert(e.to_owned())?;
+                self.snapshots.invalidate(&id);
             }
         }
         Ok(())
@@ -144,33 +162,31 @@ impl<T: ReplicaSigning> Replicas<T> {
             .locks
             .iter()
             .map(|r| *r.key())
-            .filter_map(|id| TransferStore::new(id.into(), &self.root_dir).ok())
-            .map(|store| store.get_all())
+            .filter_map(|id| TransferStore::read_all(id.into(), &self.root_dir).ok())
             .flatten()
             .collect();
         Ok(events)
     }
 
-    /// History of actor
-    pub fn history(&self, id: PublicKey) -> Result<ActorHistory> {
-        let store = TransferStore::new(id.into(), &self.root_dir);
-
-        if let Err(error) = store {
-            // hmm.. can we handle this in a better way?
-            let err_string = error.to_string();
-            let no_such_file_or_dir = err_string.contains("No such file or directory");
-            let system_cannot_find_file =
-                err_string.contains("The system cannot find the file specified");
-            if no_such_file_or_dir || system_cannot_find_file {
-                // we have no history yet, so lets report that.
-                return Ok(ActorHistory::empty());
-            }
-
-            return Err(error);
-        };
+    /// The breaches of the invariants of each wallet held, verified over the
+    /// events stored for it, from the summary of those compacted, if any.
+    pub fn verify_invariants(&self) -> Vec<InvariantViolation> {
+        self.locks
+            .iter()
+            .map(|r| *r.key())
+            .filter_map(|id| {
+                self.read_history(id).ok().map(|(summary, events)| {
+                    invariants::verify_wallet(id, summary.as_ref(), &events)
+                })
+            })
+            .flatten()
+            .collect()
+    }
 
-        let store = store?;
-        let events = store.get_all();
+    /// History of actor, since the summary of the events compacted, if any.
+    pub fn history(&self, id: PublicKey) -> Result<ActorHistory> {
+        // a store not created yet reads as empty, as we have no history yet
+        let (_, events) = self.read_history(id)?;
 
         if events.is_empty() {
             return Ok(ActorHistory::empty());
@@ -216,16 +232,49 @@ impl<T: ReplicaSigning> Replicas<T> {
     ///
     pub async fn balance(&self, id: PublicKey) -> Result<Token> {
         debug!("Replica: Getting balance of: {:?}", id);
-        let store = match TransferStore::new(id.into(), &self.root_dir) {
-            Ok(store) => store,
+        let (summary, events) = match self.read_history(id) {
+            Ok(history) => history,
             // store load failed, so we return 0 balance
             Err(_) => return Ok(Token::from_nano(0)),
         };
 
-        let wallet = self.load_wallet(&store, OwnerType::Single(id)).await?;
+        let wallet = self.wallet_of(summary.as_ref(), events, OwnerType::Single(id))?;
         Ok(wallet.balance())
     }
 
+    /// The state of the wallet to answer queries from, as of the last write to it.
+    pub fn snapshot(&self, id: PublicKey) -> Result<Arc<WalletSnapshot>> {
+        if let Some(snapshot) = self.snapshots.get(&id) {
+            return Ok(snapshot);
+        }
+        let generation = self.snapshots.generation();
+        // no store, so no history yet
+        let (summary, events) = self.read_history(id).unwrap_or_default();
+        let snapshot = if events.is_empty() && summary.is_none() {
+            WalletSnapshot {
+                balance: Token::zero(),
+                history: ActorHistory::empty(),
+            }
+        } else {
+            let wallet = self.wallet_of(summary.as_ref(), events.clone(), OwnerType::Single(id))?;
+            WalletSnapshot {
+                balance: wallet.balance(),
+                history: ActorHistory {
+                    credits: self.get_credits(&events),
+                    debits: self.get_debits(events),
+                },
+            }
+        };
+        Ok(self.snapshots.insert(generation, id, snapshot))
+    }
+
+    /// The summary the history of the wallet was compacted into, if compacted.
+    pub fn wallet_summary(&self, id: PublicKey) -> Option<SignedWalletSummary> {
+        TransferStore::<ReplicaEvent>::read_summary(id.into(), &self.root_dir)
+            .ok()
+            .flatten()
+    }
+
     /// Get the replica's PK set
     pub fn replicas_pk_set(&self) -> PublicKeySet {
         self.info.peer_replicas.clone()
@@ -254,6 +303,7 @@ impl<T: ReplicaSigning> Replicas<T> {
             let mut store = key_lock.lock().await;
             // Access to the specific wallet is now serialised!
             store.try_insert(e.to_owned())?;
+            self.snapshots.invalidate(&id);
         }
         Ok(())
     }
@@ -271,12 +321,101 @@ impl<T: ReplicaSigning> Replicas<T> {
                 let key_lock = self.load_key_lock(key).await?;
                 let _store = key_lock.lock().await;
                 let _ = self.locks.remove(&key);
+                self.snapshots.invalidate(&key);
                 // todo: remove db from disk
             }
         }
         Ok(())
     }
 
+    /// Compacts the history of the wallet, if held by us: the confirmed transfers of all but the
+    /// `KEEP_RECENT_EVENTS` events last stored are collapsed into the summary of the wallet,
+    /// signed by us, and their events pruned. A credit still time-locked, and the events after
+    /// it, are kept, as debits are verified not to spend it. Returns the summary, if compacted.
+    pub async fn compact(&self, id: PublicKey) -> Result<Option<SignedWalletSummary>> {
+        let key_lock = match self.load_key_lock(id).await {
+            Ok(key_lock) => key_lock,
+            Err(_) => return Ok(None),
+        };
+        let mut store = key_lock.lock().await;
+
+        // Access to the specific wallet is now serialised!
+        let (summary, mut events) = stored_history(&store);
+        let mut cut = events.len().saturating_sub(KEEP_RECENT_EVENTS);
+        let epoch = self.info.section_chain.len() as u64;
+        if let Some(locked) = events[..cut]
+            .iter()
+            .position(|event| is_locked(event, epoch))
+        {
+            cut = locked;
+        }
+        if cut == 0 {
+            return Ok(None);
+        }
+        let recent = events.split_off(cut);
+        let mut summary = summary.unwrap_or_else(|| WalletSummary::new(id));
+        let mut kept = summary.collapse(events)?;
+        kept.extend(recent.into_iter().filter(|event| !summary.covers(event)));
+
+        let share = self.info.signing.sign_wallet_summary(&summary).await?;
+        let signed = SignedWalletSummary {
+            summary,
+            key_set: self.info.peer_replicas.clone(),
+            share,
+        };
+        self.replace_history(&mut store, &signed, &kept)?;
+        Ok(Some(signed))
+    }
+
+    /// Imports the summary of the wallet compacted by another replica of our section, e.g. one
+    /// pushing its state to us as a new Elder, if signed by a share of a key of our section and
+    /// superseding our summary. The events stored of the transfers collapsed into it are pruned.
+    /// Returns whether the summary was imported.
+    pub async fn import_summary(&self, signed: SignedWalletSummary) -> Result<bool> {
+        if !signed.verify()? {
+            return Err(Error::InvalidWalletSummary(format!(
+                "share {} does not sign the summary",
+                signed.share.index
+            )));
+        }
+        if !self.exists_in_chain(&signed.key_set.public_key()) {
+            return Err(Error::InvalidWalletSummary(
+                "signed by a key not of our section".to_string(),
+            ));
+        }
+        let id = signed.summary.wallet;
+        let key_lock = self.get_load_or_create_store(id).await?;
+        let mut store = key_lock.lock().await;
+
+        // Access to the specific wallet is now serialised!
+        let (summary, events) = stored_history(&store);
+        if let Some(summary) = summary {
+            if summary == signed.summary || !signed.summary.supersedes(&summary) {
+                return Ok(false);
+            }
+        }
+        let kept: Vec<_> = events
+            .into_iter()
+            .filter(|event| !signed.summary.covers(event))
+            .collect();
+        self.replace_history(&mut store, &signed, &kept)?;
+        Ok(true)
+    }
+
+    // Replaces the history stored for the wallet, as a whole.
+    fn replace_history(
+        &self,
+        store: &mut TransferStore<ReplicaEvent>,
+        signed: &SignedWalletSummary,
+        kept: &[ReplicaEvent],
+    ) -> Result<()> {
+        let mut transaction = Transaction::default();
+        store.stage_compaction(signed, kept, &mut transaction)?;
+        self.transactions.commit(transaction, &mut [store.db()])?;
+        self.snapshots.invalidate(&signed.summary.wallet);
+        Ok(())
+    }
+
     /// Step 1. Main business logic validation of a debit.
     pub async fn validate(&self, signed_transfer: SignedTransfer) -> Result<TransferValidated> {
         debug!("Replica validating transfer: {:?}", signed_transfer);
@@ -290,6 +429,8 @@ impl<T: ReplicaSigning> Replicas<T> {
 
         debug!("Wallet loaded");
         let _ = wallet.validate(&signed_transfer.debit, &signed_transfer.credit)?;
+        let (_, events) = stored_history(&store);
+        self.assert_spendable(&events, &wallet, signed_transfer.debit.amount())?;
 
         debug!("wallet valid");
         // signing will be serialised
@@ -306,6 +447,7 @@ impl<T: ReplicaSigning> Replicas<T> {
 
         // first store to disk
         store.try_insert(ReplicaEvent::TransferValidated(event.clone()))?;
+        self.snapshots.invalidate(&id);
         let mut wallet = wallet;
         // then apply to inmem state
         wallet.apply(ReplicaEvent::TransferValidated(event.clone()))?;
@@ -340,6 +482,7 @@ impl<T: ReplicaSigning> Replicas<T> {
             Some(event) => {
                 // first store to disk
                 store.try_insert(ReplicaEvent::TransferRegistered(event.clone()))?;
+                self.snapshots.invalidate(&id);
                 let mut wallet = wallet;
                 // then apply to inmem state
                 wallet.apply(ReplicaEvent::TransferRegistered(event.clone()))?;
@@ -387,7 +530,7 @@ impl<T: ReplicaSigning> Replicas<T> {
 
         // Access to the specific wallet is now serialised!
         let wallet = self.load_wallet(&store, OwnerType::Single(id)).await?;
-        let propagation_result = wallet.receive_propagated(credit_proof);
+        let propagation_result = self.receive_verified(&wallet, credit_proof);
         if propagation_result.is_ok() {
             // update state
             let event = TransferPropagated {
@@ -397,6 +540,7 @@ impl<T: ReplicaSigning> Replicas<T> {
             if propagation_result?.is_some() {
                 // first store to disk
                 store.try_insert(ReplicaEvent::TransferPropagated(event.clone()))?;
+                self.snapshots.invalidate(&id);
                 let mut wallet = wallet;
                 // then apply to inmem state
                 wallet.apply(ReplicaEvent::TransferPropagated(event.clone()))?;
@@ -406,6 +550,171 @@ impl<T: ReplicaSigning> Replicas<T> {
         Err(Error::InvalidPropagatedTransfer(credit_proof.clone()))
     }
 
+    /// Steps 2 and 3 for a payment to a wallet held by us, e.g. to the section: the debit of the
+    /// payer is registered and the credit propagated to the recipient as a whole, so that a crash
+    /// between them does not leave the payer debited and the recipient not credited.
+    pub async fn register_payment(
+        &self,
+        transfer_proof: &TransferAgreementProof,
+    ) -> Result<TransferPropagated> {
+        let sender = transfer_proof.sender();
+        let recipient = transfer_proof.recipient();
+        if sender == recipient {
+            let _ = self.register(transfer_proof).await?;
+            return self
+                .receive_propagated(sender.into(), &transfer_proof.credit_proof())
+                .await;
+        }
+
+        // should only have been signed by our section
+        let known_key = self.exists_in_chain(&transfer_proof.replica_keys().public_key());
+        if !known_key {
+            return Err(Error::Transfer(sn_transfers::Error::SectionKeyNeverExisted));
+        }
+
+        let sender_lock = self.load_key_lock(sender).await?;
+        let recipient_lock = self.get_load_or_create_store(recipient).await?;
+        // Acquire the locks of both wallets, in the same order for any payment between them.
+        let (mut sender_store, mut recipient_store) = if sender < recipient {
+            let sender_store = sender_lock.lock().await;
+            (sender_store, recipient_lock.lock().await)
+        } else {
+            let recipient_store = recipient_lock.lock().await;
+            (sender_lock.lock().await, recipient_store)
+        };
+
+        // Access to both wallets is now serialised!
+        let mut sender_wallet = self
+            .load_wallet(&sender_store, OwnerType::Single(sender))
+            .await?;
+        let registered = match sender_wallet.register(transfer_proof)? {
+            Some(event) => ReplicaEvent::TransferRegistered(event),
+            None => {
+                info!("transfer already registered!");
+                return Err(Error::TransferAlreadyRegistered);
+            }
+        };
+        let mut recipient_wallet = self
+            .load_wallet(&recipient_store, OwnerType::Single(recipient))
+            .await?;
+        let credit_proof = transfer_proof.credit_proof();
+        let propagated = TransferPropagated {
+            credit_proof: credit_proof.clone(),
+        };
+        let is_new_credit = self
+            .receive_verified(&recipient_wallet, &credit_proof)
+            .map_err(|_| Error::InvalidPropagatedTransfer(credit_proof.clone()))?
+            .is_some();
+
+        // first store to disk, as a whole
+        let mut transaction = Transaction::default();
+        sender_store.stage_insert(&registered, &mut transaction)?;
+        if is_new_credit {
+            recipient_store.stage_insert(
+                &ReplicaEvent::TransferPropagated(propagated.clone()),
+                &mut transaction,
+            )?;
+        }
+        self.transactions
+            .commit(transaction, &mut [sender_store.db(), recipient_store.db()])?;
+        self.snapshots.invalidate(&sender);
+        self.snapshots.invalidate(&recipient);
+        // then apply to inmem state
+        sender_wallet.apply(registered)?;
+        if is_new_credit {
+            recipient_wallet.apply(ReplicaEvent::TransferPropagated(propagated.clone()))?;
+        }
+        Ok(propagated)
+    }
+
+    /// Step 3, for a batch of credits, e.g. the node rewards paid out at churn.
+    /// Each wallet credited is locked and loaded once for all its credits.
+    /// Invalid credits are skipped. Returns the credits not already known.
+    pub async fn receive_propagated_batch(
+        &self,
+        credit_proofs: &[CreditAgreementProof],
+    ) -> Result<Vec<CreditAgreementProof>> {
+        let mut by_recipient = BTreeMap::<_, Vec<_>>::new();
+        for credit_proof in credit_proofs {
+            by_recipient
+                .entry(credit_proof.recipient())
+                .or_default()
+                .push(credit_proof);
+        }
+        let mut credited = vec![];
+        for (id, credit_proofs) in by_recipient {
+            let key_lock = self.get_load_or_create_store(id).await?;
+            let mut store = key_lock.lock().await;
+            let mut wallet = self.load_wallet(&store, OwnerType::Single(id)).await?;
+            for credit_proof in credit_proofs {
+                match self.receive_verified(&wallet, credit_proof) {
+                    Ok(Some(_)) => {
+                        let event = ReplicaEvent::TransferPropagated(TransferPropagated {
+                            credit_proof: credit_proof.clone(),
+                        });
+                        store.try_insert(event.clone())?;
+                        self.snapshots.invalidate(&id);
+                        wallet.apply(event)?;
+                        credited.push(credit_proof.clone());
+                    }
+                    Ok(None) => (),
+                    Err(e) => warn!(
+                        "Skipping invalid credit {:?} of batch: {}",
+                        credit_proof.id(),
+                        e
+                    ),
+                }
+            }
+        }
+        Ok(credited)
+    }
+
+    /// Asserts that the debit spends none of the credits to the wallet still time-locked.
+    fn assert_spendable(
+        &self,
+        events: &[ReplicaEvent],
+        wallet: &WalletReplica,
+        amount: Token,
+    ) -> Result<()> {
+        let credits = self.get_credits(events);
+        let epoch = self.info.section_chain.len() as u64;
+        let locked = time_lock::locked_amount(
+            credits.iter().map(|proof| &proof.signed_credit.credit),
+            epoch,
+        )?;
+        let spendable = wallet
+            .balance()
+            .checked_sub(locked)
+            .unwrap_or_else(Token::zero);
+        if amount > spendable {
+            return Err(Error::FundsLocked { spendable });
+        }
+        Ok(())
+    }
+
+    /// As `WalletReplica::receive_propagated`, but the signature of a proof
+    /// verified before, by us or by the other duties, is not verified again.
+    fn receive_verified(
+        &self,
+        wallet: &WalletReplica,
+        credit_proof: &CreditAgreementProof,
+    ) -> std::result::Result<Option<()>, TransfersError> {
+        let bytes = bincode::serialize(&credit_proof.signed_credit)
+            .map_err(|e| TransfersError::Serialisation(e.to_string()))?;
+        let key = credit_proof.debiting_replicas_keys.public_key();
+        let sig = &credit_proof.debiting_replicas_sig;
+        if !self.info.verified.contains(&key, sig, &bytes) {
+            let outcome = wallet.receive_propagated(credit_proof)?;
+            self.info.verified.insert(&key, sig, &bytes);
+            return Ok(outcome);
+        }
+        let known = wallet
+            .wallet()
+            .map(|snapshot| snapshot.credit_ids.contains(credit_proof.id()))
+            .unwrap_or(false);
+        Ok(if known { None } else { Some(()) })
+    }
+
     async fn load_key_lock(
         &self,
         id: PublicKey,
@@ -421,17 +730,53 @@ impl<T: ReplicaSigning> Replicas<T> {
         store: &TransferStore<ReplicaEvent>,
         id: OwnerType,
     ) -> Result<WalletReplica> {
-        let events = store.get_all();
-        let wallet = WalletReplica::from_history(
+        let (summary, events) = stored_history(store);
+        self.wallet_of(summary.as_ref(), events, id)
+    }
+
+    // The wallet, from the summary of the events compacted, if any.
+    fn wallet_of(
+        &self,
+        summary: Option<&WalletSummary>,
+        events: Vec<ReplicaEvent>,
+        id: OwnerType,
+    ) -> Result<WalletReplica> {
+        let summary = match summary {
+            Some(summary) => summary,
+            None => {
+                return Ok(WalletReplica::from_history(
+                    id,
+                    self.info.id,
+                    self.info.key_index,
+                    self.info.peer_replicas.clone(),
+                    events,
+                )?)
+            }
+        };
+        let mut wallet = WalletReplica::from_snapshot(
             id,
             self.info.id,
             self.info.key_index,
             self.info.peer_replicas.clone(),
-            events,
-        )?;
+            summary.to_wallet(),
+            Default::default(),
+            None,
+        );
+        for event in events {
+            wallet.apply(event)?;
+        }
         Ok(wallet)
     }
 
+    // The summary of the wallet, if compacted, and the events stored not collapsed into it,
+    // read without locking its store.
+    fn read_history(&self, id: PublicKey) -> Result<(Option<WalletSummary>, Vec<ReplicaEvent>)> {
+        let summary: Option<SignedWalletSummary> =
+            TransferStore::<ReplicaEvent>::read_summary(id.into(), &self.root_dir)?;
+        let events = TransferStore::read_all(id.into(), &self.root_dir)?;
+        Ok(not_collapsed(summary.map(|signed| signed.summary), events))
+    }
+
     fn exists_in_chain(&self, key: &bls::PublicKey) -> bool {
         self.info
             .section_chain
@@ -518,6 +863,7 @@ impl<T: ReplicaSigning> Replicas<T> {
         store.try_insert(ReplicaEvent::TransferPropagated(TransferPropagated {
             credit_proof: transfer_proof.credit_proof(),
         }))?;
+        self.snapshots.invalidate(&id);
 
         Ok(NodeDuty::NoOp)
     }
@@ -557,6 +903,39 @@ impl<T: ReplicaSigning> Replicas<T> {
             replica_debit_sig,
             replica_credit_sig,
             replicas: self.info.peer_replicas.clone(),
-        }))
+        }))?;
+        self.snapshots.invalidate(&id);
+        Ok(())
+    }
+}
+
+// The summary of the wallet, if compacted, and the events stored not collapsed into it,
+// e.g. those merged again from the state pushed to us at churn.
+fn stored_history(
+    store: &TransferStore<ReplicaEvent>,
+) -> (Option<WalletSummary>, Vec<ReplicaEvent>) {
+    let summary: Option<SignedWalletSummary> = store.summary();
+    not_collapsed(summary.map(|signed| signed.summary), store.get_all())
+}
+
+fn not_collapsed(
+    summary: Option<WalletSummary>,
+    mut events: Vec<ReplicaEvent>,
+) -> (Option<WalletSummary>, Vec<ReplicaEvent>) {
+    if let Some(summary) = &summary {
+        events.retain(|event| !summary.covers(event));
+    }
+    (summary, events)
+}
+
+// Whether the event is of a credit still time-locked.
+fn is_locked(event: &ReplicaEvent, epoch: u64) -> bool {
+    match event {
+        ReplicaEvent::TransferPropagated(e) => {
+            TimeLock::of(&e.credit_proof.signed_credit.credit.msg)
+                .map(|lock| !lock.has_expired(epoch))
+                .unwrap_or(false)
+        }
+        _ => false,
     }
 }
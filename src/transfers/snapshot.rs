@@ -0,0 +1,98 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use dashmap::DashMap;
+use sn_data_types::{ActorHistory, PublicKey, Token};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// The state of a wallet, read at once from its store.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalletSnapshot {
+    /// Balance of the wallet.
+    pub balance: Token,
+    /// The credits and debits of the wallet.
+    pub history: ActorHistory,
+}
+
+/// Read-only snapshots of wallets, to answer queries from. A snapshot is taken
+/// at the first query of a wallet since it was last written to, and serves
+/// all queries of it until the next write, so that a batch of queries reads
+/// the store once, rather than once per query, and not while it is written.
+#[derive(Default)]
+pub(super) struct WalletSnapshots {
+    wallets: DashMap<PublicKey, Arc<WalletSnapshot>>,
+    // bumped at every write, so that a snapshot read during one is not kept
+    generation: AtomicU64,
+}
+
+impl WalletSnapshots {
+    pub(super) fn get(&self, id: &PublicKey) -> Option<Arc<WalletSnapshot>> {
+        self.wallets.get(id).map(|snapshot| snapshot.clone())
+    }
+
+    /// To be read before reading the store for a snapshot.
+    pub(super) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Keeps the snapshot, unless there have been writes since the generation read.
+    pub(super) fn insert(
+        &self,
+        generation: u64,
+        id: PublicKey,
+        snapshot: WalletSnapshot,
+    ) -> Arc<WalletSnapshot> {
+        let snapshot = Arc::new(snapshot);
+        if self.generation() == generation {
+            let _ = self.wallets.insert(id, snapshot.clone());
+        }
+        snapshot
+    }
+
+    /// Drops the snapshot of the wallet, as it has been written to.
+    pub(super) fn invalidate(&self, id: &PublicKey) {
+        let _ = self.generation.fetch_add(1, Ordering::SeqCst);
+        let _ = self.wallets.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+
+    #[test]
+    fn snapshots_are_dropped_at_writes() {
+        let id = PublicKey::from(SecretKey::random().public_key());
+        let snapshot = |nanos| WalletSnapshot {
+            balance: Token::from_nano(nanos),
+            history: ActorHistory::empty(),
+        };
+        let snapshots = WalletSnapshots::default();
+
+        let generation = snapshots.generation();
+        let _ = snapshots.insert(generation, id, snapshot(10));
+        assert_eq!(
+            snapshots.get(&id).map(|s| s.balance),
+            Some(Token::from_nano(10))
+        );
+
+        snapshots.invalidate(&id);
+        assert!(snapshots.get(&id).is_none());
+
+        // read before a write, so not kept
+        let generation = snapshots.generation();
+        snapshots.invalidate(&id);
+        let served = snapshots.insert(generation, id, snapshot(20));
+        assert_eq!(served.balance, Token::from_nano(20));
+        assert!(snapshots.get(&id).is_none());
+    }
+}
@@ -6,9 +6,11 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{utils, Error, Result, ToDbKey};
+use crate::{
+    persistence::{LoggedDb, Transaction},
+    Error, Result, ToDbKey,
+};
 use log::{debug, trace};
-use pickledb::PickleDb;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     fmt::Debug,
@@ -19,11 +21,14 @@ use xor_name::XorName;
 
 const TRANSFERS_DIR_NAME: &str = "transfers";
 const DB_EXTENSION: &str = ".db";
+// The summary of the events compacted, if any, is stored under this key, which
+// is not an index, so that it is not read as an event.
+const SUMMARY_KEY: &str = "summary";
 
 /// Disk storage for transfers.
 pub struct TransferStore<TEvent: Debug + Serialize + DeserializeOwned> {
     id: XorName,
-    db: PickleDb,
+    db: LoggedDb,
     _phantom: PhantomData<TEvent>,
 }
 
@@ -36,11 +41,33 @@ where
         let db_name = format!("{}{}", id.to_db_key()?, DB_EXTENSION);
         Ok(Self {
             id,
-            db: utils::new_auto_dump_db(db_dir.as_path(), db_name)?,
+            db: LoggedDb::open(db_dir, db_name)?,
             _phantom: PhantomData::default(),
         })
     }
 
+    /// The events stored for the id, read without opening the store,
+    /// so that they can be read while it is open to new events.
+    pub fn read_all(id: XorName, root_dir: &Path) -> Result<Vec<TEvent>> {
+        let db_dir = root_dir.join(Path::new(TRANSFERS_DIR_NAME));
+        let db_name = format!("{}{}", id.to_db_key()?, DB_EXTENSION);
+        Ok(in_order(LoggedDb::read(db_dir, db_name)?))
+    }
+
+    /// The summary the events compacted were collapsed into, if any, read
+    /// without opening the store, as `read_all` reads the events.
+    pub(crate) fn read_summary<S: DeserializeOwned>(
+        id: XorName,
+        root_dir: &Path,
+    ) -> Result<Option<S>> {
+        let db_dir = root_dir.join(Path::new(TRANSFERS_DIR_NAME));
+        let db_name = format!("{}{}", id.to_db_key()?, DB_EXTENSION);
+        Ok(LoggedDb::read(db_dir, db_name)?
+            .into_iter()
+            .find(|(key, _)| key == SUMMARY_KEY)
+            .map(|(_, summary)| summary))
+    }
+
     ///
     pub fn id(&self) -> XorName {
         self.id
@@ -50,41 +77,82 @@ where
     pub fn get_all(&self) -> Vec<TEvent> {
         let keys = self.db.get_all();
 
-        let mut events: Vec<(usize, TEvent)> = keys
-            .iter()
+        let events = keys
+            .into_iter()
             .filter_map(|key| {
-                let value = self.db.get::<TEvent>(key);
-                let key = key.parse::<usize>();
-                match value {
-                    Some(v) => match key {
-                        Ok(k) => Some((k, v)),
-                        _ => None,
-                    },
-                    None => None,
-                }
+                let value = self.db.get::<TEvent>(&key)?;
+                Some((key, value))
             })
             .collect();
 
-        events.sort_by(|(key_a, _), (key_b, _)| key_a.partial_cmp(key_b).unwrap());
-
-        let events: Vec<TEvent> = events.into_iter().map(|(_, val)| val).collect();
-
-        events
+        in_order(events)
     }
 
     ///
     pub fn try_insert(&mut self, event: TEvent) -> Result<()> {
-        let key = &self.db.total_keys().to_string();
-        if self.db.exists(key) {
+        let key = self.next_key(&event)?;
+        self.db.set(&key, &event)
+    }
+
+    /// Stages the event to be inserted by the transaction, committed to `db`,
+    /// along with the changes to other stores.
+    pub(crate) fn stage_insert(&self, event: &TEvent, transaction: &mut Transaction) -> Result<()> {
+        let key = self.next_key(event)?;
+        transaction.set(&self.db, &key, event)
+    }
+
+    /// The summary the events compacted were collapsed into, if any.
+    pub(crate) fn summary<S: DeserializeOwned>(&self) -> Option<S> {
+        self.db.get(SUMMARY_KEY)
+    }
+
+    /// Stages replacing the events stored with those kept, and the summary
+    /// with the one the others were collapsed into, to be committed to `db`.
+    pub(crate) fn stage_compaction<S: Serialize>(
+        &self,
+        summary: &S,
+        kept: &[TEvent],
+        transaction: &mut Transaction,
+    ) -> Result<()> {
+        for key in self.db.get_all() {
+            transaction.rem(&self.db, &key);
+        }
+        transaction.set(&self.db, SUMMARY_KEY, summary)?;
+        for (index, event) in kept.iter().enumerate() {
+            transaction.set(&self.db, &index.to_string(), event)?;
+        }
+        Ok(())
+    }
+
+    /// The db of the store, to commit the transactions staged to.
+    pub(crate) fn db(&mut self) -> &mut LoggedDb {
+        &mut self.db
+    }
+
+    fn next_key(&self, event: &TEvent) -> Result<String> {
+        let key = self.db.total_keys().to_string();
+        if self.db.exists(&key) {
             return Err(Error::Logic(format!(
                 "Key exists: {}. Event: {:?}",
                 key, event
             )));
         }
-        self.db.set(key, &event).map_err(Error::PickleDb)
+        Ok(key)
     }
 }
 
+// The events, stored under their index, in the order of it.
+fn in_order<TEvent>(events: Vec<(String, TEvent)>) -> Vec<TEvent> {
+    let mut events: Vec<(usize, TEvent)> = events
+        .into_iter()
+        .filter_map(|(key, event)| Some((key.parse::<usize>().ok()?, event)))
+        .collect();
+
+    events.sort_by_key(|(key, _)| *key);
+
+    events.into_iter().map(|(_, val)| val).collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::super::test_utils::get_genesis;
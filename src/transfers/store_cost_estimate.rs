@@ -0,0 +1,183 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    capacity::SectionFullness, node_msg::ClientMsg, node_ops::NodeDuty,
+    section_funds::treasury_share, Error, Result,
+};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{PublicKey, SignatureShare, Token};
+use sn_messaging::{EndUser, MessageId};
+use std::collections::BTreeMap;
+
+/// How long an estimate is valid for, in seconds.
+pub const ESTIMATE_VALIDITY: u64 = 10 * 60;
+/// The chunks of an upload estimated at most.
+pub const MAX_ESTIMATED_CHUNKS: u64 = 1_000_000;
+// Expiries are rounded up to this, in seconds, so that the
+// Elders estimating at about the same time state the same expiry.
+const EXPIRY_GRANULARITY: u64 = 60;
+
+/// The number of chunks of each size an upload of the bytes is split into, as few
+/// chunks of at most the max chunk size as there can be, as even as can be.
+pub(crate) fn chunk_sizes(bytes: u64, max_chunk_size: u64) -> Result<BTreeMap<u64, u64>> {
+    let mut sizes = BTreeMap::new();
+    let chunks = bytes.div_ceil(max_chunk_size.max(1));
+    if chunks > MAX_ESTIMATED_CHUNKS {
+        return Err(Error::InvalidOperation(format!(
+            "Cannot estimate {} chunks, at most {} are estimated",
+            chunks, MAX_ESTIMATED_CHUNKS
+        )));
+    }
+    if chunks == 0 {
+        return Ok(sizes);
+    }
+    // the remainder is spread over the first chunks, a byte each
+    let (size, remainder) = (bytes / chunks, bytes % chunks);
+    if remainder > 0 {
+        let _ = sizes.insert(size + 1, remainder);
+    }
+    if chunks > remainder {
+        let _ = sizes.insert(size, chunks - remainder);
+    }
+    Ok(sizes)
+}
+
+/// The store cost of the chunks of an upload of the same size.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreCostItem {
+    /// Bytes of each chunk.
+    pub bytes: u64,
+    /// Chunks of this size.
+    pub chunks: u64,
+    /// The store cost of each chunk, with standard redundancy.
+    pub cost: Token,
+    /// The store cost of each chunk, with premium redundancy.
+    pub premium_cost: Token,
+}
+
+/// The itemized store cost of an upload, as estimated by an Elder until it expires.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreCostEstimate {
+    /// Key of the section estimating.
+    pub section_key: PublicKey,
+    /// The cost of the chunks, by size.
+    pub items: Vec<StoreCostItem>,
+    /// The cost of all chunks, with standard redundancy.
+    pub total: Token,
+    /// The cost of all chunks, with premium redundancy.
+    pub premium_total: Token,
+    /// Of the total, the fee retained by the section treasury, rather than rewarded to nodes.
+    pub treasury_fee: Token,
+    /// Seconds since unix epoch, after which the estimate is no longer valid.
+    pub expires_at: u64,
+    /// How full the section was, as of estimating.
+    pub fullness: SectionFullness,
+}
+
+impl StoreCostEstimate {
+    /// The estimate of the items, expiring `ESTIMATE_VALIDITY` from now, in secs since unix epoch.
+    pub(crate) fn new(
+        section_key: PublicKey,
+        items: Vec<StoreCostItem>,
+        treasury_percentage: u8,
+        fullness: SectionFullness,
+        now: u64,
+    ) -> Result<Self> {
+        let sum = |cost: fn(&StoreCostItem) -> Token| {
+            items.iter().try_fold(Token::zero(), |total, item| {
+                let nanos = cost(item).as_nano().checked_mul(item.chunks)?;
+                total.checked_add(Token::from_nano(nanos))
+            })
+        };
+        let exceeded = || Error::InvalidOperation("Store cost of the upload overflows".to_string());
+        let total = sum(|item| item.cost).ok_or_else(exceeded)?;
+        let premium_total = sum(|item| item.premium_cost).ok_or_else(exceeded)?;
+        let expires_at =
+            (now + ESTIMATE_VALIDITY).div_ceil(EXPIRY_GRANULARITY) * EXPIRY_GRANULARITY;
+        Ok(Self {
+            section_key,
+            items,
+            total,
+            premium_total,
+            treasury_fee: treasury_share(total, treasury_percentage),
+            expires_at,
+            fullness,
+        })
+    }
+}
+
+/// The estimate, with the share of the Elder estimating it of the signature of the
+/// section, so that clients can tell it was stated by the Elders of the section.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedStoreCostEstimate {
+    /// The estimate.
+    pub estimate: StoreCostEstimate,
+    /// The share of the Elder of the signature of the section over the estimate.
+    pub share: SignatureShare,
+}
+
+impl SignedStoreCostEstimate {
+    /// The msg sending the estimate to the client, along with the response to its store
+    /// cost query of the id, under an id derived from that of the query, so that clients
+    /// not expecting it don't take it for the response.
+    pub(crate) fn msg(&self, id: MessageId, origin: EndUser) -> Result<NodeDuty> {
+        let correlation_id = MessageId::in_response_to(&id);
+        Ok(NodeDuty::Send(
+            ClientMsg::StoreCostEstimate(self.clone()).msg(correlation_id, origin)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+
+    #[test]
+    fn upload_is_split_into_chunks_as_even_as_can_be() -> Result<()> {
+        let sizes = chunk_sizes(10, 3)?;
+        assert_eq!(sizes, vec![(2, 2), (3, 2)].into_iter().collect());
+
+        let sizes = chunk_sizes(8, 4)?;
+        assert_eq!(sizes, vec![(4, 2)].into_iter().collect());
+        assert!(chunk_sizes(0, 4)?.is_empty());
+
+        assert!(chunk_sizes(MAX_ESTIMATED_CHUNKS + 1, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn estimate_totals_the_items() -> Result<()> {
+        let item = |bytes, chunks, cost| StoreCostItem {
+            bytes,
+            chunks,
+            cost: Token::from_nano(cost),
+            premium_cost: Token::from_nano(cost * 3),
+        };
+        let section_key = PublicKey::from(SecretKey::random().public_key());
+        let fullness = SectionFullness::default();
+        let now = 1_600_000_000;
+        let estimate = StoreCostEstimate::new(
+            section_key,
+            vec![item(2, 2, 10), item(3, 2, 20)],
+            10,
+            fullness,
+            now,
+        )?;
+        assert_eq!(estimate.total, Token::from_nano(60));
+        assert_eq!(estimate.premium_total, Token::from_nano(180));
+        assert_eq!(estimate.treasury_fee, Token::from_nano(6));
+        assert_eq!(estimate.expires_at % EXPIRY_GRANULARITY, 0);
+        assert!(estimate.expires_at >= now + ESTIMATE_VALIDITY);
+
+        let overflowing = vec![item(1, u64::MAX, 2)];
+        assert!(StoreCostEstimate::new(section_key, overflowing, 10, fullness, now).is_err());
+        Ok(())
+    }
+}
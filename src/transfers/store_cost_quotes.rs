@@ -0,0 +1,123 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The store costs quoted to clients lately, so that the payments for their writes
+//! are charged as quoted, rather than at the cost of when the payments arrive.
+
+use crate::capacity::StoreCostQuote;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+// Quotes are charged by for this long after being given.
+const QUOTE_TTL: Duration = Duration::from_secs(10 * 60);
+// Quotes remembered at most, the oldest being forgotten first.
+const MAX_QUOTES: usize = 10_000;
+
+/// A quote, by the name of the key of the client it was given to, which is cheaper
+/// to order by than the key itself, and the bytes quoted for.
+pub(super) type QuoteKey = (XorName, u64);
+
+/// The quotes given recently, the last one given for each client and bytes.
+#[derive(Default)]
+pub(super) struct StoreCostQuotes {
+    quotes: BTreeMap<QuoteKey, (Instant, StoreCostQuote)>,
+    // the quotes in the order given, to forget them by
+    given: VecDeque<(Instant, QuoteKey)>,
+}
+
+impl StoreCostQuotes {
+    /// The quote given to the client for the bytes, if given recently.
+    pub(super) fn quoted(&mut self, key: &QuoteKey, now: Instant) -> Option<StoreCostQuote> {
+        self.forget_expired(now);
+        self.quotes.get(key).map(|(_, quote)| *quote)
+    }
+
+    /// Records the quote as given, in place of any given before for the client and bytes.
+    pub(super) fn record(&mut self, key: QuoteKey, quote: StoreCostQuote, now: Instant) {
+        self.forget_expired(now);
+        while self.given.len() >= MAX_QUOTES {
+            match self.given.pop_front() {
+                Some((given, oldest)) => self.forget(given, &oldest),
+                None => break,
+            }
+        }
+        let _ = self.quotes.insert(key, (now, quote));
+        self.given.push_back((now, key));
+    }
+
+    fn forget_expired(&mut self, now: Instant) {
+        while let Some((given, key)) = self.given.front().copied() {
+            if now.duration_since(given) < QUOTE_TTL {
+                break;
+            }
+            self.forget(given, &key);
+            let _ = self.given.pop_front();
+        }
+    }
+
+    // Forgets the quote, unless another was given for the client and bytes since.
+    fn forget(&mut self, given: Instant, key: &QuoteKey) {
+        if matches!(self.quotes.get(key), Some((at, _)) if *at == given) {
+            let _ = self.quotes.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EconomyConfig;
+    use sn_data_types::Token;
+
+    fn client() -> XorName {
+        XorName::random()
+    }
+
+    fn quote(nanos: u64) -> StoreCostQuote {
+        EconomyConfig::default().quote(Token::from_nano(nanos))
+    }
+
+    #[test]
+    fn quotes_are_charged_by_until_expired() {
+        let mut quotes = StoreCostQuotes::default();
+        let now = Instant::now();
+        let key = (client(), 1024);
+        assert!(quotes.quoted(&key, now).is_none());
+
+        quotes.record(key, quote(1_000), now);
+        assert_eq!(quotes.quoted(&key, now), Some(quote(1_000)));
+        // quotes are for the bytes and client they were given for
+        assert!(quotes.quoted(&(key.0, 2048), now).is_none());
+        assert!(quotes.quoted(&(client(), 1024), now).is_none());
+
+        // a quote given again replaces the one before, and outlives it
+        let later = now + Duration::from_secs(1);
+        quotes.record(key, quote(2_000), later);
+        assert_eq!(quotes.quoted(&key, now + QUOTE_TTL), Some(quote(2_000)));
+        assert!(quotes.quoted(&key, later + QUOTE_TTL).is_none());
+    }
+
+    #[test]
+    fn oldest_quotes_are_forgotten_first() {
+        let mut quotes = StoreCostQuotes::default();
+        let now = Instant::now();
+        let client = client();
+        let keys: Vec<_> = (0..=MAX_QUOTES as u64)
+            .map(|bytes| (client, bytes))
+            .collect();
+        for key in &keys {
+            quotes.record(*key, quote(1_000), now);
+        }
+        assert_eq!(quotes.quotes.len(), MAX_QUOTES);
+        assert!(quotes.quoted(&keys[0], now).is_none());
+        assert!(quotes.quoted(&keys[1], now).is_some());
+    }
+}
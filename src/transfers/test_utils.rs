@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::replica_signing::ReplicaSigning;
+use super::{history_compaction::WalletSummary, replica_signing::ReplicaSigning};
 use crate::{Error, Result};
 use async_trait::async_trait;
 use bls::{PublicKeySet, PublicKeyShare, SecretKeySet, SecretKeyShare};
@@ -89,6 +89,16 @@ impl ReplicaSigning for TestReplicaSigning {
         }
     }
 
+    async fn sign_wallet_summary(&self, summary: &WalletSummary) -> Result<SignatureShare> {
+        match bincode::serialize(summary) {
+            Err(_) => Err(Error::Logic("Could not serialise summary".into())),
+            Ok(data) => Ok(SignatureShare {
+                index: self.key_index,
+                share: self.secret_key.sign(data),
+            }),
+        }
+    }
+
     async fn known_replicas(
         &self,
         _wallet_name: &sn_routing::XorName,
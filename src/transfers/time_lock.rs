@@ -0,0 +1,123 @@
+// Copyright 2021 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sn_data_types::{Credit, Token};
+use std::fmt::{self, Display, Formatter};
+
+/// Tags the msg of a credit locked by a `TimeLock`, which is followed by the lock and the
+/// text of the msg, as JSON, e.g. `sn_node::TimeLock{"lock":{"until_epoch":12},"text":""}`.
+/// The msg of a credit is free text, so a lock is only read from a msg of this form, as
+/// a whole, rather than from any text which happens to look like one.
+pub const TIME_LOCK_TAG: &str = "sn_node::TimeLock";
+
+/// Until when a credit is not spendable by the recipient: until our section has had a
+/// number of keys since genesis. Unlike the clocks of its Elders, the keys of our section
+/// are agreed by them, so they tell alike whether the lock has expired. The credit is
+/// validated and registered, and counted in the balance, at once, but debits of the
+/// recipient are not to spend it until the lock has expired.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeLock {
+    /// The number of keys of our section since genesis, from which on the credit is spendable.
+    pub until_epoch: u64,
+}
+
+/// The msg of a locked credit, following the tag.
+#[derive(Serialize, Deserialize)]
+struct LockedMsg {
+    lock: TimeLock,
+    text: String,
+}
+
+impl TimeLock {
+    /// The lock of a credit, if its msg is of a locked credit.
+    pub fn of(msg: &str) -> Option<Self> {
+        let json = msg.strip_prefix(TIME_LOCK_TAG)?;
+        serde_json::from_str::<LockedMsg>(json)
+            .ok()
+            .map(|locked| locked.lock)
+    }
+
+    /// The msg of a credit locked by this, along with the text given.
+    pub fn to_msg(&self, text: &str) -> Result<String> {
+        let json = serde_json::to_string(&LockedMsg {
+            lock: *self,
+            text: text.to_string(),
+        })?;
+        Ok(format!("{}{}", TIME_LOCK_TAG, json))
+    }
+
+    /// Whether the lock has expired, as of the section key epoch given.
+    pub fn has_expired(&self, epoch: u64) -> bool {
+        epoch >= self.until_epoch
+    }
+}
+
+impl Display for TimeLock {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "section key epoch {}", self.until_epoch)
+    }
+}
+
+/// The amount of the credits still locked, as of the section key epoch given.
+pub(crate) fn locked_amount<'a>(
+    credits: impl Iterator<Item = &'a Credit>,
+    epoch: u64,
+) -> Result<Token> {
+    credits
+        .filter(|credit| TimeLock::of(&credit.msg).is_some_and(|lock| !lock.has_expired(epoch)))
+        .try_fold(Token::zero(), |locked, credit| {
+            locked
+                .checked_add(credit.amount)
+                .ok_or_else(|| Error::Logic("Overflow of the locked amount".to_string()))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bls::SecretKey;
+    use sn_data_types::PublicKey;
+
+    #[test]
+    fn locks_are_read_from_the_tagged_msg() -> Result<()> {
+        let lock = TimeLock { until_epoch: 12 };
+        let msg = lock.to_msg("vesting")?;
+        assert!(msg.starts_with(TIME_LOCK_TAG));
+        assert_eq!(TimeLock::of(&msg), Some(lock));
+        // free text is never a lock, nor is a tagged msg not read as one as a whole
+        assert_eq!(TimeLock::of("locked-until-epoch:12"), None);
+        assert_eq!(TimeLock::of(&format!("{} 12", TIME_LOCK_TAG)), None);
+        assert_eq!(TimeLock::of(&format!("{} trailing", msg)), None);
+        assert_eq!(TimeLock::of("reward"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn only_credits_not_yet_expired_are_locked() -> Result<()> {
+        let recipient = PublicKey::from(SecretKey::random().public_key());
+        let credit = |nanos, msg: String| Credit {
+            id: rand::random(),
+            amount: Token::from_nano(nanos),
+            recipient,
+            msg,
+        };
+        let credits = [
+            credit(1, "payment".to_string()),
+            credit(10, TimeLock { until_epoch: 3 }.to_msg("")?),
+            credit(100, TimeLock { until_epoch: 5 }.to_msg("")?),
+        ];
+
+        let locked = |epoch| locked_amount(credits.iter(), epoch);
+        assert_eq!(locked(2)?, Token::from_nano(110));
+        assert_eq!(locked(3)?, Token::from_nano(100));
+        assert_eq!(locked(5)?, Token::zero());
+        Ok(())
+    }
+}